@@ -793,6 +793,73 @@ pub enum ConfigurationError {
 
     #[error(transparent)]
     SerdeError(#[from] Arc<serde_yaml::Error>),
+
+    #[error("Invalid configuration in {}: {message}", path.to_print_string())]
+    InvalidConfigFile {
+        path: Path,
+        message: String,
+    },
+
+    #[error("Invalid configuration in {}:{line}:{column}: {message}\n\n{snippet}", path.to_print_string())]
+    InvalidConfigFileAt {
+        path: Path,
+        line: usize,
+        column: usize,
+        message: String,
+        snippet: String,
+    },
+
+    #[error("{}", .0.iter().map(|error| error.to_string()).collect::<Vec<_>>().join("\n\n"))]
+    MultipleErrors(Vec<ConfigurationError>),
+}
+
+impl ConfigurationError {
+    /// Attributes a deserialization error to the rc file it comes from,
+    /// along with an excerpt of the offending line when the location is
+    /// known.
+    fn from_yaml_error(path: &Path, text: &str, error: serde_yaml::Error) -> Self {
+        let Some(location) = error.location() else {
+            return ConfigurationError::InvalidConfigFile {
+                path: path.clone(),
+                message: error.to_string(),
+            };
+        };
+
+        let line
+            = location.line();
+        let column
+            = location.column();
+
+        // serde_yaml appends the location to its messages; we print it
+        // ourselves alongside the file path.
+        let message
+            = error.to_string();
+        let message
+            = message.strip_suffix(&format!(" at line {} column {}", line, column))
+                .unwrap_or(&message)
+                .to_string();
+
+        let line_text
+            = text.lines().nth(line.saturating_sub(1)).unwrap_or("");
+
+        let gutter
+            = format!("{} | ", line);
+
+        let snippet = format!(
+            "{}{}\n{}^",
+            gutter,
+            line_text,
+            " ".repeat(gutter.len() + line_text.chars().take(column.saturating_sub(1)).count()),
+        );
+
+        ConfigurationError::InvalidConfigFileAt {
+            path: path.clone(),
+            line,
+            column,
+            message,
+            snippet,
+        }
+    }
 }
 
 impl From<std::io::Error> for ConfigurationError {
@@ -864,6 +931,9 @@ impl Configuration {
         let mut intermediate_project_config
             = Partial::Missing;
 
+        let mut errors
+            = Vec::new();
+
         if let Some(user_config_path) = user_config_path.as_ref() {
             let metadata
                 = user_config_path.fs_metadata()
@@ -881,10 +951,10 @@ impl Configuration {
                     = user_config_path
                         .fs_read_text_with_size(metadata.len())?;
 
-                let user_config: intermediate::Settings
-                    = serde_yaml::from_str(&user_config_text)?;
-
-                intermediate_user_config = Partial::Value(user_config);
+                match serde_yaml::from_str::<intermediate::Settings>(&user_config_text) {
+                    Ok(user_config) => intermediate_user_config = Partial::Value(user_config),
+                    Err(error) => errors.push(ConfigurationError::from_yaml_error(user_config_path, &user_config_text, error)),
+                }
             }
         }
 
@@ -905,13 +975,24 @@ impl Configuration {
                     = project_config_path
                         .fs_read_text_with_size(metadata.len())?;
 
-                let project_config: intermediate::Settings
-                    = serde_yaml::from_str(&project_config_text)?;
-
-                intermediate_project_config = Partial::Value(project_config);
+                match serde_yaml::from_str::<intermediate::Settings>(&project_config_text) {
+                    Ok(project_config) => intermediate_project_config = Partial::Value(project_config),
+                    Err(error) => errors.push(ConfigurationError::from_yaml_error(project_config_path, &project_config_text, error)),
+                }
             }
         }
 
+        // Both files are parsed before bailing out, so that users fixing
+        // one of them don't discover the other one is broken as well only
+        // after the next run.
+        if errors.len() > 1 {
+            return Err(ConfigurationError::MultipleErrors(errors));
+        }
+
+        if let Some(error) = errors.pop() {
+            return Err(error);
+        }
+
         let mut settings = Settings::merge(
             &context,
             intermediate_user_config,
@@ -965,5 +1046,10 @@ merge_settings!(zpm_utils::Libc, |s: &str| FromFileString::from_file_string(s).u
 merge_settings!(zpm_utils::Os, |s: &str| FromFileString::from_file_string(s).unwrap());
 merge_settings!(zpm_utils::Secret<String>, |s: &str| FromFileString::from_file_string(s).unwrap());
 
+merge_settings!(crate::types::EnableScripts, |s: &str| FromFileString::from_file_string(s).unwrap());
+merge_settings!(crate::types::LicenseSeverity, |s: &str| FromFileString::from_file_string(s).unwrap());
+merge_settings!(crate::types::NmHoistingLimits, |s: &str| FromFileString::from_file_string(s).unwrap());
+merge_settings!(crate::types::NmMode, |s: &str| FromFileString::from_file_string(s).unwrap());
 merge_settings!(crate::types::NodeLinker, |s: &str| FromFileString::from_file_string(s).unwrap());
 merge_settings!(crate::types::PnpFallbackMode, |s: &str| FromFileString::from_file_string(s).unwrap());
+merge_settings!(crate::types::RefreshTagRanges, |s: &str| FromFileString::from_file_string(s).unwrap());
@@ -1,4 +1,6 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 use zpm_macro_enum::zpm_enum;
+use zpm_utils::{DataType, FromFileString, ToFileString, ToHumanString};
 
 use crate::ConfigurationError;
 
@@ -15,6 +17,29 @@ pub enum NodeLinker {
     NodeModules,
 }
 
+#[zpm_enum(error = ConfigurationError, or_else = |s| Err(ConfigurationError::EnumError(s.to_string())))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmHoistingLimits {
+    #[literal("none")]
+    None,
+
+    #[literal("workspaces")]
+    Workspaces,
+
+    #[literal("dependencies")]
+    Dependencies,
+}
+
+#[zpm_enum(error = ConfigurationError, or_else = |s| Err(ConfigurationError::EnumError(s.to_string())))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmMode {
+    #[literal("classic")]
+    Classic,
+
+    #[literal("copy-reflink")]
+    CopyReflink,
+}
+
 #[zpm_enum(error = ConfigurationError, or_else = |s| Err(ConfigurationError::EnumError(s.to_string())))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PnpFallbackMode {
@@ -27,3 +52,101 @@ pub enum PnpFallbackMode {
     #[literal("all")]
     All,
 }
+
+#[zpm_enum(error = ConfigurationError, or_else = |s| Err(ConfigurationError::EnumError(s.to_string())))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LicenseSeverity {
+    #[literal("ignore")]
+    Ignore,
+
+    #[literal("warning")]
+    Warning,
+
+    #[literal("error")]
+    Error,
+}
+
+#[zpm_enum(error = ConfigurationError, or_else = |s| Err(ConfigurationError::EnumError(s.to_string())))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshTagRanges {
+    #[literal("follow")]
+    Follow,
+
+    #[literal("pin")]
+    Pin,
+}
+
+/// Accepts booleans on top of `allowlist`, so that the settings written
+/// before it became a tri-state (`enableScripts: false`) keep working.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnableScripts {
+    /// Only the build scripts of the packages listed in `allowedBuildDependencies` run
+    Enabled,
+
+    /// No dependency build script runs
+    Disabled,
+
+    /// Same as `Enabled`, spelled out to make the allowlist behaviour explicit
+    Allowlist,
+}
+
+impl FromFileString for EnableScripts {
+    type Error = ConfigurationError;
+
+    fn from_file_string(src: &str) -> Result<Self, Self::Error> {
+        match src {
+            "true" | "1" => Ok(EnableScripts::Enabled),
+            "false" | "0" => Ok(EnableScripts::Disabled),
+            "allowlist" => Ok(EnableScripts::Allowlist),
+            _ => Err(ConfigurationError::EnumError(src.to_string())),
+        }
+    }
+}
+
+impl ToFileString for EnableScripts {
+    fn to_file_string(&self) -> String {
+        match self {
+            EnableScripts::Enabled => "true".to_string(),
+            EnableScripts::Disabled => "false".to_string(),
+            EnableScripts::Allowlist => "allowlist".to_string(),
+        }
+    }
+}
+
+impl ToHumanString for EnableScripts {
+    fn to_print_string(&self) -> String {
+        match self {
+            EnableScripts::Allowlist => DataType::Code.colorize(&self.to_file_string()),
+            _ => DataType::Boolean.colorize(&self.to_file_string()),
+        }
+    }
+}
+
+zpm_utils::impl_file_string_from_str!(EnableScripts);
+
+impl Serialize for EnableScripts {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            EnableScripts::Enabled => serializer.serialize_bool(true),
+            EnableScripts::Disabled => serializer.serialize_bool(false),
+            EnableScripts::Allowlist => serializer.serialize_str("allowlist"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EnableScripts {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum BoolOrString {
+            Bool(bool),
+            String(String),
+        }
+
+        match BoolOrString::deserialize(deserializer)? {
+            BoolOrString::Bool(true) => Ok(EnableScripts::Enabled),
+            BoolOrString::Bool(false) => Ok(EnableScripts::Disabled),
+            BoolOrString::String(s) => EnableScripts::from_file_string(&s).map_err(de::Error::custom),
+        }
+    }
+}
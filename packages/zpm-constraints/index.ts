@@ -77,6 +77,7 @@ declare const CONFIG_PATH: string;
 declare const FIX: boolean;
 
 const RESULT_PATH = process.argv[2]!;
+const DEPENDENCIES_PATH = process.argv[3];
 
 type InputDependency = {
   ident: string;
@@ -351,6 +352,15 @@ async function main() {
   const output = applyEngineReport(FIX);
 
   writeFileSync(RESULT_PATH, JSON.stringify(output, null, 2));
+
+  // The files loaded by the config are part of the cache key; dependencies
+  // are already covered by the lockfile, so we only list the local files.
+  if (typeof DEPENDENCIES_PATH !== `undefined`) {
+    const dependencies = Object.keys(require.cache)
+      .filter(path => !path.split(/[\\/]/).includes(`node_modules`));
+
+    writeFileSync(DEPENDENCIES_PATH, JSON.stringify(dependencies));
+  }
 }
 
 main();
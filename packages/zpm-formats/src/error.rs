@@ -19,6 +19,9 @@ pub enum Error {
     #[error("Compression level must be between 0 and 9")]
     InvalidCompressionLevel,
 
+    #[error("Brotli quality must be between 0 and 11")]
+    InvalidBrotliQuality,
+
     #[error("Invalid os string conversion")]
     OsStringConversion(std::ffi::OsString),
 
@@ -30,6 +33,9 @@ pub enum Error {
 
     #[error("Invalid tar file")]
     InvalidTarFile,
+
+    #[error("Invalid entry path: {0}")]
+    InvalidEntryPath(String),
 }
 
 impl From<std::io::Error> for Error {
@@ -187,6 +187,10 @@ impl<'a, T> Iterator for Compress<T> where T: Iterator<Item = Entry<'a>> {
         };
 
         let compressed_data = match algorithm {
+            // The deflate backend is pinned to zlib-rs through the flate2
+            // features (rather than whatever zlib the system provides), and
+            // we only ever use its default window and memory settings, so
+            // that the same data always compresses to the same bytes
             CompressionAlgorithm::Deflate(level) => {
                 let mut encoder
                     = DeflateEncoder::new(Vec::new(), flate2::Compression::new(level as u32));
@@ -194,6 +198,14 @@ impl<'a, T> Iterator for Compress<T> where T: Iterator<Item = Entry<'a>> {
                 encoder.write_all(&next.data).unwrap();
                 encoder.finish().unwrap()
             },
+
+            CompressionAlgorithm::Brotli(quality) => {
+                let mut encoder
+                    = brotli::CompressorWriter::new(Vec::new(), 4096, quality, 22);
+
+                encoder.write_all(&next.data).unwrap();
+                encoder.into_inner()
+            },
         };
 
         next.compression = Some(Compression {
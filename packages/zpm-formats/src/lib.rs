@@ -1,6 +1,6 @@
 use std::{borrow::Cow, os::unix::fs::PermissionsExt};
 
-use zpm_utils::{FromFileString, impl_file_string_from_str, Path, ToFileString, ToHumanString};
+use zpm_utils::{FromFileString, Glob, impl_file_string_from_str, Path, ToFileString, ToHumanString};
 
 pub(crate) mod zip_structs;
 
@@ -13,9 +13,18 @@ pub mod zip;
 
 pub use error::Error;
 
+#[cfg(test)]
+#[path = "./lib.test.rs"]
+mod lib_tests;
+
+/// The quality used when `brotli` is set without an explicit one; the
+/// highest levels are much slower for little gain.
+pub const DEFAULT_BROTLI_QUALITY: u32 = 9;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CompressionAlgorithm {
     Deflate(usize),
+    Brotli(u32),
 }
 
 impl FromFileString for CompressionAlgorithm {
@@ -26,6 +35,22 @@ impl FromFileString for CompressionAlgorithm {
             return Err(Error::MixedValueDeprecated);
         }
 
+        if src == "brotli" {
+            return Ok(CompressionAlgorithm::Brotli(DEFAULT_BROTLI_QUALITY));
+        }
+
+        if let Some(quality) = src.strip_prefix("brotli-") {
+            let quality
+                = quality.parse::<u32>()
+                    .map_err(|_| Error::InvalidBrotliQuality)?;
+
+            if quality > 11 {
+                return Err(Error::InvalidBrotliQuality);
+            }
+
+            return Ok(CompressionAlgorithm::Brotli(quality));
+        }
+
         let level
             = src.parse::<usize>()
                 .map_err(|_| Error::InvalidCompressionLevel)?;
@@ -42,6 +67,7 @@ impl ToFileString for CompressionAlgorithm {
     fn to_file_string(&self) -> String {
         match self {
             CompressionAlgorithm::Deflate(level) => level.to_string(),
+            CompressionAlgorithm::Brotli(quality) => format!("brotli-{}", quality),
         }
     }
 }
@@ -50,16 +76,24 @@ impl ToHumanString for CompressionAlgorithm {
     fn to_print_string(&self) -> String {
         match self {
             CompressionAlgorithm::Deflate(level) => level.to_string(),
+            CompressionAlgorithm::Brotli(quality) => format!("brotli-{}", quality),
         }
     }
 }
 
 impl<'de> serde::Deserialize<'de> for CompressionAlgorithm {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: serde::Deserializer<'de> {
-        let s
-            = usize::deserialize(deserializer)?;
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Level(usize),
+            Name(String),
+        }
 
-        Ok(CompressionAlgorithm::Deflate(s))
+        match Repr::deserialize(deserializer)? {
+            Repr::Level(level) => Ok(CompressionAlgorithm::Deflate(level)),
+            Repr::Name(name) => CompressionAlgorithm::from_file_string(&name).map_err(serde::de::Error::custom),
+        }
     }
 }
 
@@ -68,6 +102,9 @@ impl serde::Serialize for CompressionAlgorithm {
         match self {
             CompressionAlgorithm::Deflate(level)
                 => serializer.serialize_u32(*level as u32),
+
+            CompressionAlgorithm::Brotli(_)
+                => serializer.serialize_str(&self.to_file_string()),
         }
     }
 }
@@ -113,17 +150,40 @@ impl<'a> Entry<'a> {
 
 pub fn entries_to_disk<'a>(entries: &[Entry<'a>], base: &Path) -> Result<(), Error> {
     for entry in entries {
-        base.with_join(&entry.name)
-            .fs_create_parent()?
-            .fs_change(&entry.data, entry.mode & 0o111 == 0o111)?;
+        entry_to_disk(entry, base)?;
     }
 
     Ok(())
 }
 
-pub fn entries_from_folder<'a>(path: &Path) -> Result<Vec<Entry<'a>>, Error> {
+/// Writes the given entry within the base folder. Entries come from archives
+/// we don't control, so those whose name would resolve outside of the base
+/// folder (absolute paths or paths going through `..`) are rejected.
+pub fn entry_to_disk(entry: &Entry<'_>, base: &Path) -> Result<(), Error> {
+    if !entry.name.is_forward() {
+        return Err(Error::InvalidEntryPath(entry.name.to_file_string()));
+    }
+
+    base.with_join(&entry.name)
+        .fs_create_parent()?
+        .fs_change(&entry.data, entry.mode & 0o111 == 0o111)?;
+
+    Ok(())
+}
+
+pub fn entries_from_folder<'a>(root: &Path) -> Result<Vec<Entry<'a>>, Error> {
+    entries_from_folder_filtered(root, &[], &[])
+}
+
+/// Same as `entries_from_folder`, but only keeps the files matching one of
+/// the `include` patterns (all of them if empty). Paths matching one of the
+/// `exclude` patterns are skipped; for folders this happens before descending
+/// into them, so `**/node_modules` doesn't even get traversed.
+///
+/// Patterns are matched against the paths relative to the root.
+pub fn entries_from_folder_filtered<'a>(root: &Path, include: &[Glob], exclude: &[Glob]) -> Result<Vec<Entry<'a>>, Error> {
     let mut entries = vec![];
-    let mut process_queue = vec![path.clone()];
+    let mut process_queue = vec![root.clone()];
 
     while let Some(path) = process_queue.pop() {
         let listing = path.fs_read_dir()?;
@@ -132,12 +192,23 @@ pub fn entries_from_folder<'a>(path: &Path) -> Result<Vec<Entry<'a>>, Error> {
             let entry = entry?;
             let path = Path::try_from(entry.path())?;
 
+            // Entries are named relative to the root so that nested files
+            // keep their location within the archive
+            let name = path.relative_to(root);
+
+            if exclude.iter().any(|glob| glob.is_match(name.as_str())) {
+                continue;
+            }
+
             if path.fs_is_dir() {
                 process_queue.push(path);
                 continue;
             }
 
-            let name = Path::try_from(entry.file_name().into_string()?)?;
+            if !include.is_empty() && !include.iter().any(|glob| glob.is_match(name.as_str())) {
+                continue;
+            }
+
             let data = path.fs_read()?;
             let metadata = path.fs_metadata()?;
 
@@ -0,0 +1,116 @@
+use std::borrow::Cow;
+
+use zpm_utils::{Glob, Path};
+
+use crate::{entries_from_folder, entries_from_folder_filtered, entry_to_disk, Entry, Error};
+
+fn create_fixture() -> Path {
+    let root
+        = Path::temp_dir().unwrap();
+
+    for rel_path in ["index.js", "lib/util.js", "lib/README.md", "node_modules/dep/index.js", ".git/HEAD"] {
+        root.with_join_str(rel_path)
+            .fs_create_parent().unwrap()
+            .fs_write_text("").unwrap();
+    }
+
+    root
+}
+
+fn entry_names(root: &Path, include: &[Glob], exclude: &[Glob]) -> Vec<String> {
+    let mut names = entries_from_folder_filtered(root, include, exclude).unwrap()
+        .into_iter()
+        .map(|entry| entry.name.as_str().to_string())
+        .collect::<Vec<_>>();
+
+    names.sort();
+    names
+}
+
+#[test]
+fn it_names_entries_relative_to_the_root() {
+    let root
+        = create_fixture();
+
+    let mut names = entries_from_folder(&root).unwrap()
+        .into_iter()
+        .map(|entry| entry.name.as_str().to_string())
+        .collect::<Vec<_>>();
+
+    names.sort();
+
+    // Nested files must keep their folders, otherwise they would all end up
+    // at the top level of the archives built from these entries
+    assert_eq!(names, vec![
+        ".git/HEAD",
+        "index.js",
+        "lib/README.md",
+        "lib/util.js",
+        "node_modules/dep/index.js",
+    ]);
+}
+
+#[test]
+fn it_keeps_everything_without_filters() {
+    let root
+        = create_fixture();
+
+    assert_eq!(entry_names(&root, &[], &[]), vec![
+        ".git/HEAD",
+        "index.js",
+        "lib/README.md",
+        "lib/util.js",
+        "node_modules/dep/index.js",
+    ]);
+}
+
+#[test]
+fn it_prunes_excluded_folders() {
+    let root
+        = create_fixture();
+
+    let exclude
+        = [Glob::parse("**/node_modules").unwrap(), Glob::parse(".git").unwrap()];
+
+    assert_eq!(entry_names(&root, &[], &exclude), vec![
+        "index.js",
+        "lib/README.md",
+        "lib/util.js",
+    ]);
+}
+
+#[test]
+fn it_only_keeps_included_files() {
+    let root
+        = create_fixture();
+
+    let include
+        = [Glob::parse("**/*.js").unwrap()];
+    let exclude
+        = [Glob::parse("node_modules").unwrap()];
+
+    assert_eq!(entry_names(&root, &include, &exclude), vec![
+        "index.js",
+        "lib/util.js",
+    ]);
+}
+
+#[test]
+fn it_rejects_entries_escaping_the_base_folder() {
+    let base
+        = Path::temp_dir().unwrap();
+
+    for name in ["../escaped.js", "lib/../../escaped.js", "/escaped.js"] {
+        let entry
+            = Entry::new_file(Path::try_from(name).unwrap(), Cow::Borrowed(b""));
+
+        assert!(matches!(entry_to_disk(&entry, &base), Err(Error::InvalidEntryPath(_))));
+    }
+
+    let entry
+        = Entry::new_file(Path::try_from("lib/../index.js").unwrap(), Cow::Borrowed(b"42"));
+
+    entry_to_disk(&entry, &base).unwrap();
+
+    assert_eq!(base.with_join_str("index.js").fs_read_text().unwrap(), "42");
+}
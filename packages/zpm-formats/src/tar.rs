@@ -2,7 +2,7 @@ use std::{borrow::Cow, io::{Read, Write}};
 
 use zerocopy::{Immutable, IntoBytes, KnownLayout, Unaligned};
 
-use crate::{error::Error, tar_iter::TarIterator};
+use crate::{error::Error, tar_iter::TarIterator, zip_iter::unpack_brotli};
 
 use super::Entry;
 
@@ -120,6 +120,19 @@ impl<'a> ToTar for Vec<Entry<'a>> {
     }
 }
 
+/// Detects whether the buffer looks like a tarball (either gzipped or not)
+/// based on its magic bytes rather than on the file extension.
+pub fn is_tarball(buffer: &[u8]) -> bool {
+    buffer.starts_with(&[0x1f, 0x8b]) || buffer.get(257..262) == Some(b"ustar")
+}
+
+fn is_ustar_archive(buffer: &[u8]) -> bool {
+    buffer.get(257..262) == Some(b"ustar")
+}
+
+/// Returns the tar archive contained in the given buffer, which may be
+/// gzip-compressed, Brotli-compressed (as served by some HTTP caches), or
+/// not compressed at all.
 pub fn unpack_tgz(buffer: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
     if buffer.starts_with(&[0x1f, 0x8b]) {
         let mut gz = flate2::read::GzDecoder::new(buffer);
@@ -128,7 +141,26 @@ pub fn unpack_tgz(buffer: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
         gz.read_to_end(&mut buffer)?;
 
         Ok(Cow::Owned(buffer))
-    } else {
+    } else if is_ustar_archive(buffer) {
         Ok(Cow::Borrowed(buffer))
+    } else {
+        // Brotli streams don't have a magic number, so we can only check
+        // whether they decode into a tar archive
+        match unpack_brotli(buffer) {
+            Ok(data) if is_ustar_archive(&data) => Ok(Cow::Owned(data)),
+            _ => Ok(Cow::Borrowed(buffer)),
+        }
     }
 }
+
+/// Same as `unpack_tgz`, but takes ownership of the buffer so that the
+/// compressed data gets released as soon as it's been decoded, rather than
+/// staying in memory alongside the archive we build from its content.
+pub fn unpack_tgz_owned(buffer: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let data = match unpack_tgz(&buffer)? {
+        Cow::Owned(data) => Some(data),
+        Cow::Borrowed(_) => None,
+    };
+
+    Ok(data.unwrap_or(buffer))
+}
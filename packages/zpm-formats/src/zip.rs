@@ -1,12 +1,23 @@
+use std::{borrow::Cow, collections::HashMap, io::Write};
+
 use pnp::fs::VPathInfo;
 use zerocopy::IntoBytes;
 use zerocopy::little_endian::{U16, U32};
 use zpm_utils::Path;
 
-use crate::{error::Error, zip_iter::ZipIterator, zip_structs::{CentralDirectoryRecord, EndOfCentralDirectoryRecord, FileHeader, GeneralRecord}, CompressionAlgorithm};
+use crate::{error::Error, zip_iter::{ZipFileIterator, ZipIterator}, zip_structs::{CentralDirectoryRecord, EndOfCentralDirectoryRecord, FileHeader, GeneralRecord, BROTLI_COMPRESSION_METHOD, DEFLATE_COMPRESSION_METHOD, STORE_COMPRESSION_METHOD}, CompressionAlgorithm};
 
 use super::Entry;
 
+#[cfg(test)]
+#[path = "./zip.test.rs"]
+mod zip_tests;
+
+/// All entries share the same timestamp (1984-06-22 21:50:00, in MS-DOS
+/// format), so that archives only depend on the content they store.
+const ENTRY_MOD_TIME: u16 = 0xae40;
+const ENTRY_MOD_DATE: u16 = 0x08d6;
+
 #[derive(Debug, Clone)]
 pub struct CraftZipOptions {
     pub compression: Option<CompressionAlgorithm>,
@@ -21,16 +32,52 @@ pub fn first_entry_from_zip(buffer: &[u8]) -> Result<Entry<'_>, Error> {
         .unwrap_or_else(|| Err(Error::InvalidZipFile("Empty".to_string())))
 }
 
+/// Same as `first_entry_from_zip`, but only reads the parts of the archive
+/// required to decode the first entry rather than the whole file.
+pub fn first_entry_from_zip_file(path: &Path) -> Result<Entry<'static>, Error> {
+    ZipFileIterator::new(path)?.next()
+        .unwrap_or_else(|| Err(Error::InvalidZipFile("Empty".to_string())))
+}
+
 pub trait ToZip {
     fn to_zip(&self) -> Vec<u8>;
 }
 
+/// Returns the entries that must be written in the archive, in the order they
+/// must be written. Folders aren't stored since they are implied by the files
+/// they contain, and when a name is listed multiple times the last entry wins
+/// (as when extracting a tarball), taking the position of the last one.
+fn archived_entries<'a, 'b>(entries: &'b [Entry<'a>]) -> Vec<&'b Entry<'a>> {
+    let last_indices = entries.iter()
+        .enumerate()
+        .map(|(index, entry)| (entry.name.as_str(), index))
+        .collect::<HashMap<_, _>>();
+
+    entries.iter()
+        .enumerate()
+        .filter(|(index, entry)| !entry.name.as_str().ends_with('/') && last_indices.get(entry.name.as_str()) == Some(index))
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+/// Only the executable bit is meaningful for the files we store; the other
+/// bits vary depending on the umask of whoever created the source archive.
+fn normalized_mode(mode: u32) -> u32 {
+    match mode & 0o111 != 0 {
+        true => 0o755,
+        false => 0o644,
+    }
+}
+
 impl<'a> ToZip for Vec<Entry<'a>> {
     fn to_zip(&self) -> Vec<u8> {
+        let entries
+            = archived_entries(self);
+
         let mut general_capacity = 0;
         let mut central_directory_capacity = std::mem::size_of::<EndOfCentralDirectoryRecord>();
 
-        for entry in self {
+        for entry in &entries {
             let compressed_data = entry.compression
                 .as_ref()
                 .map_or(&entry.data, |compressed_data| &compressed_data.data);
@@ -50,7 +97,7 @@ impl<'a> ToZip for Vec<Entry<'a>> {
         let mut central_directory_segment
             = Vec::with_capacity(central_directory_capacity);
 
-        for entry in self {
+        for entry in &entries {
             let compressed_data = entry.compression
                 .as_ref()
                 .map_or(&entry.data, |compressed_data| &compressed_data.data);
@@ -66,16 +113,7 @@ impl<'a> ToZip for Vec<Entry<'a>> {
         }
 
         central_directory_segment.extend_from_slice(
-            EndOfCentralDirectoryRecord {
-                signature: [0x50, 0x4b, 0x05, 0x06],
-                disk_number: U16::new(0x00),
-                disk_with_central_directory: U16::new(0x00),
-                number_of_files_on_this_disk: U16::new(self.len() as u16),
-                number_of_files: U16::new(self.len() as u16),
-                size_of_central_directory: U32::new(central_directory_segment.len() as u32),
-                offset_of_central_directory: U32::new(general_segment.len() as u32),
-                comment_length: U16::new(0x00),
-            }.as_bytes(),
+            end_of_central_directory_record(entries.len(), central_directory_segment.len(), general_segment.len()).as_bytes(),
         );
 
         assert_eq!(general_segment.len(), general_capacity);
@@ -85,31 +123,111 @@ impl<'a> ToZip for Vec<Entry<'a>> {
     }
 }
 
-fn inject_general_record(target: &mut Vec<u8>, entry: &Entry, compressed_data: &[u8], compression: Option<CompressionAlgorithm>) {
-    let compression_method: u16 = match compression {
-        Some(CompressionAlgorithm::Deflate(_)) => 0x08, // Deflate compression
-        None => 0x00, // No compression
-    };
+/// Writes an archive one entry at a time, so that archives can be generated
+/// without holding all their content in memory. The output is the same as
+/// `to_zip`, except that the entries are written as-is: they are expected to
+/// not contain folders nor duplicate names.
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    offset: usize,
+    entry_count: usize,
+    central_directory_segment: Vec<u8>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entry_count: 0,
+            central_directory_segment: Vec::new(),
+        }
+    }
+
+    pub fn write_entry(&mut self, entry: &Entry) -> Result<(), Error> {
+        let compressed_data = entry.compression
+            .as_ref()
+            .map_or(&entry.data, |compressed_data| &compressed_data.data);
+
+        let compression = entry.compression
+            .as_ref()
+            .map(|compressed_data| compressed_data.algorithm);
+
+        let name_bytes
+            = entry.name.as_str().as_bytes();
+
+        self.writer.write_all(general_record(entry, compressed_data, compression).as_bytes())?;
+        self.writer.write_all(name_bytes)?;
+        self.writer.write_all(compressed_data)?;
+
+        inject_central_directory_record(&mut self.central_directory_segment, entry, compressed_data, self.offset, compression);
+
+        self.offset += std::mem::size_of::<GeneralRecord>() + name_bytes.len() + compressed_data.len();
+        self.entry_count += 1;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, Error> {
+        let end_of_central_directory_record
+            = end_of_central_directory_record(self.entry_count, self.central_directory_segment.len(), self.offset);
 
+        self.writer.write_all(&self.central_directory_segment)?;
+        self.writer.write_all(end_of_central_directory_record.as_bytes())?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+fn end_of_central_directory_record(entry_count: usize, central_directory_size: usize, central_directory_offset: usize) -> EndOfCentralDirectoryRecord {
+    EndOfCentralDirectoryRecord {
+        signature: [0x50, 0x4b, 0x05, 0x06],
+        disk_number: U16::new(0x00),
+        disk_with_central_directory: U16::new(0x00),
+        number_of_files_on_this_disk: U16::new(entry_count as u16),
+        number_of_files: U16::new(entry_count as u16),
+        size_of_central_directory: U32::new(central_directory_size as u32),
+        offset_of_central_directory: U32::new(central_directory_offset as u32),
+        comment_length: U16::new(0x00),
+    }
+}
+
+fn compression_method(compression: Option<CompressionAlgorithm>) -> u16 {
+    match compression {
+        Some(CompressionAlgorithm::Deflate(_)) => DEFLATE_COMPRESSION_METHOD,
+        Some(CompressionAlgorithm::Brotli(_)) => BROTLI_COMPRESSION_METHOD,
+        None => STORE_COMPRESSION_METHOD,
+    }
+}
+
+fn general_record(entry: &Entry, compressed_data: &[u8], compression: Option<CompressionAlgorithm>) -> GeneralRecord {
+    let compression_method
+        = compression_method(compression);
+
+    GeneralRecord {
+        signature: [0x50, 0x4b, 0x03, 0x04],
+        header: FileHeader {
+            version_needed_to_extract: U16::new(if compression_method != STORE_COMPRESSION_METHOD { 0x14 } else { 0x0A }),
+            general_purpose_bit_flag: U16::new(0x00),
+            compression_method: U16::new(compression_method),
+            last_mod_file_time: U16::new(ENTRY_MOD_TIME),
+            last_mod_file_date: U16::new(ENTRY_MOD_DATE),
+            crc_32: U32::new(entry.crc),
+            compressed_size: U32::new(compressed_data.len() as u32),
+            uncompressed_size: U32::new(entry.data.len() as u32),
+            file_name_length: U16::new(entry.name.as_str().len() as u16),
+            extra_field_length: U16::new(0x00),
+        },
+    }
+}
+
+fn inject_general_record(target: &mut Vec<u8>, entry: &Entry, compressed_data: &[u8], compression: Option<CompressionAlgorithm>) {
     let name_bytes
         = entry.name.as_str().as_bytes();
 
     target.extend_from_slice(
-        GeneralRecord {
-            signature: [0x50, 0x4b, 0x03, 0x04],
-            header: FileHeader {
-                version_needed_to_extract: U16::new(if compression_method == 0x08 { 0x14 } else { 0x0A }),
-                general_purpose_bit_flag: U16::new(0x00),
-                compression_method: U16::new(compression_method),
-                last_mod_file_time: U16::new(0xae40),
-                last_mod_file_date: U16::new(0x08d6),
-                crc_32: U32::new(entry.crc),
-                compressed_size: U32::new(compressed_data.len() as u32),
-                uncompressed_size: U32::new(entry.data.len() as u32),
-                file_name_length: U16::new(name_bytes.len() as u16),
-                extra_field_length: U16::new(0x00),
-            },
-        }.as_bytes(),
+        general_record(entry, compressed_data, compression).as_bytes(),
     );
 
     // File name
@@ -120,10 +238,8 @@ fn inject_general_record(target: &mut Vec<u8>, entry: &Entry, compressed_data: &
 }
 
 fn inject_central_directory_record(target: &mut Vec<u8>, entry: &Entry, compressed_data: &[u8], offset: usize, compression: Option<CompressionAlgorithm>) {
-    let compression_method: u16 = match compression {
-        Some(CompressionAlgorithm::Deflate(_)) => 0x08, // Deflate compression
-        None => 0x00, // No compression
-    };
+    let compression_method
+        = compression_method(compression);
 
     let name_bytes
         = entry.name.as_str().as_bytes();
@@ -136,8 +252,8 @@ fn inject_central_directory_record(target: &mut Vec<u8>, entry: &Entry, compress
                 version_needed_to_extract: U16::new(if compression_method == 0x08 { 0x14 } else { 0x14 }),
                 general_purpose_bit_flag: U16::new(0x00),
                 compression_method: U16::new(compression_method),
-                last_mod_file_time: U16::new(0xae40),
-                last_mod_file_date: U16::new(0x08d6),
+                last_mod_file_time: U16::new(ENTRY_MOD_TIME),
+                last_mod_file_date: U16::new(ENTRY_MOD_DATE),
                 crc_32: U32::new(entry.crc),
                 compressed_size: U32::new(compressed_data.len() as u32),
                 uncompressed_size: U32::new(entry.data.len() as u32),
@@ -147,7 +263,7 @@ fn inject_central_directory_record(target: &mut Vec<u8>, entry: &Entry, compress
             file_comment_length: U16::new(0x00),
             disk_number_start: U16::new(0x00),
             internal_file_attributes: U16::new(0x00),
-            external_file_attributes: U32::new((entry.mode as u32) << 16),
+            external_file_attributes: U32::new(normalized_mode(entry.mode) << 16),
             relative_offset_of_local_header: U32::new(offset as u32),
         }.as_bytes(),
     );
@@ -156,6 +272,52 @@ fn inject_central_directory_record(target: &mut Vec<u8>, entry: &Entry, compress
     target.extend_from_slice(name_bytes);
 }
 
+/// Lists the files within the given folder, which may be located inside a
+/// zip archive (as are the packages installed by the PnP linker). Entries are
+/// named relative to the folder.
+pub fn entries_from_folder_with_zip<'a>(root: &Path) -> Result<Vec<Entry<'a>>, Error> {
+    let path_buf
+        = root.to_path_buf();
+
+    match pnp::fs::vpath(&path_buf)? {
+        pnp::fs::VPath::Native(_) => {
+            crate::entries_from_folder(root)
+        },
+
+        pnp::fs::VPath::Virtual(info) => {
+            crate::entries_from_folder(&Path::try_from(info.physical_base_path())?)
+        },
+
+        pnp::fs::VPath::Zip(info) => {
+            let zip_data
+                = Path::try_from(info.physical_base_path())?
+                    .fs_read_prealloc()?;
+
+            let folder
+                = Path::try_from(info.zip_path)?;
+
+            let entries = entries_from_zip(&zip_data)?
+                .into_iter()
+                .filter(|entry| !entry.name.as_str().ends_with('/'))
+                .filter_map(|entry| {
+                    let name = entry.name.strip_prefix(&folder)
+                        .filter(|name| !name.is_empty())?;
+
+                    Some(Entry {
+                        name,
+                        mode: entry.mode,
+                        crc: entry.crc,
+                        data: Cow::Owned(entry.data.into_owned()),
+                        compression: None,
+                    })
+                })
+                .collect();
+
+            Ok(entries)
+        },
+    }
+}
+
 pub trait ZipSupport {
     fn fs_read_text_from_zip_buffer(&self, buf: &[u8]) -> Result<String, Error>;
     fn fs_read_text_with_zip(&self) -> Result<String, Error>;
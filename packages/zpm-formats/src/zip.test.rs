@@ -0,0 +1,82 @@
+use std::borrow::Cow;
+
+use zpm_utils::{Hash64, Path, ToFileString};
+
+use crate::{iter_ext::IterExt, tar::{entries_from_tar, unpack_tgz}, zip::{entries_from_zip, ToZip, ZipWriter}, Entry};
+
+const DETERMINISTIC_TGZ: &[u8] = include_bytes!("../fixtures/deterministic.tgz");
+
+/// The fixture contains a folder entry, files with unusual modes (0664,
+/// 0775, 0600), and the same file listed twice with different contents.
+fn zip_from_fixture() -> Vec<u8> {
+    let tar_data
+        = unpack_tgz(DETERMINISTIC_TGZ).unwrap();
+
+    entries_from_tar(&tar_data).unwrap()
+        .into_iter()
+        .strip_first_segment()
+        .update_crc32()
+        .collect::<Vec<_>>()
+        .to_zip()
+}
+
+#[test]
+fn it_generates_the_exact_same_archive() {
+    // Any change to this hash means that the archives generated from the
+    // same packages will differ from the ones already in the users' caches
+    // and lockfiles
+    assert_eq!(
+        Hash64::from_data(zip_from_fixture()).to_file_string(),
+        "ce62b9893db5a36a464625c72c768626aeaca7ad78ad733a0d6a5d6541dbe6d4ddab7b133e1fb3d4e0a3bd8dcf2c08cd463a700e19dd2b75594070fec829390d",
+    );
+}
+
+#[test]
+fn it_round_trips_through_entries_from_zip() {
+    let zip_data
+        = zip_from_fixture();
+
+    let entries = entries_from_zip(&zip_data).unwrap()
+        .into_iter()
+        .map(|entry| (entry.name.as_str().to_string(), entry.mode, entry.data.into_owned()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(entries, vec![
+        ("package.json".to_string(), 0o644, b"{\"name\": \"deterministic\", \"version\": \"1.0.0\"}\n".to_vec()),
+        ("bin.js".to_string(), 0o755, b"#!/usr/bin/env node\nconsole.log(42);\n".to_vec()),
+        ("lib/index.js".to_string(), 0o644, b"module.exports = 2;\n".to_vec()),
+    ]);
+}
+
+#[test]
+fn it_writes_the_same_archive_when_streaming() {
+    let zip_data
+        = zip_from_fixture();
+
+    let mut writer
+        = ZipWriter::new(Vec::new());
+
+    for entry in entries_from_zip(&zip_data).unwrap() {
+        writer.write_entry(&entry).unwrap();
+    }
+
+    assert_eq!(writer.finish().unwrap(), zip_data);
+}
+
+#[test]
+fn it_doesnt_store_folder_entries() {
+    let entries = vec![
+        Entry::new_file(Path::try_from("lib/").unwrap(), Cow::Borrowed(b"")),
+        Entry::new_file(Path::try_from("lib/index.js").unwrap(), Cow::Borrowed(b"")),
+    ];
+
+    let zip_data
+        = entries.to_zip();
+
+    let names = entries_from_zip(&zip_data).unwrap()
+        .into_iter()
+        .map(|entry| entry.name.as_str().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, vec!["lib/index.js".to_string()]);
+}
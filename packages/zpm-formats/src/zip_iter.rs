@@ -1,9 +1,17 @@
-use std::{borrow::Cow, io::Read};
+use std::{borrow::Cow, fs::File, io::{Read, Seek, SeekFrom}};
 
 use zerocopy::FromBytes;
 use zpm_utils::Path;
 
-use crate::{zip_structs::{CentralDirectoryRecord, EndOfCentralDirectoryRecord, GeneralRecord}, Compression, CompressionAlgorithm, Entry, Error};
+use crate::{zip_structs::{CentralDirectoryRecord, EndOfCentralDirectoryRecord, ExtraFieldHeader, GeneralRecord, Zip64EndOfCentralDirectoryLocator, Zip64EndOfCentralDirectoryRecord, BROTLI_COMPRESSION_METHOD, DEFLATE_COMPRESSION_METHOD}, Compression, CompressionAlgorithm, Entry, Error};
+
+#[cfg(test)]
+#[path = "./zip_iter.test.rs"]
+mod zip_iter_tests;
+
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+const ZIP64_LOCATOR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x06, 0x07];
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x06, 0x06];
 
 fn unpack_deflate(data: &[u8]) -> Result<Vec<u8>, Error> {
     let mut decoder
@@ -17,11 +25,96 @@ fn unpack_deflate(data: &[u8]) -> Result<Vec<u8>, Error> {
     Ok(buffer)
 }
 
+pub(crate) fn unpack_brotli(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut decoder
+        = brotli::Decompressor::new(data, 4096);
+
+    let mut buffer
+        = Vec::new();
+
+    decoder.read_to_end(&mut buffer)?;
+
+    Ok(buffer)
+}
+
 pub struct ZipIterator<'a> {
     buffer: &'a [u8],
 
     central_directory_record_offset: usize,
-    end_of_central_directory_record_offset: usize,
+    central_directory_end_offset: usize,
+}
+
+/// The sizes and offset of an entry, which are stored in the zip64 extra
+/// field when they don't fit in the 32 bits of the central directory record.
+struct EntryLocation {
+    compressed_size: usize,
+    local_file_header_offset: usize,
+}
+
+impl EntryLocation {
+    fn new(central_directory_record: &CentralDirectoryRecord, extra_field: &[u8]) -> Result<EntryLocation, Error> {
+        let uncompressed_size
+            = central_directory_record.header.uncompressed_size.get();
+        let compressed_size
+            = central_directory_record.header.compressed_size.get();
+        let local_file_header_offset
+            = central_directory_record.relative_offset_of_local_header.get();
+
+        let mut location = EntryLocation {
+            compressed_size: compressed_size as usize,
+            local_file_header_offset: local_file_header_offset as usize,
+        };
+
+        if uncompressed_size != u32::MAX && compressed_size != u32::MAX && local_file_header_offset != u32::MAX {
+            return Ok(location);
+        }
+
+        let zip64_field = find_extra_field(extra_field, ZIP64_EXTRA_FIELD_ID)?
+            .ok_or_else(|| Error::InvalidZipFile("Missing zip64 extra field".to_string()))?;
+
+        // The field only contains the values that overflowed, in this order
+        let mut values = zip64_field
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()) as usize);
+
+        let mut next_value = || values.next()
+            .ok_or_else(|| Error::InvalidZipFile("Truncated zip64 extra field".to_string()));
+
+        if uncompressed_size == u32::MAX {
+            next_value()?;
+        }
+
+        if compressed_size == u32::MAX {
+            location.compressed_size = next_value()?;
+        }
+
+        if local_file_header_offset == u32::MAX {
+            location.local_file_header_offset = next_value()?;
+        }
+
+        Ok(location)
+    }
+}
+
+fn find_extra_field(mut extra_field: &[u8], header_id: u16) -> Result<Option<&[u8]>, Error> {
+    while !extra_field.is_empty() {
+        let (header, rest) = ExtraFieldHeader::ref_from_prefix(extra_field)
+            .map_err(|_| Error::InvalidZipFile("Failed to parse extra field".to_string()))?;
+
+        let data_size
+            = header.data_size.get() as usize;
+
+        let data = rest.get(..data_size)
+            .ok_or_else(|| Error::InvalidZipFile("Truncated extra field".to_string()))?;
+
+        if header.header_id.get() == header_id {
+            return Ok(Some(data));
+        }
+
+        extra_field = &rest[data_size..];
+    }
+
+    Ok(None)
 }
 
 impl<'a> ZipIterator<'a> {
@@ -39,18 +132,75 @@ impl<'a> ZipIterator<'a> {
         let end_of_central_directory_record = EndOfCentralDirectoryRecord::read_from_bytes(&buffer[end_of_central_directory_record_offset..])
             .map_err(|_| Error::InvalidZipFile("Failed to parse end of central directory record".to_string()))?;
 
-        let central_directory_record_offset
+        let mut central_directory_record_offset
             = end_of_central_directory_record.offset_of_central_directory.get() as usize;
+        let mut central_directory_size
+            = end_of_central_directory_record.size_of_central_directory.get() as usize;
+
+        let is_zip64
+            = end_of_central_directory_record.number_of_files.get() == u16::MAX
+                || end_of_central_directory_record.size_of_central_directory.get() == u32::MAX
+                || end_of_central_directory_record.offset_of_central_directory.get() == u32::MAX;
+
+        if is_zip64 {
+            let zip64_end_of_central_directory_record
+                = Self::read_zip64_end_of_central_directory_record(buffer, end_of_central_directory_record_offset)?;
+
+            central_directory_record_offset
+                = zip64_end_of_central_directory_record.offset_of_central_directory.get() as usize;
+            central_directory_size
+                = zip64_end_of_central_directory_record.size_of_central_directory.get() as usize;
+        }
+
+        let central_directory_end_offset
+            = central_directory_record_offset.saturating_add(central_directory_size);
+
+        if central_directory_end_offset > end_of_central_directory_record_offset {
+            return Err(Error::InvalidZipFile("Central directory out of bounds".to_string()));
+        }
 
         Ok(ZipIterator {
             buffer,
 
             central_directory_record_offset,
-            end_of_central_directory_record_offset,
+            central_directory_end_offset,
         })
     }
 
-    fn parse_entry_at(&self, local_file_header_offset: usize, central_directory_record: &CentralDirectoryRecord, general_record: &GeneralRecord) -> Result<Entry<'a>, Error> {
+    fn read_zip64_end_of_central_directory_record(buffer: &'a [u8], end_of_central_directory_record_offset: usize) -> Result<&'a Zip64EndOfCentralDirectoryRecord, Error> {
+        let locator_offset = end_of_central_directory_record_offset
+            .checked_sub(std::mem::size_of::<Zip64EndOfCentralDirectoryLocator>())
+            .ok_or_else(|| Error::InvalidZipFile("Too small to contain the zip64 end of central directory locator".to_string()))?;
+
+        let (locator, _) = Zip64EndOfCentralDirectoryLocator::ref_from_prefix(&buffer[locator_offset..])
+            .map_err(|_| Error::InvalidZipFile("Failed to parse zip64 end of central directory locator".to_string()))?;
+
+        if locator.signature != ZIP64_LOCATOR_SIGNATURE {
+            return Err(Error::InvalidZipFile("Missing zip64 end of central directory locator".to_string()));
+        }
+
+        let record_offset
+            = locator.offset_of_zip64_end_of_central_directory.get() as usize;
+
+        let (record, _) = buffer.get(record_offset..)
+            .and_then(|slice| Zip64EndOfCentralDirectoryRecord::ref_from_prefix(slice).ok())
+            .ok_or_else(|| Error::InvalidZipFile("Failed to parse zip64 end of central directory record".to_string()))?;
+
+        if record.signature != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+            return Err(Error::InvalidZipFile("Invalid zip64 end of central directory record".to_string()));
+        }
+
+        Ok(record)
+    }
+
+    fn parse_entry_at(&self, location: &EntryLocation, central_directory_record: &CentralDirectoryRecord) -> Result<Entry<'a>, Error> {
+        let local_file_header_offset
+            = location.local_file_header_offset;
+
+        let (general_record, _) = self.buffer.get(local_file_header_offset..)
+            .and_then(|slice| GeneralRecord::ref_from_prefix(slice).ok())
+            .ok_or_else(|| Error::InvalidZipFile("Failed to parse general record".to_string()))?;
+
         let name_offset
             = local_file_header_offset + std::mem::size_of::<GeneralRecord>();
         let data_offset
@@ -61,43 +211,61 @@ impl<'a> ZipIterator<'a> {
         let name
             = Path::try_from(name_str)?;
 
-        let data_size
-            = central_directory_record.header.compressed_size.get() as usize;
-        let data
-            = &self.buffer[data_offset..data_offset + data_size];
-
-        let mut entry = Entry {
-            name,
-            mode: (central_directory_record.external_file_attributes.get() >> 16) as u32,
-            crc: general_record.header.crc_32.get(),
-            data: Cow::Borrowed(data),
-            compression: None,
-        };
-
-        match central_directory_record.header.compression_method.get() {
-            8 => {
-                entry.compression = Some(Compression {
-                    data: Cow::Borrowed(data),
-                    algorithm: CompressionAlgorithm::Deflate(0),
-                });
-
-                entry.data = Cow::Owned(unpack_deflate(data)?);
-            },
-
-            _ => {
+        // Entries written in streaming mode (bit 3 of the general purpose
+        // flag) have their crc and sizes zeroed in the local header, with the
+        // actual values stored in a data descriptor following the data; the
+        // central directory always contains them, so we read them from there
+        // rather than having to locate the descriptor.
+        let data = self.buffer.get(data_offset..data_offset.saturating_add(location.compressed_size))
+            .ok_or_else(|| Error::InvalidZipFile("Entry data out of bounds".to_string()))?;
 
-            },
-        }
+        decode_entry(name, central_directory_record, Cow::Borrowed(data))
+    }
+}
 
-        Ok(entry)
+fn decode_entry<'a>(name: Path, central_directory_record: &CentralDirectoryRecord, data: Cow<'a, [u8]>) -> Result<Entry<'a>, Error> {
+    let mut entry = Entry {
+        name,
+        mode: (central_directory_record.external_file_attributes.get() >> 16) as u32,
+        crc: central_directory_record.header.crc_32.get(),
+        data,
+        compression: None,
+    };
+
+    match central_directory_record.header.compression_method.get() {
+        DEFLATE_COMPRESSION_METHOD => {
+            let data
+                = Cow::Owned(unpack_deflate(&entry.data)?);
+
+            entry.compression = Some(Compression {
+                data: std::mem::replace(&mut entry.data, data),
+                algorithm: CompressionAlgorithm::Deflate(0),
+            });
+        },
+
+        BROTLI_COMPRESSION_METHOD => {
+            let data
+                = Cow::Owned(unpack_brotli(&entry.data)?);
+
+            entry.compression = Some(Compression {
+                data: std::mem::replace(&mut entry.data, data),
+                algorithm: CompressionAlgorithm::Brotli(0),
+            });
+        },
+
+        _ => {
+
+        },
     }
+
+    Ok(entry)
 }
 
 impl<'a> Iterator for ZipIterator<'a> {
     type Item = Result<Entry<'a>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.central_directory_record_offset >= self.end_of_central_directory_record_offset {
+        if self.central_directory_record_offset >= self.central_directory_end_offset {
             return None;
         }
 
@@ -108,19 +276,208 @@ impl<'a> Iterator for ZipIterator<'a> {
             Err(_) => return Some(Err(Error::InvalidZipFile("Failed to parse central directory record".to_string()))),
         };
 
-        let local_file_header_offset
-            = central_directory_record.relative_offset_of_local_header.get() as usize;
+        let extra_field_offset
+            = offset + std::mem::size_of::<CentralDirectoryRecord>() + central_directory_record.header.file_name_length.get() as usize;
+        let extra_field_end_offset
+            = extra_field_offset + central_directory_record.header.extra_field_length.get() as usize;
+
+        self.central_directory_record_offset
+            = extra_field_end_offset + central_directory_record.file_comment_length.get() as usize;
+
+        let Some(extra_field) = self.buffer.get(extra_field_offset..extra_field_end_offset) else {
+            return Some(Err(Error::InvalidZipFile("Failed to parse central directory record".to_string())));
+        };
+
+        Some(EntryLocation::new(central_directory_record, extra_field)
+            .and_then(|location| self.parse_entry_at(&location, central_directory_record)))
+    }
+}
 
-        let general_record = match GeneralRecord::ref_from_prefix(&self.buffer[local_file_header_offset..]) {
+/// Same as `ZipIterator`, but reads the entries from a file one at a time
+/// rather than from a buffer, so that large archives can be processed without
+/// being loaded in memory. Only the central directory is kept around.
+pub struct ZipFileIterator {
+    file: File,
+    file_size: usize,
+
+    central_directory: Vec<u8>,
+    central_directory_record_offset: usize,
+}
+
+impl ZipFileIterator {
+    pub fn new(path: &Path) -> Result<ZipFileIterator, Error> {
+        let mut file
+            = File::open(path.to_path_buf())?;
+
+        let file_size
+            = file.metadata()?.len() as usize;
+
+        let end_of_central_directory_record_size
+            = std::mem::size_of::<EndOfCentralDirectoryRecord>();
+
+        if end_of_central_directory_record_size > file_size {
+            return Err(Error::InvalidZipFile("Too small to contain the end of central directory record".to_string()))
+        }
+
+        let end_of_central_directory_record_offset
+            = file_size - end_of_central_directory_record_size;
+
+        let end_of_central_directory_record_data
+            = read_file_range(&mut file, end_of_central_directory_record_offset, end_of_central_directory_record_size)?;
+
+        let end_of_central_directory_record = EndOfCentralDirectoryRecord::read_from_bytes(&end_of_central_directory_record_data)
+            .map_err(|_| Error::InvalidZipFile("Failed to parse end of central directory record".to_string()))?;
+
+        let mut central_directory_record_offset
+            = end_of_central_directory_record.offset_of_central_directory.get() as usize;
+        let mut central_directory_size
+            = end_of_central_directory_record.size_of_central_directory.get() as usize;
+
+        let is_zip64
+            = end_of_central_directory_record.number_of_files.get() == u16::MAX
+                || end_of_central_directory_record.size_of_central_directory.get() == u32::MAX
+                || end_of_central_directory_record.offset_of_central_directory.get() == u32::MAX;
+
+        if is_zip64 {
+            let locator_size
+                = std::mem::size_of::<Zip64EndOfCentralDirectoryLocator>();
+
+            let locator_offset = end_of_central_directory_record_offset
+                .checked_sub(locator_size)
+                .ok_or_else(|| Error::InvalidZipFile("Too small to contain the zip64 end of central directory locator".to_string()))?;
+
+            let locator_data
+                = read_file_range(&mut file, locator_offset, locator_size)?;
+
+            let locator = Zip64EndOfCentralDirectoryLocator::read_from_bytes(&locator_data)
+                .map_err(|_| Error::InvalidZipFile("Failed to parse zip64 end of central directory locator".to_string()))?;
+
+            if locator.signature != ZIP64_LOCATOR_SIGNATURE {
+                return Err(Error::InvalidZipFile("Missing zip64 end of central directory locator".to_string()));
+            }
+
+            let record_size
+                = std::mem::size_of::<Zip64EndOfCentralDirectoryRecord>();
+
+            let record_offset
+                = locator.offset_of_zip64_end_of_central_directory.get() as usize;
+
+            if record_offset.saturating_add(record_size) > file_size {
+                return Err(Error::InvalidZipFile("Failed to parse zip64 end of central directory record".to_string()));
+            }
+
+            let record_data
+                = read_file_range(&mut file, record_offset, record_size)?;
+
+            let record = Zip64EndOfCentralDirectoryRecord::read_from_bytes(&record_data)
+                .map_err(|_| Error::InvalidZipFile("Failed to parse zip64 end of central directory record".to_string()))?;
+
+            if record.signature != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+                return Err(Error::InvalidZipFile("Invalid zip64 end of central directory record".to_string()));
+            }
+
+            central_directory_record_offset
+                = record.offset_of_central_directory.get() as usize;
+            central_directory_size
+                = record.size_of_central_directory.get() as usize;
+        }
+
+        let central_directory_end_offset
+            = central_directory_record_offset.saturating_add(central_directory_size);
+
+        if central_directory_end_offset > end_of_central_directory_record_offset {
+            return Err(Error::InvalidZipFile("Central directory out of bounds".to_string()));
+        }
+
+        let central_directory
+            = read_file_range(&mut file, central_directory_record_offset, central_directory_size)?;
+
+        Ok(ZipFileIterator {
+            file,
+            file_size,
+
+            central_directory,
+            central_directory_record_offset: 0,
+        })
+    }
+
+    fn read_entry_at(&mut self, location: &EntryLocation, central_directory_record: &CentralDirectoryRecord) -> Result<Entry<'static>, Error> {
+        let general_record_size
+            = std::mem::size_of::<GeneralRecord>();
+
+        if location.local_file_header_offset.saturating_add(general_record_size) > self.file_size {
+            return Err(Error::InvalidZipFile("Failed to parse general record".to_string()));
+        }
+
+        let general_record_data
+            = read_file_range(&mut self.file, location.local_file_header_offset, general_record_size)?;
+
+        let general_record = GeneralRecord::read_from_bytes(&general_record_data)
+            .map_err(|_| Error::InvalidZipFile("Failed to parse general record".to_string()))?;
+
+        let name_offset
+            = location.local_file_header_offset + general_record_size;
+        let name_size
+            = general_record.header.file_name_length.get() as usize;
+        let data_offset
+            = name_offset + name_size + general_record.header.extra_field_length.get() as usize;
+
+        if data_offset.saturating_add(location.compressed_size) > self.file_size {
+            return Err(Error::InvalidZipFile("Entry data out of bounds".to_string()));
+        }
+
+        let name_data
+            = read_file_range(&mut self.file, name_offset, name_size)?;
+        let name
+            = Path::try_from(std::str::from_utf8(&name_data)?)?;
+
+        // See `ZipIterator::parse_entry_at` regarding why the sizes are read
+        // from the central directory rather than the local header
+        let data
+            = read_file_range(&mut self.file, data_offset, location.compressed_size)?;
+
+        decode_entry(name, central_directory_record, Cow::Owned(data))
+    }
+}
+
+impl Iterator for ZipFileIterator {
+    type Item = Result<Entry<'static>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.central_directory_record_offset >= self.central_directory.len() {
+            return None;
+        }
+
+        let offset = self.central_directory_record_offset;
+
+        let central_directory_record = match CentralDirectoryRecord::read_from_prefix(&self.central_directory[offset..]) {
             Ok((record, _)) => record,
-            Err(_) => return Some(Err(Error::InvalidZipFile("Failed to parse general record".to_string()))),
+            Err(_) => return Some(Err(Error::InvalidZipFile("Failed to parse central directory record".to_string()))),
         };
 
-        self.central_directory_record_offset += std::mem::size_of::<CentralDirectoryRecord>()
-            + central_directory_record.header.file_name_length.get() as usize
-            + central_directory_record.header.extra_field_length.get() as usize
-            + central_directory_record.file_comment_length.get() as usize;
+        let extra_field_offset
+            = offset + std::mem::size_of::<CentralDirectoryRecord>() + central_directory_record.header.file_name_length.get() as usize;
+        let extra_field_end_offset
+            = extra_field_offset + central_directory_record.header.extra_field_length.get() as usize;
 
-        Some(self.parse_entry_at(local_file_header_offset, central_directory_record, general_record))
+        self.central_directory_record_offset
+            = extra_field_end_offset + central_directory_record.file_comment_length.get() as usize;
+
+        let Some(extra_field) = self.central_directory.get(extra_field_offset..extra_field_end_offset) else {
+            return Some(Err(Error::InvalidZipFile("Failed to parse central directory record".to_string())));
+        };
+
+        Some(EntryLocation::new(&central_directory_record, extra_field)
+            .and_then(|location| self.read_entry_at(&location, &central_directory_record)))
     }
 }
+
+fn read_file_range(file: &mut File, offset: usize, size: usize) -> Result<Vec<u8>, Error> {
+    let mut buffer
+        = vec![0; size];
+
+    file.seek(SeekFrom::Start(offset as u64))?;
+    file.read_exact(&mut buffer)?;
+
+    Ok(buffer)
+}
@@ -0,0 +1,108 @@
+use std::borrow::Cow;
+
+use zpm_utils::Path;
+
+use crate::{iter_ext::IterExt, zip::{entries_from_zip, ToZip}, zip_iter::ZipFileIterator, CompressionAlgorithm, Entry};
+
+const DATA_DESCRIPTOR_ZIP: &[u8] = include_bytes!("../fixtures/data-descriptor.zip");
+const ZIP64_ZIP: &[u8] = include_bytes!("../fixtures/zip64.zip");
+
+#[test]
+fn it_reads_entries_with_data_descriptors() {
+    let entries
+        = entries_from_zip(DATA_DESCRIPTOR_ZIP).unwrap();
+
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0].name.as_str(), "package/index.js");
+    assert_eq!(entries[0].data.as_ref(), "module.exports = 42;\n".repeat(4).as_bytes());
+    assert_eq!(entries[0].crc, crc32fast::hash(&entries[0].data));
+    assert_eq!(entries[0].mode, 0o100644);
+
+    assert_eq!(entries[1].name.as_str(), "package/README.md");
+    assert_eq!(entries[1].data.as_ref(), b"# Streamed\n");
+    assert_eq!(entries[1].crc, crc32fast::hash(&entries[1].data));
+}
+
+#[test]
+fn it_reads_zip64_archives() {
+    let entries
+        = entries_from_zip(ZIP64_ZIP).unwrap();
+
+    assert_eq!(entries.len(), 1);
+
+    assert_eq!(entries[0].name.as_str(), "package/index.js");
+    assert_eq!(entries[0].data.as_ref(), b"module.exports = 64;\n");
+    assert_eq!(entries[0].crc, crc32fast::hash(&entries[0].data));
+}
+
+#[test]
+fn it_rejects_zip64_archives_without_locator() {
+    let mut data
+        = ZIP64_ZIP.to_vec();
+
+    // Corrupt the signature of the zip64 end of central directory locator,
+    // which sits right before the regular end of central directory record
+    let locator_offset
+        = data.len() - 22 - 20;
+
+    data[locator_offset] = 0;
+
+    assert!(entries_from_zip(&data).is_err());
+}
+
+#[test]
+fn it_reads_back_brotli_compressed_entries() {
+    let data
+        = "module.exports = 42;\n".repeat(100);
+
+    let entries
+        = vec![Entry::new_file(Path::try_from("package/index.js").unwrap(), Cow::Owned(data.as_bytes().to_vec()))]
+            .into_iter()
+            .compress(Some(CompressionAlgorithm::Brotli(9)))
+            .collect::<Vec<_>>();
+
+    let compressed_size = entries[0].compression.as_ref()
+        .map(|compression| compression.data.len())
+        .unwrap();
+
+    assert!(compressed_size < data.len());
+
+    let zip
+        = entries.to_zip();
+
+    let read_entries
+        = entries_from_zip(&zip).unwrap();
+
+    assert_eq!(read_entries.len(), 1);
+    assert_eq!(read_entries[0].data.as_ref(), data.as_bytes());
+    assert_eq!(read_entries[0].compression.as_ref().map(|compression| compression.algorithm), Some(CompressionAlgorithm::Brotli(0)));
+}
+
+#[test]
+fn it_reads_the_same_entries_from_files() {
+    let archive_path = Path::temp_dir().unwrap()
+        .with_join_str("archive.zip");
+
+    for data in [DATA_DESCRIPTOR_ZIP, ZIP64_ZIP] {
+        archive_path
+            .fs_write(data).unwrap();
+
+        let file_entries
+            = ZipFileIterator::new(&archive_path).unwrap()
+                .collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(file_entries, entries_from_zip(data).unwrap());
+    }
+}
+
+#[test]
+fn it_rejects_truncated_files() {
+    let archive_path = Path::temp_dir().unwrap()
+        .with_join_str("archive.zip");
+
+    archive_path
+        .fs_write(&DATA_DESCRIPTOR_ZIP[..16]).unwrap();
+
+    assert!(ZipFileIterator::new(&archive_path).is_err());
+}
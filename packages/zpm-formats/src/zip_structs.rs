@@ -1,5 +1,13 @@
 use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
-use zerocopy::little_endian::{U16, U32};
+use zerocopy::little_endian::{U16, U32, U64};
+
+pub const STORE_COMPRESSION_METHOD: u16 = 0x00;
+pub const DEFLATE_COMPRESSION_METHOD: u16 = 0x08;
+
+/// Brotli doesn't have an identifier assigned by the zip specification, so
+/// we use one outside of the assigned range; archives using it are only
+/// meant to be read back by zpm.
+pub const BROTLI_COMPRESSION_METHOD: u16 = 0x79;
 
 #[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
 #[repr(C)]
@@ -48,3 +56,34 @@ pub struct EndOfCentralDirectoryRecord {
     pub offset_of_central_directory: U32,
     pub comment_length: U16,
 }
+
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+#[repr(C)]
+pub struct Zip64EndOfCentralDirectoryLocator {
+    pub signature: [u8; 4],
+    pub disk_with_zip64_end_of_central_directory: U32,
+    pub offset_of_zip64_end_of_central_directory: U64,
+    pub number_of_disks: U32,
+}
+
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+#[repr(C)]
+pub struct Zip64EndOfCentralDirectoryRecord {
+    pub signature: [u8; 4],
+    pub size_of_record: U64,
+    pub version_made_by: U16,
+    pub version_needed_to_extract: U16,
+    pub disk_number: U32,
+    pub disk_with_central_directory: U32,
+    pub number_of_files_on_this_disk: U64,
+    pub number_of_files: U64,
+    pub size_of_central_directory: U64,
+    pub offset_of_central_directory: U64,
+}
+
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+#[repr(C)]
+pub struct ExtraFieldHeader {
+    pub header_id: U16,
+    pub data_size: U16,
+}
@@ -1,4 +1,4 @@
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock, RwLock};
 
 use fancy_regex::Regex;
 
@@ -8,6 +8,10 @@ mod reference;
 mod source;
 mod treeish;
 
+#[cfg(test)]
+#[path = "./lib.test.rs"]
+mod lib_tests;
+
 pub use crate::{
     error::Error,
     range::{GitRange, PrepareParams},
@@ -16,10 +20,77 @@ pub use crate::{
     treeish::GitTreeish,
 };
 
-pub(crate) static GH_URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:github:|(?:https):\/\/github\.com\/|git(?:\+ssh)?:\/\/(?:git@)?github\.com\/|git@github\.com:)?(?!\.{1,2}\/)([a-zA-Z0-9._-]+)\/(?!\.{1,2}(?:#|$))([a-zA-Z0-9._-]+?)(?:\.git)?(#.*)?$").unwrap());
-pub(crate) static GH_TARBALL_URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^https?:\/\/github\.com\/(?!\.{1,2}\/)([a-zA-Z0-9._-]+)\/(?!\.{1,2}(?:#|$))([a-zA-Z0-9._-]+?)\/tarball\/(.+)?$").unwrap());
+/// The patterns recognizing the repositories hosted on a GitHub instance.
+/// Shorthands (`github:owner/repo` and `owner/repo`) always refer to
+/// github.com; other hosts only match fully qualified urls, but otherwise
+/// go through the exact same fragment and `.git` handling.
+pub(crate) struct GitHubHost {
+    pub host: String,
+    pub url: Regex,
+    pub tarball_url: Regex,
+    pub ssh_url: Regex,
+}
+
+impl GitHubHost {
+    fn new(host: &str, allow_shorthands: bool) -> Self {
+        let host_pattern
+            = fancy_regex::escape(host);
+
+        let prefix
+            = format!(r"(?:https):\/\/{host_pattern}\/|git(?:\+ssh)?:\/\/(?:git@)?{host_pattern}\/|git@{host_pattern}:");
+
+        let prefix = match allow_shorthands {
+            true => format!("(?:github:|{prefix})?"),
+            false => format!("(?:{prefix})"),
+        };
+
+        Self {
+            host: host.to_string(),
+            url: Regex::new(&format!(r"^{prefix}(?!\.{{1,2}}\/)([a-zA-Z0-9._-]+)\/(?!\.{{1,2}}(?:#|$))([a-zA-Z0-9._-]+?)(?:\.git)?(#.*)?$")).unwrap(),
+            tarball_url: Regex::new(&format!(r"^https?:\/\/{host_pattern}\/(?!\.{{1,2}}\/)([a-zA-Z0-9._-]+)\/(?!\.{{1,2}}(?:#|$))([a-zA-Z0-9._-]+?)\/tarball\/(.+)?$")).unwrap(),
+            ssh_url: Regex::new(&format!(r"^git@{host_pattern}:[^/]+/[^/]+(?:\.git)?(?:#.*)?$")).unwrap(),
+        }
+    }
+
+    fn matches(&self, url: &str) -> bool {
+        [&self.url, &self.tarball_url, &self.ssh_url].iter()
+            .any(|regex| regex.is_match(url).unwrap())
+    }
+
+    fn normalize(&self, url: &str) -> String {
+        let url_replacement
+            = format!("https://{}/$1/$2.git$3", self.host);
+        let tarball_replacement
+            = format!("https://{}/$1/$2.git#$3", self.host);
+
+        let normalized
+            = self.url.replace(url, url_replacement.as_str()).to_string();
+
+        self.tarball_url.replace(&normalized, tarball_replacement.as_str()).to_string()
+    }
+}
+
+pub(crate) static GITHUB_COM: LazyLock<GitHubHost> = LazyLock::new(|| GitHubHost::new("github.com", true));
+
+static GITHUB_ENTERPRISE_HOSTS: LazyLock<RwLock<Arc<Vec<GitHubHost>>>> = LazyLock::new(|| RwLock::new(Arc::new(vec![])));
+
+/// Register the GitHub Enterprise hosts whose urls must be recognized and
+/// normalized like github.com ones. Replaces any previously registered list.
+pub fn set_github_enterprise_hosts<I: IntoIterator<Item = S>, S: AsRef<str>>(hosts: I) {
+    let hosts = hosts.into_iter()
+        .map(|host| host.as_ref().trim().to_ascii_lowercase())
+        .filter(|host| !host.is_empty() && host != "github.com")
+        .map(|host| GitHubHost::new(&host, false))
+        .collect::<Vec<_>>();
 
-pub(crate) static GH_URL_SET: LazyLock<Vec<Regex>> = LazyLock::new(|| vec![
+    *GITHUB_ENTERPRISE_HOSTS.write().unwrap() = Arc::new(hosts);
+}
+
+fn github_enterprise_hosts() -> Arc<Vec<GitHubHost>> {
+    GITHUB_ENTERPRISE_HOSTS.read().unwrap().clone()
+}
+
+pub(crate) static GIT_URL_SET: LazyLock<Vec<Regex>> = LazyLock::new(|| vec![
     Regex::new(r"^ssh:").unwrap(),
     Regex::new(r"^git(?:\+[^:]+)?:").unwrap(),
 
@@ -27,18 +98,17 @@ pub(crate) static GH_URL_SET: LazyLock<Vec<Regex>> = LazyLock::new(|| vec![
     Regex::new(r"^(?:git\+)?https?:[^#]+\/[^#]+(?:\.git)(?:#.*)?$").unwrap(),
 
     Regex::new(r"^git@[^#]+\/[^#]+\.git(?:#.*)?$").unwrap(),
-    // Also match git@github.com:user/repo format (with colon)
-    Regex::new(r"^git@github\.com:[^/]+/[^/]+(?:\.git)?(?:#.*)?$").unwrap(),
-
-    Regex::new(r"^(?:github:|https:\/\/github\.com\/)?(?!\.{1,2}\/)([a-zA-Z._0-9-]+)\/(?!\.{1,2}(?:#|$))([a-zA-Z._0-9-]+?)(?:\.git)?(?:#.*)?$").unwrap(),
-    // GitHub `/tarball/` URLs
-    Regex::new(r"^https?:\/\/github\.com\/(?!\.{1,2}\/)([a-zA-Z0-9._-]+)\/(?!\.{1,2}(?:#|$))([a-zA-Z0-9._-]+?)\/tarball\/(.+)?$").unwrap(),
 ]);
 
 pub(crate) static LEGACY_HASH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"#([^#=:]+):(.*)$").unwrap());
 
 pub fn is_git_url<P: AsRef<str>>(url: P) -> bool {
-    GH_URL_SET.iter().any(|r| r.is_match(url.as_ref()).unwrap())
+    let url
+        = url.as_ref();
+
+    GIT_URL_SET.iter().any(|r| r.is_match(url).unwrap())
+        || GITHUB_COM.matches(url)
+        || github_enterprise_hosts().iter().any(|host| host.matches(url))
 }
 
 pub fn normalize_git_url<P: AsRef<str>>(url: P) -> String {
@@ -48,8 +118,12 @@ pub fn normalize_git_url<P: AsRef<str>>(url: P) -> String {
         normalized = normalized[4..].to_string();
     }
 
-    normalized = GH_URL.replace(&normalized, "https://github.com/$1/$2.git$3").to_string();
-    normalized = GH_TARBALL_URL.replace(&normalized, "https://github.com/$1/$2.git#$3").to_string();
+    normalized = GITHUB_COM.normalize(&normalized);
+
+    for host in github_enterprise_hosts().iter() {
+        normalized = host.normalize(&normalized);
+    }
+
     normalized = LEGACY_HASH.replace(&normalized, "#$1=$2").to_string();
 
     normalized
@@ -0,0 +1,37 @@
+use zpm_utils::{FromFileString, UnwrapInfallible};
+
+use super::*;
+
+#[test]
+fn it_normalizes_github_urls() {
+    assert_eq!(normalize_git_url("github:owner/repo"), "https://github.com/owner/repo.git");
+    assert_eq!(normalize_git_url("owner/repo#main"), "https://github.com/owner/repo.git#main");
+    assert_eq!(normalize_git_url("git@github.com:owner/repo.git#v1.0.0"), "https://github.com/owner/repo.git#v1.0.0");
+    assert_eq!(normalize_git_url("https://github.com/owner/repo/tarball/main"), "https://github.com/owner/repo.git#main");
+}
+
+#[test]
+fn it_supports_github_enterprise_hosts() {
+    set_github_enterprise_hosts(["github.mycorp.com"]);
+
+    assert!(is_git_url("git@github.mycorp.com:owner/repo"));
+    assert!(is_git_url("https://github.mycorp.com/owner/repo#main"));
+    assert!(is_git_url("https://github.mycorp.com/owner/repo/tarball/main"));
+
+    assert_eq!(normalize_git_url("https://github.mycorp.com/owner/repo"), "https://github.mycorp.com/owner/repo.git");
+    assert_eq!(normalize_git_url("git@github.mycorp.com:owner/repo.git#main"), "https://github.mycorp.com/owner/repo.git#main");
+    assert_eq!(normalize_git_url("git+ssh://git@github.mycorp.com/owner/repo#commit=abcdef"), "https://github.mycorp.com/owner/repo.git#commit=abcdef");
+    assert_eq!(normalize_git_url("https://github.mycorp.com/owner/repo/tarball/v1.0.0"), "https://github.mycorp.com/owner/repo.git#v1.0.0");
+
+    // Shorthands keep pointing to github.com
+    assert_eq!(normalize_git_url("owner/repo"), "https://github.com/owner/repo.git");
+
+    // Similar looking hosts aren't matched
+    assert!(!is_git_url("https://github.mycorp.com.evil.com/owner/repo"));
+    assert!(!is_git_url("https://githubxmycorp.com/owner/repo"));
+
+    assert_eq!(
+        GitSource::from_file_string("https://github.mycorp.com/owner/repo.git").unwrap_infallible(),
+        GitSource::Url("https://github.mycorp.com/owner/repo.git".to_string()),
+    );
+}
@@ -4,7 +4,7 @@ use rkyv::Archive;
 use serde::{Deserialize, Serialize};
 use zpm_utils::{DataType, FromFileString, ToFileString, ToHumanString};
 
-use crate::{normalize_git_url, GH_TARBALL_URL, GH_URL};
+use crate::{normalize_git_url, GITHUB_COM};
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[rkyv(derive(PartialEq, Eq, Hash, PartialOrd, Ord))]
@@ -39,7 +39,7 @@ impl FromFileString for GitSource {
             = normalize_git_url(value);
 
         // Check if it's a GitHub URL
-        if let Ok(Some(captures)) = GH_URL.captures(&normalized) {
+        if let Ok(Some(captures)) = GITHUB_COM.url.captures(&normalized) {
             if let (Some(owner), Some(repo)) = (captures.get(1), captures.get(2)) {
                 return Ok(GitSource::GitHub {
                     owner: owner.as_str().to_string(),
@@ -50,7 +50,7 @@ impl FromFileString for GitSource {
 
         // Check GitHub tarball URLs (on the original URL, not normalized)
         // TODO: Do we need this? Wouldn't tarball URLs be handled by the previous block anyway?
-        if let Ok(Some(captures)) = GH_TARBALL_URL.captures(value) {
+        if let Ok(Some(captures)) = GITHUB_COM.tarball_url.captures(value) {
             if let (Some(owner), Some(repo)) = (captures.get(1), captures.get(2)) {
                 return Ok(GitSource::GitHub {
                     owner: owner.as_str().to_string(),
@@ -185,8 +185,14 @@ pub fn descriptor_map_deserializer<'de, D>(deserializer: D) -> Result<BTreeMap<I
                 = BTreeMap::new();
 
             while let Some((key, value)) = access.next_entry::<Cow<'de, str>, Cow<'de, str>>()? {
+                // Like npm, treat empty dependency ranges as `*`
+                let range = match value.trim().is_empty() {
+                    true => "*",
+                    false => &value,
+                };
+
                 let descriptor
-                    = extract_descriptor(&key, &value)
+                    = extract_descriptor(&key, range)
                         .map_err(|e| serde::de::Error::custom(e.to_string()))?;
 
                 map.insert(descriptor.ident.clone(), descriptor);
@@ -258,6 +264,7 @@ impl_file_string_serialization!(Descriptor);
 #[rstest]
 #[case("foo@npm:1.0.0")]
 #[case("foo@npm:1.0.0::parent=root@workspace:")]
+#[case("foo@exec:./gen.js::parent=root@workspace:")]
 fn test_descriptor_serialization(#[case] str: &str) {
     assert_eq!(str, Descriptor::from_file_string(str).unwrap().to_file_string());
 }
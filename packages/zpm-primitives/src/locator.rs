@@ -157,6 +157,20 @@ impl_file_string_serialization!(Locator);
 #[rstest]
 #[case("foo@npm:1.0.0")]
 #[case("foo@npm:1.0.0::parent=root@workspace:")]
+#[case("foo@exec:./gen.js#0123abcd::parent=root@workspace:")]
+#[case("foo@file:./foo.tgz::parent=root@workspace:")]
+#[case("foo@file:./vendor/foo#0123abcd::parent=root@workspace:")]
+#[case("foo@file:./vendor/foo#0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef::parent=root@workspace:")]
 fn test_locator_serialization(#[case] str: &str) {
     assert_eq!(str, Locator::from_file_string(str).unwrap().to_file_string());
 }
+
+#[rstest]
+#[case("foo@file:./vendor/foo#0123abcd", false)]
+#[case("foo@file:./vendor/foo#0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef", true)]
+fn test_file_reference_kind(#[case] str: &str, #[case] is_tarball: bool) {
+    let locator
+        = Locator::from_file_string(str).unwrap();
+
+    assert_eq!(matches!(locator.reference, Reference::Tarball(_)), is_tarball);
+}
@@ -13,6 +13,13 @@ fn format_patch(inner: &UrlEncoded<Locator>, path: &str, checksum: &Option<Hash6
     }
 }
 
+fn format_tarball(path: &str, hash: &Option<Hash64>) -> String {
+    match hash {
+        Some(hash) => format!("file:{}#{}", path, hash.to_file_string()),
+        None => format!("file:{}", path),
+    }
+}
+
 fn format_registry(ident: &Ident, version: &zpm_semver::Version, url: Option<&String>) -> String {
     match url {
         Some(url) => format!("npm:{}@{}#{}", ident.to_file_string(), version.to_file_string(), url.to_file_string()),
@@ -63,11 +70,13 @@ pub enum Reference {
         url: Option<UrlEncoded<String>>,
     },
 
+    #[pattern(r"file:(?<path>.*)#(?<hash>[a-f0-9]{128})$")]
     #[pattern(r"file:(?<path>.*\.(?:tgz|tar\.gz))")]
-    #[to_file_string(|params| format!("file:{}", params.path))]
-    #[to_print_string(|params| DataType::Reference.colorize(&format!("file:{}", params.path)))]
+    #[to_file_string(|params| format_tarball(&params.path, &params.hash))]
+    #[to_print_string(|params| DataType::Reference.colorize(&format_tarball(&params.path, &params.hash)))]
     Tarball {
         path: String,
+        hash: Option<Hash64>,
     },
 
     #[pattern(r"file:(?<path>.*)")]
@@ -91,6 +100,14 @@ pub enum Reference {
         path: String,
     },
 
+    #[pattern(r"exec:(?<path>.*)#(?<hash>[a-f0-9]*)$")]
+    #[to_file_string(|params| format!("exec:{}#{}", params.path, params.hash.to_file_string()))]
+    #[to_print_string(|params| DataType::Reference.colorize(&format!("exec:{}#{}", params.path, params.hash.to_file_string())))]
+    Exec {
+        path: String,
+        hash: Hash64,
+    },
+
     #[pattern(r"patch:(?<inner>.*)#(?<path>.*)(?:&checksum=(?<checksum>[a-f0-9]*))?$")]
     #[to_file_string(|params| format_patch(&params.inner, &params.path, &params.checksum))]
     #[to_print_string(|params| DataType::Reference.colorize(&format_patch(&params.inner, &params.path, &params.checksum)))]
@@ -154,7 +171,7 @@ impl Reference {
             return params.inner.0.reference.must_bind() || (params.path.as_str() != "<builtin>" && !params.path.as_str().starts_with("~/"));
         }
 
-        matches!(&self, Reference::Link(_) | Reference::Portal(_) | Reference::Tarball(_) | Reference::Folder(_))
+        matches!(&self, Reference::Link(_) | Reference::Portal(_) | Reference::Tarball(_) | Reference::Folder(_) | Reference::Exec(_))
     }
 
     pub fn is_workspace_reference(&self) -> bool {
@@ -201,6 +218,10 @@ impl Reference {
                 format!("npm-{}", params.version.to_file_string())
             },
 
+            Reference::Exec(_) => {
+                "exec".to_string()
+            },
+
             Reference::Git(_) => {
                 "git".to_string()
             },
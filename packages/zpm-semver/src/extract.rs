@@ -458,5 +458,38 @@ pub fn infix_to_prefix(input: &[Token]) -> Option<Vec<Token>> {
 
     prefix.reverse();
 
+    if !is_well_formed_prefix(&prefix) {
+        return None;
+    }
+
     Some(prefix)
 }
+
+/// Checks that each operator of a prefix expression has its two operands and
+/// that no parenthesis is left over, so that evaluating the range can't run
+/// out of tokens (as would happen with `1.2.3 ||`).
+fn is_well_formed_prefix(prefix: &[Token]) -> bool {
+    let mut operands = 0;
+
+    for token in prefix.iter().rev() {
+        match token {
+            Token::Operation(_, _) => {
+                operands += 1;
+            }
+
+            Token::Syntax(TokenType::SAnd | TokenType::And | TokenType::Or) => {
+                if operands < 2 {
+                    return false;
+                }
+
+                operands -= 1;
+            }
+
+            Token::Syntax(TokenType::LParen | TokenType::RParen) => {
+                return false;
+            }
+        }
+    }
+
+    operands == 1
+}
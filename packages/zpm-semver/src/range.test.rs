@@ -114,6 +114,19 @@ fn test_range_min(#[case] range: Range, #[case] expected: Option<Version>) {
     assert_eq!(range.range_min(), expected);
 }
 
+#[rstest]
+#[case("||")]
+#[case("1.2.3 ||")]
+#[case("|| 1.2.3")]
+#[case("1.2.3 && && 2.3.4")]
+#[case("()")]
+#[case("(1.2.3")]
+#[case("1.2.3)")]
+#[case("")]
+fn test_range_invalid(#[case] range: &str) {
+    assert!(Range::from_file_string(range).is_err());
+}
+
 #[rstest]
 #[case(Range::caret(Version { major: 1, minor: 2, patch: 3, rc: None }), "^1.2.3")]
 #[case(Range::tilde(Version { major: 1, minor: 2, patch: 3, rc: None }), "~1.2.3")]
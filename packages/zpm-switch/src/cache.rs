@@ -3,13 +3,13 @@ use std::{future::Future, io::Write};
 use serde::{Deserialize, Serialize};
 use zpm_parsers::JsonDocument;
 use zpm_semver::{Version, VersionRc};
-use zpm_utils::{DataType, Hash64, Path, ToFileString, ToHumanString, Unit, is_terminal};
+use zpm_utils::{DataType, Hash64, IoResultExt, Path, ToFileString, ToHumanString, Unit, get_system_string, is_terminal};
 
-use crate::errors::Error;
+use crate::{errors::Error, integrity};
 
 pub const CACHE_VERSION: usize = 1;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CacheKey {
     pub cache_version: usize,
@@ -18,6 +18,14 @@ pub struct CacheKey {
 }
 
 impl CacheKey {
+    pub fn new(version: Version) -> CacheKey {
+        CacheKey {
+            cache_version: CACHE_VERSION,
+            version,
+            platform: get_system_string().to_string(),
+        }
+    }
+
     pub fn to_npm_url(&self) -> Option<String> {
         if self.version.rc.as_ref().map_or(true, |rc| !rc.starts_with(&[VersionRc::String("git".to_string())])) {
             // Older RC versions (<6.0.0-rc.9) are not available in npm
@@ -37,6 +45,25 @@ impl CacheKey {
     }
 }
 
+/// The content of the `meta.json` file of each cache entry. Only the key is
+/// used to compute the location of the entry, so the checksums can be added
+/// without invalidating the existing entries.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheMetadata {
+    #[serde(flatten)]
+    pub key: CacheKey,
+
+    /// The sha512 of the downloaded artifact, verified against the checksum
+    /// published for it on the npm registry or on repo.yarnpkg.com.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_checksum: Option<String>,
+
+    /// The checksum of the files extracted from the artifact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_checksum: Option<String>,
+}
+
 pub fn cache_dir() -> Result<Path, Error> {
     let cache_dir = Path::home_dir()?
         .ok_or(Error::MissingHomeFolder)?
@@ -66,6 +93,65 @@ pub fn cache_last_used(p: &Path) -> Result<std::time::SystemTime, Error> {
     Ok(metadata.modified()?)
 }
 
+/// A release stored in the cache.
+pub struct CacheEntry {
+    pub path: Path,
+    pub key: CacheKey,
+    pub last_used: std::time::SystemTime,
+}
+
+/// Lists the releases stored in the cache, skipping the entries that aren't
+/// ready to be used yet.
+pub fn cache_entries() -> Result<Vec<CacheEntry>, Error> {
+    let Some(dir_entries) = cache_dir()?.fs_read_dir().ok_missing()? else {
+        return Ok(vec![]);
+    };
+
+    let mut entries
+        = vec![];
+
+    for entry in dir_entries {
+        let entry
+            = entry?;
+
+        let entry_path
+            = Path::try_from(entry.path())?;
+
+        let Ok(key) = cache_metadata(&entry_path) else {
+            continue;
+        };
+
+        let Ok(last_used) = cache_last_used(&entry_path) else {
+            continue;
+        };
+
+        entries.push(CacheEntry {
+            path: entry_path,
+            key,
+            last_used,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Returns the highest version among the cached releases that can run on
+/// this system and match the given predicate.
+pub fn find_cached_version<F: Fn(&Version) -> bool>(predicate: F) -> Result<Option<Version>, Error> {
+    let platform
+        = get_system_string();
+
+    let version = cache_entries()?
+        .into_iter()
+        .map(|entry| entry.key)
+        .filter(|key| key.cache_version == CACHE_VERSION && key.platform == platform)
+        .map(|key| key.version)
+        .filter(|version| predicate(version))
+        .max();
+
+    Ok(version)
+}
+
 async fn pretty_download<F: Future<Output = Result<(), Error>>>(key_data: &CacheKey, f: F) -> Result<(), Error> {
     if is_terminal() {
         print!(
@@ -128,11 +214,44 @@ fn access(key_data: &CacheKey) -> Result<(Path, bool), Error> {
     Ok((cache_path, ready_path.fs_exists()))
 }
 
+/// Returns whether the cache contains the given release; errors if it does,
+/// but its files don't match the checksum recorded when they were installed.
 pub fn check(key_data: &CacheKey) -> Result<bool, Error> {
-    Ok(access(key_data)?.1)
+    let (cache_path, is_ready)
+        = access(key_data)?;
+
+    if !is_ready {
+        return Ok(false);
+    }
+
+    let metadata: CacheMetadata
+        = JsonDocument::hydrate_from_str(&cache_path.with_join_str("meta.json").fs_read_text()?)?;
+
+    if let Some(content_checksum) = metadata.content_checksum {
+        if integrity::content_checksum(&cache_path)? != content_checksum {
+            return Err(Error::CacheIntegrityMismatch(key_data.version.clone()));
+        }
+    }
+
+    Ok(true)
+}
+
+/// Returns the sha512 of the artifact the given release got installed from,
+/// or `None` if it wasn't verified when downloading it.
+pub fn artifact_checksum(key_data: &CacheKey) -> Result<Option<String>, Error> {
+    let (cache_path, _)
+        = access(key_data)?;
+
+    let metadata: CacheMetadata
+        = JsonDocument::hydrate_from_str(&cache_path.with_join_str("meta.json").fs_read_text()?)?;
+
+    Ok(metadata.artifact_checksum)
 }
 
-pub async fn ensure<R: Future<Output = Result<(), Error>>, F: FnOnce(Path) -> R>(key_data: &CacheKey, f: F) -> Result<Path, Error> {
+/// Ensures that the given release is in the cache, calling `f` to download
+/// and extract it if it isn't. The callback returns the verified checksum of
+/// the artifact it downloaded, if any.
+pub async fn ensure<R: Future<Output = Result<Option<String>, Error>>, F: FnOnce(Path) -> R>(key_data: &CacheKey, f: F) -> Result<Path, Error> {
     match access(key_data)? {
         (cache_path, true) => {
             let ready_path = cache_path
@@ -151,10 +270,24 @@ pub async fn ensure<R: Future<Output = Result<(), Error>>, F: FnOnce(Path) -> R>
                 let temp_dir
                     = Path::temp_dir()?;
 
-                f(temp_dir.clone()).await?;
+                let artifact_checksum = match f(temp_dir.clone()).await {
+                    Ok(artifact_checksum) => artifact_checksum,
+
+                    Err(err) => {
+                        // Don't leave a partial (or tampered) download behind
+                        let _ = temp_dir.fs_rm();
+                        return Err(err);
+                    },
+                };
+
+                let metadata = CacheMetadata {
+                    key: key_data.clone(),
+                    artifact_checksum,
+                    content_checksum: Some(integrity::content_checksum(&temp_dir)?),
+                };
 
                 let meta_content
-                    = JsonDocument::to_string(&key_data)?;
+                    = JsonDocument::to_string(&metadata)?;
 
                 temp_dir
                     .with_join_str("meta.json")
@@ -1,9 +1,9 @@
-use std::process::ExitStatus;
+use std::process::ExitCode;
 
 use clipanion::cli;
 use zpm_utils::ToFileString;
 
-use crate::{cwd::get_final_cwd, errors::Error, manifest::find_closest_package_manager, yarn::get_default_yarn_version};
+use crate::{cwd::get_final_cwd, errors::Error, manifest::{find_closest_package_manager, VersionPackageManagerReference}, yarn::{get_default_yarn_version, resolve_dev_engines_range}};
 
 use super::switch::explicit::ExplicitCommand;
 
@@ -16,7 +16,7 @@ pub struct InitCommand {
 }
 
 impl InitCommand {
-    pub async fn execute(&self) -> Result<ExitStatus, Error> {
+    pub async fn execute(&self) -> Result<ExitCode, Error> {
         let lookup_path
             = get_final_cwd()?;
 
@@ -27,9 +27,10 @@ impl InitCommand {
             std::env::set_var("YARNSW_DETECTED_ROOT", detected_root_path.to_file_string());
         }
 
-        let reference = match find_result.detected_package_manager {
-            Some(package_manager) => package_manager.into_reference("yarn"),
-            None => get_default_yarn_version(None).await,
+        let reference = match (find_result.detected_package_manager, find_result.detected_package_manager_range) {
+            (Some(package_manager), _) => package_manager.into_reference("yarn"),
+            (None, Some(range)) => resolve_dev_engines_range(&range).await.map(|version| VersionPackageManagerReference {version, hash: None}.into()),
+            (None, None) => get_default_yarn_version(None).await,
         }?;
 
         let mut args = vec!["init".to_string()];
@@ -24,6 +24,7 @@ enum SwitchExecCli {
     PostinstallCommand(switch::postinstall::PostinstallCommand),
     UnlinkCommand(switch::unlink::UnlinkCommand),
     UpdateCommand(switch::update::UpdateCommand),
+    UseCommand(switch::use_version::UseCommand),
     VersionCommand(switch::version::VersionCommand),
     WhichCommand(switch::which::WhichCommand),
     ProxyCommand(proxy::ProxyCommand),
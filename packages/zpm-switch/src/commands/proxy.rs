@@ -1,9 +1,9 @@
-use std::{io::IsTerminal, process::ExitStatus};
+use std::{io::IsTerminal, process::ExitCode};
 
 use clipanion::cli;
 use zpm_utils::{DataType, Note, ToFileString};
 
-use crate::{cwd::{get_fake_cwd, get_final_cwd}, errors::Error, links::{LinkTarget, get_link, unset_link}, manifest::{LocalPackageManagerReference, PackageManagerField, find_closest_package_manager}, yarn::get_default_yarn_version, yarn_enums::ReleaseLine};
+use crate::{cwd::{get_fake_cwd, get_final_cwd}, errors::Error, links::{LinkTarget, get_link, unset_link}, manifest::{LocalPackageManagerReference, PackageManagerField, VersionPackageManagerReference, find_closest_package_manager}, yarn::{get_default_yarn_version, resolve_dev_engines_range}, yarn_enums::ReleaseLine};
 
 use super::switch::explicit::ExplicitCommand;
 
@@ -16,7 +16,7 @@ pub struct ProxyCommand {
 }
 
 impl ProxyCommand {
-    pub async fn execute(&self) -> Result<ExitStatus, Error> {
+    pub async fn execute(&self) -> Result<ExitCode, Error> {
         let lookup_path
             = get_final_cwd()?;
 
@@ -57,9 +57,10 @@ impl ProxyCommand {
             }
         }
 
-        let reference = match find_result.detected_package_manager {
-            Some(package_manager) => package_manager.into_reference("yarn"),
-            None => get_default_yarn_version(Some(ReleaseLine::Classic)).await,
+        let reference = match (find_result.detected_package_manager, find_result.detected_package_manager_range) {
+            (Some(package_manager), _) => package_manager.into_reference("yarn"),
+            (None, Some(range)) => resolve_dev_engines_range(&range).await.map(|version| VersionPackageManagerReference {version, hash: None}.into()),
+            (None, None) => get_default_yarn_version(Some(ReleaseLine::Classic)).await,
         }?;
 
         let mut args
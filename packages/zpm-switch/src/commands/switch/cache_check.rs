@@ -4,6 +4,8 @@ use zpm_utils::{get_system_string};
 use crate::{cache, errors::Error};
 
 /// Check if the specified versions are available in the cache
+///
+/// The files of the cached releases are also checked against the checksum recorded when they got installed, so that releases modified since then are reported.
 #[cli::command]
 #[cli::path("switch", "cache")]
 #[cli::category("Cache management")]
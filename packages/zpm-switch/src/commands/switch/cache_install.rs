@@ -1,8 +1,10 @@
 use clipanion::cli;
 
-use crate::{cwd::get_final_cwd, errors::Error, install::install_package_manager, manifest::{find_closest_package_manager, PackageManagerReference, VersionPackageManagerReference}};
+use crate::{cwd::get_final_cwd, errors::Error, install::install_package_manager, integrity::is_verification_skipped, manifest::{find_closest_package_manager, PackageManagerReference, VersionPackageManagerReference}};
 
 /// Install the current project's Yarn version, or specific Yarn releases
+///
+/// The downloaded releases are checked against the checksums published along with them, either on the npm registry or on repo.yarnpkg.com, and the installation fails if none can be found. The `--insecure-skip-verify` flag (or the `YARNSW_INSECURE_SKIP_VERIFY=1` environment variable) disables this verification; it's only meant for air-gapped mirrors that don't serve the registry metadata.
 #[cli::command]
 #[cli::path("switch", "cache")]
 #[cli::category("Cache management")]
@@ -11,11 +13,18 @@ pub struct CacheInstallCommand {
     #[cli::option("-i,--install")]
     _install: bool,
 
+    /// Install the releases without verifying their integrity
+    #[cli::option("--insecure-skip-verify", default = false)]
+    insecure_skip_verify: bool,
+
     versions: Vec<zpm_semver::Version>,
 }
 
 impl CacheInstallCommand {
     pub async fn execute(&self) -> Result<(), Error> {
+        let skip_verify
+            = self.insecure_skip_verify || is_verification_skipped();
+
         if self.versions.is_empty() {
             let lookup_path
                 = get_final_cwd()?;
@@ -31,14 +40,14 @@ impl CacheInstallCommand {
                 = package_manager_field.into_reference("yarn")?;
 
             if let PackageManagerReference::Version(params) = reference {
-                install_package_manager(&params).await?;
+                install_package_manager(&params, skip_verify).await?;
             }
         } else {
             for version in &self.versions {
                 let params
-                    = VersionPackageManagerReference {version: version.clone()};
+                    = VersionPackageManagerReference {version: version.clone(), hash: None};
 
-                install_package_manager(&params.into()).await?;
+                install_package_manager(&params.into(), skip_verify).await?;
             }
         }
 
@@ -1,9 +1,9 @@
-use std::{process::{Command, ExitStatus, Stdio}, sync::Arc};
+use std::{process::{Command, ExitCode, Stdio}, sync::Arc};
 
 use clipanion::cli;
-use zpm_utils::ToFileString;
+use zpm_utils::{exit_code_from_status, wait_forwarding_signals, ToFileString};
 
-use crate::{cwd::{get_fake_cwd, get_final_cwd}, errors::Error, install::install_package_manager, manifest::{find_closest_package_manager, PackageManagerReference, VersionPackageManagerReference}, yarn::resolve_selector, yarn_enums::Selector};
+use crate::{cwd::{get_fake_cwd, get_final_cwd}, errors::Error, install::install_package_manager, integrity::is_verification_skipped, manifest::{find_closest_package_manager, PackageManagerReference, VersionPackageManagerReference}, yarn::resolve_selector, yarn_enums::Selector};
 
 /// Call a custom Yarn binary for the current project
 #[cli::command(proxy)]
@@ -16,10 +16,10 @@ pub struct ExplicitCommand {
 }
 
 impl ExplicitCommand {
-    pub async fn run(reference: &PackageManagerReference, args: &[String]) -> Result<ExitStatus, Error> {
+    pub async fn run(reference: &PackageManagerReference, args: &[String]) -> Result<ExitCode, Error> {
         let mut binary = match reference {
             PackageManagerReference::Version(params)
-                => install_package_manager(params).await?,
+                => install_package_manager(params, is_verification_skipped()).await?,
 
             PackageManagerReference::Local(params)
                 => Command::new(params.path.to_file_string()),
@@ -28,14 +28,22 @@ impl ExplicitCommand {
         binary.stdout(Stdio::inherit());
         binary.args(args);
 
-        let exit_code
-            = binary.status()
-                .map_err(|err| Error::FailedToExecuteBinary(binary.get_program().to_string_lossy().to_string(), Arc::new(err)))?;
+        let program
+            = binary.get_program().to_string_lossy().to_string();
 
-        Ok(exit_code)
+        let mut child
+            = tokio::process::Command::from(binary)
+                .spawn()
+                .map_err(|err| Error::FailedToExecuteBinary(program.clone(), Arc::new(err)))?;
+
+        let status
+            = wait_forwarding_signals(&mut child).await
+                .map_err(|err| Error::FailedToExecuteBinary(program, Arc::new(err)))?;
+
+        Ok(ExitCode::from(exit_code_from_status(&status)))
     }
 
-    pub async fn execute(&self) -> Result<ExitStatus, Error> {
+    pub async fn execute(&self) -> Result<ExitCode, Error> {
         let lookup_path
             = get_final_cwd()?;
 
@@ -58,7 +66,7 @@ impl ExplicitCommand {
             = resolve_selector(&self.selector).await?;
 
         let reference
-            = VersionPackageManagerReference {version};
+            = VersionPackageManagerReference {version, hash: None};
 
         ExplicitCommand::run(&reference.into(), &args).await
     }
@@ -11,5 +11,6 @@ pub mod link;
 pub mod postinstall;
 pub mod unlink;
 pub mod update;
+pub mod use_version;
 pub mod version;
 pub mod which;
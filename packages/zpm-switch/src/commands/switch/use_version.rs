@@ -0,0 +1,106 @@
+use clipanion::cli;
+use dialoguer::Confirm;
+use zpm_parsers::{Document, JsonDocument, Value};
+use zpm_utils::{is_terminal, DataType, ToFileString, ToHumanString};
+
+use crate::{cache, cwd::get_final_cwd, errors::Error, install::install_package_manager, integrity::is_verification_skipped, manifest::{find_closest_manifest_path, PackageManagerField, PackageManagerHash, VersionPackageManagerReference}, yarn::resolve_selector, yarn_enums::Selector};
+
+/// Pin the Yarn version used by the current project
+///
+/// This command resolves the given selector (a version, a semver range, or a release channel), downloads the matching release into the cache,
+/// and stores it in the `packageManager` field of the closest project manifest. The `--dev-engines` flag also records it in the
+/// `devEngines.packageManager` field, and the `--with-hash` flag appends the sha512 of the downloaded release to the pinned version (as
+/// Corepack does), so that later downloads are checked against it.
+///
+/// If the project doesn't have a manifest yet, the command will offer to create one.
+#[cli::command]
+#[cli::path("switch", "use")]
+#[cli::category("Switch commands")]
+#[derive(Debug)]
+pub struct UseCommand {
+    /// Also set the `devEngines.packageManager` field
+    #[cli::option("--dev-engines", default = false)]
+    dev_engines: bool,
+
+    /// Append the hash of the release to the `packageManager` field
+    #[cli::option("--with-hash", default = false)]
+    with_hash: bool,
+
+    selector: Selector,
+}
+
+impl UseCommand {
+    pub async fn execute(&self) -> Result<(), Error> {
+        let lookup_path
+            = get_final_cwd()?;
+
+        let manifest_path
+            = find_closest_manifest_path(&lookup_path)?;
+
+        if !manifest_path.fs_exists() {
+            let should_create
+                = is_terminal() && Confirm::new()
+                    .with_prompt(format!("No manifest found; create one at {}?", manifest_path.to_print_string()))
+                    .default(true)
+                    .interact()
+                    .map_err(std::io::Error::from)?;
+
+            if !should_create {
+                return Err(Error::ManifestNotFound(manifest_path));
+            }
+
+            manifest_path
+                .fs_write_text("{}\n")?;
+        }
+
+        let version
+            = resolve_selector(&self.selector).await?;
+
+        let mut reference
+            = VersionPackageManagerReference {version: version.clone(), hash: None};
+
+        install_package_manager(&reference, is_verification_skipped()).await?;
+
+        if self.with_hash {
+            let artifact_checksum
+                = cache::artifact_checksum(&cache::CacheKey::new(version.clone()))?
+                    .ok_or_else(|| Error::MissingArtifactChecksum(version.clone()))?;
+
+            reference.hash
+                = Some(PackageManagerHash::new_sha512(artifact_checksum));
+        }
+
+        let package_manager
+            = PackageManagerField::new_yarn(reference.into());
+
+        let mut document
+            = JsonDocument::new(manifest_path.fs_read_prealloc()?)?;
+
+        document.set_path(
+            &zpm_parsers::Path::from_segments(vec!["packageManager".to_string()]),
+            Value::String(package_manager.to_file_string()),
+        )?;
+
+        if self.dev_engines {
+            document.set_path(
+                &zpm_parsers::Path::from_segments(vec!["devEngines".to_string(), "packageManager".to_string()]),
+                Value::Object(vec![
+                    ("name".to_string(), Value::String("yarn".to_string())),
+                    ("version".to_string(), Value::String(version.to_file_string())),
+                ]),
+            )?;
+        }
+
+        manifest_path
+            .fs_change_atomic(&document.input, false)?;
+
+        let previous_pin = find_result.detected_package_manager
+            .map_or_else(|| DataType::Null.colorize("none"), |field| field.to_print_string());
+
+        println!("Previous pin: {}", previous_pin);
+        println!("New pin: {}", package_manager.to_print_string());
+        println!("Saved into {}", manifest_path.to_print_string());
+
+        Ok(())
+    }
+}
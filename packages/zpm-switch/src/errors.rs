@@ -35,14 +35,29 @@ pub enum Error {
     #[error("Cache not found: {version}", version = .0.to_print_string())]
     CacheNotFound(zpm_semver::Version),
 
+    #[error("The cached files of Yarn {} don't match the checksum recorded when they were installed; run {} to download them again", .0.to_print_string(), DataType::Code.colorize("yarn switch cache --clear"))]
+    CacheIntegrityMismatch(zpm_semver::Version),
+
+    #[error("The checksum of {} doesn't match the one published for Yarn {}; the download may have been tampered with", DataType::Code.colorize(&.1), .0.to_print_string())]
+    ReleaseIntegrityMismatch(zpm_semver::Version, String),
+
+    #[error("The {} checksum of Yarn {} doesn't match the one pinned in the {} field; the download may have been tampered with", DataType::Code.colorize(&.1), .0.to_print_string(), DataType::Code.colorize("packageManager"))]
+    PinnedIntegrityMismatch(zpm_semver::Version, String),
+
+    #[error("No verified checksum is available for Yarn {}; run {} to download it again", .0.to_print_string(), DataType::Code.colorize("yarn switch cache --clear"))]
+    MissingArtifactChecksum(zpm_semver::Version),
+
+    #[error("No checksum has been published for {} (Yarn {}); set {} to install it without verifying its integrity", DataType::Code.colorize(&.1), .0.to_print_string(), DataType::Code.colorize("YARNSW_INSECURE_SKIP_VERIFY=1"))]
+    MissingReleaseChecksum(zpm_semver::Version, String),
+
     #[error("Failed to get current executable path")]
     FailedToGetExecutablePath,
 
     #[error("Invalid packageManager string")]
     InvalidPackageManagerString,
 
-    #[error("Invalid version selector: {0}")]
-    InvalidVersionSelector(String),
+    #[error("Unsupported version selector ({}); expected a version (4.1.0), a semver range (^4.1.0), or a release channel ([classic|berry|zpm|default][-stable|-canary])", DataType::Code.colorize(&.0))]
+    UnsupportedVersionSelector(String),
 
     #[error("Failed to parse manifest: {0}")]
     FailedToParseManifest(zpm_parsers::Error),
@@ -50,9 +65,15 @@ pub enum Error {
     #[error("Server answered with HTTP {0} ({1})")]
     HttpStatus(StatusCode, String),
 
+    #[error("Failed to reach {} ({}); check your network connection", DataType::Url.colorize(&.0), .1.to_string())]
+    NetworkError(String, Arc<reqwest::Error>),
+
     #[error("Project not found")]
     ProjectNotFound,
 
+    #[error("No manifest found at {}", .0.to_print_string())]
+    ManifestNotFound(Path),
+
     #[error("Failed to retrieve the latest tag from the Yarn registry")]
     FailedToRetrieveLatestYarnTag,
 
@@ -77,10 +98,32 @@ pub enum Error {
     #[error("You opted-in to a package manager migration, but the manifest in {} doesn't list a {} field", .0.to_print_string(), DataType::Code.colorize("packageManagerMigration"))]
     MissingMigration(Path),
 
+    #[error("The {} field ({}) doesn't match the {} field ({}) in {}", DataType::Code.colorize("packageManager"), .1, DataType::Code.colorize("devEngines.packageManager"), .2, .0.to_print_string())]
+    DevEnginesConflict(Path, String, String),
+
+    #[error("This project requires {0}, as per its {field} field", field = DataType::Code.colorize("devEngines.packageManager"))]
+    DevEnginesUnsupportedPackageManager(String),
+
+    #[error("Invalid version range in the {field} field ({})", DataType::Code.colorize(&.0), field = DataType::Code.colorize("devEngines.packageManager"))]
+    InvalidDevEnginesVersion(String),
+
     #[error("Yarn cannot be used on project configured for use with {0}")]
     UnsupportedProject(&'static str),
 }
 
+impl Error {
+    /// Whether the error is caused by the registry being unreachable (or
+    /// unable to answer), in which case using a cached release is preferable
+    /// to failing.
+    pub fn is_network_error(&self) -> bool {
+        match self {
+            Error::NetworkError(..) => true,
+            Error::HttpStatus(status, _) => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
 impl From<std::str::Utf8Error> for Error {
     fn from(value: std::str::Utf8Error) -> Self {
         Error::Utf8Error(Arc::new(value))
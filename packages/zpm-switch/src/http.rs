@@ -1,4 +1,4 @@
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
 
 use reqwest::Client;
 use zpm_utils::is_ci;
@@ -32,7 +32,8 @@ pub async fn fetch(url: &str) -> Result<Vec<u8>, Error> {
         = client.get(url)
             .header("User-Agent", "zpm-switch")
             .header("X-Switch-CI", is_ci_header)
-            .send().await?;
+            .send().await
+            .map_err(|err| Error::NetworkError(url.to_string(), Arc::new(err)))?;
 
     let status
         = request.status();
@@ -41,8 +42,8 @@ pub async fn fetch(url: &str) -> Result<Vec<u8>, Error> {
         return Err(Error::HttpStatus(status, url.to_string()));
     }
 
-    let data
-        = request.bytes().await?;
+    let data = request.bytes().await
+        .map_err(|err| Error::NetworkError(url.to_string(), Arc::new(err)))?;
 
     Ok(data.to_vec())
 }
@@ -3,15 +3,15 @@ use std::{process::Command, str::FromStr};
 use serde::Deserialize;
 use zpm_formats::{entries_to_disk, iter_ext::IterExt};
 use zpm_parsers::JsonDocument;
-use zpm_utils::{get_system_string, FromFileString, Path};
+use zpm_utils::Path;
 
-use crate::{cache, errors::Error, http::fetch, manifest::VersionPackageManagerReference};
+use crate::{cache, errors::Error, integrity::fetch_verified, manifest::{PackageManagerHash, VersionPackageManagerReference}, yarn::YarnDistribution};
 
-async fn install_native_from_zpm(source: &cache::CacheKey, binary_name: &Path) -> Result<Command, Error> {
+async fn install_native_from_zpm(source: &cache::CacheKey, pinned_hash: Option<&PackageManagerHash>, skip_verify: bool, binary_name: &Path) -> Result<Command, Error> {
     let cache_path = cache::ensure(source, |p| async move {
         if let Some(npm_url) = source.to_npm_url() {
-            let tgz_data
-                = fetch(&npm_url).await?;
+            let (tgz_data, artifact_checksum)
+                = fetch_verified(source, &npm_url, pinned_hash, skip_verify).await?;
 
             let tar_data
                 = zpm_formats::tar::unpack_tgz(&tgz_data)?;
@@ -53,12 +53,14 @@ async fn install_native_from_zpm(source: &cache::CacheKey, binary_name: &Path) -
                 .with_join_str("bin");
 
             entries_to_disk(&[bin_entry], &target_dir)?;
+
+            Ok(Some(artifact_checksum))
         } else {
             let repo_url
                 = source.to_url();
 
-            let zip_data
-                = fetch(&repo_url).await?;
+            let (zip_data, artifact_checksum)
+                = fetch_verified(source, &repo_url, pinned_hash, skip_verify).await?;
 
             let entries
                 = zpm_formats::zip::entries_from_zip(&zip_data)?;
@@ -72,9 +74,9 @@ async fn install_native_from_zpm(source: &cache::CacheKey, binary_name: &Path) -
                 .with_join_str("bin");
 
             entries_to_disk(&[bin_entry], &target_dir)?;
-        }
 
-        Ok(())
+            Ok(Some(artifact_checksum))
+        }
     }).await?;
 
     let main_file_abs = cache_path
@@ -87,10 +89,14 @@ async fn install_native_from_zpm(source: &cache::CacheKey, binary_name: &Path) -
     Ok(command)
 }
 
-async fn install_node_js_from_url(source: &cache::CacheKey) -> Result<Command, Error> {
+async fn install_node_js_from_url(source: &cache::CacheKey, pinned_hash: Option<&PackageManagerHash>, skip_verify: bool) -> Result<Command, Error> {
     let cache_path = cache::ensure(source, |p| async move {
-        p.with_join_str("bin.js").fs_write(fetch(&source.to_url()).await?)?;
-        Ok(())
+        let (data, artifact_checksum)
+            = fetch_verified(source, &source.to_url(), pinned_hash, skip_verify).await?;
+
+        p.with_join_str("bin.js").fs_write(data)?;
+
+        Ok(Some(artifact_checksum))
     }).await?;
 
     let main_file_abs = cache_path
@@ -104,10 +110,10 @@ async fn install_node_js_from_url(source: &cache::CacheKey) -> Result<Command, E
     Ok(command)
 }
 
-async fn install_node_js_from_package(source: &cache::CacheKey, main_file: &Path) -> Result<Command, Error> {
+async fn install_node_js_from_package(source: &cache::CacheKey, pinned_hash: Option<&PackageManagerHash>, skip_verify: bool, main_file: &Path) -> Result<Command, Error> {
     let cache_path = cache::ensure(source, |p| async move {
-        let compressed_data
-            = fetch(&source.to_url()).await?;
+        let (compressed_data, artifact_checksum)
+            = fetch_verified(source, &source.to_url(), pinned_hash, skip_verify).await?;
 
         let data
             = zpm_formats::tar::unpack_tgz(&compressed_data)?;
@@ -120,7 +126,7 @@ async fn install_node_js_from_package(source: &cache::CacheKey, main_file: &Path
 
         zpm_formats::entries_to_disk(&entries, &p)?;
 
-        Ok(())
+        Ok(Some(artifact_checksum))
     }).await?;
 
     let main_file_abs = cache_path
@@ -134,24 +140,21 @@ async fn install_node_js_from_package(source: &cache::CacheKey, main_file: &Path
     Ok(command)
 }
 
-pub async fn install_package_manager(package_manager: &VersionPackageManagerReference) -> Result<Command, Error> {
-    let version_platform = cache::CacheKey {
-        cache_version: cache::CACHE_VERSION,
-        version: package_manager.version.clone(),
-        platform: get_system_string().to_string(),
-    };
+/// Installs the given release into the cache (unless already there) and
+/// returns the command to run it. Setting `skip_verify` disables the
+/// verification of the downloaded artifacts against their published checksums.
+pub async fn install_package_manager(package_manager: &VersionPackageManagerReference, skip_verify: bool) -> Result<Command, Error> {
+    let version_platform
+        = cache::CacheKey::new(package_manager.version.clone());
 
-    if zpm_semver::Range::from_file_string(">=6.0.0-0").unwrap().check(&package_manager.version) {
-        return install_native_from_zpm(&version_platform, &Path::from_str("yarn-bin").unwrap()).await;
-    }
+    match YarnDistribution::from_version(&package_manager.version)? {
+        YarnDistribution::Native
+            => install_native_from_zpm(&version_platform, package_manager.hash.as_ref(), skip_verify, &Path::from_str("yarn-bin")?).await,
 
-    if zpm_semver::Range::from_file_string(">=2.0.0-0").unwrap().check(&package_manager.version) {
-        return install_node_js_from_url(&version_platform).await;
-    }
+        YarnDistribution::Bundle
+            => install_node_js_from_url(&version_platform, package_manager.hash.as_ref(), skip_verify).await,
 
-    if zpm_semver::Range::from_file_string(">=0.0.0-0").unwrap().check(&package_manager.version) {
-        return install_node_js_from_package(&version_platform, &Path::from_str("bin/yarn.js").unwrap()).await;
+        YarnDistribution::Package
+            => install_node_js_from_package(&version_platform, package_manager.hash.as_ref(), skip_verify, &Path::from_str("bin/yarn.js")?).await,
     }
-
-    unreachable!()
 }
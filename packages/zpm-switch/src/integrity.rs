@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use zpm_parsers::JsonDocument;
+use zpm_utils::{DataType, Path, Sha512, ToFileString, ToHumanString};
+
+use crate::{cache::CacheKey, errors::Error, http::fetch, manifest::PackageManagerHash};
+
+/// Setting this variable to `1` disables the integrity verification of the
+/// downloaded releases, like the `--insecure-skip-verify` flag of `yarn
+/// switch cache --install` does; meant for air-gapped mirrors that don't
+/// serve the registry metadata or the checksum manifests.
+pub const INSECURE_SKIP_VERIFY_ENV: &str = "YARNSW_INSECURE_SKIP_VERIFY";
+
+pub fn is_verification_skipped() -> bool {
+    std::env::var(INSECURE_SKIP_VERIFY_ENV).is_ok_and(|value| value == "1" || value == "true")
+}
+
+#[derive(Deserialize)]
+struct NpmVersionMetadata {
+    dist: NpmDistMetadata,
+}
+
+#[derive(Deserialize)]
+struct NpmDistMetadata {
+    integrity: Option<String>,
+}
+
+/// Retrieves the sha512 integrity that the npm registry lists for the
+/// release tarball, as found in the `dist.integrity` field of its version
+/// metadata (for example `sha512-<base64 digest>`).
+async fn fetch_npm_integrity(key_data: &CacheKey) -> Result<String, Error> {
+    let metadata_url
+        = format!("https://registry.npmjs.org/@yarnpkg/yarn-{}/{}", key_data.platform, key_data.version.to_file_string());
+
+    let metadata_data
+        = fetch(&metadata_url).await?;
+
+    let metadata: NpmVersionMetadata
+        = JsonDocument::hydrate_from_slice(&metadata_data)?;
+
+    // The field may contain multiple space-separated hashes, we only check
+    // the sha512 one
+    metadata.dist.integrity.as_deref()
+        .and_then(|integrity| integrity.split_whitespace().find(|hash| hash.starts_with("sha512-")))
+        .map(|hash| hash.to_string())
+        .ok_or_else(|| Error::MissingReleaseChecksum(key_data.version.clone(), format!("@yarnpkg/yarn-{}", key_data.platform)))
+}
+
+/// The name of the artifact, which is the last segment of the url it's
+/// downloaded from; the checksum manifests are keyed by it.
+fn artifact_name(url: &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+/// Retrieves the sha512 (hex-encoded) listed for the artifact in the
+/// `checksums.json` manifest published along with the release on
+/// repo.yarnpkg.com.
+async fn fetch_repo_checksum(key_data: &CacheKey, url: &str) -> Result<String, Error> {
+    let name
+        = artifact_name(url);
+
+    let checksums_url
+        = format!("https://repo.yarnpkg.com/releases/{}/checksums.json", key_data.version.to_file_string());
+
+    let checksums_data = match fetch(&checksums_url).await {
+        Ok(checksums_data) => checksums_data,
+        Err(Error::HttpStatus(StatusCode::NOT_FOUND, _)) => return Err(Error::MissingReleaseChecksum(key_data.version.clone(), name.to_string())),
+        Err(error) => return Err(error),
+    };
+
+    let checksums: BTreeMap<String, String>
+        = JsonDocument::hydrate_from_slice(&checksums_data)?;
+
+    checksums.get(name)
+        .cloned()
+        .ok_or_else(|| Error::MissingReleaseChecksum(key_data.version.clone(), name.to_string()))
+}
+
+/// The checksum an artifact is expected to match, in the format of the
+/// place it's been published to.
+enum ExpectedChecksum {
+    /// An npm `dist.integrity` (`sha512-<base64 digest>`)
+    Integrity(String),
+
+    /// A hex-encoded sha512 from a repo.yarnpkg.com checksum manifest
+    Hex(String),
+}
+
+impl ExpectedChecksum {
+    async fn fetch(key_data: &CacheKey, url: &str) -> Result<ExpectedChecksum, Error> {
+        match key_data.to_npm_url().as_deref() == Some(url) {
+            true => Ok(ExpectedChecksum::Integrity(fetch_npm_integrity(key_data).await?)),
+            false => Ok(ExpectedChecksum::Hex(fetch_repo_checksum(key_data, url).await?)),
+        }
+    }
+
+    fn matches(&self, checksum: &Sha512) -> bool {
+        match self {
+            ExpectedChecksum::Integrity(integrity) => format!("sha512-{}", checksum.to_base64()) == *integrity,
+            ExpectedChecksum::Hex(hex) => checksum.to_hex().eq_ignore_ascii_case(hex),
+        }
+    }
+}
+
+/// Checks the artifact against the hash pinned in the `packageManager` field,
+/// if any. Unknown algorithms only print a warning, as failing would prevent
+/// projects pinned by newer tools from running at all.
+fn verify_pinned_hash(key_data: &CacheKey, pinned_hash: Option<&PackageManagerHash>, data: &[u8]) -> Result<(), Error> {
+    let Some(pinned_hash) = pinned_hash else {
+        return Ok(());
+    };
+
+    let Some(actual_digest) = pinned_hash.compute(data) else {
+        eprintln!(
+            "{} Unsupported hash algorithm in the {} field ({}); skipping the verification of Yarn {}",
+            DataType::Warning.colorize("warning:"),
+            DataType::Code.colorize("packageManager"),
+            DataType::Code.colorize(&pinned_hash.algorithm),
+            key_data.version.to_print_string(),
+        );
+
+        return Ok(());
+    };
+
+    if !actual_digest.eq_ignore_ascii_case(&pinned_hash.digest) {
+        return Err(Error::PinnedIntegrityMismatch(key_data.version.clone(), pinned_hash.algorithm.clone()));
+    }
+
+    Ok(())
+}
+
+/// Downloads a release artifact and checks it against the checksum published
+/// for it: the `dist.integrity` of the npm registry for the artifacts coming
+/// from there, and the checksum manifest of the release for those coming from
+/// repo.yarnpkg.com. Failing to find a checksum is an error unless
+/// `skip_verify` is set. The artifact must also match the hash pinned in the
+/// project manifest, if any. Returns the artifact along with its sha512.
+pub async fn fetch_verified(key_data: &CacheKey, url: &str, pinned_hash: Option<&PackageManagerHash>, skip_verify: bool) -> Result<(Vec<u8>, String), Error> {
+    let expected_checksum = match skip_verify {
+        true => {
+            eprintln!(
+                "{} Skipping the integrity verification of Yarn {}; only do this with mirrors you trust",
+                DataType::Warning.colorize("warning:"),
+                key_data.version.to_print_string(),
+            );
+
+            None
+        },
+
+        false => {
+            Some(ExpectedChecksum::fetch(key_data, url).await?)
+        },
+    };
+
+    let data
+        = fetch(url).await?;
+
+    let actual_checksum
+        = Sha512::new(&data);
+
+    if let Some(expected_checksum) = expected_checksum {
+        if !expected_checksum.matches(&actual_checksum) {
+            return Err(Error::ReleaseIntegrityMismatch(key_data.version.clone(), artifact_name(url).to_string()));
+        }
+    }
+
+    verify_pinned_hash(key_data, pinned_hash, &data)?;
+
+    Ok((data, actual_checksum.to_hex()))
+}
+
+/// Computes the checksum of the files extracted into a cache entry, so that
+/// `yarn switch cache --check` can detect entries modified after the fact.
+pub fn content_checksum(cache_path: &Path) -> Result<String, Error> {
+    let mut entries
+        = zpm_formats::entries_from_folder(cache_path)?;
+
+    entries.retain(|entry| !matches!(entry.name.as_str(), "meta.json" | ".ready"));
+    entries.sort_by(|a, b| a.name.as_str().cmp(b.name.as_str()));
+
+    let mut content
+        = Vec::new();
+
+    for entry in &entries {
+        content.extend_from_slice(entry.name.as_str().as_bytes());
+        content.push(0);
+        content.extend_from_slice(Sha512::new(&entry.data).to_hex().as_bytes());
+        content.push(0);
+    }
+
+    Ok(Sha512::new(&content).to_hex())
+}
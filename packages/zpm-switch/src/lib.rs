@@ -1,5 +1,7 @@
+pub mod cache;
 mod errors;
 mod http;
+pub mod integrity;
 mod manifest;
 mod yarn_enums;
 mod yarn;
@@ -10,8 +12,10 @@ pub use errors::{
 
 pub use manifest::{
     PackageManagerField,
+    PackageManagerHash,
     PackageManagerReference,
     VersionPackageManagerReference,
+    find_closest_manifest_path,
     find_closest_package_manager,
 };
 
@@ -27,6 +31,8 @@ pub use yarn::{
     get_bin_version,
     get_default_yarn_version,
     resolve_channel_selector,
+    resolve_dev_engines_range,
     resolve_semver_range,
     resolve_selector,
+    YarnDistribution,
 };
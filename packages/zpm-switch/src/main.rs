@@ -8,6 +8,7 @@ mod cwd;
 mod errors;
 mod http;
 mod install;
+mod integrity;
 mod links;
 mod manifest;
 mod yarn_enums;
@@ -2,13 +2,14 @@ use std::mem::take;
 
 use rkyv::Archive;
 use serde::Deserialize;
+use serde_with::{serde_as, DefaultOnError};
 use zpm_macro_enum::zpm_enum;
 use zpm_parsers::JsonDocument;
-use zpm_utils::{impl_file_string_from_str, impl_file_string_serialization, FromFileString, IoResultExt, Path, ToFileString, ToHumanString};
+use zpm_utils::{impl_file_string_from_str, impl_file_string_serialization, DataType, FromFileString, IoResultExt, Path, Sha1, Sha224, Sha256, Sha512, ToFileString, ToHumanString};
 
 use crate::errors::Error;
 
-use zpm_semver::Version;
+use zpm_semver::{Range, Version};
 
 #[zpm_enum(or_else = |s| Err(Error::UnknownBinaryName(s.to_string())))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Archive, rkyv::Serialize, rkyv::Deserialize)]
@@ -22,16 +23,90 @@ enum BinaryName {
 }
 
 
+/// The integrity suffix that Corepack appends to the version it pins in the
+/// `packageManager` field (`yarn@4.5.0+sha512.<hex digest>`).
+#[derive(Clone, Debug, PartialEq, Eq, Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[rkyv(derive(PartialEq, Eq))]
+pub struct PackageManagerHash {
+    pub algorithm: String,
+    pub digest: String,
+}
+
+impl PackageManagerHash {
+    pub fn new_sha512(digest: String) -> PackageManagerHash {
+        PackageManagerHash {
+            algorithm: "sha512".to_string(),
+            digest,
+        }
+    }
+
+    /// Hashes the given data with the same algorithm as the pinned digest;
+    /// returns `None` if the algorithm isn't supported.
+    pub fn compute(&self, data: &[u8]) -> Option<String> {
+        match self.algorithm.as_str() {
+            "sha1" => Some(Sha1::new(data).to_hex()),
+            "sha224" => Some(Sha224::new(data).to_hex()),
+            "sha256" => Some(Sha256::new(data).to_hex()),
+            "sha512" => Some(Sha512::new(data).to_hex()),
+            _ => None,
+        }
+    }
+}
+
+impl FromFileString for PackageManagerHash {
+    type Error = Error;
+
+    fn from_file_string(s: &str) -> Result<Self, Error> {
+        let (algorithm, digest) = s
+            .split_once('.')
+            .ok_or_else(|| Error::InvalidPackageManagerReference(s.to_string()))?;
+
+        Ok(PackageManagerHash {
+            algorithm: algorithm.to_string(),
+            digest: digest.to_string(),
+        })
+    }
+}
+
+impl ToFileString for PackageManagerHash {
+    fn to_file_string(&self) -> String {
+        format!("{}.{}", self.algorithm, self.digest)
+    }
+}
+
+impl ToHumanString for PackageManagerHash {
+    fn to_print_string(&self) -> String {
+        DataType::Reference.colorize(&self.to_file_string())
+    }
+}
+
+impl_file_string_from_str!(PackageManagerHash);
+
+fn format_version(version: &Version, hash: &Option<PackageManagerHash>) -> String {
+    match hash {
+        Some(hash) => format!("{}+{}", version.to_file_string(), hash.to_file_string()),
+        None => version.to_file_string(),
+    }
+}
+
+fn print_version(version: &Version, hash: &Option<PackageManagerHash>) -> String {
+    match hash {
+        Some(hash) => format!("{}+{}", version.to_print_string(), hash.to_print_string()),
+        None => version.to_print_string(),
+    }
+}
+
 #[zpm_enum(or_else = |s| Err(Error::InvalidPackageManagerReference(s.to_string())))]
 #[derive(Clone, Debug, PartialEq, Eq, Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[derive_variants(Clone, Debug, PartialEq, Eq, Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[variant_struct_attr(rkyv(derive(PartialEq, Eq)))]
 pub enum PackageManagerReference {
-    #[pattern(r"(?<version>.*)")]
-    #[to_file_string(|params| params.version.to_file_string())]
-    #[to_print_string(|params| params.version.to_print_string())]
+    #[pattern(r"(?<version>.*?)(?:\+(?<hash>[a-z0-9]+\.[0-9a-fA-F]+))?")]
+    #[to_file_string(|params| format_version(&params.version, &params.hash))]
+    #[to_print_string(|params| print_version(&params.version, &params.hash))]
     Version {
         version: Version,
+        hash: Option<PackageManagerHash>,
     },
 
     #[no_pattern]
@@ -111,11 +186,97 @@ impl ToHumanString for PackageManagerField {
 impl_file_string_from_str!(PackageManagerField);
 impl_file_string_serialization!(PackageManagerField);
 
+/// What to do when the package manager doesn't match the one listed in
+/// `devEngines.packageManager`; `download` is only meaningful for runtimes,
+/// so we treat it the same as `error`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DevEngineOnFail {
+    Ignore,
+    Warn,
+    #[default]
+    Error,
+    Download,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevEngineDependency {
+    pub name: String,
+    pub version: Option<String>,
+
+    #[serde(default)]
+    pub on_fail: DevEngineOnFail,
+}
+
+impl DevEngineDependency {
+    fn range(&self) -> Result<Option<Range>, Error> {
+        self.version.as_ref()
+            .map(|version| Range::from_file_string(version).map_err(|_| Error::InvalidDevEnginesVersion(version.clone())))
+            .transpose()
+    }
+
+    fn accepts(&self, package_manager: &PackageManagerField) -> Result<bool, Error> {
+        if self.name != package_manager.name {
+            return Ok(false);
+        }
+
+        let Some(range) = self.range()? else {
+            return Ok(true);
+        };
+
+        match &package_manager.reference {
+            PackageManagerReference::Version(params) => Ok(range.check(&params.version)),
+            PackageManagerReference::Local(_) => Ok(true),
+        }
+    }
+}
+
+impl ToHumanString for DevEngineDependency {
+    fn to_print_string(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{}", self.name.to_print_string(), DataType::Range.colorize(version)),
+            None => self.name.to_print_string(),
+        }
+    }
+}
+
+/// The `devEngines.packageManager` field accepts either a single package
+/// manager or a list of alternatives.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum DevEnginePackageManagers {
+    One(DevEngineDependency),
+    Many(Vec<DevEngineDependency>),
+}
+
+impl DevEnginePackageManagers {
+    fn into_vec(self) -> Vec<DevEngineDependency> {
+        match self {
+            DevEnginePackageManagers::One(dependency) => vec![dependency],
+            DevEnginePackageManagers::Many(dependencies) => dependencies,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevEngines {
+    package_manager: Option<DevEnginePackageManagers>,
+}
+
+#[serde_as]
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct Manifest {
     package_manager: Option<PackageManagerField>,
     package_manager_migration: Option<PackageManagerField>,
+
+    // Other tools may support forms we don't know about; better ignore them
+    // than to fail running Yarn at all
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnError")]
+    dev_engines: Option<DevEngines>,
 }
 
 #[derive(Debug)]
@@ -123,6 +284,66 @@ pub struct FindResult {
     pub detected_root_path: Option<Path>,
     pub detected_package_manager: Option<PackageManagerField>,
     pub detected_package_manager_migration: Option<PackageManagerField>,
+
+    /// Set when the project only lists a range in `devEngines.packageManager`,
+    /// in which case the caller is expected to pick a version matching it.
+    pub detected_package_manager_range: Option<Range>,
+}
+
+fn report_dev_engines_failure(on_fail: DevEngineOnFail, err: Error) -> Result<(), Error> {
+    match on_fail {
+        DevEngineOnFail::Ignore => Ok(()),
+
+        DevEngineOnFail::Warn => {
+            eprintln!("{} {}", DataType::Warning.colorize("warning:"), err);
+            Ok(())
+        },
+
+        DevEngineOnFail::Error | DevEngineOnFail::Download => Err(err),
+    }
+}
+
+/// Reconciles the `packageManager` field with the `devEngines.packageManager`
+/// one; the former always wins when both are set, but the latter may require
+/// them to agree.
+fn select_package_manager(manifest_path: &Path, package_manager: Option<PackageManagerField>, dev_engines: Vec<DevEngineDependency>) -> Result<(Option<PackageManagerField>, Option<Range>), Error> {
+    if let Some(package_manager) = package_manager {
+        let dependency = dev_engines.iter()
+            .find(|dependency| dependency.name == package_manager.name)
+            .or(dev_engines.first());
+
+        if let Some(dependency) = dependency {
+            if !dependency.accepts(&package_manager)? {
+                report_dev_engines_failure(dependency.on_fail, Error::DevEnginesConflict(manifest_path.clone(), package_manager.to_print_string(), dependency.to_print_string()))?;
+            }
+        }
+
+        return Ok((Some(package_manager), None));
+    }
+
+    let yarn_dependency = dev_engines.iter()
+        .find(|dependency| dependency.name == "yarn");
+
+    let Some(yarn_dependency) = yarn_dependency else {
+        if let Some(dependency) = dev_engines.first() {
+            report_dev_engines_failure(dependency.on_fail, Error::DevEnginesUnsupportedPackageManager(dependency.to_print_string()))?;
+        }
+
+        return Ok((None, None));
+    };
+
+    let Some(range) = yarn_dependency.range()? else {
+        return Ok((None, None));
+    };
+
+    if let Some(version) = range.exact_version() {
+        let reference
+            = VersionPackageManagerReference {version, hash: None};
+
+        return Ok((Some(PackageManagerField::new_yarn(reference.into())), None));
+    }
+
+    Ok((None, Some(range)))
 }
 
 const ROOT_FILES: &[&'static str] = &[
@@ -144,11 +365,19 @@ pub fn find_closest_package_manager(path: &Path) -> Result<FindResult, Error> {
             let parsed_manifest: Manifest = JsonDocument::hydrate_from_str(&manifest)
                 .map_err(|err| Error::FailedToParseManifest(err))?;
 
-            if let Some(package_manager) = parsed_manifest.package_manager {
+            let dev_engines = parsed_manifest.dev_engines
+                .and_then(|dev_engines| dev_engines.package_manager)
+                .map_or_else(Vec::new, |package_managers| package_managers.into_vec());
+
+            if parsed_manifest.package_manager.is_some() || !dev_engines.is_empty() {
+                let (detected_package_manager, detected_package_manager_range)
+                    = select_package_manager(&manifest_path, parsed_manifest.package_manager, dev_engines)?;
+
                 return Ok(FindResult {
                     detected_root_path: Some(parent),
-                    detected_package_manager: Some(package_manager),
+                    detected_package_manager,
                     detected_package_manager_migration: parsed_manifest.package_manager_migration,
+                    detected_package_manager_range,
                 });
             }
         }
@@ -162,6 +391,7 @@ pub fn find_closest_package_manager(path: &Path) -> Result<FindResult, Error> {
                     detected_root_path: Some(parent),
                     detected_package_manager: None,
                     detected_package_manager_migration: None,
+                    detected_package_manager_range: None,
                 });
             }
         }
@@ -175,5 +405,40 @@ pub fn find_closest_package_manager(path: &Path) -> Result<FindResult, Error> {
         detected_root_path: last_package_folder,
         detected_package_manager: None,
         detected_package_manager_migration: None,
+        detected_package_manager_range: None,
     })
 }
+
+/// Returns the path of the manifest that should record the project's Yarn
+/// version: the one at the root of the detected project if it has one, and
+/// otherwise the closest manifest (the root may have been detected through
+/// its lockfile alone). If there's no manifest at all, returns the path where
+/// one should be created.
+pub fn find_closest_manifest_path(path: &Path) -> Result<Path, Error> {
+    let find_result
+        = find_closest_package_manager(path)?;
+
+    if let Some(detected_root_path) = &find_result.detected_root_path {
+        let root_manifest_path = detected_root_path
+            .with_join_str("package.json");
+
+        if root_manifest_path.fs_exists() {
+            return Ok(root_manifest_path);
+        }
+    }
+
+    for parent in path.iter_path().rev() {
+        let manifest_path = parent
+            .with_join_str("package.json");
+
+        if manifest_path.fs_exists() {
+            return Ok(manifest_path);
+        }
+    }
+
+    let manifest_path = find_result.detected_root_path
+        .unwrap_or_else(|| path.clone())
+        .with_join_str("package.json");
+
+    Ok(manifest_path)
+}
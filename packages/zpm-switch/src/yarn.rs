@@ -3,9 +3,45 @@ use serde_with::serde_as;
 use zpm_parsers::JsonDocument;
 use std::{collections::BTreeMap, fmt::Debug, str::FromStr};
 use zpm_semver::{Range, Version, VersionRc};
-use zpm_utils::{ExplicitPath, FromFileString, Path, ToFileString};
+use zpm_utils::{DataType, ExplicitPath, FromFileString, Path, ToFileString, ToHumanString};
 
-use crate::{errors::Error, http::fetch, manifest::{LocalPackageManagerReference, PackageManagerReference, VersionPackageManagerReference}, yarn_enums::{ChannelSelector, Selector}};
+use crate::{cache, errors::Error, http::fetch, manifest::{LocalPackageManagerReference, PackageManagerReference, VersionPackageManagerReference}, yarn_enums::{ChannelSelector, Selector}};
+
+/// How a given Yarn release is distributed, which determines how it gets
+/// installed and run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YarnDistribution {
+    /// A native binary published on the npm registry (6.x and later).
+    Native,
+
+    /// A single JavaScript bundle hosted on repo.yarnpkg.com (2.x to 5.x).
+    Bundle,
+
+    /// The classic npm package, whose bin entry must be run with Node.
+    Package,
+}
+
+impl YarnDistribution {
+    /// Prerelease tags are ignored when matching the version, as an rc of a
+    /// given release line is distributed the same way as its stable releases
+    /// are (whereas the npm semantics would exclude e.g. `4.0.0-rc.36` from
+    /// `>=2.0.0-0`).
+    pub fn from_version(version: &Version) -> Result<YarnDistribution, Error> {
+        let distributions = [
+            (">=6.0.0-0", YarnDistribution::Native),
+            (">=2.0.0-0", YarnDistribution::Bundle),
+            (">=0.0.0-0", YarnDistribution::Package),
+        ];
+
+        for (range, distribution) in distributions {
+            if Range::from_file_string(range)?.check_ignore_rc(version) {
+                return Ok(distribution);
+            }
+        }
+
+        Err(Error::UnsupportedVersionSelector(version.to_file_string()))
+    }
+}
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -34,7 +70,7 @@ pub async fn get_default_yarn_version(release_line: Option<crate::yarn_enums::Re
     let version
         = resolve_channel_selector(&channel_selector).await?;
 
-    Ok(VersionPackageManagerReference {version}.into())
+    Ok(VersionPackageManagerReference {version, hash: None}.into())
 }
 
 pub async fn resolve_selector(selector: &Selector) -> Result<Version, Error> {
@@ -53,9 +89,35 @@ pub async fn resolve_selector(selector: &Selector) -> Result<Version, Error> {
   }
 }
 
+/// Called when the Yarn registry couldn't be reached while resolving a
+/// selector; uses the highest cached release matching it instead, so that
+/// Yarn keeps working offline. Explicit versions never go through this path,
+/// as silently running a different version than the one requested would be
+/// worse than failing.
+fn resolve_from_cache<F: Fn(&Version) -> bool>(err: Error, selector: &str, predicate: F) -> Result<Version, Error> {
+    if !err.is_network_error() {
+        return Err(err);
+    }
+
+    let Ok(Some(version)) = cache::find_cached_version(predicate) else {
+        return Err(err);
+    };
+
+    eprintln!(
+        "{} Couldn't reach the Yarn registry to resolve {}; using Yarn {} from the cache instead",
+        DataType::Warning.colorize("warning:"),
+        DataType::Code.colorize(selector),
+        version.to_print_string(),
+    );
+
+    Ok(version)
+}
+
 pub async fn resolve_semver_range(range: &Range) -> Result<Version, Error> {
-    let response
-        = fetch("https://repo.yarnpkg.com/releases").await?;
+    let response = match fetch("https://repo.yarnpkg.com/releases").await {
+        Ok(response) => response,
+        Err(err) => return resolve_from_cache(err, &range.to_file_string(), |version| range.check(version)),
+    };
 
     let data: TagsPayload
         = JsonDocument::hydrate_from_slice(&response)?;
@@ -69,6 +131,17 @@ pub async fn resolve_semver_range(range: &Range) -> Result<Version, Error> {
     Ok(highest.clone())
 }
 
+/// Resolves the range listed in `devEngines.packageManager`. As it's
+/// resolved on every invocation, the highest cached release matching it is
+/// preferred over asking the Yarn registry for the latest one.
+pub async fn resolve_dev_engines_range(range: &Range) -> Result<Version, Error> {
+    if let Some(version) = cache::find_cached_version(|version| range.check(version))? {
+        return Ok(version);
+    }
+
+    resolve_semver_range(range).await
+}
+
 pub async fn resolve_channel_selector(channel_selector: &ChannelSelector) -> Result<Version, Error> {
     let release_line = channel_selector.release_line.as_ref()
         .unwrap_or(&crate::yarn_enums::ReleaseLine::Classic)
@@ -95,8 +168,10 @@ pub async fn resolve_channel_selector(channel_selector: &ChannelSelector) -> Res
     let channel_url
         = format!("https://repo.yarnpkg.com/channels/{}/{}", release_line, channel);
 
-    let response
-        = fetch(&channel_url).await?;
+    let response = match fetch(&channel_url).await {
+        Ok(response) => response,
+        Err(err) => return resolve_from_cache(err, &format!("{}-{}", release_line, channel), |version| channel_selector.contains(version)),
+    };
 
     let version_str
         = std::str::from_utf8(&response)?
@@ -12,7 +12,7 @@ fn format_channel_selector(release_line: &Option<ReleaseLine>, channel: &Option<
     }
 }
 
-#[zpm_enum(or_else = |s| Err(Error::InvalidVersionSelector(s.to_string())))]
+#[zpm_enum(or_else = |s| Err(Error::UnsupportedVersionSelector(s.to_string())))]
 #[derive(Debug)]
 #[derive_variants(Debug)]
 pub enum Channel {
@@ -28,7 +28,7 @@ pub enum Channel {
 }
 
 
-#[zpm_enum(or_else = |s| Err(Error::InvalidVersionSelector(s.to_string())))]
+#[zpm_enum(or_else = |s| Err(Error::UnsupportedVersionSelector(s.to_string())))]
 #[derive(Debug, Copy, Clone)]
 #[derive_variants(Debug, Copy, Clone)]
 pub enum ReleaseLine {
@@ -54,6 +54,17 @@ pub enum ReleaseLine {
 }
 
 impl ReleaseLine {
+    /// Whether the given version is part of the release line; only used to
+    /// pick a cached release when the channels can't be queried.
+    pub fn contains(&self, version: &zpm_semver::Version) -> bool {
+        match self {
+            ReleaseLine::Classic => version.major < 2,
+            ReleaseLine::Berry => (2..6).contains(&version.major),
+            ReleaseLine::Zpm => version.major >= 6,
+            ReleaseLine::Default => version.major >= 2,
+        }
+    }
+
     pub fn stable(&self) -> ChannelSelector {
         ChannelSelector {
             release_line: Some(*self),
@@ -69,8 +80,21 @@ impl ReleaseLine {
     }
 }
 
+impl ChannelSelector {
+    /// Whether the given version could have been published on the channel;
+    /// prereleases are only published on the canary channels.
+    pub fn contains(&self, version: &zpm_semver::Version) -> bool {
+        let release_line
+            = self.release_line.unwrap_or(ReleaseLine::Classic);
+
+        let is_canary
+            = matches!(self.channel, Some(Channel::Canary));
+
+        release_line.contains(version) && (is_canary || version.rc.is_none())
+    }
+}
 
-#[zpm_enum(or_else = |s| Err(Error::InvalidVersionSelector(s.to_string())))]
+#[zpm_enum(or_else = |s| Err(Error::UnsupportedVersionSelector(s.to_string())))]
 #[derive(Debug)]
 #[derive_variants(Debug)]
 pub enum Selector {
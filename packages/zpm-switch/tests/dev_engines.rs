@@ -0,0 +1,88 @@
+use zpm_switch::{Error, find_closest_package_manager};
+use zpm_utils::{Path, ToFileString};
+
+fn find_in_manifest(manifest: &str) -> Result<(Option<String>, Option<String>), Error> {
+    let project_dir
+        = Path::temp_dir().unwrap();
+
+    project_dir
+        .with_join_str("package.json")
+        .fs_write_text(manifest).unwrap();
+
+    let find_result
+        = find_closest_package_manager(&project_dir)?;
+
+    Ok((
+        find_result.detected_package_manager.map(|field| field.to_file_string()),
+        find_result.detected_package_manager_range.map(|range| range.to_file_string()),
+    ))
+}
+
+#[test]
+fn it_reads_an_exact_version_from_the_object_form() {
+    let result = find_in_manifest(r#"{
+        "devEngines": {"packageManager": {"name": "yarn", "version": "4.5.0"}}
+    }"#).unwrap();
+
+    assert_eq!(result, (Some("yarn@4.5.0".to_string()), None));
+}
+
+#[test]
+fn it_reads_a_range_from_the_array_form() {
+    let result = find_in_manifest(r#"{
+        "devEngines": {"packageManager": [
+            {"name": "pnpm", "version": "^9.0.0"},
+            {"name": "yarn", "version": "^4.1.0"}
+        ]}
+    }"#).unwrap();
+
+    assert_eq!(result, (None, Some("^4.1.0".to_string())));
+}
+
+#[test]
+fn it_accepts_matching_fields() {
+    let result = find_in_manifest(r#"{
+        "packageManager": "yarn@4.5.0",
+        "devEngines": {"packageManager": {"name": "yarn", "version": "^4.0.0"}}
+    }"#).unwrap();
+
+    assert_eq!(result, (Some("yarn@4.5.0".to_string()), None));
+}
+
+#[test]
+fn it_rejects_conflicting_fields_by_default() {
+    let result = find_in_manifest(r#"{
+        "packageManager": "yarn@4.5.0",
+        "devEngines": {"packageManager": {"name": "yarn", "version": "^3.0.0"}}
+    }"#);
+
+    assert!(matches!(result, Err(Error::DevEnginesConflict(..))));
+}
+
+#[test]
+fn it_prefers_the_package_manager_field_when_conflicts_only_warn() {
+    let result = find_in_manifest(r#"{
+        "packageManager": "yarn@4.5.0",
+        "devEngines": {"packageManager": {"name": "yarn", "version": "^3.0.0", "onFail": "warn"}}
+    }"#).unwrap();
+
+    assert_eq!(result, (Some("yarn@4.5.0".to_string()), None));
+}
+
+#[test]
+fn it_rejects_projects_requiring_another_package_manager() {
+    let result = find_in_manifest(r#"{
+        "devEngines": {"packageManager": {"name": "pnpm", "version": "^9.0.0"}}
+    }"#);
+
+    assert!(matches!(result, Err(Error::DevEnginesUnsupportedPackageManager(..))));
+}
+
+#[test]
+fn it_ignores_unsupported_forms() {
+    let result = find_in_manifest(r#"{
+        "devEngines": {"packageManager": "yarn@4.5.0"}
+    }"#).unwrap();
+
+    assert_eq!(result, (None, None));
+}
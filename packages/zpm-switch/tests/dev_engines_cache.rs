@@ -0,0 +1,43 @@
+use zpm_parsers::JsonDocument;
+use zpm_semver::{Range, Version};
+use zpm_switch::{cache::{self, CacheKey}, resolve_dev_engines_range};
+use zpm_utils::{FromFileString, Path, ToFileString, get_system_string};
+
+fn add_cache_entry(version: &str) {
+    let key = CacheKey {
+        cache_version: cache::CACHE_VERSION,
+        version: Version::from_file_string(version).unwrap(),
+        platform: get_system_string().to_string(),
+    };
+
+    let entry_path = cache::cache_dir().unwrap()
+        .with_join_str(format!("{}-{}", version, get_system_string()));
+
+    entry_path
+        .fs_create_dir_all().unwrap();
+
+    entry_path
+        .with_join_str("meta.json")
+        .fs_write(JsonDocument::to_string(&key).unwrap()).unwrap();
+
+    entry_path
+        .with_join_str(".ready")
+        .fs_write([]).unwrap();
+}
+
+#[tokio::test]
+async fn it_prefers_the_cached_releases_matching_the_dev_engines_range() {
+    let home_dir
+        = Path::temp_dir().unwrap();
+
+    std::env::set_var("HOME", home_dir.as_str());
+
+    add_cache_entry("4.1.0");
+    add_cache_entry("4.5.0");
+    add_cache_entry("5.0.0");
+
+    let version
+        = resolve_dev_engines_range(&Range::from_file_string("^4.0.0").unwrap()).await.unwrap();
+
+    assert_eq!(version.to_file_string(), "4.5.0");
+}
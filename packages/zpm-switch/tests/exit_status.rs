@@ -0,0 +1,110 @@
+use assert_cmd::prelude::*;
+use std::{fs::Permissions, os::unix::fs::PermissionsExt, process::{Command, Stdio}, time::{Duration, Instant}};
+use zpm_utils::Path;
+
+struct TestEnv {
+    cmd: Command,
+    tmp_dir: Path,
+}
+
+/// Prepares a `yarn` command that proxies to the given shell script instead
+/// of an actual Yarn release.
+fn init_test_env(script: &str) -> TestEnv {
+    let mut cmd
+        = Command::cargo_bin("yarn")
+            .expect("Failed to get yarn command");
+
+    let tmp_dir
+        = Path::temp_dir()
+            .expect("Failed to create temp dir");
+
+    let script_path = tmp_dir
+        .with_join_str("fake-yarn.sh");
+
+    script_path
+        .fs_write_text(&format!("#!/bin/sh\n{}", script))
+        .expect("Failed to write the script")
+        .fs_set_permissions(Permissions::from_mode(0o755))
+        .expect("Failed to make the script executable");
+
+    cmd.current_dir(tmp_dir.to_path_buf());
+    cmd.env("HOME", tmp_dir.as_str());
+    cmd.env("YARNSW_DEFAULT", format!("local:{}", script_path.as_str()));
+
+    TestEnv {
+        cmd,
+        tmp_dir,
+    }
+}
+
+#[test]
+fn child_exiting_successfully() {
+    let TestEnv {mut cmd, ..}
+        = init_test_env("exit 0\n");
+
+    cmd.assert()
+        .code(0);
+}
+
+#[test]
+fn child_exiting_with_an_error() {
+    let TestEnv {mut cmd, ..}
+        = init_test_env("exit 7\n");
+
+    cmd.assert()
+        .code(7);
+}
+
+#[test]
+fn child_killed_by_a_signal() {
+    let TestEnv {mut cmd, ..}
+        = init_test_env("kill -SEGV $$\n");
+
+    cmd.assert()
+        .code(128 + 11);
+}
+
+#[test]
+fn child_output_closed_by_the_consumer() {
+    let TestEnv {mut cmd, ..}
+        = init_test_env("yes | head -n 100000\nexit 7\n");
+
+    cmd.stdout(Stdio::piped());
+
+    let mut child
+        = cmd.spawn().unwrap();
+
+    // Closing the read end of the pipe right away makes the writes fail
+    drop(child.stdout.take());
+
+    assert_eq!(child.wait().unwrap().code(), Some(7));
+}
+
+#[test]
+fn child_ignoring_sigterm() {
+    let TestEnv {mut cmd, tmp_dir}
+        = init_test_env("trap '' TERM\ntouch ready\nsleep 1\nexit 3\n");
+
+    let mut child
+        = cmd.spawn().unwrap();
+
+    let ready_path = tmp_dir
+        .with_join_str("ready");
+
+    let start
+        = Instant::now();
+
+    while !ready_path.fs_exists() {
+        assert!(start.elapsed() < Duration::from_secs(30), "The child never started");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+
+    // The proxy must outlive the child, and report its status rather than
+    // its own termination
+    assert_eq!(child.wait().unwrap().code(), Some(3));
+}
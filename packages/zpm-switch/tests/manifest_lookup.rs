@@ -0,0 +1,68 @@
+use zpm_switch::find_closest_manifest_path;
+use zpm_utils::Path;
+
+fn setup_project(files: &[(&str, &str)]) -> Path {
+    let project_dir
+        = Path::temp_dir().unwrap();
+
+    for (name, content) in files {
+        project_dir
+            .with_join_str(name)
+            .fs_create_parent().unwrap()
+            .fs_write_text(content).unwrap();
+    }
+
+    project_dir
+}
+
+#[test]
+fn it_picks_the_manifest_of_the_project_root() {
+    let project_dir = setup_project(&[
+        ("package.json", r#"{"packageManager": "yarn@4.5.0"}"#),
+        ("packages/foo/package.json", "{}"),
+    ]);
+
+    let lookup_path
+        = project_dir.with_join_str("packages/foo");
+
+    assert_eq!(find_closest_manifest_path(&lookup_path).unwrap(), project_dir.with_join_str("package.json"));
+}
+
+#[test]
+fn it_picks_the_closest_manifest_when_the_root_has_none() {
+    let project_dir = setup_project(&[
+        ("yarn.lock", ""),
+        ("packages/foo/package.json", "{}"),
+    ]);
+
+    let lookup_path
+        = project_dir.with_join_str("packages/foo/src");
+
+    lookup_path
+        .fs_create_dir_all().unwrap();
+
+    assert_eq!(find_closest_manifest_path(&lookup_path).unwrap(), project_dir.with_join_str("packages/foo/package.json"));
+}
+
+#[test]
+fn it_creates_the_manifest_at_the_project_root() {
+    let project_dir = setup_project(&[
+        ("yarn.lock", ""),
+    ]);
+
+    let lookup_path
+        = project_dir.with_join_str("src");
+
+    lookup_path
+        .fs_create_dir_all().unwrap();
+
+    assert_eq!(find_closest_manifest_path(&lookup_path).unwrap(), project_dir.with_join_str("package.json"));
+}
+
+#[test]
+fn it_creates_the_manifest_in_the_lookup_folder_outside_of_projects() {
+    let project_dir
+        = setup_project(&[]);
+
+    assert_eq!(find_closest_manifest_path(&project_dir).unwrap(), project_dir.with_join_str("package.json"));
+}
@@ -0,0 +1,56 @@
+use zpm_parsers::JsonDocument;
+use zpm_semver::Version;
+use zpm_switch::{ReleaseLine, cache::{self, CacheKey}};
+use zpm_utils::{FromFileString, Path, ToFileString, get_system_string};
+
+fn add_cache_entry(version: &str, platform: &str) {
+    let key = CacheKey {
+        cache_version: cache::CACHE_VERSION,
+        version: Version::from_file_string(version).unwrap(),
+        platform: platform.to_string(),
+    };
+
+    let entry_path = cache::cache_dir().unwrap()
+        .with_join_str(format!("{}-{}", version, platform));
+
+    entry_path
+        .fs_create_dir_all().unwrap();
+
+    entry_path
+        .with_join_str("meta.json")
+        .fs_write(JsonDocument::to_string(&key).unwrap()).unwrap();
+
+    entry_path
+        .with_join_str(".ready")
+        .fs_write([]).unwrap();
+}
+
+#[test]
+fn it_finds_the_highest_cached_version_matching_a_selector() {
+    let home_dir
+        = Path::temp_dir().unwrap();
+
+    std::env::set_var("HOME", home_dir.as_str());
+
+    assert_eq!(cache::find_cached_version(|_| true).unwrap(), None);
+
+    add_cache_entry("1.22.19", get_system_string());
+    add_cache_entry("4.1.0", get_system_string());
+    add_cache_entry("4.2.0-rc.1", get_system_string());
+    add_cache_entry("4.9.0", "some-other-platform");
+
+    let find = |release_line: ReleaseLine, canary: bool| {
+        let channel_selector = match canary {
+            true => release_line.canary(),
+            false => release_line.stable(),
+        };
+
+        cache::find_cached_version(|version| channel_selector.contains(version)).unwrap()
+            .map(|version| version.to_file_string())
+    };
+
+    assert_eq!(find(ReleaseLine::Classic, false), Some("1.22.19".to_string()));
+    assert_eq!(find(ReleaseLine::Berry, false), Some("4.1.0".to_string()));
+    assert_eq!(find(ReleaseLine::Berry, true), Some("4.2.0-rc.1".to_string()));
+    assert_eq!(find(ReleaseLine::Zpm, true), None);
+}
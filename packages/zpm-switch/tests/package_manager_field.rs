@@ -0,0 +1,54 @@
+use zpm_switch::{PackageManagerField, PackageManagerHash, PackageManagerReference};
+use zpm_utils::{FromFileString, ToFileString};
+
+fn parse_hash(field: &str) -> Option<PackageManagerHash> {
+    let reference = PackageManagerField::from_file_string(field).unwrap()
+        .into_reference("yarn").unwrap();
+
+    let PackageManagerReference::Version(params) = reference else {
+        panic!("Expected {field} to reference a version");
+    };
+
+    params.hash
+}
+
+#[test]
+fn it_parses_fields_without_hash() {
+    assert_eq!(parse_hash("yarn@4.5.0"), None);
+    assert_eq!(parse_hash("yarn@6.0.0-rc.13"), None);
+}
+
+#[test]
+fn it_preserves_the_corepack_hash() {
+    let field_str
+        = "yarn@4.5.0+sha224.ca91e3f5c6e6f1e0e3a2c4bd9d1a3b1c0c1e95b0a1a4ff2d1b24a5cf";
+
+    let hash
+        = parse_hash(field_str).unwrap();
+
+    assert_eq!(hash.algorithm, "sha224");
+    assert_eq!(hash.digest, "ca91e3f5c6e6f1e0e3a2c4bd9d1a3b1c0c1e95b0a1a4ff2d1b24a5cf");
+
+    assert_eq!(PackageManagerField::from_file_string(field_str).unwrap().to_file_string(), field_str);
+}
+
+#[test]
+fn it_accepts_unknown_hash_algorithms() {
+    let hash
+        = parse_hash("yarn@4.5.0+sha3.0123abcd").unwrap();
+
+    assert_eq!(hash.algorithm, "sha3");
+    assert_eq!(hash.compute(b"yarn"), None);
+}
+
+#[test]
+fn it_computes_the_supported_hashes() {
+    let compute = |algorithm: &str| PackageManagerHash {algorithm: algorithm.to_string(), digest: String::new()}
+        .compute(b"abc")
+        .unwrap();
+
+    assert_eq!(compute("sha1"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(compute("sha224"), "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7");
+    assert_eq!(compute("sha256"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(&compute("sha512")[..32], "ddaf35a193617abacc417349ae204131");
+}
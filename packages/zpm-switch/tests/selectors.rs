@@ -0,0 +1,89 @@
+use zpm_semver::Version;
+use zpm_switch::{Error, Selector, YarnDistribution};
+use zpm_utils::FromFileString;
+
+const FRAGMENTS: &[&str] = &[
+    "", "0", "1", "2", "4", "6", "99999999999", ".", "-", "+", "x", "*", "^", "~", ">=", "<", " ", "||",
+    "rc", "rc.36", "alpha", "canary", "stable", "classic", "berry", "zpm", "default", "latest", "-0", "é",
+];
+
+/// A small xorshift generator, so that the inputs are arbitrary yet the same
+/// on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 as usize
+    }
+
+    fn selector(&mut self) -> String {
+        let len
+            = self.next() % 8;
+
+        (0..len)
+            .map(|_| FRAGMENTS[self.next() % FRAGMENTS.len()])
+            .collect()
+    }
+}
+
+#[test]
+fn it_never_panics_on_arbitrary_selectors() {
+    let mut rng
+        = Rng(0x2545F4914F6CDD1D);
+
+    for _ in 0..20_000 {
+        let selector_str
+            = rng.selector();
+
+        match Selector::from_file_string(&selector_str) {
+            Ok(Selector::Version(params)) => {
+                YarnDistribution::from_version(&params.version)
+                    .unwrap_or_else(|err| panic!("Expected {selector_str} to be installable, got {err}"));
+            },
+
+            Ok(Selector::Range(params)) => {
+                params.range.check(&Version::new_from_components(4, 0, 0, None));
+            },
+
+            Ok(Selector::Channel(_)) => {},
+
+            Err(Error::UnsupportedVersionSelector(value)) => {
+                assert_eq!(value, selector_str);
+            },
+
+            Err(err) => {
+                panic!("Unexpected error for {selector_str}: {err}");
+            },
+        }
+    }
+}
+
+#[test]
+fn it_supports_prerelease_versions() {
+    let cases = [
+        ("4.0.0-rc.36", YarnDistribution::Bundle),
+        ("2.0.0-rc.1", YarnDistribution::Bundle),
+        ("6.0.0-rc.13", YarnDistribution::Native),
+        ("7.1.0-canary.2", YarnDistribution::Native),
+        ("1.22.0-0", YarnDistribution::Package),
+    ];
+
+    for (version, expected) in cases {
+        let version
+            = Version::from_file_string(version).unwrap();
+
+        assert_eq!(YarnDistribution::from_version(&version).unwrap(), expected);
+    }
+}
+
+#[test]
+fn it_reports_unsupported_selectors() {
+    let err
+        = Selector::from_file_string("berry-nightly").unwrap_err();
+
+    assert!(matches!(&err, Error::UnsupportedVersionSelector(value) if value == "berry-nightly"));
+    assert!(err.to_string().contains("stable"));
+}
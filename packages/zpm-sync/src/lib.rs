@@ -1,20 +1,27 @@
-use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
+use std::{borrow::Cow, collections::BTreeMap, sync::{Arc, LazyLock, Mutex}};
 
 use itertools::Itertools;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use zpm_formats::{Entry, iter_ext::IterExt};
 use zpm_utils::{IoResultExt, Path, PathError, Serialized, ToHumanString};
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum SyncTemplate {
     Zip {
         archive_path: Path,
         inner_path: Path,
     },
+
+    /// Materializes the content of an unpacked folder by cloning its files
+    /// (through reflinks when the filesystem supports them, plain copies
+    /// otherwise) rather than symlinking it.
+    Folder {
+        source_path: Path,
+    },
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum SyncItem<'a> {
     Folder {
@@ -29,6 +36,17 @@ pub enum SyncItem<'a> {
         data: Cow<'a, [u8]>,
         is_exec: bool,
     },
+
+    Clone {
+        source_path: Path,
+    },
+
+    /// Hard links the file from the given source (typically an entry of a
+    /// content-addressable store), falling back to a copy when both paths
+    /// live on different devices.
+    Hardlink {
+        source_path: Path,
+    },
 }
 
 #[derive(thiserror::Error, Clone, Debug)]
@@ -74,6 +92,8 @@ pub enum FileOp {
     CreateFolder(Path),
     CreateSymlink(Path, Path),
     CreateFile(Path, Vec<u8>),
+    CloneFile(Path, Path),
+    HardlinkFile(Path, Path),
 }
 
 impl std::fmt::Display for FileOp {
@@ -83,11 +103,96 @@ impl std::fmt::Display for FileOp {
             FileOp::CreateFolder(path) => write!(f, "create folder: {}", path.to_print_string()),
             FileOp::CreateSymlink(path, target_path) => write!(f, "create symlink: {} -> {}", path.to_print_string(), target_path.to_print_string()),
             FileOp::CreateFile(path, data) => write!(f, "create file: {} (starting with {})", path.to_print_string(), Serialized::new(String::from_utf8_lossy(data)).to_print_string()),
+            FileOp::CloneFile(path, source_path) => write!(f, "clone file: {} (from {})", path.to_print_string(), source_path.to_print_string()),
+            FileOp::HardlinkFile(path, source_path) => write!(f, "hardlink file: {} (from {})", path.to_print_string(), source_path.to_print_string()),
+        }
+    }
+}
+
+/// Whether reflinks are known to work for a given sync root; we only pay for
+/// a failed clone attempt once per root, then go straight to plain copies.
+static REFLINK_SUPPORT: LazyLock<Mutex<BTreeMap<Path, bool>>>
+    = LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+fn clone_file(root_path: &Path, source_path: &Path, path: &Path) -> Result<(), SyncError> {
+    let is_reflink_supported = REFLINK_SUPPORT.lock().unwrap()
+        .get(root_path)
+        .copied();
+
+    if is_reflink_supported != Some(false) {
+        if source_path.fs_reflink(path).is_ok() {
+            if is_reflink_supported.is_none() {
+                REFLINK_SUPPORT.lock().unwrap().insert(root_path.clone(), true);
+            }
+
+            return Ok(());
+        }
+
+        // Reflinks may still fail for individual files once we know the root
+        // supports them (for example when the source lives on another device),
+        // so we only disable them if the very first attempt fails.
+        if is_reflink_supported.is_none() {
+            REFLINK_SUPPORT.lock().unwrap().insert(root_path.clone(), false);
         }
     }
+
+    source_path.fs_copy_file(path)?;
+
+    Ok(())
+}
+
+fn hardlink_file(source_path: &Path, path: &Path) -> Result<(), SyncError> {
+    if source_path.fs_hard_link(path).is_err() {
+        source_path.fs_copy_file(path)?;
+    }
+
+    Ok(())
 }
 
 impl<'a> SyncTree<'a> {
+    /// Builds a tree cloning every file from the given folder, preserving
+    /// the symlinks it contains.
+    pub fn from_folder(source_path: &Path) -> Result<Self, SyncError> {
+        let mut sync_tree
+            = Self::new();
+
+        let mut queue
+            = vec![Path::new()];
+
+        while let Some(rel_dir) = queue.pop() {
+            let listing = source_path
+                .with_join(&rel_dir)
+                .fs_read_dir()?;
+
+            for entry in listing {
+                let entry
+                    = entry?;
+
+                let rel_path
+                    = rel_dir.with_join_str(entry.file_name().to_string_lossy());
+                let abs_path
+                    = source_path.with_join(&rel_path);
+
+                let file_type
+                    = entry.file_type()?;
+
+                if file_type.is_symlink() {
+                    sync_tree.register_entry(rel_path, SyncItem::Symlink {
+                        target_path: abs_path.fs_read_link()?,
+                    })?;
+                } else if file_type.is_dir() {
+                    queue.push(rel_path);
+                } else {
+                    sync_tree.register_entry(rel_path, SyncItem::Clone {
+                        source_path: abs_path,
+                    })?;
+                }
+            }
+        }
+
+        Ok(sync_tree)
+    }
+
     pub fn from_entries(entries: &[Entry<'a>]) -> Result<Self, SyncError> {
         let mut sync_tree
             = Self::new();
@@ -140,6 +245,39 @@ impl<'a> SyncTree<'a> {
         Ok(())
     }
 
+    /// Leaves the given entry untouched on disk, whatever its content; used
+    /// to skip the subtrees we already know to be up-to-date.
+    pub fn ignore_entry(&mut self, rel_path: Path) -> Result<(), SyncError> {
+        if !rel_path.is_forward() {
+            return Err(SyncError::ForwardPathRequired(rel_path.clone()));
+        }
+
+        let mut segments_it
+            = rel_path.segments();
+
+        let basename
+            = segments_it.next_back()
+                .expect("Expected the entry to have a path");
+
+        let parent_idx
+            = self.ensure_folder(segments_it)?;
+
+        let candidate_idx
+            = self.nodes.len();
+
+        let parent_node
+            = &mut self.nodes[parent_idx];
+
+        let SyncNode::Folder {children, ..} = parent_node else {
+            return Err(SyncError::NotAFolder);
+        };
+
+        children.insert(basename.to_string(), candidate_idx);
+        self.nodes.push(SyncNode::Any);
+
+        Ok(())
+    }
+
     pub fn is_node_filtered_out(&self, node_idx: usize) -> bool {
         let node
             = &self.nodes[node_idx];
@@ -197,15 +335,19 @@ impl<'a> SyncTree<'a> {
     }
 
     pub fn run(&self, root_path: Path) -> Result<Vec<FileOp>, SyncError> {
+        self.run_from(&root_path.clone(), root_path)
+    }
+
+    fn run_from(&self, root_path: &Path, path: Path) -> Result<Vec<FileOp>, SyncError> {
         let mut file_ops
             = Vec::new();
 
         let mut queue
-            = vec![(root_path, 0)];
+            = vec![(path, 0)];
 
         while let Some((path, node_idx)) = queue.pop() {
             let next_tasks
-                = self.process_node(path, node_idx, &mut file_ops)?;
+                = self.process_node(root_path, path, node_idx, &mut file_ops)?;
 
             queue.extend(next_tasks);
         }
@@ -275,7 +417,7 @@ impl<'a> SyncTree<'a> {
                 })
             },
 
-            SyncNode::File {..} => {
+            SyncNode::File {..} | SyncNode::Clone {..} | SyncNode::Hardlink {..} => {
                 Ok(SyncCheck {
                     must_remove: !metadata.is_file(),
                     must_create: !metadata.is_file(),
@@ -299,7 +441,7 @@ impl<'a> SyncTree<'a> {
         }
     }
 
-    fn process_node(&self, path: Path, node_idx: usize, file_ops: &mut Vec<FileOp>) -> Result<Vec<(Path, usize)>, SyncError> {
+    fn process_node(&self, root_path: &Path, path: Path, node_idx: usize, file_ops: &mut Vec<FileOp>) -> Result<Vec<(Path, usize)>, SyncError> {
         if self.is_node_filtered_out(node_idx) {
             return Ok(vec![]);
         }
@@ -334,34 +476,40 @@ impl<'a> SyncTree<'a> {
                 }
 
                 if let Some(template) = &template {
-                    match template {
+                    let zip_buffer;
+                    let zip_entries;
+
+                    let mut template_tree = match template {
                         SyncTemplate::Zip {archive_path, inner_path} => {
-                            let zip_buffer
+                            zip_buffer
                                 = archive_path.fs_read()?;
 
-                            let zip_entries
+                            zip_entries
                                 = zpm_formats::zip::entries_from_zip(&zip_buffer)?
                                     .into_iter()
                                     .strip_path_prefix(inner_path)
                                     .collect_vec();
 
-                            let mut template_tree
-                                = SyncTree::from_entries(&zip_entries)?;
-
-                            template_tree.dry_run = self.dry_run;
+                            SyncTree::from_entries(&zip_entries)?
+                        },
 
-                            // We must instruct the template tree to ignore the entries
-                            // that our side of the tree expects to handle
-                            for segment in children.keys() {
-                                template_tree.ignore_root_entry(segment.clone())?;
-                            }
+                        SyncTemplate::Folder {source_path} => {
+                            SyncTree::from_folder(source_path)?
+                        },
+                    };
 
-                            let inner_file_ops
-                                = template_tree.run(path.clone())?;
+                    template_tree.dry_run = self.dry_run;
 
-                            file_ops.extend(inner_file_ops);
-                        },
+                    // We must instruct the template tree to ignore the entries
+                    // that our side of the tree expects to handle
+                    for segment in children.keys() {
+                        template_tree.ignore_root_entry(segment.clone())?;
                     }
+
+                    let inner_file_ops
+                        = template_tree.run_from(root_path, path.clone())?;
+
+                    file_ops.extend(inner_file_ops);
                 } else {
                     if !check.must_create {
                         let extraneous_entries = path.fs_read_dir()
@@ -418,6 +566,30 @@ impl<'a> SyncTree<'a> {
 
                 Ok(vec![])
             },
+
+            SyncNode::Clone {source_path} => {
+                if check.must_create {
+                    if self.dry_run {
+                        file_ops.push(FileOp::CloneFile(path.clone(), source_path.clone()));
+                    } else {
+                        clone_file(root_path, source_path, &path)?;
+                    }
+                }
+
+                Ok(vec![])
+            },
+
+            SyncNode::Hardlink {source_path} => {
+                if check.must_create {
+                    if self.dry_run {
+                        file_ops.push(FileOp::HardlinkFile(path.clone(), source_path.clone()));
+                    } else {
+                        hardlink_file(source_path, &path)?;
+                    }
+                }
+
+                Ok(vec![])
+            },
         }
     }
 }
@@ -439,6 +611,14 @@ pub enum SyncNode<'a> {
     Symlink {
         target_path: Path,
     },
+
+    Clone {
+        source_path: Path,
+    },
+
+    Hardlink {
+        source_path: Path,
+    },
 }
 
 impl<'a> From<SyncItem<'a>> for SyncNode<'a> {
@@ -457,6 +637,14 @@ impl<'a> From<SyncItem<'a>> for SyncNode<'a> {
             SyncItem::Symlink {target_path} => SyncNode::Symlink {
                 target_path,
             },
+
+            SyncItem::Clone {source_path} => SyncNode::Clone {
+                source_path,
+            },
+
+            SyncItem::Hardlink {source_path} => SyncNode::Hardlink {
+                source_path,
+            },
         }
     }
 }
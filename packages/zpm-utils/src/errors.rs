@@ -44,6 +44,9 @@ pub enum PathError {
 
     #[error("Invalid explicit path parameter: {0}")]
     InvalidExplicitPathParameter(String),
+
+    #[error("Path goes above the filesystem root: {0}")]
+    EscapesRoot(String),
 }
 
 impl PathError {
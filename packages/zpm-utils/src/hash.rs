@@ -1,12 +1,16 @@
-use std::hash::Hash;
+use std::{hash::Hash, io::{Read, Write}};
 
 use blake2::{Blake2b, Digest, digest::consts::U64};
 use rkyv::Archive;
 
-use crate::{impl_file_string_from_str, impl_file_string_serialization, DataType, FromFileString, ToFileString, ToHumanString};
+use crate::{impl_file_string_from_str, impl_file_string_serialization, DataType, FromFileString, Path, PathError, ToFileString, ToHumanString};
 
 pub type Blake2b80 = Blake2b<U64>;
 
+/// Size of the chunks read when hashing a stream, so that hashing large files
+/// (typically cache archives) doesn't require holding them in memory.
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[rkyv(derive(PartialEq, Eq, Hash, PartialOrd, Ord))]
 pub struct Hash64 {
@@ -21,6 +25,31 @@ impl Hash64 {
         Hash64 {state: hasher.finalize().to_vec()}
     }
 
+    pub fn from_reader<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut hasher = Blake2b80::new();
+        let mut buffer = vec![0; HASH_CHUNK_SIZE];
+
+        loop {
+            let read_size = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read_size) => read_size,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            hasher.update(&buffer[..read_size]);
+        }
+
+        Ok(Hash64 {state: hasher.finalize().to_vec()})
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, PathError> {
+        let file
+            = std::fs::File::open(path.to_path_buf())?;
+
+        Ok(Self::from_reader(file)?)
+    }
+
     pub fn from_string<T: ToFileString>(str: &T) -> Self {
         let mut hasher = Blake2b80::new();
         hasher.update(str.to_file_string().as_bytes());
@@ -54,6 +83,34 @@ impl<'a, I: Iterator<Item = &'a Hash64>> CollectHash for I {
     }
 }
 
+/// Hashes the data written into it, so that content generated in a streaming
+/// fashion (typically archives) can be hashed without being buffered first.
+#[derive(Default)]
+pub struct Hash64Writer {
+    hasher: Blake2b80,
+}
+
+impl Hash64Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn finish(self) -> Hash64 {
+        Hash64 {state: self.hasher.finalize().to_vec()}
+    }
+}
+
+impl Write for Hash64Writer {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.hasher.update(buffer);
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 impl FromFileString for Hash64 {
     type Error = hex::FromHexError;
 
@@ -105,6 +162,34 @@ impl Sha1 {
     }
 }
 
+pub struct Sha224 {
+    data: Vec<u8>,
+}
+
+impl Sha224 {
+    pub fn new(data: &[u8]) -> Self {
+        let mut hasher
+            = sha2::Sha224::new();
+
+        hasher.update(data);
+
+        let data
+            = hasher.finalize().to_vec();
+
+        Self {
+            data,
+        }
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.data)
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &self.data)
+    }
+}
+
 pub struct Sha256 {
     data: Vec<u8>,
 }
@@ -160,3 +245,65 @@ impl Sha512 {
         base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &self.data)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Produces `remaining` bytes without ever storing them, while keeping
+    /// track of the largest read requested by the consumer.
+    struct SyntheticReader {
+        remaining: usize,
+        largest_read: usize,
+    }
+
+    impl Read for SyntheticReader {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            self.largest_read = self.largest_read.max(buffer.len());
+
+            let read_size
+                = buffer.len().min(self.remaining);
+
+            buffer[..read_size].fill(42);
+            self.remaining -= read_size;
+
+            Ok(read_size)
+        }
+    }
+
+    #[test]
+    fn test_from_reader_matches_from_data() {
+        let data
+            = (0..HASH_CHUNK_SIZE * 3 + 17).map(|index| index as u8).collect::<Vec<_>>();
+
+        assert_eq!(Hash64::from_reader(data.as_slice()).unwrap(), Hash64::from_data(&data));
+    }
+
+    #[test]
+    fn test_writer_matches_from_data() {
+        let data
+            = (0..HASH_CHUNK_SIZE + 17).map(|index| index as u8).collect::<Vec<_>>();
+
+        let mut writer
+            = Hash64Writer::new();
+
+        for chunk in data.chunks(1000) {
+            writer.write_all(chunk).unwrap();
+        }
+
+        assert_eq!(writer.finish(), Hash64::from_data(&data));
+    }
+
+    #[test]
+    fn test_from_reader_streams_large_inputs() {
+        let mut reader = SyntheticReader {
+            remaining: 64 * 1024 * 1024,
+            largest_read: 0,
+        };
+
+        Hash64::from_reader(&mut reader).unwrap();
+
+        assert_eq!(reader.remaining, 0);
+        assert!(reader.largest_read <= HASH_CHUNK_SIZE);
+    }
+}
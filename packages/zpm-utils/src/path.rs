@@ -2,7 +2,7 @@ use std::{collections::BTreeMap, io::{Read, Write}, os::unix::ffi::OsStrExt, str
 
 use rkyv::Archive;
 
-use crate::{diff_data, impl_file_string_from_str, impl_file_string_serialization, path_resolve::resolve_path, DataType, FromFileString, IoResultExt, PathError, PathIterator, ToFileString, ToHumanString};
+use crate::{diff_data, impl_file_string_from_str, impl_file_string_serialization, path_resolve::{resolve_path, try_resolve_path}, DataType, FromFileString, IoResultExt, PathError, PathIterator, ToFileString, ToHumanString};
 
 #[derive(Debug)]
 pub struct SyncEntry {
@@ -542,6 +542,71 @@ impl Path {
         Ok(self)
     }
 
+    /**
+     * Write a file by first writing the data into a temporary file in the
+     * same directory, then renaming it over the destination. Readers and
+     * crashes will thus only ever observe either the old or the new content.
+     *
+     * The permissions of the existing file, if any, are preserved.
+     */
+    pub fn fs_write_atomic<T: AsRef<[u8]>>(&self, data: T) -> Result<&Self, PathError> {
+        static COUNTER: std::sync::atomic::AtomicUsize
+            = std::sync::atomic::AtomicUsize::new(0);
+
+        let temp_name = format!(".{}.{}-{}.tmp",
+            self.basename().unwrap_or("file"),
+            std::process::id(),
+            COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        );
+
+        let temp_path = self.dirname()
+            .unwrap_or_default()
+            .with_join_str(&temp_name);
+
+        let write_result = (|| -> Result<(), PathError> {
+            let mut file
+                = std::fs::File::create(temp_path.to_path_buf())?;
+
+            file.write_all(data.as_ref())?;
+
+            if let Some(metadata) = self.fs_metadata().ok_missing()? {
+                file.set_permissions(metadata.permissions())?;
+            }
+
+            file.sync_all()?;
+
+            // On Windows the rename replaces the destination as well, but
+            // may transiently fail while another process holds it open.
+            let mut attempts = 0;
+            loop {
+                match std::fs::rename(temp_path.to_path_buf(), self.to_path_buf()) {
+                    Err(err) if cfg!(windows) && err.kind() == std::io::ErrorKind::PermissionDenied && attempts < 10 => {
+                        attempts += 1;
+                        std::thread::sleep(std::time::Duration::from_millis(10 * attempts));
+                    },
+
+                    result => break result?,
+                }
+            }
+
+            // The rename itself must reach the disk too, which requires
+            // syncing the parent directory on Unix systems.
+            #[cfg(unix)]
+            if let Some(dirname) = self.dirname() {
+                std::fs::File::open(dirname.to_path_buf())?.sync_all()?;
+            }
+
+            Ok(())
+        })();
+
+        if write_result.is_err() {
+            let _ = std::fs::remove_file(temp_path.to_path_buf());
+        }
+
+        write_result?;
+        Ok(self)
+    }
+
     pub fn fs_set_modified(&self, modified: std::time::SystemTime) -> Result<&Self, PathError> {
         let file
             = std::fs::File::open(self.to_path_buf())?;
@@ -698,14 +763,33 @@ impl Path {
     }
 
     pub fn fs_change<T: AsRef<[u8]>>(&self, data: T, is_exec: bool) -> Result<&Self, PathError> {
+        self.fs_change_with(data.as_ref(), is_exec, |path, data| {
+            std::fs::write(path.to_path_buf(), data)?;
+            Ok(())
+        })
+    }
+
+    /**
+     * Same as `fs_change`, but writes the file through `fs_write_atomic`.
+     * Prefer it for files that would be corrupted by a partial write, such
+     * as lockfiles or configuration files.
+     */
+    pub fn fs_change_atomic<T: AsRef<[u8]>>(&self, data: T, is_exec: bool) -> Result<&Self, PathError> {
+        self.fs_change_with(data.as_ref(), is_exec, |path, data| {
+            path.fs_write_atomic(data)?;
+            Ok(())
+        })
+    }
+
+    fn fs_change_with(&self, data: &[u8], is_exec: bool, write: impl FnOnce(&Path, &[u8]) -> Result<(), PathError>) -> Result<&Self, PathError> {
         let path_buf = self.to_path_buf();
 
         let update_content = self.fs_read()
             .ok_missing()
-            .map(|current| current.map(|current| current.ne(data.as_ref())).unwrap_or(true))?;
+            .map(|current| current.map(|current| current.ne(data)).unwrap_or(true))?;
 
         if update_content {
-            std::fs::write(&path_buf, data)?;
+            write(self, data)?;
         }
 
         #[cfg(unix)]
@@ -748,6 +832,30 @@ impl Path {
         Ok(self)
     }
 
+    /**
+     * Clone a file using a copy-on-write reflink (`FICLONE` on Linux,
+     * `clonefile` on macOS), sharing the underlying blocks until either side
+     * is modified. The destination must not exist.
+     *
+     * Fails with `ErrorKind::Unsupported` on other platforms; on filesystems
+     * without reflink support the OS error is returned as-is, so callers are
+     * expected to fall back to `fs_copy_file`.
+     */
+    pub fn fs_reflink(&self, new_path: &Path) -> Result<&Self, PathError> {
+        reflink_file(&self.to_path_buf(), &new_path.to_path_buf())?;
+        Ok(self)
+    }
+
+    /**
+     * Create a hard link at `new_path` pointing to the same inode as this
+     * file. Fails (typically with `ErrorKind::CrossesDevices`) when both
+     * paths don't live on the same filesystem.
+     */
+    pub fn fs_hard_link(&self, new_path: &Path) -> Result<&Self, PathError> {
+        std::fs::hard_link(self.to_path_buf(), new_path.to_path_buf())?;
+        Ok(self)
+    }
+
     pub fn fs_copy(&self, new_path: &Path) -> Result<&Self, PathError> {
         match self.fs_is_dir() {
             true => {
@@ -769,6 +877,39 @@ impl Path {
         Ok(self)
     }
 
+    /**
+     * Recursively copy a directory to a new location, preserving the modes of
+     * the files and folders it contains. Symlinks are either followed (their
+     * target being copied in their place) or recreated with the same target,
+     * depending on `follow_symlinks`.
+     *
+     * This is meant as a fallback for when neither symlinks, hardlinks, nor
+     * reflinks can be used (for example across devices).
+     */
+    pub fn fs_copy_dir(&self, new_path: &Path, follow_symlinks: bool) -> Result<&Self, PathError> {
+        new_path.fs_create_dir_all()?;
+
+        for entry in self.fs_read_dir()? {
+            let entry = entry?;
+            let entry_path = Path::try_from(entry.path())?;
+
+            let destination_path = new_path.with_join(&Path::try_from(entry.file_name())?);
+
+            if entry.file_type()?.is_symlink() && !follow_symlinks {
+                destination_path.fs_symlink(&entry_path.fs_read_link()?)?;
+            } else if entry_path.fs_is_dir() {
+                entry_path.fs_copy_dir(&destination_path, follow_symlinks)?;
+            } else {
+                entry_path.fs_copy_file(&destination_path)?;
+            }
+        }
+
+        // Applied last so that read-only folders can still be filled
+        new_path.fs_set_permissions(self.fs_metadata()?.permissions())?;
+
+        Ok(self)
+    }
+
     /**
      * Move a file or directory to a new location, copying it if the source and
      * destination are on different devices.
@@ -781,7 +922,11 @@ impl Path {
         match std::fs::rename(self.to_path_buf(), new_path.to_path_buf()) {
             Ok(_) => Ok(self),
             Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
-                self.fs_copy(new_path)?;
+                match self.fs_is_real_dir() {
+                    true => self.fs_copy_dir(new_path, false)?,
+                    false => self.fs_copy(new_path)?,
+                };
+
                 self.fs_rm()
             },
             Err(err) => Err(err.into()),
@@ -897,7 +1042,7 @@ impl Path {
                     self.path.push('/');
                 }
                 self.path.push_str(&other.path);
-                self.normalize();
+                self.path = resolve_path(&self.path);
             }
         }
 
@@ -996,8 +1141,20 @@ impl Path {
         }
     }
 
-    fn normalize(&mut self) {
-        self.path = resolve_path(&self.path);
+    /// Lexically resolves the `.` and `..` segments of the path, without
+    /// accessing the filesystem; unlike `fs_canonicalize`, the path doesn't
+    /// have to exist and symlinks are left untouched. Relative paths stay
+    /// relative (keeping their leading `..` segments); absolute paths going
+    /// above the root are rejected when parsed, so they never get here.
+    ///
+    /// ```
+    /// use zpm_utils::p;
+    ///
+    /// assert_eq!(p!("/a/./b/../c").normalize().as_str(), "/a/c");
+    /// assert_eq!(p!("../a/b/..").normalize().as_str(), "../a");
+    /// ```
+    pub fn normalize(&self) -> Path {
+        Path {path: resolve_path(&self.path)}
     }
 }
 
@@ -1043,7 +1200,10 @@ impl FromFileString for Path {
     type Error = PathError;
 
     fn from_file_string(s: &str) -> Result<Self, Self::Error> {
-        Ok(Path {path: resolve_path(s)})
+        let path = try_resolve_path(s)
+            .ok_or_else(|| PathError::EscapesRoot(s.to_string()))?;
+
+        Ok(Path {path})
     }
 }
 
@@ -1061,3 +1221,198 @@ impl ToHumanString for Path {
 
 impl_file_string_from_str!(Path);
 impl_file_string_serialization!(Path);
+
+#[cfg(target_os = "linux")]
+fn reflink_file(source: &std::path::Path, destination: &std::path::Path) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // _IOW(0x94, 9, int), from linux/fs.h
+    const FICLONE: u32 = 0x40049409;
+
+    let source_file
+        = std::fs::File::open(source)?;
+    let destination_file
+        = std::fs::OpenOptions::new().write(true).create_new(true).open(destination)?;
+
+    let result = unsafe {
+        libc::ioctl(destination_file.as_raw_fd(), FICLONE as _, source_file.as_raw_fd())
+    };
+
+    if result != 0 {
+        let error
+            = std::io::Error::last_os_error();
+
+        drop(destination_file);
+        let _ = std::fs::remove_file(destination);
+
+        return Err(error);
+    }
+
+    destination_file.set_permissions(source_file.metadata()?.permissions())?;
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn reflink_file(source: &std::path::Path, destination: &std::path::Path) -> std::io::Result<()> {
+    let source_c
+        = std::ffi::CString::new(source.as_os_str().as_bytes())?;
+    let destination_c
+        = std::ffi::CString::new(destination.as_os_str().as_bytes())?;
+
+    let result = unsafe {
+        libc::clonefile(source_c.as_ptr(), destination_c.as_ptr(), 0)
+    };
+
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink_file(_source: &std::path::Path, _destination: &std::path::Path) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_resolves_dot_segments() {
+        assert_eq!(p!("a/./b/../c").normalize().as_str(), "a/c");
+        assert_eq!(p!("/a/./b/../c/").normalize().as_str(), "/a/c/");
+        assert_eq!(p!("a//b/.").normalize().as_str(), "a/b");
+        assert_eq!(p!("a/..").normalize().as_str(), "");
+        assert_eq!(p!("/a/..").normalize().as_str(), "/");
+    }
+
+    #[test]
+    fn test_normalize_preserves_leading_parent_segments() {
+        assert_eq!(p!("../x").normalize().as_str(), "../x");
+        assert_eq!(p!("./../../x/y/..").normalize().as_str(), "../../x");
+        assert_eq!(p!("a/../../x").normalize().as_str(), "../x");
+    }
+
+    #[test]
+    fn test_parse_rejects_absolute_paths_escaping_the_root() {
+        assert!(matches!(Path::from_str("/.."), Err(PathError::EscapesRoot(_))));
+        assert!(matches!(Path::from_str("/a/../../b"), Err(PathError::EscapesRoot(_))));
+        assert!(matches!(Path::try_from(std::path::Path::new("/a/../..")), Err(PathError::EscapesRoot(_))));
+
+        assert_eq!(Path::from_str("/a/../b").unwrap().as_str(), "/b");
+    }
+
+    #[test]
+    fn test_fs_write_atomic_replaces_the_file() {
+        let temp_dir
+            = Path::temp_dir().unwrap();
+        let file_path
+            = temp_dir.with_join_str("file.txt");
+
+        file_path.fs_write_text("old").unwrap();
+        file_path.fs_write_atomic("new").unwrap();
+
+        assert_eq!(file_path.fs_read_text().unwrap(), "new");
+
+        // The temporary file must have been renamed into place
+        let entries = temp_dir.fs_read_dir().unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(entries, vec!["file.txt".to_string()]);
+    }
+
+    #[test]
+    fn test_fs_write_atomic_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir
+            = Path::temp_dir().unwrap();
+        let file_path
+            = temp_dir.with_join_str("file.txt");
+
+        file_path.fs_write_text("old").unwrap();
+        std::fs::set_permissions(file_path.to_path_buf(), std::fs::Permissions::from_mode(0o600)).unwrap();
+
+        file_path.fs_write_atomic("new").unwrap();
+
+        assert_eq!(file_path.fs_metadata().unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_fs_hard_link_shares_inode() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir
+            = Path::temp_dir().unwrap();
+        let source_path
+            = temp_dir.with_join_str("source.txt");
+        let destination_path
+            = temp_dir.with_join_str("destination.txt");
+
+        source_path.fs_write_text("hello").unwrap();
+        source_path.fs_hard_link(&destination_path).unwrap();
+
+        assert_eq!(destination_path.fs_read_text().unwrap(), "hello");
+        assert_eq!(source_path.fs_metadata().unwrap().ino(), destination_path.fs_metadata().unwrap().ino());
+    }
+
+    #[test]
+    fn test_fs_copy_dir_copies_the_tree() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir
+            = Path::temp_dir().unwrap();
+        let source_path
+            = temp_dir.with_join_str("source");
+        let executable_path
+            = source_path.with_join_str("nested/bin.sh");
+
+        executable_path.fs_create_parent().unwrap();
+        executable_path.fs_write_text("#!/bin/sh\n").unwrap();
+        executable_path.fs_set_permissions(std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        source_path.with_join_str("link.sh")
+            .fs_symlink(&Path::from_str("nested/bin.sh").unwrap()).unwrap();
+
+        let reproduced_path
+            = temp_dir.with_join_str("reproduced");
+
+        source_path.fs_copy_dir(&reproduced_path, false).unwrap();
+
+        assert_eq!(reproduced_path.with_join_str("nested/bin.sh").fs_read_text().unwrap(), "#!/bin/sh\n");
+        assert_eq!(reproduced_path.with_join_str("nested/bin.sh").fs_metadata().unwrap().permissions().mode() & 0o777, 0o755);
+        assert_eq!(reproduced_path.with_join_str("link.sh").fs_read_link().unwrap().as_str(), "nested/bin.sh");
+
+        let followed_path
+            = temp_dir.with_join_str("followed");
+
+        source_path.fs_copy_dir(&followed_path, true).unwrap();
+
+        assert!(!followed_path.with_join_str("link.sh").fs_is_symlink());
+        assert_eq!(followed_path.with_join_str("link.sh").fs_read_text().unwrap(), "#!/bin/sh\n");
+        assert_eq!(followed_path.with_join_str("link.sh").fs_metadata().unwrap().permissions().mode() & 0o777, 0o755);
+    }
+
+    #[test]
+    fn test_fs_reflink_clones_or_leaves_nothing_behind() {
+        let temp_dir
+            = Path::temp_dir().unwrap();
+        let source_path
+            = temp_dir.with_join_str("source.txt");
+        let destination_path
+            = temp_dir.with_join_str("destination.txt");
+
+        source_path.fs_write_text("hello").unwrap();
+
+        // Whether reflinks are supported depends on the filesystem running
+        // the tests; either way we must never end up with a partial file
+        match source_path.fs_reflink(&destination_path) {
+            Ok(_) => assert_eq!(destination_path.fs_read_text().unwrap(), "hello"),
+            Err(_) => assert!(!destination_path.fs_exists()),
+        }
+    }
+}
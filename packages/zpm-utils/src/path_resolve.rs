@@ -1,6 +1,17 @@
 pub fn resolve_path(input: &str) -> String {
+    resolve_path_impl(input, false)
+        .expect("Expected lenient resolution to always succeed")
+}
+
+/// Same as `resolve_path`, but returns `None` when the `..` segments of an
+/// absolute path go above the root (rather than stopping at the root).
+pub fn try_resolve_path(input: &str) -> Option<String> {
+    resolve_path_impl(input, true)
+}
+
+fn resolve_path_impl(input: &str, is_strict: bool) -> Option<String> {
     if input.is_empty() {
-        return "".to_string();
+        return Some("".to_string());
     }
 
     let mut path = Vec::new();
@@ -9,7 +20,9 @@ pub fn resolve_path(input: &str) -> String {
             ".." => {
                 let last = path.last();
                 if last == Some(&"") {
-                    // Do nothing
+                    if is_strict {
+                        return None;
+                    }
                 } else if last != None && last != Some(&"..") {
                     path.pop();
                 } else {
@@ -33,8 +46,8 @@ pub fn resolve_path(input: &str) -> String {
     }
 
     if path == vec![""] {
-        return "/".to_string();
+        return Some("/".to_string());
     } else {
-        format!("{}", path.join("/"))
+        Some(format!("{}", path.join("/")))
     }
 }
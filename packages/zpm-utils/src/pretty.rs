@@ -1,4 +1,4 @@
-use std::{fmt::Display, ops::{DivAssign, Rem}, sync::atomic::{AtomicBool, Ordering}};
+use std::{fmt::{self, Display}, ops::{DivAssign, Rem}, sync::atomic::{AtomicBool, Ordering}};
 
 use num::NumCast;
 use serde::{Deserialize, Deserializer, Serialize};
@@ -65,7 +65,7 @@ impl<T> Unit<T> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Secret<T> {
     pub value: T,
 }
@@ -82,6 +82,23 @@ impl<T> Secret<T> {
     }
 }
 
+// Unlike the file and human representations, the Debug and Display outputs
+// are always redacted (even when `set_redacted(false)` was called), since
+// they typically end up in logs and error messages rather than in outputs
+// the user explicitly asked for.
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+impl<T> Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
 impl<T: FromFileString> FromFileString for Secret<T> {
     type Error = <T as FromFileString>::Error;
 
@@ -273,3 +290,35 @@ impl ToHumanString for RawString {
         self.value.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::AbstractValue;
+
+    use super::*;
+
+    #[test]
+    fn test_secret_formatting_never_contains_the_token() {
+        let token
+            = "npm_SuperSecretToken1234".to_string();
+        let secret
+            = Secret::new(token.clone());
+
+        assert_eq!(format!("{:?}", secret), "Secret(<redacted>)");
+        assert_eq!(format!("{}", secret), "<redacted>");
+
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct Registry {
+            auth_token: Secret<String>,
+        }
+
+        let registry_debug
+            = format!("{:?}", Registry {auth_token: secret.clone()});
+        let abstract_debug
+            = format!("{:?}", AbstractValue::new(secret));
+
+        assert!(!registry_debug.contains(&token));
+        assert!(!abstract_debug.contains(&token));
+    }
+}
@@ -1,5 +1,24 @@
-use std::process::Command;
+use std::{os::unix::process::ExitStatusExt, process::{Command, ExitStatus, Output}};
 use shlex::{try_quote, QuoteError};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// What happens to the stdout and stderr of a child process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// The output is captured and only made available once the process
+    /// exits.
+    #[default]
+    Capture,
+
+    /// The output is directly written to our own stdout and stderr; nothing
+    /// gets captured.
+    Forward,
+
+    /// The output is written to our own stdout and stderr as it comes, and
+    /// also captured. Both streams are kept in distinct buffers, so the
+    /// stdout of a process can be parsed even if it logs on stderr.
+    ForwardAndCapture,
+}
 
 pub fn to_shell_line(cmd: &Command) -> Result<String, QuoteError> {
     let mut parts: Vec<String> = Vec::new();
@@ -31,3 +50,120 @@ pub fn to_shell_line(cmd: &Command) -> Result<String, QuoteError> {
     // Glue it together
     Ok(format!("({})", parts.join(" ")))
 }
+
+async fn forward_stream<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(reader: Option<R>, mut writer: W) -> std::io::Result<Vec<u8>> {
+    let mut captured
+        = Vec::new();
+
+    let Some(mut reader) = reader else {
+        return Ok(captured);
+    };
+
+    let mut buffer
+        = [0u8; 8192];
+
+    // Once the consumer closes our own stream (for instance when piping into
+    // `head`), we stop forwarding but keep draining the child's output; that
+    // way it doesn't block on a full pipe, and its exit status is still what
+    // we report.
+    let mut is_forwarding
+        = true;
+
+    loop {
+        let size
+            = reader.read(&mut buffer).await?;
+
+        if size == 0 {
+            break;
+        }
+
+        if is_forwarding {
+            let write_result = async {
+                writer.write_all(&buffer[..size]).await?;
+                writer.flush().await
+            }.await;
+
+            if write_result.is_err() {
+                is_forwarding = false;
+            }
+        }
+
+        captured.extend_from_slice(&buffer[..size]);
+    }
+
+    Ok(captured)
+}
+
+/// Waits for a child process spawned with piped stdout and stderr, forwarding
+/// both streams to our own as they're written while also capturing them (see
+/// `OutputMode::ForwardAndCapture`).
+pub async fn wait_with_forwarded_output(mut child: tokio::process::Child) -> std::io::Result<Output> {
+    let stdout
+        = child.stdout.take();
+    let stderr
+        = child.stderr.take();
+
+    let (stdout, stderr, status) = tokio::try_join!(
+        forward_stream(stdout, tokio::io::stdout()),
+        forward_stream(stderr, tokio::io::stderr()),
+        child.wait(),
+    )?;
+
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// The exit code a shell would report for the given status; processes killed
+/// by a signal are reported as `128 + signum`.
+pub fn exit_code_from_status(status: &ExitStatus) -> u8 {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => code as u8,
+        (None, Some(signal)) => (128 + signal) as u8,
+        (None, None) => 1,
+    }
+}
+
+fn forward_signal(child: &tokio::process::Child, signal: libc::c_int) {
+    if let Some(pid) = child.id() {
+        unsafe {
+            libc::kill(pid as libc::pid_t, signal);
+        }
+    }
+}
+
+/// Waits for a child process to exit, relaying the termination signals we
+/// receive in the meantime. We never exit before the child does, so that the
+/// status we report is always its own.
+pub async fn wait_forwarding_signals(child: &mut tokio::process::Child) -> std::io::Result<ExitStatus> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm
+        = signal(SignalKind::terminate())?;
+    let mut sighup
+        = signal(SignalKind::hangup())?;
+    let mut sigint
+        = signal(SignalKind::interrupt())?;
+
+    loop {
+        tokio::select! {
+            status = child.wait() => {
+                return status;
+            },
+
+            Some(()) = sigterm.recv() => {
+                forward_signal(child, libc::SIGTERM);
+            },
+
+            Some(()) = sighup.recv() => {
+                forward_signal(child, libc::SIGHUP);
+            },
+
+            // Interrupts usually come from the terminal, which already sends
+            // them to the whole foreground process group (child included)
+            Some(()) = sigint.recv() => {},
+        }
+    }
+}
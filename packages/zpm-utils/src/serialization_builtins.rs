@@ -14,6 +14,21 @@ impl<'de> serde::Deserialize<'de> for ColoredJsonValue {
     }
 }
 
+impl serde::Serialize for ColoredJsonValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl From<serde_json::Value> for ColoredJsonValue {
+    fn from(value: serde_json::Value) -> Self {
+        ColoredJsonValue(value)
+    }
+}
+
 impl ToHumanString for ColoredJsonValue {
     fn to_print_string(&self) -> String {
         self.0.to_print_string()
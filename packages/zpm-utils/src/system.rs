@@ -222,22 +222,28 @@ impl Requirements {
     }
 
     pub fn validate_system(&self, system: &System) -> bool {
-        let is_arch_valid
-            = self.arch.is_empty() || system.arch.as_ref().map_or(false, |arch| self.arch.contains(&arch));
+        matches_requirements(&self.arch, system.arch.as_ref())
+            && matches_requirements(&self.os, system.os.as_ref())
+            && matches_requirements(&self.libc, system.libc.as_ref())
+    }
+
+    pub fn validate_any(&self, info: &Vec<System>) -> bool {
+        let is_arch_valid = self.arch.is_empty() || info.iter()
+            .any(|system| matches_requirements(&self.arch, system.arch.as_ref()));
 
         if !is_arch_valid {
             return false;
         }
 
-        let is_os_valid
-            = self.os.is_empty() || system.os.as_ref().map_or(false, |os| self.os.contains(&os));
+        let is_os_valid = self.os.is_empty() || info.iter()
+            .any(|system| matches_requirements(&self.os, system.os.as_ref()));
 
         if !is_os_valid {
             return false;
         }
 
-        let is_libc_valid
-            = self.libc.is_empty() || system.libc.as_ref().map_or(false, |libc| self.libc.contains(&libc));
+        let is_libc_valid = self.libc.is_empty() || info.iter()
+            .any(|system| matches_requirements(&self.libc, system.libc.as_ref()));
 
         if !is_libc_valid {
             return false;
@@ -245,29 +251,75 @@ impl Requirements {
 
         true
     }
+}
 
-    pub fn validate_any(&self, info: &Vec<System>) -> bool {
-        let is_arch_valid = self.arch.is_empty() || self.arch.iter()
-            .any(|requirement| info.iter().any(|system| system.arch.as_ref() == Some(requirement)));
-
-        if !is_arch_valid {
-            return false;
+/// Checks a value against the entries of an `os`/`cpu`/`libc` field. Entries
+/// prefixed with `!` exclude the value they name (`"os": ["!win32"]`); the
+/// other ones must include it, if there are any.
+fn matches_requirements<T: ToFileString>(requirements: &[T], value: Option<&T>) -> bool {
+    let value
+        = value.map(|value| value.to_file_string());
+
+    let mut has_inclusions
+        = false;
+    let mut is_included
+        = false;
+
+    for requirement in requirements {
+        let requirement
+            = requirement.to_file_string();
+
+        if let Some(excluded) = requirement.strip_prefix('!') {
+            if value.as_deref() == Some(excluded) {
+                return false;
+            }
+        } else {
+            has_inclusions = true;
+            is_included |= value.as_deref() == Some(requirement.as_str());
         }
+    }
 
-        let is_os_valid = self.os.is_empty() || self.os.iter()
-            .any(|requirement| info.iter().any(|system| system.os.as_ref() == Some(requirement)));
+    !has_inclusions || is_included
+}
 
-        if !is_os_valid {
-            return false;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let is_libc_valid = self.libc.is_empty() || self.libc.iter()
-            .any(|requirement| info.iter().any(|system| system.libc.as_ref() == Some(requirement)));
+    fn requirements(src: &str) -> Requirements {
+        serde_json::from_str(src).unwrap()
+    }
 
-        if !is_libc_valid {
-            return false;
-        }
+    #[test]
+    fn test_validate_system() {
+        let linux_x64
+            = System::new(Some(Cpu::X86_64), Some(Os::Linux), Some(Libc::Glibc));
 
-        true
+        assert!(requirements(r#"{}"#).validate_system(&linux_x64));
+        assert!(requirements(r#"{"os": ["linux", "darwin"]}"#).validate_system(&linux_x64));
+        assert!(!requirements(r#"{"os": ["darwin"], "cpu": ["arm64"]}"#).validate_system(&linux_x64));
+        assert!(!requirements(r#"{"libc": ["musl"]}"#).validate_system(&linux_x64));
+    }
+
+    #[test]
+    fn test_validate_system_with_exclusions() {
+        let linux_x64
+            = System::new(Some(Cpu::X86_64), Some(Os::Linux), Some(Libc::Glibc));
+
+        assert!(requirements(r#"{"os": ["!win32"]}"#).validate_system(&linux_x64));
+        assert!(!requirements(r#"{"os": ["!linux"]}"#).validate_system(&linux_x64));
+        assert!(!requirements(r#"{"cpu": ["x64", "!x64"]}"#).validate_system(&linux_x64));
+    }
+
+    #[test]
+    fn test_validate_any() {
+        let systems = vec![
+            System::new(Some(Cpu::X86_64), Some(Os::Linux), Some(Libc::Glibc)),
+            System::new(Some(Cpu::Aarch64), Some(Os::MacOS), None),
+        ];
+
+        assert!(requirements(r#"{"os": ["darwin"], "cpu": ["arm64"]}"#).validate_any(&systems));
+        assert!(requirements(r#"{"os": ["!win32"]}"#).validate_any(&systems));
+        assert!(!requirements(r#"{"os": ["win32"]}"#).validate_any(&systems));
     }
 }
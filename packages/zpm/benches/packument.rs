@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::json;
+use zpm::{manifest::RemoteManifest, resolvers::npm::RegistryMetadata};
+use zpm_parsers::JsonDocument;
+use zpm_utils::FromFileString;
+
+fn main() {
+    divan::main();
+}
+
+/// Generates a packument shaped like react's, which has well over a
+/// thousand versions (most of them canary and experimental builds).
+fn react_like_packument(abbreviated: bool) -> Vec<u8> {
+    let mut versions
+        = serde_json::Map::new();
+
+    for index in 0..1500u32 {
+        let version = match index % 3 {
+            0 => format!("{}.{}.{}", index / 150, (index / 15) % 10, index % 15),
+            1 => format!("{}.{}.{}-canary-{:08x}-20240101", index / 150, (index / 15) % 10, index % 15, index),
+            _ => format!("0.0.0-experimental-{:08x}-20240101", index),
+        };
+
+        let mut manifest = json!({
+            "name": "react",
+            "version": version,
+            "dependencies": {
+                "loose-envify": "^1.1.0",
+            },
+            "peerDependencies": {},
+            "engines": {
+                "node": ">=0.10.0",
+            },
+            "dist": {
+                "shasum": "0123456789abcdef0123456789abcdef01234567",
+                "tarball": format!("https://registry.npmjs.org/react/-/react-{}.tgz", version),
+                "integrity": "sha512-AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA==",
+            },
+        });
+
+        if !abbreviated {
+            let extra_fields = json!({
+                "description": "React is a JavaScript library for building user interfaces.",
+                "keywords": ["react"],
+                "homepage": "https://react.dev/",
+                "license": "MIT",
+                "main": "index.js",
+                "repository": {"type": "git", "url": "git+https://github.com/facebook/react.git", "directory": "packages/react"},
+                "bugs": {"url": "https://github.com/facebook/react/issues"},
+                "scripts": {},
+                "readme": "# `react`\n\nReact is a JavaScript library for creating user interfaces.\n".repeat(20),
+                "maintainers": [{"name": "fb", "email": "opensource+npm@fb.com"}, {"name": "react-bot", "email": "react-core@meta.com"}],
+                "_npmUser": {"name": "react-bot", "email": "react-core@meta.com"},
+                "_npmVersion": "10.2.4",
+                "_nodeVersion": "20.11.0",
+                "_hasShrinkwrap": false,
+            });
+
+            manifest.as_object_mut().unwrap()
+                .extend(extra_fields.as_object().unwrap().clone());
+        }
+
+        versions.insert(version, manifest);
+    }
+
+    let packument = json!({
+        "name": "react",
+        "dist-tags": {"latest": "9.9.14"},
+        "versions": versions,
+    });
+
+    JsonDocument::to_string(&packument).unwrap().into_bytes()
+}
+
+/// Deserializes every version entry upfront, as a naive resolver would.
+#[divan::bench(sample_count = 10)]
+fn full_packument_eager(bencher: divan::Bencher) {
+    #[derive(Deserialize)]
+    struct EagerMetadata {
+        versions: BTreeMap<zpm_semver::Version, RemoteManifest>,
+    }
+
+    let packument
+        = react_like_packument(false);
+    let range
+        = zpm_semver::Range::from_file_string("^9.0.0").unwrap();
+
+    bencher.bench_local(|| {
+        let metadata: EagerMetadata
+            = JsonDocument::hydrate_from_slice(&packument).unwrap();
+
+        metadata.versions.into_iter()
+            .rev()
+            .find(|(version, _)| range.check(version))
+            .unwrap()
+    });
+}
+
+/// Only reads the version keys, then deserializes the selected entry.
+#[divan::bench(sample_count = 10)]
+fn abbreviated_packument_lazy(bencher: divan::Bencher) {
+    let packument
+        = react_like_packument(true);
+    let range
+        = zpm_semver::Range::from_file_string("^9.0.0").unwrap();
+
+    bencher.bench_local(|| {
+        let metadata: RegistryMetadata
+            = JsonDocument::hydrate_from_slice(&packument).unwrap();
+
+        let (version, manifest) = metadata.versions.iter()
+            .rev()
+            .find(|(version, _)| range.check(version))
+            .unwrap();
+
+        let manifest: RemoteManifest
+            = JsonDocument::hydrate_from_value(manifest).unwrap();
+
+        (version.clone(), manifest)
+    });
+}
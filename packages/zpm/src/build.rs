@@ -1,8 +1,8 @@
 use std::collections::{BTreeMap, BTreeSet};
 
 use zpm_parsers::JsonDocument;
-use zpm_primitives::Locator;
-use zpm_utils::{CollectHash, Hash64, IoResultExt, Path, ToFileString};
+use zpm_primitives::{Ident, Locator};
+use zpm_utils::{CollectHash, DataType, Hash64, IoResultExt, Path, System, ToFileString, ToHumanString};
 use rkyv::Archive;
 use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
 use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize};
@@ -10,9 +10,10 @@ use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize};
 use crate::{
     algos,
     diff_finder::{DiffController, DiffFinder},
-    error::Error,
+    engines,
+    error::{Error, ErrorCode},
     project::Project,
-    report::{with_context_result, ReportContext},
+    report::{current_report, with_context_result, ReportContext},
     script::{ScriptEnvironment, ScriptResult},
 };
 
@@ -48,6 +49,27 @@ impl DiffController for ArtifactFinder {
     }
 }
 
+/// How many lines of output are printed when a build fails; the full output
+/// is kept in the build log.
+const BUILD_LOG_TAIL_LINES: usize = 20;
+
+impl Command {
+    fn to_command_line(&self) -> String {
+        match self {
+            Command::Program {name, args} => {
+                std::iter::once(name.as_str())
+                    .chain(args.iter().map(|arg| arg.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            },
+
+            Command::Script {script, ..} => {
+                script.clone()
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BuildRequest {
     pub cwd: Path,
@@ -89,6 +111,14 @@ impl BuildRequest {
                 artifact_finder.rsync()?;
             }
 
+            // Each package gets its own log so that concurrent builds don't
+            // interleave their output; only its tail is printed on failure.
+            let log_path = project.build_logs_path()
+                .with_join_str(format!("{}.log", self.locator.slug()));
+
+            let mut log_content
+                = String::new();
+
             for command in self.commands.iter() {
                 let script_result = match command {
                     Command::Program {name, args} => {
@@ -98,21 +128,45 @@ impl BuildRequest {
                     Command::Script {event, script} => {
                         if let Some(event) = event {
                             script_env = script_env
-                                .with_env_variable("npm_lifecycle_event", event);
+                                .with_lifecycle_event(event, script);
                         }
 
                         script_env.run_script(script, Vec::<&str>::new()).await?
                     },
                 };
 
-                if !script_result.success() {
+                let success
+                    = script_result.success();
+
+                let output
+                    = script_result.output();
+
+                let stdout
+                    = String::from_utf8_lossy(&output.stdout);
+                let stderr
+                    = String::from_utf8_lossy(&output.stderr);
+
+                log_content.push_str(&format!("=== COMMAND ===\n\n{}\n\n=== STDOUT ===\n\n{}\n=== STDERR ===\n\n{}\n", command.to_command_line(), stdout, stderr));
+
+                log_path
+                    .fs_create_parent()?
+                    .fs_write_text(&log_content)?;
+
+                if !success {
                     return match self.allowed_to_fail {
                         true => {
                             Ok(ScriptResult::new_success())
                         },
 
                         false => {
-                            Err(script_result.ok().unwrap_err())
+                            let output_lines = stdout.lines()
+                                .chain(stderr.lines())
+                                .collect::<Vec<_>>();
+
+                            let tail = output_lines[output_lines.len().saturating_sub(BUILD_LOG_TAIL_LINES)..]
+                                .join("\n");
+
+                            Err(Error::BuildScriptFailed(self.locator.clone(), tail, log_path))
                         },
                     };
                 }
@@ -212,16 +266,31 @@ impl BuildState {
 pub struct BuildRequests {
     pub entries: Vec<BuildRequest>,
     pub dependencies: BTreeMap<usize, BTreeSet<usize>>,
+    pub skipped_builds: BTreeSet<Locator>,
 }
 
 pub struct Build {
     pub build_errors: BTreeSet<(Locator, Path)>,
 }
 
+/// Why a build request did or didn't run; reported at the end of the build
+/// so users can tell why a native module was (or wasn't) rebuilt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildDecision {
+    Cached,
+    FirstBuild,
+    InputsChanged,
+    Forced,
+}
+
 pub struct BuildManager<'a> {
     pub requests: BuildRequests,
     pub dependents: BTreeMap<usize, BTreeSet<usize>>,
     pub tree_hashes: BTreeMap<Locator, Hash64>,
+    pub checksums: BTreeMap<Locator, Hash64>,
+    pub environment_hash: Hash64,
+    pub decisions: BTreeMap<(Locator, Path), BuildDecision>,
+    pub selection: Option<BTreeSet<Ident>>,
     pub queued: Vec<usize>,
     pub running: FuturesUnordered<BoxFuture<'a, (usize, Hash64, Result<ScriptResult, Error>)>>,
     pub build_errors: BTreeSet<(Locator, Path)>,
@@ -256,6 +325,10 @@ impl<'a> BuildManager<'a> {
             requests,
             dependents,
             tree_hashes: BTreeMap::new(),
+            checksums: BTreeMap::new(),
+            environment_hash: Hash64::from_string(&""),
+            decisions: BTreeMap::new(),
+            selection: None,
             queued: Vec::new(),
             running: FuturesUnordered::new(),
             build_errors: BTreeSet::new(),
@@ -263,6 +336,26 @@ impl<'a> BuildManager<'a> {
         }
     }
 
+    /// The lockfile checksums of the packages; they're part of the build
+    /// inputs so that a package whose content changed gets rebuilt even if
+    /// its locator didn't.
+    pub fn with_checksums(mut self, checksums: BTreeMap<Locator, Hash64>) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Restrict the builds to the packages with the given idents (all their
+    /// installed copies); the other packages keep their previous build state.
+    pub fn with_selection(mut self, selection: Option<BTreeSet<Ident>>) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    fn is_selected(&self, locator: &Locator) -> bool {
+        self.selection.as_ref()
+            .map_or(true, |selection| selection.contains(&locator.ident))
+    }
+
     fn record(&mut self, idx: usize, hash: Hash64, script_result: ScriptResult) {
         let request
             = &self.requests.entries[idx];
@@ -274,43 +367,121 @@ impl<'a> BuildManager<'a> {
                 .or_insert_with(BTreeMap::new)
                 .insert(request.cwd.clone(), hash);
 
-            if let Some(dependents) = self.dependents.get_mut(&idx) {
-                for &dependent_idx in dependents.iter() {
-                    let dependencies
-                        = self.requests.dependencies.get_mut(&dependent_idx)
-                            .expect("Expected this package to have dependencies, since it's listed as a dependent");
+            self.unblock_dependents(idx);
+        }
+    }
+
+    fn unblock_dependents(&mut self, idx: usize) {
+        if let Some(dependents) = self.dependents.get_mut(&idx) {
+            for &dependent_idx in dependents.iter() {
+                let dependencies
+                    = self.requests.dependencies.get_mut(&dependent_idx)
+                        .expect("Expected this package to have dependencies, since it's listed as a dependent");
+
+                dependencies.remove(&idx);
+
+                if dependencies.is_empty() {
+                    self.queued.push(dependent_idx);
+                }
+            }
+        }
+    }
+
+    async fn report_selection(&self) {
+        let Some(selection) = &self.selection else {
+            return;
+        };
+
+        current_report().await.as_ref().map(|report| {
+            for ident in selection {
+                let matches = self.requests.entries.iter()
+                    .map(|request| &request.locator)
+                    .filter(|locator| locator.ident == *ident)
+                    .collect::<BTreeSet<_>>();
+
+                if matches.is_empty() {
+                    report.warn(format!("No installed package named {} has build scripts", ident.to_print_string()));
+                } else {
+                    let match_list = matches.iter()
+                        .map(|locator| locator.to_print_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    report.info(format!("Rebuilding {} ({})", ident.to_print_string(), match_list));
+                }
+            }
+        });
+    }
+
+    /// Mark all the transitive dependents of a failed build as failed too;
+    /// they would otherwise stay blocked forever (or run against a broken
+    /// dependency), and the user wouldn't know why they didn't build.
+    fn fail_dependents(&mut self, idx: usize) -> Vec<Error> {
+        let failed_locator
+            = self.requests.entries[idx].locator.clone();
 
-                    dependencies.remove(&idx);
+        let mut errors
+            = Vec::new();
 
-                    if dependencies.is_empty() {
-                        self.queued.push(dependent_idx);
-                    }
+        let mut queue
+            = vec![idx];
+
+        while let Some(current_idx) = queue.pop() {
+            let Some(dependents) = self.dependents.get(&current_idx) else {
+                continue;
+            };
+
+            for &dependent_idx in dependents.iter() {
+                let dependent
+                    = &self.requests.entries[dependent_idx];
+
+                if self.build_errors.insert(dependent.key()) {
+                    errors.push(Error::DependencyBuildFailed(dependent.locator.clone(), failed_locator.clone()));
+                    queue.push(dependent_idx);
                 }
             }
         }
+
+        errors
     }
 
     fn trigger(&mut self, project: &'a Project, build_state: &BuildState) {
-        while self.running.len() < 5 {
+        let build_concurrency
+            = project.config.settings.build_concurrency.value.max(1);
+
+        while self.running.len() < build_concurrency {
             if let Some(idx) = self.queued.pop() {
                 let req
                     = self.requests.entries[idx].clone();
 
+                if !self.is_selected(&req.locator) {
+                    self.unblock_dependents(idx);
+                    continue;
+                }
+
                 let force_rebuild
                     = req.force_rebuild;
 
                 let tree_hash
-                    = self.get_hash(project, &req.locator);
+                    = [self.get_hash(project, &req.locator), self.environment_hash.clone()].iter()
+                        .collect_hash();
+
+                let existing_hash = build_state.entries
+                    .get(&req.locator)
+                    .and_then(|entries| entries.get(&req.cwd));
+
+                let decision = match existing_hash {
+                    _ if force_rebuild => BuildDecision::Forced,
+                    Some(existing_hash) if existing_hash == &tree_hash => BuildDecision::Cached,
+                    Some(_) => BuildDecision::InputsChanged,
+                    None => BuildDecision::FirstBuild,
+                };
 
-                if !force_rebuild {
-                    let existing_hash = build_state.entries
-                        .get(&req.locator)
-                        .and_then(|entries| entries.get(&req.cwd));
+                self.decisions.insert(req.key(), decision);
 
-                    if existing_hash == Some(&tree_hash) {
-                        self.record(idx, tree_hash.clone(), ScriptResult::new_success());
-                        continue;
-                    }
+                if decision == BuildDecision::Cached {
+                    self.record(idx, tree_hash.clone(), ScriptResult::new_success());
+                    continue;
                 }
 
                 self.build_state_out.entries.get_mut(&req.locator)
@@ -327,7 +498,7 @@ impl<'a> BuildManager<'a> {
         }
     }
 
-    fn get_hash_impl<'b>(tree_hashes: &'b mut BTreeMap<Locator, Hash64>, project: &'a Project, locator: &Locator) -> Hash64 {
+    fn get_hash_impl<'b>(tree_hashes: &'b mut BTreeMap<Locator, Hash64>, checksums: &BTreeMap<Locator, Hash64>, project: &'a Project, locator: &Locator) -> Hash64 {
         let hash
             = tree_hashes.get(locator);
 
@@ -347,10 +518,14 @@ impl<'a> BuildManager<'a> {
         let self_hash
             = Hash64::from_string(&locator.to_file_string());
 
+        let checksum
+            = checksums.get(&locator.physical_locator()).cloned();
+
         let hashes = resolution.dependencies.values()
             .map(|descriptor| &install_state.resolution_tree.descriptor_to_locator[descriptor])
-            .map(|dependency| BuildManager::get_hash_impl(tree_hashes, project, dependency))
+            .map(|dependency| BuildManager::get_hash_impl(tree_hashes, checksums, project, dependency))
             .chain(Some(self_hash))
+            .chain(checksum)
             .collect::<Vec<_>>();
 
         let hash = hashes.iter()
@@ -362,10 +537,67 @@ impl<'a> BuildManager<'a> {
     }
 
     fn get_hash(&mut self, project: &'a Project, locator: &Locator) -> Hash64 {
-        BuildManager::get_hash_impl(&mut self.tree_hashes, project, locator)
+        BuildManager::get_hash_impl(&mut self.tree_hashes, &self.checksums, project, locator)
+    }
+
+    /// The parts of the environment that may affect the output of a build;
+    /// changing any of them invalidates all the previous builds.
+    async fn get_environment_hash(project: &Project) -> Hash64 {
+        let node_version = engines::current_node_version(project).await
+            .map(|version| version.to_file_string())
+            .unwrap_or_default();
+
+        let system
+            = System::from_current().to_file_string();
+
+        [
+            Hash64::from_string(&node_version),
+            Hash64::from_string(&system),
+            Hash64::from_string(&project.config.settings.enable_native_shell.value.to_string()),
+        ].iter().collect_hash()
+    }
+
+    async fn report_decisions(&self) {
+        let cached_builds = self.decisions.iter()
+            .filter(|(_, &decision)| decision == BuildDecision::Cached)
+            .map(|((locator, _), _)| locator.to_print_string())
+            .collect::<Vec<_>>();
+
+        current_report().await.as_ref().map(|report| {
+            for ((locator, _), decision) in &self.decisions {
+                let reason = match decision {
+                    BuildDecision::Cached => continue,
+                    BuildDecision::FirstBuild => "no previous build recorded, or it was reset",
+                    BuildDecision::InputsChanged => "its dependencies, content, or environment changed",
+                    BuildDecision::Forced => "its files were just extracted",
+                };
+
+                report.info(format!("Ran the build scripts of {} ({})", locator.to_print_string(), reason));
+            }
+
+            if !cached_builds.is_empty() {
+                report.info(format!("Skipped the builds of {} as their inputs didn't change since they last ran", cached_builds.join(", ")));
+            }
+        });
     }
 
     pub async fn run(mut self, project: &'a mut Project) -> Result<Build, Error> {
+        self.environment_hash
+            = BuildManager::get_environment_hash(project).await;
+
+        if !self.requests.skipped_builds.is_empty() {
+            let skipped_list = self.requests.skipped_builds.iter()
+                .map(|locator| locator.to_print_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            current_report().await.as_ref().map(|report| {
+                report.warn_with_code(ErrorCode::BuildSkipped, format!("Some packages list build scripts, but their builds were skipped: {}; add them to {} to run them, or use {} to build them once", skipped_list, DataType::Code.colorize("allowedBuildDependencies"), DataType::Code.colorize("yarn rebuild <name>")));
+            });
+        }
+
+        self.report_selection().await;
+
         let locators_to_build = self.requests.entries.iter()
             .map(|req| req.locator.clone())
             .collect::<BTreeSet<_>>();
@@ -406,6 +638,15 @@ impl<'a> BuildManager<'a> {
 
                 Err(_) => {
                     self.build_errors.insert(request.key());
+
+                    let errors
+                        = self.fail_dependents(idx);
+
+                    current_report().await.as_ref().map(|report| {
+                        for error in errors {
+                            report.error(error);
+                        }
+                    });
                 }
             }
 
@@ -419,6 +660,8 @@ impl<'a> BuildManager<'a> {
             }
         }
 
+        self.report_decisions().await;
+
         Ok(Build {
             build_errors: self.build_errors,
         })
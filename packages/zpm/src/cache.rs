@@ -1,7 +1,7 @@
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::collections::HashSet;
 use std::sync::Mutex;
 use itertools::Itertools;
@@ -16,7 +16,11 @@ use crate::{
     error::Error,
 };
 
-pub const CACHE_VERSION: usize = 1;
+/// Must be bumped whenever the archives we generate change for a same input,
+/// so that archives generated by older versions don't get mixed with new ones.
+/// Lockfile checksums are prefixed by this version, so those computed from
+/// older archives get recomputed rather than reported as mismatches.
+pub const CACHE_VERSION: usize = 2;
 
 #[zpm_enum]
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -27,19 +31,12 @@ pub enum CacheEntry {
         path: Path,
         checksum: Option<Hash64>,
     },
-
-    #[no_pattern]
-    Data {
-        info: InfoCacheEntry,
-        data: Vec<u8>,
-    },
 }
 
 impl CacheEntry {
     pub fn into_info(self) -> InfoCacheEntry {
         match self {
             CacheEntry::Info(params) => params,
-            CacheEntry::Data(params) => params.info,
         }
     }
 }
@@ -107,11 +104,13 @@ impl CompositeCache {
         panic!("Expected at least one cache to be set");
     }
 
-    async fn load<R, F>(func: F) -> Result<Vec<u8>, Error>
+    async fn load<R, F>(fetched: &AtomicBool, func: F) -> Result<Vec<u8>, Error>
     where
         R: Future<Output = Result<Vec<u8>, Error>>,
         F: FnOnce() -> R,
     {
+        fetched.store(true, Ordering::Relaxed);
+
         current_report().await.as_ref().map(|report| {
             report.counters.fetch_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         });
@@ -128,48 +127,39 @@ impl CompositeCache {
         res
     }
 
-    pub async fn ensure_blob<R, F>(&self, key: Locator, ext: &str, func: F) -> Result<CacheEntry, Error>
-    where
-        R: Future<Output = Result<Vec<u8>, Error>>,
-        F: FnOnce() -> R,
-    {
-        if let Some(ref cache) = self.local_cache {
-            return cache.ensure_blob(key.clone(), ext, || async {
-                if let Some(ref cache) = self.global_cache {
-                    Ok(cache.upsert_blob(key, ext, || Self::load(func)).await?.data)
-                } else {
-                    Self::load(func).await
-                }
-            }).await;
-        }
-
-        if let Some(ref cache) = self.global_cache {
-            return cache.ensure_blob(key, ext, || Self::load(func)).await;
-        }
-
-        panic!("Expected at least one cache to be set");
+    async fn report_fetch(locator: Locator, size: u64, fetched: &AtomicBool) {
+        current_report().await.as_ref().map(|report| {
+            report.fetch(locator, size, !fetched.load(Ordering::Relaxed));
+        });
     }
 
-    pub async fn upsert_blob<R, F>(&self, key: Locator, ext: &str, func: F) -> Result<DataCacheEntry, Error>
+    pub async fn ensure_blob<R, F>(&self, key: Locator, ext: &str, func: F) -> Result<CacheEntry, Error>
     where
         R: Future<Output = Result<Vec<u8>, Error>>,
         F: FnOnce() -> R,
     {
-        if let Some(ref cache) = self.local_cache {
-            return cache.upsert_blob(key.clone(), ext, || async {
-                if let Some(ref cache) = self.global_cache {
-                    Ok(cache.upsert_blob(key, ext, || Self::load(func)).await?.data)
-                } else {
-                    Self::load(func).await
-                }
-            }).await;
-        }
+        let fetched
+            = AtomicBool::new(false);
+
+        let entry = if let Some(ref cache) = self.local_cache {
+            if let Some(ref global_cache) = self.global_cache {
+                cache.ensure_blob_from(key.clone(), ext, global_cache, || Self::load(&fetched, func)).await?
+            } else {
+                cache.ensure_blob(key.clone(), ext, || Self::load(&fetched, func)).await?
+            }
+        } else if let Some(ref cache) = self.global_cache {
+            cache.ensure_blob(key.clone(), ext, || Self::load(&fetched, func)).await?
+        } else {
+            panic!("Expected at least one cache to be set");
+        };
 
-        if let Some(ref cache) = self.global_cache {
-            return cache.upsert_blob(key, ext, || Self::load(func)).await;
-        }
+        let size = match &entry {
+            CacheEntry::Info(params) => params.path.fs_metadata().map_or(0, |metadata| metadata.len()),
+        };
 
-        panic!("Expected at least one cache to be set");
+        Self::report_fetch(key, size, &fetched).await;
+
+        Ok(entry)
     }
 
     pub async fn clean(&self) -> Result<usize, Error> {
@@ -241,11 +231,9 @@ impl DiskCache {
     {
         let key_path
             = self.key_path(&key, ext);
-        let key_path_buf
-            = key_path.to_path_buf();
 
         let exists
-            = tokio::fs::try_exists(key_path_buf.clone()).await?;
+            = tokio::fs::try_exists(key_path.to_path_buf()).await?;
 
         Ok(match exists {
             true => {
@@ -261,7 +249,7 @@ impl DiskCache {
                 }
 
                 let data
-                    = self.fetch_and_store_blob::<R, F>(key_path_buf, func).await?;
+                    = self.fetch_and_store_blob::<R, F>(&key_path, func).await?;
 
                 tokio::task::spawn_blocking(move || {
                     let checksum
@@ -276,59 +264,56 @@ impl DiskCache {
         })
     }
 
-    pub async fn upsert_blob<R, F>(&self, key: Locator, ext: &str, func: F) -> Result<DataCacheEntry, Error>
+    /// Same as `ensure_blob`, but populates the entry from another cache
+    /// (fetching it there first if needed) by copying its archive file, so
+    /// that the archive content never has to be loaded in memory.
+    pub async fn ensure_blob_from<R, F>(&self, key: Locator, ext: &str, source: &DiskCache, func: F) -> Result<CacheEntry, Error>
     where
         R: Future<Output = Result<Vec<u8>, Error>>,
         F: FnOnce() -> R,
     {
         let key_path
             = self.key_path(&key, ext);
-        let key_path_buf
-            = key_path.to_path_buf();
 
-        let read
-            = tokio::fs::read(key_path_buf.clone()).await;
+        let exists
+            = tokio::fs::try_exists(key_path.to_path_buf()).await?;
 
-        Ok(match read {
-            Ok(data) => {
-                DataCacheEntry {
-                    info: InfoCacheEntry {
-                        path: key_path,
-                        checksum: None,
-                    },
-                    data,
-                }
-            },
+        if exists {
+            return Ok(InfoCacheEntry {
+                path: key_path,
+                checksum: None,
+            }.into());
+        }
 
-            Err(err) => {
-                if err.kind() != std::io::ErrorKind::NotFound {
-                    return Err(err)?;
-                }
+        if self.immutable {
+            return Err(Error::ImmutableCache(key));
+        }
 
-                if self.immutable {
-                    return Err(Error::ImmutableCache(key));
-                }
+        let source_entry
+            = source.ensure_blob(key, ext, func).await?.into_info();
 
-                let data
-                    = self.fetch_and_store_blob::<R, F>(key_path_buf, func).await?;
+        self.store_blob(&key_path, |temp_path| {
+            source_entry.path.fs_copy_file(temp_path)?;
+            Ok(())
+        })?;
 
-                tokio::task::spawn(async move {
-                    let checksum
-                        = Hash64::from_data(&data);
+        let checksum = match source_entry.checksum {
+            Some(checksum) => checksum,
+            None => {
+                let key_path
+                    = key_path.clone();
 
-                    DataCacheEntry {
-                        info: InfoCacheEntry {
-                            path: key_path,
-                            checksum: Some(checksum),
-                        },
-                        data,
-                    }
-                }).await.unwrap()
+                tokio::task::spawn_blocking(move || Hash64::from_file(&key_path)).await.unwrap()?
             },
-        })
+        };
+
+        Ok(InfoCacheEntry {
+            path: key_path,
+            checksum: Some(checksum),
+        }.into())
     }
 
-    async fn fetch_and_store_blob<R, F>(&self, key_path: PathBuf, func: F) -> Result<Vec<u8>, Error>
+    async fn fetch_and_store_blob<R, F>(&self, key_path: &Path, func: F) -> Result<Vec<u8>, Error>
     where
         R: Future<Output = Result<Vec<u8>, Error>>,
         F: FnOnce() -> R,
@@ -336,14 +321,45 @@ impl DiskCache {
         let data
             = func().await?;
 
-        let mut file
-            = File::create(key_path.clone())?;
+        self.store_blob(key_path, |temp_path| {
+            let mut file
+                = File::create(temp_path.to_path_buf())?;
 
-        file.write_all(&data)?;
+            file.write_all(&data)?;
+
+            Ok(())
+        })?;
 
         Ok(data)
     }
 
+    fn store_blob<W>(&self, key_path: &Path, write: W) -> Result<(), Error>
+    where
+        W: FnOnce(&Path) -> Result<(), Error>,
+    {
+        static TEMP_COUNTER: AtomicUsize
+            = AtomicUsize::new(0);
+
+        // The archive is written next to its final location then renamed, so
+        // that an interrupted install never leaves a truncated archive that
+        // later installs would mistake for a valid cache entry
+        let temp_path = self.cache_path
+            .with_join_str(format!(".{}.{}-{}.tmp", key_path.basename().unwrap_or("blob"), std::process::id(), TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+
+        let write_result = write(&temp_path).and_then(|_| {
+            temp_path
+                .fs_rename(key_path)?;
+
+            Ok(())
+        });
+
+        if write_result.is_err() {
+            let _ = temp_path.fs_rm_file();
+        }
+
+        write_result
+    }
+
     pub async fn clean(&self) -> Result<usize, Error> {
         let accessed_files
             = self.accessed_files.lock()
@@ -1,17 +1,19 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use clipanion::cli;
+use http::StatusCode;
 use zpm_parsers::{Document, JsonDocument, Value};
-use zpm_primitives::{AnonymousSemverRange, Descriptor};
+use zpm_primitives::{AnonymousSemverRange, Descriptor, Ident};
 use zpm_semver::RangeKind;
 use zpm_utils::{FromFileString, ToFileString, ToHumanString};
 
 use crate::{
-    algolia::query_algolia,
     descriptor_loose::{self, LooseDescriptor},
     error::Error,
+    http_npm,
     install::InstallContext,
-    project::{self, InstallMode}
+    project::{self, InstallMode, Project},
+    resolvers,
 };
 
 #[derive(Clone, Debug)]
@@ -37,10 +39,8 @@ async fn expand_with_types<'a>(install_context: &InstallContext<'a>, _resolve_op
         .map(|(descriptor, _)| descriptor.ident.clone())
         .collect::<HashSet<_>>();
 
-    let mut search_space
-        = Vec::new();
-    let mut candidate_requests
-        = HashMap::new();
+    let mut candidates
+        = vec![];
 
     'request_loop: for (descriptor, request) in &requests {
         let type_ident
@@ -84,8 +84,23 @@ async fn expand_with_types<'a>(install_context: &InstallContext<'a>, _resolve_op
             continue;
         };
 
+        candidates.push((descriptor, type_ident, type_request, semver_range, range_min));
+    }
+
+    // Each candidate requires the packuments of both the package and its
+    // `@types` counterpart, so we query them all concurrently
+    let type_candidates = futures::future::try_join_all(candidates.into_iter().map(|(descriptor, type_ident, type_request, semver_range, range_min)| async move {
+        let (bundles_types, type_package_exists) = futures::try_join!(
+            has_bundled_types(project, &descriptor.ident, &semver_range),
+            package_exists(project, &type_ident),
+        )?;
+
+        if bundles_types || !type_package_exists {
+            return Ok::<_, Error>(None);
+        }
+
         let type_descriptor = Descriptor::new(
-            descriptor.ident.type_ident(),
+            type_ident,
             AnonymousSemverRange {
                 // TODO: We don't use `caret` here to match the Yarn Berry testsuite; we
                 // should clean that up once we can afford to update the tests.
@@ -93,22 +108,65 @@ async fn expand_with_types<'a>(install_context: &InstallContext<'a>, _resolve_op
             }.into(),
         );
 
-        search_space.push(descriptor.ident.clone());
-        candidate_requests.insert(descriptor.ident.clone(), (type_descriptor, type_request));
-    }
+        Ok(Some((type_descriptor, type_request)))
+    })).await?;
 
-    let type_idents
-        = query_algolia(&search_space, &project.http_client).await?;
+    type_requests.extend(type_candidates.into_iter().flatten());
 
-    for (ident, _) in type_idents {
-        let Some((descriptor, request)) = candidate_requests.remove(&ident) else {
-            continue;
-        };
+    Ok(type_requests)
+}
+
+/// Returns the full packument of the package (the abbreviated one doesn't
+/// list the `types` and `exports` fields), or `None` if it doesn't exist.
+async fn fetch_packument(project: &Project, ident: &Ident) -> Result<Option<serde_json::Value>, Error> {
+    let response
+        = resolvers::npm::fetch_packument_with_format(project, ident, http_npm::PackumentFormat::Full, true).await;
 
-        type_requests.push((descriptor, request));
+    match response {
+        Ok(bytes) => Ok(Some(JsonDocument::hydrate_from_slice(&bytes[..])?)),
+        Err(Error::HttpError {inner, ..}) if inner.status() == Some(StatusCode::NOT_FOUND) => Ok(None),
+        Err(error) => Err(error),
     }
+}
 
-    Ok(type_requests)
+async fn package_exists(project: &Project, ident: &Ident) -> Result<bool, Error> {
+    Ok(fetch_packument(project, ident).await?.is_some())
+}
+
+/// Check whether the highest version matching the range ships its own type
+/// definitions, in which case there's no need for a `@types` package.
+async fn has_bundled_types(project: &Project, ident: &Ident, range: &zpm_semver::Range) -> Result<bool, Error> {
+    let Some(packument) = fetch_packument(project, ident).await? else {
+        return Ok(false);
+    };
+
+    let manifest = packument.get("versions")
+        .and_then(|versions| versions.as_object())
+        .into_iter()
+        .flatten()
+        .filter_map(|(version, manifest)| Some((zpm_semver::Version::from_file_string(version).ok()?, manifest)))
+        .filter(|(version, _)| range.check(version))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, manifest)| manifest);
+
+    let Some(manifest) = manifest else {
+        return Ok(false);
+    };
+
+    if manifest.get("types").is_some() || manifest.get("typings").is_some() {
+        return Ok(true);
+    }
+
+    Ok(manifest.get("exports").is_some_and(exports_provide_types))
+}
+
+fn exports_provide_types(exports: &serde_json::Value) -> bool {
+    match exports {
+        serde_json::Value::String(path) => path.ends_with(".d.ts") || path.ends_with(".d.mts") || path.ends_with(".d.cts"),
+        serde_json::Value::Array(entries) => entries.iter().any(exports_provide_types),
+        serde_json::Value::Object(entries) => entries.iter().any(|(key, value)| key == "types" || exports_provide_types(value)),
+        _ => false,
+    }
 }
 
 /// Add new dependencies to the project
@@ -186,6 +244,10 @@ pub struct Add {
     #[cli::option("--silent", default = false)]
     silent: bool,
 
+    /// Print the packages added, removed, or changed by the install
+    #[cli::option("--summary", default = true)]
+    summary: bool,
+
     // ---
 
     /// Packages to add
@@ -333,6 +395,7 @@ impl Add {
             mode: self.mode,
             enforced_resolutions,
             silent_or_error: self.silent,
+            summary: self.summary,
             ..Default::default()
         }).await?;
 
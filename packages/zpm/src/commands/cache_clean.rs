@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+
+use clipanion::cli;
+use zpm_primitives::Locator;
+use zpm_utils::{DataType, IoResultExt, Path, ToFileString, ToHumanString, Unit};
+
+use crate::{error::Error, project, report::{StreamReport, StreamReportConfig, current_report, with_report_result}};
+
+/// Remove unused archives from the cache
+///
+/// This command removes the cache archives that aren't referenced by the current project anymore. The set of referenced archives is computed from
+/// the packages listed in the lockfile (and in the install state, if any).
+///
+/// If the `--all` option is set, every archive will be removed from the cache, regardless of whether they're used or not.
+///
+/// If the `--dry-run` option is set, the archives that would be removed will be listed but left untouched.
+///
+/// Note that when `enableGlobalCache` is set, the cache is shared between all your projects; archives only used by other projects will be
+/// considered unreferenced.
+///
+#[cli::command]
+#[cli::path("cache", "clean")]
+#[cli::category("Cache management")]
+pub struct CacheClean {
+    /// Remove all archives, not only the unreferenced ones
+    #[cli::option("--all", default = false)]
+    all: bool,
+
+    /// Print the archives that would be removed without removing them
+    #[cli::option("-n,--dry-run", default = false)]
+    dry_run: bool,
+}
+
+impl CacheClean {
+    pub async fn execute(&self) -> Result<(), Error> {
+        let mut project
+            = project::Project::new(None).await?;
+
+        // The install state is only used to refine the set of referenced
+        // archives; we can work from the lockfile alone if it's missing.
+        let _ = project.import_install_state();
+
+        let report = StreamReport::new(StreamReportConfig {
+            ..StreamReportConfig::from_config(&project.config)
+        });
+
+        with_report_result(report, async {
+            let package_cache
+                = project.package_cache()?;
+
+            let mut referenced_files
+                = BTreeSet::new();
+
+            if !self.all {
+                let mut referenced_locators: BTreeSet<Locator>
+                    = project.lockfile()?.entries.into_keys().collect();
+
+                if let Some(install_state) = &project.install_state {
+                    referenced_locators.extend(install_state.content_flags.keys().cloned());
+                }
+
+                let inner_locators = referenced_locators.iter()
+                    .filter_map(|locator| locator.reference.inner_locator().cloned())
+                    .collect::<Vec<_>>();
+
+                referenced_locators.extend(inner_locators);
+
+                for locator in &referenced_locators {
+                    let key_path
+                        = package_cache.key_path(locator, ".zip");
+
+                    if let Some(basename) = key_path.basename() {
+                        referenced_files.insert(basename.to_string());
+                    }
+                }
+            }
+
+            let cache_entries
+                = project.preferred_cache_path()
+                    .fs_read_dir()
+                    .ok_missing()?;
+
+            let mut removed_entries
+                = Vec::new();
+
+            if let Some(cache_entries) = cache_entries {
+                for entry in cache_entries {
+                    let entry
+                        = entry?;
+
+                    if !entry.file_type()?.is_file() {
+                        continue;
+                    }
+
+                    let Ok(path) = Path::try_from(entry.path()) else {
+                        continue;
+                    };
+
+                    let Some(basename) = path.basename() else {
+                        continue;
+                    };
+
+                    if !basename.ends_with(".zip") || referenced_files.contains(basename) {
+                        continue;
+                    }
+
+                    let size
+                        = entry.metadata()?.len();
+
+                    removed_entries.push((path, size));
+                }
+            }
+
+            removed_entries.sort();
+
+            let mut reclaimed_bytes
+                = 0;
+
+            for (path, size) in &removed_entries {
+                if self.dry_run {
+                    println!("{}", path.to_file_string());
+                } else {
+                    path.fs_rm_file().ok_missing()?;
+                }
+
+                reclaimed_bytes += size;
+            }
+
+            current_report().await.as_ref().map(|report| {
+                if removed_entries.is_empty() {
+                    report.info("No entries to clean from the cache.".to_string());
+                } else if self.dry_run {
+                    report.info(format!("Would remove {} entries from the cache ({}).", DataType::Number.colorize(&removed_entries.len().to_string()), Unit::bytes(reclaimed_bytes).to_print_string()));
+                } else {
+                    report.info(format!("Removed {} entries from the cache ({} reclaimed).", DataType::Number.colorize(&removed_entries.len().to_string()), Unit::bytes(reclaimed_bytes).to_print_string()));
+                }
+            });
+
+            Ok(())
+        }).await?;
+
+        Ok(())
+    }
+}
@@ -7,7 +7,6 @@ use crate::{error::Error, project, report::{StreamReport, StreamReportConfig, cu
 /// Clear the global cache
 #[cli::command]
 #[cli::path("cache", "clear")]
-#[cli::path("cache", "clean")]
 #[cli::category("Cache management")]
 pub struct CacheClear {
     /// Clear cache entries older than 7 days
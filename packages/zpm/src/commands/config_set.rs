@@ -60,7 +60,7 @@ impl ConfigSet {
         )?;
 
         document_path
-            .fs_change(&updated_document, false)?;
+            .fs_change_atomic(&updated_document, false)?;
 
         Ok(())
     }
@@ -2,10 +2,10 @@ use std::process::ExitCode;
 
 use clipanion::cli;
 use colored::Colorize;
+use itertools::Itertools;
 use zpm_utils::{tree, AbstractValue, DataType, ToFileString, ToHumanString};
-use zpm_parsers::{Document, JsonDocument, Value};
 
-use crate::{constraints::{check_constraints, structs::{ConstraintsOutput, WorkspaceError, WorkspaceOperation}}, error::Error, project::Project};
+use crate::{constraints::{apply::{apply_constraints_operations, AppliedChange}, check_constraints, structs::{ConstraintsOutput, WorkspaceError}}, error::Error, project::Project};
 
 /// Check constraints
 ///
@@ -13,7 +13,9 @@ use crate::{constraints::{check_constraints, structs::{ConstraintsOutput, Worksp
 /// will exit with a non-zero exit code.
 ///
 /// If the `--fix` flag is used, Yarn will attempt to automatically fix the issues the best it can, following a multi-pass process (with a maximum of
-/// 10 iterations). Some ambiguous patterns cannot be autofixed, in which case you'll have to manually specify the right resolution.
+/// 10 iterations). Some ambiguous patterns cannot be autofixed, in which case you'll have to manually specify the right resolution. The fields
+/// changed in each workspace are listed once done, and the constraints are then checked one last time; the command will exit with a non-zero exit
+/// code if some errors remain.
 ///
 /// For more information as to how to write constraints, please consult our dedicated page on our website: https://yarnpkg.com/features/constraints.
 ///
@@ -30,77 +32,112 @@ pub struct Constraints {
     json: bool,
 }
 
+/// Maximum number of fix passes; fixes may unlock new constraints (for
+/// example when they add a dependency), so we apply them until the output
+/// stabilizes.
+const MAX_FIX_PASSES: usize = 10;
+
 impl Constraints {
     pub async fn execute(&self) -> Result<ExitCode, Error> {
         let mut project
             = Project::new(None).await?;
 
-        let max_loops = if self.fix {
-            10
-        } else {
-            1
-        };
-
-        for loop_idx in 1..=max_loops {
-            project
-                .lazy_install().await?;
-
-            let output
-                = check_constraints(&project, self.fix).await?;
-
-            for (workspace_rel_path, operations) in &output.all_workspace_operations {
-                // Read the current manifest
-                let manifest_path = project.project_cwd
-                    .with_join(workspace_rel_path)
-                    .with_join_str("package.json");
-
-                let manifest_content = manifest_path
-                    .fs_read_prealloc()?;
-
-                let mut document
-                    = JsonDocument::new(manifest_content)?;
-
-                // Apply each operation
-                for operation in operations {
-                    match operation {
-                        WorkspaceOperation::Set { path, value } => {
-                            document.set_path(&zpm_parsers::Path::from_segments(path.clone()), value.into())?;
-                        },
-
-                        WorkspaceOperation::Unset { path } => {
-                            document.set_path(&zpm_parsers::Path::from_segments(path.clone()), Value::Undefined)?;
-                        },
-                    }
-                }
+        if self.fix {
+            let mut changes
+                = Vec::new();
 
-                // Write the formatted result back
-                manifest_path
-                    .fs_change(&document.input, false)?;
-            }
+            for _ in 0..MAX_FIX_PASSES {
+                project
+                    .lazy_install().await?;
 
-            let should_break = false
-                || output.all_workspace_operations.is_empty()
-                || output.all_workspace_errors.is_empty()
-                || loop_idx == max_loops;
+                let output
+                    = check_constraints(&project, true).await?;
 
-            if should_break {
-                if self.json {
-                    println!("{}", String::from_utf8_lossy(&output.raw_json));
+                if output.all_workspace_operations.is_empty() {
+                    break;
                 }
 
-                if !output.all_workspace_errors.is_empty() {
-                    if !self.json {
-                        display_report(&project, &output)?;
-                    }
-                    return Ok(ExitCode::FAILURE);
-                } else {
-                    return Ok(ExitCode::SUCCESS);
-                }
+                changes.extend(apply_constraints_operations(&project, &output)?);
+
+                // The manifests changed, so the workspaces must be reloaded
+                project = Project::new(None).await?;
             }
+
+            if !self.json && !changes.is_empty() {
+                display_changes(&project, &changes)?;
+            }
+        }
+
+        // We always finish with a check-only pass, so that `--fix` fails if
+        // some errors couldn't be fixed automatically.
+        project
+            .lazy_install().await?;
+
+        let output
+            = check_constraints(&project, false).await?;
+
+        if self.json {
+            println!("{}", String::from_utf8_lossy(&output.raw_json));
         }
 
-        unreachable!()
+        if output.all_workspace_errors.is_empty() {
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        if !self.json {
+            display_report(&project, &output)?;
+        }
+
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+fn display_changes(project: &Project, changes: &[AppliedChange]) -> Result<(), Error> {
+    let print_value = |value: &Option<serde_json::Value>| match value {
+        Some(value) => value.to_print_string(),
+        None => DataType::Code.colorize("undefined"),
+    };
+
+    let mut root_children
+        = vec![];
+
+    // Changes to the same workspace may not be contiguous (they're applied
+    // in successive passes), so we sort them before grouping them.
+    let sorted_changes = changes.iter()
+        .sorted_by_key(|change| &change.workspace_rel_path);
+
+    for (workspace_rel_path, workspace_changes) in &sorted_changes.chunk_by(|change| &change.workspace_rel_path) {
+        let workspace
+            = project.workspace_by_rel_path(workspace_rel_path)?;
+
+        let change_nodes = workspace_changes
+            .map(|change| tree::Node {
+                label: Some(format!("{}: {} → {}", change.field_path.to_print_string(), print_value(&change.previous_value), print_value(&change.new_value))),
+                value: None,
+                children: None,
+            })
+            .collect::<Vec<_>>();
+
+        root_children.push(tree::Node {
+            label: None,
+            value: Some(AbstractValue::new(workspace.locator_path())),
+            children: Some(tree::TreeNodeChildren::Vec(change_nodes)),
+        });
     }
+
+    let root = tree::Node {
+        label: None,
+        value: None,
+        children: Some(tree::TreeNodeChildren::Vec(root_children)),
+    };
+
+    println!("➤ Applied {} fixes", DataType::Number.colorize(&changes.len().to_string()));
+    println!();
+
+    print!("{}", root.to_string());
+    println!();
+
+    Ok(())
 }
 
 fn display_report(project: &Project, output: &ConstraintsOutput) -> Result<(), Error> {
@@ -0,0 +1,28 @@
+use std::process::ExitCode;
+
+use clipanion::cli;
+
+use crate::{error::Error, shell};
+
+/// Run a script through the builtin shell
+///
+/// This command is used internally to run the `scripts` entries (unless `enableNativeShell` is set). The arguments are appended to the last
+/// command of the script as-is, without being subject to expansion.
+#[cli::command(proxy)]
+#[cli::path("debug", "sh")]
+pub struct Sh {
+    /// The script to run
+    script: String,
+
+    /// The arguments to append to the script
+    args: Vec<String>,
+}
+
+impl Sh {
+    pub async fn execute(&self) -> Result<ExitCode, Error> {
+        let status
+            = shell::run_script(&self.script, &self.args)?;
+
+        Ok(ExitCode::from(status))
+    }
+}
@@ -8,10 +8,20 @@ use zpm_utils::{tree, AbstractValue};
 
 use crate::{error::Error, project::{InstallMode, Project, RunInstallOptions}};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 enum DedupeStrategy {
     #[default]
     Highest,
+    Fewer,
+}
+
+impl DedupeStrategy {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Highest => "highest",
+            Self::Fewer => "fewer",
+        }
+    }
 }
 
 impl FromStr for DedupeStrategy {
@@ -22,6 +32,9 @@ impl FromStr for DedupeStrategy {
             "highest"
                 => Ok(Self::Highest),
 
+            "fewer"
+                => Ok(Self::Fewer),
+
             _
                 => Err(Error::InvalidDedupeStrategy(s.to_string())),
         }
@@ -33,11 +46,14 @@ impl FromStr for DedupeStrategy {
 /// Duplicates are defined as descriptors with overlapping ranges being resolved and locked to different locators. They are a natural consequence of
 /// Yarn's deterministic installs, but they can sometimes pile up and unnecessarily increase the size of your project.
 ///
-/// This command dedupes dependencies in the current project using different strategies (only one is implemented at the moment):
+/// This command dedupes dependencies in the current project using different strategies:
 ///
 /// - `highest`: Reuses (where possible) the locators with the highest versions. This means that dependencies can only be upgraded, never downgraded.
 /// It's also guaranteed that it never takes more than a single pass to dedupe the entire dependency tree.
 ///
+/// - `fewer`: Reuses the locators that satisfy the largest number of descriptors, so that the project ends up with as few distinct versions of
+/// each package as possible. Unlike `highest`, this strategy may pick a version that isn't the highest one satisfying a given range.
+///
 /// Note: Even though it never produces a wrong dependency tree, this command should be used with caution, as it modifies the dependency tree, which
 /// can sometimes cause problems when packages don't strictly follow semver recommendations. Because of this, it is recommended to also review the
 /// changes manually.
@@ -90,7 +106,7 @@ impl Dedupe {
             .lazy_install().await?;
 
         let enforced_resolutions
-            = prepare_highest_dedupe(&project, &self.patterns)?;
+            = prepare_dedupe(&project, &self.patterns, self.strategy)?;
 
         if self.check {
             if enforced_resolutions.is_empty() {
@@ -158,7 +174,7 @@ impl Dedupe {
 
         if !self.json {
             println!();
-            println!("{} {} can be deduped using the highest strategy", enforced_resolutions.len(), if enforced_resolutions.len() == 1 {"package"} else {"packages"});
+            println!("{} {} can be deduped using the {} strategy", enforced_resolutions.len(), if enforced_resolutions.len() == 1 {"package"} else {"packages"}, self.strategy.name());
         }
 
         Ok(())
@@ -177,80 +193,116 @@ fn extract_semver_version(locator: &Locator) -> Option<(&Ident, &zpm_semver::Ver
     }
 }
 
-fn prepare_highest_dedupe(project: &Project, patterns: &Vec<IdentGlob>) -> Result<BTreeMap<Descriptor, Locator>, Error> {
+fn dedupe_target(descriptor: &Descriptor) -> Option<(&Ident, &zpm_semver::Range)> {
+    match &descriptor.range {
+        Range::AnonymousSemver(params)
+            => Some((&descriptor.ident, &params.range)),
+
+        Range::RegistrySemver(RegistrySemverRange {ident: None, range})
+            => Some((&descriptor.ident, range)),
+
+        Range::RegistrySemver(RegistrySemverRange {ident: Some(ident), range})
+            => Some((ident, range)),
+
+        _ => None,
+    }
+}
+
+fn dedupe_locator(descriptor: &Descriptor, version: &zpm_semver::Version) -> Locator {
+    match &descriptor.range {
+        Range::RegistrySemver(RegistrySemverRange {ident: Some(ident), ..}) => {
+            Locator::new(descriptor.ident.clone(), RegistryReference {
+                ident: ident.clone(),
+                version: version.clone(),
+                url: None,
+            }.into())
+        },
+
+        _ => {
+            Locator::new(descriptor.ident.clone(), ShorthandReference {
+                version: version.clone(),
+            }.into())
+        },
+    }
+}
+
+fn prepare_dedupe(project: &Project, patterns: &Vec<IdentGlob>, strategy: DedupeStrategy) -> Result<BTreeMap<Descriptor, Locator>, Error> {
     let install_state
         = project.install_state.as_ref()
             .ok_or(Error::InstallStateNotFound)?;
 
-    let locators_by_ident
+    let versions_by_ident
         = install_state.normalized_resolutions.keys()
             .filter_map(extract_semver_version)
             .into_group_map_by(|(ident, _)| *ident);
 
-    let best_version_for = |ident: &Ident, range: &zpm_semver::Range| {
-        locators_by_ident.get(&ident).unwrap().iter()
-            .map(|(_, version)| *version)
-            .filter(|version| range.check(version))
-            .max()
-    };
-
-    let attach_highest_version = |descriptor: &Descriptor| {
-        let suggested_locator = match &descriptor.range {
-            Range::AnonymousSemver(params) => {
-                let best_version
-                    = best_version_for(&descriptor.ident, &params.range);
-
-                best_version.map(|version| {
-                    Locator::new(descriptor.ident.clone(), ShorthandReference {
-                        version: version.clone(),
-                    }.into())
-                })
-            },
-
-            Range::RegistrySemver(RegistrySemverRange {ident: None, range}) => {
-                let best_version
-                    = best_version_for(&descriptor.ident, range);
-
-                best_version.map(|version| {
-                    Locator::new(descriptor.ident.clone(), ShorthandReference {
-                        version: version.clone(),
-                    }.into())
-                })
-            },
+    let candidates_by_ident
+        = install_state.descriptor_to_locator.keys()
+            .filter(|descriptor| patterns.is_empty() || patterns.iter().any(|matcher| matcher.check(&descriptor.ident)))
+            .filter_map(|descriptor| dedupe_target(descriptor).map(|(ident, range)| (ident, (descriptor, range))))
+            .into_group_map();
 
-            Range::RegistrySemver(RegistrySemverRange {ident: Some(ident), range}) => {
-                let best_version
-                    = best_version_for(ident, range);
-
-                best_version.map(|version| {
-                    Locator::new(descriptor.ident.clone(), RegistryReference {
-                        ident: ident.clone(),
-                        version: version.clone(),
-                        url: None,
-                    }.into())
-                })
-            },
+    let mut selected_versions
+        = BTreeMap::new();
 
-            _ => return None,
+    for (ident, candidates) in candidates_by_ident {
+        let Some(versions) = versions_by_ident.get(ident) else {
+            continue;
         };
 
-        suggested_locator.and_then(|locator| {
-            let current_resolution
-                = install_state.descriptor_to_locator
-                    .get(descriptor);
+        let versions = versions.iter()
+            .map(|(_, version)| *version)
+            .sorted()
+            .dedup()
+            .collect_vec();
+
+        match strategy {
+            DedupeStrategy::Highest => {
+                for (descriptor, range) in candidates {
+                    let best_version = versions.iter()
+                        .rev()
+                        .find(|version| range.check(version));
+
+                    if let Some(version) = best_version {
+                        selected_versions.insert(descriptor, *version);
+                    }
+                }
+            },
 
-            if current_resolution != Some(&locator) {
-                Some((descriptor.clone(), locator))
-            } else {
-                None
-            }
-        })
-    };
+            DedupeStrategy::Fewer => {
+                let mut remaining
+                    = candidates;
+
+                // Greedily pick the version satisfying the most remaining
+                // descriptors (preferring the highest one on ties) until all
+                // descriptors have been assigned a version.
+                while !remaining.is_empty() {
+                    let best_version = versions.iter()
+                        .map(|version| (remaining.iter().filter(|(_, range)| range.check(version)).count(), *version))
+                        .filter(|(count, _)| *count > 0)
+                        .max();
+
+                    let Some((_, version)) = best_version else {
+                        break;
+                    };
+
+                    remaining.retain(|(descriptor, range)| {
+                        if range.check(version) {
+                            selected_versions.insert(*descriptor, version);
+                            false
+                        } else {
+                            true
+                        }
+                    });
+                }
+            },
+        }
+    }
 
     let upgradable_candidates
-        = install_state.descriptor_to_locator.keys()
-            .filter(|descriptor| patterns.is_empty() || patterns.iter().any(|matcher| matcher.check(&descriptor.ident)))
-            .filter_map(attach_highest_version)
+        = selected_versions.into_iter()
+            .map(|(descriptor, version)| (descriptor.clone(), dedupe_locator(descriptor, version)))
+            .filter(|(descriptor, locator)| install_state.descriptor_to_locator.get(descriptor) != Some(locator))
             .collect::<BTreeMap<_, _>>();
 
     Ok(upgradable_candidates)
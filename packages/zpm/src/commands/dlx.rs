@@ -8,7 +8,7 @@ use zpm_semver::RangeKind;
 use crate::{
     descriptor_loose::{self, LooseDescriptor, LooseResolution},
     error::Error,
-    install::InstallContext,
+    install::{InstallContext, InstallResult},
     project::{Project, RunInstallOptions},
     script::{Binary, ScriptEnvironment},
 };
@@ -65,7 +65,7 @@ impl DlxWithPackages {
         let descriptors
             = LooseDescriptor::resolve_all(&install_context, &resolve_options, &self.packages).await?;
 
-        let dlx_project
+        let (dlx_project, _)
             = install_dependencies(&dlx_project.project_cwd, descriptors, self.quiet).await?;
         let bin
             = find_binary(&dlx_project, self.name.as_str(), false)?;
@@ -112,7 +112,7 @@ impl Dlx {
         let preferred_name
             = resolution.descriptor.ident.name().to_string();
 
-        let dlx_project
+        let (dlx_project, _)
             = install_dependencies(&dlx_project.project_cwd, vec![resolution], self.quiet).await?;
         let bin
             = find_binary(&dlx_project, &preferred_name, true)?;
@@ -141,7 +141,7 @@ pub async fn setup_project() -> Result<Project, Error> {
     Ok(project)
 }
 
-pub async fn install_dependencies(workspace_path: &Path, loose_resolutions: Vec<LooseResolution>, quiet: bool) -> Result<Project, Error> {
+pub async fn install_dependencies(workspace_path: &Path, loose_resolutions: Vec<LooseResolution>, quiet: bool) -> Result<(Project, InstallResult), Error> {
     let manifest_path = workspace_path
         .with_join_str("package.json");
 
@@ -169,14 +169,14 @@ pub async fn install_dependencies(workspace_path: &Path, loose_resolutions: Vec<
             .filter_map(|resolution| resolution.locator.map(|locator| (resolution.descriptor, locator)))
             .collect();
 
-    project
+    let install_result = project
         .run_install(RunInstallOptions {
             silent_or_error: quiet,
             enforced_resolutions,
             ..Default::default()
         }).await?;
 
-    Ok(project)
+    Ok((project, install_result))
 }
 
 pub fn find_binary(project: &Project, preferred_name: &str, fallback: bool) -> Result<Binary, Error> {
@@ -1,8 +1,10 @@
 use std::process::ExitStatus;
 
 use clipanion::cli;
+use itertools::Itertools;
+use zpm_utils::Path;
 
-use crate::{error::Error, project, script::ScriptEnvironment};
+use crate::{error::Error, project::{self, Project}, script::ScriptEnvironment};
 
 /// Run a shell command in the package environment
 ///
@@ -11,10 +13,19 @@ use crate::{error::Error, project, script::ScriptEnvironment};
 /// It also makes sure to call it in a way that's compatible with the current project (for example, on PnP projects the environment will be setup in
 /// such a way that PnP will be correctly injected into the environment).
 ///
+/// When the command is a single program name, it's first looked up amongst the binaries visible from the active workspace (the same ones as
+/// `yarn bin`), then in the PATH, and executed directly rather than through the shell.
+///
+/// If the `--cwd` option is set, the command will be executed within the given directory, but with the environment of the active workspace.
+///
 #[cli::command(proxy)]
 #[cli::path("exec")]
 #[cli::category("Scripting commands")]
 pub struct Exec {
+    /// The directory in which to execute the command
+    #[cli::option("--cwd")]
+    cwd: Option<Path>,
+
     /// The shell command to execute
     script: String,
 
@@ -28,14 +39,123 @@ impl Exec {
             = project::Project::new(None).await?;
 
         project
-            .lazy_install().await?;
-
-        Ok(ScriptEnvironment::new()?
-            .with_project(&project)
-            .with_package(&project, &project.active_package()?)?
-            .enable_shell_forwarding()
-            .run_script(&self.script, &self.args)
-            .await?
-            .into())
+            .lazy_install_or_verify().await?;
+
+        let active_package
+            = project.active_package()?;
+
+        let mut script_environment
+            = ScriptEnvironment::new()?
+                .with_project(&project)
+                .with_package(&project, &active_package)?;
+
+        if let Some(workspace) = project.try_workspace_by_locator(&active_package)? {
+            script_environment = script_environment
+                .with_bin_path(&project, workspace)?;
+        }
+
+        // INIT_CWD keeps pointing to the directory the command was called
+        // from, since it was set from the project's shell cwd
+        if let Some(cwd) = &self.cwd {
+            script_environment = script_environment
+                .with_cwd(project.project_cwd.with_join(&project.shell_cwd).with_join(cwd));
+        }
+
+        script_environment = script_environment
+            .enable_shell_forwarding();
+
+        if !is_program_name(&self.script) {
+            return Ok(script_environment
+                .run_script(&self.script, &self.args)
+                .await?
+                .into());
+        }
+
+        match project.find_binary(&self.script) {
+            Ok(binary) => {
+                return Ok(script_environment
+                    .run_binary(&binary, &self.args)
+                    .await?
+                    .into());
+            },
+
+            // Packages excluded from the current platform may still have a
+            // system-wide counterpart, so we check the PATH before reporting
+            // them
+            Err(Error::BinaryNotFound(_)) | Err(Error::BinaryFromExcludedPackage(..)) => {},
+
+            Err(error) => {
+                return Err(error);
+            },
+        }
+
+        match script_environment.run_exec(&self.script, &self.args).await {
+            Err(Error::SpawnFailed {error, ..}) if is_not_found_error(&error) => {
+                Err(self.binary_not_found_error(&project)?)
+            },
+
+            result => {
+                Ok(result?.into())
+            },
+        }
     }
+
+    fn binary_not_found_error(&self, project: &Project) -> Result<Error, Error> {
+        if let Err(error @ Error::BinaryFromExcludedPackage(..)) = project.find_binary(&self.script) {
+            return Ok(error);
+        }
+
+        let binaries
+            = project.package_visible_binaries(&project.active_package()?)?;
+
+        let suggestions = binaries.keys()
+            .filter(|name| is_near_miss(&self.script, name))
+            .join(", ");
+
+        if suggestions.is_empty() {
+            return Ok(Error::BinaryNotFound(self.script.clone()));
+        }
+
+        Ok(Error::BinaryNotFoundWithSuggestions(self.script.clone(), suggestions))
+    }
+}
+
+/// Whether the command is a single program name rather than a shell
+/// expression or a path.
+fn is_program_name(script: &str) -> bool {
+    !script.is_empty() && !script.contains(|c: char| c.is_whitespace() || "/\\'\"$`|&;<>()*?[]{}~=#".contains(c))
+}
+
+fn is_not_found_error(error: &(dyn std::error::Error + Send + Sync)) -> bool {
+    error.downcast_ref::<std::io::Error>()
+        .is_some_and(|error| error.kind() == std::io::ErrorKind::NotFound)
+}
+
+fn is_near_miss(name: &str, candidate: &str) -> bool {
+    candidate.starts_with(name) || name.starts_with(candidate) || edit_distance(name, candidate) <= 2
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b
+        = b.chars().collect_vec();
+
+    let mut row
+        = (0..=b.len()).collect_vec();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous_diagonal
+            = row[0];
+
+        row[0] = i + 1;
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution
+                = previous_diagonal + usize::from(a_char != *b_char);
+
+            previous_diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[b.len()]
 }
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use clipanion::cli;
+use indexmap::IndexMap;
+use zpm_primitives::{Ident, Locator, PeerRange, Range};
+use zpm_utils::{AbstractValue, ToFileString, tree};
+
+use crate::{
+    error::Error,
+    install::InstallState,
+    project::Project,
+};
+
+/// Explain the peer dependency requirements of the project
+///
+/// This command lists the packages that require the given packages as peer dependencies, along with the range they require and the package
+/// currently providing them (which is always the package depending on the requester).
+///
+/// By default only the unmet requirements (missing peer dependencies and provided versions that don't satisfy the requested range) are
+/// listed; use `--all` to also list the satisfied ones. Optional peer dependencies that aren't provided aren't considered unmet.
+///
+#[cli::command]
+#[cli::path("explain", "peer-requirements")]
+#[cli::category("Dependency management")]
+pub struct ExplainPeerRequirements {
+    /// Also list the peer requirements that are satisfied
+    #[cli::option("--all", default = false)]
+    all: bool,
+
+    /// Format the output as an NDJSON stream
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    /// The peer dependencies to explain
+    identifiers: Vec<Ident>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PeerRequirementStatus {
+    Satisfied,
+    Incompatible,
+    Missing,
+    OptionalMissing,
+}
+
+impl PeerRequirementStatus {
+    fn is_unmet(&self) -> bool {
+        matches!(self, PeerRequirementStatus::Incompatible | PeerRequirementStatus::Missing)
+    }
+
+    fn to_label(&self) -> &'static str {
+        match self {
+            PeerRequirementStatus::Satisfied => "satisfied",
+            PeerRequirementStatus::Incompatible => "❌ incompatible version provided",
+            PeerRequirementStatus::Missing => "❌ missing",
+            PeerRequirementStatus::OptionalMissing => "not provided (optional)",
+        }
+    }
+}
+
+struct PeerRequirement<'a> {
+    requester: &'a Locator,
+    parent: Option<&'a Locator>,
+    range: &'a PeerRange,
+    provided_by: Option<&'a Locator>,
+    status: PeerRequirementStatus,
+}
+
+impl ExplainPeerRequirements {
+    pub async fn execute(&self) -> Result<(), Error> {
+        let mut project
+            = Project::new(None).await?;
+
+        project.lazy_install().await?;
+
+        let install_state = project
+            .install_state
+            .as_ref()
+            .ok_or(Error::InstallStateNotFound)?;
+
+        let requirements
+            = self.collect_requirements(install_state);
+
+        let mut root_children
+            = vec![];
+
+        for (ident, requirements) in requirements {
+            let mut requirement_nodes
+                = IndexMap::new();
+
+            for requirement in requirements {
+                let mut fields
+                    = IndexMap::new();
+
+                if let Some(parent) = requirement.parent {
+                    fields.insert("Parent".to_string(), tree::Node {
+                        label: Some("Parent".to_string()),
+                        value: Some(AbstractValue::new(parent.clone())),
+                        children: None,
+                    });
+                }
+
+                fields.insert("Range".to_string(), tree::Node {
+                    label: Some("Range".to_string()),
+                    value: Some(AbstractValue::new(requirement.range.clone())),
+                    children: None,
+                });
+
+                fields.insert("Provided by".to_string(), tree::Node {
+                    label: Some("Provided by".to_string()),
+                    value: Some(requirement.provided_by.map_or_else(|| AbstractValue::new("nothing".to_string()), |locator| AbstractValue::new(locator.clone()))),
+                    children: None,
+                });
+
+                fields.insert("Status".to_string(), tree::Node {
+                    label: Some("Status".to_string()),
+                    value: Some(AbstractValue::new(requirement.status.to_label().to_string())),
+                    children: None,
+                });
+
+                requirement_nodes.insert(requirement.requester.to_file_string(), tree::Node {
+                    label: None,
+                    value: Some(AbstractValue::new(requirement.requester.clone())),
+                    children: Some(tree::TreeNodeChildren::Map(fields)),
+                });
+            }
+
+            root_children.push(tree::Node {
+                label: None,
+                value: Some(AbstractValue::new(ident.clone())),
+                children: Some(tree::TreeNodeChildren::Map(requirement_nodes)),
+            });
+        }
+
+        let root_node = tree::Node {
+            label: None,
+            value: None,
+            children: Some(tree::TreeNodeChildren::Vec(root_children)),
+        };
+
+        let rendering
+            = tree::TreeRenderer::new()
+                .render(&root_node, self.json);
+
+        print!("{}", rendering);
+
+        Ok(())
+    }
+
+    fn collect_requirements<'a>(&self, install_state: &'a InstallState) -> BTreeMap<&'a Ident, Vec<PeerRequirement<'a>>> {
+        let resolution_tree
+            = &install_state.resolution_tree;
+
+        // Virtual packages are the ones whose peer dependencies got
+        // resolved; the package depending on them is the one that's
+        // supposed to provide their peers.
+        let mut parents
+            = BTreeMap::new();
+
+        for (locator, resolution) in &resolution_tree.locator_resolutions {
+            for descriptor in resolution.dependencies.values() {
+                if let Some(dependency) = resolution_tree.descriptor_to_locator.get(descriptor) {
+                    if dependency.reference.is_virtual_reference() {
+                        parents.insert(dependency, locator);
+                    }
+                }
+            }
+        }
+
+        let mut requirements: BTreeMap<&Ident, Vec<PeerRequirement>>
+            = BTreeMap::new();
+
+        let virtual_resolutions = resolution_tree.locator_resolutions.iter()
+            .filter(|(locator, _)| locator.reference.is_virtual_reference());
+
+        for (locator, resolution) in virtual_resolutions {
+            for (peer_ident, peer_range) in &resolution.peer_dependencies {
+                if !self.identifiers.is_empty() && !self.identifiers.contains(peer_ident) {
+                    continue;
+                }
+
+                let provided_by = resolution.dependencies.get(peer_ident)
+                    .filter(|descriptor| !matches!(descriptor.range, Range::MissingPeerDependency))
+                    .and_then(|descriptor| resolution_tree.descriptor_to_locator.get(descriptor));
+
+                let status = match provided_by {
+                    Some(provider) => {
+                        let provided_version = resolution_tree.locator_resolutions.get(provider)
+                            .map(|provider_resolution| &provider_resolution.version);
+
+                        match (peer_range, provided_version) {
+                            (PeerRange::Semver(params), Some(version)) if !params.range.check(version)
+                                => PeerRequirementStatus::Incompatible,
+
+                            _ => PeerRequirementStatus::Satisfied,
+                        }
+                    },
+
+                    None if resolution.optional_peer_dependencies.contains(peer_ident)
+                        => PeerRequirementStatus::OptionalMissing,
+
+                    None
+                        => PeerRequirementStatus::Missing,
+                };
+
+                if !self.all && !status.is_unmet() {
+                    continue;
+                }
+
+                requirements.entry(peer_ident).or_default().push(PeerRequirement {
+                    requester: locator,
+                    parent: parents.get(locator).copied(),
+                    range: peer_range,
+                    provided_by,
+                    status,
+                });
+            }
+        }
+
+        requirements
+    }
+}
@@ -0,0 +1,393 @@
+use std::time::{Duration, SystemTime};
+
+use clipanion::cli;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use zpm_primitives::{Ident, Locator};
+use zpm_utils::{IoResultExt, ToFileString};
+
+use crate::{
+    error::Error,
+    manifest::HardDependencyKind,
+    project::Project,
+};
+
+/// The version of the protocol spoken by the server. It must be bumped each
+/// time a method or a payload changes in a backward-incompatible way.
+const PROTOCOL_VERSION: u64 = 1;
+
+/// How often the install state file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often we try to load the project again after it failed to load (for
+/// instance because of a syntax error in a manifest).
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+const INCOMPATIBLE_PROTOCOL: i64 = -32001;
+const HANDSHAKE_REQUIRED: i64 = -32002;
+
+/// Start a server for editor integrations
+///
+/// This command starts a long-running server that editor extensions can query instead of repeatedly spawning Yarn
+/// commands. The server speaks JSON-RPC 2.0 over stdio, one message per line, and exits once its standard input is
+/// closed.
+///
+/// The first request must be a `handshake` call with a `protocolVersion` parameter; the server rejects it if it
+/// doesn't implement the requested version, and rejects any other request until a handshake succeeded. The
+/// following read-only methods are then available:
+///
+/// - `resolveRequest(ident, from)` returns the package a dependency resolves to from the given locator
+/// - `getWorkspaces()` lists the workspaces of the project
+/// - `getDependents(locator)` lists the packages depending on the given locator
+/// - `getDiagnostics(workspace)` lists the problems affecting the given workspace
+/// - `subscribeInstallState()` enables `installStateChanged` notifications
+///
+/// The server never installs the project nor locks it; it only reads the install state, and reloads its view of the
+/// project whenever the install state changes on disk.
+///
+#[cli::command]
+#[cli::path("ide-server")]
+#[cli::category("Project management")]
+pub struct IdeServer {
+}
+
+#[derive(Deserialize)]
+struct Request {
+    id: Option<serde_json::Value>,
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HandshakeParams {
+    protocol_version: u64,
+}
+
+#[derive(Deserialize)]
+struct ResolveRequestParams {
+    ident: Ident,
+    from: Locator,
+}
+
+#[derive(Deserialize)]
+struct GetDependentsParams {
+    locator: Locator,
+}
+
+#[derive(Deserialize)]
+struct GetDiagnosticsParams {
+    workspace: Ident,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {code, message: message.into()}
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(error: Error) -> Self {
+        RpcError::new(SERVER_ERROR, error.to_string())
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: serde_json::Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+struct ServerState {
+    project: Result<Project, Error>,
+    install_state_mtime: Option<SystemTime>,
+    handshake_done: bool,
+    subscribed: bool,
+}
+
+impl ServerState {
+    fn project(&self) -> Result<&Project, RpcError> {
+        self.project.as_ref()
+            .map_err(|err| err.clone().into())
+    }
+
+    fn handle(&mut self, method: &str, params: serde_json::Value) -> Result<serde_json::Value, RpcError> {
+        if method == "handshake" {
+            let params: HandshakeParams
+                = parse_params(params)?;
+
+            if params.protocol_version != PROTOCOL_VERSION {
+                return Err(RpcError::new(INCOMPATIBLE_PROTOCOL, format!("Unsupported protocol version {} (the server implements version {})", params.protocol_version, PROTOCOL_VERSION)));
+            }
+
+            self.handshake_done = true;
+
+            return Ok(json!({"protocolVersion": PROTOCOL_VERSION}));
+        }
+
+        if !self.handshake_done {
+            return Err(RpcError::new(HANDSHAKE_REQUIRED, "A handshake is required before calling any other method"));
+        }
+
+        match method {
+            "resolveRequest" => self.resolve_request(parse_params(params)?),
+            "getWorkspaces" => self.get_workspaces(),
+            "getDependents" => self.get_dependents(parse_params(params)?),
+            "getDiagnostics" => self.get_diagnostics(parse_params(params)?),
+
+            "subscribeInstallState" => {
+                self.subscribed = true;
+                Ok(json!(true))
+            },
+
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+        }
+    }
+
+    fn resolve_request(&self, params: ResolveRequestParams) -> Result<serde_json::Value, RpcError> {
+        let project
+            = self.project()?;
+        let install_state
+            = project.install_state.as_ref().ok_or(Error::InstallStateNotFound)?;
+
+        let resolution
+            = install_state.resolution_tree.locator_resolutions.get(&params.from)
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Package not found: {}", params.from.to_file_string())))?;
+
+        let Some(descriptor) = resolution.dependencies.get(&params.ident) else {
+            return Ok(serde_json::Value::Null);
+        };
+
+        let Some(locator) = install_state.resolution_tree.descriptor_to_locator.get(descriptor) else {
+            return Ok(serde_json::Value::Null);
+        };
+
+        let location
+            = install_state.locations_by_package.get(locator)
+                .map(|location| project.project_cwd.with_join(location));
+
+        Ok(json!({
+            "descriptor": descriptor,
+            "locator": locator,
+            "location": location,
+        }))
+    }
+
+    fn get_workspaces(&self) -> Result<serde_json::Value, RpcError> {
+        let project
+            = self.project()?;
+
+        let workspaces = project.workspaces.iter()
+            .map(|workspace| json!({
+                "name": workspace.name,
+                "cwd": workspace.path,
+                "locator": workspace.locator(),
+            }))
+            .collect::<Vec<_>>();
+
+        Ok(json!(workspaces))
+    }
+
+    fn get_dependents(&self, params: GetDependentsParams) -> Result<serde_json::Value, RpcError> {
+        let project
+            = self.project()?;
+        let install_state
+            = project.install_state.as_ref().ok_or(Error::InstallStateNotFound)?;
+
+        let dependents = install_state.resolution_tree.locator_resolutions.values()
+            .filter(|resolution| resolution.dependencies.values().any(|descriptor| {
+                install_state.resolution_tree.descriptor_to_locator.get(descriptor)
+                    .is_some_and(|locator| locator.physical_locator() == params.locator.physical_locator())
+            }))
+            .map(|resolution| &resolution.locator)
+            .collect::<Vec<_>>();
+
+        Ok(json!(dependents))
+    }
+
+    fn get_diagnostics(&self, params: GetDiagnosticsParams) -> Result<serde_json::Value, RpcError> {
+        let project
+            = self.project()?;
+        let workspace
+            = project.workspace_by_ident(&params.workspace)?;
+
+        let mut diagnostics
+            = vec![];
+
+        let Some(install_state) = &project.install_state else {
+            diagnostics.push(json!({"code": "missingInstallState", "message": "The project hasn't been installed yet"}));
+            return Ok(json!(diagnostics));
+        };
+
+        if project.lockfile_hash()? != install_state.lockfile_hash {
+            diagnostics.push(json!({"code": "outdatedInstallState", "message": "The lockfile changed since the last install"}));
+        }
+
+        if workspace.last_changed_at > install_state.last_installed_at {
+            diagnostics.push(json!({"code": "outdatedInstallState", "message": "The manifest changed since the last install"}));
+        }
+
+        let resolution
+            = install_state.resolution_tree.locator_resolutions.get(&workspace.locator());
+
+        for dependency in workspace.manifest.iter_hard_dependencies() {
+            // Optional dependencies may legitimately be missing when they
+            // don't support the current platform.
+            if matches!(dependency.kind, HardDependencyKind::OptionalDependency) {
+                continue;
+            }
+
+            let is_installed
+                = resolution.is_some_and(|resolution| resolution.dependencies.contains_key(&dependency.descriptor.ident));
+
+            if !is_installed {
+                diagnostics.push(json!({
+                    "code": "missingDependency",
+                    "message": format!("{} is listed in {} but isn't installed", dependency.descriptor.ident.to_file_string(), dependency.kind.to_str()),
+                    "ident": dependency.descriptor.ident,
+                }));
+            }
+        }
+
+        Ok(json!(diagnostics))
+    }
+
+    async fn reload(&mut self) {
+        self.project = load_project().await;
+    }
+}
+
+async fn load_project() -> Result<Project, Error> {
+    let mut project
+        = Project::new(None).await?;
+
+    match project.import_install_state() {
+        Ok(_) | Err(Error::InstallStateNotFound | Error::InvalidInstallState) => {},
+        Err(err) => return Err(err),
+    }
+
+    Ok(project)
+}
+
+fn install_state_mtime(project: &Result<Project, Error>) -> Result<Option<SystemTime>, Error> {
+    let Ok(project) = project else {
+        return Ok(None);
+    };
+
+    let metadata = project.install_state_path()
+        .fs_metadata()
+        .ok_missing()?;
+
+    Ok(metadata.and_then(|metadata| metadata.modified().ok()))
+}
+
+async fn write_message(stdout: &mut tokio::io::Stdout, message: serde_json::Value) -> Result<(), Error> {
+    stdout.write_all(format!("{}\n", message).as_bytes()).await?;
+    stdout.flush().await?;
+
+    Ok(())
+}
+
+impl IdeServer {
+    pub async fn execute(&self) -> Result<(), Error> {
+        let project
+            = load_project().await;
+
+        let mut state = ServerState {
+            install_state_mtime: install_state_mtime(&project)?,
+            project,
+            handshake_done: false,
+            subscribed: false,
+        };
+
+        let mut lines
+            = BufReader::new(tokio::io::stdin()).lines();
+        let mut stdout
+            = tokio::io::stdout();
+
+        let mut watch_interval
+            = tokio::time::interval(WATCH_INTERVAL);
+        let mut retry_interval
+            = tokio::time::interval(RETRY_INTERVAL);
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => {
+                    let Some(line) = line? else {
+                        break;
+                    };
+
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+
+                    let request = match serde_json::from_str::<Request>(&line) {
+                        Ok(request) => request,
+                        Err(err) => {
+                            write_message(&mut stdout, json!({"jsonrpc": "2.0", "id": null, "error": {"code": PARSE_ERROR, "message": err.to_string()}})).await?;
+                            continue;
+                        },
+                    };
+
+                    let result
+                        = state.handle(&request.method, request.params);
+
+                    // Requests without an id are notifications, which never
+                    // get a response.
+                    let Some(id) = request.id else {
+                        continue;
+                    };
+
+                    let response = match result {
+                        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                        Err(err) => json!({"jsonrpc": "2.0", "id": id, "error": {"code": err.code, "message": err.message}}),
+                    };
+
+                    write_message(&mut stdout, response).await?;
+                },
+
+                _ = watch_interval.tick() => {
+                    let mtime
+                        = install_state_mtime(&state.project)?;
+
+                    if mtime == state.install_state_mtime {
+                        continue;
+                    }
+
+                    state.reload().await;
+                    state.install_state_mtime = install_state_mtime(&state.project)?;
+
+                    if state.subscribed {
+                        write_message(&mut stdout, json!({"jsonrpc": "2.0", "method": "installStateChanged", "params": {}})).await?;
+                    }
+                },
+
+                _ = retry_interval.tick(), if state.project.is_err() => {
+                    state.reload().await;
+
+                    if state.project.is_err() {
+                        continue;
+                    }
+
+                    state.install_state_mtime = install_state_mtime(&state.project)?;
+
+                    if state.subscribed {
+                        write_message(&mut stdout, json!({"jsonrpc": "2.0", "method": "installStateChanged", "params": {}})).await?;
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -2,11 +2,14 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use clipanion::cli;
 use indexmap::IndexMap;
-use zpm_primitives::{DescriptorResolution, IdentGlob, IdentResolution, Locator, Reference};
-use zpm_utils::{tree, AbstractValue, Unit, ToFileString};
+use itertools::Itertools;
+use zpm_formats::zip::ZipSupport;
+use zpm_parsers::JsonDocument;
+use zpm_primitives::{AnonymousSemverRange, AnonymousTagRange, DescriptorResolution, Ident, IdentGlob, IdentResolution, Locator, Range, Reference};
+use zpm_utils::{tree, AbstractValue, FromFileString, Unit, ToFileString};
 
 use crate::{
-    cache::CompositeCache, error::Error, install::InstallState, project::{Project, Workspace}
+    cache::CompositeCache, commands::why, descriptor_loose::{DescriptorLooseDescriptor, IdentLooseDescriptor, LooseDescriptor}, error::Error, http_npm::PackumentFormat, install::InstallState, project::{Project, Workspace}, resolvers
 };
 
 /// See information related to packages
@@ -23,6 +26,8 @@ use crate::{
 /// Note that this command will only print the information directly related to the selected packages - if you wish to know why the package is there
 /// in the first place, use yarn why which will do just that (it also provides a -R,--recursive flag that may be of some help).
 ///
+/// To see the manifest of a package as published on the registry instead, use the `--registry` flag.
+///
 #[cli::command]
 #[cli::path("info")]
 #[cli::category("Dependency management")]
@@ -34,6 +39,8 @@ By default, if the locator reference is missing, Yarn will default to print the
 Some fields will be hidden by default in order to keep the output readable, but can be selectively displayed by using additional options (`--dependents`, `--manifest`, `--virtuals`, ...) described in the option descriptions.
 
 Note that this command will only print the information directly related to the selected packages - if you wish to know why the package is there in the first place, use `yarn why` which will do just that (it also provides a `-R,--recursive` flag that may be of some help).
+
+To see the manifest of a package as published on the registry instead, use the `--registry` flag.
 "#)]
 pub struct Info {
     /// Print versions of a package from the whole project
@@ -197,6 +204,10 @@ impl Info {
                 children: None,
             });
 
+            if let Some(status_node) = why::platform_status_node(install_state, &locator) {
+                children.insert("Status".to_string(), status_node);
+            }
+
             if virtual_instances.len() > 0 {
                 children.insert("Instances".to_string(), tree::Node {
                     label: Some("Instances".to_string()),
@@ -526,3 +537,215 @@ impl Info {
         Ok(virtual_map)
     }
 }
+
+/// See the manifest of a package published on the registry
+///
+/// When the `--registry` option is set, this command fetches the manifest of the given package from the registry and prints it, regardless of
+/// whether the package is part of the project. If a range is provided, the highest version matching it will be selected; if a tag is provided,
+/// the version it points to will be selected. The `latest` tag is used by default.
+///
+/// Field paths (for example `version` or `dist.tarball`) can be provided after the package name to only print the matching values. Fields that
+/// don't exist are skipped, unless the `--strict` option is set, in which case they fail and list the available top-level fields.
+///
+/// If the `--version <range>` option is set, the highest version matching the range will be selected; it's an alternative to adding the range
+/// to the package name.
+///
+/// If the `--cached` option is set, the manifest will be read from the lockfile and the cache instead of the registry, without making any
+/// network request. This only works for packages that are already installed in the project.
+///
+/// If the `--json` option is set, the output will be formatted as JSON.
+///
+#[cli::command]
+#[cli::path("info")]
+#[cli::category("Dependency management")]
+pub struct InfoRegistry {
+    #[cli::option("--registry")]
+    _registry: bool,
+
+    /// Read the package manifest from the lockfile and the cache instead of the registry
+    #[cli::option("--cached", default = false)]
+    cached: bool,
+
+    /// Format the output as JSON
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    /// Fail when a requested field doesn't exist
+    #[cli::option("--strict", default = false)]
+    strict: bool,
+
+    /// Select the highest version matching the given range
+    #[cli::option("--version")]
+    version: Option<zpm_semver::Range>,
+
+    /// The package to inspect
+    package: LooseDescriptor,
+
+    /// The fields to print
+    fields: Vec<zpm_parsers::Path>,
+}
+
+impl InfoRegistry {
+    pub async fn execute(&self) -> Result<(), Error> {
+        let project
+            = Project::new(None).await?;
+
+        let (ident, range) = match &self.package {
+            LooseDescriptor::Descriptor(DescriptorLooseDescriptor {descriptor})
+                => (&descriptor.ident, Some(descriptor.range.clone())),
+
+            LooseDescriptor::Ident(IdentLooseDescriptor {ident})
+                => (ident, None),
+
+            LooseDescriptor::Range(params)
+                => return Err(Error::UnsufficientLooseDescriptor(params.range.clone())),
+        };
+
+        let range = match (range, &self.version) {
+            (Some(_), Some(_)) => {
+                return Err(Error::ConflictingOptions("The --version option can't be used when the package name already contains a range".to_string()));
+            },
+
+            (None, Some(version)) => {
+                Some(AnonymousSemverRange {range: version.clone()}.into())
+            },
+
+            (range, None) => {
+                range
+            },
+        };
+
+        let manifest = if self.cached {
+            self.read_cached_manifest(&project, ident, range.as_ref())?
+        } else {
+            self.fetch_manifest(&project, ident, range.as_ref()).await?
+        };
+
+        if self.fields.is_empty() {
+            self.print_value(&manifest)?;
+            return Ok(());
+        }
+
+        for field in &self.fields {
+            let value = field.segments().iter()
+                .try_fold(&manifest, |value, segment| match value {
+                    serde_json::Value::Object(object) => object.get(segment),
+                    serde_json::Value::Array(array) => segment.parse::<usize>().ok().and_then(|idx| array.get(idx)),
+                    _ => None,
+                });
+
+            let Some(value) = value else {
+                if !self.strict {
+                    continue;
+                }
+
+                let available_fields = manifest.as_object()
+                    .map(|object| object.keys().join(", "))
+                    .unwrap_or_default();
+
+                return Err(Error::UnknownPackageInfoField(field.to_file_string(), available_fields));
+            };
+
+            self.print_value(value)?;
+        }
+
+        Ok(())
+    }
+
+    fn print_value(&self, value: &serde_json::Value) -> Result<(), Error> {
+        match value {
+            serde_json::Value::String(value) if !self.json => {
+                println!("{}", value);
+            },
+
+            _ if self.json => {
+                println!("{}", JsonDocument::to_string(value)?);
+            },
+
+            _ => {
+                println!("{}", JsonDocument::to_string_pretty(value)?);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn read_cached_manifest(&self, project: &Project, ident: &Ident, range: Option<&Range>) -> Result<serde_json::Value, Error> {
+        let semver_range = match range {
+            None => None,
+            Some(Range::AnonymousSemver(AnonymousSemverRange {range})) => Some(range),
+            Some(range) => return Err(Error::NoCandidatesFound(range.clone())),
+        };
+
+        let lockfile
+            = project.lockfile()?;
+
+        let locator = lockfile.entries.values()
+            .filter(|entry| entry.resolution.locator.ident == *ident)
+            .filter(|entry| semver_range.map_or(true, |range| range.check(&entry.resolution.version)))
+            .max_by(|a, b| a.resolution.version.cmp(&b.resolution.version))
+            .map(|entry| entry.resolution.locator.clone())
+            .ok_or_else(|| Error::PackageNotFound(ident.clone()))?;
+
+        let registry_ident = match &locator.reference {
+            Reference::Shorthand(_) => &locator.ident,
+            Reference::Registry(params) => &params.ident,
+            _ => return Err(Error::PackageNotFound(ident.clone())),
+        };
+
+        let manifest_path = project.package_cache()?
+            .key_path(&locator, ".zip")
+            .with_join(&registry_ident.nm_subdir())
+            .with_join_str("package.json");
+
+        let manifest_text = manifest_path
+            .fs_read_text_with_zip()
+            .map_err(|_| Error::PackageNotFound(ident.clone()))?;
+
+        Ok(JsonDocument::hydrate_from_str(&manifest_text)?)
+    }
+
+    async fn fetch_manifest(&self, project: &Project, ident: &Ident, range: Option<&Range>) -> Result<serde_json::Value, Error> {
+        let response
+            = resolvers::npm::fetch_packument_with_format(project, ident, PackumentFormat::Full, true).await?;
+
+        let mut packument: serde_json::Value
+            = JsonDocument::hydrate_from_slice(&response[..])?;
+
+        let tag = match range {
+            None => Some("latest"),
+            Some(Range::AnonymousTag(AnonymousTagRange {tag})) => Some(tag.as_str()),
+            Some(Range::AnonymousSemver(_)) => None,
+            Some(range) => return Err(Error::NoCandidatesFound(range.clone())),
+        };
+
+        let version = match (tag, range) {
+            (Some(tag), _) => {
+                packument.get("dist-tags")
+                    .and_then(|dist_tags| dist_tags.get(tag))
+                    .and_then(|version| version.as_str())
+                    .map(|version| version.to_string())
+                    .ok_or_else(|| Error::TagNotFound(tag.to_string()))?
+            },
+
+            (None, Some(Range::AnonymousSemver(AnonymousSemverRange {range: semver_range}))) => {
+                packument.get("versions")
+                    .and_then(|versions| versions.as_object())
+                    .into_iter()
+                    .flat_map(|versions| versions.keys())
+                    .filter_map(|version| zpm_semver::Version::from_file_string(version).ok())
+                    .filter(|version| semver_range.check(version))
+                    .max()
+                    .map(|version| version.to_file_string())
+                    .ok_or_else(|| Error::NoCandidatesFound(range.unwrap().clone()))?
+            },
+
+            _ => unreachable!(),
+        };
+
+        packument.get_mut("versions")
+            .and_then(|versions| versions.get_mut(&version))
+            .map(|manifest| manifest.take())
+            .ok_or_else(|| Error::PackageNotFound(ident.clone()))
+    }
+}
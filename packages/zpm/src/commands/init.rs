@@ -1,15 +1,20 @@
+use std::{borrow::Cow, collections::{BTreeMap, BTreeSet}};
+
 use clipanion::cli;
+use zpm_config::Setting;
+use zpm_formats::{iter_ext::IterExt, Entry};
 use zpm_parsers::{Document, JsonDocument, Value};
-use zpm_primitives::Ident;
-use zpm_utils::{IoResultExt, Path, ToFileString};
+use zpm_primitives::{Descriptor, Ident, PortalRange};
+use zpm_utils::{DataType, FromFileString, Glob, IoResultExt, Path, ToFileString, ToHumanString};
 
 use crate::{
     commands::dlx,
-    descriptor_loose::{self, LooseDescriptor},
+    descriptor_loose::{self, LooseDescriptor, LooseResolution},
     error::Error,
+    fetchers::PackageData,
     install::InstallContext,
-    manifest::Manifest,
-    project::{Project, RunInstallOptions},
+    manifest::{Manifest, helpers::read_manifest},
+    project::{Project, RunInstallOptions, MANIFEST_NAME},
     script::ScriptEnvironment,
 };
 
@@ -17,7 +22,13 @@ use crate::{
 ///
 /// If the `-p,--private` or `-w,--workspace` options are set, the package will be private by default.
 ///
-/// If the `-w,--workspace` option is set, the package will be configured to accept a set of workspaces in the `packages/` directory.
+/// If the `-w,--workspace` option is set, the package will be configured to accept a set of workspaces in the `packages/` directory. If it's
+/// given a path instead (`--workspace=packages/foo`), Yarn will scaffold a new workspace in this folder and add it to the `workspaces` field of
+/// the project's root manifest.
+///
+/// If the `-t,--template` option is set, Yarn will fetch the given package (from the registry, a git repository, ...) and extract its files into
+/// the new project, replacing the `{{name}}` and `{{author}}` placeholders in text files. If the template declares an `init` script, it will then
+/// run in the new project, provided the template is allowed to run scripts (see `enableScripts`).
 ///
 /// If the `-i,--install` option is given a value, Yarn will first download it using `yarn set version` and only then forward the init call to the
 /// newly downloaded bundle. Without arguments, the downloaded bundle will be latest.
@@ -25,6 +36,10 @@ use crate::{
 /// The initial settings of the manifest can be changed by using the `initScope` and `initFields` configuration values. Additionally, Yarn will
 /// generate an `.editorconfig` file whose rules can be altered via `initEditorConfig`, and will initialize a Git repository in the current directory.
 ///
+/// Templates are first looked up in the folders listed in `initTemplateFolders` (for example `templates/@acme/template-lib`), and only resolved
+/// from the registry if no local folder matches. Local templates are used in place, without being copied into the cache. A template may list
+/// other templates in the `extends` field of its manifest; they'll be resolved the same way and run before it.
+///
 #[cli::command(proxy)]
 #[cli::path("init")]
 #[cli::category("Project management")]
@@ -82,33 +97,67 @@ impl InitWithTemplate {
             .with_package_cache(Some(&package_cache))
             .with_project(Some(&project));
 
-        let template
-            = self.template.resolve(&install_context, &resolve_options).await?;
+        let mut templates
+            = Vec::new();
+
+        collect_templates(&install_context, &resolve_options, &project.config.settings.init_template_folders, &self.template, &mut Vec::new(), &mut BTreeSet::new(), &mut templates).await?;
+
+        let mut template_resolutions
+            = Vec::new();
+
+        for template in templates {
+            let template_resolution = match template {
+                TemplateSource::Folder {ident, path} => {
+                    println!("Using {} from {}", ident.to_print_string(), path.to_print_string());
+
+                    LooseResolution {
+                        descriptor: Descriptor::new(ident, PortalRange {path: path.to_file_string()}.into()),
+                        locator: None,
+                    }
+                },
+
+                TemplateSource::Remote(resolution) => {
+                    println!("Using {} (remote template)", resolution.descriptor.to_print_string());
 
-        let preferred_name
-            = template.descriptor.ident.name().to_string();
+                    resolution
+                },
+            };
+
+            template_resolutions.push(template_resolution);
+        }
 
         let enforced_resolutions
-            = vec![template.clone()].into_iter()
-                .filter_map(|resolution| resolution.locator.map(|locator| (resolution.descriptor, locator)))
-                .collect();
+            = template_resolutions.iter()
+                .filter_map(|resolution| resolution.locator.clone().map(|locator| (resolution.descriptor.clone(), locator)))
+                .collect::<BTreeMap<_, _>>();
 
         project.run_install(RunInstallOptions {
             enforced_resolutions,
             ..Default::default()
         }).await?;
 
-        println!();
+        for template in template_resolutions {
+            let preferred_name
+                = template.descriptor.ident.name().to_string();
+
+            println!();
+
+            let dlx_project
+                = dlx::setup_project().await?;
+            let (dlx_project, _)
+                = dlx::install_dependencies(&dlx_project.project_cwd, vec![template], false).await?;
+            let bin
+                = dlx::find_binary(&dlx_project, &preferred_name, true)?;
 
-        let dlx_project
-            = dlx::setup_project().await?;
-        let dlx_project
-            = dlx::install_dependencies(&dlx_project.project_cwd, vec![template], false).await?;
-        let bin
-            = dlx::find_binary(&dlx_project, &preferred_name, true)?;
+            println!();
 
-        println!();
-        dlx::run_binary(&dlx_project, bin, self.args.clone(), init_cwd.clone()).await?;
+            let status
+                = dlx::run_binary(&dlx_project, bin, self.args.clone(), init_cwd.clone()).await?;
+
+            if !status.success() {
+                return Err(Error::SilentError);
+            }
+        }
 
         Ok(())
     }
@@ -121,12 +170,15 @@ pub struct Init {
     #[cli::option("-p,--private")]
     private: Option<bool>,
 
-    #[cli::option("-w,--workspace", default = false)]
-    workspace: bool,
+    #[cli::option("-w,--workspace")]
+    workspace: Option<Option<Path>>,
 
     #[cli::option("-n,--name")]
     name: Option<String>,
 
+    #[cli::option("-t,--template")]
+    template: Option<LooseDescriptor>,
+
     // Hidden legacy options
     #[cli::option("-2", default = false)]
     usev2: bool,
@@ -140,21 +192,323 @@ impl Init {
         let init_cwd
             = Path::current_dir()?;
 
+        if let Some(Some(workspace_path)) = &self.workspace {
+            return self.init_workspace(&init_cwd, workspace_path).await;
+        }
+
         let params = InitParams {
             private: self.private,
-            workspace: self.workspace,
+            workspace: self.workspace.is_some(),
             name: self.name.clone(),
         };
 
         let mut project
             = init_project(&init_cwd, params).await?;
 
+        if let Some(template) = &self.template {
+            apply_template(&project, &init_cwd, template).await?;
+
+            // The init script may have changed the manifest
+            project = Project::new(Some(init_cwd.clone())).await?;
+        }
+
         project.run_install(RunInstallOptions {
             ..Default::default()
         }).await?;
 
         Ok(())
     }
+
+    async fn init_workspace(&self, init_cwd: &Path, workspace_path: &Path) -> Result<(), Error> {
+        let (project_cwd, _)
+            = Project::find_closest_project(init_cwd.clone())?;
+
+        let workspace_cwd
+            = init_cwd.with_join(workspace_path);
+
+        let workspace_rel_path = workspace_cwd.forward_relative_to(&project_cwd)
+            .filter(|_| workspace_cwd != project_cwd)
+            .ok_or_else(|| Error::WorkspaceOutsideProject(workspace_cwd.clone()))?;
+
+        let manifest_path
+            = workspace_cwd.with_join_str(MANIFEST_NAME);
+
+        if !manifest_path.fs_exists() {
+            let name = self.name.as_ref()
+                .map(|n| Ident::new(n))
+                .unwrap_or_else(|| Ident::new(workspace_cwd.basename().unwrap_or("package")));
+
+            let mut document
+                = JsonDocument::new(b"{}".to_vec())?;
+
+            document.set_path(
+                &zpm_parsers::Path::from_segments(vec!["name".to_string()]),
+                Value::String(name.to_file_string()),
+            )?;
+
+            if self.private == Some(true) {
+                document.set_path(
+                    &zpm_parsers::Path::from_segments(vec!["private".to_string()]),
+                    Value::Bool(true),
+                )?;
+            }
+
+            manifest_path
+                .fs_create_parent()?
+                .fs_write(&document.input)?;
+        }
+
+        let root_manifest_path
+            = project_cwd.with_join_str(MANIFEST_NAME);
+        let root_manifest
+            = read_manifest(&root_manifest_path)?;
+
+        let mut patterns
+            = root_manifest.workspaces.unwrap_or_default();
+
+        let workspace_rel_path
+            = workspace_rel_path.to_file_string();
+
+        let is_already_listed = patterns.iter().any(|pattern| {
+            Glob::parse(pattern.as_str()).is_ok_and(|glob| glob.is_match(&workspace_rel_path))
+        });
+
+        if !is_already_listed {
+            patterns.push(workspace_rel_path);
+
+            let mut document
+                = JsonDocument::new(root_manifest_path.fs_read_prealloc()?)?;
+
+            document.set_path(
+                &zpm_parsers::Path::from_segments(vec!["workspaces".to_string()]),
+                Value::Array(patterns.into_iter().map(Value::String).collect()),
+            )?;
+
+            root_manifest_path
+                .fs_change(&document.input, false)?;
+        }
+
+        let mut project
+            = Project::new(Some(workspace_cwd)).await?;
+
+        project.run_install(RunInstallOptions {
+            ..Default::default()
+        }).await?;
+
+        Ok(())
+    }
+}
+
+/// Extracts the files of the template package into the target directory,
+/// then runs its `init` script if the template is allowed to run scripts.
+/// The template's own manifest isn't extracted, since it describes the
+/// template rather than the project being created.
+async fn apply_template(project: &Project, init_cwd: &Path, template: &LooseDescriptor) -> Result<(), Error> {
+    let resolve_options = descriptor_loose::ResolveOptions {
+        active_workspace_ident: project.active_workspace()?.name.clone(),
+        range_kind: zpm_semver::RangeKind::Exact,
+        resolve_tags: true,
+        allow_reuse: true,
+    };
+
+    let package_cache
+        = project.package_cache()?;
+
+    let install_context = InstallContext::default()
+        .with_package_cache(Some(&package_cache))
+        .with_project(Some(project));
+
+    let resolution
+        = template.resolve(&install_context, &resolve_options).await?;
+
+    let template_ident
+        = resolution.descriptor.ident.clone();
+
+    println!("Using {} (template)", resolution.descriptor.to_print_string());
+
+    let (archive_data, package_subpath)
+        = fetch_template_archive(resolution).await?;
+
+    let (template_manifest, mut entries)
+        = read_template_archive(&archive_data, &package_subpath)?;
+
+    let name = project.active_workspace()?.name
+        .to_file_string();
+
+    let author = ScriptEnvironment::new()?
+        .run_exec("git", ["config", "user.name"])
+        .await.ok()
+        .and_then(|result| result.ok().ok())
+        .and_then(|result| result.stdout_text().ok())
+        .unwrap_or_default();
+
+    for entry in &mut entries {
+        let Ok(text) = std::str::from_utf8(&entry.data) else {
+            continue;
+        };
+
+        if !text.contains("{{") {
+            continue;
+        }
+
+        let text = text
+            .replace("{{name}}", &name)
+            .replace("{{author}}", &author);
+
+        entry.data = Cow::Owned(text.into_bytes());
+    }
+
+    zpm_formats::entries_to_disk(&entries, init_cwd)?;
+
+    let Some(init_script) = template_manifest.scripts.get("init") else {
+        return Ok(());
+    };
+
+    let is_script_allowed
+        = project.is_build_allowed(&template_ident, template_manifest.remote.version.as_ref());
+
+    if !is_script_allowed {
+        println!("Skipped the init script of {}; add it to {} to run it", template_ident.to_print_string(), DataType::Code.colorize("allowedBuildDependencies"));
+        return Ok(());
+    }
+
+    ScriptEnvironment::new()?
+        .with_project(project)
+        .with_cwd(init_cwd.clone())
+        .with_lifecycle_event("init", init_script)
+        .enable_shell_forwarding()
+        .run_script(init_script, Vec::<String>::new())
+        .await?
+        .ok()?;
+
+    Ok(())
+}
+
+/// Installs the template into a temporary project and returns the content of
+/// its archive, along with the path of the package within it.
+async fn fetch_template_archive(resolution: LooseResolution) -> Result<(Vec<u8>, Path), Error> {
+    let template_ident
+        = resolution.descriptor.ident.clone();
+
+    let dlx_project
+        = dlx::setup_project().await?;
+    let (dlx_project, install_result)
+        = dlx::install_dependencies(&dlx_project.project_cwd, vec![resolution], true).await?;
+
+    let install_state = dlx_project.install_state.as_ref()
+        .ok_or(Error::InstallStateNotFound)?;
+
+    let template_locator
+        = install_state.normalized_resolutions.get(&dlx_project.root_workspace().locator())
+            .and_then(|resolution| resolution.dependencies.get(&template_ident))
+            .and_then(|descriptor| install_state.descriptor_to_locator.get(descriptor))
+            .ok_or_else(|| Error::PackageNotFound(template_ident.clone()))?;
+
+    let Some(package_data @ PackageData::Zip {archive_path, ..}) = install_result.package_data.get(template_locator) else {
+        return Err(Error::UnsupportedTemplateSource(template_locator.clone()));
+    };
+
+    let archive_data = archive_path
+        .fs_read_prealloc()?;
+
+    Ok((archive_data, package_data.package_subpath()))
+}
+
+/// Splits the files of the template from its manifest.
+fn read_template_archive<'a>(archive_data: &'a [u8], package_subpath: &Path) -> Result<(Manifest, Vec<Entry<'a>>), Error> {
+    let (manifest_entries, entries): (Vec<_>, Vec<_>)
+        = zpm_formats::zip::entries_from_zip(archive_data)?
+            .into_iter()
+            .strip_path_prefix(package_subpath)
+            .partition(|entry| entry.name.as_str() == MANIFEST_NAME);
+
+    let template_manifest: Manifest = match manifest_entries.first() {
+        Some(entry) => JsonDocument::hydrate_from_slice(&entry.data)?,
+        None => Manifest::default(),
+    };
+
+    Ok((template_manifest, entries))
+}
+
+enum TemplateSource {
+    Folder {
+        ident: Ident,
+        path: Path,
+    },
+
+    Remote(LooseResolution),
+}
+
+fn find_template_folder(folders: &[Setting<Path>], template: &LooseDescriptor) -> Option<(Ident, Path)> {
+    let ident = match template {
+        LooseDescriptor::Descriptor(params) => &params.descriptor.ident,
+        LooseDescriptor::Ident(params) => &params.ident,
+        LooseDescriptor::Range(_) => return None,
+    };
+
+    folders.iter()
+        .map(|folder| folder.value.with_join_str(ident.as_str()))
+        .find(|path| path.with_join_str("package.json").fs_exists())
+        .map(|path| (ident.clone(), path))
+}
+
+// Templates are returned in the order they should run: the templates listed
+// in `extends` come first, followed by the template that extends them. Remote
+// templates are fetched so that their own `extends` can be followed as well.
+async fn collect_templates(install_context: &InstallContext<'_>, resolve_options: &descriptor_loose::ResolveOptions, folders: &[Setting<Path>], template: &LooseDescriptor, stack: &mut Vec<String>, seen: &mut BTreeSet<String>, templates: &mut Vec<TemplateSource>) -> Result<(), Error> {
+    let key
+        = template.to_file_string();
+
+    if stack.contains(&key) {
+        let cycle = stack.iter()
+            .skip_while(|entry| **entry != key)
+            .chain(std::iter::once(&key))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        return Err(Error::TemplateCycle(cycle.join(" -> ")));
+    }
+
+    if !seen.insert(key.clone()) {
+        return Ok(());
+    }
+
+    let (extends, source) = match find_template_folder(folders, template) {
+        Some((ident, path)) => {
+            let manifest
+                = read_manifest(&path.with_join_str("package.json"))?;
+
+            (manifest.extends, TemplateSource::Folder {ident, path})
+        },
+
+        None => {
+            let resolution
+                = template.resolve(install_context, resolve_options).await?;
+
+            let (archive_data, package_subpath)
+                = fetch_template_archive(resolution.clone()).await?;
+
+            let (manifest, _)
+                = read_template_archive(&archive_data, &package_subpath)?;
+
+            (manifest.extends, TemplateSource::Remote(resolution))
+        },
+    };
+
+    stack.push(key);
+
+    for parent in &extends {
+        let parent
+            = LooseDescriptor::from_file_string(parent)?;
+
+        Box::pin(collect_templates(install_context, resolve_options, folders, &parent, stack, seen, templates)).await?;
+    }
+
+    stack.pop();
+
+    templates.push(source);
+
+    Ok(())
 }
 
 pub struct InitParams {
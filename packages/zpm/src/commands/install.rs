@@ -1,8 +1,9 @@
 use clipanion::cli;
 use zpm_config::Source;
 use zpm_parsers::JsonDocument;
+use zpm_utils::Path;
 
-use crate::{error::Error, project::{self, InstallMode, RunInstallOptions}};
+use crate::{error::Error, profile::{self, ProfileSummary}, project::{self, InstallMode, RunInstallOptions}, report::{StreamReport, StreamReportConfig, current_report, with_report_result}};
 
 /// Install dependencies
 ///
@@ -22,12 +23,18 @@ use crate::{error::Error, project::{self, InstallMode, RunInstallOptions}};
 ///
 /// If the `--immutable-cache` option is set, Yarn will abort with an error exit code if the cache folder was to be modified (either because files would be added, or because they'd be removed).
 ///
-/// If the `--refresh-lockfile` option is set, Yarn will keep the same resolution for the packages currently in the lockfile but will refresh their metadata. If used together with `--immutable`, it can validate that the lockfile information are consistent. This flag is enabled by default when Yarn detects it runs within a pull request context.
+/// If the `--refresh-lockfile` option is set, Yarn will keep the same resolution for the packages currently in the lockfile but will refresh their metadata. If used together with `--immutable`, it can validate that the lockfile information are consistent. This flag is enabled by default when Yarn detects it runs within a pull request context. Dependencies declared through a dist-tag stay on their locked version unless `refreshTagRanges` is set to `follow`, in which case they're resolved again to the version the tag currently points to.
 ///
-/// If the `--check-cache` option is set, Yarn will always refetch the packages and will ensure that their checksum matches what's 1/ described in the lockfile 2/ inside the existing cache files (if present). This is recommended as part of your CI workflow if you're both following the Zero-Installs model and accepting PRs from third-parties, as they'd otherwise have the ability to alter the checked-in packages before submitting them.
+/// If the `--refresh-checksums` option is set, Yarn will recompute the checksums of the packages from the archives already present in the cache (without downloading them again) and update the lockfile accordingly. This is useful when the checksums got outdated for a legitimate reason, for instance after a change in the cache format, and avoids having to wipe the cache entirely.
+///
+/// If the `--check-cache` option is set, Yarn will re-extract each archive from the cache into a temporary directory, rebuild it from the extracted files, and ensure that the result matches the checksum described in the lockfile; the install fails with the list of mismatching packages otherwise. This is recommended as part of your CI workflow if you're both following the Zero-Installs model and accepting PRs from third-parties, as they'd otherwise have the ability to alter the checked-in packages before submitting them.
+///
+/// If the `--production` option is set (defaults to true when the `NODE_ENV` environment variable is set to `production`), Yarn will skip the `devDependencies` of the workspaces when resolving and linking the project. The lockfile is left untouched so that it keeps describing the full dependency tree.
 ///
 /// If the `--inline-builds` option is set, Yarn will verbosely print the output of the build steps of your dependencies (instead of writing them into individual files). This is likely useful mostly for debug purposes only when using Docker-like environments.
 ///
+/// If the `--profile` option is set, Yarn will measure the time spent in its main internal functions and print a summary at the end of the install: the total time spent in each of them, how many times they got called, and the slowest individual calls. The `--profile-out=<path>` option additionally writes the measurements in the Chrome trace event format, which can be inspected with tools such as Perfetto.
+///
 /// If the `--mode=<mode>` option is set, Yarn will change which artifacts are generated. The modes currently supported are:
 ///
 /// - `skip-build` will not run the build scripts at all. Note that this is different from setting `enableScripts` to false because the latter will disable build scripts, and thus affect the content of the artifacts generated on disk, whereas the former will just disable the build step - but not the scripts themselves, which just won't run.
@@ -53,6 +60,18 @@ pub struct Install {
     #[cli::option("--check-checksums", default = false)]
     check_checksums: bool,
 
+    /// Re-extract the cached archives and validate their content against the lockfile checksums
+    #[cli::option("--check-cache", default = false)]
+    check_cache: bool,
+
+    /// Only install the production dependencies, skipping the dev dependencies of the workspaces
+    #[cli::option("--production,--prod")]
+    production: Option<bool>,
+
+    /// Recompute the checksums stored in the lockfile from the cached archives
+    #[cli::option("--refresh-checksums", default = false)]
+    refresh_checksums: bool,
+
     /// Refresh the package metadata stored in the lockfile
     #[cli::option("--refresh-lockfile", default = false)]
     refresh_lockfile: bool,
@@ -64,6 +83,26 @@ pub struct Install {
     /// Hide any output but errors
     #[cli::option("--silent", default = false)]
     silent: bool,
+
+    /// Print additional information, such as the network statistics
+    #[cli::option("-v,--verbose", default = false)]
+    verbose: bool,
+
+    /// Format the output as an NDJSON stream
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    /// Print the packages added, removed, or changed by the install
+    #[cli::option("--summary", default = true)]
+    summary: bool,
+
+    /// Print a summary of where the install spent its time
+    #[cli::option("--profile", default = false)]
+    profile: bool,
+
+    /// Write the profiling data to the given path, in the Chrome trace event format
+    #[cli::option("--profile-out")]
+    profile_out: Option<Path>,
 }
 
 impl Install {
@@ -79,6 +118,14 @@ impl Install {
             return Err(Error::IncompatibleOptions(vec!["--immutable-cache".to_string(), "--mode=update-lockfile".to_string()]));
         }
 
+        if self.refresh_checksums && self.check_checksums {
+            return Err(Error::IncompatibleOptions(vec!["--check-checksums".to_string(), "--refresh-checksums".to_string()]));
+        }
+
+        if self.mode == Some(InstallMode::UpdateLockfile) && self.production == Some(true) {
+            return Err(Error::IncompatibleOptions(vec!["--production".to_string(), "--mode=update-lockfile".to_string()]));
+        }
+
         if self.immutable == Some(true) {
             project.config.settings.enable_immutable_installs.value = true;
             project.config.settings.enable_immutable_installs.source = Source::Cli;
@@ -93,19 +140,78 @@ impl Install {
             project.config.settings.enable_immutable_cache.source = Source::Cli;
         }
 
+        if self.json {
+            project.config.settings.json_output.value = true;
+            project.config.settings.json_output.source = Source::Cli;
+        }
+
         sort_workspace_dependencies(&project)?;
 
-        project.run_install(RunInstallOptions {
+        // Updating the lockfile requires the full dependency tree, so the
+        // environment is only taken into account for regular installs
+        let production = self.production.unwrap_or_else(|| {
+            self.mode != Some(InstallMode::UpdateLockfile) && std::env::var("NODE_ENV").is_ok_and(|node_env| node_env == "production")
+        });
+
+        let is_profiling
+            = self.profile || self.profile_out.is_some();
+
+        if is_profiling {
+            profile::enable();
+        }
+
+        let install_result = project.run_install(RunInstallOptions {
+            check_cache: self.check_cache,
             check_checksums: self.check_checksums,
             check_resolutions: self.check_resolutions,
+            refresh_checksums: self.refresh_checksums,
             refresh_lockfile: self.refresh_lockfile,
             mode: self.mode,
+            prune_dev_dependencies: production,
             silent_or_error: self.silent,
+            summary: self.summary,
+            verbose: self.verbose,
             ..Default::default()
-        }).await?;
+        }).await;
+
+        // The profile is also useful to understand why an install failed
+        // (for instance because it timed out), so it's reported regardless
+        if is_profiling {
+            self.report_profile(&project).await?;
+        }
+
+        install_result?;
 
         Ok(())
     }
+
+    async fn report_profile(&self, project: &project::Project) -> Result<(), Error> {
+        let samples
+            = profile::take_samples();
+
+        if let Some(profile_out) = &self.profile_out {
+            profile_out
+                .fs_create_parent()?
+                .fs_write_text(profile::to_chrome_trace(&samples)?)?;
+        }
+
+        if !self.profile {
+            return Ok(());
+        }
+
+        let report
+            = StreamReport::new(StreamReportConfig::from_config(&project.config));
+
+        with_report_result(report, async {
+            current_report().await.as_ref().map(|report| {
+                for line in ProfileSummary::new(&samples).to_print_lines() {
+                    report.info(line);
+                }
+            });
+
+            Ok(())
+        }).await
+    }
 }
 
 /// Sort dependency fields in all workspace package.json files alphabetically.
@@ -3,11 +3,14 @@ use std::process::ExitCode;
 use clipanion::{prelude::*, Environment};
 use zpm_switch::{extract_bin_meta, BinMeta};
 
+use crate::report;
+
 mod debug;
 mod npm;
 
 mod add;
 mod bin;
+mod cache_clean;
 mod cache_clear;
 mod config;
 mod config_get;
@@ -16,6 +19,8 @@ mod constraints;
 mod dedupe;
 mod dlx;
 mod exec;
+mod explain_peer_requirements;
+mod ide_server;
 mod info;
 mod init;
 mod install;
@@ -63,8 +68,10 @@ pub enum YarnCli {
     PrintChangedWorkspaces(debug::print_changed_workspaces::PrintChangedWorkspaces),
     PrintHoisting(debug::print_hoisting::PrintHoisting),
     PrintPlatform(debug::print_platform::PrintPlatform),
+    Sh(debug::sh::Sh),
     SyncFs(debug::sync_fs::SyncFs),
 
+    NpmAudit(npm::audit::NpmAudit),
     Login(npm::login::Login),
     LogoutAll(npm::logout_all::LogoutAll),
     Logout(npm::logout::Logout),
@@ -78,6 +85,7 @@ pub enum YarnCli {
     Add(add::Add),
     BinList(bin::BinList),
     Bin(bin::Bin),
+    CacheClean(cache_clean::CacheClean),
     CacheClear(cache_clear::CacheClear),
     CacheClear2(cache_clear::CacheClear2),
     Config(config::Config),
@@ -88,6 +96,9 @@ pub enum YarnCli {
     DlxWithPackages(dlx::DlxWithPackages),
     Dlx(dlx::Dlx),
     Exec(exec::Exec),
+    ExplainPeerRequirements(explain_peer_requirements::ExplainPeerRequirements),
+    IdeServer(ide_server::IdeServer),
+    InfoRegistry(info::InfoRegistry),
     Info(info::Info),
     InitWithTemplate(init::InitWithTemplate),
     Init(init::Init),
@@ -102,6 +113,7 @@ pub enum YarnCli {
     Patch(patch::Patch),
     Rebuild(rebuild::Rebuild),
     Remove(remove::Remove),
+    RunList(run::RunList),
     Run(run::Run),
     Unlink(unlink::Unlink),
     Unplug(unplug::Unplug),
@@ -116,10 +128,17 @@ pub enum YarnCli {
 pub async fn run_default(args: Option<Vec<String>>) -> ExitCode {
     let BinMeta {
         cwd,
-        args,
+        mut args,
         version,
     } = extract_bin_meta(args);
 
+    // The global flag must be set before the command name so that it doesn't
+    // conflict with the `--json` options of the individual commands
+    if args.first().map(String::as_str) == Some("--json") {
+        args.remove(0);
+        report::force_json_output();
+    }
+
     if let Some(cwd) = cwd {
         cwd.sys_set_current_dir()
             .expect("Failed to set current directory");
@@ -23,7 +23,7 @@ impl Node {
             = project::Project::new(None).await?;
 
         project
-            .lazy_install().await?;
+            .lazy_install_or_verify().await?;
 
         Ok(ScriptEnvironment::new()?
             .with_project(&project)
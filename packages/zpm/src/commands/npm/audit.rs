@@ -0,0 +1,623 @@
+use std::{collections::{BTreeMap, BTreeSet}, process::ExitCode};
+
+use clipanion::cli;
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer};
+use zpm_macro_enum::zpm_enum;
+use zpm_parsers::JsonDocument;
+use zpm_primitives::{Descriptor, Ident, IdentGlob, Locator, Reference};
+use zpm_utils::{tree, AbstractValue, DataType, FromFileString, ToFileString, ToHumanString};
+
+use crate::{
+    commands::up,
+    error::Error,
+    http_npm::{self, AuthorizationMode, GetAuthorizationOptions, NpmHttpParams, get_authorization, get_registry},
+    install::InstallContext,
+    project::{Project, RunInstallOptions, Workspace},
+    resolvers,
+};
+
+const BULK_ADVISORIES_PATH: &str = "/-/npm/v1/security/advisories/bulk";
+
+#[zpm_enum]
+#[derive(Debug)]
+enum AuditEnvironment {
+    #[literal("all")]
+    All,
+
+    #[literal("production")]
+    Production,
+
+    #[literal("development")]
+    Development,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Advisory {
+    #[serde(deserialize_with = "deserialize_advisory_id")]
+    id: String,
+    title: String,
+    url: String,
+    severity: String,
+    vulnerable_versions: String,
+}
+
+fn deserialize_advisory_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum AdvisoryId {
+        Number(u64),
+        String(String),
+    }
+
+    Ok(match AdvisoryId::deserialize(deserializer)? {
+        AdvisoryId::Number(id) => id.to_string(),
+        AdvisoryId::String(id) => id,
+    })
+}
+
+impl Advisory {
+    /// Deprecated versions are reported alongside the advisories, the same
+    /// way the npm CLI does.
+    fn from_deprecation(ident: &Ident, version: &zpm_semver::Version, message: String) -> Self {
+        Self {
+            id: format!("{} (deprecation)", ident.to_file_string()),
+            title: message,
+            url: format!("https://www.npmjs.com/package/{}", ident.to_file_string()),
+            severity: "moderate".to_string(),
+            vulnerable_versions: version.to_file_string(),
+        }
+    }
+
+    fn affects(&self, version: &zpm_semver::Version) -> bool {
+        // Unparsable ranges are assumed to affect every version
+        zpm_semver::Range::from_file_string(&self.vulnerable_versions)
+            .map_or(true, |range| range.check_ignore_rc(version))
+    }
+}
+
+fn registry_version(locator: &Locator) -> Option<(&Ident, &zpm_semver::Version)> {
+    match &locator.reference {
+        Reference::Shorthand(params)
+            => Some((&locator.ident, &params.version)),
+
+        Reference::Registry(params)
+            => Some((&params.ident, &params.version)),
+
+        _ => None,
+    }
+}
+
+fn is_vulnerable(advisories: &BTreeMap<Ident, Vec<Advisory>>, ident: &Ident, version: &zpm_semver::Version) -> bool {
+    advisories.get(ident)
+        .is_some_and(|advisories| advisories.iter().any(|advisory| advisory.affects(version)))
+}
+
+/// Perform a vulnerability audit against the installed packages
+///
+/// This command checks for known security reports on the packages you use. The reports are by default extracted from the npm registry, and may or
+/// may not be relevant to your actual program (not all vulnerabilities affect all code paths).
+///
+/// For consistency with our other commands the default is to only check the direct dependencies for the active workspace. To extend this search to
+/// all workspaces, use `-A,--all`. To extend this search to both direct and transitive dependencies, use `-R,--recursive`.
+///
+/// Applying the `--environment` flag will limit the audit to the dependencies of the specified kind (`production` or `development`).
+///
+/// If the `--json` flag is set, the report will be printed as an NDJSON stream. Regardless of this flag, the process will exit with a non-zero
+/// exit code if a report is found for the selected packages.
+///
+/// If certain packages produce false positives for a particular environment, the `--exclude` flag can be used to exclude any number of packages
+/// from the audit. Similarly, specific advisories can be skipped using the `--ignore` flag.
+///
+/// If the `--fix` flag is set, Yarn will re-resolve the vulnerable lockfile entries to the nearest version that isn't affected by any advisory
+/// while still satisfying the range declared by their dependents. Only the lockfile is updated; your manifests are left untouched. Advisories that
+/// can't be fixed this way (because fixing them would require a version outside of the declared ranges) are reported along with a `resolutions`
+/// override you can paste into your root manifest. Combine it with `--dry-run` to preview the changes without applying them.
+///
+#[cli::command]
+#[cli::path("npm", "audit")]
+#[cli::category("Npm-related commands")]
+pub struct NpmAudit {
+    /// Audit dependencies from all workspaces
+    #[cli::option("-A,--all", default = false)]
+    all: bool,
+
+    /// Audit transitive dependencies as well
+    #[cli::option("-R,--recursive", default = false)]
+    recursive: bool,
+
+    /// Which environments to cover
+    #[cli::option("--environment", default = AuditEnvironment::All)]
+    environment: AuditEnvironment,
+
+    /// Format the output as an NDJSON stream
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    /// Packages to exclude from the audit
+    #[cli::option("--exclude", default = vec![])]
+    exclude: Vec<IdentGlob>,
+
+    /// Advisory IDs to ignore
+    #[cli::option("--ignore", default = vec![])]
+    ignore: Vec<String>,
+
+    /// Upgrade the vulnerable lockfile entries to non-vulnerable versions within their ranges
+    #[cli::option("--fix", default = false)]
+    fix: bool,
+
+    /// Only print the changes that `--fix` would apply
+    #[cli::option("--dry-run", default = false)]
+    dry_run: bool,
+}
+
+impl NpmAudit {
+    pub async fn execute(&self) -> Result<ExitCode, Error> {
+        let mut project
+            = Project::new(None).await?;
+
+        project
+            .lazy_install().await?;
+
+        let selection
+            = self.extract_selection(&project)?;
+
+        let mut advisories
+            = self.fetch_advisories(&project, &selection).await?;
+
+        if self.fix {
+            return self.fix_advisories(&mut project, &selection, &advisories).await;
+        }
+
+        for (ident, deprecations) in self.fetch_deprecations(&project, &selection).await? {
+            advisories.entry(ident)
+                .or_default()
+                .extend(deprecations);
+        }
+
+        if advisories.is_empty() {
+            if !self.json {
+                println!("➤ No audit suggestions");
+            }
+
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        self.report_advisories(&selection, &advisories);
+
+        Ok(ExitCode::FAILURE)
+    }
+
+    fn is_followed(&self, workspace: &Workspace, ident: &Ident) -> bool {
+        let is_production_dependency
+            = workspace.manifest.remote.dependencies.contains_key(ident)
+                || workspace.manifest.remote.optional_dependencies.contains_key(ident);
+
+        let is_development_dependency
+            = workspace.manifest.dev_dependencies.contains_key(ident);
+
+        match self.environment {
+            AuditEnvironment::All => true,
+            AuditEnvironment::Production => is_production_dependency,
+            AuditEnvironment::Development => is_development_dependency,
+        }
+    }
+
+    /// Returns the versions of each registry package selected for the audit.
+    fn extract_selection(&self, project: &Project) -> Result<BTreeMap<Ident, BTreeSet<zpm_semver::Version>>, Error> {
+        let install_state
+            = project.install_state.as_ref()
+                .ok_or(Error::InstallStateNotFound)?;
+
+        let tree
+            = &install_state.resolution_tree;
+
+        let workspaces = match self.all {
+            true => project.workspaces.iter().collect::<Vec<_>>(),
+            false => vec![project.active_workspace()?],
+        };
+
+        let mut queue
+            = Vec::new();
+
+        for workspace in workspaces {
+            let Some(resolution) = tree.locator_resolutions.get(&workspace.locator()) else {
+                continue;
+            };
+
+            for (ident, descriptor) in &resolution.dependencies {
+                if !self.is_followed(workspace, ident) {
+                    continue;
+                }
+
+                if let Some(locator) = tree.descriptor_to_locator.get(descriptor) {
+                    queue.push(locator);
+                }
+            }
+        }
+
+        let mut seen
+            = BTreeSet::new();
+
+        while let Some(locator) = queue.pop() {
+            if !seen.insert(locator) || !self.recursive {
+                continue;
+            }
+
+            if let Some(resolution) = tree.locator_resolutions.get(locator) {
+                for descriptor in resolution.dependencies.values() {
+                    if let Some(dependency) = tree.descriptor_to_locator.get(descriptor) {
+                        queue.push(dependency);
+                    }
+                }
+            }
+        }
+
+        let mut selection: BTreeMap<Ident, BTreeSet<zpm_semver::Version>>
+            = BTreeMap::new();
+
+        for locator in seen {
+            let physical_locator
+                = locator.physical_locator();
+
+            let Some((ident, version)) = registry_version(&physical_locator) else {
+                continue;
+            };
+
+            if self.exclude.iter().any(|glob| glob.check(ident)) {
+                continue;
+            }
+
+            selection.entry(ident.clone())
+                .or_default()
+                .insert(version.clone());
+        }
+
+        Ok(selection)
+    }
+
+    async fn fetch_advisories(&self, project: &Project, selection: &BTreeMap<Ident, BTreeSet<zpm_semver::Version>>) -> Result<BTreeMap<Ident, Vec<Advisory>>, Error> {
+        if selection.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        let registry = match &project.config.settings.npm_audit_registry.value {
+            Some(registry) => registry.strip_suffix('/').unwrap_or(registry),
+            None => get_registry(&project.config, None, false)?,
+        };
+
+        let authorization
+            = get_authorization(&GetAuthorizationOptions {
+                configuration: &project.config,
+                http_client: &project.http_client,
+                registry,
+                ident: None,
+                auth_mode: AuthorizationMode::RespectConfiguration,
+                allow_oidc: false,
+            }).await?;
+
+        let payload = selection.iter()
+            .map(|(ident, versions)| (ident.to_file_string(), versions.iter().map(|version| version.to_file_string()).collect::<Vec<_>>()))
+            .collect::<BTreeMap<_, _>>();
+
+        let response = http_npm::post(&NpmHttpParams {
+            http_client: &project.http_client,
+            registry,
+            path: BULK_ADVISORIES_PATH,
+            authorization: authorization.as_deref(),
+            otp: None,
+        }, JsonDocument::to_string(&payload)?).await?;
+
+        let body
+            = response.bytes().await?;
+
+        let mut reports: BTreeMap<String, Vec<Advisory>>
+            = JsonDocument::hydrate_from_slice(&body[..])?;
+
+        let mut advisories
+            = BTreeMap::new();
+
+        for (ident, versions) in selection {
+            let Some(package_advisories) = reports.remove(&ident.to_file_string()) else {
+                continue;
+            };
+
+            let package_advisories = package_advisories.into_iter()
+                .filter(|advisory| !self.ignore.contains(&advisory.id))
+                .filter(|advisory| versions.iter().any(|version| advisory.affects(version)))
+                .collect::<Vec<_>>();
+
+            if !package_advisories.is_empty() {
+                advisories.insert(ident.clone(), package_advisories);
+            }
+        }
+
+        Ok(advisories)
+    }
+
+    /// Checks the selected versions against their packuments, which are
+    /// retrieved from the registry the packages are installed from (rather
+    /// than the audit registry).
+    async fn fetch_deprecations(&self, project: &Project, selection: &BTreeMap<Ident, BTreeSet<zpm_semver::Version>>) -> Result<BTreeMap<Ident, Vec<Advisory>>, Error> {
+        #[derive(Deserialize)]
+        struct DeprecationManifest {
+            #[serde(default)]
+            deprecated: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct DeprecationPackument {
+            #[serde(default)]
+            versions: BTreeMap<String, DeprecationManifest>,
+        }
+
+        let packuments = futures::future::try_join_all(selection.keys().map(|ident| async move {
+            let bytes
+                = resolvers::npm::fetch_packument_with_format(project, ident, http_npm::PackumentFormat::Abbreviated, true).await?;
+
+            let packument: DeprecationPackument
+                = JsonDocument::hydrate_from_slice(&bytes[..])?;
+
+            Ok::<_, Error>((ident, packument))
+        })).await?;
+
+        let mut deprecations
+            = BTreeMap::new();
+
+        for (ident, mut packument) in packuments {
+            let package_deprecations = selection[ident].iter()
+                .filter_map(|version| {
+                    // Unlike during installs, deprecation messages made of
+                    // whitespaces are still reported; only empty strings are
+                    // used to undo a deprecation.
+                    let message = packument.versions.remove(&version.to_file_string())?
+                        .deprecated
+                        .filter(|message| !message.is_empty())?;
+
+                    Some(Advisory::from_deprecation(ident, version, message))
+                })
+                .filter(|advisory| !self.ignore.contains(&advisory.id))
+                .collect::<Vec<_>>();
+
+            if !package_deprecations.is_empty() {
+                deprecations.insert(ident.clone(), package_deprecations);
+            }
+        }
+
+        Ok(deprecations)
+    }
+
+    fn report_advisories(&self, selection: &BTreeMap<Ident, BTreeSet<zpm_semver::Version>>, advisories: &BTreeMap<Ident, Vec<Advisory>>) {
+        let mut children
+            = vec![];
+
+        for (ident, package_advisories) in advisories {
+            let mut advisory_nodes
+                = IndexMap::new();
+
+            for advisory in package_advisories {
+                let tree_versions = selection[ident].iter()
+                    .filter(|version| advisory.affects(version))
+                    .map(|version| tree::Node {
+                        label: None,
+                        value: Some(AbstractValue::new(version.to_file_string())),
+                        children: None,
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut fields
+                    = IndexMap::new();
+
+                let entries = [
+                    ("id", "ID", advisory.id.clone()),
+                    ("issue", "Issue", advisory.title.clone()),
+                    ("url", "URL", advisory.url.clone()),
+                    ("severity", "Severity", advisory.severity.clone()),
+                    ("vulnerableVersions", "Vulnerable Versions", advisory.vulnerable_versions.clone()),
+                ];
+
+                for (key, label, value) in entries {
+                    fields.insert(key.to_string(), tree::Node {
+                        label: Some(label.to_string()),
+                        value: Some(AbstractValue::new(value)),
+                        children: None,
+                    });
+                }
+
+                fields.insert("treeVersions".to_string(), tree::Node {
+                    label: Some("Tree Versions".to_string()),
+                    value: None,
+                    children: Some(tree::TreeNodeChildren::Vec(tree_versions)),
+                });
+
+                advisory_nodes.insert(advisory.id.clone(), tree::Node {
+                    label: None,
+                    value: Some(AbstractValue::new(advisory.title.clone())),
+                    children: Some(tree::TreeNodeChildren::Map(fields)),
+                });
+            }
+
+            children.push(tree::Node {
+                label: None,
+                value: Some(AbstractValue::new(ident.clone())),
+                children: Some(tree::TreeNodeChildren::Map(advisory_nodes)),
+            });
+        }
+
+        let root_node = tree::Node {
+            label: None,
+            value: None,
+            children: Some(tree::TreeNodeChildren::Vec(children)),
+        };
+
+        let render
+            = tree::TreeRenderer::new()
+                .render(&root_node, self.json);
+
+        print!("{}", render);
+    }
+
+    async fn fix_advisories(&self, project: &mut Project, selection: &BTreeMap<Ident, BTreeSet<zpm_semver::Version>>, advisories: &BTreeMap<Ident, Vec<Advisory>>) -> Result<ExitCode, Error> {
+        let lockfile
+            = project.lockfile()?;
+
+        // Only the entries part of the audited selection (as defined by the
+        // -A,--all and -R,--recursive flags) are upgraded.
+        let is_selected = |ident: &Ident, version: &zpm_semver::Version| {
+            selection.get(ident).is_some_and(|versions| versions.contains(version))
+        };
+
+        let vulnerable_resolutions = lockfile.resolutions.iter()
+            .filter(|(_, locator)| registry_version(locator).is_some_and(|(ident, version)| is_selected(ident, version) && is_vulnerable(advisories, ident, version)))
+            .collect::<Vec<_>>();
+
+        let package_cache
+            = project.package_cache()?;
+
+        let (enforced_resolutions, suggested_resolutions) = {
+            let install_context = InstallContext::default()
+                .with_package_cache(Some(&package_cache))
+                .with_project(Some(&*project));
+
+            // We pick the lowest safe version above the current one, to
+            // keep the refresh as close as possible to what was locked.
+            let enforced_resolutions = up::resolve_lockfile_descriptors(&install_context, vulnerable_resolutions.iter().copied(), |locator, candidates| {
+                let (ident, current_version)
+                    = registry_version(locator)?;
+
+                candidates.iter()
+                    .find(|version| **version > current_version && !is_vulnerable(advisories, ident, version))
+                    .map(|version| (*version).clone())
+            }).await?;
+
+            let mut suggested_resolutions
+                = BTreeMap::new();
+
+            for (descriptor, locator) in &vulnerable_resolutions {
+                if enforced_resolutions.contains_key(*descriptor) {
+                    continue;
+                }
+
+                let Some((ident, current_version)) = registry_version(locator) else {
+                    continue;
+                };
+
+                let patched_version = resolvers::npm::resolve_versions(&install_context, ident).await?
+                    .into_iter()
+                    .find(|version| version > current_version && !is_vulnerable(advisories, ident, version));
+
+                suggested_resolutions.insert((*descriptor).clone(), (locator.physical_locator(), patched_version));
+            }
+
+            (enforced_resolutions, suggested_resolutions)
+        };
+
+        if !enforced_resolutions.is_empty() {
+            self.report_fixes(&lockfile.resolutions, &enforced_resolutions);
+        }
+
+        if !suggested_resolutions.is_empty() {
+            self.report_unfixable(&suggested_resolutions)?;
+        }
+
+        if enforced_resolutions.is_empty() && suggested_resolutions.is_empty() && !self.json {
+            println!("➤ No audit suggestions");
+        }
+
+        if !self.dry_run && !enforced_resolutions.is_empty() {
+            project.run_install(RunInstallOptions {
+                enforced_resolutions,
+                ..Default::default()
+            }).await?;
+        }
+
+        match suggested_resolutions.is_empty() {
+            true => Ok(ExitCode::SUCCESS),
+            false => Ok(ExitCode::FAILURE),
+        }
+    }
+
+    fn report_fixes(&self, resolutions: &BTreeMap<Descriptor, Locator>, enforced_resolutions: &BTreeMap<Descriptor, Locator>) {
+        let mut children
+            = vec![];
+
+        for (descriptor, locator) in enforced_resolutions {
+            let mut child_children
+                = IndexMap::new();
+
+            child_children.insert("oldResolution".to_string(), tree::Node {
+                label: Some("Old resolution".to_string()),
+                value: Some(AbstractValue::new(resolutions[descriptor].clone())),
+                children: None,
+            });
+
+            child_children.insert("newResolution".to_string(), tree::Node {
+                label: Some("New resolution".to_string()),
+                value: Some(AbstractValue::new(locator.clone())),
+                children: None,
+            });
+
+            children.push(tree::Node {
+                label: None,
+                value: Some(AbstractValue::new(descriptor.clone())),
+                children: Some(tree::TreeNodeChildren::Map(child_children)),
+            });
+        }
+
+        let root_node = tree::Node {
+            label: None,
+            value: None,
+            children: Some(tree::TreeNodeChildren::Vec(children)),
+        };
+
+        let render
+            = tree::TreeRenderer::new()
+                .render(&root_node, self.json);
+
+        print!("{}", render);
+
+        if !self.json {
+            let verb = match self.dry_run {
+                true => "Would upgrade",
+                false => "Upgraded",
+            };
+
+            println!();
+            println!("➤ {} {} {} to a non-vulnerable version", verb, enforced_resolutions.len(), if enforced_resolutions.len() == 1 {"package"} else {"packages"});
+        }
+    }
+
+    fn report_unfixable(&self, suggested_resolutions: &BTreeMap<Descriptor, (Locator, Option<zpm_semver::Version>)>) -> Result<(), Error> {
+        let overrides = suggested_resolutions.iter()
+            .filter_map(|(descriptor, (_, version))| version.as_ref().map(|version| (descriptor.to_file_string(), version.to_file_string())))
+            .collect::<BTreeMap<_, _>>();
+
+        if self.json {
+            println!("{}", JsonDocument::to_string(&serde_json::json!({"resolutions": overrides}))?);
+            return Ok(());
+        }
+
+        if !overrides.is_empty() {
+            println!();
+        }
+
+        for (descriptor, (locator, version)) in suggested_resolutions {
+            match version {
+                Some(_) => println!("➤ {} is locked to {}; no version satisfying its range is safe", descriptor.to_print_string(), locator.to_print_string()),
+                None => println!("➤ {} is locked to {}; no patched version is available", descriptor.to_print_string(), locator.to_print_string()),
+            }
+        }
+
+        if !overrides.is_empty() {
+            let snippet
+                = JsonDocument::to_string_pretty(&serde_json::json!({"resolutions": overrides}))?;
+
+            println!();
+            println!("➤ Add the following to your root {} to override them:", DataType::Path.colorize("package.json"));
+            println!();
+            println!("{}", snippet);
+        }
+
+        Ok(())
+    }
+}
@@ -140,7 +140,7 @@ impl Login {
             )?;
 
             config_path
-                .fs_write_text(&updated_content)?;
+                .fs_write_atomic(&updated_content)?;
 
             current_report().await.as_ref().map(|report| {
                 report.info("Successfully logged in".to_string());
@@ -107,7 +107,7 @@ impl Logout {
             };
 
             config_path
-                .fs_write_text(&updated_content)?;
+                .fs_write_atomic(&updated_content)?;
 
             Ok(())
         }).await
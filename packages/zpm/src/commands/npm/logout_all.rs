@@ -54,7 +54,7 @@ impl LogoutAll {
                     .expect("Document was originally valid UTF-8");
 
             config_path
-                .fs_write_text(&updated_content)?;
+                .fs_write_atomic(&updated_content)?;
 
             current_report().await.as_ref().map(|report| {
                 report.info("Successfully logged out from all npm registries".to_string());
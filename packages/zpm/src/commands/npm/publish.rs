@@ -182,21 +182,22 @@ impl Publish {
                 digest: provenance_digest,
             };
 
+            // We'd rather fail than publish the package without the
+            // provenance the user asked for.
             let sigstore_token
                 = http_npm::get_id_token(&GetIdTokenOptions {
                     http_client: &project.http_client,
                     audience: "sigstore",
-                }).await?;
+                }).await?
+                    .ok_or(Error::ProvenanceRequiresOidcToken)?;
 
             let provenance_payload
-                = create_provenance_payload(&project.http_client, &provenance_file, &sigstore_token.unwrap()).await?;
+                = create_provenance_payload(&project.http_client, &provenance_file, &sigstore_token).await?;
 
-            if let Some(provenance_payload) = provenance_payload {
-                attachments.insert(
-                    format!("{}-{}.sigstore", ident.to_file_string(), version.to_file_string()),
-                    AttachmentInfo::from_str("application/json".to_string(), &provenance_payload),
-                );
-            }
+            attachments.insert(
+                format!("{}-{}.sigstore", ident.to_file_string(), version.to_file_string()),
+                AttachmentInfo::from_str("application/json".to_string(), &provenance_payload),
+            );
         }
 
         let readme
@@ -425,30 +426,22 @@ const INTOTO_STATEMENT_V1_TYPE: &str = "https://in-toto.io/Statement/v1";
 const SLSA_PREDICATE_V02_TYPE: &str = "https://slsa.dev/provenance/v0.2";
 const SLSA_PREDICATE_V1_TYPE: &str = "https://slsa.dev/provenance/v1";
 
-async fn create_provenance_payload(http_client: &HttpClient, subject: &ProvenanceSubject, oidc_token: &str) -> Result<Option<String>, Error> {
-    let Some(provider) = is_ci() else {
-        return Ok(None);
-    };
-
-    let payload = match provider {
-        Provider::GitHubActions
-            => Some(create_github_provenance_payload(subject)),
-
-        Provider::GitLab
-            => Some(create_gitlab_provenance_payload(subject)),
+async fn create_provenance_payload(http_client: &HttpClient, subject: &ProvenanceSubject, oidc_token: &str) -> Result<String, Error> {
+    let payload = match is_ci() {
+        Some(Provider::GitHubActions)
+            => create_github_provenance_payload(subject),
 
-        Provider::Unknown
-            => None,
-    };
+        Some(Provider::GitLab)
+            => create_gitlab_provenance_payload(subject),
 
-    let Some(payload) = payload else {
-        return Ok(None);
+        Some(Provider::Unknown) | None
+            => return Err(Error::ProvenanceRequiresSupportedCi),
     };
 
     let payload = payload
         .map_err(|e| Error::MissingEnvironmentVariableForProvenancePayload(e.to_string()))?;
 
-    Ok(Some(JsonDocument::to_string(&attest(http_client, &payload, INTOTO_PAYLOAD_TYPE, oidc_token).await?)?))
+    Ok(JsonDocument::to_string(&attest(http_client, &payload, INTOTO_PAYLOAD_TYPE, oidc_token).await?)?)
 }
 
 const GITHUB_BUILDER_ID_PREFIX: &str = "https://github.com/actions/runner";
@@ -19,6 +19,9 @@ use crate::{error::Error, fetchers::PackageData, install::InstallResult, project
 /// Calling the command when you already have a patch won't import it by default (in other words, the default behavior is to reset existing
 /// patches). However, adding the `-u,--update` flag will import any current patch.
 ///
+/// If the `--json` flag is set, the command will only print a JSON object containing the absolute path of the editable folder (`path`) and the
+/// locator of the patched package (`locator`), so that other tools can open the folder before calling `yarn patch-commit`.
+///
 #[cli::command]
 #[cli::path("patch")]
 #[cli::category("Dependency management")]
@@ -27,7 +30,7 @@ pub struct Patch {
     #[cli::option("-u,--update", default = false)]
     update: bool,
 
-    /// Format the output as an NDJSON stream
+    /// Print the editable folder and patched locator as a JSON object
     #[cli::option("--json", default = false)]
     json: bool,
 
@@ -45,9 +48,13 @@ impl Patch {
             ..Default::default()
         }).await?;
 
+        // Packages with peer dependencies are found through their virtual
+        // locators, but patches (like the package data) apply to the physical
+        // package they're derived from
         let locator
             = Self::find_closest_dependency(&project, &self.ident)?
-                .ok_or_else(|| Error::PackageNotFound(self.ident.clone()))?;
+                .ok_or_else(|| Error::PackageNotFound(self.ident.clone()))?
+                .physical_locator();
 
         let original_locator = if let Reference::Patch(params) = &locator.reference {
             &params.inner.0
@@ -61,8 +68,10 @@ impl Patch {
             &original_locator
         };
 
-        let root_path
-            = Path::temp_dir_pattern("patch-<>")?;
+        // The temporary folder may be relative if TMPDIR is, but the path we
+        // print must remain valid regardless of the current directory
+        let root_path = Path::current_dir()?
+            .with_join(&Path::temp_dir_pattern("patch-<>")?);
 
         let locator_path = root_path
             .with_join_str(".locator");
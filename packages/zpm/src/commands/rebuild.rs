@@ -9,43 +9,67 @@ use crate::{
 
 /// Rebuild dependencies
 ///
-/// This command will automatically cause Yarn to forget about previous compilations of the given packages and to run them again.
+/// This command will run the build scripts of the given packages again if their inputs (dependencies, package content, Node version, platform)
+/// changed since their last build. Set `--force` to make Yarn forget about previous compilations and run them again regardless.
 ///
 /// Note that while Yarn forgets the compilation, the previous artifacts aren't erased from the filesystem and may affect the next builds (in good
 /// or bad). To avoid this, you may remove the `.yarn/unplugged` folder, or any other relevant location where packages might have been stored (Yarn
 /// may offer a way to do that automatically in the future).
 ///
-/// By default all packages will be rebuilt, but you can filter the list by specifying the names of the packages you want to clear from memory.
+/// By default (or when `--all` is set) all packages will be considered, but you can filter the list by specifying the names of the packages
+/// you want to rebuild; all their installed copies will then be rebuilt, while the other packages will be left untouched. Yarn reports which
+/// packages matched each name, so that typos don't go unnoticed.
+///
+/// Packages explicitly listed on the command line are built even if they aren't part of `allowedBuildDependencies`.
+/// This only applies to this specific run; add them to the setting to also build them during subsequent installs.
 ///
 #[cli::command]
 #[cli::path("rebuild")]
 #[cli::category("Dependency management")]
 pub struct Rebuild {
+    /// Rebuild all packages
+    #[cli::option("-A,--all", default = false)]
+    all: bool,
+
+    /// Ignore the recorded build state and rebuild the packages even if their inputs didn't change
+    #[cli::option("-f,--force", default = false)]
+    force: bool,
+
     /// The packages to rebuild
     identifiers: Vec<Ident>,
 }
 
 impl Rebuild {
     pub async fn execute(&self) -> Result<(), Error> {
+        if self.all && !self.identifiers.is_empty() {
+            return Err(Error::IncompatibleOptions(vec!["--all".to_string(), "<identifiers>".to_string()]));
+        }
+
         let mut project
             = project::Project::new(None).await?;
 
-        let mut build_state
-            = BuildState::load(&project).await;
+        if self.force {
+            let mut build_state
+                = BuildState::load(&project).await;
 
-        if self.identifiers.is_empty() {
-            build_state.entries.clear();
-        } else {
-            for ident in &self.identifiers {
-                build_state.entries.retain(|locator, _| {
-                    locator.ident != *ident
-                });
+            if self.identifiers.is_empty() {
+                build_state.entries.clear();
+            } else {
+                for ident in &self.identifiers {
+                    build_state.entries.retain(|locator, _| {
+                        locator.ident != *ident
+                    });
+                }
             }
-        }
 
-        build_state.save(&project)?;
+            build_state.save(&project)?;
+        }
 
-        project.run_install(RunInstallOptions::default()).await?;
+        project.run_install(RunInstallOptions {
+            build_selection: (!self.identifiers.is_empty()).then(|| self.identifiers.iter().cloned().collect()),
+            forced_builds: self.identifiers.iter().cloned().collect(),
+            ..Default::default()
+        }).await?;
 
         Ok(())
     }
@@ -1,15 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
 use clipanion::cli;
 use wax::{Glob, Program};
 use zpm_config::Configuration;
-use zpm_parsers::{Document, JsonDocument};
+use zpm_parsers::{DataDocument, Document, JsonDocument};
 use zpm_primitives::Ident;
-use zpm_utils::ToFileString;
+use zpm_utils::{IoResultExt, ToFileString, ToHumanString};
 
 use crate::{
     error::Error,
-    project::{InstallMode, Project, RunInstallOptions, Workspace},
+    project::{self, InstallMode, Project, RunInstallOptions, Workspace},
+    report::{StreamReport, StreamReportConfig, current_report, with_report_result},
 };
 
 /// Remove dependencies from the project
@@ -28,6 +29,10 @@ use crate::{
 ///
 /// This command accepts glob patterns as arguments. Make sure to escape the patterns, to prevent your own shell from trying to expand them.
 ///
+/// Once the dependencies are removed, Yarn will look for `resolutions` (in the root manifest) and `packageExtensions` (in the project
+/// configuration) entries targeting packages that aren't part of the project anymore, and will offer to remove them. With `--skip-prompt`,
+/// or when the terminal isn't interactive, they will be reported as warnings instead.
+///
 #[cli::command]
 #[cli::path("remove")]
 #[cli::category("Dependency management")]
@@ -40,6 +45,14 @@ pub struct Remove {
     #[cli::option("--mode")]
     mode: Option<InstallMode>,
 
+    /// Report the orphaned resolutions and package extensions as warnings instead of offering to remove them
+    #[cli::option("--skip-prompt", default = false)]
+    skip_prompt: bool,
+
+    /// Print the packages added, removed, or changed by the install
+    #[cli::option("--summary", default = true)]
+    summary: bool,
+
     // ---
 
     identifiers: Vec<Ident>,
@@ -73,9 +86,115 @@ impl Remove {
 
         project.run_install(RunInstallOptions {
             mode: self.mode,
+            summary: self.summary,
             ..Default::default()
         }).await?;
 
+        self.cleanup_orphaned_overrides(&project).await?;
+
+        Ok(())
+    }
+
+    async fn cleanup_orphaned_overrides(&self, project: &Project) -> Result<(), Error> {
+        let mut known_idents: BTreeSet<Ident> = project.lockfile()?.entries.keys()
+            .map(|locator| locator.ident.clone())
+            .collect();
+
+        known_idents.extend(project.workspaces.iter().map(|workspace| workspace.name.clone()));
+
+        let orphaned_resolutions = project.root_workspace().manifest.resolutions.entries.iter()
+            .filter(|(selector, _)| !known_idents.contains(selector.target_ident()))
+            .map(|(selector, _)| selector)
+            .collect::<Vec<_>>();
+
+        let orphaned_extensions = project.config.settings.package_extensions.keys()
+            .filter(|descriptor| !known_idents.contains(&descriptor.ident))
+            .collect::<Vec<_>>();
+
+        if orphaned_resolutions.is_empty() && orphaned_extensions.is_empty() {
+            return Ok(());
+        }
+
+        let can_prompt
+            = !self.skip_prompt && zpm_utils::is_terminal();
+
+        if !can_prompt {
+            let report = StreamReport::new(StreamReportConfig {
+                ..StreamReportConfig::from_config(&project.config)
+            });
+
+            return with_report_result(report, async {
+                current_report().await.as_ref().map(|report| {
+                    for selector in &orphaned_resolutions {
+                        report.warn(format!("The {} resolution doesn't target any package of the project anymore", selector.to_print_string()));
+                    }
+
+                    for descriptor in &orphaned_extensions {
+                        report.warn(format!("The {} package extension doesn't target any package of the project anymore", descriptor.to_print_string()));
+                    }
+                });
+
+                Ok(())
+            }).await;
+        }
+
+        for selector in &orphaned_resolutions {
+            println!("Orphaned resolution: {}", selector.to_print_string());
+        }
+
+        for descriptor in &orphaned_extensions {
+            println!("Orphaned package extension: {}", descriptor.to_print_string());
+        }
+
+        let confirmed = dialoguer::Confirm::new()
+            .with_prompt("Remove these entries?")
+            .default(false)
+            .interact()
+            .map_err(std::io::Error::from)?;
+
+        if !confirmed {
+            return Ok(());
+        }
+
+        if !orphaned_resolutions.is_empty() {
+            let manifest_path = project.root_workspace().path
+                .with_join_str(project::MANIFEST_NAME);
+
+            let manifest_content = manifest_path
+                .fs_read_prealloc()?;
+
+            let mut document
+                = JsonDocument::new(manifest_content)?;
+
+            for selector in &orphaned_resolutions {
+                document.set_path(
+                    &zpm_parsers::Path::from_segments(vec!["resolutions".to_string(), selector.to_file_string()]),
+                    zpm_parsers::Value::Undefined,
+                )?;
+            }
+
+            manifest_path
+                .fs_change(&document.input, false)?;
+        }
+
+        if let (false, Some(config_path)) = (orphaned_extensions.is_empty(), &project.config.project_config_path) {
+            let mut document = config_path
+                .fs_read_text()
+                .ok_missing()?
+                .unwrap_or_default();
+
+            for descriptor in &orphaned_extensions {
+                document = DataDocument::update_document_field(
+                    &document,
+                    zpm_parsers::Path::from_segments(vec!["packageExtensions".to_string(), descriptor.to_file_string()]),
+                    zpm_parsers::Value::Undefined,
+                )?;
+            }
+
+            config_path
+                .fs_change(&document, false)?;
+        }
+
         Ok(())
     }
 
@@ -1,9 +1,81 @@
 use std::{os::unix::process::ExitStatusExt, process::ExitStatus};
 
-use zpm_utils::Path;
+use zpm_parsers::JsonDocument;
+use zpm_primitives::Locator;
+use zpm_utils::{DataType, Path};
 use clipanion::cli;
 
-use crate::{error::Error, project, script::ScriptEnvironment};
+use crate::{error::Error, project::{self, Project}, script::ScriptEnvironment};
+
+/// List the scripts available in the current workspace
+///
+/// This command will print the scripts defined in the current workspace. When `enableScriptInheritance` is set, the scripts of the root
+/// workspace that the current workspace doesn't override are listed as well, and marked as inherited.
+///
+#[cli::command]
+#[cli::path("run")]
+#[cli::category("Scripting commands")]
+pub struct RunList {
+    /// Format the output as an NDJSON stream
+    #[cli::option("--json", default = false)]
+    json: bool,
+}
+
+impl RunList {
+    pub async fn execute(&self) -> Result<(), Error> {
+        let mut project
+            = project::Project::new(None).await?;
+
+        project
+            .lazy_install().await?;
+
+        let active_package
+            = project.active_package()?;
+
+        let mut scripts = project.package_scripts(&active_package)?.into_iter()
+            .map(|(name, script)| (name, script, false))
+            .collect::<Vec<_>>();
+
+        let is_inheriting
+            = project.config.settings.enable_script_inheritance.value
+                && active_package.reference.is_workspace_reference()
+                && active_package != project.root_workspace().locator();
+
+        if is_inheriting {
+            for (name, script) in project.package_scripts(&project.root_workspace().locator())? {
+                if !scripts.iter().any(|(local_name, _, _)| *local_name == name) {
+                    scripts.push((name, script, true));
+                }
+            }
+        }
+
+        let name_width = scripts.iter()
+            .map(|(name, _, _)| name.len())
+            .max()
+            .unwrap_or_default();
+
+        for (name, script, inherited) in &scripts {
+            if self.json {
+                #[derive(serde::Serialize)]
+                struct Payload<'a> {
+                    name: &'a str,
+                    script: &'a str,
+
+                    #[serde(skip_serializing_if = "std::ops::Not::not")]
+                    inherited: bool,
+                }
+
+                println!("{}", JsonDocument::to_string(&Payload {name, script, inherited: *inherited})?);
+            } else if *inherited {
+                println!("{:width$}   {} (inherited from the root workspace)", name, DataType::Code.colorize(script), width = name_width);
+            } else {
+                println!("{:width$}   {}", name, DataType::Code.colorize(script), width = name_width);
+            }
+        }
+
+        Ok(())
+    }
+}
 
 /// Run a dependency binary or local script
 ///
@@ -11,13 +83,19 @@ use crate::{error::Error, project, script::ScriptEnvironment};
 ///
 /// - If the `scripts` field from your local package.json contains a matching script name, its definition will get executed.
 ///
+/// - Otherwise, if `enableScriptInheritance` is set and the root workspace contains a matching script, this script will get executed within
+///   the local workspace; the binaries of the local workspace's dependencies take precedence over those of the root workspace's.
+///
 /// - Otherwise, if one of the local workspace's dependencies exposes a binary with a matching name, this binary will get executed.
 ///
 /// - Otherwise, if the specified name contains a colon character and if one of the workspaces in the project contains exactly one script with a
 ///   matching name, then this script will get executed.
 ///
 /// Whatever happens, the cwd of the spawned process will be the workspace that declares the script (which makes it possible to call commands
-/// cross-workspaces using the third syntax).
+/// cross-workspaces using the third syntax), except for inherited scripts which run within the local workspace.
+///
+/// If the `-T,--top-level` option is set, the script or binary will be resolved against the root workspace regardless of the current
+/// directory, making it possible to run the shared scripts declared there from any workspace.
 ///
 #[cli::command(default, proxy)]
 #[cli::path("run")]
@@ -68,7 +146,7 @@ impl Run {
             = project::Project::new(None).await?;
 
         project
-            .lazy_install().await?;
+            .lazy_install_or_verify().await?;
 
         if self.top_level {
             project.package_cwd = Path::new();
@@ -110,9 +188,10 @@ impl Run {
                 = project.find_binary(&self.name);
 
             if let Ok(binary) = maybe_binary {
-                Ok(ScriptEnvironment::new()?
-                    .with_project(&project)
-                    .with_package(&project, &project.active_package()?)?
+                let active_package
+                    = project.active_package()?;
+
+                Ok(self.script_environment(&project, &active_package)?
                     .with_node_args(get_node_args())
                     .enable_shell_forwarding()
                     .run_binary(&binary, &self.args)
@@ -128,6 +207,12 @@ impl Run {
                 } else {
                     Ok(ExitStatus::from_raw(0))
                 }
+            } else if let Err(Error::BinaryFromExcludedPackage(..)) = maybe_binary {
+                if self.error_if_missing {
+                    Err(maybe_binary.unwrap_err())
+                } else {
+                    Ok(ExitStatus::from_raw(0))
+                }
             } else {
                 Err(maybe_binary.unwrap_err())
             }
@@ -137,29 +222,52 @@ impl Run {
             return execute_binary(false).await;
         }
 
+        let execute_script = async |locator: Locator, script: String| -> Result<ExitStatus, Error> {
+            let node_args = get_node_args();
+
+            // TODO: Investigate whether --require should be forwarded to scripts via NODE_OPTIONS.
+            if !node_args.is_empty() {
+                return Err(Error::InvalidRunScriptOptions(node_args));
+            }
+
+            Ok(self.script_environment(&project, &locator)?
+                .with_lifecycle_event(&self.name, &script)
+                .enable_shell_forwarding()
+                .run_script(&script, &self.args)
+                .await?
+                .into())
+        };
+
         match project.find_script(&self.name) {
-            Ok((locator, script)) => {
-                let node_args = get_node_args();
+            Ok((locator, script))
+                => execute_script(locator, script).await,
 
-                // TODO: Investigate whether --require should be forwarded to scripts via NODE_OPTIONS.
-                if !node_args.is_empty() {
-                    return Err(Error::InvalidRunScriptOptions(node_args));
+            Err(Error::ScriptNotFound(_)) | Err(Error::GlobalScriptNotFound(_)) => {
+                // Inherited scripts run within the active workspace, so they
+                // see its binaries first and those of the root after that
+                if let Some(script) = project.find_inherited_script(&self.name)? {
+                    return execute_script(project.active_package()?, script).await;
                 }
 
-                Ok(ScriptEnvironment::new()?
-                    .with_project(&project)
-                    .with_package(&project, &locator)?
-                    .with_env_variable("npm_lifecycle_event", &self.name)
-                    .enable_shell_forwarding()
-                    .run_script(&script, &self.args)
-                    .await?
-                    .into())
+                execute_binary(true).await
             },
 
-            Err(Error::ScriptNotFound(_)) | Err(Error::GlobalScriptNotFound(_))
-                => execute_binary(true).await,
-
             Err(err) => Err(err),
         }
     }
+
+    fn script_environment(&self, project: &Project, locator: &Locator) -> Result<ScriptEnvironment, Error> {
+        let mut script_environment
+            = ScriptEnvironment::new()?
+                .with_project(project)
+                .with_package(project, locator)?
+                .with_timeout(project.config.settings.script_timeout.value);
+
+        if let Some(workspace) = project.try_workspace_by_locator(locator)? {
+            script_environment = script_environment
+                .with_bin_path(project, workspace)?;
+        }
+
+        Ok(script_environment)
+    }
 }
@@ -42,6 +42,7 @@ impl SetVersion {
 
         let reference: PackageManagerReference = VersionPackageManagerReference {
             version: resolved_version.clone(),
+            hash: None,
         }.into();
 
         let package_manager
@@ -53,7 +54,7 @@ impl SetVersion {
         )?;
 
         manifest_path
-            .fs_change(&document.input, false)?;
+            .fs_change_atomic(&document.input, false)?;
 
         println!("Switching to {}", resolved_version.to_print_string());
         println!("Saved into {}", manifest_path.to_print_string());
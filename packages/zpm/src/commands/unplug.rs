@@ -1,11 +1,16 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use clipanion::cli;
+use itertools::Itertools;
+use serde::Serialize;
 use zpm_parsers::{Document, JsonDocument, Value};
-use zpm_primitives::Ident;
-use zpm_utils::ToFileString;
+use zpm_primitives::{FilterDescriptor, Locator};
+use zpm_utils::{Path, ToFileString, ToHumanString};
 
 use crate::{
     error::Error,
-    project,
+    install::InstallState,
+    project::{self, Project},
 };
 
 /// Requests a package to be materialized on disk
@@ -18,7 +23,7 @@ use crate::{
 /// required depending on the context (for example when the package contains shellscripts).
 ///
 /// Running the command will set a persistent flag inside your top-level `package.json`, in the `dependenciesMeta` field. As such, to undo its effects,
-/// you'll need to revert the changes made to the manifest and run `yarn install` to apply the modification.
+/// you'll need to revert the changes made to the manifest and run `yarn install` to apply the modification; the `--revert` flag does just that.
 ///
 /// By default, only direct dependencies from the current workspace are affected. If `-A,--all` is set, direct dependencies from the entire project are
 /// affected. Using the `-R,--recursive` flag will affect transitive dependencies as well as direct ones.
@@ -26,22 +31,90 @@ use crate::{
 /// This command accepts glob patterns inside the scope and name components (not the range). Make sure to escape the patterns to prevent your own
 /// shell from trying to expand them.
 ///
+/// If the `--list` flag is set, the command will instead print the packages currently unplugged along with their location on disk (optionally
+/// filtered by the given patterns). The `--dry-run` flag prints the packages that would be affected without modifying anything.
+///
 #[cli::command]
 #[cli::path("unplug")]
 #[cli::category("Dependency management")]
 pub struct Unplug {
+    /// Unplug direct dependencies from the entire project
+    #[cli::option("-A,--all", default = false)]
+    all: bool,
+
+    /// Unplug both direct and transitive dependencies
+    #[cli::option("-R,--recursive", default = false)]
+    recursive: bool,
+
     /// Revert the changes made to the manifest
     #[cli::option("--revert", default = false)]
     revert: bool,
 
-    /// The identifiers to unplug
-    identifiers: Vec<Ident>,
+    /// List the packages currently unplugged instead of unplugging new ones
+    #[cli::option("--list", default = false)]
+    list: bool,
+
+    /// Print the packages that would be affected without modifying anything
+    #[cli::option("--dry-run", default = false)]
+    dry_run: bool,
+
+    /// Format the output as an NDJSON stream
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    /// The patterns of the packages to unplug
+    patterns: Vec<FilterDescriptor>,
 }
 
 impl Unplug {
     pub async fn execute(&self) -> Result<(), Error> {
-        let project
-            = project::Project::new(None).await?;
+        let mut project
+            = Project::new(None).await?;
+
+        project
+            .lazy_install().await?;
+
+        let install_state = project.install_state.as_ref()
+            .ok_or(Error::InstallStateNotFound)?;
+
+        if self.list {
+            return self.print_unplugged_packages(&project, install_state);
+        }
+
+        let candidates
+            = self.find_candidates(&project, install_state)?;
+
+        if candidates.is_empty() {
+            return Err(Error::NoMatchingPackages(self.patterns.iter().map(|pattern| pattern.to_file_string()).join(", ")));
+        }
+
+        for (locator, version) in &candidates {
+            if self.json {
+                #[derive(Serialize)]
+                struct Payload<'a> {
+                    locator: &'a Locator,
+                    version: &'a zpm_semver::Version,
+                }
+
+                println!("{}", JsonDocument::to_string(&Payload {
+                    locator,
+                    version,
+                })?);
+            } else {
+                let action = match (self.dry_run, self.revert) {
+                    (true, true) => "Would stop unplugging",
+                    (true, false) => "Would unplug",
+                    (false, true) => "Will stop unplugging",
+                    (false, false) => "Will unplug",
+                };
+
+                println!("{} {}", action, locator.to_print_string());
+            }
+        }
+
+        if self.dry_run {
+            return Ok(());
+        }
 
         let manifest_path = project.project_cwd
             .with_join_str(project::MANIFEST_NAME);
@@ -52,9 +125,12 @@ impl Unplug {
         let mut document
             = JsonDocument::new(manifest_content)?;
 
-        for identifier in &self.identifiers {
+        for (locator, version) in &candidates {
+            let selector
+                = format!("{}@{}", locator.ident.to_file_string(), version.to_file_string());
+
             document.set_path(
-                &zpm_parsers::Path::from_segments(vec!["dependenciesMeta".to_string(), identifier.to_file_string(), "unplugged".to_string()]),
+                &zpm_parsers::Path::from_segments(vec!["dependenciesMeta".to_string(), selector, "unplugged".to_string()]),
                 if self.revert {Value::Undefined} else {Value::Bool(true)},
             )?;
         }
@@ -63,12 +139,98 @@ impl Unplug {
             .fs_change(&document.input, false)?;
 
         let mut project
-            = project::Project::new(None).await?;
+            = Project::new(None).await?;
 
         project.run_install(project::RunInstallOptions {
+            silent_or_error: self.json,
             ..Default::default()
         }).await?;
 
         Ok(())
     }
+
+    /// Returns the packages matching the patterns among the dependencies of
+    /// the selected workspaces, along with their versions.
+    fn find_candidates<'a>(&self, project: &Project, install_state: &'a InstallState) -> Result<BTreeMap<Locator, &'a zpm_semver::Version>, Error> {
+        let roots = match self.all {
+            true => project.workspaces.iter().map(|workspace| workspace.locator()).collect_vec(),
+            false => vec![project.active_workspace()?.locator()],
+        };
+
+        // Roots are marked as seen from the start so that depending on
+        // another workspace doesn't prevent it from being traversed as a
+        // root itself
+        let mut seen
+            = roots.iter().cloned().collect::<BTreeSet<_>>();
+
+        let mut queue
+            = roots.into_iter().map(|root| (root, 0)).collect_vec();
+
+        let mut candidates
+            = BTreeMap::new();
+
+        while let Some((locator, depth)) = queue.pop() {
+            if depth > 0 && !self.recursive {
+                continue;
+            }
+
+            let resolution = install_state.normalized_resolutions.get(&locator)
+                .expect("Expected the package to have a resolution");
+
+            for descriptor in resolution.dependencies.values() {
+                let Some(dependency) = install_state.descriptor_to_locator.get(descriptor) else {
+                    continue;
+                };
+
+                if !seen.insert(dependency.clone()) {
+                    continue;
+                }
+
+                queue.push((dependency.clone(), depth + 1));
+
+                if dependency.reference.is_workspace_reference() {
+                    continue;
+                }
+
+                let dependency_resolution = install_state.normalized_resolutions.get(dependency)
+                    .expect("Expected the package to have a resolution");
+
+                if self.patterns.iter().any(|pattern| pattern.check(&dependency.ident, &dependency_resolution.version)) {
+                    candidates.insert(dependency.physical_locator(), &dependency_resolution.version);
+                }
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    fn print_unplugged_packages(&self, project: &Project, install_state: &InstallState) -> Result<(), Error> {
+        let unplugged_rel_path
+            = project.unplugged_path().relative_to(&project.project_cwd);
+
+        let unplugged_packages = install_state.locations_by_package.iter()
+            .filter(|(_, location)| unplugged_rel_path.contains(location))
+            .map(|(locator, location)| (locator.physical_locator(), location))
+            .filter(|(locator, _)| self.patterns.is_empty() || install_state.normalized_resolutions.get(locator).is_some_and(|resolution| self.patterns.iter().any(|pattern| pattern.check(&locator.ident, &resolution.version))))
+            .collect::<BTreeMap<_, _>>();
+
+        for (locator, location) in unplugged_packages {
+            if self.json {
+                #[derive(Serialize)]
+                struct Payload<'a> {
+                    locator: &'a Locator,
+                    location: &'a Path,
+                }
+
+                println!("{}", JsonDocument::to_string(&Payload {
+                    locator: &locator,
+                    location,
+                })?);
+            } else {
+                println!("{} ({})", locator.to_print_string(), location.to_print_string());
+            }
+        }
+
+        Ok(())
+    }
 }
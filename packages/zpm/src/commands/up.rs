@@ -1,16 +1,19 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use clipanion::cli;
+use dialoguer::Select;
+use itertools::Itertools;
 use zpm_parsers::{Document, JsonDocument, Value};
-use zpm_primitives::Ident;
+use zpm_primitives::{AnonymousTagRange, Descriptor, Ident, Locator, Range, RegistrySemverRange, RegistryTagRange};
 use zpm_semver::RangeKind;
-use zpm_utils::ToFileString;
+use zpm_utils::{ToFileString, ToHumanString};
 
 use crate::{
-    descriptor_loose::{self, LooseDescriptor},
+    descriptor_loose::{self, DescriptorLooseDescriptor, IdentLooseDescriptor, LooseDescriptor, LooseResolution},
     error::Error,
     install::InstallContext,
-    project::{InstallMode, Project, RunInstallOptions, Workspace}
+    project::{InstallMode, Project, RunInstallOptions, Workspace},
+    resolvers,
 };
 
 /// Update dependencies to the latest versions
@@ -24,8 +27,12 @@ use crate::{
 /// lockfile. It however won't touch your manifests anymore, so depending on your needs you might want to run both `yarn up` and `yarn up -R` to
 /// cover all bases.
 ///
-/// If `-i,--interactive` is set (or if the `preferInteractive` settings is toggled on) the command will offer various choices, depending on the
-/// detected upgrade paths. Some upgrades require this flag in order to resolve ambiguities.
+/// If `-i,--interactive` is set and the output is a terminal, the command will list each matching package along with its current range and
+/// resolved versions, and let you pick between keeping it as-is or upgrading it to its most recent patch, minor, or major release. Packages
+/// without any newer release are reported as up-to-date and left untouched.
+///
+/// Dependencies declared through a dist-tag (such as `latest` or `next`) keep their tag in the manifest; their resolution is upgraded to the
+/// version the tag currently points to. This is the only way to move them when `refreshTagRanges` is set to `pin`.
 ///
 /// The, -C,--caret, -E,--exact and -T,--tilde options have the same meaning as in the add command (they change the modifier used when the range is
 /// missing or a tag, and are ignored when the range is explicitly set).
@@ -69,12 +76,24 @@ pub struct Up {
     #[cli::option("-C,--caret", default = false)]
     caret: bool,
 
+    /// Pick the version to use for each package
+    #[cli::option("-i,--interactive", default = false)]
+    interactive: bool,
+
+    /// Resolve again the matching lockfile entries without modifying the manifests
+    #[cli::option("-R,--recursive", default = false)]
+    recursive: bool,
+
     // ---
 
     /// Change what artifacts this install will generate
     #[cli::option("--mode")]
     mode: Option<InstallMode>,
 
+    /// Print the packages added, removed, or changed by the install
+    #[cli::option("--summary", default = true)]
+    summary: bool,
+
     // ---
 
     /// The packages to update
@@ -83,6 +102,10 @@ pub struct Up {
 
 impl Up {
     pub async fn execute(&self) -> Result<(), Error> {
+        if self.recursive {
+            return self.execute_recursive().await;
+        }
+
         let project
             = Project::new(None).await?;
 
@@ -120,8 +143,36 @@ impl Up {
             .with_package_cache(Some(&package_cache))
             .with_project(Some(&project));
 
-        let loose_resolutions
-            = LooseDescriptor::resolve_all(&install_context, &resolve_options, &expanded_descriptors).await?;
+        // Dependencies declared through a dist-tag keep it in their manifest;
+        // upgrading them only moves their resolution to the tag's target.
+        let current_tags = expanded_descriptors.iter()
+            .map(|descriptor| self.find_current_tag(&project, descriptor))
+            .collect::<Vec<_>>();
+
+        let query_descriptors = expanded_descriptors.iter()
+            .zip(&current_tags)
+            .map(|(descriptor, tag)| match (descriptor, tag) {
+                (LooseDescriptor::Ident(IdentLooseDescriptor {ident}), Some(tag)) => LooseDescriptor::Descriptor(DescriptorLooseDescriptor {
+                    descriptor: Descriptor::new(ident.clone(), AnonymousTagRange {tag: tag.clone()}.into()),
+                }),
+
+                _ => descriptor.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut loose_resolutions
+            = LooseDescriptor::resolve_all(&install_context, &resolve_options, &query_descriptors).await?;
+
+        for (resolution, tag) in loose_resolutions.iter_mut().zip(&current_tags) {
+            if let Some(tag) = tag {
+                resolution.descriptor = Descriptor::new(resolution.descriptor.ident.clone(), RegistryTagRange {ident: None, tag: tag.clone()}.into());
+            }
+        }
+
+        if self.interactive && zpm_utils::is_terminal() {
+            loose_resolutions
+                = self.pick_resolutions(&project, &install_context, &expanded_descriptors, loose_resolutions).await?;
+        }
 
         for workspace in &project.workspaces {
             let manifest_path = workspace.path
@@ -165,27 +216,276 @@ impl Up {
         project.run_install(RunInstallOptions {
             mode: self.mode,
             enforced_resolutions,
+            summary: self.summary,
             ..Default::default()
         }).await?;
 
         Ok(())
     }
 
-    fn list_workspace_idents(&self, workspace: &Workspace) -> Vec<Ident> {
-        let mut idents = Vec::new();
+    async fn execute_recursive(&self) -> Result<(), Error> {
+        let mut project
+            = Project::new(None).await?;
+
+        let lockfile
+            = project.lockfile()?;
+
+        let all_idents = lockfile.resolutions.keys()
+            .map(|descriptor| descriptor.ident.clone())
+            .collect::<BTreeSet<_>>();
+
+        let mut idents
+            = BTreeSet::new();
 
-        for dependency in workspace.manifest.remote.dependencies.values() {
-            idents.push(dependency.ident.clone());
+        for descriptor in &self.descriptors {
+            for expanded_descriptor in descriptor.expand(&all_idents) {
+                let LooseDescriptor::Ident(IdentLooseDescriptor {ident}) = expanded_descriptor else {
+                    return Err(Error::InvalidRecursiveUpPattern(descriptor.to_file_string()));
+                };
+
+                idents.insert(ident);
+            }
         }
 
-        for dependency in workspace.manifest.remote.optional_dependencies.values() {
-            idents.push(dependency.ident.clone());
+        let package_cache
+            = project.package_cache()?;
+
+        let enforced_resolutions = {
+            let install_context = InstallContext::default()
+                .with_package_cache(Some(&package_cache))
+                .with_project(Some(&project));
+
+            let resolutions = lockfile.resolutions.iter()
+                .filter(|(descriptor, _)| idents.contains(&descriptor.ident));
+
+            resolve_lockfile_descriptors(&install_context, resolutions, |_, candidates| {
+                candidates.last().map(|version| (*version).clone())
+            }).await?
+        };
+
+        project.run_install(RunInstallOptions {
+            mode: self.mode,
+            enforced_resolutions,
+            summary: self.summary,
+            ..Default::default()
+        }).await?;
+
+        Ok(())
+    }
+
+    async fn pick_resolutions(&self, project: &Project, install_context: &InstallContext<'_>, descriptors: &[LooseDescriptor], latest_resolutions: Vec<LooseResolution>) -> Result<Vec<LooseResolution>, Error> {
+        let lockfile
+            = project.lockfile()?;
+
+        let mut picked_resolutions
+            = Vec::new();
+
+        for (loose_descriptor, latest_resolution) in descriptors.iter().zip(latest_resolutions) {
+            // Packages requested with an explicit range don't leave anything to pick
+            let LooseDescriptor::Ident(IdentLooseDescriptor {ident}) = loose_descriptor else {
+                picked_resolutions.push(latest_resolution);
+                continue;
+            };
+
+            let current_descriptor = project.workspaces.iter()
+                .flat_map(|workspace| self.list_workspace_descriptors(workspace))
+                .find(|descriptor| descriptor.ident == *ident);
+
+            let current_versions = lockfile.entries.values()
+                .filter(|entry| entry.resolution.locator.ident == *ident)
+                .map(|entry| entry.resolution.version.clone())
+                .collect::<BTreeSet<_>>();
+
+            let current_versions_label = current_versions.iter()
+                .map(|version| version.to_file_string())
+                .join(", ");
+
+            // Dependencies declared through a semver range get to pick between
+            // the next patch, minor, and major releases; the others (tags,
+            // aliases, or packages missing from the lockfile) can only move to
+            // the latest version.
+            let mut candidates = match (current_descriptor.and_then(registry_target), current_versions.last()) {
+                (Some((package_ident, current_range)), Some(current_version)) if package_ident == ident
+                    => self.list_upgrade_targets(project, install_context, ident, current_range, current_version).await?,
+
+                _ => vec![("Latest", latest_resolution)],
+            };
+
+            if candidates.is_empty() {
+                println!("{} is already up-to-date ({})", ident.to_print_string(), current_versions_label);
+                continue;
+            }
+
+            let current_label = match current_descriptor {
+                Some(descriptor) => format!("Keep current ({}, resolved to {})", descriptor.range.to_anonymous_range().to_file_string(), current_versions_label),
+                None => "Keep current".to_string(),
+            };
+
+            let items = std::iter::once(current_label)
+                .chain(candidates.iter().map(|(label, resolution)| format!("{}: {}", label, resolution.descriptor.range.to_anonymous_range().to_file_string())))
+                .collect_vec();
+
+            let selection = Select::new()
+                .with_prompt(format!("Pick the range to use for {}", ident.to_print_string()))
+                .items(&items)
+                .default(items.len() - 1)
+                .interact()
+                .map_err(std::io::Error::from)?;
+
+            if selection > 0 {
+                picked_resolutions.push(candidates.swap_remove(selection - 1).1);
+            }
         }
 
-        for dependency in workspace.manifest.dev_dependencies.values() {
-            idents.push(dependency.ident.clone());
+        Ok(picked_resolutions)
+    }
+
+    /// Lists the most recent patch, minor, and major releases newer than the
+    /// currently installed version, each stored using the same modifier as
+    /// the current range. Prereleases are only considered if the installed
+    /// version is one.
+    async fn list_upgrade_targets(&self, project: &Project, install_context: &InstallContext<'_>, ident: &Ident, current_range: &zpm_semver::Range, current_version: &zpm_semver::Version) -> Result<Vec<(&'static str, LooseResolution)>, Error> {
+        let newer_versions = resolvers::npm::resolve_versions(install_context, ident).await?
+            .into_iter()
+            .filter(|version| version > current_version)
+            .filter(|version| version.rc.is_none() || current_version.rc.is_some())
+            .collect_vec();
+
+        let latest_matching = |predicate: &dyn Fn(&zpm_semver::Version) -> bool| {
+            newer_versions.iter()
+                .filter(|version| predicate(version))
+                .max()
+                .cloned()
+        };
+
+        let targets = [
+            ("Patch", latest_matching(&|version| version.major == current_version.major && version.minor == current_version.minor)),
+            ("Minor", latest_matching(&|version| version.major == current_version.major)),
+            ("Major", latest_matching(&|_| true)),
+        ];
+
+        let range_kind = current_range.kind()
+            .unwrap_or(project.config.settings.default_semver_range_prefix.value);
+
+        let mut picked_versions
+            = BTreeSet::new();
+
+        let mut candidates
+            = Vec::new();
+
+        for (label, version) in targets {
+            let Some(version) = version else {
+                continue;
+            };
+
+            if !picked_versions.insert(version.clone()) {
+                continue;
+            }
+
+            let params = RegistrySemverRange {
+                ident: None,
+                range: zpm_semver::Range::exact(version.clone()),
+            };
+
+            let descriptor
+                = Descriptor::new(ident.clone(), params.clone().into());
+
+            let resolution_result
+                = resolvers::npm::resolve_semver_descriptor(install_context, &descriptor, &params).await?;
+
+            candidates.push((label, LooseResolution {
+                descriptor: Descriptor::new(ident.clone(), RegistrySemverRange {ident: None, range: version.to_range(range_kind)}.into()),
+                locator: Some(resolution_result.resolution.locator),
+            }));
         }
 
-        idents
+        Ok(candidates)
     }
+
+    fn find_current_tag(&self, project: &Project, descriptor: &LooseDescriptor) -> Option<String> {
+        let LooseDescriptor::Ident(IdentLooseDescriptor {ident}) = descriptor else {
+            return None;
+        };
+
+        project.workspaces.iter()
+            .flat_map(|workspace| self.list_workspace_descriptors(workspace))
+            .find(|descriptor| descriptor.ident == *ident)
+            .and_then(|descriptor| match &descriptor.range {
+                Range::AnonymousTag(AnonymousTagRange {tag}) => Some(tag.clone()),
+                Range::RegistryTag(RegistryTagRange {ident: None, tag}) => Some(tag.clone()),
+                _ => None,
+            })
+    }
+
+    fn list_workspace_descriptors<'a>(&self, workspace: &'a Workspace) -> impl Iterator<Item = &'a Descriptor> {
+        workspace.manifest.remote.dependencies.values()
+            .chain(workspace.manifest.remote.optional_dependencies.values())
+            .chain(workspace.manifest.dev_dependencies.values())
+    }
+
+    fn list_workspace_idents(&self, workspace: &Workspace) -> Vec<Ident> {
+        self.list_workspace_descriptors(workspace)
+            .map(|descriptor| descriptor.ident.clone())
+            .collect()
+    }
+}
+
+fn registry_target(descriptor: &Descriptor) -> Option<(&Ident, &zpm_semver::Range)> {
+    match &descriptor.range {
+        Range::AnonymousSemver(params)
+            => Some((&descriptor.ident, &params.range)),
+
+        Range::RegistrySemver(RegistrySemverRange {ident: None, range})
+            => Some((&descriptor.ident, range)),
+
+        Range::RegistrySemver(RegistrySemverRange {ident: Some(ident), range})
+            => Some((ident, range)),
+
+        _ => None,
+    }
+}
+
+/// Resolves the given lockfile entries again, locking each descriptor to the
+/// version picked by `select` amongst the registry versions satisfying its
+/// range (sorted from the oldest to the most recent one). Manifests aren't
+/// touched; the returned resolutions are meant to be enforced by the next
+/// install, and only include the descriptors whose locator changed.
+pub async fn resolve_lockfile_descriptors<'a>(install_context: &InstallContext<'_>, resolutions: impl IntoIterator<Item = (&'a Descriptor, &'a Locator)>, select: impl Fn(&Locator, &[&zpm_semver::Version]) -> Option<zpm_semver::Version>) -> Result<BTreeMap<Descriptor, Locator>, Error> {
+    let mut versions_by_ident: BTreeMap<Ident, Vec<zpm_semver::Version>>
+        = BTreeMap::new();
+
+    let mut enforced_resolutions
+        = BTreeMap::new();
+
+    for (descriptor, locator) in resolutions {
+        let Some((package_ident, range)) = registry_target(descriptor) else {
+            continue;
+        };
+
+        if !versions_by_ident.contains_key(package_ident) {
+            versions_by_ident.insert(package_ident.clone(), resolvers::npm::resolve_versions(install_context, package_ident).await?);
+        }
+
+        let candidates = versions_by_ident[package_ident].iter()
+            .filter(|version| range.check(version))
+            .collect_vec();
+
+        let Some(version) = select(locator, &candidates) else {
+            continue;
+        };
+
+        let params = RegistrySemverRange {
+            ident: (*package_ident != descriptor.ident).then(|| package_ident.clone()),
+            range: zpm_semver::Range::exact(version),
+        };
+
+        let resolution_result
+            = resolvers::npm::resolve_semver_descriptor(install_context, descriptor, &params).await?;
+
+        if resolution_result.resolution.locator != *locator {
+            enforced_resolutions.insert(descriptor.clone(), resolution_result.resolution.locator);
+        }
+    }
+
+    Ok(enforced_resolutions)
 }
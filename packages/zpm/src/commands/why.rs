@@ -100,9 +100,16 @@ impl Why {
                         let descriptor_resolution
                             = DescriptorResolution::new(descriptor.clone(), dep_locator.clone());
 
+                        let mut node
+                            = tree::Node::new_value(descriptor_resolution);
+
+                        if let Some(status_node) = platform_status_node(install_state, dep_locator) {
+                            node.children = Some(tree::TreeNodeChildren::Vec(vec![status_node]));
+                        }
+
                         children_map.insert(
                             dep_locator.to_file_string(),
-                            tree::Node::new_value(descriptor_resolution),
+                            node,
                         );
                     }
                 }
@@ -269,6 +276,10 @@ impl Why {
             }
         }
 
+        if let Some(status_node) = platform_status_node(install_state, locator) {
+            node_children.insert("Status".to_string(), status_node);
+        }
+
         let node_value = if let Some(desc) = descriptor {
             AbstractValue::new(DescriptorResolution::new(desc.clone(), locator.clone()))
         } else {
@@ -285,3 +296,18 @@ impl Why {
         );
     }
 }
+
+/// Packages excluded from the current platform are still part of the
+/// resolution tree, but they don't exist on disk; we make it explicit so
+/// users don't wonder why they can't find them in their install.
+pub fn platform_status_node(install_state: &InstallState, locator: &Locator) -> Option<tree::Node<'static>> {
+    if !install_state.disabled_locators.contains(&locator.physical_locator()) {
+        return None;
+    }
+
+    Some(tree::Node {
+        label: Some("Status".to_string()),
+        value: Some(AbstractValue::new("not fetched (platform excluded)".to_string())),
+        children: None,
+    })
+}
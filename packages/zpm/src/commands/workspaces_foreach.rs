@@ -9,14 +9,14 @@ use zpm_primitives::Ident;
 use zpm_utils::{DataType, Path, ToFileString, ToHumanString, Unit};
 
 use crate::{
-    algos::scc_tarjan_pearce, commands::{PartialYarnCli, YarnCli}, error::Error, git_utils, project::{Project, Workspace}, workspace_glob::WorkspaceGlob
+    commands::{PartialYarnCli, YarnCli}, error::Error, git_utils, project::{Project, Workspace, sort_workspaces}, workspace_glob::WorkspaceGlob
 };
 
-/// An SCC island containing workspaces that can be run together,
+/// An island containing workspaces that can be run together,
 /// along with external dependencies that must complete first.
 #[derive(Debug)]
 pub struct TopologicalIsland {
-    /// Workspaces in this SCC (can be run in any order within the island)
+    /// Workspaces in this island (can be run in any order within the island)
     pub idents: Vec<Ident>,
     /// Dependencies from other islands that must complete before this island can start
     pub depends_on: BTreeSet<Ident>,
@@ -424,62 +424,45 @@ impl WorkspacesForeach {
         }
 
         if self.is_topological {
-            Ok(Selection::Topological(self.topological_sort(project, &selection)))
+            Ok(Selection::Topological(self.topological_sort(project, &selection)?))
         } else {
             Ok(Selection::List(selection.into_iter().collect()))
         }
     }
 
-    fn topological_sort(&self, project: &Project, selection: &BTreeSet<Ident>) -> Vec<TopologicalIsland> {
-        // Build dependency graph for selected workspaces only
-        let mut graph: BTreeMap<Ident, BTreeSet<Ident>>
-            = BTreeMap::new();
+    fn topological_sort(&self, project: &Project, selection: &BTreeSet<Ident>) -> Result<Vec<TopologicalIsland>, Error> {
+        // Only keep the dependencies between selected workspaces
+        let dependencies = project.workspace_dependencies().into_iter()
+            .filter(|dependency| self.follows_field(dependency.field))
+            .filter(|dependency| selection.contains(&dependency.dependent) && selection.contains(&dependency.dependency))
+            .collect_vec();
 
-        for ident in selection {
-            let workspace
-                = project.workspace_by_ident(ident)
-                    .expect("We should only be selecting workspaces");
+        // Running the workspaces of a cycle in an arbitrary order would
+        // silently break the guarantee the --topological flag provides
+        let order
+            = sort_workspaces(selection.iter().cloned(), &dependencies)
+                .map_err(Error::WorkspaceCycles)?;
 
-            // Get dependencies that are also in the selection
-            let deps: BTreeSet<Ident> = self.followed_dependencies(workspace)
-                .into_iter()
-                .filter(|dep| selection.contains(dep))
-                .collect();
+        let mut depends_on: BTreeMap<Ident, BTreeSet<Ident>>
+            = BTreeMap::new();
 
-            graph.insert(ident.clone(), deps);
+        for dependency in dependencies {
+            depends_on.entry(dependency.dependent).or_default().insert(dependency.dependency);
         }
 
-        // SCC returns components in reverse topological order (dependencies first)
-        let sccs
-            = scc_tarjan_pearce(&graph);
-
-        // Build islands with their external dependencies
-        sccs.into_iter()
-            .map(|scc_idents| {
-                let scc_set: BTreeSet<Ident>
-                    = scc_idents.iter().cloned().collect();
-
-                // External dependencies are deps that are not in this SCC
-                let mut depends_on: BTreeSet<Ident>
-                    = BTreeSet::new();
-
-                for ident in &scc_idents {
-                    if let Some(deps) = graph.get(ident) {
-                        for dep in deps {
-                            // Only include if it's external to this SCC
-                            if !scc_set.contains(dep) {
-                                depends_on.insert(dep.clone());
-                            }
-                        }
-                    }
-                }
+        let islands = order.into_iter()
+            .map(|ident| {
+                let depends_on
+                    = depends_on.remove(&ident).unwrap_or_default();
 
                 TopologicalIsland {
-                    idents: scc_idents,
+                    idents: vec![ident],
                     depends_on,
                 }
             })
-            .collect()
+            .collect();
+
+        Ok(islands)
     }
 
     fn script_name(&self, args: Vec<String>) -> Option<(String, bool)> {
@@ -523,6 +506,14 @@ impl WorkspacesForeach {
         })
     }
 
+    fn follows_field(&self, field: &str) -> bool {
+        match self.followed_dependencies {
+            FollowedDependencies::All => true,
+            FollowedDependencies::Prod => field == "dependencies",
+            FollowedDependencies::Dev => field == "devDependencies",
+        }
+    }
+
     fn followed_dependencies(&self, workspace: &Workspace) -> BTreeSet<Ident> {
         let mut dependencies
             = BTreeSet::new();
@@ -2,7 +2,7 @@ use std::{collections::{BTreeMap, BTreeSet}, str::FromStr};
 
 use clipanion::cli;
 use zpm_parsers::JsonDocument;
-use zpm_primitives::{Ident, Reference};
+use zpm_primitives::{Ident, Range, Reference};
 use zpm_utils::{Path, ToFileString};
 
 use crate::{
@@ -24,7 +24,8 @@ use crate::{
 /// - If `--no-private` is set, Yarn will not list any workspaces that have the `private` field set to true.
 ///
 /// If both the `-v,--verbose` and `--json` options are set, Yarn will also return the cross-dependencies between each workspaces (useful when you
-/// wish to automatically generate Bazel rules).
+/// wish to automatically generate Bazel rules), the dependencies on workspaces whose range doesn't match them, and the absolute path, version,
+/// and privacy of each workspace. This information is computed from the manifests alone, so it doesn't require the project to be installed.
 ///
 #[cli::command]
 #[cli::path("workspaces", "list")]
@@ -149,11 +150,53 @@ impl WorkspacesList {
         Ok(workspaces)
     }
 
+    /// Returns the workspaces the given workspace depends on (through its
+    /// `dependencies` and `devDependencies` fields), along with the
+    /// dependencies naming a workspace but whose range it doesn't match.
+    /// Only the manifests are used, so this works even if the project
+    /// can't be installed.
+    fn get_workspace_dependencies<'a>(&self, project: &'a Project, workspace: &Workspace) -> (Vec<&'a Path>, Vec<String>) {
+        let mut workspace_dependencies
+            = BTreeSet::new();
+        let mut mismatched_workspace_dependencies
+            = BTreeSet::new();
+
+        let descriptors = workspace.manifest.remote.dependencies.values()
+            .chain(workspace.manifest.dev_dependencies.values());
+
+        for descriptor in descriptors {
+            let dependency = match &descriptor.range {
+                Range::WorkspaceSemver(params) => project.try_workspace_by_descriptor(descriptor).ok().flatten()
+                    .filter(|dependency| params.range.check(&dependency.manifest.remote.version.clone().unwrap_or_default())),
+
+                Range::AnonymousSemver(params) if project.config.settings.enable_transparent_workspaces.value => project.workspaces_by_ident.get(&descriptor.ident)
+                    .map(|&idx| &project.workspaces[idx])
+                    .filter(|dependency| params.range.check(&dependency.manifest.remote.version.clone().unwrap_or_default())),
+
+                _ => project.try_workspace_by_descriptor(descriptor).ok().flatten(),
+            };
+
+            match dependency {
+                Some(dependency) => {
+                    workspace_dependencies.insert(&dependency.rel_path);
+                },
+
+                None if descriptor.range.is_workspace() || project.workspaces_by_ident.contains_key(&descriptor.ident) => {
+                    mismatched_workspace_dependencies.insert(descriptor.to_file_string());
+                },
+
+                None => {},
+            }
+        }
+
+        (workspace_dependencies.into_iter().collect(), mismatched_workspace_dependencies.into_iter().collect())
+    }
+
     pub async fn execute(&self) -> Result<(), Error> {
         let mut project
             = Project::new(None).await?;
 
-        if self.verbose || (self.recursive && self.since.is_some()) {
+        if self.recursive && self.since.is_some() {
             project
                 .lazy_install().await?;
         }
@@ -189,50 +232,42 @@ impl WorkspacesList {
                     name: Option<&'a Ident>,
 
                     #[serde(skip_serializing_if = "Option::is_none")]
-                    workspace_dependencies: Option<Vec<&'a Path>>,
+                    path: Option<&'a Path>,
 
                     #[serde(skip_serializing_if = "Option::is_none")]
-                    mismatched_workspace_dependencies: Option<Vec<&'a str>>,
-                }
-
-                let mut workspace_dependencies = None;
-                let mut mismatched_workspace_dependencies = None;
-
-                if self.verbose {
-                    let install_state = project.install_state.as_ref()
-                        .expect("Expected the install state to have been retrieved earlier");
-
-                    let workspace_resolution = install_state.resolution_tree.locator_resolutions.get(&workspace.locator())
-                        .expect("Expected the workspace to be in the resolution tree");
-
-                    // TODO: Deprecate this field; we can't run the command if the workspaces
-                    // are mismatched anyway, since the install will fail.
-                    mismatched_workspace_dependencies = Some(vec![]);
-
-                    workspace_dependencies = Some(workspace_resolution.dependencies.values()
-                        .filter_map(|dependency_descriptor| {
-                            let dependency_locator = install_state.resolution_tree.descriptor_to_locator.get(dependency_descriptor)
-                                .expect("Expected the descriptor to be in the resolution tree");
+                    version: Option<Option<&'a zpm_semver::Version>>,
 
-                            let Reference::WorkspaceIdent(locator_params) = &dependency_locator.reference else {
-                                return None;
-                            };
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    private: Option<bool>,
 
-                            let workspace = project
-                                .workspace_by_ident(&locator_params.ident)
-                                .expect("Expected the workspace to be in the project");
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    workspace_dependencies: Option<Vec<&'a Path>>,
 
-                            Some(&workspace.rel_path)
-                        }).collect::<Vec<_>>());
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    mismatched_workspace_dependencies: Option<Vec<String>>,
                 }
 
-                let payload = Payload {
+                let mut payload = Payload {
                     location: workspace_printed_path,
                     name: workspace.manifest.name.as_ref(),
-                    workspace_dependencies,
-                    mismatched_workspace_dependencies,
+                    path: None,
+                    version: None,
+                    private: None,
+                    workspace_dependencies: None,
+                    mismatched_workspace_dependencies: None,
                 };
 
+                if self.verbose {
+                    let (workspace_dependencies, mismatched_workspace_dependencies)
+                        = self.get_workspace_dependencies(&project, workspace);
+
+                    payload.path = Some(&workspace.path);
+                    payload.version = Some(workspace.manifest.remote.version.as_ref());
+                    payload.private = Some(workspace.manifest.private == Some(true));
+                    payload.workspace_dependencies = Some(workspace_dependencies);
+                    payload.mismatched_workspace_dependencies = Some(mismatched_workspace_dependencies);
+                }
+
                 println!("{}", JsonDocument::to_string(&payload)?);
             } else {
                 println!("{}", workspace_printed_path);
@@ -0,0 +1,75 @@
+use zpm_parsers::{Document, JsonDocument, Value};
+use zpm_utils::Path;
+
+use crate::{
+    constraints::structs::{ConstraintsOutput, WorkspaceOperation},
+    error::Error,
+    project::{Project, MANIFEST_NAME},
+};
+
+#[derive(Debug)]
+pub struct AppliedChange {
+    pub workspace_rel_path: Path,
+    pub field_path: zpm_parsers::Path,
+    pub previous_value: Option<serde_json::Value>,
+    pub new_value: Option<serde_json::Value>,
+}
+
+fn get_field<'a>(value: &'a serde_json::Value, path: &[String]) -> Option<&'a serde_json::Value> {
+    path.iter().try_fold(value, |value, segment| value.get(segment))
+}
+
+/// Writes the operations proposed by the constraints engine back into the
+/// workspace manifests. Only the targeted fields are rewritten, so the rest
+/// of the files keep their formatting. Returns the changes that actually
+/// modified a manifest.
+pub fn apply_constraints_operations(project: &Project, output: &ConstraintsOutput) -> Result<Vec<AppliedChange>, Error> {
+    let mut changes
+        = Vec::new();
+
+    for (workspace_rel_path, operations) in &output.all_workspace_operations {
+        let manifest_path = project.project_cwd
+            .with_join(workspace_rel_path)
+            .with_join_str(MANIFEST_NAME);
+
+        let manifest_content = manifest_path
+            .fs_read_prealloc()?;
+
+        let previous_manifest: serde_json::Value
+            = JsonDocument::hydrate_from_slice(&manifest_content)?;
+
+        let mut document
+            = JsonDocument::new(manifest_content)?;
+
+        for operation in operations {
+            let (path, new_value) = match operation {
+                WorkspaceOperation::Set {path, value} => (path, Some(value)),
+                WorkspaceOperation::Unset {path} => (path, None),
+            };
+
+            let field_path
+                = zpm_parsers::Path::from_segments(path.clone());
+
+            document.set_path(&field_path, new_value.map_or(Value::Undefined, |value| value.into()))?;
+
+            let previous_value
+                = get_field(&previous_manifest, path);
+
+            if previous_value == new_value {
+                continue;
+            }
+
+            changes.push(AppliedChange {
+                workspace_rel_path: workspace_rel_path.clone(),
+                field_path,
+                previous_value: previous_value.cloned(),
+                new_value: new_value.cloned(),
+            });
+        }
+
+        manifest_path
+            .fs_change(&document.input, false)?;
+    }
+
+    Ok(changes)
+}
@@ -1029,6 +1029,7 @@ var createErrorFn = (workspaceCwd) => (message) => {
   });
 };
 var RESULT_PATH = process.argv[2];
+var DEPENDENCIES_PATH = process.argv[3];
 var input = JSON.parse(SERIALIZED_CONTEXT);
 var packageByLocator = /* @__PURE__ */ new Map();
 var workspaceByCwd = /* @__PURE__ */ new Map();
@@ -1217,5 +1218,9 @@ async function main() {
   await defaultConfig.constraints?.(context);
   const output = applyEngineReport(FIX);
   (0, import_fs.writeFileSync)(RESULT_PATH, JSON.stringify(output, null, 2));
+  if (typeof DEPENDENCIES_PATH !== `undefined`) {
+    const dependencies = Object.keys(require2.cache).filter((path) => !path.split(/[\\/]/).includes(`node_modules`));
+    (0, import_fs.writeFileSync)(DEPENDENCIES_PATH, JSON.stringify(dependencies));
+  }
 }
 main();
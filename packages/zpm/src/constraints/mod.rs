@@ -1,12 +1,17 @@
+use std::collections::BTreeMap;
+
+use native::{evaluate_native_rules, NativeRules};
+use serde::{Deserialize, Serialize};
 use structs::{ConstraintsDependency, ConstraintsPackage, ConstraintsWorkspace};
 use zpm_parsers::JsonDocument;
-use zpm_utils::{Path, ToFileString};
+use zpm_utils::{FromFileString, Hash64, IoResultExt, OutputMode, Path, ToFileString};
 
 use crate::{
-    constraints::structs::{ConstraintsContext, ConstraintsOutput}, error::Error, install::InstallState, project::{Project, Workspace}, resolvers::Resolution, script::ScriptEnvironment
+    constraints::structs::{ConstraintsContext, ConstraintsOutput}, error::Error, install::InstallState, project::{Project, Workspace, MANIFEST_NAME}, resolvers::Resolution, script::ScriptEnvironment
 };
 
 pub mod apply;
+pub mod native;
 pub mod structs;
 
 pub async fn check_constraints(project: &Project, fix: bool) -> Result<ConstraintsOutput, Error> {
@@ -29,15 +34,149 @@ pub async fn check_constraints(project: &Project, fix: bool) -> Result<Constrain
         packages: constraints_packages,
     };
 
+    // The native rules run first, so their fixes are applied before the
+    // ones coming from the config file.
+    let native_rules
+        = NativeRules::from_settings(&project.config.settings.constraints);
+
+    let native_output
+        = evaluate_native_rules(&native_rules, &constraints_context, fix);
+
     let config_path =
-        [".ts", ".mjs", ".cjs"].iter()
+        [".cjs", ".mjs", ".ts"].iter()
             .map(|ext| project.project_cwd.with_join_str(&format!("yarn.config{}", ext)))
-            .find(|path| path.fs_exists())
-            .ok_or(Error::ConstraintsConfigNotFound)?;
+            .find(|path| path.fs_exists());
+
+    let Some(config_path) = config_path else {
+        if native_rules.is_empty() {
+            return Err(Error::ConstraintsConfigNotFound);
+        }
+
+        return Ok(with_raw_json(native_output));
+    };
 
     let script
         = generate_constraints_adapter(&config_path, &constraints_context, fix);
 
+    // The adapter embeds the serialized context, so hashing it along with
+    // the config file tells us whether a previous run can be reused, as long
+    // as the local files the config file required didn't change either.
+    let config_content = config_path
+        .fs_read_prealloc()?;
+
+    let cache_key
+        = Hash64::from_data([script.as_bytes(), &config_content].concat()).to_file_string();
+
+    let cache_path
+        = project.constraints_cache_path();
+    let cache_key_path = cache_path
+        .with_join_str("key.json");
+    let cache_result_path = cache_path
+        .with_join_str("result.json");
+
+    let cached_key = cache_key_path
+        .fs_read_text()
+        .ok_missing()?
+        .and_then(|content| JsonDocument::hydrate_from_str::<ConstraintsCacheKey>(&content).ok());
+
+    let is_cache_valid = cached_key.is_some_and(|cached_key| {
+        cached_key.key == cache_key && cached_key.dependencies_match()
+    });
+
+    let result_content = match is_cache_valid {
+        true if cache_result_path.fs_exists() => {
+            cache_result_path
+                .fs_read_prealloc()?
+        },
+
+        _ => {
+            let (result_content, dependencies) = if config_path.extname() == Some(".ts") {
+                let emitted_path
+                    = transpile_typescript_config(project, &config_path).await?;
+
+                let script
+                    = generate_constraints_adapter(&emitted_path, &constraints_context, fix);
+
+                run_constraints_adapter(project, &script).await?
+            } else {
+                run_constraints_adapter(project, &script).await?
+            };
+
+            let cache_key = ConstraintsCacheKey {
+                key: cache_key,
+                dependencies: ConstraintsCacheKey::hash_dependencies(&dependencies),
+            };
+
+            cache_result_path
+                .fs_create_parent()?
+                .fs_write(&result_content)?;
+            cache_key_path
+                .fs_write_text(&JsonDocument::to_string(&cache_key)?)?;
+
+            result_content
+        },
+    };
+
+    // We can't use sonic_rs here (and thus JsonDocument) due to this bug:
+    // https://github.com/cloudwego/sonic-rs/issues/181
+    let mut output
+        = serde_json::from_slice::<ConstraintsOutput>(&result_content)
+            .expect("Failed to deserialize ConstraintsOutput");
+
+    output.raw_json = result_content;
+
+    if native_rules.is_empty() {
+        return Ok(output);
+    }
+
+    let mut merged_output
+        = native_output;
+
+    merged_output.merge(output);
+
+    Ok(with_raw_json(merged_output))
+}
+
+fn with_raw_json(mut output: ConstraintsOutput) -> ConstraintsOutput {
+    output.raw_json = serde_json::to_vec(&output)
+        .expect("Failed to serialize ConstraintsOutput");
+
+    output
+}
+
+/// Identifies the inputs of a previous run of the constraints adapter: the
+/// adapter itself (including the context), the config file, and the local
+/// files the config file required along with their content hashes.
+#[derive(Debug, Deserialize, Serialize)]
+struct ConstraintsCacheKey {
+    key: String,
+    dependencies: BTreeMap<String, Option<String>>,
+}
+
+impl ConstraintsCacheKey {
+    fn hash_dependencies(paths: &[String]) -> BTreeMap<String, Option<String>> {
+        paths.iter()
+            .map(|path| (path.clone(), Self::hash_dependency(path)))
+            .collect()
+    }
+
+    fn hash_dependency(path: &str) -> Option<String> {
+        let path
+            = Path::from_file_string(path).ok()?;
+
+        Hash64::from_file(&path).ok()
+            .map(|hash| hash.to_file_string())
+    }
+
+    fn dependencies_match(&self) -> bool {
+        self.dependencies.iter()
+            .all(|(path, hash)| Self::hash_dependency(path) == *hash)
+    }
+}
+
+/// Runs the adapter, returning its result along with the list of local
+/// files the config file required.
+async fn run_constraints_adapter(project: &Project, script: &str) -> Result<(Vec<u8>, Vec<String>), Error> {
     let temp_dir
         = Path::temp_dir()?;
 
@@ -45,30 +184,60 @@ pub async fn check_constraints(project: &Project, fix: bool) -> Result<Constrain
         .with_join_str("script.js");
     let result_path = temp_dir
         .with_join_str("result.json");
+    let dependencies_path = temp_dir
+        .with_join_str("dependencies.json");
 
     script_path
-        .fs_write_text(&script)?;
+        .fs_write_text(script)?;
 
     ScriptEnvironment::new()?
         .with_cwd(project.project_cwd.clone())
         .with_project(&project)
-        .enable_shell_forwarding()
-        .run_exec("node", &vec![script_path.to_file_string(), result_path.to_file_string()])
+        .with_bin_path(&project, project.root_workspace())?
+        .with_output_mode(OutputMode::ForwardAndCapture)
+        .run_exec("node", &vec![script_path.to_file_string(), result_path.to_file_string(), dependencies_path.to_file_string()])
         .await?
         .ok()?;
 
-    let result_content = result_path
-        .fs_read_prealloc()?;
+    let dependencies: Vec<String>
+        = JsonDocument::hydrate_from_slice(&dependencies_path.fs_read_prealloc()?)?;
 
-    // We can't use sonic_rs here (and thus JsonDocument) due to this bug:
-    // https://github.com/cloudwego/sonic-rs/issues/181
-    let mut output
-        = serde_json::from_slice::<ConstraintsOutput>(&result_content)
-            .expect("Failed to deserialize ConstraintsOutput");
+    Ok((result_path.fs_read_prealloc()?, dependencies))
+}
 
-    output.raw_json = result_content;
+/// Transpiles a `yarn.config.ts` file into a CommonJS file that the adapter
+/// can require, using whichever of esbuild, swc, or typescript the project
+/// provides. Returns the original path if none of them is available.
+async fn transpile_typescript_config(project: &Project, config_path: &Path) -> Result<Path, Error> {
+    let temp_dir
+        = Path::temp_dir()?;
+
+    let script_path = temp_dir
+        .with_join_str("transpile.js");
+
+    let emitted_path = project.constraints_cache_path()
+        .with_join_str("yarn.config.cjs");
+
+    script_path
+        .fs_write_text(std::include_str!("transpile.js"))?;
+
+    emitted_path
+        .fs_create_parent()?
+        .fs_rm_file()
+        .ok_missing()?;
 
-    Ok(output)
+    ScriptEnvironment::new()?
+        .with_cwd(project.project_cwd.clone())
+        .with_project(&project)
+        .with_output_mode(OutputMode::ForwardAndCapture)
+        .run_exec("node", &vec![script_path.to_file_string(), config_path.to_file_string(), emitted_path.to_file_string()])
+        .await?
+        .ok()?;
+
+    match emitted_path.fs_exists() {
+        true => Ok(emitted_path),
+        false => Ok(config_path.clone()),
+    }
 }
 
 fn generate_constraints_adapter(config_path: &Path, context: &ConstraintsContext, fix: bool) -> String {
@@ -136,8 +305,16 @@ fn to_constraints_workspace<'a>(workspace: &'a Workspace, install_state: &'a Ins
             }
         }).collect::<Vec<_>>();
 
+    let manifest_content = workspace.path
+        .with_join_str(MANIFEST_NAME)
+        .fs_read_prealloc()?;
+
+    let manifest
+        = JsonDocument::hydrate_from_slice(&manifest_content)?;
+
     Ok(ConstraintsWorkspace {
         cwd: workspace.rel_path.clone(),
+        manifest,
         ident: workspace.name.clone(),
         dependencies,
         peer_dependencies,
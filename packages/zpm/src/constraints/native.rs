@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+
+use zpm_config::NativeConstraints;
+use zpm_primitives::{Ident, IdentGlob, Range};
+use zpm_utils::ToFileString;
+
+use crate::constraints::structs::{ConstraintsContext, ConstraintsDependency, ConstraintsOutput, ConstraintsWorkspace, WorkspaceError, WorkspaceOperation};
+
+#[cfg(test)]
+#[path = "./native.test.rs"]
+mod native_tests;
+
+/// The constraints rules that Yarn evaluates by itself, configured through
+/// the `constraints` setting rather than a `yarn.config.cjs` file.
+#[derive(Debug, Default)]
+pub struct NativeRules {
+    pub consistent_dependencies: bool,
+    pub engines: BTreeMap<String, String>,
+    pub forbidden_dependencies: Vec<IdentGlob>,
+    pub required_fields: Vec<String>,
+}
+
+impl NativeRules {
+    pub fn from_settings(settings: &NativeConstraints) -> Self {
+        Self {
+            consistent_dependencies: settings.consistent_dependencies.value,
+            engines: settings.engines.iter()
+                .map(|(name, range)| (name.clone(), range.value.clone()))
+                .collect(),
+            forbidden_dependencies: settings.forbidden_dependencies.iter()
+                .map(|glob| glob.value.clone())
+                .collect(),
+            required_fields: settings.required_fields.iter()
+                .map(|field| field.value.clone())
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.consistent_dependencies
+            && self.engines.is_empty()
+            && self.forbidden_dependencies.is_empty()
+            && self.required_fields.is_empty()
+    }
+}
+
+/// Collects the changes a workspace needs, reporting them either as
+/// operations (when fixing) or as errors, the same way the JS engine does.
+struct WorkspaceReport<'a> {
+    output: &'a mut ConstraintsOutput,
+    workspace: &'a ConstraintsWorkspace,
+    fix: bool,
+}
+
+impl WorkspaceReport<'_> {
+    fn set(&mut self, path: Vec<String>, expected: Option<serde_json::Value>) {
+        let current_value
+            = path.iter().try_fold(&self.workspace.manifest, |value, segment| value.get(segment));
+
+        if current_value == expected.as_ref() {
+            return;
+        }
+
+        if self.fix {
+            let operation = match expected {
+                Some(value) => WorkspaceOperation::Set {path, value},
+                None => WorkspaceOperation::Unset {path},
+            };
+
+            self.output.all_workspace_operations.entry(self.workspace.cwd.clone())
+                .or_default()
+                .push(operation);
+
+            return;
+        }
+
+        let field_path
+            = zpm_parsers::Path::from_segments(path);
+
+        let error = match (current_value, expected) {
+            (None, Some(expected)) => WorkspaceError::MissingField {
+                field_path,
+                expected: expected.into(),
+            },
+
+            (Some(current_value), None) => WorkspaceError::ExtraneousField {
+                field_path,
+                current_value: current_value.clone().into(),
+            },
+
+            (Some(current_value), Some(expected)) => WorkspaceError::InvalidField {
+                field_path,
+                expected: expected.into(),
+                current_value: current_value.clone().into(),
+            },
+
+            (None, None) => unreachable!("Equal values are skipped above"),
+        };
+
+        self.error(error);
+    }
+
+    fn error(&mut self, error: WorkspaceError) {
+        self.output.all_workspace_errors.entry(self.workspace.cwd.clone())
+            .or_default()
+            .push(error);
+    }
+}
+
+fn all_dependencies(workspace: &ConstraintsWorkspace) -> impl Iterator<Item = &ConstraintsDependency> {
+    workspace.dependencies.iter()
+        .chain(workspace.dev_dependencies.iter())
+        .chain(workspace.peer_dependencies.iter())
+}
+
+fn dependency_path(dependency: &ConstraintsDependency) -> Vec<String> {
+    vec![dependency.dependency_type.clone(), dependency.ident.to_file_string()]
+}
+
+/// Picks the range all workspaces should use for a dependency: the one with
+/// the highest lower bound, then the most used one. Only semver ranges are
+/// considered, since other protocols can't be compared with each other.
+fn pick_consistent_range<'a>(ranges: &[&'a Range]) -> Option<&'a Range> {
+    let mut counts: BTreeMap<&Range, usize>
+        = BTreeMap::new();
+
+    for range in ranges {
+        *counts.entry(*range).or_default() += 1;
+    }
+
+    counts.into_iter()
+        .filter_map(|(range, count)| {
+            let range_min = range.to_semver_range()?
+                .range_min();
+
+            Some((range_min, count, range))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(b.2.cmp(a.2)))
+        .map(|(_, _, range)| range)
+}
+
+fn consistent_ranges<'a>(context: &'a ConstraintsContext) -> BTreeMap<&'a Ident, &'a Range> {
+    let mut ranges_by_ident: BTreeMap<&Ident, Vec<&Range>>
+        = BTreeMap::new();
+
+    let dependencies = context.workspaces.iter()
+        .flat_map(|workspace| workspace.dependencies.iter().chain(workspace.dev_dependencies.iter()));
+
+    for dependency in dependencies {
+        ranges_by_ident.entry(&dependency.ident)
+            .or_default()
+            .push(&dependency.range);
+    }
+
+    ranges_by_ident.into_iter()
+        .filter_map(|(ident, ranges)| Some((ident, pick_consistent_range(&ranges)?)))
+        .collect()
+}
+
+/// Evaluates the native constraints rules against the project. The result
+/// has the same shape as the one returned by the JS engine, so both can be
+/// merged and applied through the same code paths.
+pub fn evaluate_native_rules(rules: &NativeRules, context: &ConstraintsContext, fix: bool) -> ConstraintsOutput {
+    let mut output
+        = ConstraintsOutput::default();
+
+    let consistent_ranges = match rules.consistent_dependencies {
+        true => consistent_ranges(context),
+        false => BTreeMap::new(),
+    };
+
+    for workspace in &context.workspaces {
+        let mut report = WorkspaceReport {
+            output: &mut output,
+            workspace,
+            fix,
+        };
+
+        for dependency in all_dependencies(workspace) {
+            if rules.forbidden_dependencies.iter().any(|glob| glob.check(&dependency.ident)) {
+                report.set(dependency_path(dependency), None);
+                continue;
+            }
+
+            if dependency.dependency_type == "peerDependencies" {
+                continue;
+            }
+
+            if let Some(range) = consistent_ranges.get(&dependency.ident) {
+                if dependency.range.to_semver_range().is_some() && *range != &dependency.range {
+                    report.set(dependency_path(dependency), Some(serde_json::Value::String(range.to_file_string())));
+                }
+            }
+        }
+
+        for (engine, range) in &rules.engines {
+            report.set(vec!["engines".to_string(), engine.clone()], Some(serde_json::Value::String(range.clone())));
+        }
+
+        for field in &rules.required_fields {
+            if workspace.manifest.get(field).is_none() {
+                report.error(WorkspaceError::UserError {
+                    message: format!("The {} field is required", field),
+                });
+            }
+        }
+    }
+
+    output
+}
@@ -0,0 +1,200 @@
+use serde_json::json;
+use zpm_utils::{FromFileString, Path};
+
+use super::*;
+
+fn cwd(cwd: &str) -> Path {
+    match cwd {
+        "" => Path::new(),
+        _ => Path::from_file_string(cwd).unwrap(),
+    }
+}
+
+fn dependency(dependency_type: &str, ident: &str, range: &str) -> ConstraintsDependency {
+    ConstraintsDependency {
+        ident: Ident::new(ident),
+        range: Range::from_file_string(range).unwrap(),
+        dependency_type: dependency_type.to_string(),
+        resolution: None,
+    }
+}
+
+fn workspace(cwd: &str, manifest: serde_json::Value) -> ConstraintsWorkspace {
+    let by_type = |dependency_type: &str| {
+        manifest.get(dependency_type)
+            .and_then(|dependencies| dependencies.as_object())
+            .into_iter()
+            .flatten()
+            .map(|(ident, range)| dependency(dependency_type, ident, range.as_str().unwrap()))
+            .collect::<Vec<_>>()
+    };
+
+    ConstraintsWorkspace {
+        cwd: self::cwd(cwd),
+        ident: Ident::new(manifest["name"].as_str().unwrap()),
+        dependencies: by_type("dependencies"),
+        peer_dependencies: by_type("peerDependencies"),
+        dev_dependencies: by_type("devDependencies"),
+        manifest,
+    }
+}
+
+fn context(workspaces: Vec<ConstraintsWorkspace>) -> ConstraintsContext<'static> {
+    ConstraintsContext {
+        workspaces,
+        packages: vec![],
+    }
+}
+
+fn operations<'a>(output: &'a ConstraintsOutput, cwd: &str) -> &'a [WorkspaceOperation] {
+    output.all_workspace_operations.get(&self::cwd(cwd))
+        .map(|operations| operations.as_slice())
+        .unwrap_or(&[])
+}
+
+fn set(path: &[&str], value: serde_json::Value) -> WorkspaceOperation {
+    WorkspaceOperation::Set {
+        path: path.iter().map(|segment| segment.to_string()).collect(),
+        value,
+    }
+}
+
+fn unset(path: &[&str]) -> WorkspaceOperation {
+    WorkspaceOperation::Unset {
+        path: path.iter().map(|segment| segment.to_string()).collect(),
+    }
+}
+
+#[test]
+fn empty_rules_report_nothing() {
+    let context = context(vec![
+        workspace("", json!({"name": "root", "dependencies": {"lodash": "^1.0.0"}})),
+    ]);
+
+    let rules
+        = NativeRules::default();
+
+    assert!(rules.is_empty());
+    assert!(evaluate_native_rules(&rules, &context, false).is_empty());
+}
+
+#[test]
+fn aligns_dependencies_on_the_highest_range() {
+    let context = context(vec![
+        workspace("", json!({"name": "root", "devDependencies": {"lodash": "^1.2.0"}})),
+        workspace("packages/a", json!({"name": "a", "dependencies": {"lodash": "^1.0.0"}})),
+        workspace("packages/b", json!({"name": "b", "dependencies": {"lodash": "^1.0.0"}, "peerDependencies": {"lodash": "*"}})),
+    ]);
+
+    let rules = NativeRules {
+        consistent_dependencies: true,
+        ..Default::default()
+    };
+
+    let output
+        = evaluate_native_rules(&rules, &context, true);
+
+    assert!(operations(&output, "").is_empty());
+    assert_eq!(operations(&output, "packages/a"), &[set(&["dependencies", "lodash"], json!("^1.2.0"))]);
+    assert_eq!(operations(&output, "packages/b"), &[set(&["dependencies", "lodash"], json!("^1.2.0"))]);
+    assert!(output.all_workspace_errors.is_empty());
+}
+
+#[test]
+fn prefers_the_most_used_range_on_ties() {
+    let context = context(vec![
+        workspace("packages/a", json!({"name": "a", "dependencies": {"lodash": "^1.0.0"}})),
+        workspace("packages/b", json!({"name": "b", "dependencies": {"lodash": ">=1.0.0"}})),
+        workspace("packages/c", json!({"name": "c", "dependencies": {"lodash": ">=1.0.0"}})),
+    ]);
+
+    let rules = NativeRules {
+        consistent_dependencies: true,
+        ..Default::default()
+    };
+
+    let output
+        = evaluate_native_rules(&rules, &context, true);
+
+    assert_eq!(operations(&output, "packages/a"), &[set(&["dependencies", "lodash"], json!(">=1.0.0"))]);
+    assert_eq!(output.all_workspace_operations.len(), 1);
+}
+
+#[test]
+fn reports_forbidden_dependencies() {
+    let context = context(vec![
+        workspace("", json!({"name": "root", "dependencies": {"left-pad": "^1.0.0", "lodash": "^1.0.0"}, "devDependencies": {"@types/left-pad": "^1.0.0"}})),
+    ]);
+
+    let rules = NativeRules {
+        forbidden_dependencies: vec![IdentGlob::new("left-pad").unwrap(), IdentGlob::new("@types/*").unwrap()],
+        ..Default::default()
+    };
+
+    let output
+        = evaluate_native_rules(&rules, &context, false);
+
+    let errors
+        = &output.all_workspace_errors[&cwd("")];
+
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().all(|error| matches!(error, WorkspaceError::ExtraneousField {..})));
+
+    let output
+        = evaluate_native_rules(&rules, &context, true);
+
+    assert_eq!(operations(&output, ""), &[
+        unset(&["dependencies", "left-pad"]),
+        unset(&["devDependencies", "@types/left-pad"]),
+    ]);
+}
+
+#[test]
+fn pins_engines() {
+    let context = context(vec![
+        workspace("", json!({"name": "root", "engines": {"node": ">=20"}})),
+        workspace("packages/a", json!({"name": "a", "engines": {"node": ">=18"}})),
+        workspace("packages/b", json!({"name": "b"})),
+    ]);
+
+    let rules = NativeRules {
+        engines: BTreeMap::from([("node".to_string(), ">=20".to_string())]),
+        ..Default::default()
+    };
+
+    let output
+        = evaluate_native_rules(&rules, &context, false);
+
+    assert!(!output.all_workspace_errors.contains_key(&cwd("")));
+    assert!(matches!(output.all_workspace_errors[&cwd("packages/a")].as_slice(), [WorkspaceError::InvalidField {..}]));
+    assert!(matches!(output.all_workspace_errors[&cwd("packages/b")].as_slice(), [WorkspaceError::MissingField {..}]));
+
+    let output
+        = evaluate_native_rules(&rules, &context, true);
+
+    assert_eq!(operations(&output, "packages/a"), &[set(&["engines", "node"], json!(">=20"))]);
+    assert_eq!(operations(&output, "packages/b"), &[set(&["engines", "node"], json!(">=20"))]);
+}
+
+#[test]
+fn requires_fields_on_all_workspaces() {
+    let context = context(vec![
+        workspace("", json!({"name": "root", "private": true, "license": "MIT"})),
+        workspace("packages/a", json!({"name": "a", "license": "MIT"})),
+        workspace("packages/b", json!({"name": "b"})),
+        workspace("packages/c", json!({"name": "c", "private": true})),
+    ]);
+
+    let rules = NativeRules {
+        required_fields: vec!["license".to_string()],
+        ..Default::default()
+    };
+
+    let output
+        = evaluate_native_rules(&rules, &context, true);
+
+    assert!(output.all_workspace_operations.is_empty());
+    assert_eq!(output.all_workspace_errors.len(), 2);
+    assert!(matches!(output.all_workspace_errors[&cwd("packages/b")].as_slice(), [WorkspaceError::UserError {..}]));
+    assert!(matches!(output.all_workspace_errors[&cwd("packages/c")].as_slice(), [WorkspaceError::UserError {..}]));
+}
@@ -5,7 +5,7 @@ use serde_with::serde_as;
 use zpm_primitives::{Ident, Locator, Range};
 use zpm_utils::{ColoredJsonValue, DataType, Path, ToFileString, ToHumanString};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct Caller {
@@ -76,13 +76,13 @@ impl ToHumanString for Caller {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PerValueInfo {
     pub callers: Vec<Caller>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
 #[serde(rename_all_fields = "camelCase")]
@@ -114,7 +114,7 @@ pub enum WorkspaceError {
     },
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
 #[serde(rename_all_fields = "camelCase")]
@@ -129,7 +129,7 @@ pub enum WorkspaceOperation {
 }
 
 #[serde_as]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct ConstraintsOutput {
@@ -145,6 +145,22 @@ impl ConstraintsOutput {
     pub fn is_empty(&self) -> bool {
         self.all_workspace_operations.is_empty() && self.all_workspace_errors.is_empty()
     }
+
+    /// Appends the operations and errors of another output after the ones
+    /// already registered for each workspace.
+    pub fn merge(&mut self, other: ConstraintsOutput) {
+        for (workspace_cwd, operations) in other.all_workspace_operations {
+            self.all_workspace_operations.entry(workspace_cwd)
+                .or_default()
+                .extend(operations);
+        }
+
+        for (workspace_cwd, errors) in other.all_workspace_errors {
+            self.all_workspace_errors.entry(workspace_cwd)
+                .or_default()
+                .extend(errors);
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -168,6 +184,8 @@ pub struct ConstraintsDependency {
 #[serde(rename_all = "camelCase")]
 pub struct ConstraintsWorkspace {
     pub cwd: Path,
+    #[serde(skip)]
+    pub manifest: serde_json::Value,
     pub ident: Ident,
     pub dependencies: Vec<ConstraintsDependency>,
     pub peer_dependencies: Vec<ConstraintsDependency>,
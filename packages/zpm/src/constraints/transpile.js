@@ -0,0 +1,54 @@
+"use strict";
+
+const {createRequire} = require(`module`);
+const {readFileSync, writeFileSync} = require(`fs`);
+const {dirname} = require(`path`);
+
+const [SOURCE_PATH, OUTPUT_PATH] = process.argv.slice(2);
+
+const source = readFileSync(SOURCE_PATH, `utf8`);
+const projectRequire = createRequire(SOURCE_PATH);
+
+function tryRequire(name) {
+  try {
+    return projectRequire(name);
+  } catch (err) {
+    if (err.code === `MODULE_NOT_FOUND`)
+      return null;
+
+    throw err;
+  }
+}
+
+function transpile() {
+  const esbuild = tryRequire(`esbuild`);
+  if (esbuild)
+    return esbuild.transformSync(source, {loader: `ts`, format: `cjs`, sourcefile: SOURCE_PATH}).code;
+
+  const swc = tryRequire(`@swc/core`);
+  if (swc)
+    return swc.transformSync(source, {filename: SOURCE_PATH, jsc: {parser: {syntax: `typescript`}}, module: {type: `commonjs`}}).code;
+
+  const typescript = tryRequire(`typescript`);
+  if (typescript)
+    return typescript.transpileModule(source, {fileName: SOURCE_PATH, compilerOptions: {module: typescript.ModuleKind.CommonJS}}).outputText;
+
+  return null;
+}
+
+const code = transpile();
+
+// No transpiler available; the adapter will require the TypeScript file
+// as-is, which works on Node versions able to strip types natively.
+if (code === null)
+  process.exit(0);
+
+// The emitted file lives in the cache folder, so we make sure relative
+// requires keep resolving from the location of the original file.
+const prelude = [
+  `var __filename = ${JSON.stringify(SOURCE_PATH)};`,
+  `var __dirname = ${JSON.stringify(dirname(SOURCE_PATH))};`,
+  `var require = require("module").createRequire(__filename);`,
+].join(` `);
+
+writeFileSync(OUTPUT_PATH, `${prelude}\n${code}`);
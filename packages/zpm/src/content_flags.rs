@@ -52,6 +52,13 @@ pub struct ContentFlags {
      */
     #[serde(default, skip_serializing_if = "zpm_utils::is_default")]
     pub suggest_extracted: bool,
+
+    /**
+     * The license declared by the package, used to evaluate the license
+     * policy without having to read the archive again.
+     */
+    #[serde(default, skip_serializing_if = "zpm_utils::is_default")]
+    pub license: Option<String>,
 }
 
 impl Default for ContentFlags {
@@ -61,6 +68,7 @@ impl Default for ContentFlags {
             build_commands: vec![],
             prefer_extracted: None,
             suggest_extracted: false,
+            license: None,
         }
     }
 }
@@ -84,6 +92,10 @@ struct Manifest {
     #[serde(default)]
     prefer_unplugged: Option<bool>,
 
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    license: Option<String>,
+
     #[serde(default)]
     #[serde_as(deserialize_as = "DefaultOnError")]
     scripts: BTreeMap<String, String>,
@@ -145,15 +157,13 @@ impl ContentFlags {
             build_commands,
             prefer_extracted: Some(false),
             suggest_extracted: false,
+            license: manifest.license,
         })
     }
 
     fn extract_zip(locator: &Locator, archive_path: &Path) -> Result<Self, Error> {
-        let package_bytes
-            = archive_path.fs_read()?;
-
         let first_entry
-            = zpm_formats::zip::first_entry_from_zip(&package_bytes)?;
+            = zpm_formats::zip::first_entry_from_zip_file(archive_path)?;
 
         let meta_manifest: Manifest
             = JsonDocument::hydrate_from_slice(&first_entry.data)?;
@@ -188,6 +198,7 @@ impl ContentFlags {
             build_commands,
             prefer_extracted,
             suggest_extracted,
+            license: meta_manifest.license,
         })
     }
 }
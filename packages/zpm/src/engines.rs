@@ -0,0 +1,54 @@
+use std::str::FromStr;
+
+use zpm_utils::{DataType, ToHumanString};
+
+use crate::{
+    error::{Error, ErrorCode},
+    project::Project,
+    report::current_report,
+    script::ScriptEnvironment,
+};
+
+/// Returns the version of the Node binary found in the PATH, or `None` if we
+/// can't find it (in which case there's nothing we can check).
+pub async fn current_node_version(project: &Project) -> Option<zpm_semver::Version> {
+    let stdout = ScriptEnvironment::new().ok()?
+        .with_cwd(project.project_cwd.clone())
+        .run_exec("node", vec!["--version"])
+        .await.ok()?
+        .stdout_text().ok()?;
+
+    zpm_semver::Version::from_str(stdout.trim().trim_start_matches('v')).ok()
+}
+
+/// Checks the running Node version against the `engines.node` field of the
+/// root workspace. Mismatches are reported as warnings, unless `engineStrict`
+/// is set, in which case they make the install fail.
+pub async fn check_engines(project: &Project) -> Result<(), Error> {
+    let Some(range) = project.root_workspace().manifest.engines.get("node") else {
+        return Ok(());
+    };
+
+    let Some(version) = current_node_version(project).await else {
+        return Ok(());
+    };
+
+    if range.check(&version) {
+        return Ok(());
+    }
+
+    if project.config.settings.engine_strict.value {
+        return Err(Error::EngineMismatch("node".to_string(), range.clone(), version));
+    }
+
+    current_report().await.as_ref().map(|report| {
+        report.warn_with_code(ErrorCode::EngineMismatch, format!(
+            "The current Node version ({}) doesn't satisfy the {} range required by the project ({})",
+            version.to_print_string(),
+            DataType::Code.colorize("engines.node"),
+            range.to_print_string(),
+        ));
+    });
+
+    Ok(())
+}
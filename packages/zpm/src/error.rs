@@ -1,9 +1,11 @@
 use std::{future::Future, sync::Arc};
 
 use zpm_primitives::{Descriptor, Ident, Locator, Range};
-use zpm_utils::{DataType, Path, ToHumanString};
+use zpm_utils::{DataType, Path, ToHumanString, Unit};
 use tokio::task::JoinError;
 
+use crate::{lockfile::LockfileError, project::WorkspaceCycle};
+
 fn render_backtrace(backtrace: &std::backtrace::Backtrace) -> String {
     if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
         backtrace.to_string().trim_end().to_string()
@@ -60,6 +62,12 @@ pub enum Error {
     #[error("Cannot publish packages with a missing name or version")]
     CannotPublishMissingNameOrVersion,
 
+    #[error("{} is listed in bundledDependencies, but isn't a dependency of the packed workspace", .0.to_print_string())]
+    BundledDependencyNotFound(Ident),
+
+    #[error("{} is listed in bundledDependencies, but isn't installed; run an install before packing", .0.to_print_string())]
+    BundledDependencyNotInstalled(Ident),
+
     #[error("Invalid publish access: {0}")]
     InvalidNpmPublishAccess(String),
 
@@ -72,6 +80,12 @@ pub enum Error {
     #[error("Publishing a package with provenance requires authentication")]
     ProvenanceRequiresAuthentication,
 
+    #[error("Provenance can only be generated when publishing from GitHub Actions or GitLab CI")]
+    ProvenanceRequiresSupportedCi,
+
+    #[error("Provenance requires an OIDC token, but none could be obtained from the CI environment (on GitHub Actions, make sure the workflow has the id-token: write permission)")]
+    ProvenanceRequiresOidcToken,
+
     #[error("Serialization error: {0}")]
     SerializationError(String),
 
@@ -87,6 +101,9 @@ pub enum Error {
     #[error("Checksum mismatch for {}", .0.to_print_string())]
     ChecksumMismatch(Locator),
 
+    #[error("The content of some cached archives doesn't match the lockfile checksums:\n\n{}", .0.iter().map(|locator| format!("- {}", locator.to_print_string())).collect::<Vec<_>>().join("\n"))]
+    CacheCheckFailed(Vec<Locator>),
+
     #[error("[YN0028] The lockfile would have been created by this install, which is explicitly forbidden.")]
     ImmutableLockfile,
 
@@ -96,9 +113,6 @@ pub enum Error {
     #[error("Found an incorrectly formatted package manifest when running an immutable install ({})", .0.to_print_string())]
     ImmutablePackageManifest(Path),
 
-    #[error("Git returned an error when attempting to autofix the lockfile: {0}")]
-    LockfileAutofixGitError(String),
-
     #[error("The argument folder didn't get created by 'yarn patch'")]
     NotAPatchFolder(Path),
 
@@ -120,14 +134,23 @@ pub enum Error {
     #[error("[YN0091] Cache path does not exist ({}).", .0.to_print_string())]
     MissingCacheFolder(Path),
 
+    #[error("A package cache is required to locate the archive of {}", .0.to_print_string())]
+    MissingPackageCache(Locator),
+
+    #[error("Brotli-compressed caches can't be read by the PnP runtime; set {} to {} or {}, or pick another {}", DataType::Code.colorize("nodeLinker"), DataType::Code.colorize("node-modules"), DataType::Code.colorize("pnpm"), DataType::Code.colorize("compressionLevel"))]
+    BrotliCacheWithPnp,
+
     #[error("[YN0080] Request to '{0}' has been blocked because of your configuration settings.")]
     NetworkDisabledError(reqwest::Url),
 
     #[error("[YN0081] Unsafe http requests must be explicitly whitelisted in your configuration ({}).", .0.host_str().expect("\"http:\" URL should have a host"))]
     UnsafeHttpError(reqwest::Url),
 
-    #[error("Algolia registry error")]
-    AlgoliaRegistryError(Arc<reqwest::Error>),
+    #[error("The {0} header can't be configured through httpHeaders")]
+    ForbiddenHttpHeader(String),
+
+    #[error("Invalid http header ({0})")]
+    InvalidHttpHeader(String),
 
     #[error("Authentication error: {0}")]
     AuthenticationError(String),
@@ -189,12 +212,18 @@ pub enum Error {
     #[error("Package not found ({})", .0.to_print_string())]
     PackageNotFound(Ident),
 
+    #[error("Unknown field ({0}); available fields are: {1}")]
+    UnknownPackageInfoField(String, String),
+
     #[error("No matching variant found for {}", .0.to_print_string())]
     NoMatchingVariantFound(Locator),
 
     #[error("No candidates found for {}", .0.to_print_string())]
     NoCandidatesFound(Range),
 
+    #[error("No candidates found for {}; the available tags are {}", .0.to_print_string(), .1)]
+    NoCandidatesFoundWithTags(Range, String),
+
     #[error("I/O error ({inner})\n\n{}", render_backtrace(backtrace))]
     IoError {
         inner: Arc<std::io::Error>,
@@ -210,6 +239,9 @@ pub enum Error {
     #[error("Invalid glob pattern ({0})")]
     InvalidGlob(String),
 
+    #[error("Invalid SPDX license expression ({0})")]
+    InvalidSpdxExpression(String),
+
     #[error("Glob error")]
     GlobError(#[from] globset::Error),
 
@@ -252,6 +284,9 @@ pub enum Error {
     #[error("An error occured while parsing the lockfile: {0}")]
     LockfileParseError(zpm_parsers::Error),
 
+    #[error("The lockfile is corrupted:\n{}", .0.iter().map(|error| format!("- {}", error)).collect::<Vec<_>>().join("\n"))]
+    LockfileIntegrityError(Vec<LockfileError>),
+
     #[error("Can't perform this operation without a git root")]
     NoGitRoot,
 
@@ -291,6 +326,9 @@ pub enum Error {
     #[error("Invalid dedupe strategy ({0})")]
     InvalidDedupeStrategy(String),
 
+    #[error("Recursive upgrades only accept package names, not ranges ({0})")]
+    InvalidRecursiveUpPattern(String),
+
     #[error("Invalid Git specifier")]
     InvalidGitSpecifier,
 
@@ -336,6 +374,9 @@ pub enum Error {
     #[error("Invalid install state; please run an install operation to fix it")]
     InvalidInstallState,
 
+    #[error("The install state is out of date ({0}); please run an install operation to fix it")]
+    OutdatedInstallState(String),
+
     #[error("Couldn't find a package matching the current working directory")]
     ActivePackageNotFound,
 
@@ -354,6 +395,12 @@ pub enum Error {
     #[error("Binary not found ({0})")]
     BinaryNotFound(String),
 
+    #[error("Binary not found ({0}); did you mean one of: {1}?")]
+    BinaryNotFoundWithSuggestions(String, String),
+
+    #[error("Binary not found ({0}); it may have been provided by a dependency that wasn't fetched because it doesn't support the current platform ({1})")]
+    BinaryFromExcludedPackage(String, String),
+
     #[error("Binary failed to spawn: {error} ({}, in {})", DataType::Code.colorize(name), path.to_print_string())]
     SpawnFailed { name: String, path: Path, error: Arc<Box<dyn std::error::Error + Send + Sync>> },
 
@@ -363,12 +410,48 @@ pub enum Error {
     #[error("Ambiguous dlx context; use the -p syntax to clarify package and binary names")]
     AmbiguousDlxContext,
 
+    #[error("Circular template dependency detected ({0})")]
+    TemplateCycle(String),
+
+    #[error("Templates must be fetched as archives ({})", .0.to_print_string())]
+    UnsupportedTemplateSource(Locator),
+
+    #[error("Workspaces must be located inside the project ({})", .0.to_print_string())]
+    WorkspaceOutsideProject(Path),
+
     #[error("Circular build dependency detected")]
     CircularBuildDependency(Locator),
 
+    #[error("The packed workspace depends on {} through the workspace: protocol, but it's private and won't be published", .0.to_print_string())]
+    PrivateWorkspaceDependency(Ident),
+
+    #[error("The packed workspace depends on {} through the workspace: protocol, but it has no version to replace the range with", .0.to_print_string())]
+    UnversionedWorkspaceDependency(Ident),
+
+    #[error("Workspaces depend on each other, so they can't be processed in topological order:\n\n{}", .0.iter().map(|cycle| format!("- {}", cycle.to_print_string())).collect::<Vec<_>>().join("\n"))]
+    WorkspaceCycles(Vec<WorkspaceCycle>),
+
     #[error("Some build scripts failed to run")]
     BuildScriptsFailedToRun,
 
+    #[error("No installed package matches the given patterns ({0})")]
+    NoMatchingPackages(String),
+
+    #[error("Portal {}: dependency {} conflicts with parent dependency {}", .0.to_print_string(), .1.to_print_string(), .2.to_print_string())]
+    PortalDependencyConflict(Locator, Locator, Locator),
+
+    #[error("Portal {}: dependency {} can't be hoisted outside of the portal, and portals can't host their own node_modules folder", .0.to_print_string(), .1.to_print_string())]
+    UnhoistablePortalDependency(Locator, Locator),
+
+    #[error("{} failed to build; the last lines of its output were:\n\n{}\n\nThe full log is available at {}", .0.to_print_string(), .1, .2.to_print_string())]
+    BuildScriptFailed(Locator, String, Path),
+
+    #[error("{} wasn't built because its dependency {} failed to build", .0.to_print_string(), .1.to_print_string())]
+    DependencyBuildFailed(Locator, Locator),
+
+    #[error("The generator script of {} isn't allowed to run; allow the build scripts of {} to trust it", .0.to_print_string(), .1.ident.to_print_string())]
+    ExecScriptNotAllowed(Locator, Locator),
+
     #[error("Invalid pack pattern ({0})")]
     InvalidPackPattern(String),
 
@@ -381,6 +464,21 @@ pub enum Error {
     #[error("Child process failed ({0})")]
     ChildProcessFailed(String),
 
+    #[error("Script timed out and was killed ({script})")]
+    ScriptTimeout { script: String },
+
+    #[error("Invalid shell script ({0})")]
+    ShellParseError(String),
+
+    #[error("{0}")]
+    LicensePolicyViolation(String),
+
+    #[error("The install doesn't comply with the license policy")]
+    LicensePolicyError,
+
+    #[error("The current {} version ({}) doesn't satisfy the range required by the project ({})", .0, .2.to_print_string(), .1.to_print_string())]
+    EngineMismatch(String, zpm_semver::Range, zpm_semver::Version),
+
     #[error("Child process failed ({}); check {} for details", .0, .1.to_print_string())]
     ChildProcessFailedWithLog(String, Path),
 
@@ -435,6 +533,9 @@ pub enum Error {
     #[error("Task timeout")]
     TaskTimeout,
 
+    #[error("Task timeout ({} transferred before giving up)", Unit::bytes(*.0).to_print_string())]
+    TaskTimeoutAfterTransfer(u64),
+
     #[error("Invalid install mode ({0})")]
     InvalidInstallMode(String),
 
@@ -450,6 +551,12 @@ pub enum Error {
     #[error("Your version of npm doesn't support workspaces")]
     UnsupportedNpmWorkspaces(zpm_semver::Version),
 
+    #[error("No workspace matches {0} in the git repository")]
+    GitWorkspaceNotFound(String),
+
+    #[error("Multiple workspaces match {0} in the git repository ({1})")]
+    AmbiguousGitWorkspace(String, String),
+
     #[error("Declining a version bump is only allowed when using the `--deferred` flag or when `preferDeferredVersions` is enabled")]
     VersionDeclineNotAllowed,
 
@@ -497,6 +604,38 @@ pub enum Error {
     SilentError,
 }
 
+/// Stable identifiers for the categories of errors and warnings the reports
+/// may contain. Unlike the messages, which may be reworded at any time, these
+/// codes are part of the JSON output and are meant to let tools filter the
+/// events they care about; existing codes must never be renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    Unnamed,
+    Configuration,
+    NetworkFailure,
+    NetworkDisabled,
+    SlowNetwork,
+    AuthenticationFailure,
+    ChecksumMismatch,
+    ImmutableLockfile,
+    ImmutableCache,
+    ResolutionFailure,
+    InvalidManifest,
+    InvalidLockfile,
+    FileSystem,
+    BuildFailure,
+    BuildSkipped,
+    ScriptNotAllowed,
+    Timeout,
+    EngineMismatch,
+    LicensePolicy,
+    PatchFailure,
+    DeprecatedPackage,
+    UnsatisfiedDependency,
+    WorkspaceCycle,
+}
+
 impl Error {
     pub fn ignore<T, F: FnOnce(&Error) -> bool>(self, f: F) -> Result<Option<T>, Error> {
         match f(&self) {
@@ -504,6 +643,69 @@ impl Error {
             false => Err(self),
         }
     }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::ConfigurationError(_) | Error::ConfigurationHydrateError(_) | Error::ConfigurationParseError(_) | Error::BrotliCacheWithPnp
+                => ErrorCode::Configuration,
+
+            Error::HttpError {..} | Error::DnsResolutionError(_) | Error::RemoteRegistryError(_)
+                => ErrorCode::NetworkFailure,
+
+            Error::NetworkDisabledError(_) | Error::UnsafeHttpError(_)
+                => ErrorCode::NetworkDisabled,
+
+            Error::AuthenticationError(_)
+                => ErrorCode::AuthenticationFailure,
+
+            Error::ChecksumMismatch(_) | Error::CacheCheckFailed(_)
+                => ErrorCode::ChecksumMismatch,
+
+            Error::ImmutableLockfile | Error::ImmutableLockfileAutofix | Error::ImmutablePackageManifest(_)
+                => ErrorCode::ImmutableLockfile,
+
+            Error::ImmutableCache(_) | Error::ImmutableCacheCleanup(_)
+                => ErrorCode::ImmutableCache,
+
+            Error::TagNotFound(_) | Error::PackageNotFound(_) | Error::NoCandidatesFound(_) | Error::NoCandidatesFoundWithTags(_, _) | Error::NoMatchingVariantFound(_)
+                => ErrorCode::ResolutionFailure,
+
+            Error::ManifestNotFound(_) | Error::ManifestParseError(_, _) | Error::BundledDependencyNotFound(_) | Error::PrivateWorkspaceDependency(_) | Error::UnversionedWorkspaceDependency(_)
+                => ErrorCode::InvalidManifest,
+
+            Error::LockfileReadError(_) | Error::LockfileParseError(_) | Error::LockfileIntegrityError(_) | Error::LegacyLockfileParseError(_) | Error::LockfileV1Error
+                => ErrorCode::InvalidLockfile,
+
+            Error::PathError(_) | Error::SyncError(_) | Error::SyncError2(_) | Error::IoError {..}
+                => ErrorCode::FileSystem,
+
+            Error::BuildScriptsFailedToRun | Error::BuildScriptFailed(_, _, _) | Error::DependencyBuildFailed(_, _) | Error::ChildProcessFailed(_) | Error::ChildProcessFailedWithLog(_, _)
+                => ErrorCode::BuildFailure,
+
+            Error::ExecScriptNotAllowed(_, _)
+                => ErrorCode::ScriptNotAllowed,
+
+            Error::ScriptTimeout {..} | Error::TaskTimeout | Error::TaskTimeoutAfterTransfer(_)
+                => ErrorCode::Timeout,
+
+            Error::EngineMismatch(_, _, _)
+                => ErrorCode::EngineMismatch,
+
+            Error::LicensePolicyViolation(_) | Error::LicensePolicyError
+                => ErrorCode::LicensePolicy,
+
+            Error::UnmatchedHunk(_) | Error::PatchedFileNotFound(_) | Error::HunkIntegrityCheckFailed | Error::EmptyPatchFile
+                => ErrorCode::PatchFailure,
+
+            Error::WorkspaceCycles(_)
+                => ErrorCode::WorkspaceCycle,
+
+            Error::PortalDependencyConflict(_, _, _) | Error::UnhoistablePortalDependency(_, _)
+                => ErrorCode::UnsatisfiedDependency,
+
+            _ => ErrorCode::Unnamed,
+        }
+    }
 }
 
 impl From<JoinError> for Error {
@@ -0,0 +1,151 @@
+use zpm_parsers::JsonDocument;
+use zpm_primitives::{ExecReference, Locator};
+use zpm_formats::Entry;
+use zpm_utils::{Path, ToFileString};
+
+use crate::{
+    error::Error, install::{FetchResult, InstallContext, InstallOpResult}, manifest::{helpers::read_manifest, RemoteManifest}, npm::NpmEntryExt, resolvers::Resolution, script::ScriptEnvironment
+};
+
+use super::PackageData;
+
+/// Generates a package by running the referenced script with Node. The
+/// script runs from an empty build directory and receives the following
+/// environment variables:
+///
+/// - `EXEC_BUILD_DIR`: the directory the package files must be written to
+///   (also the script's working directory).
+/// - `EXEC_TEMP_DIR`: an empty directory the script is free to use.
+/// - `EXEC_LOCATOR`: the stringified locator of the generated package.
+///
+/// For compatibility with Yarn Berry the same values are also exposed through
+/// the `execEnv` global, along with the Node builtin modules.
+///
+/// Once the script exits successfully, the content of the build directory is
+/// packed into the cache like any other package.
+pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator, params: &ExecReference, dependencies: Vec<InstallOpResult>) -> Result<FetchResult, Error> {
+    let project = context.project
+        .expect("The project is required for fetching exec packages");
+
+    let parent_data
+        = dependencies[0].as_fetched();
+
+    let script_path = parent_data.package_data
+        .context_directory()
+        .with_join_str(&params.path);
+
+    let package_cache = context.package_cache
+        .expect("The package cache is required for fetching exec packages");
+
+    let package_subdir
+        = locator.ident.nm_subdir();
+
+    let pkg_blob = package_cache.ensure_blob(locator.clone(), ".zip", || async {
+        // Generators declared by the project's own workspaces are trusted like
+        // their build scripts are; those coming from third-party packages
+        // must be explicitly allowed. The generator is part of the package
+        // declaring it, so that's the package (and version) that must be
+        // trusted; the generated package's version is only known once the
+        // script ran.
+        let is_script_allowed = match locator.parent.as_deref() {
+            Some(parent) if parent.reference.is_workspace_reference() => {
+                true
+            },
+
+            Some(parent) => {
+                let parent_version = read_manifest(&parent_data.package_data.package_directory().with_join_str("package.json")).ok()
+                    .and_then(|manifest| manifest.remote.version);
+
+                project.is_build_allowed(&parent.ident, parent_version.as_ref())
+            },
+
+            None => {
+                project.is_build_allowed(&locator.ident, None)
+            },
+        };
+
+        if !is_script_allowed {
+            let trusted_locator = locator.parent.as_deref()
+                .unwrap_or(locator);
+
+            return Err(Error::ExecScriptNotAllowed(locator.clone(), trusted_locator.clone()));
+        }
+
+        let work_dir
+            = Path::temp_dir()?;
+
+        // The work directory is removed even when the script fails, so that
+        // failed installs don't leave partial builds behind.
+        let entries
+            = run_generator(locator, &script_path, &work_dir, &package_subdir).await;
+
+        let _ = work_dir
+            .fs_rm();
+
+        let entries
+            = entries?;
+
+        Ok(package_cache.bundle_entries(entries)?)
+    }).await?.into_info();
+
+    let first_entry
+        = zpm_formats::zip::first_entry_from_zip_file(&pkg_blob.path)?;
+
+    let remote_manifest: RemoteManifest
+        = JsonDocument::hydrate_from_slice(&first_entry.data)?;
+
+    let resolution
+        = Resolution::from_remote_manifest(locator.clone(), remote_manifest);
+
+    let package_directory = pkg_blob.path
+        .with_join(&package_subdir);
+
+    Ok(FetchResult {
+        resolution: Some(resolution),
+        package_data: PackageData::Zip {
+            archive_path: pkg_blob.path,
+            checksum: pkg_blob.checksum,
+            context_directory: package_directory.clone(),
+            package_directory,
+        },
+    })
+}
+
+async fn run_generator(locator: &Locator, script_path: &Path, work_dir: &Path, package_subdir: &Path) -> Result<Vec<Entry<'static>>, Error> {
+    let build_dir = work_dir
+        .with_join_str("build");
+    let temp_dir = work_dir
+        .with_join_str("temp");
+    let runtime_path = work_dir
+        .with_join_str("runtime.cjs");
+
+    build_dir
+        .fs_create_dir()?;
+    temp_dir
+        .fs_create_dir()?;
+    runtime_path
+        .fs_write_text(std::include_str!("exec.runtime.js"))?;
+
+    ScriptEnvironment::new()?
+        .with_cwd(build_dir.clone())
+        .with_env_variable("EXEC_BUILD_DIR", &build_dir.to_file_string())
+        .with_env_variable("EXEC_TEMP_DIR", &temp_dir.to_file_string())
+        .with_env_variable("EXEC_LOCATOR", &locator.to_file_string())
+        .run_exec("node", vec!["--require".to_string(), runtime_path.to_file_string(), script_path.to_file_string()])
+        .await?
+        .ok()?;
+
+    if !build_dir.with_join_str("package.json").fs_exists() {
+        return Err(Error::ManifestNotFound(build_dir.with_join_str("package.json")));
+    }
+
+    // Entries are sorted by name before being bundled, so running the
+    // same script twice yields the exact same archive (and checksum).
+    let entries
+        = zpm_formats::entries_from_folder(&build_dir)?
+            .into_iter()
+            .prepare_npm_entries(package_subdir)
+            .collect::<Vec<_>>();
+
+    Ok(entries)
+}
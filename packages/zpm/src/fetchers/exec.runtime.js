@@ -0,0 +1,30 @@
+// Preloaded before running `exec:` generators; mirrors the runtime
+// environment that Yarn Berry exposes to those scripts.
+
+const Module = require(`module`);
+
+Object.defineProperty(global, `Module`, {
+  get: () => Module,
+  configurable: true,
+  enumerable: false,
+});
+
+for (const name of Module.builtinModules) {
+  if (name === `module` || name.startsWith(`_`))
+    continue;
+
+  Object.defineProperty(global, name, {
+    get: () => require(name),
+    configurable: true,
+    enumerable: false,
+  });
+}
+
+Object.defineProperty(global, `execEnv`, {
+  value: {
+    tempDir: process.env.EXEC_TEMP_DIR,
+    buildDir: process.env.EXEC_BUILD_DIR,
+    locator: process.env.EXEC_LOCATOR,
+  },
+  enumerable: true,
+});
@@ -21,7 +21,7 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
     let package_subdir
         = locator.ident.nm_subdir();
 
-    let pkg_blob = package_cache.upsert_blob(locator.clone(), ".zip", || async {
+    let pkg_blob = package_cache.ensure_blob(locator.clone(), ".zip", || async {
         let entries
             = zpm_formats::entries_from_folder(&context_directory)?
                 .into_iter()
@@ -29,10 +29,10 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
                 .collect::<Vec<_>>();
 
         Ok(package_cache.bundle_entries(entries)?)
-    }).await?;
+    }).await?.into_info();
 
     let first_entry
-        = zpm_formats::zip::first_entry_from_zip(&pkg_blob.data)?;
+        = zpm_formats::zip::first_entry_from_zip_file(&pkg_blob.path)?;
 
     let remote_manifest: RemoteManifest
         = JsonDocument::hydrate_from_slice(&first_entry.data)?;
@@ -40,14 +40,14 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
     let resolution
         = Resolution::from_remote_manifest(locator.clone(), remote_manifest);
 
-    let package_directory = pkg_blob.info.path
+    let package_directory = pkg_blob.path
         .with_join(&package_subdir);
 
     Ok(FetchResult {
         resolution: Some(resolution),
         package_data: PackageData::Zip {
-            archive_path: pkg_blob.info.path,
-            checksum: pkg_blob.info.checksum,
+            archive_path: pkg_blob.path,
+            checksum: pkg_blob.checksum,
             context_directory,
             package_directory,
         },
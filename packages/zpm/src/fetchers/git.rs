@@ -15,14 +15,21 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
     let package_subdir
         = locator.ident.nm_subdir();
 
-    let pkg_blob = package_cache.upsert_blob(locator.clone(), ".zip", || async {
+    let pkg_blob = package_cache.ensure_blob(locator.clone(), ".zip", || async {
         let repository_path
             = git::clone_repository(context, &params.git.repo, &params.git.commit).await?;
 
+        // Repositories typically don't commit their build artifacts, so we
+        // need to install their devDependencies and run their prepare
+        // scripts unless the user opted out of running scripts.
+        let enable_scripts = context.project
+            .map_or(true, |project| project.config.settings.enable_git_prepare.value && project.is_build_allowed(&locator.ident, None));
+
         let pack_tgz = prepare::prepare_project(
             locator,
             &repository_path,
             &params.git.prepare_params,
+            enable_scripts,
         ).await?;
 
         let pack_tar
@@ -36,10 +43,10 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
                 .collect::<Vec<_>>();
 
         Ok(package_cache.bundle_entries(entries)?)
-    }).await?;
+    }).await?.into_info();
 
     let first_entry
-        = zpm_formats::zip::first_entry_from_zip(&pkg_blob.data)?;
+        = zpm_formats::zip::first_entry_from_zip_file(&pkg_blob.path)?;
 
     let remote_manifest: RemoteManifest
         = JsonDocument::hydrate_from_slice(&first_entry.data)?;
@@ -47,14 +54,14 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
     let resolution
         = Resolution::from_remote_manifest(locator.clone(), remote_manifest);
 
-    let package_directory = pkg_blob.info.path
+    let package_directory = pkg_blob.path
         .with_join(&package_subdir);
 
     Ok(FetchResult {
         resolution: Some(resolution),
         package_data: PackageData::Zip {
-            archive_path: pkg_blob.info.path,
-            checksum: pkg_blob.info.checksum,
+            archive_path: pkg_blob.path,
+            checksum: pkg_blob.checksum,
             context_directory: package_directory.clone(),
             package_directory,
         },
@@ -8,6 +8,7 @@ use crate::{
 };
 
 pub mod builtin;
+pub mod exec;
 pub mod folder;
 pub mod git;
 pub mod link;
@@ -39,8 +40,9 @@ pub enum PackageData {
         is_synthetic_package: bool,
     },
 
+    /** A placeholder for a package that wasn't fetched because it doesn't support the current platform; linkers must skip it */
     MissingZip {
-        /** Path of the .zip file on disk */
+        /** Path the .zip file would have on disk */
         archive_path: Path,
 
         /** Directory from which relative links from link:/file:/portal: dependencies will be resolved */
@@ -174,9 +176,15 @@ pub async fn fetch_locator<'a>(context: InstallContext<'a>, locator: &Locator, i
         Reference::Portal(params)
             => portal::fetch_locator(&context, locator, params, dependencies),
 
+        Reference::Url(_) | Reference::Git(_) if is_mock_request
+            => fetch_mock_locator(&context, locator),
+
         Reference::Url(params)
             => url::fetch_locator(&context, locator, params).await,
 
+        Reference::Exec(params)
+            => exec::fetch_locator(&context, locator, params, dependencies).await,
+
         Reference::Tarball(params)
             => tarball::fetch_locator(&context, locator, params, dependencies).await,
 
@@ -204,3 +212,19 @@ pub async fn fetch_locator<'a>(context: InstallContext<'a>, locator: &Locator, i
         _ => panic!("This reference ({}) should never end up being passed to a fetcher", locator.reference.to_print_string()),
     }
 }
+
+/// Remote packages excluded from the current platform aren't downloaded at
+/// all; we only generate the paths their archive would have had so that the
+/// rest of the install can reason about them like any other package.
+fn fetch_mock_locator(context: &InstallContext, locator: &Locator) -> Result<FetchResult, Error> {
+    let package_cache = context.package_cache
+        .ok_or_else(|| Error::MissingPackageCache(locator.clone()))?;
+
+    let archive_path = package_cache
+        .key_path(locator, ".zip");
+
+    let package_directory = archive_path
+        .with_join(&locator.ident.nm_subdir());
+
+    Ok(FetchResult::new_mock(archive_path, package_directory))
+}
@@ -77,6 +77,9 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
         }).await?;
 
     let cached_blob = package_cache.ensure_blob(locator.clone(), ".zip", || async {
+        let _download_slot
+            = project.http_client.acquire_download_slot().await;
+
         let bytes
             = http_npm::get(&http_npm::NpmHttpParams {
                 http_client: &project.http_client,
@@ -87,7 +90,7 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
             }).await?;
 
         let tar_data
-            = zpm_formats::tar::unpack_tgz(&bytes)?;
+            = zpm_formats::tar::unpack_tgz_owned(bytes.into())?;
 
         let entries
             = zpm_formats::tar::entries_from_tar(&tar_data)?
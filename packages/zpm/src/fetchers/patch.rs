@@ -81,7 +81,7 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
     let package_subdir
         = locator.ident.nm_subdir();
 
-    let cached_blob = package_cache.upsert_blob(locator.clone(), ".zip", || async {
+    let cached_blob = package_cache.ensure_blob(locator.clone(), ".zip", || async {
         let original_bytes = match &original_data.package_data {
             PackageData::Zip {archive_path, ..} => Some(archive_path.fs_read()?),
             _ => None,
@@ -136,10 +136,10 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
             .collect::<Vec<_>>();
 
         Ok(package_cache.bundle_entries(patched_entries)?)
-    }).await?;
+    }).await?.into_info();
 
     let package_json_entry
-        = zpm_formats::zip::first_entry_from_zip(&cached_blob.data)?;
+        = zpm_formats::zip::first_entry_from_zip_file(&cached_blob.path)?;
 
     let manifest: RemoteManifest
         = JsonDocument::hydrate_from_slice(&package_json_entry.data)?;
@@ -147,14 +147,14 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
     let resolution
         = Resolution::from_remote_manifest(locator.clone(), manifest);
 
-    let package_directory = cached_blob.info.path
+    let package_directory = cached_blob.path
         .with_join(&package_subdir);
 
     Ok(FetchResult {
         resolution: Some(resolution),
         package_data: PackageData::Zip {
-            archive_path: cached_blob.info.path,
-            checksum: cached_blob.info.checksum,
+            archive_path: cached_blob.path,
+            checksum: cached_blob.checksum,
             context_directory: package_directory.clone(),
             package_directory,
         },
@@ -22,11 +22,9 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
     let package_subdir
         = locator.ident.nm_subdir();
 
-    let cached_blob = package_cache.upsert_blob(locator.clone(), ".zip", || async {
-        let tgz_data
-            = tarball_path.fs_read()?;
+    let cached_blob = package_cache.ensure_blob(locator.clone(), ".zip", || async {
         let tar_data
-            = zpm_formats::tar::unpack_tgz(&tgz_data)?;
+            = zpm_formats::tar::unpack_tgz_owned(tarball_path.fs_read()?)?;
 
         let entries
             = zpm_formats::tar::entries_from_tar(&tar_data)?
@@ -36,10 +34,10 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
                 .collect();
 
         Ok(package_cache.bundle_entries(entries)?)
-    }).await?;
+    }).await?.into_info();
 
     let first_entry
-        = zpm_formats::zip::first_entry_from_zip(&cached_blob.data)?;
+        = zpm_formats::zip::first_entry_from_zip_file(&cached_blob.path)?;
 
     let manifest: RemoteManifest
         = JsonDocument::hydrate_from_slice(&first_entry.data)?;
@@ -47,14 +45,14 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
     let resolution
         = Resolution::from_remote_manifest(locator.clone(), manifest);
 
-    let package_directory = cached_blob.info.path
+    let package_directory = cached_blob.path
         .with_join(&package_subdir);
 
     Ok(FetchResult {
         resolution: Some(resolution),
         package_data: PackageData::Zip {
-            archive_path: cached_blob.info.path,
-            checksum: cached_blob.info.checksum,
+            archive_path: cached_blob.path,
+            checksum: cached_blob.checksum,
             context_directory: package_directory.clone(),
             package_directory,
         },
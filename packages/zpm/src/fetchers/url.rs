@@ -1,5 +1,3 @@
-use std::sync::Arc;
-
 use zpm_formats::iter_ext::IterExt;
 use zpm_parsers::JsonDocument;
 use zpm_primitives::{Locator, UrlReference};
@@ -47,15 +45,32 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
         None
     };
 
-    let cached_blob = package_cache.upsert_blob(locator.clone(), ".zip", || async {
-        let response = project.http_client.get(&params.url)?
-            .header("authorization", authorization.as_deref())
-            .send().await?;
+    let cached_blob = package_cache.ensure_blob(locator.clone(), ".zip", || async {
+        let _download_slot
+            = project.http_client.acquire_download_slot().await;
+
+        // Interrupted downloads are kept next to the cache entry, so that
+        // the next attempt can resume them rather than start over
+        let part_path
+            = package_cache.key_path(locator, ".tgz.part");
+
+        let request = project.http_client.get(&params.url)?
+            .header("authorization", authorization.as_deref());
+
+        let mut download = request.try_clone()
+            .expect("GET requests should be clonable")
+            .send_resumable(&part_path).await?;
+
+        // A resumed download is made of two different responses; if the
+        // resource changed in-between, the archive will be invalid and we
+        // have to download it again in full
+        if download.resumed && zpm_formats::tar::unpack_tgz(&download.data).is_err() {
+            download = request
+                .send_resumable(&part_path).await?;
+        }
 
-        let tgz_data = response.bytes().await
-            .map_err(|err| Error::RemoteRegistryError(Arc::new(err)))?;
         let tar_data
-            = zpm_formats::tar::unpack_tgz(&tgz_data)?;
+            = zpm_formats::tar::unpack_tgz_owned(download.data.into())?;
 
         let entries
             = zpm_formats::tar::entries_from_tar(&tar_data)?
@@ -65,10 +80,10 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
                 .collect::<Vec<_>>();
 
         Ok(package_cache.bundle_entries(entries)?)
-    }).await?;
+    }).await?.into_info();
 
     let first_entry
-        = zpm_formats::zip::first_entry_from_zip(&cached_blob.data)?;
+        = zpm_formats::zip::first_entry_from_zip_file(&cached_blob.path)?;
 
     let manifest: RemoteManifest
         = JsonDocument::hydrate_from_slice(&first_entry.data)?;
@@ -76,14 +91,14 @@ pub async fn fetch_locator<'a>(context: &InstallContext<'a>, locator: &Locator,
     let resolution
         = Resolution::from_remote_manifest(locator.clone(), manifest);
 
-    let package_directory = cached_blob.info.path
+    let package_directory = cached_blob.path
         .with_join(&package_subdir);
 
     Ok(FetchResult {
         resolution: Some(resolution),
         package_data: PackageData::Zip {
-            archive_path: cached_blob.info.path,
-            checksum: cached_blob.info.checksum,
+            archive_path: cached_blob.path,
+            checksum: cached_blob.checksum,
             context_directory: package_directory.clone(),
             package_directory,
         },
@@ -1,12 +1,13 @@
 use std::collections::BTreeMap;
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
+use std::time::Instant;
 
 use git_url_parse::GitUrl;
 use regex::Regex;
 use reqwest::Url;
 use zpm_git::{GitRange, GitSource, GitTreeish};
 use zpm_primitives::AnonymousSemverRange;
-use zpm_utils::{repeat_until_ok, Path};
+use zpm_utils::{repeat_until_ok, DataType, Hash64, Path, ToHumanString};
 use zpm_utils::FromFileString;
 
 use crate::{
@@ -14,46 +15,11 @@ use crate::{
     github,
     http::HttpConfig,
     install::InstallContext,
+    project::Project,
+    report::current_report,
     script::ScriptEnvironment,
 };
 
-#[derive(Debug)]
-pub enum GitOperation {
-    Merge,
-    Rebase,
-}
-
-impl GitOperation {
-    pub fn true_theirs(&self) -> &str {
-        match self {
-            GitOperation::Merge => "--theirs",
-            GitOperation::Rebase => "--ours",
-        }
-    }
-}
-
-pub async fn detect_git_operation(p: &Path) -> Result<Option<GitOperation>, Error> {
-    let merge_head_check = ScriptEnvironment::new()?
-        .with_cwd(p.clone())
-        .run_exec("git", &["rev-parse", "-q", "--verify", "MERGE_HEAD"])
-        .await?;
-
-    if merge_head_check.success() {
-        return Ok(Some(GitOperation::Merge));
-    }
-
-    let rebase_head_check = ScriptEnvironment::new()?
-        .with_cwd(p.clone())
-        .run_exec("git", &["rev-parse", "-q", "--verify", "REBASE_HEAD"])
-        .await?;
-
-    if rebase_head_check.success() {
-        return Ok(Some(GitOperation::Rebase));
-    }
-
-    Ok(None)
-}
-
 static DIFF_PATH_NORMALIZER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^/?(.*)/?$").unwrap());
 
 pub async fn diff_folders(original: &Path, user: &Path) -> Result<String, Error> {
@@ -261,14 +227,51 @@ pub async fn clone_repository(context: &InstallContext<'_>, source: &GitSource,
     let clone_dir
         = Path::temp_dir()?;
 
+    let start
+        = Instant::now();
+
     if download_into(&source, commit, &clone_dir, &project.http_client).await?.is_some() {
         return Ok(clone_dir);
     }
 
-    git_clone_into(source, commit, &clone_dir, &project.http_client.config).await?;
+    let cache_options = GitCacheOptions {
+        cache_root: project.git_cache_path(),
+        depth: project.config.settings.git_fetch_depth.value,
+    };
+
+    // The cache is only an optimization; if it gets corrupted (or if the
+    // remote doesn't support partial clones) we still want the install to
+    // succeed, so we fall back to a regular clone.
+    if git_fetch_from_cache(source, commit, &clone_dir, &cache_options, &project.http_client.config).await.is_err() {
+        let _ = clone_dir.fs_rm();
+
+        let clone_dir
+            = Path::temp_dir()?;
+
+        if let Err(err) = git_clone_into(source, commit, &clone_dir, &project.http_client.config).await {
+            let _ = clone_dir.fs_rm();
+            return Err(err);
+        }
+
+        report_clone_time(project, source, start).await;
+
+        return Ok(clone_dir);
+    }
+
+    report_clone_time(project, source, start).await;
+
     Ok(clone_dir)
 }
 
+async fn report_clone_time(project: &Project, source: &GitSource, start: Instant) {
+    if !project.config.settings.enable_timers.value {
+        return;
+    }
+
+    current_report().await.as_ref()
+        .map(|report| report.info(format!("Fetched {} in {}ms", source.to_print_string(), DataType::Number.colorize(&start.elapsed().as_millis().to_string()))));
+}
+
 async fn download_into(source: &GitSource, commit: &str, download_dir: &Path, http_client: &std::sync::Arc<crate::http::HttpClient>) -> Result<Option<()>, Error> {
     if github::download_into(source, commit, download_dir, http_client).await?.is_some() {
         return Ok(Some(()));
@@ -277,6 +280,123 @@ async fn download_into(source: &GitSource, commit: &str, download_dir: &Path, ht
     Ok(None)
 }
 
+struct GitCacheOptions {
+    cache_root: Path,
+    depth: usize,
+}
+
+/// Git doesn't support concurrent fetches into the same repository (they
+/// race on the shallow file), so we serialize the operations per remote.
+static GIT_CACHE_LOCKS: LazyLock<std::sync::Mutex<BTreeMap<Path, Arc<tokio::sync::Mutex<()>>>>> = LazyLock::new(Default::default);
+
+fn git_cache_lock(cache_dir: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    GIT_CACHE_LOCKS.lock().unwrap()
+        .entry(cache_dir.clone())
+        .or_default()
+        .clone()
+}
+
+async fn run_git(cwd: &Path, args: &[&str]) -> Result<String, Error> {
+    ScriptEnvironment::new()?
+        .with_cwd(cwd.clone())
+        .with_env(make_git_env())
+        .run_exec("git", args)
+        .await?
+        .ok()?
+        .stdout_text()
+}
+
+async fn init_git_cache(cache_dir: &Path, clone_url: &str) -> Result<(), Error> {
+    cache_dir.fs_create_dir_all()?;
+
+    run_git(cache_dir, &["init", "--bare"]).await?;
+    run_git(cache_dir, &["remote", "add", "origin", clone_url]).await?;
+    run_git(cache_dir, &["config", "remote.origin.promisor", "true"]).await?;
+    run_git(cache_dir, &["config", "remote.origin.partialclonefilter", "blob:none"]).await?;
+    run_git(cache_dir, &["config", "core.autocrlf", "false"]).await?;
+
+    Ok(())
+}
+
+async fn is_valid_git_cache(cache_dir: &Path) -> bool {
+    let is_bare
+        = run_git(cache_dir, &["rev-parse", "--is-bare-repository"]).await;
+    let remote_url
+        = run_git(cache_dir, &["config", "--get", "remote.origin.url"]).await;
+
+    matches!(is_bare.as_deref(), Ok("true")) && remote_url.is_ok()
+}
+
+/// Fetches the commit into a bare repository shared by all the installs
+/// using the same remote, then checks it out as a worktree. Only the
+/// requested commit is fetched, and its blobs are downloaded lazily.
+async fn git_fetch_from_cache(source: &GitSource, commit: &str, clone_dir: &Path, options: &GitCacheOptions, config: &HttpConfig) -> Result<(), Error> {
+    let refs
+        = ls_remote(source, config).await?;
+
+    // Servers only accept fetching commits by hash when they're advertised;
+    // other commits require fetching the whole history.
+    let is_advertised
+        = refs.values().any(|hash| hash == commit);
+
+    repeat_until_ok(source.to_urls(), |clone_url| async move {
+        validate_repo_url(&clone_url, config)?;
+
+        let cache_dir = options.cache_root
+            .with_join_str(format!("{}.git", Hash64::from_data(clone_url.as_bytes()).short()));
+
+        let lock
+            = git_cache_lock(&cache_dir);
+        let _guard
+            = lock.lock().await;
+
+        // A previous install may have been interrupted while initializing
+        // the cache; we discard it if it's not a usable repository.
+        if cache_dir.fs_exists() && !is_valid_git_cache(&cache_dir).await {
+            cache_dir.fs_rm()?;
+        }
+
+        if !cache_dir.fs_exists() {
+            if let Err(err) = init_git_cache(&cache_dir, &clone_url).await {
+                let _ = cache_dir.fs_rm();
+                return Err(err);
+            }
+        }
+
+        let depth_arg
+            = format!("--depth={}", options.depth);
+
+        let mut fetch_args
+            = vec!["fetch", "--filter=blob:none"];
+
+        if is_advertised {
+            if options.depth > 0 {
+                fetch_args.push(&depth_arg);
+            }
+
+            fetch_args.extend(["origin", commit]);
+        } else {
+            let is_shallow
+                = run_git(&cache_dir, &["rev-parse", "--is-shallow-repository"]).await?;
+
+            if is_shallow == "true" {
+                fetch_args.push("--unshallow");
+            }
+
+            fetch_args.extend(["origin", "+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"]);
+        }
+
+        run_git(&cache_dir, &fetch_args).await?;
+
+        // Worktrees from previous installs are removed along with their
+        // temporary folders; we just need to forget about them.
+        run_git(&cache_dir, &["worktree", "prune"]).await?;
+        run_git(&cache_dir, &["worktree", "add", "--detach", "--force", clone_dir.as_str(), commit]).await?;
+
+        Ok::<(), Error>(())
+    }).await
+}
+
 async fn git_clone_into(source: &GitSource, commit: &str, clone_dir: &Path, config: &HttpConfig) -> Result<(), Error> {
     repeat_until_ok(source.to_urls(), |clone_url| async move {
         validate_repo_url(&clone_url, config)?;
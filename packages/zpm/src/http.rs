@@ -1,21 +1,42 @@
-use std::{collections::HashSet, net::SocketAddr, sync::{Arc, LazyLock, OnceLock}, time::Duration};
+use std::{collections::{BTreeMap, HashSet}, fs::OpenOptions, io::Write, net::SocketAddr, sync::{atomic::{AtomicUsize, Ordering}, Arc, LazyLock, OnceLock}, task::{Context, Poll}, time::Duration};
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use dashmap::DashMap;
 use hickory_resolver::{config::LookupIpStrategy, TokioResolver};
 use http::HeaderMap;
 use itertools::Itertools;
-use reqwest::{dns::{self, Addrs}, header::{HeaderName, HeaderValue}, Body, Client, Method, RequestBuilder, Response, Url};
-use tokio::sync::OnceCell;
+use reqwest::{dns::{self, Addrs}, header::{HeaderName, HeaderValue}, Body, Client, Method, RequestBuilder, Response, StatusCode, Url};
+use tokio::sync::{OnceCell, OwnedSemaphorePermit, Semaphore, SemaphorePermit};
 use wax::Program;
 use zpm_config::{Configuration, NetworkSettings, Setting};
-use zpm_utils::Glob;
+use zpm_utils::{Glob, Path, Secret};
 
 use crate::{
-    error::Error,
-    report::current_report,
+    error::{Error, ErrorCode},
+    http_npm_bulk::BulkPackumentBatcher,
+    http_npm_cache::PackumentCache,
+    report::{ReportCounters, TransferUnit, current_report},
 };
 
+/// Headers that can't be set through the httpHeaders settings, either
+/// because they're hop-by-hop headers managed by the client, or because
+/// they carry credentials that have dedicated settings.
+const FORBIDDEN_HTTP_HEADERS: &[&str] = &[
+    "authorization",
+    "connection",
+    "content-length",
+    "cookie",
+    "host",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
 static WARNED_HOSTNAMES: LazyLock<tokio::sync::Mutex<HashSet<String>>> = LazyLock::new(|| tokio::sync::Mutex::new(HashSet::new()));
 
 #[derive(Debug)]
@@ -28,6 +49,10 @@ pub struct HttpConfig {
     enable_network: bool,
 
     network_settings: Vec<(Glob, NetworkSettings)>,
+
+    /// The configured headers, along with the registry whose host they're
+    /// restricted to (if any). Later entries take precedence.
+    http_headers: Vec<(Option<Url>, HeaderMap)>,
 }
 
 impl HttpConfig {
@@ -46,6 +71,92 @@ impl HttpConfig {
 
         self.enable_network
     }
+
+    fn apply_http_headers(&self, url: &Url, mut builder: RequestBuilder) -> RequestBuilder {
+        for (registry_url, headers) in &self.http_headers {
+            let is_match = registry_url.as_ref().is_none_or(|registry_url| {
+                registry_url.host_str() == url.host_str() && registry_url.port_or_known_default() == url.port_or_known_default()
+            });
+
+            if is_match {
+                builder = builder.headers(headers.clone());
+            }
+        }
+
+        builder
+    }
+}
+
+/// Reads the body of the response chunk by chunk, reporting its progress to
+/// the current report (if any) so that large downloads don't look stalled.
+pub async fn read_body(mut response: Response) -> Result<Bytes, reqwest::Error> {
+    let expected_size
+        = response.content_length();
+
+    let transfer = current_report().await.as_ref()
+        .map(|report| report.start_transfer(TransferUnit::Bytes, None, expected_size));
+
+    let mut body
+        = BytesMut::with_capacity(expected_size.unwrap_or(0) as usize);
+
+    while let Some(chunk) = response.chunk().await? {
+        if let Some(transfer) = &transfer {
+            transfer.advance(chunk.len() as u64);
+        }
+
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
+/// The body of a download made through `HttpRequest::send_resumable`.
+pub struct ResumableBody {
+    pub data: Bytes,
+
+    /// Whether the start of the body came from a previous, interrupted,
+    /// attempt; if the resource changed in the meantime the data will be
+    /// corrupted, so it must be validated before being used.
+    pub resumed: bool,
+}
+
+/// Returns the offset at which the `Content-Range` header of a 206 response
+/// says its body starts.
+fn content_range_start(response: &Response) -> Option<u64> {
+    response.headers()
+        .get(http::header::CONTENT_RANGE)?
+        .to_str().ok()?
+        .strip_prefix("bytes ")?
+        .split_once('-')?
+        .0
+        .parse()
+        .ok()
+}
+
+fn to_header_map(headers: &BTreeMap<String, Setting<Secret<String>>>) -> Result<HeaderMap, Error> {
+    let mut header_map
+        = HeaderMap::new();
+
+    for (name, value) in headers {
+        let header_name
+            = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| Error::InvalidHttpHeader(name.clone()))?;
+
+        if FORBIDDEN_HTTP_HEADERS.contains(&header_name.as_str()) {
+            return Err(Error::ForbiddenHttpHeader(name.clone()));
+        }
+
+        let mut header_value
+            = HeaderValue::from_str(&value.value.value)
+                .map_err(|_| Error::InvalidHttpHeader(name.clone()))?;
+
+        // Keeps the value out of the debug output of the requests
+        header_value.set_sensitive(true);
+
+        header_map.insert(header_name, header_value);
+    }
+
+    Ok(header_map)
 }
 
 #[derive(Clone)]
@@ -89,9 +200,7 @@ impl dns::Resolve for HickoryDnsResolver {
                         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
 
                 let addrs
-                    = lookup.into_iter()
-                        .map(|ip_addr| SocketAddr::new(ip_addr, 0))
-                        .collect::<Vec<_>>();
+                    = interleave_address_families(lookup.into_iter().map(|ip_addr| SocketAddr::new(ip_addr, 0)).collect());
 
                 Ok::<_, std::io::Error>(addrs)
             }).await?;
@@ -104,6 +213,95 @@ impl dns::Resolve for HickoryDnsResolver {
     }
 }
 
+/// Orders the resolved addresses as described by RFC 8305, alternating
+/// between IPv6 and IPv4. The connector starts by trying the family of the
+/// first address and races it against the other family after a short delay
+/// (RFC 6555), so hosts with a broken IPv6 setup only pay that delay rather
+/// than waiting for the IPv6 connections to time out.
+fn interleave_address_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let (ipv6_addrs, ipv4_addrs): (Vec<_>, Vec<_>)
+        = addrs.into_iter().partition(|addr| addr.is_ipv6());
+
+    ipv6_addrs.into_iter()
+        .interleave(ipv4_addrs)
+        .collect()
+}
+
+/// Wraps the connector to count how many connections are opened; comparing
+/// it to the number of requests tells how well the pool reuses them.
+#[derive(Clone)]
+struct CountConnections<S> {
+    inner: S,
+    stats: Arc<HttpStats>,
+}
+
+impl<S, R> tower::Service<R> for CountConnections<S> where S: tower::Service<R> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.stats.connections_opened.fetch_add(1, Ordering::Relaxed);
+        self.inner.call(request)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HttpStats {
+    connections_opened: AtomicUsize,
+    requests_sent: AtomicUsize,
+    requests_in_flight: AtomicUsize,
+}
+
+impl HttpStats {
+    pub fn connections_opened(&self) -> usize {
+        self.connections_opened.load(Ordering::Relaxed)
+    }
+
+    pub fn connections_reused(&self) -> usize {
+        self.requests_sent().saturating_sub(self.connections_opened())
+    }
+
+    pub fn requests_sent(&self) -> usize {
+        self.requests_sent.load(Ordering::Relaxed)
+    }
+
+    pub fn requests_in_flight(&self) -> usize {
+        self.requests_in_flight.load(Ordering::Relaxed)
+    }
+}
+
+/// Held for as long as a request is in flight, which includes reading its
+/// response body; see `HttpClient::acquire_request_slot`. Code sending a
+/// follow-up request while still holding a response must first release its
+/// slot through `release_request_slot`, or it could wait on itself.
+#[derive(Debug)]
+struct RequestSlot {
+    _permit: OwnedSemaphorePermit,
+    stats: Arc<HttpStats>,
+    counters: Option<Arc<ReportCounters>>,
+}
+
+impl Drop for RequestSlot {
+    fn drop(&mut self) {
+        self.stats.requests_in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        if let Some(counters) = &self.counters {
+            counters.network_requests.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Releases the request slot held by the given response, which must not be
+/// read from afterwards.
+pub fn release_request_slot(response: &mut Response) {
+    response.extensions_mut().remove::<Arc<RequestSlot>>();
+}
+
 fn new_resolver() -> TokioResolver {
     let mut builder
         = TokioResolver::builder_tokio()
@@ -121,6 +319,24 @@ pub struct HttpClient {
     /// Cache for GET requests to avoid duplicate network calls for the same URL.
     /// Uses OnceCell for each URL to handle concurrent requests to the same URL.
     get_cache: DashMap<String, Arc<OnceCell<Result<Bytes, Error>>>>,
+
+    /// Groups concurrent packument requests into bulk requests when the
+    /// registry supports them.
+    pub bulk_packuments: BulkPackumentBatcher,
+
+    /// Keeps the packuments on disk so that they can be revalidated rather
+    /// than downloaded again on later installs.
+    pub packument_cache: PackumentCache,
+
+    pub stats: Arc<HttpStats>,
+
+    /// Limits how many packages can be downloaded at the same time
+    /// (networkConcurrency).
+    download_slots: Semaphore,
+
+    /// Limits how many requests can be in flight at the same time, regardless
+    /// of what they're for (networkConcurrency).
+    request_slots: Arc<Semaphore>,
 }
 
 impl std::fmt::Debug for HttpClient {
@@ -129,6 +345,9 @@ impl std::fmt::Debug for HttpClient {
             .field("config", &self.config)
             .field("client", &self.client)
             .field("get_cache", &format!("<{} entries>", self.get_cache.len()))
+            .field("bulk_packuments", &self.bulk_packuments)
+            .field("packument_cache", &self.packument_cache)
+            .field("stats", &self.stats)
             .finish()
     }
 }
@@ -145,7 +364,7 @@ pub struct HttpRequest<'a> {
 impl<'a> HttpRequest<'a> {
     pub fn new(client: &'a HttpClient, url: Url, method: Method) -> Self {
         let builder
-            = client.client.request(method.clone(), url.clone());
+            = client.config.apply_http_headers(&url, client.client.request(method.clone(), url.clone()));
 
         Self {
             builder,
@@ -175,6 +394,11 @@ impl<'a> HttpRequest<'a> {
                 .map(|s| s.to_string());
 
         loop {
+            let request_slot
+                = self.client.acquire_request_slot().await;
+
+            self.client.stats.requests_sent.fetch_add(1, Ordering::Relaxed);
+
             let mut fetch_future = Box::pin(async {
                 self.builder.try_clone()
                     .expect("builder should be clonable")
@@ -193,13 +417,13 @@ impl<'a> HttpRequest<'a> {
 
                     if should_warn {
                         current_report().await.as_ref().map(|report| {
-                            report.warn(format!("Requests to {} are taking suspiciously long...", hostname));
+                            report.warn_with_code(ErrorCode::SlowNetwork, format!("Requests to {} are taking suspiciously long...", hostname));
                         });
                     }
                 }
             };
 
-            let response = tokio::select! {
+            let mut response = tokio::select! {
                 result = &mut fetch_future => result,
                 _ = warning_future => {
                     // Warning was issued, now wait for the actual fetch to complete
@@ -216,6 +440,9 @@ impl<'a> HttpRequest<'a> {
                 if is_failure {
                     retry_count += 1;
 
+                    // Don't keep other requests waiting while backing off
+                    drop(request_slot);
+
                     let sleep_duration
                         = 2_u64.saturating_pow(retry_count as u32);
                     let bounded_sleep_duration
@@ -226,6 +453,12 @@ impl<'a> HttpRequest<'a> {
                 }
             }
 
+            // The slot is released once the response (and thus its body) is
+            // dropped, so reading the body counts against the limit as well
+            if let Ok(response) = &mut response {
+                response.extensions_mut().insert(Arc::new(request_slot));
+            }
+
             return if self.enable_status_check {
                 response?.error_for_status()
             } else {
@@ -234,6 +467,105 @@ impl<'a> HttpRequest<'a> {
         }
     }
 
+    /// Sends the request and stores the response body into `part_path` as it
+    /// arrives, so that an interrupted download can be resumed rather than
+    /// restarted: if the file already exists, only the missing bytes are
+    /// requested (through a `Range` header). Servers that don't honor the
+    /// range get the whole body downloaded again. The file is removed once
+    /// the download completes.
+    pub async fn send_resumable(self, part_path: &Path) -> Result<ResumableBody, Error> {
+        let mut attempt_count
+            = 0;
+
+        loop {
+            let existing_size = part_path.fs_metadata()
+                .map_or(0, |metadata| metadata.len());
+
+            let range
+                = (existing_size > 0).then(|| format!("bytes={}-", existing_size));
+
+            let response = self.try_clone()
+                .expect("Resumable requests should have a clonable body")
+                .enable_status_check(false)
+                .header("range", range)
+                .send().await?;
+
+            let status
+                = response.status();
+
+            let resumed
+                = existing_size > 0 && status == StatusCode::PARTIAL_CONTENT && content_range_start(&response) == Some(existing_size);
+
+            // The server didn't accept our range (for example because the
+            // resource changed); we start over from scratch
+            if existing_size > 0 && !resumed && matches!(status, StatusCode::PARTIAL_CONTENT | StatusCode::RANGE_NOT_SATISFIABLE) {
+                part_path.fs_rm_file()?;
+                continue;
+            }
+
+            let mut response
+                = response.error_for_status()?;
+
+            let mut file
+                = OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(resumed)
+                    .truncate(!resumed)
+                    .open(part_path.to_path_buf())?;
+
+            let mut body = match resumed {
+                true => BytesMut::from(part_path.fs_read()?.as_slice()),
+                false => BytesMut::new(),
+            };
+
+            let expected_size = response.content_length()
+                .map(|content_length| body.len() as u64 + content_length);
+
+            let transfer = current_report().await.as_ref()
+                .map(|report| report.start_transfer(TransferUnit::Bytes, None, expected_size));
+
+            if let Some(transfer) = &transfer {
+                transfer.advance(body.len() as u64);
+            }
+
+            if let Some(expected_size) = expected_size {
+                body.reserve((expected_size as usize).saturating_sub(body.len()));
+            }
+
+            let read_result = async {
+                while let Some(chunk) = response.chunk().await? {
+                    if let Some(transfer) = &transfer {
+                        transfer.advance(chunk.len() as u64);
+                    }
+
+                    file.write_all(&chunk)?;
+                    body.extend_from_slice(&chunk);
+                }
+
+                Ok::<_, Error>(())
+            }.await;
+
+            // What we managed to download stays in the part file, so retrying
+            // only requests the remaining bytes
+            if let Err(error) = read_result {
+                if attempt_count < self.client.config.http_retry {
+                    attempt_count += 1;
+                    continue;
+                }
+
+                return Err(error);
+            }
+
+            part_path.fs_rm_file()?;
+
+            return Ok(ResumableBody {
+                data: body.freeze(),
+                resumed,
+            });
+        }
+    }
+
     pub fn headers(&self) -> HeaderMap {
         // TODO: This is filthy
         self.builder.try_clone().unwrap().build().unwrap().headers().clone()
@@ -279,6 +611,12 @@ impl<'a> HttpRequest<'a> {
 
 impl HttpClient {
     pub fn new(config: &Configuration) -> Result<Arc<Self>, Error> {
+        let stats
+            = Arc::new(HttpStats::default());
+
+        let connection_stats
+            = stats.clone();
+
         let client = reqwest::Client::builder()
             // Connection pooling settings
             .pool_max_idle_per_host(config.settings.network_concurrency.value)
@@ -297,13 +635,38 @@ impl HttpClient {
             // Enable connection keep-alive
             .tcp_keepalive(Duration::from_secs(60))
 
-            .connector_layer(tower::limit::concurrency::ConcurrencyLimitLayer::new(config.settings.network_concurrency.value))
+            .connector_layer(tower::layer::layer_fn(move |inner| CountConnections {inner, stats: connection_stats.clone()}))
 
             .use_rustls_tls()
             .dns_resolver(Arc::new(HickoryDnsResolver::default()))
             .build()
             .map_err(|err| Error::DnsResolutionError(Arc::new(err)))?;
 
+        let mut http_headers
+            = vec![(None, to_header_map(&config.settings.http_headers)?)];
+
+        for (registry, registry_settings) in &config.settings.npm_registries {
+            if registry_settings.http_headers.is_empty() {
+                continue;
+            }
+
+            let registry_url
+                = Url::parse(registry)
+                    .map_err(|_| Error::InvalidUrl(registry.clone()))?;
+
+            http_headers.push((Some(registry_url), to_header_map(&registry_settings.http_headers)?));
+        }
+
+        let metadata_cache_path
+            = config.settings.global_folder.value
+                .with_join_str("metadata/npm");
+
+        let bulk_metadata_path
+            = config.settings.npm_bulk_metadata_path.value.clone();
+
+        let network_concurrency
+            = config.settings.network_concurrency.value.max(1);
+
         let config = HttpConfig {
             enforce_unsafe_http: config.settings.enforce_unsafe_http.value,
             http_retry: config.settings.http_retry.value,
@@ -317,15 +680,52 @@ impl HttpClient {
                 // Sort the config by key length to match on the most specific pattern.
                 .sorted_by_cached_key(|(glob, _)| -(glob.raw().len() as isize))
                 .collect(),
+
+            http_headers,
         };
 
         Ok(Arc::new(Self {
             client,
             config,
             get_cache: DashMap::new(),
+            bulk_packuments: BulkPackumentBatcher::new(bulk_metadata_path),
+            packument_cache: PackumentCache::new(metadata_cache_path),
+            stats,
+            download_slots: Semaphore::new(network_concurrency),
+            request_slots: Arc::new(Semaphore::new(network_concurrency)),
         }))
     }
 
+    /// Waits until fewer than networkConcurrency requests are in flight.
+    /// Cached responses never reach this point, so they don't count against
+    /// the limit.
+    async fn acquire_request_slot(&self) -> RequestSlot {
+        let permit = self.request_slots.clone().acquire_owned().await
+            .expect("The request semaphore is never closed");
+
+        self.stats.requests_in_flight.fetch_add(1, Ordering::Relaxed);
+
+        let counters = current_report().await.as_ref()
+            .map(|report| report.counters.clone());
+
+        if let Some(counters) = &counters {
+            counters.network_requests.fetch_add(1, Ordering::Relaxed);
+        }
+
+        RequestSlot {
+            _permit: permit,
+            stats: self.stats.clone(),
+            counters,
+        }
+    }
+
+    /// Waits until a download slot is available; fetchers hold the returned
+    /// permit while retrieving a package from the network.
+    pub async fn acquire_download_slot(&self) -> SemaphorePermit<'_> {
+        self.download_slots.acquire().await
+            .expect("The download semaphore is never closed")
+    }
+
     pub fn request(&self, url: impl AsRef<str>, method: Method) -> Result<HttpRequest<'_>, Error> {
         let url
             = url.as_ref();
@@ -367,12 +767,7 @@ impl HttpClient {
         let url_str
             = url.as_ref().to_string();
 
-        let cell = self.get_cache
-            .entry(url_str.clone())
-            .or_insert_with(|| Arc::new(OnceCell::new()))
-            .clone();
-
-        let result = cell.get_or_init(|| async {
+        self.cached_get_with(&url_str, || async {
             let request
                 = self.get(&url_str)?;
 
@@ -380,14 +775,44 @@ impl HttpClient {
                 = request.send().await?;
 
             let bytes
-                = result.bytes().await?;
+                = read_body(result).await?;
 
             Ok(bytes)
-        }).await;
+        }).await
+    }
+
+    /// Same as `cached_get`, but lets the caller decide how the response is
+    /// obtained when the URL hasn't been fetched yet.
+    pub async fn cached_get_with<F, Fut>(&self, url: impl AsRef<str>, f: F) -> Result<Bytes, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<Bytes, Error>>,
+    {
+        let cell = self.get_cache
+            .entry(url.as_ref().to_string())
+            .or_insert_with(|| Arc::new(OnceCell::new()))
+            .clone();
+
+        let result
+            = cell.get_or_init(f).await;
 
         result.clone()
     }
 
+    /// Returns whether a cached GET request has already been issued for the
+    /// given URL (even if it's still in flight).
+    pub fn has_cached_get(&self, url: impl AsRef<str>) -> bool {
+        self.get_cache.contains_key(url.as_ref())
+    }
+
+    /// Stores a response obtained by other means (for instance a bulk
+    /// request) so that later cached GET requests to the URL reuse it.
+    pub fn seed_cached_get(&self, url: impl Into<String>, bytes: Bytes) {
+        self.get_cache
+            .entry(url.into())
+            .or_insert_with(|| Arc::new(OnceCell::new_with(Some(Ok(bytes)))));
+    }
+
     pub fn post(&self, url: impl AsRef<str>) -> Result<HttpRequest<'_>, Error> {
         self.request(url, Method::POST)
     }
@@ -2,7 +2,7 @@ use std::sync::{Arc, LazyLock};
 
 use bytes::Bytes;
 use regex::{Captures, Regex};
-use reqwest::Response;
+use reqwest::{Response, StatusCode};
 use serde::Deserialize;
 use zpm_config::Configuration;
 use zpm_parsers::JsonDocument;
@@ -11,7 +11,8 @@ use zpm_utils::DataType;
 
 use crate::{
     error::Error,
-    http::{HttpClient, HttpRequest},
+    http::{HttpClient, HttpRequest, read_body, release_request_slot},
+    http_npm_cache::{CachedPackument, PackumentValidators},
     report::{current_report, PromptType},
 };
 
@@ -329,14 +330,14 @@ pub async fn get(params: &NpmHttpParams<'_>) -> Result<Bytes, Error> {
 
     let bytes = match params.authorization {
         Some(authorization) => {
-            let response = params.http_client.get(&url)?
+            let mut response = params.http_client.get(&url)?
                 .header("authorization", Some(authorization))
                 .enable_status_check(false)
                 .send().await?;
 
-            handle_invalid_authentication_error(params, &response).await?;
+            handle_invalid_authentication_error(params, &mut response).await?;
 
-            response.error_for_status()?.bytes().await?
+            read_body(response.error_for_status()?).await?
         },
 
         None => {
@@ -347,6 +348,127 @@ pub async fn get(params: &NpmHttpParams<'_>) -> Result<Bytes, Error> {
     Ok(bytes)
 }
 
+/// The formats in which registries can serve packuments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackumentFormat {
+    /// Only contains the fields required to install the package versions
+    /// (dependencies, `dist`, `engines`, ...); much smaller to download and
+    /// parse. Registries not supporting it return the full packument instead.
+    Abbreviated,
+
+    /// Also contains the release times, scripts, readme, etc.
+    Full,
+}
+
+impl PackumentFormat {
+    fn accept_header(&self) -> Option<&'static str> {
+        match self {
+            PackumentFormat::Abbreviated => Some("application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*"),
+            PackumentFormat::Full => None,
+        }
+    }
+
+    fn cache_key(&self, url: &str) -> String {
+        match self {
+            PackumentFormat::Abbreviated => format!("{}#abbreviated", url),
+            PackumentFormat::Full => url.to_string(),
+        }
+    }
+}
+
+/// Fetches the packument of the given package.
+///
+/// Packuments are kept in an on-disk cache, and cached entries are
+/// revalidated through a conditional request rather than downloaded again.
+/// Setting `use_metadata_cache` to false ignores the cached entry (the fresh
+/// packument still gets stored in the cache).
+///
+/// Unauthenticated requests for packuments missing from the cache may be
+/// served from a bulk request shared with other concurrent resolutions,
+/// falling back to a regular request when that isn't possible.
+pub async fn get_packument(params: &NpmHttpParams<'_>, ident: &Ident, format: PackumentFormat, use_metadata_cache: bool) -> Result<Bytes, Error> {
+    let url
+        = format!("{}{}", params.registry, params.path);
+
+    // Full packuments are cached in memory under their plain URL so that
+    // they can be shared with the other commands querying the registry.
+    let cache_key
+        = format.cache_key(&url);
+
+    // Packuments retrieved with credentials may be private, so they're never
+    // written to the on-disk cache, which is shared by all projects.
+    let cached_packument = if use_metadata_cache && params.authorization.is_none() {
+        params.http_client.packument_cache.get(&cache_key)
+    } else {
+        None
+    };
+
+    if cached_packument.is_none() && params.authorization.is_none() && !params.http_client.has_cached_get(&cache_key) {
+        let bulk_result
+            = params.http_client.bulk_packuments.get(params.http_client, params.registry, ident).await;
+
+        if let Some(bytes) = bulk_result {
+            return Ok(bytes);
+        }
+    }
+
+    match params.authorization {
+        Some(_) => {
+            fetch_packument(params, format, &cache_key, cached_packument).await
+        },
+
+        None => {
+            params.http_client.cached_get_with(&cache_key, || fetch_packument(params, format, &cache_key, cached_packument)).await
+        },
+    }
+}
+
+async fn fetch_packument(params: &NpmHttpParams<'_>, format: PackumentFormat, cache_key: &str, cached_packument: Option<CachedPackument>) -> Result<Bytes, Error> {
+    let url
+        = format!("{}{}", params.registry, params.path);
+
+    let validators = cached_packument.as_ref()
+        .map(|cached_packument| cached_packument.validators.clone())
+        .unwrap_or_default();
+
+    let mut response = params.http_client.get(&url)?
+        .header("accept", format.accept_header())
+        .header("authorization", params.authorization)
+        .header("if-none-match", validators.etag.as_deref())
+        .header("if-modified-since", validators.last_modified.as_deref())
+        .enable_status_check(false)
+        .send().await?;
+
+    if let Some(cached_packument) = cached_packument {
+        if response.status() == StatusCode::NOT_MODIFIED {
+            params.http_client.packument_cache.record_revalidation();
+            return Ok(cached_packument.data);
+        }
+    }
+
+    handle_invalid_authentication_error(params, &mut response).await?;
+
+    let response
+        = response.error_for_status()?;
+
+    let validators
+        = PackumentValidators::from_headers(response.headers());
+
+    let data
+        = response.bytes().await?;
+
+    // The cache is only an optimization; failing to write into it shouldn't
+    // prevent the install from proceeding.
+    if !validators.is_empty() && params.authorization.is_none() {
+        let _ = params.http_client.packument_cache.set(cache_key, &CachedPackument {
+            validators,
+            data: data.clone(),
+        });
+    }
+
+    Ok(data)
+}
+
 pub async fn post(params: &NpmHttpParams<'_>, body: String) -> Result<Response, Error> {
     let url
         = format!("{}{}", params.registry, params.path);
@@ -366,6 +488,8 @@ pub async fn post(params: &NpmHttpParams<'_>, body: String) -> Result<Response,
             .await?;
 
     if is_otp_error(&response) {
+        release_request_slot(&mut response);
+
         let otp
             = ask_for_otp(params, &response).await?;
 
@@ -373,7 +497,7 @@ pub async fn post(params: &NpmHttpParams<'_>, body: String) -> Result<Response,
         response = request.send().await?;
     }
 
-    handle_invalid_authentication_error(params, &response).await?;
+    handle_invalid_authentication_error(params, &mut response).await?;
 
     Ok(response.error_for_status()?)
 }
@@ -397,6 +521,8 @@ pub async fn put(params: &NpmHttpParams<'_>, body: String) -> Result<Response, E
             .await?;
 
     if is_otp_error(&response) {
+        release_request_slot(&mut response);
+
         let otp
             = ask_for_otp(params, &response).await?;
 
@@ -404,7 +530,7 @@ pub async fn put(params: &NpmHttpParams<'_>, body: String) -> Result<Response, E
         response = request.send().await?;
     }
 
-    handle_invalid_authentication_error(params, &response).await?;
+    handle_invalid_authentication_error(params, &mut response).await?;
 
     if let Err(error) = response.error_for_status_ref() {
         let body
@@ -430,7 +556,7 @@ fn inject_otp_headers(request: HttpRequest<'_>, otp: String) -> HttpRequest<'_>
     request.header("npm-otp", Some(otp))
 }
 
-async fn handle_invalid_authentication_error(params: &NpmHttpParams<'_>, response: &Response) -> Result<(), Error> {
+async fn handle_invalid_authentication_error(params: &NpmHttpParams<'_>, response: &mut Response) -> Result<(), Error> {
     if is_otp_error(response) {
         return Err(Error::AuthenticationError(
             "Invalid OTP token".to_string()
@@ -438,6 +564,8 @@ async fn handle_invalid_authentication_error(params: &NpmHttpParams<'_>, respons
     }
 
     if response.status().as_u16() == 401 {
+        release_request_slot(response);
+
         let attempted_as = match whoami(params.http_client, params.registry, params.authorization).await {
             Ok(Some(username)) => username,
             Ok(None) => "an anonymous user".to_string(),
@@ -0,0 +1,170 @@
+use std::{collections::BTreeMap, sync::atomic::{AtomicUsize, Ordering}, time::Duration};
+
+use bytes::Bytes;
+use dashmap::DashMap;
+use tokio::sync::{oneshot, Mutex};
+use zpm_parsers::JsonDocument;
+use zpm_primitives::Ident;
+
+use crate::http::HttpClient;
+
+/// How long the first request targeting a registry waits for concurrent
+/// resolutions to join its batch before sending it.
+const AGGREGATION_WINDOW: Duration = Duration::from_millis(5);
+
+/// Maximum number of packuments requested in a single bulk request.
+const MAX_BATCH_SIZE: usize = 100;
+
+type PendingRequest = (Ident, oneshot::Sender<Option<Bytes>>);
+
+/// Groups the packument requests issued concurrently against the same
+/// registry into bulk requests, when `npmBulkMetadataPath` is set.
+///
+/// Requests that can't be served by a bulk response (disabled batching,
+/// unsupported registry, missing entry, per-package error) resolve to
+/// `None`, in which case the caller is expected to fall back to an
+/// individual request; this way the errors are always reported against the
+/// package that caused them.
+#[derive(Debug, Default)]
+pub struct BulkPackumentBatcher {
+    bulk_path: Option<String>,
+    pending: Mutex<BTreeMap<String, Vec<PendingRequest>>>,
+    support: DashMap<String, bool>,
+    saved_requests: AtomicUsize,
+}
+
+impl BulkPackumentBatcher {
+    pub fn new(bulk_path: Option<String>) -> Self {
+        Self {
+            bulk_path,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the number of requests that have been avoided by batching.
+    pub fn saved_requests(&self) -> usize {
+        self.saved_requests.load(Ordering::Relaxed)
+    }
+
+    pub async fn get(&self, http_client: &HttpClient, registry: &str, ident: &Ident) -> Option<Bytes> {
+        if self.bulk_path.is_none() || self.support.get(registry).is_some_and(|supported| !*supported) {
+            return None;
+        }
+
+        let (tx, rx)
+            = oneshot::channel();
+
+        let is_leader = {
+            let mut pending
+                = self.pending.lock().await;
+
+            let queue
+                = pending.entry(registry.to_string()).or_default();
+
+            queue.push((ident.clone(), tx));
+            queue.len() == 1
+        };
+
+        if is_leader {
+            tokio::time::sleep(AGGREGATION_WINDOW).await;
+
+            let queue = self.pending.lock().await
+                .remove(registry)
+                .unwrap_or_default();
+
+            let mut batches
+                = Vec::new();
+
+            let mut queue
+                = queue.into_iter().peekable();
+
+            while queue.peek().is_some() {
+                batches.push(queue.by_ref().take(MAX_BATCH_SIZE).collect::<Vec<_>>());
+            }
+
+            futures::future::join_all(batches.into_iter().map(|batch| {
+                self.send_batch(http_client, registry, batch)
+            })).await;
+        }
+
+        rx.await.ok().flatten()
+    }
+
+    async fn send_batch(&self, http_client: &HttpClient, registry: &str, batch: Vec<PendingRequest>) {
+        // A single package doesn't benefit from the bulk endpoint; the
+        // caller will send a regular request instead.
+        if batch.len() < 2 {
+            return;
+        }
+
+        let mut packuments
+            = self.fetch_packuments(http_client, registry, &batch).await
+                .unwrap_or_default();
+
+        let mut fetched_count
+            = 0;
+
+        for (ident, tx) in batch {
+            let packument
+                = packuments.remove(ident.as_str());
+
+            if let Some(packument) = &packument {
+                let url
+                    = format!("{}{}", registry, crate::npm::registry_url_for_all_versions(&ident));
+
+                http_client.seed_cached_get(url, packument.clone());
+                fetched_count += 1;
+            }
+
+            let _ = tx.send(packument);
+        }
+
+        if fetched_count > 1 {
+            self.saved_requests.fetch_add(fetched_count - 1, Ordering::Relaxed);
+        }
+    }
+
+    async fn fetch_packuments(&self, http_client: &HttpClient, registry: &str, batch: &[PendingRequest]) -> Option<BTreeMap<String, Bytes>> {
+        let bulk_path
+            = self.bulk_path.as_ref()?;
+
+        let names = batch.iter()
+            .map(|(ident, _)| ident.as_str())
+            .collect::<Vec<_>>();
+
+        let body
+            = JsonDocument::to_string(&serde_json::json!({"packages": names})).ok()?;
+
+        let response
+            = http_client.post(format!("{}{}", registry, bulk_path)).ok()?
+                .enable_status_check(false)
+                .header("content-type", Some("application/json"))
+                .body(body)
+                .send().await.ok()?;
+
+        // Any error (including authentication and server errors) disables
+        // the bulk requests for this registry; the packages will be fetched
+        // individually, which reports the errors against each of them.
+        if !response.status().is_success() {
+            self.support.insert(registry.to_string(), false);
+            return None;
+        }
+
+        let bytes
+            = response.bytes().await.ok()?;
+
+        let entries: BTreeMap<String, serde_json::Value>
+            = JsonDocument::hydrate_from_slice(&bytes[..]).ok()?;
+
+        self.support.insert(registry.to_string(), true);
+
+        // Entries reporting an error are left out so that the affected
+        // packages get fetched (and fail) individually.
+        let packuments = entries.into_iter()
+            .filter(|(_, packument)| packument.get("error").is_none())
+            .filter_map(|(name, packument)| Some((name, Bytes::from(JsonDocument::to_string(&packument).ok()?))))
+            .collect();
+
+        Some(packuments)
+    }
+}
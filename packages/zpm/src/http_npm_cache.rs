@@ -0,0 +1,189 @@
+use std::{sync::atomic::{AtomicUsize, Ordering}, time::{Duration, SystemTime}};
+
+use bytes::Bytes;
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use zpm_parsers::JsonDocument;
+use zpm_utils::{Hash64, Path, ToFileString};
+
+use crate::error::Error;
+
+/// Maximum size of the metadata cache; once exceeded, the least recently
+/// used entries get evicted.
+const MAX_CACHE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Listing the whole cache isn't free, so we only check its size once in a
+/// while rather than after every install.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The headers a registry may send to let clients revalidate a packument
+/// through a conditional request.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackumentValidators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl PackumentValidators {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let get_header = |name: &str| headers.get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        Self {
+            etag: get_header("etag"),
+            last_modified: get_header("last-modified"),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedPackument {
+    pub validators: PackumentValidators,
+    pub data: Bytes,
+}
+
+/// On-disk cache of the packuments retrieved from the registries, keyed by
+/// their URL (and format, as registries may serve the same packument in
+/// either its full or abbreviated form). Each entry keeps the validators
+/// the registry sent along with the packument, so that later requests can
+/// be made conditional and answered by an empty 304 response when the
+/// package didn't change.
+///
+/// Entries are stored as a single line of JSON holding the validators,
+/// followed by the raw packument. Their modification time is bumped each
+/// time they're read, which is what `prune` relies on to evict the least
+/// recently used ones.
+#[derive(Debug)]
+pub struct PackumentCache {
+    cache_path: Path,
+    revalidated_requests: AtomicUsize,
+}
+
+impl PackumentCache {
+    pub fn new(cache_path: Path) -> Self {
+        Self {
+            cache_path,
+            revalidated_requests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of packuments that have been served from the
+    /// cache after the registry confirmed they were still up-to-date.
+    pub fn revalidated_requests(&self) -> usize {
+        self.revalidated_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn record_revalidation(&self) {
+        self.revalidated_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn entry_path(&self, key: &str) -> Path {
+        self.cache_path
+            .with_join_str(format!("{}.dat", Hash64::from_data(key).to_file_string()))
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedPackument> {
+        let entry_path
+            = self.entry_path(key);
+
+        let content
+            = Bytes::from(entry_path.fs_read().ok()?);
+
+        let separator
+            = content.iter().position(|byte| *byte == b'\n')?;
+
+        let validators: PackumentValidators
+            = JsonDocument::hydrate_from_slice(&content[..separator]).ok()?;
+
+        if validators.is_empty() {
+            return None;
+        }
+
+        let _ = entry_path
+            .fs_set_modified(SystemTime::now());
+
+        Some(CachedPackument {
+            validators,
+            data: content.slice(separator + 1..),
+        })
+    }
+
+    pub fn set(&self, key: &str, packument: &CachedPackument) -> Result<(), Error> {
+        let mut content
+            = JsonDocument::to_string(&packument.validators)?.into_bytes();
+
+        content.push(b'\n');
+        content.extend_from_slice(&packument.data);
+
+        self.cache_path
+            .fs_create_dir_all()?;
+
+        self.entry_path(key)
+            .fs_write_atomic(content)?;
+
+        Ok(())
+    }
+
+    /// Evicts the least recently used entries until the cache fits within
+    /// its size limit. Does nothing if the cache was already checked within
+    /// the last `PRUNE_INTERVAL`.
+    pub fn prune(&self) -> Result<(), Error> {
+        let marker_path = self.cache_path
+            .with_join_str(".last-prune");
+
+        let last_prune = marker_path.fs_metadata().ok()
+            .and_then(|metadata| metadata.modified().ok());
+
+        let is_recent = last_prune
+            .and_then(|last_prune| SystemTime::now().duration_since(last_prune).ok())
+            .is_some_and(|elapsed| elapsed < PRUNE_INTERVAL);
+
+        if is_recent {
+            return Ok(());
+        }
+
+        let Ok(dir_entries) = self.cache_path.fs_read_dir() else {
+            return Ok(());
+        };
+
+        marker_path
+            .fs_write(b"")?;
+
+        let mut entries = dir_entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.metadata().ok().map(|metadata| (entry.path(), metadata)))
+            .filter(|(_, metadata)| metadata.is_file())
+            .map(|(path, metadata)| (path, metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+            .collect::<Vec<_>>();
+
+        let mut total_size
+            = entries.iter().map(|(_, size, _)| size).sum::<u64>();
+
+        if total_size <= MAX_CACHE_SIZE {
+            return Ok(());
+        }
+
+        entries.sort_by_key(|(_, _, modified)| *modified);
+
+        for (path, size, _) in entries {
+            if total_size <= MAX_CACHE_SIZE {
+                break;
+            }
+
+            if std::fs::remove_file(&path).is_ok() {
+                total_size -= size;
+            }
+        }
+
+        Ok(())
+    }
+}
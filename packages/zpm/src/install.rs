@@ -1,10 +1,11 @@
-use std::{collections::{BTreeMap, BTreeSet}, hash::Hash, marker::PhantomData, sync::LazyLock};
+use std::{borrow::Cow, collections::{BTreeMap, BTreeSet}, future::Future, hash::Hash, marker::PhantomData, sync::{Arc, LazyLock, atomic::{AtomicU64, Ordering}}, time::Duration};
 
 use chrono::{DateTime, Utc};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use zpm_config::PackageExtension;
+use zpm_config::{PackageExtension, RefreshTagRanges};
+use zpm_formats::{CompressionAlgorithm, Entry, iter_ext::IterExt, zip::ZipWriter, zip_iter::ZipFileIterator};
 use zpm_primitives::{Descriptor, GitRange, Ident, Locator, PatchRange, PeerRange, Range, Reference, RegistrySemverRange, RegistryTagRange, SemverDescriptor, SemverPeerRange, WorkspaceIdentRange};
-use zpm_utils::{Hash64, IoResultExt, Path, System, ToHumanString, UrlEncoded};
+use zpm_utils::{DataType, Hash64, Hash64Writer, IoResultExt, Path, System, ToHumanString, UrlEncoded};
 use rkyv::Archive;
 use serde::{Deserialize, Serialize};
 use zpm_utils::{FromFileString, ToFileString};
@@ -18,11 +19,14 @@ use crate::{
     fetchers::{PackageData, SyncFetchAttempt, fetch_locator, patch::has_builtin_patch, try_fetch_locator_sync},
     graph::{GraphCache, GraphIn, GraphOut, GraphTasks},
     linker,
+    license_policy::check_license_policy,
     lockfile::{Lockfile, LockfileEntry, LockfileMetadata},
+    manifest::resolutions::ResolutionSelector,
     primitives_exts::{InnerDependencyKind, RangeExt},
     project::{InstallMode, Project},
-    report::{ReportContext, async_section, current_report, with_context_result},
-    resolvers::{Resolution, SyncResolutionAttempt, catalog::lookup_catalog_entry, resolve_descriptor, resolve_locator, try_resolve_descriptor_sync, validate_resolution}, tree_resolver::{ResolutionTree, TreeResolver},
+    report::{ReportContext, async_section, current_report, with_context_result, with_transfer_tracking},
+    resolvers::{Resolution, SyncResolutionAttempt, catalog::lookup_catalog_entry, resolve_descriptor, resolve_locator, try_resolve_descriptor_sync, validate_resolution}, tree_resolver::{PathOverride, ResolutionTree, TreeResolver},
+    yarn_track_time,
 };
 
 #[derive(Clone)]
@@ -30,10 +34,12 @@ pub struct InstallContext<'a> {
     pub package_cache: Option<&'a CompositeCache>,
     pub project: Option<&'a Project>,
     pub systems: Option<&'a Vec<System>>,
+    pub check_cache: bool,
     pub check_checksums: bool,
     pub check_resolutions: bool,
     pub prune_dev_dependencies: bool,
     pub enforced_resolutions: BTreeMap<Descriptor, Locator>,
+    pub refresh_checksums: bool,
     pub refresh_lockfile: bool,
     pub install_time: DateTime<Utc>,
     pub mode: Option<InstallMode>,
@@ -45,10 +51,12 @@ impl<'a> Default for InstallContext<'a> {
             package_cache: None,
             project: None,
             systems: None,
+            check_cache: false,
             check_checksums: false,
             check_resolutions: false,
             prune_dev_dependencies: false,
             enforced_resolutions: BTreeMap::new(),
+            refresh_checksums: false,
             refresh_lockfile: false,
             install_time: Utc::now(),
             mode: None,
@@ -67,6 +75,11 @@ impl<'a> InstallContext<'a> {
         self
     }
 
+    pub fn set_check_cache(mut self, check_cache: bool) -> Self {
+        self.check_cache = check_cache;
+        self
+    }
+
     pub fn set_check_checksums(mut self, check_checksums: bool) -> Self {
         self.check_checksums = check_checksums;
         self
@@ -87,6 +100,11 @@ impl<'a> InstallContext<'a> {
         self
     }
 
+    pub fn set_refresh_checksums(mut self, refresh_checksums: bool) -> Self {
+        self.refresh_checksums = refresh_checksums;
+        self
+    }
+
     pub fn set_refresh_lockfile(mut self, refresh_lockfile: bool) -> Self {
         self.refresh_lockfile = refresh_lockfile;
         self
@@ -300,6 +318,21 @@ enum InstallOp<'a> {
     },
 }
 
+/// How long a single install task (resolving or fetching a package) may run
+/// before being aborted.
+const TASK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Runs the task with the install timeout. Should it expire, the error
+/// mentions how many bytes the task had transferred, which tells a stalled
+/// download apart from a merely slow one.
+async fn with_task_timeout<F: Future>(f: F) -> Result<F::Output, Error> {
+    let transferred_bytes
+        = Arc::new(AtomicU64::new(0));
+
+    with_transfer_tracking(transferred_bytes.clone(), tokio::time::timeout(TASK_TIMEOUT, f)).await
+        .map_err(|_| Error::TaskTimeoutAfterTransfer(transferred_bytes.load(Ordering::Relaxed)))
+}
+
 impl<'a> GraphIn<'a, InstallContext<'a>, InstallOpResult, Error> for InstallOp<'a> {
     fn graph_dependencies(&self, ctx: &InstallContext<'a>, resolved_dependencies: &[&InstallOpResult]) -> Vec<Self> {
         let mut dependencies = vec![];
@@ -379,7 +412,6 @@ impl<'a> GraphIn<'a, InstallContext<'a>, InstallOpResult, Error> for InstallOp<'
     }
 
     async fn graph_run(self, context: InstallContext<'a>, dependencies: Vec<InstallOpResult>) -> Result<InstallOpResult, Error> {
-        let timeout = std::time::Duration::from_secs(600);
         match self {
             InstallOp::Phantom(_) =>
                 unreachable!("PhantomData should never be instantiated"),
@@ -390,10 +422,11 @@ impl<'a> GraphIn<'a, InstallContext<'a>, InstallOpResult, Error> for InstallOp<'
                 });
 
                 with_context_result(ReportContext::Descriptor(descriptor.clone()), async {
-                    tokio::time::timeout(
-                        timeout,
-                        validate_resolution(context.clone(), descriptor.clone(), locator.clone(), dependencies)
-                    ).await.map_err(|_| Error::TaskTimeout)??;
+                    yarn_track_time!("validate_resolution", {
+                        with_task_timeout(
+                            validate_resolution(context.clone(), descriptor.clone(), locator.clone(), dependencies)
+                        ).await??;
+                    });
 
                     Ok(InstallOpResult::Validated)
                 }).await
@@ -404,36 +437,51 @@ impl<'a> GraphIn<'a, InstallContext<'a>, InstallOpResult, Error> for InstallOp<'
                     report.counters.resolution_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                 });
 
-                with_context_result(ReportContext::Locator(locator.clone()), async {
-                    let future = tokio::time::timeout(
-                        timeout,
-                        resolve_locator(context.clone(), locator.clone(), dependencies)
-                    ).await.map_err(|_| Error::TaskTimeout)?;
+                let result = with_context_result(ReportContext::Locator(locator.clone()), async {
+                    let future = yarn_track_time!("resolve_locator", {
+                        with_task_timeout(
+                            resolve_locator(context.clone(), locator.clone(), dependencies)
+                        ).await?
+                    });
 
                     Ok(InstallOpResult::Resolved(future?))
-                }).await
+                }).await;
+
+                report_resolution(&result).await;
+
+                result
             },
 
             InstallOp::Resolve {descriptor} => {
-                if !descriptor.range.details().transient_resolution {
+                let is_transient_resolution
+                    = descriptor.range.details().transient_resolution;
+
+                if !is_transient_resolution {
                     current_report().await.as_ref().map(|report| {
                         report.counters.resolution_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                     });
                 }
 
-                with_context_result(ReportContext::Descriptor(descriptor.clone()), async {
+                let result = with_context_result(ReportContext::Descriptor(descriptor.clone()), async {
                     let dependencies = match try_resolve_descriptor_sync(context.clone(), descriptor.clone(), dependencies)? {
                         SyncResolutionAttempt::Success(result) => return Ok(InstallOpResult::Resolved(result)),
                         SyncResolutionAttempt::Failure(dependencies) => dependencies,
                     };
 
-                    let future = tokio::time::timeout(
-                        timeout,
-                        resolve_descriptor(context.clone(), descriptor.clone(), dependencies)
-                    ).await.map_err(|_| Error::TaskTimeout)?;
+                    let future = yarn_track_time!("resolve_descriptor", {
+                        with_task_timeout(
+                            resolve_descriptor(context.clone(), descriptor.clone(), dependencies)
+                        ).await?
+                    });
 
                     Ok(InstallOpResult::Resolved(future?))
-                }).await
+                }).await;
+
+                if !is_transient_resolution {
+                    report_resolution(&result).await;
+                }
+
+                result
             },
 
             InstallOp::Fetch {locator, is_mock_request} => {
@@ -443,10 +491,11 @@ impl<'a> GraphIn<'a, InstallContext<'a>, InstallOpResult, Error> for InstallOp<'
                         SyncFetchAttempt::Failure(dependencies) => dependencies,
                     };
 
-                    let future = tokio::time::timeout(
-                        timeout,
-                        fetch_locator(context.clone(), &locator.clone(), is_mock_request, dependencies)
-                    ).await.map_err(|_| Error::TaskTimeout)?;
+                    let future = yarn_track_time!("fetch_locator", {
+                        with_task_timeout(
+                            fetch_locator(context.clone(), &locator.clone(), is_mock_request, dependencies)
+                        ).await?
+                    });
 
                     if is_mock_request {
                         if let Ok(result) = future.as_ref() {
@@ -466,6 +515,14 @@ impl<'a> GraphIn<'a, InstallContext<'a>, InstallOpResult, Error> for InstallOp<'
     }
 }
 
+async fn report_resolution(result: &Result<InstallOpResult, Error>) {
+    if let Ok(InstallOpResult::Resolved(ResolutionResult {resolution, ..})) = result {
+        current_report().await.as_ref().map(|report| {
+            report.resolution(resolution.locator.clone());
+        });
+    }
+}
+
 struct InstallCache {
     pub lockfile: Lockfile,
 }
@@ -488,6 +545,18 @@ impl<'a> GraphCache<InstallContext<'a>, InstallOp<'a>, InstallOpResult, Error> f
                 return Ok(None);
             }
 
+            // Tag ranges are pinned to their locked version by default; in
+            // follow mode, refreshing the lockfile re-resolves them to the
+            // version the tag currently points to.
+            if ctx.refresh_lockfile && matches!(descriptor.range, Range::RegistryTag(_)) {
+                let follow_tags = ctx.project
+                    .is_some_and(|project| project.config.settings.refresh_tag_ranges.value == RefreshTagRanges::Follow);
+
+                if follow_tags && !ctx.enforced_resolutions.contains_key(descriptor) {
+                    return Ok(None);
+                }
+            }
+
             let enforced_resolution
                 = ctx.enforced_resolutions.get(descriptor);
 
@@ -517,11 +586,17 @@ impl<'a> GraphCache<InstallContext<'a>, InstallOp<'a>, InstallOpResult, Error> f
     }
 }
 
+/// Bumped whenever the data stored in the install state changes in a way
+/// that requires it to be recomputed (for instance new content flags), so
+/// that states written by older versions get discarded.
+pub const INSTALL_STATE_VERSION: u32 = 2;
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[rkyv(serialize_bounds(__S: rkyv::ser::Writer + rkyv::ser::Allocator + rkyv::ser::Sharing, <__S as rkyv::rancor::Fallible>::Error: rkyv::rancor::Source))]
 #[rkyv(deserialize_bounds(__D: rkyv::de::Pooling, <__D as rkyv::rancor::Fallible>::Error: rkyv::rancor::Source))]
 #[rkyv(bytecheck(bounds(__C: rkyv::validation::ArchiveContext + rkyv::validation::SharedContext, <__C as rkyv::rancor::Fallible>::Error: rkyv::rancor::Source)))]
 pub struct InstallState {
+    pub version: u32,
     pub last_installed_at: u128,
     pub content_flags: BTreeMap<Locator, ContentFlags>,
     pub resolution_tree: ResolutionTree,
@@ -532,8 +607,13 @@ pub struct InstallState {
     pub optional_packages: BTreeSet<Locator>,
     pub disabled_locators: BTreeSet<Locator>,
     pub conditional_locators: BTreeSet<Locator>,
+    pub skipped_builds: BTreeSet<Locator>,
+    pub lockfile_hash: Option<Hash64>,
 }
 
+/// The result of `InstallManager::resolve_and_fetch`. The lockfile, install
+/// state, and resolution tree can be inspected as-is; call `link_and_build`
+/// to actually write them to disk and install the packages.
 #[derive(Clone, Default)]
 pub struct Install {
     pub lockfile: Lockfile,
@@ -541,6 +621,8 @@ pub struct Install {
     pub package_data: BTreeMap<Locator, PackageData>,
     pub install_state: InstallState,
     pub roots: BTreeSet<Descriptor>,
+    pub forced_builds: BTreeSet<Ident>,
+    pub build_selection: Option<BTreeSet<Ident>>,
     pub skip_build: bool,
     pub skip_link_step: bool,
     pub skip_lockfile_update: bool,
@@ -552,22 +634,47 @@ pub struct InstallResult {
     pub package_data: BTreeMap<Locator, PackageData>,
 }
 
+/// Packages whose `os`, `cpu`, or `libc` fields don't match any of the
+/// supported architectures are neither fetched nor linked; we let the user
+/// know which ones so that missing native bindings don't come as a surprise.
+async fn report_disabled_packages(project: &Project, install_state: &InstallState) {
+    if install_state.disabled_locators.is_empty() {
+        return;
+    }
+
+    let systems = project.config.settings.supported_architectures.to_systems().iter()
+        .map(|system| system.to_file_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    current_report().await.as_ref().map(|report| {
+        for locator in &install_state.disabled_locators {
+            report.info(format!("{} isn't compatible with {} and has been skipped", locator.to_print_string(), DataType::Code.colorize(&systems)));
+        }
+    });
+}
+
 impl Install {
     pub async fn link_and_build(mut self, project: &mut Project) -> Result<InstallResult, Error> {
-        if self.skip_link_step {
-            project.attach_install_state(self.install_state)?;
+        check_license_policy(project, &self.install_state).await?;
+        report_disabled_packages(project, &self.install_state).await;
 
+        if self.skip_link_step {
             if !self.skip_lockfile_update {
                 project.write_lockfile(&self.lockfile)?;
             }
+
+            self.install_state.lockfile_hash = project.lockfile_hash()?;
+            project.attach_install_state(self.install_state)?;
         } else {
             self.install_state.last_installed_at = project.last_modified_at.as_nanos();
 
             let link_future
                 = linker::link_project(project, &mut self);
 
-            let link_result
-                = async_section("Linking the project", link_future).await?;
+            let link_result = yarn_track_time!("link_project", {
+                async_section("Linking the project", link_future).await?
+            });
 
             for (location, locator) in &link_result.packages_by_location {
                 self.install_state.locations_by_package.insert(locator.clone(), location.clone());
@@ -576,18 +683,37 @@ impl Install {
             self.install_state.packages_by_location
                 = link_result.packages_by_location;
 
-            project.attach_install_state(self.install_state)?;
+            self.install_state.skipped_builds
+                = link_result.build_requests.skipped_builds.clone();
 
-            if !self.skip_lockfile_update {
-                project.write_lockfile(&self.lockfile)?;
-            }
+            yarn_track_time!("persist_install", {
+                if !self.skip_lockfile_update {
+                    project.write_lockfile(&self.lockfile)?;
+                }
+
+                self.install_state.lockfile_hash = project.lockfile_hash()?;
+                project.attach_install_state(self.install_state)?;
+            });
+
+            // When specific packages are selected we still run the build manager
+            // so it can report the ones that don't match anything
+            let has_build_requests
+                = !link_result.build_requests.entries.is_empty() || !link_result.build_requests.skipped_builds.is_empty() || self.build_selection.is_some();
+
+            if !self.skip_build && has_build_requests {
+                let checksums = self.lockfile.entries.iter()
+                    .filter_map(|(locator, entry)| entry.checksum.clone().map(|checksum| (locator.clone(), checksum)))
+                    .collect();
 
-            if !self.skip_build && !link_result.build_requests.entries.is_empty() {
                 let build_future
-                    = build::BuildManager::new(link_result.build_requests).run(project);
+                    = build::BuildManager::new(link_result.build_requests)
+                        .with_checksums(checksums)
+                        .with_selection(self.build_selection.clone())
+                        .run(project);
 
-                let build_result
-                    = async_section("Building the project", build_future).await?;
+                let build_result = yarn_track_time!("build_project", {
+                    async_section("Building the project", build_future).await?
+                });
 
                 if !build_result.build_errors.is_empty() {
                     return Err(Error::SilentError);
@@ -619,6 +745,60 @@ impl Install {
     }
 }
 
+/// Extracts the given archive into a temporary directory and bundles the
+/// extracted files back into an archive, returning the checksum of the result.
+/// Archives can be arbitrarily large and many of them are checked in parallel,
+/// so both the extraction and the bundling are done one entry at a time.
+fn rebuild_archive_checksum(archive_path: &Path, compression_algorithm: Option<CompressionAlgorithm>) -> Result<Hash64, Error> {
+    let extraction_dir
+        = Path::temp_dir()?;
+
+    let rebuild_result = (|| -> Result<Hash64, Error> {
+        let mut extracted_entries
+            = vec![];
+
+        for entry in ZipFileIterator::new(archive_path)? {
+            let entry
+                = entry?;
+
+            zpm_formats::entry_to_disk(&entry, &extraction_dir)?;
+
+            // The names and modes are taken from the archive since the disk
+            // doesn't preserve them faithfully, but the content comes from
+            // the extracted files
+            extracted_entries.push((entry.name, entry.mode));
+        }
+
+        let mut zip_writer
+            = ZipWriter::new(Hash64Writer::new());
+
+        for (name, mode) in extracted_entries {
+            let data = extraction_dir
+                .with_join(&name)
+                .fs_read()?;
+
+            let extracted_entry = Entry {
+                name,
+                mode,
+                crc: 0,
+                data: Cow::Owned(data),
+                compression: None,
+            };
+
+            for rebuilt_entry in std::iter::once(extracted_entry).update_crc32().compress(compression_algorithm) {
+                zip_writer.write_entry(&rebuilt_entry)?;
+            }
+        }
+
+        Ok(zip_writer.finish()?.finish())
+    })();
+
+    extraction_dir
+        .fs_rm()?;
+
+    rebuild_result
+}
+
 pub struct InstallManager<'a> {
     initial_lockfile: Lockfile,
     context: InstallContext<'a>,
@@ -677,6 +857,16 @@ impl<'a> InstallManager<'a> {
         self
     }
 
+    pub fn with_forced_builds(mut self, forced_builds: BTreeSet<Ident>) -> Self {
+        self.result.forced_builds = forced_builds;
+        self
+    }
+
+    pub fn with_build_selection(mut self, build_selection: Option<BTreeSet<Ident>>) -> Self {
+        self.result.build_selection = build_selection;
+        self
+    }
+
     pub async fn resolve_and_fetch(mut self) -> Result<Install, Error> {
         let cache
             = InstallCache::new(self.initial_lockfile.clone());
@@ -690,12 +880,44 @@ impl<'a> InstallManager<'a> {
             });
         }
 
-        let graph_run
-            = async_section("Installing packages", graph.run()).await;
+        let path_overrides
+            = normalize_path_overrides(&self.context)?;
+
+        for path_override in &path_overrides {
+            graph.register(InstallOp::Resolve {
+                descriptor: path_override.descriptor.clone(),
+            });
+        }
+
+        let graph_run = yarn_track_time!("install_graph", {
+            async_section("Installing packages", graph.run()).await
+        });
 
         let installed_entries = graph_run
             .ok_or(Error::SilentError)?;
 
+        if let Some(project) = self.context.project {
+            let saved_requests
+                = project.http_client.bulk_packuments.saved_requests();
+
+            if saved_requests > 0 {
+                current_report().await.as_ref().map(|report| {
+                    report.info(format!("Saved {} metadata requests by batching them", DataType::Number.colorize(&saved_requests.to_string())));
+                });
+            }
+
+            let revalidated_requests
+                = project.http_client.packument_cache.revalidated_requests();
+
+            if revalidated_requests > 0 {
+                current_report().await.as_ref().map(|report| {
+                    report.info(format!("Reused {} cached metadata entries after revalidating them", DataType::Number.colorize(&revalidated_requests.to_string())));
+                });
+            }
+
+            let _ = project.http_client.packument_cache.prune();
+        }
+
         for entry in installed_entries {
             match entry {
                 (InstallOp::Resolve {..}, InstallOpResult::Validated) => {
@@ -730,7 +952,10 @@ impl<'a> InstallManager<'a> {
                 let has_checksum
                     = previous_entry.map_or(false, |s| s.checksum.is_some());
 
-                !has_checksum
+                // When refreshing the checksums we recompute them from the
+                // archives already in the cache rather than trusting those
+                // stored in the lockfile.
+                !has_checksum || self.context.refresh_checksums
             })
             .flat_map(|entry| {
                 let package_data = self.result.package_data.get(&entry.resolution.locator)
@@ -746,11 +971,10 @@ impl<'a> InstallManager<'a> {
 
         let late_checksums = missing_checksums.into_par_iter()
             .map(|(locator, archive_path)| -> Result<_, Error> {
-                let archive_data = archive_path
-                    .fs_read_prealloc()?;
-
+                // Archives can be arbitrarily large, and many of them are
+                // hashed in parallel, so we stream them from the disk
                 let checksum
-                    = Hash64::from_data(&archive_data);
+                    = Hash64::from_file(archive_path)?;
 
                 Ok((locator, checksum))
             })
@@ -767,9 +991,14 @@ impl<'a> InstallManager<'a> {
             let previous_checksum = previous_entry
                 .and_then(|s| s.checksum.as_ref());
 
-            let mut checksum = package_data.checksum()
-                .or_else(|| previous_checksum.cloned())
-                .or_else(|| late_checksums.get(&entry.resolution.locator).cloned());
+            let mut checksum = match self.context.refresh_checksums {
+                true => package_data.checksum()
+                    .or_else(|| late_checksums.get(&entry.resolution.locator).cloned()),
+
+                false => package_data.checksum()
+                    .or_else(|| previous_checksum.cloned())
+                    .or_else(|| late_checksums.get(&entry.resolution.locator).cloned()),
+            };
 
             let is_conditional_locator
                 = self.result.install_state.conditional_locators
@@ -789,12 +1018,11 @@ impl<'a> InstallManager<'a> {
                                     .with_join_str(entry.resolution.locator.slug())
                                     .with_ext("zip");
 
-                                let data = archive_path
-                                    .fs_read_prealloc()?;
-
                                 quarantine_path
-                                    .fs_create_parent()?
-                                    .fs_write(&data)?;
+                                    .fs_create_parent()?;
+
+                                archive_path
+                                    .fs_copy_file(&quarantine_path)?;
                             }
 
                             return Err(Error::ChecksumMismatch(entry.resolution.locator.clone()));
@@ -806,10 +1034,17 @@ impl<'a> InstallManager<'a> {
             entry.checksum = checksum;
         }
 
-        self.result.install_state.resolution_tree = TreeResolver::default()
-            .with_resolutions(&self.result.install_state.descriptor_to_locator, &self.result.install_state.normalized_resolutions)?
-            .with_roots(self.result.roots.clone())
-            .run();
+        if self.context.check_cache {
+            self.check_cache().await?;
+        }
+
+        self.result.install_state.resolution_tree = yarn_track_time!("tree_resolution", {
+            TreeResolver::default()
+                .with_resolutions(&self.result.install_state.descriptor_to_locator, &self.result.install_state.normalized_resolutions)?
+                .with_roots(self.result.roots.clone())
+                .with_path_overrides(path_overrides)
+                .run()
+        });
 
         self.result.lockfile.resolutions = self.result.install_state.descriptor_to_locator.clone();
         self.result.lockfile_changed = self.result.lockfile != self.initial_lockfile;
@@ -817,12 +1052,60 @@ impl<'a> InstallManager<'a> {
         self.result.skip_build = self.context.mode == Some(InstallMode::SkipBuild);
 
         if let Some(cache) = &self.context.package_cache {
-            cache.clean().await?;
+            yarn_track_time!("cache_clean", {
+                cache.clean().await?;
+            });
         }
 
         Ok(self.result)
     }
 
+    /// Re-extracts the cached archives of all packages with a checksum in
+    /// the lockfile and rebuilds them from the extracted files. Archives are
+    /// generated deterministically, so any difference with the locked
+    /// checksum means that the cache content was altered.
+    async fn check_cache(&self) -> Result<(), Error> {
+        let compression_algorithm = self.context.package_cache
+            .expect("The package cache is required for checking the cache")
+            .compression_algorithm;
+
+        let archives = self.initial_lockfile.entries.iter()
+            .filter_map(|(locator, entry)| {
+                let checksum
+                    = entry.checksum.as_ref()?;
+
+                let Some(PackageData::Zip {archive_path, ..}) = self.result.package_data.get(locator) else {
+                    return None;
+                };
+
+                Some((locator, archive_path, checksum))
+            })
+            .collect::<Vec<_>>();
+
+        let verified_count
+            = archives.len();
+
+        // Archives that can't even be extracted are as suspicious as the
+        // ones whose content changed, so both are reported as mismatches
+        let mismatches = archives.into_par_iter()
+            .filter(|(_, archive_path, checksum)| {
+                rebuild_archive_checksum(archive_path, compression_algorithm)
+                    .map_or(true, |rebuilt_checksum| rebuilt_checksum != **checksum)
+            })
+            .map(|(locator, _, _)| locator.clone())
+            .collect::<Vec<_>>();
+
+        if !mismatches.is_empty() {
+            return Err(Error::CacheCheckFailed(mismatches));
+        }
+
+        current_report().await.as_ref().map(|report| {
+            report.info(format!("Verified {} cached packages against their lockfile checksums", verified_count));
+        });
+
+        Ok(())
+    }
+
     fn record_resolution(&mut self, resolution: Resolution, original_resolution: Resolution, package_data: Option<PackageData>) -> Result<(), Error> {
         self.result.install_state.normalized_resolutions.insert(resolution.locator.clone(), resolution.clone());
 
@@ -908,6 +1191,10 @@ fn normalize_resolution(context: &InstallContext<'_>, descriptor: &mut Descripto
         }
     }
 
+    normalize_range(context, descriptor)
+}
+
+fn normalize_range(context: &InstallContext<'_>, descriptor: &mut Descriptor) -> Result<(), Error> {
     match &mut descriptor.range {
         Range::Catalog(params) => {
             let project
@@ -923,11 +1210,11 @@ fn normalize_resolution(context: &InstallContext<'_>, descriptor: &mut Descripto
                 descriptor.parent = None;
             }
 
-            normalize_resolution(context, descriptor, resolution, false)?;
+            normalize_range(context, descriptor)?;
         },
 
         Range::Patch(params) => {
-            normalize_resolution(context, &mut params.inner.as_mut().0, resolution, false)?;
+            normalize_range(context, &mut params.inner.as_mut().0)?;
         },
 
         Range::AnonymousSemver(params) => {
@@ -950,6 +1237,48 @@ fn normalize_resolution(context: &InstallContext<'_>, descriptor: &mut Descripto
     Ok(())
 }
 
+/// Returns the overrides scoped to a dependency path (`a/b/c`), with their
+/// replacement descriptors normalized like any other dependency. They get
+/// resolved along with the project's dependencies, but it's up to the tree
+/// resolver to substitute them on the branches matching their paths.
+fn normalize_path_overrides(context: &InstallContext<'_>) -> Result<Vec<PathOverride>, Error> {
+    let Some(project) = context.project else {
+        return Ok(vec![]);
+    };
+
+    let mut path_overrides
+        = vec![];
+
+    for (selector, range) in project.root_workspace().manifest.resolutions.iter() {
+        let ResolutionSelector::PathIdent(params) = selector else {
+            continue;
+        };
+
+        let mut descriptor
+            = Descriptor::new(params.ident.clone(), range.clone());
+
+        if descriptor.range.details().require_binding {
+            descriptor.parent = Some(project.root_workspace().locator());
+        }
+
+        if has_builtin_patch(&descriptor.ident) {
+            descriptor.range = PatchRange {
+                inner: Box::new(UrlEncoded::new(descriptor.clone())),
+                path: "<builtin>".to_string(),
+            }.into();
+        }
+
+        normalize_range(context, &mut descriptor)?;
+
+        path_overrides.push(PathOverride {
+            path: params.parent_path.idents.clone(),
+            descriptor,
+        });
+    }
+
+    Ok(path_overrides)
+}
+
 const BUILTIN_EXTENSIONS_JSON: &str = include_str!("../data/builtin-extensions.json");
 
 static BUILTIN_EXTENSIONS: LazyLock<BTreeMap<SemverDescriptor, PackageExtension>> = LazyLock::new(|| {
@@ -0,0 +1,209 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use zpm_primitives::Ident;
+use zpm_semver::Version;
+use zpm_utils::{DataType, ToHumanString};
+
+use crate::{report::current_report, tree_resolver::ResolutionTree};
+
+#[cfg(test)]
+#[path = "./install_summary.test.rs"]
+mod install_summary_tests;
+
+/// Past this number of changes, the summary only lists the first ones and
+/// mentions how many were left out.
+const MAX_LISTED_CHANGES: usize = 10;
+
+/// The versions of each package installed in a project, in a form that can
+/// be cheaply kept around and compared with the result of another install.
+/// Virtual packages are folded into the package they're derived from, and
+/// workspaces are tracked separately.
+#[derive(Debug, Default)]
+pub struct InstalledPackages {
+    packages: BTreeMap<Ident, BTreeSet<Version>>,
+    workspaces: BTreeSet<Ident>,
+}
+
+impl InstalledPackages {
+    pub fn from_tree(tree: &ResolutionTree) -> Self {
+        let mut installed_packages
+            = InstalledPackages::default();
+
+        for (locator, resolution) in &tree.locator_resolutions {
+            if locator.reference.is_virtual_reference() {
+                continue;
+            }
+
+            if locator.reference.is_workspace_reference() {
+                installed_packages.workspaces.insert(locator.ident.clone());
+            } else {
+                installed_packages.insert(locator.ident.clone(), resolution.version.clone());
+            }
+        }
+
+        installed_packages
+    }
+
+    pub fn insert(&mut self, ident: Ident, version: Version) {
+        self.packages.entry(ident)
+            .or_default()
+            .insert(version);
+    }
+
+    pub fn insert_workspace(&mut self, ident: Ident) {
+        self.workspaces.insert(ident);
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PackageChange {
+    Added(Ident, Vec<Version>),
+    Removed(Ident, Vec<Version>),
+    Changed(Ident, Vec<Version>, Vec<Version>),
+}
+
+impl PackageChange {
+    fn format_versions(versions: &[Version]) -> String {
+        versions.iter()
+            .map(|version| version.to_print_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn to_print_string(&self) -> String {
+        match self {
+            PackageChange::Added(ident, versions) => format!(
+                "{} {}@{}",
+                DataType::Success.colorize("+"),
+                ident.to_print_string(),
+                Self::format_versions(versions),
+            ),
+
+            PackageChange::Removed(ident, versions) => format!(
+                "{} {}@{}",
+                DataType::Error.colorize("-"),
+                ident.to_print_string(),
+                Self::format_versions(versions),
+            ),
+
+            PackageChange::Changed(ident, from, to) => format!(
+                "{} {} {} → {}",
+                DataType::Warning.colorize("~"),
+                ident.to_print_string(),
+                Self::format_versions(from),
+                Self::format_versions(to),
+            ),
+        }
+    }
+}
+
+/// The differences between the packages installed before and after an
+/// install. Packages are compared by ident, so that a package whose version
+/// changed shows up as a single change rather than as a removal followed by
+/// an addition.
+#[derive(Debug, Default)]
+pub struct InstallSummary {
+    pub changes: Vec<PackageChange>,
+    pub added_workspaces: usize,
+    pub removed_workspaces: usize,
+}
+
+impl InstallSummary {
+    pub fn new(before: &InstalledPackages, after: &InstalledPackages) -> Self {
+        let idents = before.packages.keys()
+            .chain(after.packages.keys())
+            .collect::<BTreeSet<_>>();
+
+        let no_versions
+            = BTreeSet::new();
+
+        let mut changes
+            = Vec::new();
+
+        for ident in idents {
+            let before_versions
+                = before.packages.get(ident).unwrap_or(&no_versions);
+            let after_versions
+                = after.packages.get(ident).unwrap_or(&no_versions);
+
+            let removed_versions = before_versions.difference(after_versions)
+                .cloned()
+                .collect::<Vec<_>>();
+            let added_versions = after_versions.difference(before_versions)
+                .cloned()
+                .collect::<Vec<_>>();
+
+            match (removed_versions.is_empty(), added_versions.is_empty()) {
+                (true, true) => {},
+                (true, false) => changes.push(PackageChange::Added(ident.clone(), added_versions)),
+                (false, true) => changes.push(PackageChange::Removed(ident.clone(), removed_versions)),
+                (false, false) => changes.push(PackageChange::Changed(ident.clone(), removed_versions, added_versions)),
+            }
+        }
+
+        InstallSummary {
+            changes,
+            added_workspaces: after.workspaces.difference(&before.workspaces).count(),
+            removed_workspaces: before.workspaces.difference(&after.workspaces).count(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.added_workspaces == 0 && self.removed_workspaces == 0
+    }
+
+    pub fn to_print_lines(&self) -> Vec<String> {
+        let mut lines
+            = Vec::new();
+
+        let count = |predicate: fn(&PackageChange) -> bool| {
+            self.changes.iter().filter(|change| predicate(change)).count()
+        };
+
+        let added
+            = count(|change| matches!(change, PackageChange::Added(..)));
+        let removed
+            = count(|change| matches!(change, PackageChange::Removed(..)));
+        let changed
+            = count(|change| matches!(change, PackageChange::Changed(..)));
+
+        if !self.changes.is_empty() {
+            lines.push(format!(
+                "Packages: {} added, {} removed, {} changed",
+                DataType::Number.colorize(&added.to_string()),
+                DataType::Number.colorize(&removed.to_string()),
+                DataType::Number.colorize(&changed.to_string()),
+            ));
+        }
+
+        if self.added_workspaces > 0 || self.removed_workspaces > 0 {
+            lines.push(format!(
+                "Workspaces: {} added, {} removed",
+                DataType::Number.colorize(&self.added_workspaces.to_string()),
+                DataType::Number.colorize(&self.removed_workspaces.to_string()),
+            ));
+        }
+
+        lines.extend(self.changes.iter()
+            .take(MAX_LISTED_CHANGES)
+            .map(|change| change.to_print_string()));
+
+        if self.changes.len() > MAX_LISTED_CHANGES {
+            lines.push(format!("... and {} more", DataType::Number.colorize(&(self.changes.len() - MAX_LISTED_CHANGES).to_string())));
+        }
+
+        lines
+    }
+
+    pub async fn report(&self) {
+        if self.is_empty() {
+            return;
+        }
+
+        current_report().await.as_ref().map(|report| {
+            for line in self.to_print_lines() {
+                report.info(line);
+            }
+        });
+    }
+}
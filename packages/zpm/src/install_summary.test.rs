@@ -0,0 +1,99 @@
+use zpm_utils::FromFileString;
+
+use super::*;
+
+fn version(version: &str) -> Version {
+    Version::from_file_string(version).unwrap()
+}
+
+fn packages(entries: &[(&str, &str)]) -> InstalledPackages {
+    let mut installed_packages
+        = InstalledPackages::default();
+
+    for (ident, version_str) in entries {
+        installed_packages.insert(Ident::new(ident), version(version_str));
+    }
+
+    installed_packages
+}
+
+#[test]
+fn it_detects_additions_removals_and_changes() {
+    let before
+        = packages(&[("left-pad", "1.3.0"), ("react", "18.2.0"), ("stable", "1.0.0")]);
+    let after
+        = packages(&[("lodash", "4.17.21"), ("react", "18.3.1"), ("stable", "1.0.0")]);
+
+    let summary
+        = InstallSummary::new(&before, &after);
+
+    assert_eq!(summary.changes, vec![
+        PackageChange::Removed(Ident::new("left-pad"), vec![version("1.3.0")]),
+        PackageChange::Added(Ident::new("lodash"), vec![version("4.17.21")]),
+        PackageChange::Changed(Ident::new("react"), vec![version("18.2.0")], vec![version("18.3.1")]),
+    ]);
+}
+
+#[test]
+fn it_only_reports_the_versions_that_differ() {
+    let before
+        = packages(&[("pkg", "1.0.0"), ("pkg", "2.0.0")]);
+    let after
+        = packages(&[("pkg", "1.0.0"), ("pkg", "2.0.0"), ("pkg", "3.0.0")]);
+
+    let summary
+        = InstallSummary::new(&before, &after);
+
+    assert_eq!(summary.changes, vec![
+        PackageChange::Added(Ident::new("pkg"), vec![version("3.0.0")]),
+    ]);
+}
+
+#[test]
+fn it_counts_workspaces_separately() {
+    let before
+        = packages(&[]);
+
+    let mut after
+        = packages(&[]);
+
+    after.insert_workspace(Ident::new("workspace-a"));
+
+    let summary
+        = InstallSummary::new(&before, &after);
+
+    assert!(summary.changes.is_empty());
+    assert_eq!(summary.added_workspaces, 1);
+    assert_eq!(summary.removed_workspaces, 0);
+}
+
+#[test]
+fn it_collapses_large_diffs() {
+    let before
+        = packages(&[]);
+
+    let entries = (0..MAX_LISTED_CHANGES + 5)
+        .map(|index| format!("pkg-{:02}", index))
+        .collect::<Vec<_>>();
+
+    let after = packages(&entries.iter()
+        .map(|ident| (ident.as_str(), "1.0.0"))
+        .collect::<Vec<_>>());
+
+    let lines
+        = InstallSummary::new(&before, &after).to_print_lines();
+
+    // One header line, the listed changes, and the line mentioning the rest
+    assert_eq!(lines.len(), MAX_LISTED_CHANGES + 2);
+    assert!(lines.last().unwrap().contains("5"));
+}
+
+#[test]
+fn it_is_empty_when_nothing_changed() {
+    let before
+        = packages(&[("pkg", "1.0.0")]);
+    let after
+        = packages(&[("pkg", "1.0.0")]);
+
+    assert!(InstallSummary::new(&before, &after).is_empty());
+}
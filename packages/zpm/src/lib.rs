@@ -1,4 +1,3 @@
-pub mod algolia;
 pub mod algos;
 pub mod build;
 pub mod builtins;
@@ -8,6 +7,7 @@ pub mod constraints;
 pub mod content_flags;
 pub mod descriptor_loose;
 pub mod diff_finder;
+pub mod engines;
 pub mod manifest_finder;
 pub mod error;
 pub mod fetchers;
@@ -16,8 +16,12 @@ pub mod git_utils;
 pub mod git;
 pub mod graph;
 pub mod http_npm;
+pub mod http_npm_bulk;
+pub mod http_npm_cache;
 pub mod http;
 pub mod install;
+pub mod install_summary;
+pub mod license_policy;
 pub mod linker;
 pub mod lockfile;
 pub mod manifest;
@@ -27,12 +31,15 @@ pub mod pack;
 pub mod patch;
 pub mod prepare;
 pub mod primitives_exts;
+pub mod profile;
 pub mod project;
 pub mod provenance;
 pub mod resolvers;
 pub mod report;
 pub mod script;
 pub mod scratchpad;
+pub mod shell;
+pub mod spdx;
 pub mod tree_resolver;
 pub mod versioning;
 pub mod workspace_glob;
@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use zpm_config::{LicensePolicy, LicenseSeverity, Setting};
+use zpm_primitives::Locator;
+use zpm_utils::{FromFileString, Glob, ToHumanString};
+
+use crate::{
+    error::{Error, ErrorCode},
+    install::InstallState,
+    project::Project,
+    report::current_report,
+    spdx::SpdxExpression,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Verdict {
+    Allowed,
+    ReviewRequired,
+    Denied,
+}
+
+fn matches_any(globs: &[Setting<Glob>], id: &str, exception: Option<&str>) -> bool {
+    let full_id
+        = exception.map(|exception| format!("{} WITH {}", id, exception));
+
+    globs.iter().any(|glob| {
+        glob.value.is_match(id) || full_id.as_ref().is_some_and(|full_id| glob.value.is_match(full_id))
+    })
+}
+
+fn evaluate_license(policy: &LicensePolicy, id: &str, exception: Option<&str>) -> Verdict {
+    if matches_any(&policy.deny, id, exception) {
+        return Verdict::Denied;
+    }
+
+    if matches_any(&policy.review_required, id, exception) {
+        return Verdict::ReviewRequired;
+    }
+
+    if !policy.allow.is_empty() && !matches_any(&policy.allow, id, exception) {
+        return Verdict::Denied;
+    }
+
+    Verdict::Allowed
+}
+
+/// Picks the most permissive option for `OR` expressions (the consumer gets
+/// to choose which license applies) and the most restrictive one for `AND`
+/// expressions (all licenses apply).
+fn evaluate(policy: &LicensePolicy, expression: &SpdxExpression) -> Verdict {
+    match expression {
+        SpdxExpression::License {id, exception}
+            => evaluate_license(policy, id, exception.as_deref()),
+
+        SpdxExpression::And(left, right)
+            => evaluate(policy, left).max(evaluate(policy, right)),
+
+        SpdxExpression::Or(left, right)
+            => evaluate(policy, left).min(evaluate(policy, right)),
+    }
+}
+
+/// Returns the packages reachable from the workspaces through their
+/// production dependencies, along with the package that first required them.
+fn production_packages(project: &Project, install_state: &InstallState) -> BTreeMap<Locator, Option<Locator>> {
+    let tree
+        = &install_state.resolution_tree;
+
+    let mut parents
+        = BTreeMap::new();
+    let mut queue
+        = VecDeque::new();
+
+    let workspace_locators = project.workspaces.iter()
+        .map(|workspace| workspace.locator())
+        .collect::<BTreeSet<_>>();
+
+    for workspace in &project.workspaces {
+        let locator
+            = workspace.locator();
+
+        let Some(resolution) = tree.locator_resolutions.get(&locator) else {
+            continue;
+        };
+
+        parents.insert(locator.clone(), None);
+
+        for (ident, descriptor) in &resolution.dependencies {
+            let is_production_dependency
+                = workspace.manifest.remote.dependencies.contains_key(ident)
+                    || workspace.manifest.remote.optional_dependencies.contains_key(ident);
+
+            if !is_production_dependency {
+                continue;
+            }
+
+            if let Some(dependency) = tree.descriptor_to_locator.get(descriptor) {
+                queue.push_back((dependency.clone(), locator.clone()));
+            }
+        }
+    }
+
+    while let Some((locator, parent)) = queue.pop_front() {
+        if parents.contains_key(&locator) {
+            continue;
+        }
+
+        parents.insert(locator.clone(), Some(parent));
+
+        let Some(resolution) = tree.locator_resolutions.get(&locator) else {
+            continue;
+        };
+
+        for descriptor in resolution.dependencies.values() {
+            if let Some(dependency) = tree.descriptor_to_locator.get(descriptor) {
+                queue.push_back((dependency.clone(), locator.clone()));
+            }
+        }
+    }
+
+    parents.retain(|locator, _| !workspace_locators.contains(locator));
+    parents
+}
+
+fn dependency_chain(parents: &BTreeMap<Locator, Option<Locator>>, project: &Project, locator: &Locator) -> String {
+    let mut chain
+        = vec![locator.physical_locator().to_print_string()];
+
+    let mut current
+        = parents.get(locator).cloned().flatten();
+
+    while let Some(parent) = current {
+        current = parents.get(&parent).cloned().flatten();
+
+        let label = project.workspaces.iter()
+            .find(|workspace| workspace.locator() == parent)
+            .map_or_else(|| parent.physical_locator().to_print_string(), |workspace| workspace.name.to_print_string());
+
+        chain.push(label);
+    }
+
+    chain.reverse();
+    chain.join(" → ")
+}
+
+/// Checks the licenses of the production dependencies against the
+/// `licensePolicy` setting. Violations are reported as warnings, unless the
+/// policy is enforced (or the severity configured as such for packages
+/// without a valid license), in which case they make the install fail.
+pub async fn check_license_policy(project: &Project, install_state: &InstallState) -> Result<(), Error> {
+    let policy
+        = &project.config.settings.license_policy;
+
+    if policy.allow.is_empty() && policy.deny.is_empty() && policy.review_required.is_empty() {
+        return Ok(());
+    }
+
+    let parents
+        = production_packages(project, install_state);
+
+    let mut violations
+        = Vec::new();
+    let mut checked_locators
+        = BTreeSet::new();
+
+    for locator in parents.keys() {
+        let physical_locator
+            = locator.physical_locator();
+
+        // Virtual instances of a same package share the same license
+        if !checked_locators.insert(physical_locator.clone()) {
+            continue;
+        }
+
+        if install_state.disabled_locators.contains(&physical_locator) {
+            continue;
+        }
+
+        let license = install_state.content_flags.get(&physical_locator)
+            .and_then(|content_flags| content_flags.license.as_deref());
+
+        let expression
+            = license.and_then(|license| SpdxExpression::from_file_string(license).ok());
+
+        let (severity, reason) = match expression.map(|expression| evaluate(policy, &expression)) {
+            Some(Verdict::Allowed)
+                => continue,
+
+            Some(Verdict::ReviewRequired)
+                => (LicenseSeverity::Warning, "uses a license that requires a review"),
+
+            Some(Verdict::Denied) if policy.enforce.value
+                => (LicenseSeverity::Error, "uses a license denied by the policy"),
+
+            Some(Verdict::Denied)
+                => (LicenseSeverity::Warning, "uses a license denied by the policy"),
+
+            None
+                => (policy.unknown_license_severity.value, "doesn't declare a valid license"),
+        };
+
+        if severity == LicenseSeverity::Ignore {
+            continue;
+        }
+
+        let message = format!(
+            "{} {} ({}); required through {}",
+            physical_locator.to_print_string(),
+            reason,
+            license.unwrap_or("none"),
+            dependency_chain(&parents, project, locator),
+        );
+
+        violations.push((severity, message));
+    }
+
+    let has_errors
+        = violations.iter().any(|(severity, _)| *severity == LicenseSeverity::Error);
+
+    current_report().await.as_ref().map(|report| {
+        for (severity, message) in violations {
+            match severity {
+                LicenseSeverity::Error => report.error(Error::LicensePolicyViolation(message)),
+                _ => report.warn_with_code(ErrorCode::LicensePolicy, message),
+            }
+        }
+    });
+
+    if has_errors {
+        return Err(Error::LicensePolicyError);
+    }
+
+    Ok(())
+}
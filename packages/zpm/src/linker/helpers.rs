@@ -1,5 +1,6 @@
 use std::{collections::{BTreeMap, BTreeSet}, fs::Permissions, os::unix::fs::PermissionsExt, vec};
 
+use zpm_config::EnableScripts;
 use zpm_formats::iter_ext::IterExt;
 use zpm_parsers::JsonDocument;
 use zpm_primitives::{Descriptor, FilterDescriptor, Locator};
@@ -13,6 +14,7 @@ use crate::{
     fetchers::PackageData,
     install::Install,
     project::Project,
+    report::Transfer,
     resolvers::Resolution,
 };
 
@@ -82,7 +84,10 @@ pub fn fs_remove_nm(nm_path: Path) -> Result<(), Error> {
     }
 }
 
-pub fn fs_extract_archive(destination: &Path, package_data: &PackageData) -> Result<bool, Error> {
+/// Extracts the package archive at the given location, unless it was already
+/// extracted there. The progress (in files written) is reported through the
+/// provided transfer, if any.
+pub fn fs_extract_archive(destination: &Path, package_data: &PackageData, transfer: Option<&Transfer>) -> Result<bool, Error> {
     let ready_path = destination
         .with_join_str(".ready");
 
@@ -101,6 +106,10 @@ pub fn fs_extract_archive(destination: &Path, package_data: &PackageData) -> Res
                 .strip_path_prefix(&package_subpath)
                 .collect::<Vec<_>>();
 
+        if let Some(transfer) = transfer {
+            transfer.set_total(entries.len() as u64);
+        }
+
         for entry in entries {
             let target_path = destination
                 .with_join(&entry.name);
@@ -109,6 +118,10 @@ pub fn fs_extract_archive(destination: &Path, package_data: &PackageData) -> Res
                 .fs_create_parent()?
                 .fs_write(&entry.data)?
                 .fs_set_permissions(Permissions::from_mode(entry.mode as u32))?;
+
+            if let Some(transfer) = transfer {
+                transfer.advance(1);
+            }
         }
 
         ready_path
@@ -166,6 +179,7 @@ pub fn populate_build_entry_dependencies(package_build_entries: &BTreeMap<Locato
 pub struct PackageBuildInfo {
     pub must_extract: bool,
     pub build_commands: Option<Vec<build::Command>>,
+    pub skipped_build: bool,
 }
 
 pub fn get_package_internal_info(project: &Project, install: &Install, dependencies_meta: &Vec<(FilterDescriptor, PackageMeta)>, locator: &Locator, resolution: &Resolution, physical_package_data: &PackageData) -> PackageBuildInfo {
@@ -187,12 +201,23 @@ pub fn get_package_internal_info(project: &Project, install: &Install, dependenc
         .get(&locator.physical_locator())
         .expect("Expected package flags to be set");
 
+    // Third-party build scripts only run when explicitly allowed, either
+    // through `allowedBuildDependencies` or `dependenciesMeta.built`. The
+    // project's own workspaces are always trusted, and `yarn rebuild` can
+    // force a one-time build of specific packages.
+    let is_build_allowed
+        = locator.reference.is_workspace_reference() || install.forced_builds.contains(&locator.ident) || package_meta.built.unwrap_or_else(|| {
+            project.is_build_allowed(&locator.ident, Some(&resolution.version))
+        });
+
+    let has_build_commands
+        = package_flags.build_commands.len() > 0;
+
     // We don't take into account `is_compatible` here, as it may change
     // depending on the system and we don't want the paths encoded in the
     // .pnp.cjs file to change depending on the system.
     let should_build_if_compatible
-        = package_flags.build_commands.len() > 0
-            && (locator.reference.is_workspace_reference() || package_meta.built.unwrap_or(project.config.settings.enable_scripts.value));
+        = has_build_commands && is_build_allowed;
 
     // Optional dependencies baked by zip archives are always extracted,
     // as we have no way to know whether they would be extracted if we
@@ -220,8 +245,15 @@ pub fn get_package_internal_info(project: &Project, install: &Install, dependenc
     let build_commands
         = must_build.then_some(package_flags.build_commands.clone());
 
+    // Packages explicitly disabled through `dependenciesMeta` (or through
+    // `enableScripts`) aren't reported, since the user already reviewed them.
+    let skipped_build
+        = has_build_commands && !is_build_allowed && is_compatible
+            && package_meta.built.is_none() && project.config.settings.enable_scripts.value != EnableScripts::Disabled;
+
     PackageBuildInfo {
         must_extract,
         build_commands,
+        skipped_build,
     }
 }
@@ -1,6 +1,7 @@
 use std::collections::{BTreeMap, BTreeSet};
 
 use itertools::Itertools;
+use zpm_config::NmHoistingLimits;
 use zpm_primitives::{Ident, LinkReference, Locator, Reference};
 use zpm_utils::{Path, ToFileString, ToHumanString, tree};
 
@@ -37,6 +38,16 @@ pub struct WorkNode {
     pub updated: bool,
 }
 
+/// A dependency that doesn't resolve to the expected package once the tree
+/// has been materialized, either because another version shadows it or
+/// because it isn't reachable at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDependency {
+    pub node_locator: Locator,
+    pub expected: Locator,
+    pub found: Option<Locator>,
+}
+
 pub struct WorkTree<'a> {
     pub project: &'a Project,
     pub install_state: &'a InstallState,
@@ -185,6 +196,96 @@ impl<'a> WorkTree<'a> {
         node.children = Some(children);
         node.updated = false;
     }
+
+    /// Walk the tree and return every dependency whose ident doesn't
+    /// resolve to the expected locator from the node requiring it. An empty
+    /// result means the node_modules layout satisfies all dependencies.
+    pub fn validate(&self) -> Vec<InvalidDependency> {
+        let mut invalid_dependencies
+            = Vec::new();
+
+        let mut parent_stack
+            = vec![];
+
+        self.validate_impl(0, BTreeMap::new(), &mut parent_stack, &mut invalid_dependencies);
+
+        invalid_dependencies
+    }
+
+    fn validate_impl<'s>(&'s self, node_idx: usize, available_dependencies: BTreeMap<&'s Ident, &'s Locator>, parent_stack: &mut Vec<usize>, invalid_dependencies: &mut Vec<InvalidDependency>) {
+        if parent_stack.contains(&node_idx) {
+            return;
+        }
+
+        parent_stack.push(node_idx);
+
+        let available_dependencies
+            = self.extend_available_dependencies(available_dependencies, node_idx);
+
+        let node
+            = &self.nodes[node_idx];
+
+        for expected in self.invalid_dependencies(node_idx, &available_dependencies) {
+            invalid_dependencies.push(InvalidDependency {
+                node_locator: node.locator.clone(),
+                expected: expected.clone(),
+                found: available_dependencies.get(&expected.ident).map(|&locator| locator.clone()),
+            });
+        }
+
+        for child_idx in self.node_children(node_idx) {
+            self.validate_impl(child_idx, available_dependencies.clone(), parent_stack, invalid_dependencies);
+        }
+
+        parent_stack.pop();
+    }
+
+    fn node_children(&self, node_idx: usize) -> Vec<usize> {
+        let node
+            = &self.nodes[node_idx];
+
+        node.children
+            .as_ref()
+            .expect("Expected the children to be present since we just expanded this node")
+            .values()
+            .chain(node.workspaces_idx.iter())
+            .copied()
+            .collect_vec()
+    }
+
+    fn invalid_dependencies<'s>(&'s self, node_idx: usize, available_dependencies: &BTreeMap<&'s Ident, &'s Locator>) -> Vec<&'s Locator> {
+        let node
+            = &self.nodes[node_idx];
+
+        let is_workspace_link
+            = |expected_locator: &Locator, locator: &Locator|
+                expected_locator.reference.is_workspace_reference() && locator.ident == expected_locator.ident && locator == &convert_workspace_to_link(self.project, expected_locator.clone());
+
+        let is_dependency_valid
+            = |expected_locator: &Locator|
+                available_dependencies.get(&expected_locator.ident)
+                    .map_or(false, |&available_locator| available_locator == expected_locator || is_workspace_link(expected_locator, available_locator));
+
+        node.dependencies.values()
+            .filter(|&expected_locator| !is_dependency_valid(expected_locator))
+            .collect_vec()
+    }
+
+    fn extend_available_dependencies<'s>(&'s self, mut available_dependencies: BTreeMap<&'s Ident, &'s Locator>, node_idx: usize) -> BTreeMap<&'s Ident, &'s Locator> {
+        let node
+            = &self.nodes[node_idx];
+
+        available_dependencies.insert(&node.locator.ident, &node.locator);
+
+        available_dependencies.extend(node.children.as_ref().unwrap().iter().map(|(ident, child_idx)| {
+            let child_node
+                = &self.nodes[*child_idx];
+
+            (ident, &child_node.locator)
+        }));
+
+        available_dependencies
+    }
 }
 
 pub struct TreeRenderer<'a, 'b> {
@@ -211,24 +312,13 @@ impl<'a, 'b> TreeRenderer<'a, 'b> {
         self.parent_stack.push(node_idx);
 
         let available_dependencies
-            = self.extend_available_dependencies(available_dependencies, node_idx);
+            = self.tree.extend_available_dependencies(available_dependencies, node_idx);
 
         let node
             = &self.tree.nodes[node_idx];
 
-        let is_workspace_link
-            = |expected_locator: &Locator, locator: &Locator|
-                expected_locator.reference.is_workspace_reference() && locator.ident == expected_locator.ident && locator == &convert_workspace_to_link(self.tree.project, expected_locator.clone());
-
-        let is_dependency_valid
-            = |expected_locator: &Locator|
-                available_dependencies.get(&expected_locator.ident)
-                    .map_or(false, |&available_locator| available_locator == expected_locator || is_workspace_link(expected_locator, available_locator));
-
         let invalid_dependencies
-            = node.dependencies.values()
-                .filter(|&expected_locator| !is_dependency_valid(expected_locator))
-                .collect_vec();
+            = self.tree.invalid_dependencies(node_idx, &available_dependencies);
 
         let mut label
             = node.locator.to_print_string();
@@ -257,16 +347,7 @@ impl<'a, 'b> TreeRenderer<'a, 'b> {
             });
         }
 
-        let node_children
-            = node.children
-                .as_ref()
-                .expect("Expected the children to be present since we just expanded this node")
-                .values()
-                .chain(node.workspaces_idx.iter())
-                .copied()
-                .collect_vec();
-
-        for child_idx in node_children {
+        for child_idx in self.tree.node_children(node_idx) {
             tree_children.push(self.convert_impl(child_idx, available_dependencies.clone()));
         }
 
@@ -278,37 +359,26 @@ impl<'a, 'b> TreeRenderer<'a, 'b> {
             children: Some(tree::TreeNodeChildren::Vec(tree_children)),
         }
     }
-
-    fn extend_available_dependencies(&self, mut available_dependencies: BTreeMap<&'a Ident, &'a Locator>, node_idx: usize) -> BTreeMap<&'a Ident, &'a Locator> {
-        let node
-            = &self.tree.nodes[node_idx];
-
-        available_dependencies.insert(&node.locator.ident, &node.locator);
-
-        available_dependencies.extend(node.children.as_ref().unwrap().iter().map(|(ident, child_idx)| {
-            let child_node
-                = &self.tree.nodes[*child_idx];
-
-            (ident, &child_node.locator)
-        }));
-
-        available_dependencies
-    }
 }
 
 pub struct Hoister<'a, 'b> {
     work_tree: &'a mut WorkTree<'b>,
     stack: Vec<usize>,
     has_changed: bool,
+    hoisting_limits: NmHoistingLimits,
     print_logs: bool,
 }
 
 impl<'a, 'b> Hoister<'a, 'b> {
     pub fn new(work_tree: &'a mut WorkTree<'b>) -> Self {
+        let hoisting_limits
+            = work_tree.project.config.settings.nm_hoisting_limits.value;
+
         Self {
             work_tree,
             stack: vec![],
             has_changed: false,
+            hoisting_limits,
             print_logs: false,
         }
     }
@@ -317,6 +387,30 @@ impl<'a, 'b> Hoister<'a, 'b> {
         self.print_logs = print_logs;
     }
 
+    /// Whether the children of the given child node must stay within it
+    /// rather than being hoisted into the parent node.
+    fn is_hoisting_border(&self, parent_idx: usize, child_idx: usize) -> bool {
+        let physical_reference = |node_idx: usize| {
+            self.work_tree.nodes[node_idx].locator.physical_locator().reference
+        };
+
+        let is_workspace
+            = |node_idx: usize| physical_reference(node_idx).is_workspace_reference();
+
+        match self.hoisting_limits {
+            NmHoistingLimits::None
+                => false,
+
+            NmHoistingLimits::Workspaces
+                => is_workspace(child_idx),
+
+            // Portals are transparent: their dependencies are considered to be
+            // direct dependencies of the workspace depending on them
+            NmHoistingLimits::Dependencies
+                => is_workspace(child_idx) || (is_workspace(parent_idx) && !matches!(physical_reference(child_idx), Reference::Portal(_))),
+        }
+    }
+
     pub fn hoist(&mut self) {
         self.stack.clear();
 
@@ -367,6 +461,21 @@ impl<'a, 'b> Hoister<'a, 'b> {
         for &child_idx in node_children.iter() {
             self.work_tree.expand_node(child_idx);
 
+            // Packages are never hoisted past a border, even when the same
+            // package is also found below other children; each border then
+            // keeps its own copy
+            if self.is_hoisting_border(node_idx, child_idx) {
+                if self.print_logs {
+                    self.print(&format!(
+                        "Cannot hoist the dependencies of {} into {} because of the hoisting limits",
+                        self.work_tree.nodes[child_idx].locator.to_print_string(),
+                        self.work_tree.nodes[node_idx].locator.to_print_string(),
+                    ));
+                }
+
+                continue;
+            }
+
             let flattened_node
                 = &self.work_tree.nodes[child_idx];
 
@@ -1,17 +1,112 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
+use zpm_config::NmMode;
 use zpm_primitives::{Ident, Reference};
+use zpm_parsers::JsonDocument;
 use zpm_sync::{SyncItem, SyncTemplate, SyncTree};
-use zpm_utils::{FromFileString, Path, ToHumanString};
+use zpm_utils::{FromFileString, Hash64, Path, ToFileString, ToHumanString};
 
 use crate::{
-    build::BuildRequests, error::Error, fetchers::PackageData, install::Install, linker::{LinkResult, nm::hoist::{Hoister, WorkTree}}, project::Project
+    build::BuildRequests, error::{Error, ErrorCode}, fetchers::PackageData, install::Install, linker::{self, LinkResult, nm::{hoist::{Hoister, WorkTree}, state::NmState}}, project::Project, report::current_report
 };
 
 pub mod hoist;
+pub mod state;
 
 const EXPECT_CHILDREN: &str = "All nodes should be expanded by the end of the hoisting process";
 
+/// The entries to materialize in a node_modules folder, grouped by the package
+/// folder they belong to so that the packages which didn't change since the
+/// last install can be skipped without even looking at their content.
+#[derive(Default)]
+struct NmEntries<'a> {
+    root_entries: Vec<(Path, SyncItem<'a>)>,
+    package_entries: BTreeMap<Path, Vec<(Path, SyncItem<'a>)>>,
+}
+
+impl<'a> NmEntries<'a> {
+    /// Returns the folder of the package the given entry belongs to (for
+    /// example `@scope/foo` for `@scope/foo/node_modules/bar/index.js`).
+    fn package_rel_path(rel_path: &Path) -> Option<Path> {
+        let mut segments
+            = rel_path.segments();
+
+        let first_segment
+            = segments.next()?;
+
+        if first_segment == ".bin" {
+            return None;
+        }
+
+        let package_rel_path
+            = Path::new().with_join_str(first_segment);
+
+        if !first_segment.starts_with('@') {
+            return Some(package_rel_path);
+        }
+
+        segments.next()
+            .map(|second_segment| package_rel_path.with_join_str(second_segment))
+    }
+
+    fn register_entry(&mut self, rel_path: Path, entry: SyncItem<'a>) -> Result<(), Error> {
+        match Self::package_rel_path(&rel_path) {
+            Some(package_rel_path) => {
+                self.package_entries.entry(package_rel_path).or_default().push((rel_path, entry));
+            },
+
+            None => {
+                self.root_entries.push((rel_path, entry));
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Builds the sync tree for the node_modules folder, replacing the
+    /// packages that are still laid out the same way as during the previous
+    /// install by placeholders that the sync will leave untouched.
+    fn into_sync_tree(self, project: &Project, nm_path: &Path, previous_nm_state: &NmState, nm_state: &mut NmState) -> Result<SyncTree<'a>, Error> {
+        let mut sync_tree
+            = SyncTree::new();
+
+        sync_tree.dry_run = false;
+
+        for (rel_path, entry) in self.root_entries {
+            sync_tree.register_entry(rel_path, entry)?;
+        }
+
+        for (package_rel_path, entries) in self.package_entries {
+            let package_abs_path
+                = nm_path.with_join(&package_rel_path);
+
+            let location
+                = package_abs_path.relative_to(&project.project_cwd);
+
+            // The entries are hashed through their JSON serialization, which
+            // (unlike their debug representation) is stable across releases
+            let hash
+                = Hash64::from_data(JsonDocument::to_string(&entries)?);
+
+            let is_up_to_date
+                = previous_nm_state.entries.get(&location) == Some(&hash) && package_abs_path.fs_exists();
+
+            nm_state.entries.insert(location, hash);
+
+            if is_up_to_date {
+                sync_tree.ignore_entry(package_rel_path)?;
+                continue;
+            }
+
+            for (rel_path, entry) in entries {
+                sync_tree.register_entry(rel_path, entry)?;
+            }
+        }
+
+        Ok(sync_tree)
+    }
+}
+
 fn collect_binaries_from_dependencies(install: &Install, children: &BTreeMap<Ident, usize>, work_tree: &WorkTree) -> BTreeMap<String, (Ident, Path)> {
     let mut binaries
         = BTreeMap::new();
@@ -52,7 +147,7 @@ fn collect_workspace_binaries(install: &Install, workspace_node: &hoist::WorkNod
 /// Registers bin symlinks in the sync tree for a given node_modules subfolder.
 /// `node_rel_path` is the path within node_modules where dependencies are located
 /// (e.g., "" for top-level, or "foo/node_modules" for nested).
-fn register_bin_symlinks_at_path(workspace_nm_tree: &mut SyncTree, node_rel_path: &Path, binaries: &BTreeMap<String, (Ident, Path)>) -> Result<(), Error> {
+fn register_bin_symlinks_at_path(workspace_nm_entries: &mut NmEntries, node_rel_path: &Path, binaries: &BTreeMap<String, (Ident, Path)>) -> Result<(), Error> {
     for (bin_name, (dep_ident, bin_path)) in binaries {
         // The symlink will be at <node_rel_path>/.bin/<bin_name>
         // It needs to point to ../<dep_name>/<bin_path>
@@ -65,7 +160,7 @@ fn register_bin_symlinks_at_path(workspace_nm_tree: &mut SyncTree, node_rel_path
             .with_join_str(&dep_ident.as_str())
             .with_join(bin_path);
 
-        workspace_nm_tree.register_entry(bin_symlink_path, SyncItem::Symlink {
+        workspace_nm_entries.register_entry(bin_symlink_path, SyncItem::Symlink {
             target_path,
         })?;
     }
@@ -73,7 +168,7 @@ fn register_bin_symlinks_at_path(workspace_nm_tree: &mut SyncTree, node_rel_path
     Ok(())
 }
 
-fn register_workspace_bin_symlinks(workspace_nm_tree: &mut SyncTree, workspace_path: &Path, binaries: &BTreeMap<String, (Ident, Path)>) -> Result<(), Error> {
+fn register_workspace_bin_symlinks(workspace_nm_entries: &mut NmEntries, workspace_path: &Path, binaries: &BTreeMap<String, (Ident, Path)>) -> Result<(), Error> {
     for (bin_name, (_ident, bin_path)) in binaries {
         let target_abs_path
             = workspace_path
@@ -92,7 +187,7 @@ fn register_workspace_bin_symlinks(workspace_nm_tree: &mut SyncTree, workspace_p
             .with_join_str("..")
             .with_join(bin_path);
 
-        workspace_nm_tree.register_entry(bin_symlink_path, SyncItem::Symlink {
+        workspace_nm_entries.register_entry(bin_symlink_path, SyncItem::Symlink {
             target_path,
         })?;
     }
@@ -100,6 +195,101 @@ fn register_workspace_bin_symlinks(workspace_nm_tree: &mut SyncTree, workspace_p
     Ok(())
 }
 
+/// Extracts the package into the nm store so that its files can be cloned
+/// into node_modules. The folder is only moved into place once fully
+/// extracted, so its existence is enough to know it can be used.
+fn ensure_store_entry(store_entry_path: &Path, package_data: &PackageData) -> Result<(), Error> {
+    if store_entry_path.fs_exists() {
+        return Ok(());
+    }
+
+    let temp_path
+        = Path::try_from(format!("{}.{}.tmp", store_entry_path.to_file_string(), std::process::id()))?;
+
+    linker::helpers::fs_extract_archive(&temp_path, package_data)?;
+
+    temp_path
+        .with_join_str(".ready")
+        .fs_rm_file()?;
+
+    temp_path
+        .fs_concurrent_move(store_entry_path)?;
+
+    // If another process moved its own copy first, ours is left behind
+    if temp_path.fs_exists() {
+        temp_path.fs_rm()?;
+    }
+
+    Ok(())
+}
+
+fn is_portal_node(node: &hoist::WorkNode) -> bool {
+    matches!(node.locator.physical_locator().reference, Reference::Portal(_))
+}
+
+/// Portals are symlinked to their target folder, which we mustn't modify; the
+/// dependencies of the portal must thus all have been hoisted into the tree
+/// surrounding it, otherwise they wouldn't be reachable at runtime.
+fn check_portal_dependencies(work_tree: &WorkTree) -> Result<(), Error> {
+    for node in work_tree.nodes.iter().filter(|node| is_portal_node(node)) {
+        let Some(children) = &node.children else {
+            continue;
+        };
+
+        let Some((ident, &child_idx)) = children.iter().next() else {
+            continue;
+        };
+
+        let child_locator
+            = work_tree.nodes[child_idx].locator.clone();
+
+        let parent_node = node.parent_idx
+            .map(|parent_idx| &work_tree.nodes[parent_idx]);
+
+        let parent_locator = parent_node
+            .and_then(|parent_node| parent_node.children.as_ref()?.get(ident))
+            .map(|&parent_child_idx| &work_tree.nodes[parent_child_idx].locator)
+            .or_else(|| parent_node.and_then(|parent_node| parent_node.dependencies.get(ident)));
+
+        return Err(match parent_locator {
+            Some(parent_locator) if parent_locator != &child_locator
+                => Error::PortalDependencyConflict(node.locator.clone(), child_locator, parent_locator.clone()),
+
+            _ => Error::UnhoistablePortalDependency(node.locator.clone(), child_locator),
+        });
+    }
+
+    Ok(())
+}
+
+/// Workspaces listing themselves in their dependencies (typically to import
+/// their own files through their package name) don't get a node in the work
+/// tree, since they're their own parent; we still need the symlink on disk.
+fn register_self_reference(workspace_nm_entries: &mut NmEntries, workspace_node: &hoist::WorkNode, workspace_dir: &Path, workspace_abs_path: &Path) -> Result<(), Error> {
+    let ident
+        = &workspace_node.locator.ident;
+
+    if workspace_node.dependencies.get(ident) != Some(&workspace_node.locator) {
+        return Ok(());
+    }
+
+    if workspace_node.children.as_ref().expect(EXPECT_CHILDREN).contains_key(ident) {
+        return Ok(());
+    }
+
+    let self_reference_abs_path
+        = workspace_abs_path.with_join_str(&ident.as_str());
+
+    let target_path
+        = workspace_dir.relative_to(&self_reference_abs_path.dirname().unwrap());
+
+    workspace_nm_entries.register_entry(Path::new().with_join_str(&ident.as_str()), SyncItem::Symlink {
+        target_path,
+    })?;
+
+    Ok(())
+}
+
 pub async fn link_project_nm(project: &Project, install: &Install) -> Result<LinkResult, Error> {
     let mut work_tree
         = WorkTree::new(project, &install.install_state);
@@ -112,6 +302,31 @@ pub async fn link_project_nm(project: &Project, install: &Install) -> Result<Lin
 
     hoister.hoist();
 
+    // The hoister should never break the dependency graph, but when it does
+    // it's better to let the user know than to silently produce a
+    // node_modules folder that won't work at runtime.
+    let invalid_dependencies
+        = work_tree.validate();
+
+    current_report().await.as_ref().map(|report| {
+        for invalid_dependency in &invalid_dependencies {
+            report.warn_with_code(ErrorCode::UnsatisfiedDependency, format!(
+                "{} depends on {}, but the hoisted tree provides {}",
+                invalid_dependency.node_locator.to_print_string(),
+                invalid_dependency.expected.to_print_string(),
+                invalid_dependency.found.as_ref().map_or_else(|| "nothing".to_string(), |locator| locator.to_print_string()),
+            ));
+        }
+    });
+
+    check_portal_dependencies(&work_tree)?;
+
+    let previous_nm_state
+        = NmState::load(project);
+
+    let mut nm_state
+        = NmState::default();
+
     let mut project_queue
         = vec![0usize];
 
@@ -132,15 +347,14 @@ pub async fn link_project_nm(project: &Project, install: &Install) -> Result<Lin
             = workspace_dir
                 .with_join_str("node_modules");
 
-        let mut workspace_nm_tree
-            = SyncTree::new();
-
-        workspace_nm_tree.dry_run = false;
+        let mut workspace_nm_entries
+            = NmEntries::default();
 
         let workspace_binaries
             = collect_workspace_binaries(install, &work_tree.nodes[workspace_node_idx]);
 
-        register_workspace_bin_symlinks(&mut workspace_nm_tree, &workspace_dir, &workspace_binaries)?;
+        register_workspace_bin_symlinks(&mut workspace_nm_entries, &workspace_dir, &workspace_binaries)?;
+        register_self_reference(&mut workspace_nm_entries, workspace_node, &workspace_dir, &workspace_abs_path)?;
 
         let mut workspace_queue
             = vec![(Path::new(), workspace_node_idx)];
@@ -160,7 +374,11 @@ pub async fn link_project_nm(project: &Project, install: &Install) -> Result<Lin
                 let child_rel_path
                     = node_rel_path.with_join_str(&ident.as_str());
 
-                workspace_queue.push((child_rel_path.with_join_str("node_modules"), *child_idx));
+                // The portal dependencies have all been hoisted (we checked
+                // that earlier), so there's nothing to write inside them
+                if !is_portal_node(child_node) {
+                    workspace_queue.push((child_rel_path.with_join_str("node_modules"), *child_idx));
+                }
 
                 let abs_path
                     = workspace_abs_path
@@ -188,22 +406,39 @@ pub async fn link_project_nm(project: &Project, install: &Install) -> Result<Lin
                         let target_path
                             = package_directory.relative_to(&child_abs_path.dirname().unwrap());
 
-                        workspace_nm_tree.register_entry(child_rel_path, SyncItem::Symlink {
+                        workspace_nm_entries.register_entry(child_rel_path, SyncItem::Symlink {
                             target_path: target_path.clone(),
                         })?;
                     },
 
-                    Some(PackageData::Zip {archive_path, package_directory, ..}) => {
-                        workspace_nm_tree.register_entry(child_rel_path, SyncItem::Folder {
-                            template: Some(SyncTemplate::Zip {
+                    Some(package_data @ PackageData::Zip {archive_path, package_directory, ..}) => {
+                        let template = match project.config.settings.nm_mode.value {
+                            NmMode::Classic => SyncTemplate::Zip {
                                 archive_path: archive_path.clone(),
                                 inner_path: package_directory.relative_to(&archive_path),
-                            }),
+                            },
+
+                            NmMode::CopyReflink => {
+                                let source_path = project.nm_store_path()
+                                    .with_join_str(child_node.locator.physical_locator().slug());
+
+                                ensure_store_entry(&source_path, package_data)?;
+
+                                SyncTemplate::Folder {
+                                    source_path,
+                                }
+                            },
+                        };
+
+                        workspace_nm_entries.register_entry(child_rel_path, SyncItem::Folder {
+                            template: Some(template),
                         })?;
                     },
 
                     Some(PackageData::MissingZip {..}) => {
-                        // Nothing to do here
+                        // The package is excluded from the current platform, so
+                        // there's nothing on disk to link; it's tracked through
+                        // the install state's disabled locators instead.
                     },
 
                     None => match &child_node.locator.reference {
@@ -211,7 +446,7 @@ pub async fn link_project_nm(project: &Project, install: &Install) -> Result<Lin
                             let target_path
                                 = Path::from_file_string(&params.path)?;
 
-                            workspace_nm_tree.register_entry(child_rel_path, SyncItem::Symlink {
+                            workspace_nm_entries.register_entry(child_rel_path, SyncItem::Symlink {
                                 target_path,
                             })?;
                         },
@@ -235,20 +470,24 @@ pub async fn link_project_nm(project: &Project, install: &Install) -> Result<Lin
                 }
             }
 
-            register_bin_symlinks_at_path(&mut workspace_nm_tree, &node_rel_path, &binaries)?;
+            register_bin_symlinks_at_path(&mut workspace_nm_entries, &node_rel_path, &binaries)?;
         }
 
-        workspace_nm_tree
+        workspace_nm_entries
+            .into_sync_tree(project, &workspace_abs_path, &previous_nm_state, &mut nm_state)?
             .run(workspace_abs_path)?;
 
         project_queue.extend_from_slice(&workspace_node.workspaces_idx);
     }
 
+    nm_state.save(project)?;
+
     Ok(LinkResult {
         packages_by_location,
         build_requests: BuildRequests {
             entries: vec![],
             dependencies: BTreeMap::new(),
+            skipped_builds: BTreeSet::new(),
         },
     })
 }
@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use zpm_parsers::JsonDocument;
+use zpm_utils::{Hash64, Path};
+
+use crate::{error::Error, project::Project};
+
+/// Remembers, for each package folder laid out in the node_modules folders
+/// of the project, a hash of the entries we registered for it. Packages whose
+/// hash didn't change since the last install don't need to be synced again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NmState {
+    pub entries: BTreeMap<Path, Hash64>,
+}
+
+impl NmState {
+    /// Missing or unreadable states (for example if they were written by an
+    /// incompatible version) simply lead to a full re-link.
+    pub fn load(project: &Project) -> Self {
+        project.nm_state_path()
+            .fs_read_text()
+            .ok()
+            .and_then(|nm_state_text| JsonDocument::hydrate_from_str::<Self>(&nm_state_text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, project: &Project) -> Result<(), Error> {
+        let nm_state_path = project
+            .nm_state_path();
+
+        nm_state_path
+            .fs_create_parent()?;
+
+        let nm_state_text
+            = JsonDocument::to_string(self)?;
+
+        nm_state_path
+            .fs_change(nm_state_text, false)?;
+
+        Ok(())
+    }
+}
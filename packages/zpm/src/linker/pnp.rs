@@ -3,7 +3,7 @@ use std::{collections::{BTreeMap, BTreeSet}, str::FromStr};
 use zpm_config::PnpFallbackMode;
 use zpm_parsers::JsonDocument;
 use zpm_primitives::{Ident, Locator, Reference};
-use zpm_utils::{IoResultExt, Path, SyncEntryKind, ToHumanString};
+use zpm_utils::{Glob, IoResultExt, Path, SyncEntryKind, ToHumanString};
 use sha2::{Sha512, Digest};
 use hex;
 use itertools::Itertools;
@@ -19,6 +19,7 @@ use crate::{
     linker::{self, LinkResult},
     misc,
     project::Project,
+    report::{TransferUnit, current_report},
 };
 
 const PNP_CJS_TEMPLATE: &[u8] = std::include_bytes!("pnp-cjs.brotli.dat");
@@ -143,7 +144,7 @@ struct PnpState {
     #[serde_as(as = "Vec<(_, _)>")]
     fallback_exclusion_list: BTreeMap<Ident, BTreeSet<PnpReference>>,
 
-    ignore_pattern_data: Option<Vec<String>>,
+    ignore_pattern_data: Option<String>,
 
     #[serde_as(as = "Vec<(_, Vec<(_, _)>)>")]
     package_registry_data: BTreeMap<Option<Ident>, BTreeMap<Option<PnpReference>, PnpPackageInformation>>,
@@ -252,6 +253,8 @@ pub async fn link_project_pnp<'a>(project: &'a Project, install: &'a Install) ->
         = Vec::new();
     let mut package_build_entries
         = BTreeMap::new();
+    let mut skipped_builds
+        = BTreeSet::new();
 
     let unplugged_path
         = project.unplugged_path();
@@ -347,9 +350,13 @@ pub async fn link_project_pnp<'a>(project: &'a Project, install: &'a Install) ->
                     package_location_abs.clone(),
                 );
 
+                let transfer = current_report().await.as_ref()
+                    .map(|report| report.start_transfer(TransferUnit::Files, Some(locator.to_print_string()), None));
+
                 is_freshly_unplugged = linker::helpers::fs_extract_archive(
                     &package_location_abs,
                     physical_package_data,
+                    transfer.as_ref(),
                 )?;
             }
 
@@ -391,6 +398,10 @@ pub async fn link_project_pnp<'a>(project: &'a Project, install: &'a Install) ->
                 discard_from_lookup,
             });
 
+        if package_build_info.skipped_build {
+            skipped_builds.insert(locator.clone());
+        }
+
         if let Some(build_commands) = package_build_info.build_commands {
             let build_cwd = match is_physically_on_disk {
                 true => {
@@ -520,10 +531,20 @@ pub async fn link_project_pnp<'a>(project: &'a Project, install: &'a Install) ->
         }
     }
 
+    // The runtime compiles this into a single regex, which it then tests
+    // against the issuer paths (relative to the project root, without any
+    // leading `./`). Requests coming from matching files get resolved by the
+    // regular Node resolution rather than through the PnP maps.
     let ignore_pattern_data = project.config.settings.pnp_ignore_patterns
         .iter()
-        .map(|pattern| pattern.value.to_regex_string())
-        .collect::<Vec<String>>();
+        .map(|pattern| match pattern.value.raw().strip_prefix("./") {
+            Some(relative_pattern) => Glob::parse(relative_pattern)
+                .map(|glob| glob.to_regex_string())
+                .map_err(|err| Error::InvalidGlob(err.to_string())),
+            None => Ok(pattern.value.to_regex_string()),
+        })
+        .map(|regex| regex.map(|regex| format!("(?:{})", regex)))
+        .collect::<Result<Vec<String>, Error>>()?;
 
     let state = PnpState {
         enable_top_level_fallback,
@@ -532,7 +553,7 @@ pub async fn link_project_pnp<'a>(project: &'a Project, install: &'a Install) ->
 
         ignore_pattern_data: match ignore_pattern_data.is_empty() {
             true => None,
-            false => Some(ignore_pattern_data),
+            false => Some(ignore_pattern_data.join("|")),
         },
 
         package_registry_data,
@@ -557,6 +578,7 @@ pub async fn link_project_pnp<'a>(project: &'a Project, install: &'a Install) ->
     let build_requests = build::BuildRequests {
         entries: all_build_entries,
         dependencies: package_build_dependencies?,
+        skipped_builds,
     };
 
     Ok(LinkResult {
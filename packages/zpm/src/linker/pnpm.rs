@@ -1,7 +1,9 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{collections::{BTreeMap, BTreeSet}, fs::Permissions, os::unix::fs::PermissionsExt};
 use itertools::Itertools;
+use zpm_formats::iter_ext::IterExt;
 use zpm_primitives::{Ident, IdentGlob, Locator};
-use zpm_utils::{IoResultExt, Path, ToHumanString};
+use zpm_sync::{SyncItem, SyncTree};
+use zpm_utils::{IoResultExt, Path, Sha256, ToHumanString};
 
 use crate::{
     build,
@@ -10,6 +12,7 @@ use crate::{
     install::Install,
     linker::{self, LinkResult},
     project::Project,
+    report::{Transfer, TransferUnit, current_report},
     tree_resolver::ResolutionTree,
 };
 
@@ -45,6 +48,100 @@ fn collect_hoistable_packages<'a>(tree: &'a ResolutionTree, patterns: &[IdentGlo
     hoistable
 }
 
+/// Materializes the package at the given location by hard linking its files
+/// from the content-addressable store, adding to the store the files it
+/// doesn't contain yet. Identical files are thus only written once on disk,
+/// even when shared by multiple packages (or projects).
+///
+/// Packages with build scripts get their own copy of each file instead, as
+/// their builds could otherwise modify the files shared through the store.
+fn fs_link_archive_from_store(destination: &Path, package_data: &PackageData, content_store_path: &Path, has_build: bool, transfer: Option<&Transfer>) -> Result<bool, Error> {
+    let ready_path = destination
+        .with_join_str(".ready");
+
+    if ready_path.fs_exists() || matches!(package_data, PackageData::MissingZip {..}) {
+        return Ok(false);
+    }
+
+    let package_subpath
+        = package_data.package_subpath();
+
+    let package_bytes = match package_data {
+        PackageData::Zip {archive_path, ..} => archive_path.fs_read()?,
+        _ => panic!("Expected a zip archive"),
+    };
+
+    let entries
+        = zpm_formats::zip::entries_from_zip(&package_bytes)?
+            .into_iter()
+            .strip_path_prefix(&package_subpath)
+            .collect::<Vec<_>>();
+
+    if let Some(transfer) = transfer {
+        transfer.set_total(entries.len() as u64);
+    }
+
+    let mut sync_tree
+        = SyncTree::new();
+
+    sync_tree.dry_run = false;
+
+    for entry in &entries {
+        let is_exec
+            = entry.mode & 0o111 != 0;
+
+        if has_build {
+            sync_tree.register_entry(entry.name.clone(), SyncItem::File {
+                data: entry.data.clone(),
+                is_exec,
+            })?;
+
+            if let Some(transfer) = transfer {
+                transfer.advance(1);
+            }
+
+            continue;
+        }
+
+        // Hard links share their permissions, so executable files must be
+        // stored separately from their non-executable counterparts
+        let mut content_key
+            = Sha256::new(&entry.data).to_hex();
+
+        if is_exec {
+            content_key.push_str("-exec");
+        }
+
+        let content_path = content_store_path
+            .with_join_str(&content_key);
+
+        if !content_path.fs_exists() {
+            content_path
+                .fs_create_parent()?
+                .fs_write_atomic(&entry.data)?
+                .fs_set_permissions(Permissions::from_mode(if is_exec {0o755} else {0o644}))?;
+        }
+
+        sync_tree.register_entry(entry.name.clone(), SyncItem::Hardlink {
+            source_path: content_path,
+        })?;
+
+        if let Some(transfer) = transfer {
+            transfer.advance(1);
+        }
+    }
+
+    destination
+        .fs_create_dir_all()?;
+
+    sync_tree.run(destination.clone())?;
+
+    ready_path
+        .fs_write(vec![])?;
+
+    Ok(true)
+}
+
 pub async fn link_project_pnpm<'a>(project: &'a Project, install: &'a Install) -> Result<LinkResult, Error> {
     let tree
         = &install.install_state.resolution_tree;
@@ -53,6 +150,8 @@ pub async fn link_project_pnpm<'a>(project: &'a Project, install: &'a Install) -
         .with_join_str("node_modules");
     let store_path = project.project_cwd
         .with_join_str(&project.config.settings.pnpm_store_folder.value);
+    let content_store_path
+        = project.content_store_path();
 
     // Remove existing node_modules
     linker::helpers::fs_remove_nm(nm_path)?;
@@ -66,6 +165,8 @@ pub async fn link_project_pnpm<'a>(project: &'a Project, install: &'a Install) -
         = Vec::new();
     let mut package_build_entries
         = BTreeMap::new();
+    let mut skipped_builds
+        = BTreeSet::new();
 
     // Get dependencies meta from package.json
     let dependencies_meta
@@ -80,6 +181,24 @@ pub async fn link_project_pnpm<'a>(project: &'a Project, install: &'a Install) -
         let package_base_path = store_path
             .with_join_str(&locator.slug());
 
+        // We don't create node_modules directories and we don't build
+        // local packages that are not fully contained within the project
+        let is_external_package
+            = matches!(physical_package_data, PackageData::Local {package_directory, ..} if !project.project_cwd.contains(package_directory));
+
+        // Handle build requirements (similar to PnP logic)
+        let package_build_info = (!is_external_package).then(|| linker::helpers::get_package_internal_info(
+            project,
+            install,
+            &dependencies_meta,
+            locator,
+            resolution,
+            physical_package_data,
+        ));
+
+        let has_build = package_build_info.as_ref()
+            .is_some_and(|info| info.build_commands.is_some());
+
         let package_location_abs = match &physical_package_data {
             PackageData::Local {..} => {
                 physical_package_data.package_directory().clone()
@@ -89,9 +208,15 @@ pub async fn link_project_pnpm<'a>(project: &'a Project, install: &'a Install) -
                 let package_store_path = package_base_path
                     .with_join(&locator.ident.nm_subdir());
 
-                linker::helpers::fs_extract_archive(
+                let transfer = current_report().await.as_ref()
+                    .map(|report| report.start_transfer(TransferUnit::Files, Some(locator.to_print_string()), None));
+
+                fs_link_archive_from_store(
                     &package_store_path,
                     physical_package_data,
+                    &content_store_path,
+                    has_build,
+                    transfer.as_ref(),
                 )?;
 
                 package_store_path
@@ -111,21 +236,13 @@ pub async fn link_project_pnpm<'a>(project: &'a Project, install: &'a Install) -
             package_location_rel.clone(),
         );
 
-        // We don't create node_modules directories and we don't build
-        // local packages that are not fully contained within the project
-        if matches!(physical_package_data, PackageData::Local {package_directory, ..} if !project.project_cwd.contains(package_directory)) {
+        let Some(package_build_info) = package_build_info else {
             continue;
-        }
+        };
 
-        // Handle build requirements (similar to PnP logic)
-        let package_build_info = linker::helpers::get_package_internal_info(
-            project,
-            install,
-            &dependencies_meta,
-            locator,
-            resolution,
-            physical_package_data,
-        );
+        if package_build_info.skipped_build {
+            skipped_builds.insert(locator.clone());
+        }
 
         if let Some(build_commands) = package_build_info.build_commands {
             package_build_entries.insert(
@@ -312,6 +429,7 @@ pub async fn link_project_pnpm<'a>(project: &'a Project, install: &'a Install) -
     let build_requests = build::BuildRequests {
         entries: all_build_entries,
         dependencies: package_build_dependencies?,
+        skipped_builds,
     };
 
     Ok(LinkResult {
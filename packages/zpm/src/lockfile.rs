@@ -1,17 +1,21 @@
-use std::{collections::BTreeMap, fmt::{self, Debug, Display}, hash::Hash, marker::PhantomData, sync::Arc};
+use std::{collections::{BTreeMap, BTreeSet}, fmt::{self, Debug, Display}, hash::Hash, marker::PhantomData, sync::Arc};
 
 use rkyv::Archive;
 use itertools::Itertools;
-use serde::{de::{self, Visitor}, Deserialize, Deserializer, Serialize, Serializer};
+use serde::{de::{self, Visitor}, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
 use zpm_config::{Configuration, ConfigurationContext};
 use zpm_parsers::JsonDocument;
 use zpm_primitives::{Descriptor, Ident, Locator, Range, Reference, RegistryReference, RegistrySemverRange};
-use zpm_utils::{FromFileString, Hash64, LastModifiedAt, Path, ToFileString, UrlEncoded};
+use zpm_utils::{FromFileString, Hash64, LastModifiedAt, Path, ToFileString, ToHumanString, UrlEncoded};
 
 use crate::{
-    error::Error, http_npm, npm, primitives_exts::RangeExt, resolvers::Resolution
+    cache::CACHE_VERSION, error::Error, http_npm, npm, primitives_exts::RangeExt, resolvers::Resolution
 };
 
+#[cfg(test)]
+#[path = "./lockfile.test.rs"]
+mod lockfile_tests;
+
 const LOCKFILE_VERSION: u64 = 9;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Archive, rkyv::Serialize, rkyv::Deserialize)]
@@ -19,10 +23,41 @@ const LOCKFILE_VERSION: u64 = 9;
 #[rkyv(deserialize_bounds(__D: rkyv::de::Pooling, <__D as rkyv::rancor::Fallible>::Error: rkyv::rancor::Source))]
 #[rkyv(bytecheck(bounds(__C: rkyv::validation::ArchiveContext + rkyv::validation::SharedContext, <__C as rkyv::rancor::Fallible>::Error: rkyv::rancor::Source)))]
 pub struct LockfileEntry {
+    #[serde(default, serialize_with = "serialize_checksum", deserialize_with = "deserialize_checksum")]
     pub checksum: Option<Hash64>,
     pub resolution: Resolution,
 }
 
+/// Checksums are prefixed by the version of the cache whose archives they
+/// were computed from (`2/0123abcd...`). Archives generated by other cache
+/// versions don't have the same content, so their checksums are discarded
+/// when reading the lockfile; they are then computed again from the new
+/// archives instead of being reported as mismatches.
+fn serialize_checksum<S>(checksum: &Option<Hash64>, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    match checksum {
+        Some(checksum) => serializer.serialize_some(&format!("{}/{}", CACHE_VERSION, checksum.to_file_string())),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_checksum<'de, D>(deserializer: D) -> Result<Option<Hash64>, D::Error> where D: Deserializer<'de> {
+    let Some(checksum) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    let Some((cache_key, hash)) = checksum.split_once('/') else {
+        return Ok(None);
+    };
+
+    if cache_key.parse::<usize>().ok() != Some(CACHE_VERSION) {
+        return Ok(None);
+    }
+
+    Hash64::from_file_string(hash)
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[rkyv(serialize_bounds(__S: rkyv::ser::Writer + rkyv::ser::Allocator + rkyv::ser::Sharing, <__S as rkyv::rancor::Fallible>::Error: rkyv::rancor::Source))]
 #[rkyv(deserialize_bounds(__D: rkyv::de::Pooling, <__D as rkyv::rancor::Fallible>::Error: rkyv::rancor::Source))]
@@ -33,6 +68,21 @@ pub struct Lockfile {
     pub entries: BTreeMap<Locator, LockfileEntry>,
 }
 
+#[derive(thiserror::Error, Clone, Debug)]
+pub enum LockfileError {
+    #[error("The lockfile version ({0}) isn't supported by this version of Yarn; upgrade Yarn to install this project")]
+    UnsupportedVersion(u64),
+
+    #[error("{} resolves to {}, but the lockfile has no entry for it; remove the descriptor from the lockfile so it gets resolved again", .0.to_print_string(), .1.to_print_string())]
+    DanglingResolution(Descriptor, Locator),
+
+    #[error("The entry for {} describes {}; remove it from the lockfile so it gets resolved again", .0.to_print_string(), .1.to_print_string())]
+    MismatchedEntry(Locator, Locator),
+
+    #[error("{} depends on {}, but the lockfile doesn't say what it resolves to; remove {} from the lockfile so it gets resolved again", .0.to_print_string(), .1.to_print_string(), .0.to_print_string())]
+    UnresolvedDependency(Locator, Descriptor),
+}
+
 impl Lockfile {
     pub fn new() -> Self {
         Self {
@@ -41,6 +91,110 @@ impl Lockfile {
             entries: BTreeMap::new(),
         }
     }
+
+    /// Check that the lockfile is internally consistent: every resolution
+    /// must point to an existing entry, and the dependencies of each entry
+    /// must themselves be resolved within the lockfile. Hand-edited or badly
+    /// merged lockfiles would otherwise only fail much later, deep inside
+    /// the install.
+    ///
+    /// Dependencies are checked by ident rather than by descriptor, since the
+    /// project's `resolutions` field may remap them to another range before
+    /// they get locked. Those resolved anew at each install (workspaces,
+    /// folders, patches, ...) aren't stored in the lockfile and are ignored.
+    pub fn validate(&self) -> Result<(), Vec<LockfileError>> {
+        let mut errors
+            = Vec::new();
+
+        let supported_version
+            = LockfileMetadata::new().version.max(LOCKFILE_VERSION);
+
+        if self.metadata.version > supported_version {
+            errors.push(LockfileError::UnsupportedVersion(self.metadata.version));
+        }
+
+        for (descriptor, locator) in &self.resolutions {
+            if !self.entries.contains_key(locator) {
+                errors.push(LockfileError::DanglingResolution(descriptor.clone(), locator.clone()));
+            }
+        }
+
+        for (locator, entry) in &self.entries {
+            if entry.resolution.locator != *locator {
+                errors.push(LockfileError::MismatchedEntry(locator.clone(), entry.resolution.locator.clone()));
+            }
+        }
+
+        let locked_idents: BTreeSet<&Ident>
+            = self.resolutions.keys()
+                .map(|descriptor| &descriptor.ident)
+                .collect();
+
+        for entry in self.entries.values() {
+            for descriptor in entry.resolution.dependencies.values() {
+                let range_details
+                    = descriptor.range.details();
+
+                if range_details.transient_resolution || range_details.require_binding {
+                    continue;
+                }
+
+                let is_locked_range = matches!(
+                    descriptor.range,
+                    Range::AnonymousSemver(_) | Range::AnonymousTag(_) | Range::RegistrySemver(_) | Range::RegistryTag(_) | Range::Git(_) | Range::Url(_)
+                );
+
+                if !is_locked_range || locked_idents.contains(&descriptor.ident) {
+                    continue;
+                }
+
+                errors.push(LockfileError::UnresolvedDependency(entry.resolution.locator.clone(), descriptor.clone()));
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Merge two lockfiles into one holding the entries of both. When they
+    /// lock the same descriptor to different packages, the one with the
+    /// highest version wins (or `self`'s, if they're equal). Entries that
+    /// end up unreferenced are dropped.
+    pub fn merge(mut self, other: Lockfile) -> Lockfile {
+        self.metadata.version
+            = self.metadata.version.min(other.metadata.version);
+
+        for (locator, entry) in other.entries {
+            self.entries.entry(locator).or_insert(entry);
+        }
+
+        for (descriptor, locator) in other.resolutions {
+            let Some(current_locator) = self.resolutions.get(&descriptor) else {
+                self.resolutions.insert(descriptor, locator);
+                continue;
+            };
+
+            let current_version = self.entries.get(current_locator)
+                .map(|entry| &entry.resolution.version);
+            let other_version = self.entries.get(&locator)
+                .map(|entry| &entry.resolution.version);
+
+            if other_version > current_version {
+                self.resolutions.insert(descriptor, locator);
+            }
+        }
+
+        let referenced_locators: BTreeSet<Locator>
+            = self.resolutions.values()
+                .cloned()
+                .collect();
+
+        self.entries.retain(|locator, _| referenced_locators.contains(locator));
+
+        self
+    }
 }
 
 impl<'de> Deserialize<'de> for Lockfile {
@@ -65,13 +219,8 @@ impl<'de> Deserialize<'de> for Lockfile {
 
 impl Serialize for Lockfile {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-        struct MultiKeyLockfileEntry {
-            key: MultiKey<Descriptor>,
-            inner: LockfileEntry,
-        }
-
-        let mut descriptors_to_resolutions: BTreeMap<Locator, MultiKeyLockfileEntry> = BTreeMap::new();
-        for (descriptor, locator) in self.resolutions.iter().sorted_by_key(|(descriptor, _)| (*descriptor).clone()) {
+        let mut descriptors_by_locator: BTreeMap<&Locator, (&LockfileEntry, Vec<&Descriptor>)> = BTreeMap::new();
+        for (descriptor, locator) in self.resolutions.iter() {
             // Skip descriptors with transient_resolution set to true
             if descriptor.range.details().transient_resolution {
                 continue;
@@ -80,26 +229,59 @@ impl Serialize for Lockfile {
             let entry = self.entries.get(locator)
                 .expect("Expected a matching resolution to be found in the lockfile for any resolved locator.");
 
-            descriptors_to_resolutions.entry(entry.resolution.locator.clone())
-                .or_insert_with(|| MultiKeyLockfileEntry {inner: entry.clone(), key: MultiKey::new()})
-                .key.0
-                .push(descriptor.clone());
+            descriptors_by_locator.entry(&entry.resolution.locator)
+                .or_insert_with(|| (entry, vec![]))
+                .1
+                .push(descriptor);
         }
 
-        let mut entries = BTreeMap::new();
-        for entry in descriptors_to_resolutions.into_values() {
-            entries.insert(entry.key, entry.inner);
-        }
-
-        let payload = LockfilePayload {
-            metadata: self.metadata.clone(),
-            entries,
+        // The serialized lockfile must only depend on its content, not on the
+        // order in which the resolutions were recorded, so that adding or
+        // removing a single package only touches the lines of that package.
+        // Entries are sorted by their locator (ident first, then reference),
+        // and the descriptors merged into a single entry are sorted and
+        // deduplicated by their serialized form.
+        let entries = descriptors_by_locator.into_iter()
+            .map(|(locator, (entry, descriptors))| {
+                let key = descriptors.into_iter()
+                    .map(|descriptor| descriptor.to_file_string())
+                    .sorted()
+                    .dedup()
+                    .join(", ");
+
+                let sort_key
+                    = (locator.ident.as_str(), locator.reference.to_file_string());
+
+                (sort_key, key, entry)
+            })
+            .sorted_by(|(a, _, _), (b, _, _)| a.cmp(b))
+            .map(|(_, key, entry)| (key, entry))
+            .collect_vec();
+
+        let payload = SerializedLockfilePayload {
+            metadata: &self.metadata,
+            entries: OrderedEntries(entries),
         };
 
         payload.serialize(serializer)
     }
 }
 
+struct OrderedEntries<'a>(Vec<(String, &'a LockfileEntry)>);
+
+impl Serialize for OrderedEntries<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut map
+            = serializer.serialize_map(Some(self.0.len()))?;
+
+        for (key, entry) in &self.0 {
+            map.serialize_entry(key, entry)?;
+        }
+
+        map.end()
+    }
+}
+
 #[derive(Clone, Debug)]
 struct TolerantMap<K, V>(BTreeMap<K, V>);
 
@@ -146,28 +328,6 @@ impl<'de, K, V> Deserialize<'de> for TolerantMap<K, V> where K: Debug + Eq + Ord
 #[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
 struct MultiKey<T>(Vec<T>);
 
-impl<T> MultiKey<T> {
-    fn new() -> Self {
-        MultiKey(vec![])
-    }
-}
-
-impl<T> Serialize for MultiKey<T> where T: ToFileString {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-        let mut string = String::new();
-
-        for (index, item) in self.0.iter().enumerate() {
-            if index > 0 {
-                string.push_str(", ");
-            }
-
-            string.push_str(&item.to_file_string());
-        }
-
-        serializer.serialize_str(&string)
-    }
-}
-
 impl<'de, T: FromFileString> Deserialize<'de> for MultiKey<T> where <T as FromFileString>::Error: Display {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
         struct VecVisitor<T> {
@@ -225,7 +385,7 @@ impl Default for LockfileMetadata {
     }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize)]
 struct LockfilePayload {
     #[serde(rename = "__metadata")]
     #[serde(default)]
@@ -235,6 +395,14 @@ struct LockfilePayload {
     entries: BTreeMap<MultiKey<Descriptor>, LockfileEntry>,
 }
 
+#[derive(Serialize)]
+struct SerializedLockfilePayload<'a> {
+    #[serde(rename = "__metadata")]
+    metadata: &'a LockfileMetadata,
+
+    entries: OrderedEntries<'a>,
+}
+
 #[derive(Debug, Deserialize)]
 struct LegacyBerryLockfileEntry {
     resolution: Locator,
@@ -304,6 +472,79 @@ pub fn from_legacy_berry_lockfile(data: &str) -> Result<Lockfile, Error> {
     Ok(lockfile)
 }
 
+/// Split a lockfile containing Git conflict markers into the two versions it
+/// was made from. Lines outside of the conflicts are shared by both sides;
+/// the base section of diff3-style conflicts is discarded.
+fn split_conflicted_lockfile(data: &str) -> (String, String) {
+    enum Section {
+        Both,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut ours
+        = String::new();
+    let mut theirs
+        = String::new();
+
+    let mut section
+        = Section::Both;
+
+    for line in data.split_inclusive('\n') {
+        section = match (section, line) {
+            (Section::Both, line) if line.starts_with("<<<<<<<") => Section::Ours,
+            (Section::Ours, line) if line.starts_with("|||||||") => Section::Base,
+            (Section::Ours | Section::Base, line) if line.starts_with("=======") => Section::Theirs,
+            (Section::Theirs, line) if line.starts_with(">>>>>>>") => Section::Both,
+
+            (Section::Both, line) => {
+                ours.push_str(line);
+                theirs.push_str(line);
+                Section::Both
+            },
+
+            (Section::Ours, line) => {
+                ours.push_str(line);
+                Section::Ours
+            },
+
+            (Section::Base, _) => Section::Base,
+
+            (Section::Theirs, line) => {
+                theirs.push_str(line);
+                Section::Theirs
+            },
+        };
+    }
+
+    (ours, theirs)
+}
+
+/// Parse a lockfile containing Git conflict markers by parsing both sides
+/// of the conflicts independently, then merging them (see `Lockfile::merge`).
+/// If only one side can be parsed (for instance because the other is a Yarn
+/// Classic lockfile), it's used as-is.
+pub fn from_conflicted_lockfile(data: &str) -> Result<Lockfile, Error> {
+    let parse_side = |side: &str| -> Result<Lockfile, Error> {
+        if side.starts_with('#') {
+            return from_legacy_berry_lockfile(side);
+        }
+
+        JsonDocument::hydrate_from_str(side)
+            .map_err(Error::LockfileParseError)
+    };
+
+    let (ours, theirs)
+        = split_conflicted_lockfile(data);
+
+    match (parse_side(&ours), parse_side(&theirs)) {
+        (Ok(ours), Ok(theirs)) => Ok(ours.merge(theirs)),
+        (Ok(lockfile), Err(_)) | (Err(_), Ok(lockfile)) => Ok(lockfile),
+        (Err(err), Err(_)) => Err(err),
+    }
+}
+
 /// Dependency entry from pnpm list --json output
 #[derive(Debug, Deserialize, Clone)]
 struct PnpmListDependency {
@@ -0,0 +1,334 @@
+use zpm_utils::diff_data;
+
+use super::*;
+
+fn add_package(lockfile: &mut Lockfile, descriptors: &[&str], locator: &str, version: &str) {
+    let locator
+        = Locator::from_file_string(locator).unwrap();
+    let version
+        = zpm_semver::Version::from_file_string(version).unwrap();
+
+    lockfile.entries.insert(locator.clone(), LockfileEntry {
+        checksum: None,
+        resolution: Resolution::new_empty(locator.clone(), version),
+    });
+
+    for descriptor in descriptors {
+        lockfile.resolutions.insert(Descriptor::from_file_string(descriptor).unwrap(), locator.clone());
+    }
+}
+
+fn remove_package(lockfile: &mut Lockfile, locator: &str) {
+    let locator
+        = Locator::from_file_string(locator).unwrap();
+
+    lockfile.entries.remove(&locator);
+    lockfile.resolutions.retain(|_, resolved| *resolved != locator);
+}
+
+fn fixture() -> Lockfile {
+    let mut lockfile
+        = Lockfile::new();
+
+    add_package(&mut lockfile, &["e-pkg@npm:^2.0.0"], "e-pkg@npm:2.0.0", "2.0.0");
+    add_package(&mut lockfile, &["c-pkg@npm:^1.0.0"], "c-pkg@npm:1.0.0", "1.0.0");
+    add_package(&mut lockfile, &["a-pkg@npm:^1.0.1", "a-pkg@npm:^1.0.0"], "a-pkg@npm:1.2.0", "1.2.0");
+
+    lockfile
+}
+
+fn changed_lines(before: &Lockfile, after: &Lockfile) -> Vec<String> {
+    let before
+        = JsonDocument::to_string_pretty(before).unwrap();
+    let after
+        = JsonDocument::to_string_pretty(after).unwrap();
+
+    diff_data(before.as_bytes(), after.as_bytes())
+        .lines()
+        .filter(|line| !line.starts_with("+++") && !line.starts_with("---"))
+        .filter(|line| line.starts_with('+') || line.starts_with('-'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[test]
+fn serialization_is_independent_from_insertion_order() {
+    let mut reversed
+        = Lockfile::new();
+
+    add_package(&mut reversed, &["a-pkg@npm:^1.0.0", "a-pkg@npm:^1.0.1"], "a-pkg@npm:1.2.0", "1.2.0");
+    add_package(&mut reversed, &["c-pkg@npm:^1.0.0"], "c-pkg@npm:1.0.0", "1.0.0");
+    add_package(&mut reversed, &["e-pkg@npm:^2.0.0"], "e-pkg@npm:2.0.0", "2.0.0");
+
+    let serialized
+        = JsonDocument::to_string_pretty(&fixture()).unwrap();
+
+    assert_eq!(serialized, JsonDocument::to_string_pretty(&reversed).unwrap());
+    assert!(serialized.contains("\"a-pkg@npm:^1.0.0, a-pkg@npm:^1.0.1\""));
+
+    let a_pos = serialized.find("a-pkg@npm:^1.0.0").unwrap();
+    let c_pos = serialized.find("c-pkg@npm:^1.0.0").unwrap();
+    let e_pos = serialized.find("e-pkg@npm:^2.0.0").unwrap();
+
+    assert!(a_pos < c_pos && c_pos < e_pos);
+}
+
+#[test]
+fn serialization_roundtrips() {
+    let lockfile
+        = fixture();
+
+    let serialized
+        = JsonDocument::to_string_pretty(&lockfile).unwrap();
+
+    let parsed: Lockfile
+        = JsonDocument::hydrate_from_str(&serialized).unwrap();
+
+    assert_eq!(parsed, lockfile);
+    assert_eq!(JsonDocument::to_string_pretty(&parsed).unwrap(), serialized);
+}
+
+#[test]
+fn adding_a_package_only_adds_its_lines() {
+    let before
+        = fixture();
+
+    let mut after
+        = before.clone();
+
+    add_package(&mut after, &["b-pkg@npm:^1.0.0"], "b-pkg@npm:1.0.0", "1.0.0");
+
+    let changes
+        = changed_lines(&before, &after);
+
+    assert!(!changes.is_empty());
+    assert!(changes.iter().all(|line| line.starts_with('+')), "{changes:#?}");
+    assert!(changes.iter().any(|line| line.contains("b-pkg@npm:1.0.0")), "{changes:#?}");
+    assert!(!changes.iter().any(|line| line.contains("a-pkg") || line.contains("c-pkg") || line.contains("e-pkg")), "{changes:#?}");
+}
+
+#[test]
+fn removing_a_package_only_removes_its_lines() {
+    let before
+        = fixture();
+
+    let mut after
+        = before.clone();
+
+    remove_package(&mut after, "c-pkg@npm:1.0.0");
+
+    let changes
+        = changed_lines(&before, &after);
+
+    assert!(!changes.is_empty());
+    assert!(changes.iter().all(|line| line.starts_with('-')), "{changes:#?}");
+    assert!(!changes.iter().any(|line| line.contains("a-pkg") || line.contains("e-pkg")), "{changes:#?}");
+}
+
+#[test]
+fn upgrading_a_package_only_touches_its_lines() {
+    let before
+        = fixture();
+
+    let mut after
+        = before.clone();
+
+    remove_package(&mut after, "c-pkg@npm:1.0.0");
+    add_package(&mut after, &["c-pkg@npm:^1.0.0"], "c-pkg@npm:1.1.0", "1.1.0");
+
+    let changes
+        = changed_lines(&before, &after);
+
+    assert!(!changes.is_empty());
+    assert!(changes.iter().all(|line| line.contains("c-pkg") || line.contains("\"version\"")), "{changes:#?}");
+}
+
+#[test]
+fn validation_accepts_consistent_lockfiles() {
+    let mut lockfile
+        = fixture();
+
+    let a_pkg
+        = Locator::from_file_string("a-pkg@npm:1.2.0").unwrap();
+
+    lockfile.entries.get_mut(&a_pkg).unwrap().resolution.dependencies.insert(
+        Ident::new("c-pkg"),
+        Descriptor::from_file_string("c-pkg@^1.0.0").unwrap(),
+    );
+
+    assert!(lockfile.validate().is_ok());
+}
+
+#[test]
+fn validation_reports_dangling_resolutions() {
+    let mut lockfile
+        = fixture();
+
+    lockfile.resolutions.insert(
+        Descriptor::from_file_string("b-pkg@npm:^1.0.0").unwrap(),
+        Locator::from_file_string("b-pkg@npm:1.0.0").unwrap(),
+    );
+
+    let errors
+        = lockfile.validate().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0], LockfileError::DanglingResolution(descriptor, locator) if descriptor.to_file_string() == "b-pkg@npm:^1.0.0" && locator.to_file_string() == "b-pkg@npm:1.0.0"), "{errors:#?}");
+}
+
+#[test]
+fn validation_reports_unresolved_dependencies() {
+    let mut lockfile
+        = fixture();
+
+    let a_pkg
+        = Locator::from_file_string("a-pkg@npm:1.2.0").unwrap();
+
+    lockfile.entries.get_mut(&a_pkg).unwrap().resolution.dependencies.insert(
+        Ident::new("b-pkg"),
+        Descriptor::from_file_string("b-pkg@^1.0.0").unwrap(),
+    );
+
+    let errors
+        = lockfile.validate().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0], LockfileError::UnresolvedDependency(locator, _) if *locator == a_pkg), "{errors:#?}");
+}
+
+#[test]
+fn validation_reports_unsupported_versions() {
+    let mut lockfile
+        = fixture();
+
+    lockfile.metadata.version = LOCKFILE_VERSION + 1;
+
+    let errors
+        = lockfile.validate().unwrap_err();
+
+    assert!(matches!(errors[..], [LockfileError::UnsupportedVersion(version)] if version == LOCKFILE_VERSION + 1), "{errors:#?}");
+}
+
+#[test]
+fn conflicted_lockfiles_are_split_into_both_sides() {
+    let conflicted = [
+        "{",
+        "<<<<<<< HEAD",
+        "  \"a\": 1,",
+        "||||||| base",
+        "  \"a\": 0,",
+        "=======",
+        "  \"a\": 2,",
+        ">>>>>>> feature",
+        "  \"b\": 3",
+        "}",
+        "",
+    ].join("\n");
+
+    let (ours, theirs)
+        = split_conflicted_lockfile(&conflicted);
+
+    assert_eq!(ours, "{\n  \"a\": 1,\n  \"b\": 3\n}\n");
+    assert_eq!(theirs, "{\n  \"a\": 2,\n  \"b\": 3\n}\n");
+}
+
+#[test]
+fn conflicted_lockfiles_are_merged() {
+    let mut ours
+        = fixture();
+
+    remove_package(&mut ours, "c-pkg@npm:1.0.0");
+    add_package(&mut ours, &["c-pkg@npm:^1.0.0"], "c-pkg@npm:1.1.0", "1.1.0");
+
+    let mut theirs
+        = fixture();
+
+    add_package(&mut theirs, &["d-pkg@npm:^3.0.0"], "d-pkg@npm:3.0.0", "3.0.0");
+
+    let conflicted = format!(
+        "<<<<<<< HEAD\n{}\n=======\n{}\n>>>>>>> feature\n",
+        JsonDocument::to_string_pretty(&ours).unwrap(),
+        JsonDocument::to_string_pretty(&theirs).unwrap(),
+    );
+
+    let merged
+        = from_conflicted_lockfile(&conflicted).unwrap();
+
+    let mut expected
+        = fixture();
+
+    remove_package(&mut expected, "c-pkg@npm:1.0.0");
+    add_package(&mut expected, &["c-pkg@npm:^1.0.0"], "c-pkg@npm:1.1.0", "1.1.0");
+    add_package(&mut expected, &["d-pkg@npm:^3.0.0"], "d-pkg@npm:3.0.0", "3.0.0");
+
+    let serialized
+        = JsonDocument::to_string_pretty(&merged).unwrap();
+
+    assert!(!serialized.contains("<<<<<<<") && !serialized.contains(">>>>>>>"));
+    assert_eq!(serialized, JsonDocument::to_string_pretty(&expected).unwrap());
+}
+
+#[test]
+fn merging_lockfiles_prefers_the_highest_version() {
+    let mut older
+        = fixture();
+
+    remove_package(&mut older, "a-pkg@npm:1.2.0");
+    add_package(&mut older, &["a-pkg@npm:^1.0.1", "a-pkg@npm:^1.0.0"], "a-pkg@npm:1.1.0", "1.1.0");
+
+    let newer
+        = fixture();
+
+    let expected
+        = JsonDocument::to_string_pretty(&newer).unwrap();
+
+    assert_eq!(JsonDocument::to_string_pretty(&older.clone().merge(newer.clone())).unwrap(), expected);
+    assert_eq!(JsonDocument::to_string_pretty(&newer.merge(older)).unwrap(), expected);
+}
+
+#[test]
+fn checksums_are_prefixed_by_the_cache_version() {
+    let mut lockfile
+        = fixture();
+
+    let checksum
+        = Hash64::from_data(b"archive");
+
+    for entry in lockfile.entries.values_mut() {
+        entry.checksum = Some(checksum.clone());
+    }
+
+    let serialized
+        = JsonDocument::to_string_pretty(&lockfile).unwrap();
+
+    assert!(serialized.contains(&format!("\"{}/{}\"", CACHE_VERSION, checksum.to_file_string())), "{serialized}");
+    assert_eq!(JsonDocument::hydrate_from_str::<Lockfile>(&serialized).unwrap(), lockfile);
+}
+
+#[test]
+fn checksums_from_other_cache_versions_are_discarded() {
+    let mut lockfile
+        = fixture();
+
+    for entry in lockfile.entries.values_mut() {
+        entry.checksum = Some(Hash64::from_data(b"archive"));
+    }
+
+    let serialized
+        = JsonDocument::to_string_pretty(&lockfile).unwrap();
+
+    let checksum
+        = Hash64::from_data(b"archive").to_file_string();
+
+    let outdated
+        = serialized.replace(&format!("{}/{}", CACHE_VERSION, checksum), &format!("{}/{}", CACHE_VERSION + 1, checksum));
+    let unprefixed
+        = serialized.replace(&format!("{}/{}", CACHE_VERSION, checksum), &checksum);
+
+    for data in [outdated, unprefixed] {
+        let parsed: Lockfile
+            = JsonDocument::hydrate_from_str(&data).unwrap();
+
+        assert!(parsed.entries.values().all(|entry| entry.checksum.is_none()), "{data}");
+    }
+}
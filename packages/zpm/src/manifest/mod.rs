@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 
 use browser::BrowserField;
 use rkyv::Archive;
-use serde_with::{serde_as, DefaultOnError};
+use serde_with::{serde_as, DefaultOnError, MapSkipError};
 use zpm_parsers::{Document, Value};
 use zpm_primitives::{Descriptor, Ident, PeerRange, descriptor_map_deserializer, descriptor_map_serializer};
 use zpm_switch::PackageManagerField;
@@ -123,6 +123,7 @@ pub struct PublishConfig {
     pub provenance: Option<bool>,
 }
 
+#[serde_as]
 #[derive(Clone, Debug, Default, Deserialize, Serialize, Archive, rkyv::Serialize, rkyv::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Manifest {
@@ -178,6 +179,17 @@ pub struct Manifest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub files: Option<Vec<String>>,
 
+    /// Dependencies whose installed files are included in the packed archive
+    #[serde(default, alias = "bundleDependencies")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    pub bundled_dependencies: Option<Vec<Ident>>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde_as(deserialize_as = "MapSkipError<_, _>")]
+    pub engines: BTreeMap<String, zpm_semver::Range>,
+
     #[serde(flatten)]
     pub remote: RemoteManifest,
 
@@ -2,7 +2,7 @@ use rkyv::Archive;
 use serde::{Deserialize, Deserializer};
 use zpm_macro_enum::zpm_enum;
 use zpm_primitives::{Descriptor, Ident, Locator, Range, RegistrySemverRange};
-use zpm_utils::{FromFileString, ToFileString};
+use zpm_utils::{FromFileString, ToFileString, ToHumanString};
 
 use crate::{
     error::Error,
@@ -42,6 +42,14 @@ pub enum ResolutionSelector {
         parent_ident: Ident,
         ident: Ident,
     },
+
+    #[pattern(r"^(?<parent_path>(?:@[^/*]+/)?[^/*@][^/*]*(?:/(?:@[^/*]+/)?[^/*@][^/*]*)+)/(?<ident>(?:@[^/*]+/)?[^/*@][^/*]*)$")]
+    #[to_file_string(|params| format!("{}/{}", params.parent_path.to_file_string(), params.ident.to_file_string()))]
+    #[to_print_string(|params| format!("{}/{}", params.parent_path.to_print_string(), params.ident.to_print_string()))]
+    PathIdent {
+        parent_path: ResolutionPath,
+        ident: Ident,
+    },
 }
 
 impl ResolutionSelector {
@@ -51,6 +59,21 @@ impl ResolutionSelector {
             ResolutionSelector::Ident(params) => &params.ident,
             ResolutionSelector::DescriptorIdent(params) => &params.ident,
             ResolutionSelector::IdentIdent(params) => &params.ident,
+            ResolutionSelector::PathIdent(params) => &params.ident,
+        }
+    }
+
+    /// Selectors scoped to a dependency path are more specific than those
+    /// scoped to a parent package, which are more specific than those
+    /// matching a descriptor, which are themselves more specific than those
+    /// matching any descriptor of an ident.
+    pub fn specificity(&self) -> u8 {
+        match self {
+            ResolutionSelector::Ident(_) => 0,
+            ResolutionSelector::Descriptor(_) => 1,
+            ResolutionSelector::IdentIdent(_) => 2,
+            ResolutionSelector::DescriptorIdent(_) => 2,
+            ResolutionSelector::PathIdent(_) => 3,
         }
     }
 
@@ -99,11 +122,68 @@ impl ResolutionSelector {
 
                 Some(replacement_range.clone())
             },
+
+            // Packages are shared between all their dependents, so overrides
+            // scoped to a dependency path can't be applied to the package
+            // itself; the tree resolver applies them on the matching branches
+            ResolutionSelector::PathIdent(_) => {
+                None
+            },
         }
     }
 }
 
+/// The chain of packages through which the target of a nested override
+/// (`a/b/c`) must be reached, from the outermost package to its parent.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[rkyv(derive(PartialEq, Eq, PartialOrd, Ord, Hash))]
+pub struct ResolutionPath {
+    pub idents: Vec<Ident>,
+}
+
+impl FromFileString for ResolutionPath {
+    type Error = Error;
+
+    fn from_file_string(src: &str) -> Result<Self, Self::Error> {
+        let mut idents
+            = vec![];
+
+        let mut segments
+            = src.split('/');
+
+        while let Some(segment) = segments.next() {
+            let ident_str = match segment.starts_with('@') {
+                true => format!("{}/{}", segment, segments.next().ok_or_else(|| Error::InvalidResolution(src.to_string()))?),
+                false => segment.to_string(),
+            };
 
+            let ident = Ident::from_file_string(&ident_str)
+                .map_err(|_| Error::InvalidResolution(src.to_string()))?;
+
+            idents.push(ident);
+        }
+
+        Ok(ResolutionPath {idents})
+    }
+}
+
+impl ToFileString for ResolutionPath {
+    fn to_file_string(&self) -> String {
+        self.idents.iter()
+            .map(|ident| ident.to_file_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+impl ToHumanString for ResolutionPath {
+    fn to_print_string(&self) -> String {
+        self.idents.iter()
+            .map(|ident| ident.to_print_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
 
 use serde::{ser::SerializeMap, Serialize, Serializer};
 use serde::de::{self, Visitor, MapAccess};
@@ -141,10 +221,19 @@ impl ResolutionsField {
             = selector.target_ident();
 
         self.entries.push((selector.clone(), range.clone()));
-        self.by_ident
+
+        // The most specific overrides take precedence regardless of their
+        // position in the manifest, so a global override doesn't shadow an
+        // override scoped to a specific parent package
+        let candidates = self.by_ident
             .entry(target_ident.clone())
-            .or_default()
-            .push((selector, range));
+            .or_default();
+
+        let position = candidates.iter()
+            .position(|(candidate, _)| candidate.specificity() < selector.specificity())
+            .unwrap_or(candidates.len());
+
+        candidates.insert(position, (selector, range));
     }
 }
 
@@ -204,6 +293,7 @@ impl<'de> Visitor<'de> for ResolutionsFieldVisitor {
                 | ResolutionSelector::DescriptorIdent(DescriptorIdentResolutionSelector {parent_descriptor: Descriptor {range: Range::AnonymousSemver(_), ..}, ..})
                 | ResolutionSelector::Ident(_)
                 | ResolutionSelector::IdentIdent(_)
+                | ResolutionSelector::PathIdent(_)
             );
 
             if !is_valid_resolution_descriptor {
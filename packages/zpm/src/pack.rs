@@ -4,11 +4,13 @@ use std::collections::HashSet;
 use std::str::FromStr;
 use std::sync::LazyLock;
 
+use zpm_formats::Entry;
 use zpm_formats::iter_ext::IterExt;
 use zpm_formats::tar::ToTar;
 use zpm_parsers::{Document, JsonDocument, Value};
 use zpm_primitives::AnonymousSemverRange;
 use zpm_primitives::Descriptor;
+use zpm_primitives::Ident;
 use zpm_primitives::Locator;
 use zpm_primitives::PeerRange;
 use zpm_primitives::Range;
@@ -22,12 +24,17 @@ use crate::resolvers::catalog::lookup_catalog_entry;
 use crate::script::ScriptEnvironment;
 use crate::{
     error::Error,
+    install::InstallState,
     manifest::helpers::parse_manifest,
     manifest::Manifest,
     project::Project,
     project::Workspace,
 };
 
+#[cfg(test)]
+#[path = "./pack.test.rs"]
+mod pack_tests;
+
 #[derive(Default)]
 struct IgnoreFiles {
     pub gitignore: bool,
@@ -96,15 +103,25 @@ impl PackIgnore {
         let is_positive =
             !prefix.contains('!');
 
+        // A trailing slash restricts the pattern to directories, but doesn't
+        // anchor it to the ignore file's directory like other slashes do.
+        let (body, is_directory_only) = match captures[3].strip_suffix('/') {
+            Some(body) => (body, true),
+            None => (&captures[3], false),
+        };
+
         let is_rooted =
-            captures.get(2).is_some() || pattern.contains('/');
+            captures.get(2).is_some() || body.contains('/');
 
         let mut rooted_path = match is_rooted {
-            true => from_dir.with_join_str(&captures[3]),
-            false => from_dir.with_join_str("**").with_join_str(&captures[3]),
+            true => from_dir.with_join_str(body),
+            false => from_dir.with_join_str("**").with_join_str(body),
         };
 
-        self.push(&rooted_path.as_str(), is_positive);
+        if !is_directory_only {
+            self.push(&rooted_path.as_str(), is_positive);
+        }
+
         rooted_path.join_str("**");
         self.push(&rooted_path.as_str(), is_positive);
 
@@ -202,7 +219,9 @@ impl PackList {
         Ok(())
     }
 
-    pub fn load_ignore(&self) -> Result<Vec<String>, Error> {
+    /// Returns the patterns from the ignore files, along with the directory
+    /// they're relative to.
+    pub fn load_ignore(&self) -> Result<Vec<(Path, String)>, Error> {
         let mut patterns = vec![];
 
         for (path, ignore_files) in &self.ignore_files {
@@ -226,7 +245,7 @@ impl PackList {
                     .split('\n')
                     .map(|line| line.trim())
                     .filter(|line| !line.is_empty() && !line.starts_with('#'))
-                    .map(|line| line.to_string())
+                    .map(|line| (path.clone(), line.to_string()))
                     .collect::<Vec<_>>();
 
                 patterns.extend(ignore_list);
@@ -268,10 +287,11 @@ pub struct PackOptions {
 }
 
 // TODO: That doesn't seem a great API; feels like `find_package_script` should return a `Script` struct instead with a `run` method.
-async fn run_script(project: &Project, locator: &Locator, script: &str) -> Result<(), Error> {
+async fn run_script(project: &Project, locator: &Locator, event: &str, script: &str) -> Result<(), Error> {
     ScriptEnvironment::new()?
         .with_project(&project)
         .with_package(&project, &locator)?
+        .with_lifecycle_event(event, script)
         .run_script(&script, &Vec::<&str>::new())
         .await?
         .ok()?;
@@ -288,19 +308,22 @@ pub async fn pack_workspace(project: &mut Project, pack_locator: &Locator, optio
         = project.find_package_script(pack_locator, "postpack")
             .map(Some).or_else(|e| e.ignore(|e| matches!(e, Error::ScriptNotFound(_))))?;
 
-    if prepack_script.is_some() || postpack_script.is_some() {
+    let has_bundled_dependencies
+        = project.workspace_by_locator(pack_locator)?.manifest.bundled_dependencies.is_some();
+
+    if prepack_script.is_some() || postpack_script.is_some() || has_bundled_dependencies {
         project.lazy_install().await?;
     }
 
     if let Some((locator, script)) = prepack_script {
-        run_script(&project, &locator, &script).await?;
+        run_script(&project, &locator, "prepack", &script).await?;
     }
 
     let gen_result
         = gen_archive(&project, pack_locator, options).await?;
 
     if let Some((locator, script)) = postpack_script {
-        run_script(&project, &locator, &script).await?;
+        run_script(&project, &locator, "postpack", &script).await?;
     }
 
     Ok(gen_result)
@@ -316,30 +339,19 @@ async fn gen_archive(project: &Project, pack_locator: &Locator, options: &PackOp
     let pack_manifest
         = parse_manifest(&pack_manifest_content)?;
 
-    let pack_list
+    let mut pack_list
         = pack_list(&project, active_workspace, &pack_manifest)?;
 
     let mut entries
         = zpm_formats::entries_from_files(&active_workspace.path, &pack_list)?;
 
-    let mut executable_files
-        = pack_manifest.publish_config.executable_files
-            .clone()
-            .unwrap_or_default()
-            .into_iter()
-            .collect::<BTreeSet<_>>();
+    apply_executable_modes(&mut entries, &pack_manifest);
 
-    if let Some(bin) = &pack_manifest.bin {
-        executable_files.extend(bin.paths().cloned());
-    }
+    let bundled_entries
+        = bundled_dependency_entries(project, active_workspace, &pack_manifest)?;
 
-    for entry in entries.iter_mut() {
-        if executable_files.contains(&entry.name) {
-            entry.mode = 0o755;
-        } else {
-            entry.mode = 0o644;
-        }
-    }
+    pack_list.extend(bundled_entries.iter().map(|entry| entry.name.clone()));
+    entries.extend(bundled_entries);
 
     let manifest_entry = entries
         .iter_mut()
@@ -363,6 +375,157 @@ async fn gen_archive(project: &Project, pack_locator: &Locator, options: &PackOp
     })
 }
 
+/// Lists the files of the dependencies listed in `bundledDependencies`, as
+/// they're currently installed, so that they get shipped along with the
+/// package under its `node_modules` folder (along with their own
+/// dependencies).
+fn bundled_dependency_entries<'a>(project: &Project, workspace: &Workspace, manifest: &Manifest) -> Result<Vec<Entry<'a>>, Error> {
+    let Some(bundled_dependencies) = &manifest.bundled_dependencies else {
+        return Ok(vec![]);
+    };
+
+    let install_state = project.install_state.as_ref()
+        .ok_or(Error::InstallStateNotFound)?;
+
+    let workspace_resolution
+        = install_state.resolution_tree.locator_resolutions.get(&workspace.locator());
+
+    let mut bundled_packages
+        = BTreeMap::new();
+
+    for ident in bundled_dependencies {
+        let is_dependency
+            = manifest.remote.dependencies.contains_key(ident) || manifest.remote.optional_dependencies.contains_key(ident);
+
+        if !is_dependency {
+            return Err(Error::BundledDependencyNotFound(ident.clone()));
+        }
+
+        let locator = workspace_resolution
+            .and_then(|resolution| resolution.dependencies.get(ident))
+            .and_then(|descriptor| install_state.resolution_tree.descriptor_to_locator.get(descriptor))
+            .ok_or_else(|| Error::BundledDependencyNotInstalled(ident.clone()))?;
+
+        bundled_packages.insert(ident.clone(), locator.clone());
+    }
+
+    let mut entries
+        = vec![];
+
+    add_bundled_packages(project, install_state, &Path::new(), &bundled_packages, &mut vec![], &mut vec![], &mut entries)?;
+
+    Ok(entries)
+}
+
+/// Adds the files of the given packages into the `node_modules` folder at
+/// `base_path`, then recursively does the same for their dependencies within
+/// their own folders. Dependencies that Node would already find in a parent
+/// `node_modules` folder (because the same package got bundled there) are
+/// skipped.
+///
+/// The `levels` are the packages of the `node_modules` folders visible from
+/// `base_path`, and the `parents` are the packages `base_path` is nested in.
+fn add_bundled_packages<'a>(project: &Project, install_state: &InstallState, base_path: &Path, packages: &BTreeMap<Ident, Locator>, levels: &mut Vec<BTreeMap<Ident, Locator>>, parents: &mut Vec<Locator>, entries: &mut Vec<Entry<'a>>) -> Result<(), Error> {
+    levels.push(packages.clone());
+
+    for (ident, locator) in packages {
+        let package_location = install_state.locations_by_package.get(locator)
+            .ok_or_else(|| Error::BundledDependencyNotInstalled(ident.clone()))?;
+
+        let resolution = install_state.resolution_tree.locator_resolutions.get(locator)
+            .ok_or_else(|| Error::BundledDependencyNotInstalled(ident.clone()))?;
+
+        let package_path
+            = base_path.with_join_str(format!("node_modules/{}", ident.to_file_string()));
+
+        // The folder may contain a `node_modules` folder of its own (as with
+        // the node-modules linker); we instead add the dependencies based on
+        // the resolution tree below
+        let package_entries
+            = zpm_formats::zip::entries_from_folder_with_zip(&project.project_cwd.with_join(package_location))?
+                .into_iter()
+                .filter(|entry| !entry.name.as_str().starts_with("node_modules/"))
+                .prefix_path(&package_path);
+
+        entries.extend(package_entries);
+
+        let mut dependencies
+            = BTreeMap::new();
+
+        for (dependency_ident, descriptor) in &resolution.dependencies {
+            // Peer dependencies are provided by the package consuming ours
+            if resolution.peer_dependencies.contains_key(dependency_ident) {
+                continue;
+            }
+
+            // Optional dependencies may not have been installed
+            let Some(dependency_locator) = install_state.resolution_tree.descriptor_to_locator.get(descriptor) else {
+                continue;
+            };
+
+            let nearest_provider = levels.iter().rev()
+                .find_map(|level| level.get(dependency_ident));
+
+            if nearest_provider == Some(dependency_locator) {
+                continue;
+            }
+
+            // A cycle whose package is shadowed by another version would
+            // otherwise get nested forever
+            if locator == dependency_locator || parents.contains(dependency_locator) {
+                continue;
+            }
+
+            dependencies.insert(dependency_ident.clone(), dependency_locator.clone());
+        }
+
+        parents.push(locator.clone());
+        add_bundled_packages(project, install_state, &package_path, &dependencies, levels, parents, entries)?;
+        parents.pop();
+    }
+
+    levels.pop();
+
+    Ok(())
+}
+
+/// Sets the mode of the packed files: the `bin` targets and the files listed
+/// in `publishConfig.executableFiles` are always executable, regardless of
+/// their permissions on disk, while all other files never are.
+pub fn apply_executable_modes(entries: &mut [Entry<'_>], manifest: &Manifest) {
+    let mut executable_files
+        = manifest.publish_config.executable_files
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+
+    if let Some(bin) = &manifest.bin {
+        executable_files.extend(bin.paths().cloned());
+    }
+
+    for entry in entries.iter_mut() {
+        if executable_files.contains(&entry.name) {
+            entry.mode = 0o755;
+        } else {
+            entry.mode = 0o644;
+        }
+    }
+}
+
+/// The version replacing the `workspace:` ranges referencing the given
+/// workspace in the packed manifest. Workspaces that can't be published
+/// would make the archive uninstallable outside of the project, so we
+/// refuse to pack rather than silently writing a bogus range.
+fn packed_workspace_version(workspace: &Workspace, allow_private: bool) -> Result<zpm_semver::Version, Error> {
+    if !allow_private && workspace.manifest.private == Some(true) {
+        return Err(Error::PrivateWorkspaceDependency(workspace.name.clone()));
+    }
+
+    workspace.manifest.remote.version.clone()
+        .ok_or_else(|| Error::UnversionedWorkspaceDependency(workspace.name.clone()))
+}
+
 pub fn pack_manifest(project: &Project, workspace: &Workspace, options: &PackOptions) -> Result<String, Error> {
     let manifest_path = workspace.path
         .with_join_str("package.json");
@@ -446,6 +609,11 @@ pub fn pack_manifest(project: &Project, workspace: &Workspace, options: &PackOpt
 
     if !options.preserve_workspaces {
         for (field_name, dependencies) in hard_dependencies {
+            // Dev dependencies aren't installed by the consumers of the
+            // package, so they may reference private workspaces
+            let allow_private
+                = field_name == "devDependencies";
+
             for (ident, descriptor) in dependencies {
                 let updated_range = match &descriptor.range {
                     Range::WorkspaceSemver(params) => {
@@ -459,7 +627,7 @@ pub fn pack_manifest(project: &Project, workspace: &Workspace, options: &PackOpt
                             = project.workspace_by_ident(&descriptor.ident)?;
 
                         Some(Range::AnonymousSemver(AnonymousSemverRange {
-                            range: workspace.manifest.remote.version.clone().unwrap_or_default().to_range(params.magic),
+                            range: packed_workspace_version(workspace, allow_private)?.to_range(params.magic),
                         }))
                     },
 
@@ -468,7 +636,7 @@ pub fn pack_manifest(project: &Project, workspace: &Workspace, options: &PackOpt
                             = project.workspace_by_ident(&params.ident)?;
 
                         Some(Range::AnonymousSemver(AnonymousSemverRange {
-                            range: workspace.manifest.remote.version.clone().unwrap_or_default().to_range(zpm_semver::RangeKind::Exact),
+                            range: packed_workspace_version(workspace, allow_private)?.to_range(zpm_semver::RangeKind::Exact),
                         }))
                     },
 
@@ -477,7 +645,7 @@ pub fn pack_manifest(project: &Project, workspace: &Workspace, options: &PackOpt
                             = project.workspace_by_rel_path(&params.path)?;
 
                         Some(Range::AnonymousSemver(AnonymousSemverRange {
-                            range: workspace.manifest.remote.version.clone().unwrap_or_default().to_range(zpm_semver::RangeKind::Exact),
+                            range: packed_workspace_version(workspace, allow_private)?.to_range(zpm_semver::RangeKind::Exact),
                         }))
                     },
 
@@ -508,7 +676,7 @@ pub fn pack_manifest(project: &Project, workspace: &Workspace, options: &PackOpt
 
                 Some(workspace.and_then(move |workspace| {
                     Ok(Descriptor::new(ident.clone(), AnonymousSemverRange {
-                        range: workspace.manifest.remote.version.clone().unwrap_or_default().to_range(params.magic),
+                        range: packed_workspace_version(workspace, false)?.to_range(params.magic),
                     }.into()))
                 }))
             },
@@ -519,7 +687,7 @@ pub fn pack_manifest(project: &Project, workspace: &Workspace, options: &PackOpt
 
                 Some(workspace.and_then(move |workspace| {
                     Ok(Descriptor::new(ident.clone(), AnonymousSemverRange {
-                        range: workspace.manifest.remote.version.clone().unwrap_or_default().to_range(zpm_semver::RangeKind::Exact),
+                        range: packed_workspace_version(workspace, false)?.to_range(zpm_semver::RangeKind::Exact),
                     }.into()))
                 }))
             },
@@ -550,6 +718,15 @@ pub fn pack_manifest(project: &Project, workspace: &Workspace, options: &PackOpt
 }
 
 pub fn pack_list(project: &Project, workspace: &Workspace, manifest: &Manifest) -> Result<Vec<zpm_utils::Path>, Error> {
+    let workspace_paths = project.workspaces.iter()
+        .map(|workspace| &workspace.rel_path);
+
+    pack_folder_list(workspace, manifest, workspace_paths)
+}
+
+/// Lists the files that would be packed from the given workspace, skipping
+/// the given folders (typically the other workspaces of the project).
+pub fn pack_folder_list<'a>(workspace: &Workspace, manifest: &Manifest, skipped_rel_paths: impl IntoIterator<Item = &'a Path>) -> Result<Vec<zpm_utils::Path>, Error> {
     let mut pack_list = PackList::new(workspace.path.clone());
 
     pack_list.skip_traversal_by_name.insert(".git".to_string());
@@ -560,8 +737,8 @@ pub fn pack_list(project: &Project, workspace: &Workspace, manifest: &Manifest)
     pack_list.skip_traversal_by_name.insert("node_modules".to_string());
     pack_list.skip_traversal_by_name.insert("target".to_string());
 
-    for workspace in &project.workspaces {
-        pack_list.skip_traversal_by_rel_path.insert(workspace.rel_path.clone());
+    for rel_path in skipped_rel_paths {
+        pack_list.skip_traversal_by_rel_path.insert(rel_path.clone());
     }
 
     pack_list.traverse(&Path::new())?;
@@ -586,11 +763,13 @@ pub fn pack_list(project: &Project, workspace: &Workspace, manifest: &Manifest)
     let user_patterns = pack_list
         .load_ignore()?;
 
-    for pattern in &user_patterns {
-        glob_ignore.add(&Path::new(), pattern)?;
+    for (from_dir, pattern) in &user_patterns {
+        glob_ignore.add(from_dir, pattern)?;
     }
 
-    let always_ignored = GlobBuilder::new("{.#*,.DS_Store,.gitignore,.npmignore,.pnp.*,.yarnrc,yarn.lock,*.tsbuildinfo}")
+    // Configuration files may contain credentials, so they're never packed
+    // regardless of the files field or the ignore files.
+    let always_ignored = GlobBuilder::new("**/{.#*,._*,.*.swp,.DS_Store,.gitignore,.npmignore,.npmrc,.pnp.*,.yarnrc,.yarnrc.yml,*.orig,*.tsbuildinfo,npm-debug.log,package-lock.json,yarn.lock}")
         .build()
         .expect("Failed to build glob")
         .compile_matcher();
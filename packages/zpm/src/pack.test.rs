@@ -0,0 +1,60 @@
+use std::borrow::Cow;
+
+use super::*;
+
+fn entry(name: &str, mode: u32) -> Entry<'static> {
+    let mut entry
+        = Entry::new_file(Path::from_str(name).unwrap(), Cow::Borrowed(b""));
+
+    entry.mode = mode;
+    entry
+}
+
+fn modes(manifest: &str, entries: Vec<Entry<'static>>) -> Vec<(String, u32)> {
+    let manifest
+        = parse_manifest(manifest).unwrap();
+
+    let mut entries
+        = entries;
+
+    apply_executable_modes(&mut entries, &manifest);
+
+    entries.into_iter()
+        .map(|entry| (entry.name.to_file_string(), entry.mode))
+        .collect()
+}
+
+#[test]
+fn marks_executable_files_as_executable() {
+    let modes = modes(r#"{"publishConfig": {"executableFiles": ["./scripts/run.sh"]}}"#, vec![
+        entry("scripts/run.sh", 0o644),
+        entry("index.js", 0o644),
+    ]);
+
+    assert_eq!(modes, vec![
+        ("scripts/run.sh".to_string(), 0o755),
+        ("index.js".to_string(), 0o644),
+    ]);
+}
+
+#[test]
+fn marks_bin_targets_as_executable() {
+    let modes = modes(r#"{"name": "pkg", "bin": {"pkg": "./bin/cli.js"}}"#, vec![
+        entry("bin/cli.js", 0o644),
+    ]);
+
+    assert_eq!(modes, vec![
+        ("bin/cli.js".to_string(), 0o755),
+    ]);
+}
+
+#[test]
+fn drops_the_executable_bit_from_other_files() {
+    let modes = modes(r#"{}"#, vec![
+        entry("index.js", 0o755),
+    ]);
+
+    assert_eq!(modes, vec![
+        ("index.js".to_string(), 0o644),
+    ]);
+}
@@ -1,12 +1,16 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use itertools::Itertools;
+use serde::Deserialize;
+use zpm_formats::{iter_ext::IterExt, tar::ToTar};
 use zpm_git::PrepareParams;
 use zpm_primitives::Locator;
-use zpm_utils::{Path, ToFileString};
+use zpm_utils::{IoResultExt, Path, ToFileString};
 
 use crate::{
     error::Error,
+    pack::{apply_executable_modes, pack_folder_list},
+    project::Workspace,
     script::ScriptEnvironment,
 };
 
@@ -21,16 +25,123 @@ enum PackageManager {
     YarnZpm,
 }
 
-pub async fn prepare_project(_locator: &Locator, folder_path: &Path, params: &PrepareParams) -> Result<Vec<u8>, Error> {
+#[derive(Deserialize)]
+struct PnpmWorkspaceFile {
+    #[serde(default)]
+    packages: Vec<String>,
+}
+
+/// Lists the workspaces of the repository, starting with the root one.
+async fn list_workspaces(root_path: &Path) -> Result<Vec<Workspace>, Error> {
+    let mut root_workspace
+        = Workspace::from_root_path(root_path)?;
+
+    // pnpm doesn't read the workspaces from the manifest, but from its own
+    // configuration file.
+    if root_workspace.manifest.workspaces.is_none() {
+        let pnpm_workspace_content = root_path
+            .with_join_str("pnpm-workspace.yaml")
+            .fs_read_text()
+            .ok_missing()?;
+
+        if let Some(pnpm_workspace_content) = pnpm_workspace_content {
+            let pnpm_workspace_file: PnpmWorkspaceFile
+                = serde_yaml::from_str(&pnpm_workspace_content)
+                    .map_err(Arc::new)?;
+
+            root_workspace.manifest.workspaces
+                = Some(pnpm_workspace_file.packages);
+        }
+    }
+
+    let mut workspaces = root_workspace
+        .workspaces().await?;
+
+    workspaces.insert(0, root_workspace);
+
+    Ok(workspaces)
+}
+
+/// Finds the workspace selected by the `workspace=` parameter, which can be
+/// either a workspace name or a path relative to the repository root.
+async fn find_workspace(root_path: &Path, selector: &str) -> Result<Workspace, Error> {
+    let workspaces
+        = list_workspaces(root_path).await?;
+
+    let selector_path
+        = selector.trim_start_matches("./").trim_end_matches('/');
+
+    let mut matching_workspaces
+        = workspaces.into_iter()
+            .filter(|workspace| workspace.name.as_str() == selector || workspace.rel_path.as_str() == selector_path)
+            .collect_vec();
+
+    match matching_workspaces.len() {
+        0 => Err(Error::GitWorkspaceNotFound(selector.to_string())),
+        1 => Ok(matching_workspaces.remove(0)),
+        _ => Err(Error::AmbiguousGitWorkspace(selector.to_string(), matching_workspaces.iter().map(|workspace| workspace.rel_path.to_file_string()).join(", "))),
+    }
+}
+
+/// Packs the repository as-is, without installing its dependencies nor
+/// running its lifecycle scripts.
+async fn pack_unprepared_project(root_path: &Path, selector: Option<&str>) -> Result<Vec<u8>, Error> {
+    let workspace = match selector {
+        Some(selector) => find_workspace(root_path, selector).await?,
+        None => Workspace::from_root_path(root_path)?,
+    };
+
+    let workspaces
+        = list_workspaces(root_path).await?;
+
+    let pack_list
+        = pack_folder_list(&workspace, &workspace.manifest, workspaces.iter().map(|workspace| &workspace.rel_path))?;
+
+    let mut entries
+        = zpm_formats::entries_from_files(&workspace.path, &pack_list)?;
+
+    apply_executable_modes(&mut entries, &workspace.manifest);
+
+    let pack_tgz = entries
+        .into_iter()
+        .prefix_path(&Path::from_str("package")?)
+        .collect::<Vec<_>>()
+        .to_tgz()?;
+
+    Ok(pack_tgz)
+}
+
+pub async fn prepare_project(_locator: &Locator, folder_path: &Path, params: &PrepareParams, enable_scripts: bool) -> Result<Vec<u8>, Error> {
+    let root_path = params.cwd.as_ref()
+        .map(|cwd| folder_path.with_join_str(cwd))
+        .unwrap_or(folder_path.clone());
+
+    if !enable_scripts {
+        return pack_unprepared_project(&root_path, params.workspace.as_deref()).await;
+    }
+
     let package_manager
         = get_package_manager(folder_path)?;
 
+    // The package managers only know how to select workspaces by name, so
+    // we resolve the selector ourselves; it also lets us report clear errors
+    // when the selector doesn't match exactly one workspace.
+    let workspace = match &params.workspace {
+        Some(selector) => Some(find_workspace(&root_path, selector).await?),
+        None => None,
+    };
+
+    let params = &PrepareParams {
+        cwd: params.cwd.clone(),
+        workspace: workspace.as_ref().map(|workspace| workspace.name.to_file_string()),
+    };
+
     match package_manager {
         PackageManager::Npm
             => prepare_npm_project(folder_path, params).await,
 
         PackageManager::Pnpm
-            => prepare_pnpm_project(folder_path, params).await,
+            => prepare_pnpm_project(folder_path, workspace.as_ref()).await,
 
         PackageManager::YarnClassic
             => prepare_yarn_classic_project(folder_path, params).await,
@@ -249,6 +360,7 @@ async fn prepare_yarn_modern_project(folder_path: &Path, params: &PrepareParams)
         // We enable inline builds, because nobody wants to
         // read a logfile telling them to open another logfile
         .with_env_variable("YARN_ENABLE_INLINE_BUILDS", "1")
+        .with_env_variable("YARN_ENABLE_IMMUTABLE_INSTALLS", "0")
         .with_env_variable("YARNSW_DEFAULT", &default_yarn)
 
         .run_exec("yarn", pack_args)
@@ -261,15 +373,22 @@ async fn prepare_yarn_modern_project(folder_path: &Path, params: &PrepareParams)
     Ok(pack_tgz)
 }
 
-async fn prepare_pnpm_project(folder_path: &Path, _params: &PrepareParams) -> Result<Vec<u8>, Error> {
+async fn prepare_pnpm_project(folder_path: &Path, workspace: Option<&Workspace>) -> Result<Vec<u8>, Error> {
     ScriptEnvironment::new()?
         .with_cwd(folder_path.clone())
+        // Remove environment variables that limit the install to just production dependencies
+        .delete_env_variable("NODE_ENV")
+
         .run_exec("pnpm", vec!["install"])
         .await?
         .ok()?;
 
+    let pack_cwd = workspace
+        .map(|workspace| workspace.path.clone())
+        .unwrap_or(folder_path.clone());
+
     let pack_result = ScriptEnvironment::new()?
-        .with_cwd(folder_path.clone())
+        .with_cwd(pack_cwd.clone())
         .run_exec("pnpm", vec!["pack"])
         .await?
         .ok()?;
@@ -277,7 +396,7 @@ async fn prepare_pnpm_project(folder_path: &Path, _params: &PrepareParams) -> Re
     let pack_file
         = String::from_utf8(pack_result.output().stdout.clone())?;
 
-    let pack_tgz = folder_path
+    let pack_tgz = pack_cwd
         .with_join_str(pack_file.trim())
         .fs_read()?;
 
@@ -313,6 +432,10 @@ async fn prepare_yarn_zpm_project(folder_path: &Path, params: &PrepareParams) ->
 
         .with_env_variable("YARNSW_DEFAULT", &format!("local:{}", current_exe.to_file_string()))
 
+        // The repository is installed from scratch in its own folder, so the
+        // settings of the outer install must not leak into it
+        .with_env_variable("YARN_ENABLE_IMMUTABLE_INSTALLS", "0")
+
         .run_exec("yarn", pack_args)
         .await?
         .ok()?;
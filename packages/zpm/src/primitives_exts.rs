@@ -73,6 +73,7 @@ impl RangeExt for Range {
                 }
             },
 
+            Range::Exec(_) |
             Range::Folder(_) |
             Range::Portal(_) |
             Range::Tarball(_) => {
@@ -0,0 +1,217 @@
+use std::{collections::BTreeMap, sync::{Arc, LazyLock, Mutex, atomic::{AtomicBool, Ordering}}, time::{Duration, Instant}};
+
+use serde::Serialize;
+use zpm_parsers::JsonDocument;
+use zpm_utils::{DataType, ToHumanString, Unit};
+
+use crate::error::Error;
+
+#[cfg(test)]
+#[path = "./profile.test.rs"]
+mod profile_tests;
+
+/// How many of the slowest individual spans are listed in the summary.
+const MAX_LISTED_SPANS: usize = 10;
+
+static PROFILING_ENABLED: AtomicBool = AtomicBool::new(false);
+
+static PROFILING_START: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// The sample buffers of every thread that recorded at least one span. Each
+/// thread only ever locks its own buffer while profiling, so recording a
+/// span doesn't contend with the other threads; the buffers are merged when
+/// the samples are collected.
+static BUFFERS: LazyLock<Mutex<Vec<Arc<Mutex<Vec<Sample>>>>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+thread_local! {
+    static BUFFER: (usize, Arc<Mutex<Vec<Sample>>>) = {
+        let buffer
+            = Arc::new(Mutex::new(Vec::new()));
+
+        let mut buffers
+            = BUFFERS.lock().unwrap();
+
+        buffers.push(buffer.clone());
+
+        (buffers.len(), buffer)
+    };
+}
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub label: &'static str,
+    pub start: Instant,
+    pub duration: Duration,
+    pub thread_id: usize,
+}
+
+/// Starts recording the `yarn_track_time!` spans; until then, they're
+/// skipped without even reading the clock.
+pub fn enable() {
+    LazyLock::force(&PROFILING_START);
+    PROFILING_ENABLED.store(true, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn is_enabled() -> bool {
+    PROFILING_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns the samples recorded so far by all threads, sorted by start time.
+pub fn take_samples() -> Vec<Sample> {
+    let buffers
+        = BUFFERS.lock().unwrap();
+
+    let mut samples = buffers.iter()
+        .flat_map(|buffer| std::mem::take(&mut *buffer.lock().unwrap()))
+        .collect::<Vec<_>>();
+
+    samples.sort_by_key(|sample| sample.start);
+    samples
+}
+
+/// Measures the time until it's dropped; see `yarn_track_time!`.
+pub struct Span {
+    label: &'static str,
+    start: Instant,
+}
+
+impl Span {
+    #[inline]
+    pub fn start(label: &'static str) -> Option<Span> {
+        if !is_enabled() {
+            return None;
+        }
+
+        Some(Span {label, start: Instant::now()})
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let duration
+            = self.start.elapsed();
+
+        BUFFER.with(|(thread_id, buffer)| {
+            buffer.lock().unwrap().push(Sample {
+                label: self.label,
+                start: self.start,
+                duration,
+                thread_id: *thread_id,
+            });
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct ProfileEntry {
+    pub label: &'static str,
+    pub total: Duration,
+    pub count: usize,
+}
+
+/// The recorded samples aggregated by label, sorted by decreasing total
+/// time, along with the slowest individual spans.
+#[derive(Debug)]
+pub struct ProfileSummary {
+    pub entries: Vec<ProfileEntry>,
+    pub slowest_spans: Vec<Sample>,
+}
+
+impl ProfileSummary {
+    pub fn new(samples: &[Sample]) -> Self {
+        let mut entries_by_label: BTreeMap<&'static str, ProfileEntry>
+            = BTreeMap::new();
+
+        for sample in samples {
+            let entry = entries_by_label.entry(sample.label)
+                .or_insert(ProfileEntry {label: sample.label, total: Duration::ZERO, count: 0});
+
+            entry.total += sample.duration;
+            entry.count += 1;
+        }
+
+        let mut entries
+            = entries_by_label.into_values().collect::<Vec<_>>();
+
+        entries.sort_by(|a, b| b.total.cmp(&a.total).then(a.label.cmp(b.label)));
+
+        let mut slowest_spans
+            = samples.to_vec();
+
+        slowest_spans.sort_by(|a, b| b.duration.cmp(&a.duration));
+        slowest_spans.truncate(MAX_LISTED_SPANS);
+
+        ProfileSummary {
+            entries,
+            slowest_spans,
+        }
+    }
+
+    pub fn to_print_lines(&self) -> Vec<String> {
+        let mut lines
+            = Vec::new();
+
+        if self.entries.is_empty() {
+            lines.push("No instrumented function got called".to_string());
+            return lines;
+        }
+
+        lines.push("Time spent per function:".to_string());
+
+        for entry in &self.entries {
+            lines.push(format!(
+                "  {} {} ({} calls)",
+                DataType::Code.colorize(entry.label),
+                Unit::duration_ms(entry.total.as_millis()).to_print_string(),
+                DataType::Number.colorize(&entry.count.to_string()),
+            ));
+        }
+
+        lines.push("Slowest calls:".to_string());
+
+        for sample in &self.slowest_spans {
+            lines.push(format!(
+                "  {} {} (started at +{})",
+                DataType::Code.colorize(sample.label),
+                Unit::duration_ms(sample.duration.as_millis()).to_print_string(),
+                Unit::duration_ms(sample.start.saturating_duration_since(*PROFILING_START).as_millis()).to_print_string(),
+            ));
+        }
+
+        lines
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChromeTrace {
+    trace_events: Vec<ChromeTraceEvent>,
+}
+
+#[derive(Serialize)]
+struct ChromeTraceEvent {
+    name: &'static str,
+    cat: &'static str,
+    ph: &'static str,
+    ts: u128,
+    dur: u128,
+    pid: u32,
+    tid: usize,
+}
+
+/// Serializes the samples in the Chrome trace event format, which can be
+/// loaded into `chrome://tracing` or Perfetto to see when each span ran.
+pub fn to_chrome_trace(samples: &[Sample]) -> Result<String, Error> {
+    let trace_events = samples.iter().map(|sample| ChromeTraceEvent {
+        name: sample.label,
+        cat: "zpm",
+        ph: "X",
+        ts: sample.start.saturating_duration_since(*PROFILING_START).as_micros(),
+        dur: sample.duration.as_micros(),
+        pid: std::process::id(),
+        tid: sample.thread_id,
+    }).collect();
+
+    Ok(JsonDocument::to_string(&ChromeTrace {trace_events})?)
+}
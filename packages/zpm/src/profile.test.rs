@@ -0,0 +1,55 @@
+use super::*;
+
+fn sample(label: &'static str, start_ms: u64, duration_ms: u64) -> Sample {
+    Sample {
+        label,
+        start: *PROFILING_START + Duration::from_millis(start_ms),
+        duration: Duration::from_millis(duration_ms),
+        thread_id: 1,
+    }
+}
+
+#[test]
+fn it_aggregates_the_samples_by_label() {
+    let summary = ProfileSummary::new(&[
+        sample("fetch_locator", 0, 10),
+        sample("resolve_descriptor", 0, 5),
+        sample("fetch_locator", 10, 30),
+    ]);
+
+    let entries = summary.entries.iter()
+        .map(|entry| (entry.label, entry.total, entry.count))
+        .collect::<Vec<_>>();
+
+    assert_eq!(entries, vec![
+        ("fetch_locator", Duration::from_millis(40), 2),
+        ("resolve_descriptor", Duration::from_millis(5), 1),
+    ]);
+}
+
+#[test]
+fn it_lists_the_slowest_spans_first() {
+    let samples = (0..MAX_LISTED_SPANS as u64 + 5)
+        .map(|index| sample("fetch_locator", index, index))
+        .collect::<Vec<_>>();
+
+    let summary
+        = ProfileSummary::new(&samples);
+
+    assert_eq!(summary.slowest_spans.len(), MAX_LISTED_SPANS);
+    assert_eq!(summary.slowest_spans[0].duration, Duration::from_millis(MAX_LISTED_SPANS as u64 + 4));
+}
+
+#[test]
+fn it_serializes_the_samples_as_complete_trace_events() {
+    let trace
+        = to_chrome_trace(&[sample("link_project", 2, 3)]).unwrap();
+
+    let value: serde_json::Value
+        = JsonDocument::hydrate_from_str(&trace).unwrap();
+
+    assert_eq!(value["traceEvents"][0]["name"], "link_project");
+    assert_eq!(value["traceEvents"][0]["ph"], "X");
+    assert_eq!(value["traceEvents"][0]["ts"], 2000);
+    assert_eq!(value["traceEvents"][0]["dur"], 3000);
+}
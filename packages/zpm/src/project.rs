@@ -1,28 +1,36 @@
 use std::{collections::{BTreeMap, BTreeSet, HashSet}, io::ErrorKind, sync::Arc, time::UNIX_EPOCH};
 
 use globset::{GlobBuilder, GlobSetBuilder};
-use zpm_config::{Configuration, ConfigurationContext};
+use indexmap::IndexMap;
+use zpm_config::{Configuration, ConfigurationContext, EnableScripts, NodeLinker};
 use zpm_macro_enum::zpm_enum;
 use zpm_parsers::JsonDocument;
-use zpm_primitives::{Descriptor, Ident, Locator, Range, Reference, WorkspaceIdentReference, WorkspaceMagicRange, WorkspacePathReference};
-use zpm_utils::{Glob, LastModifiedAt, Path, ToFileString, ToHumanString};
+use zpm_primitives::{Descriptor, FilterDescriptor, Ident, Locator, Range, Reference, WorkspaceIdentReference, WorkspaceMagicRange, WorkspacePathReference};
+use zpm_utils::{DataType, Glob, Hash64, IoResultExt, LastModifiedAt, Path, ToFileString, ToHumanString};
 use serde::Deserialize;
 use zpm_formats::zip::ZipSupport;
 
 use crate::{
+    algos,
     cache::{CompositeCache, DiskCache},
     diff_finder::CacheEntry,
-    error::Error,
-    git::{GitOperation, detect_git_operation},
+    engines::check_engines,
+    error::{Error, ErrorCode},
     http::HttpClient,
-    install::{InstallContext, InstallManager, InstallResult, InstallState},
-    lockfile::{Lockfile, from_legacy_berry_lockfile, from_pnpm_node_modules},
+    install::{Install, InstallContext, InstallManager, InstallResult, InstallState, INSTALL_STATE_VERSION},
+    install_summary::{InstalledPackages, InstallSummary},
+    lockfile::{Lockfile, from_conflicted_lockfile, from_legacy_berry_lockfile, from_pnpm_node_modules},
     manifest::{Manifest, helpers::read_manifest_with_size},
     manifest_finder::CachedManifestFinder,
-    report::{StreamReport, StreamReportConfig, with_report_result},
-    script::{Binary, ScriptEnvironment},
+    report::{StreamReport, StreamReportConfig, current_report, with_report_result},
+    script::Binary,
+    yarn_track_time,
 };
 
+#[cfg(test)]
+#[path = "./project.test.rs"]
+mod project_tests;
+
 pub const LOCKFILE_NAME: &str = "yarn.lock";
 pub const MANIFEST_NAME: &str = "package.json";
 pub const PNP_CJS_NAME: &str = ".pnp.cjs";
@@ -48,14 +56,20 @@ pub enum InstallMode {
 
 #[derive(Default)]
 pub struct RunInstallOptions {
+    pub build_selection: Option<BTreeSet<Ident>>,
+    pub check_cache: bool,
     pub check_checksums: bool,
     pub check_resolutions: bool,
     pub enforced_resolutions: BTreeMap<Descriptor, Locator>,
+    pub forced_builds: BTreeSet<Ident>,
     pub prune_dev_dependencies: bool,
     pub mode: Option<InstallMode>,
+    pub refresh_checksums: bool,
     pub refresh_lockfile: bool,
     pub roots: Option<BTreeSet<Ident>>,
     pub silent_or_error: bool,
+    pub summary: bool,
+    pub verbose: bool,
 }
 
 pub struct Project {
@@ -112,6 +126,14 @@ impl Project {
         Ok((farthest_pkg, closest_pkg.unwrap()))
     }
 
+    /// Load the project that contains the given directory. This is the entry
+    /// point to use when using zpm as a library; the CLI goes through `new`,
+    /// which defaults to the current working directory.
+    ///
+    pub async fn load(cwd: Path) -> Result<Project, Error> {
+        Project::new(Some(cwd)).await
+    }
+
     pub async fn new(cwd: Option<Path>) -> Result<Project, Error> {
         let user_cwd
             = Path::home_dir()?;
@@ -142,6 +164,11 @@ impl Project {
             config.settings.enable_global_cache.source = config.settings.enable_migration_mode.source;
         }
 
+        // Must happen before reading the manifests, as they may contain git
+        // ranges pointing to these hosts
+        zpm_git::set_github_enterprise_hosts(config.settings.github_enterprise_hosts.iter()
+            .map(|host| host.value.as_str()));
+
         let root_workspace
             = Workspace::from_root_path(&project_cwd)?;
 
@@ -226,6 +253,14 @@ impl Project {
         self.ignore_path().with_join_str("unplugged")
     }
 
+    pub fn nm_store_path(&self) -> Path {
+        self.ignore_path().with_join_str("nm-store")
+    }
+
+    pub fn nm_state_path(&self) -> Path {
+        self.ignore_path().with_join_str("nm-state")
+    }
+
     pub fn install_state_path(&self) -> Path {
         self.ignore_path().with_join_str("install")
     }
@@ -234,11 +269,29 @@ impl Project {
         self.ignore_path().with_join_str("build")
     }
 
+    pub fn build_logs_path(&self) -> Path {
+        self.ignore_path().with_join_str("build-logs")
+    }
+
+    pub fn constraints_cache_path(&self) -> Path {
+        self.ignore_path().with_join_str("constraints")
+    }
+
     pub fn global_cache_path(&self) -> Path {
         self.config.settings.global_folder.value
             .with_join_str("cache")
     }
 
+    pub fn content_store_path(&self) -> Path {
+        self.config.settings.global_folder.value
+            .with_join_str("store")
+    }
+
+    pub fn git_cache_path(&self) -> Path {
+        self.config.settings.global_folder.value
+            .with_join_str("git")
+    }
+
     pub fn local_cache_path(&self) -> Path {
         self.project_cwd
             .with_join_str(".yarn")
@@ -303,9 +356,20 @@ impl Project {
             = JsonDocument::hydrate_from_str(&src)
                 .map_err(|e| Error::LockfileParseError(e))?;
 
+        lockfile.validate()
+            .map_err(Error::LockfileIntegrityError)?;
+
         Ok(lockfile)
     }
 
+    pub fn lockfile_hash(&self) -> Result<Option<Hash64>, Error> {
+        let lockfile_content = self.lockfile_path()
+            .fs_read()
+            .ok_missing()?;
+
+        Ok(lockfile_content.map(Hash64::from_data))
+    }
+
     pub fn import_install_state(&mut self) -> Result<&mut Self, Error> {
         let install_state_path
             = self.install_state_path();
@@ -321,13 +385,20 @@ impl Project {
             = rkyv::from_bytes::<InstallState, rkyv::rancor::BoxedError>(&src)
                 .map_err(|_| Error::InvalidInstallState)?;
 
+        if install_state.version != INSTALL_STATE_VERSION {
+            return Err(Error::InvalidInstallState);
+        }
+
         self.install_state
             = Some(install_state);
 
         Ok(self)
     }
 
-    pub fn attach_install_state(&mut self, install_state: InstallState) -> Result<(), Error> {
+    pub fn attach_install_state(&mut self, mut install_state: InstallState) -> Result<(), Error> {
+        install_state.version
+            = INSTALL_STATE_VERSION;
+
         if self.install_state.as_ref().map(|s| *s != install_state).unwrap_or(true) {
             self.write_install_state(&install_state)?;
         }
@@ -363,7 +434,7 @@ impl Project {
 
         link_info_path
             .fs_create_parent()?
-            .fs_change(contents, false)?;
+            .fs_change_atomic(contents, false)?;
 
         Ok(())
     }
@@ -372,13 +443,24 @@ impl Project {
         let lockfile_path
             = self.lockfile_path();
 
-        let contents
+        let mut contents
             = JsonDocument::to_string_pretty(lockfile)?;
 
+        // We preserve the trailing newline style of the existing lockfile so
+        // that editors adding or stripping it don't cause the next install to
+        // flip it back; new lockfiles always end with a newline.
+        let current_contents = lockfile_path
+            .fs_read_text()
+            .ok_missing()?;
+
+        if current_contents.as_ref().map_or(true, |current| current.ends_with('\n')) {
+            contents.push('\n');
+        }
+
         if self.config.settings.enable_immutable_installs.value {
             lockfile_path.fs_expect(contents, false)?;
         } else {
-            lockfile_path.fs_change(contents, false)?;
+            lockfile_path.fs_change_atomic(contents, false)?;
         }
 
         Ok(())
@@ -409,8 +491,15 @@ impl Project {
         let enable_immutable_cache
             = self.config.settings.enable_immutable_cache.value;
 
+        // The PnP runtime reads the packages straight from the cache, but
+        // only supports the compression methods from the zip specification
+        if matches!(compression_algorithm, Some(zpm_formats::CompressionAlgorithm::Brotli(_))) && self.config.settings.node_linker.value == NodeLinker::Pnp {
+            return Err(Error::BrotliCacheWithPnp);
+        }
+
         let name_suffix = match compression_algorithm {
             Some(zpm_formats::CompressionAlgorithm::Deflate(_)) => format!("-d{}", compression_algorithm.unwrap().to_file_string()),
+            Some(zpm_formats::CompressionAlgorithm::Brotli(quality)) => format!("-b{}", quality),
             None => "".to_string(),
         };
 
@@ -562,6 +651,77 @@ impl Project {
         }
     }
 
+    /// Returns the dependencies the workspaces have on each other, either
+    /// as regular or dev dependencies, through the `workspace:` protocol or
+    /// through paths (`file:`, `link:`, or `portal:`) pointing to them.
+    pub fn workspace_dependencies(&self) -> Vec<WorkspaceDependency> {
+        let mut dependencies
+            = vec![];
+
+        for workspace in &self.workspaces {
+            let declared_dependencies = workspace.manifest.remote.dependencies.values()
+                .map(|descriptor| (descriptor, "dependencies"))
+                .chain(workspace.manifest.dev_dependencies.values().map(|descriptor| (descriptor, "devDependencies")));
+
+            for (descriptor, field) in declared_dependencies {
+                let dependency = match descriptor.range.is_workspace() {
+                    true => self.try_workspace_by_descriptor(descriptor).ok().flatten(),
+                    false => self.try_workspace_by_path_range(workspace, descriptor),
+                };
+
+                if let Some(dependency) = dependency {
+                    dependencies.push(WorkspaceDependency {
+                        dependent: workspace.name.clone(),
+                        dependency: dependency.name.clone(),
+                        field,
+                    });
+                }
+            }
+        }
+
+        dependencies
+    }
+
+    /// Returns the workspaces sorted so that each of them comes after the
+    /// workspaces it depends on, or the cycles preventing such an order
+    /// from existing.
+    pub fn workspace_order(&self) -> Result<Vec<Ident>, Vec<WorkspaceCycle>> {
+        let workspaces
+            = self.workspaces.iter()
+                .map(|workspace| workspace.name.clone());
+
+        sort_workspaces(workspaces, &self.workspace_dependencies())
+    }
+
+    /// Same as `workspace_order`, but returns the workspaces themselves and
+    /// reports the cycles as an error.
+    pub fn workspaces_topological(&self) -> Result<Vec<&Workspace>, Error> {
+        let order = self.workspace_order()
+            .map_err(Error::WorkspaceCycles)?;
+
+        let workspaces = order.iter()
+            .map(|ident| &self.workspaces[self.workspaces_by_ident[ident]])
+            .collect();
+
+        Ok(workspaces)
+    }
+
+    fn try_workspace_by_path_range(&self, workspace: &Workspace, descriptor: &Descriptor) -> Option<&Workspace> {
+        let path = match &descriptor.range {
+            Range::Folder(params) => &params.path,
+            Range::Link(params) => &params.path,
+            Range::Portal(params) => &params.path,
+            _ => return None,
+        };
+
+        let rel_path = workspace.path
+            .with_join_str(path)
+            .relative_to(&self.project_cwd);
+
+        self.workspaces_by_rel_path.get(&rel_path)
+            .map(|idx| &self.workspaces[*idx])
+    }
+
     pub fn try_workspace_by_rel_path(&self, rel_path: &Path) -> Result<Option<&Workspace>, Error> {
         let workspace
             = self.workspaces_by_rel_path.get(rel_path)
@@ -650,9 +810,50 @@ impl Project {
     pub fn find_binary(&self, name: &str) -> Result<Binary, Error> {
         let active_package = self.active_package()?;
 
-        self.package_visible_binaries(&active_package)?
-            .remove(name)
-            .ok_or(Error::BinaryNotFound(name.to_string()))
+        if let Some(binary) = self.package_visible_binaries(&active_package)?.remove(name) {
+            return Ok(binary);
+        }
+
+        // Binaries from packages excluded from the current platform aren't
+        // known since we never fetched their archives. We only point at the
+        // excluded packages whose name matches the binary, which is the name
+        // npm gives to the binary when `bin` is a single path.
+        let install_state = self.install_state.as_ref()
+            .ok_or(Error::InstallStateNotFound)?;
+
+        let Some(resolution) = install_state.resolution_tree.locator_resolutions.get(&active_package) else {
+            return Err(Error::BinaryNotFound(name.to_string()));
+        };
+
+        let excluded_dependencies = resolution.dependencies.values()
+            .filter_map(|descriptor| install_state.resolution_tree.descriptor_to_locator.get(descriptor))
+            .filter(|locator| install_state.disabled_locators.contains(&locator.physical_locator()))
+            .filter(|locator| locator.ident.name() == name)
+            .map(|locator| locator.ident.to_print_string())
+            .collect::<Vec<_>>();
+
+        if !excluded_dependencies.is_empty() {
+            return Err(Error::BinaryFromExcludedPackage(name.to_string(), excluded_dependencies.join(", ")));
+        }
+
+        Err(Error::BinaryNotFound(name.to_string()))
+    }
+
+    /// Whether third-party scripts (build scripts, generators, init scripts)
+    /// of the given package may run. Unless `enableScripts` is disabled, only
+    /// the packages listed in `allowedBuildDependencies` are allowed (none
+    /// when it's empty). Entries that include a range only match when the
+    /// version is known.
+    pub fn is_build_allowed(&self, ident: &Ident, version: Option<&zpm_semver::Version>) -> bool {
+        if self.config.settings.enable_scripts.value == EnableScripts::Disabled {
+            return false;
+        }
+
+        self.config.settings.allowed_build_dependencies.iter().any(|filter| match (&filter.value, version) {
+            (FilterDescriptor::Ident(params), _) => params.ident.check(ident),
+            (filter, Some(version)) => filter.check(ident, version),
+            (_, None) => false,
+        })
     }
 
     pub fn find_script(&self, name: &str) -> Result<(Locator, String), Error> {
@@ -661,10 +862,30 @@ impl Project {
         self.find_package_script(&active_package, name)
     }
 
-    pub fn find_package_script(&self, locator: &Locator, name: &str) -> Result<(Locator, String), Error> {
+    /// Returns the script with the given name from the root workspace, if
+    /// `enableScriptInheritance` is set and the active package is another
+    /// workspace. The script is meant to run within the active workspace.
+    pub fn find_inherited_script(&self, name: &str) -> Result<Option<String>, Error> {
+        if !self.config.settings.enable_script_inheritance.value {
+            return Ok(None);
+        }
+
+        let active_package
+            = self.active_package()?;
+
+        if !active_package.reference.is_workspace_reference() || active_package == self.root_workspace().locator() {
+            return Ok(None);
+        }
+
+        Ok(self.root_workspace().manifest.scripts.get(name).cloned())
+    }
+
+    /// Returns the scripts of the given package, in the order in which
+    /// they're declared in its manifest.
+    pub fn package_scripts(&self, locator: &Locator) -> Result<IndexMap<String, String>, Error> {
         #[derive(Debug, Clone, Deserialize)]
         struct ScriptManifest {
-            pub scripts: Option<BTreeMap<String, String>>,
+            pub scripts: Option<IndexMap<String, String>>,
         }
 
         let package_location
@@ -678,8 +899,12 @@ impl Project {
         let manifest: ScriptManifest
             = JsonDocument::hydrate_from_str(&manifest_text)?;
 
-        if let Some(script) = manifest.scripts.as_ref().and_then(|s| s.get(name)) {
-            return Ok((locator.clone(), script.clone()));
+        Ok(manifest.scripts.unwrap_or_default())
+    }
+
+    pub fn find_package_script(&self, locator: &Locator, name: &str) -> Result<(Locator, String), Error> {
+        if let Some(script) = self.package_scripts(locator)?.shift_remove(name) {
+            return Ok((locator.clone(), script));
         }
 
         if !name.contains(':') {
@@ -740,11 +965,63 @@ impl Project {
             silent_or_error: true,
             mode: None,
             roots: None,
+            summary: false,
+            verbose: false,
+            ..Default::default()
         }).await?;
 
         Ok(())
     }
 
+    /// Same as `lazy_install`, except that when `requireUpToDateInstall` is
+    /// set we fail instead of installing if the project drifted from its
+    /// install state.
+    pub async fn lazy_install_or_verify(&mut self) -> Result<(), Error> {
+        if self.config.settings.require_up_to_date_install.value {
+            self.verify_install_state()
+        } else {
+            self.lazy_install().await
+        }
+    }
+
+    pub fn verify_install_state(&mut self) -> Result<(), Error> {
+        match self.import_install_state() {
+            Ok(_) => {},
+
+            Err(Error::InstallStateNotFound) => {
+                return Err(Error::OutdatedInstallState("no install state found".to_string()));
+            },
+
+            Err(Error::InvalidInstallState) => {
+                return Err(Error::OutdatedInstallState("the install state couldn't be read".to_string()));
+            },
+
+            Err(e) => {
+                return Err(e);
+            },
+        };
+
+        let install_state = self.install_state.as_ref()
+            .expect("The install state should have been imported");
+
+        if self.lockfile_hash()? != install_state.lockfile_hash {
+            return Err(Error::OutdatedInstallState(format!("{} changed since the last install", self.lockfile_path().to_print_string())));
+        }
+
+        let changed_workspace = self.workspaces.iter()
+            .find(|workspace| workspace.last_changed_at > install_state.last_installed_at);
+
+        if let Some(workspace) = changed_workspace {
+            return Err(Error::OutdatedInstallState(format!("{} changed since the last install", workspace.manifest_path().to_print_string())));
+        }
+
+        if self.last_modified_at.has_changed_since(install_state.last_installed_at) {
+            return Err(Error::OutdatedInstallState("the project configuration changed since the last install".to_string()));
+        }
+
+        Ok(())
+    }
+
     pub async fn run_install(&mut self, options: RunInstallOptions) -> Result<InstallResult, Error> {
         // Useful for optimization purposes as we can reuse some information such as content flags.
         // Discard errors; worst case scenario we just recompute the whole state from scratch.
@@ -758,77 +1035,222 @@ impl Project {
             ..StreamReportConfig::from_config(&self.config)
         });
 
-        let systems
-            = self.config.settings.supported_architectures.to_systems();
+        let verbose
+            = options.verbose;
+
+        let previous_packages = options.summary.then(|| {
+            self.install_state.as_ref()
+                .map(|install_state| InstalledPackages::from_tree(&install_state.resolution_tree))
+                .unwrap_or_default()
+        });
 
         with_report_result(report, async {
-            let package_cache
-                = self.package_cache()?;
+            check_engines(self).await?;
+
+            if let Err(cycles) = self.workspace_order() {
+                current_report().await.as_ref().map(|report| {
+                    for cycle in &cycles {
+                        report.warn_with_code(ErrorCode::WorkspaceCycle, format!("Workspaces depend on each other, so they can't be processed in topological order: {}", cycle.to_print_string()));
+                    }
+                });
+            }
+
+            let install = yarn_track_time!("resolve_and_fetch", {
+                self.resolve_and_fetch(options).await?
+            });
+
+            let summary = previous_packages.map(|previous_packages| {
+                InstallSummary::new(&previous_packages, &InstalledPackages::from_tree(&install.install_state.resolution_tree))
+            });
 
-            let mut lockfile
-                = self.lockfile();
+            if verbose {
+                let stats
+                    = &self.http_client.stats;
+
+                current_report().await.as_ref().map(|report| {
+                    report.info(format!(
+                        "Sent {} network requests, opened {} connections, reused {}",
+                        DataType::Number.colorize(&stats.requests_sent().to_string()),
+                        DataType::Number.colorize(&stats.connections_opened().to_string()),
+                        DataType::Number.colorize(&stats.connections_reused().to_string()),
+                    ));
+                });
+            }
 
-            if let Err(Error::LockfileParseError(_)) = lockfile {
-                let lockfile_path
-                    = self.lockfile_path();
+            let install_result = yarn_track_time!("link_and_build", {
+                install.link_and_build(self).await?
+            });
 
-                let lockfile_content = lockfile_path
-                    .fs_read_text()?;
+            if let Some(summary) = summary {
+                summary.report().await;
+            }
 
-                if lockfile_content.contains("<<<<<<<") {
-                    if self.config.settings.enable_immutable_installs.value {
-                        return Err(Error::ImmutableLockfileAutofix);
-                    }
+            Ok(install_result)
+        }).await
+    }
+
+    /// Resolve and fetch the dependency tree of the project. The lockfile
+    /// isn't written, the install state isn't persisted, and nothing gets
+    /// linked or built.
+    ///
+    /// The returned `Install` exposes the resulting lockfile, install state,
+    /// and resolution tree; `run_install` is just this function followed by
+    /// `Install::link_and_build`.
+    ///
+    /// This function doesn't print anything by itself; progress is sent to
+    /// the report set by the caller, if any (see `with_report` and
+    /// `StreamReport::with_sink`).
+    ///
+    pub async fn resolve_and_fetch(&self, options: RunInstallOptions) -> Result<Install, Error> {
+        let systems
+            = self.config.settings.supported_architectures.to_systems();
+
+        let package_cache
+            = self.package_cache()?;
 
-                    let git_operation
-                        = detect_git_operation(&self.project_cwd)
-                            .await?
-                            .unwrap_or(GitOperation::Merge);
+        let mut lockfile
+            = self.lockfile();
 
-                    ScriptEnvironment::new()?
-                        .with_cwd(self.project_cwd.clone())
-                        .run_exec("git", vec!["checkout", git_operation.true_theirs(), lockfile_path.as_str()])
-                        .await?
-                        .ok()
-                        .map_err(|e| Error::LockfileAutofixGitError(e.to_string()))?;
+        let mut fixed_merge_conflicts
+            = false;
 
-                    lockfile
-                        = self.lockfile();
+        if let Err(Error::LockfileParseError(_) | Error::LegacyLockfileParseError(_)) = lockfile {
+            let lockfile_content = self.lockfile_path()
+                .fs_read_text()?;
+
+            if lockfile_content.contains("<<<<<<<") {
+                if self.config.settings.enable_immutable_installs.value {
+                    return Err(Error::ImmutableLockfileAutofix);
                 }
+
+                lockfile
+                    = from_conflicted_lockfile(&lockfile_content);
+
+                fixed_merge_conflicts
+                    = lockfile.is_ok();
             }
+        }
 
-            let install_context
-                = InstallContext::default()
-                    .with_package_cache(Some(&package_cache))
-                    .with_project(Some(self))
-                    .set_check_checksums(options.check_checksums)
-                    .set_enforced_resolutions(options.enforced_resolutions)
-                    .set_prune_dev_dependencies(options.prune_dev_dependencies)
-                    .set_refresh_lockfile(options.refresh_lockfile)
-                    .set_mode(options.mode)
-                    .with_systems(Some(&systems));
+        if fixed_merge_conflicts {
+            current_report().await.as_ref().map(|report| {
+                report.info("Automatically fixed merge conflicts in the lockfile".to_string());
+            });
+        }
 
-            let roots
-                = self.workspaces.iter()
-                    .filter(|w| options.roots.as_ref().map_or(true, |r| r.contains(&w.name)))
-                    .map(|w| w.descriptor())
-                    .collect();
-
-            let install_result
-                = InstallManager::new()
-                    .with_context(install_context)
-                    .with_lockfile(lockfile?)
-                    .with_previous_state(self.install_state.as_ref())
-                    .with_roots(roots)
-                    .with_constraints_check(!options.silent_or_error && self.config.settings.enable_constraints_checks.value && options.roots.is_none())
-                    .with_skip_link_step(options.mode == Some(InstallMode::UpdateLockfile))
-                    .with_skip_lockfile_update(options.roots.is_some())
-                    .resolve_and_fetch().await?
-                    .link_and_build(self).await?;
+        let install_context
+            = InstallContext::default()
+                .with_package_cache(Some(&package_cache))
+                .with_project(Some(self))
+                .set_check_cache(options.check_cache)
+                .set_check_checksums(options.check_checksums)
+                .set_enforced_resolutions(options.enforced_resolutions)
+                .set_prune_dev_dependencies(options.prune_dev_dependencies)
+                .set_refresh_checksums(options.refresh_checksums)
+                .set_refresh_lockfile(options.refresh_lockfile)
+                .set_mode(options.mode)
+                .with_systems(Some(&systems));
+
+        let roots
+            = self.workspaces.iter()
+                .filter(|w| options.roots.as_ref().map_or(true, |r| r.contains(&w.name)))
+                .map(|w| w.descriptor())
+                .collect();
+
+        let mut install = InstallManager::new()
+            .with_context(install_context)
+            .with_lockfile(lockfile?)
+            .with_previous_state(self.install_state.as_ref())
+            .with_roots(roots)
+            .with_constraints_check(!options.silent_or_error && self.config.settings.enable_constraints_checks.value && options.roots.is_none())
+            .with_skip_link_step(options.mode == Some(InstallMode::UpdateLockfile))
+            .with_skip_lockfile_update(options.roots.is_some() || options.prune_dev_dependencies)
+            .with_forced_builds(options.forced_builds)
+            .with_build_selection(options.build_selection)
+            .resolve_and_fetch().await?;
+
+        // The lockfile on disk still contains the conflict markers, so it
+        // must be rewritten even if the merged content didn't change.
+        install.lockfile_changed |= fixed_merge_conflicts;
+
+        Ok(install)
+    }
+}
 
-            Ok(install_result)
-        }).await
+/// A dependency from a workspace to another through the `workspace:`
+/// protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceDependency {
+    pub dependent: Ident,
+    pub dependency: Ident,
+
+    /// The manifest field declaring the dependency.
+    pub field: &'static str,
+}
+
+/// A set of workspaces depending on each other, along with the
+/// dependencies closing the cycle.
+#[derive(Debug, Clone)]
+pub struct WorkspaceCycle {
+    pub workspaces: Vec<Ident>,
+    pub dependencies: Vec<WorkspaceDependency>,
+}
+
+impl ToHumanString for WorkspaceCycle {
+    fn to_print_string(&self) -> String {
+        self.dependencies.iter()
+            .map(|dependency| format!("{} → {} (through {})", dependency.dependent.to_print_string(), dependency.dependency.to_print_string(), DataType::Code.colorize(dependency.field)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Sorts the given workspaces so that each of them comes after the ones it
+/// depends on. Dependencies involving workspaces outside of the given set
+/// are ignored.
+pub fn sort_workspaces(workspaces: impl IntoIterator<Item = Ident>, dependencies: &[WorkspaceDependency]) -> Result<Vec<Ident>, Vec<WorkspaceCycle>> {
+    let mut graph: BTreeMap<Ident, BTreeSet<Ident>>
+        = workspaces.into_iter()
+            .map(|ident| (ident, BTreeSet::new()))
+            .collect();
+
+    for dependency in dependencies {
+        if !graph.contains_key(&dependency.dependency) {
+            continue;
+        }
+
+        if let Some(edges) = graph.get_mut(&dependency.dependent) {
+            edges.insert(dependency.dependency.clone());
+        }
+    }
+
+    // The components are returned in reverse topological order, so the
+    // dependencies always come first
+    let components
+        = algos::scc_tarjan_pearce(&graph);
+
+    let cycles = components.iter()
+        .filter(|component| component.len() > 1 || graph[&component[0]].contains(&component[0]))
+        .map(|component| {
+            let members
+                = component.iter().collect::<BTreeSet<_>>();
+
+            let dependencies = dependencies.iter()
+                .filter(|dependency| members.contains(&dependency.dependent) && members.contains(&dependency.dependency))
+                .cloned()
+                .collect();
+
+            WorkspaceCycle {
+                workspaces: component.clone(),
+                dependencies,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !cycles.is_empty() {
+        return Err(cycles);
     }
+
+    Ok(components.into_iter().flatten().collect())
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,66 @@
+use super::*;
+
+fn dependency(dependent: &str, dependency: &str) -> WorkspaceDependency {
+    WorkspaceDependency {
+        dependent: Ident::new(dependent),
+        dependency: Ident::new(dependency),
+        field: "dependencies",
+    }
+}
+
+fn idents(names: &[&str]) -> Vec<Ident> {
+    names.iter()
+        .map(|name| Ident::new(*name))
+        .collect()
+}
+
+#[test]
+fn it_sorts_workspaces_after_their_dependencies() {
+    let dependencies = vec![
+        dependency("app", "lib"),
+        dependency("lib", "utils"),
+    ];
+
+    let order
+        = sort_workspaces(idents(&["app", "lib", "utils"]), &dependencies).unwrap();
+
+    assert_eq!(order, idents(&["utils", "lib", "app"]));
+}
+
+#[test]
+fn it_ignores_dependencies_outside_of_the_sorted_workspaces() {
+    let dependencies = vec![
+        dependency("app", "lib"),
+        dependency("lib", "utils"),
+    ];
+
+    let order
+        = sort_workspaces(idents(&["app", "lib"]), &dependencies).unwrap();
+
+    assert_eq!(order, idents(&["lib", "app"]));
+}
+
+#[test]
+fn it_reports_the_dependencies_closing_a_cycle() {
+    let dependencies = vec![
+        dependency("app", "lib"),
+        dependency("lib", "utils"),
+        dependency("utils", "lib"),
+    ];
+
+    let cycles
+        = sort_workspaces(idents(&["app", "lib", "utils"]), &dependencies).unwrap_err();
+
+    assert_eq!(cycles.len(), 1);
+
+    let mut cycle_workspaces
+        = cycles[0].workspaces.clone();
+
+    cycle_workspaces.sort();
+
+    assert_eq!(cycle_workspaces, idents(&["lib", "utils"]));
+    assert_eq!(cycles[0].dependencies, vec![
+        dependency("lib", "utils"),
+        dependency("utils", "lib"),
+    ]);
+}
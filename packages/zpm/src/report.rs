@@ -1,18 +1,53 @@
-use std::{cell::RefCell, future::Future, io::{self, Write}, sync::{Arc, LazyLock, atomic::AtomicU32, mpsc}, thread::JoinHandle, time::{Duration, SystemTime}};
+use std::{cell::RefCell, collections::BTreeMap, future::Future, io::{self, Write}, sync::{Arc, LazyLock, atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering}, mpsc}, thread::JoinHandle, time::{Duration, Instant, SystemTime}};
 
 use colored::{Color, Colorize};
 use dialoguer::{Input, Password};
 use itertools::Itertools;
+use regex::Regex;
+use serde::Serialize;
 use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
 use zpm_config::Configuration;
+use zpm_parsers::JsonDocument;
 use zpm_primitives::{Descriptor, Locator};
 use zpm_switch::get_bin_version;
-use zpm_utils::{DataType, Path, ToHumanString, Unit};
+use zpm_utils::{DataType, Path, ToFileString, ToHumanString, Unit};
 
-use crate::error::Error;
+use crate::error::{Error, ErrorCode};
 
 const TOP_LEVEL_PREFIX: char = '·';
 
+/// How many of the in-flight transfers are listed below the progress bar;
+/// the most recent ones are shown first.
+const MAX_DISPLAYED_TRANSFERS: usize = 5;
+
+/// Width of the progress bar, in characters.
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/// Minimum delay between two refreshes of the transfer progress, so that
+/// installs fetching many packages don't spend their time redrawing it.
+const PROGRESS_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// When the output isn't a terminal, the transfer progress is printed as
+/// regular lines instead, at this interval.
+const PLAIN_PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Set by the global `--json` flag, in which case all reports output JSON
+/// events regardless of the `jsonOutput` setting.
+static FORCE_JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+pub fn force_json_output() {
+    FORCE_JSON_OUTPUT.store(true, Ordering::Relaxed);
+}
+
+/// The version of the JSON events schema, included in each event. Berry's
+/// `{type, name, displayName, indent, data}` events are considered to be the
+/// version 1; this number must be bumped on any breaking change.
+const JSON_EVENTS_VERSION: u32 = 2;
+
+/// The messages are built by the callers, which don't know about the output
+/// format, so their color codes have to be stripped from the JSON events.
+static ANSI_ESCAPE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+
 pub static REPORT: LazyLock<RwLock<Option<StreamReport>>> = LazyLock::new(|| RwLock::new(None));
 
 pub async fn set_current_report(report: StreamReport) {
@@ -57,6 +92,7 @@ pub enum ReportContext {
 
 tokio::task_local! {
     static CONTEXT: RefCell<Option<ReportContext>>;
+    static TRANSFERRED_BYTES: Arc<AtomicU64>;
 }
 
 pub async fn with_report<F, R>(report: StreamReport, f: F) -> R where F: Future<Output = R> {
@@ -95,6 +131,13 @@ pub async fn with_context<F>(context: ReportContext, f: F) -> () where F: Future
     CONTEXT.scope(RefCell::new(Some(context)), f).await;
 }
 
+/// Runs the future, adding to the provided counter the bytes transferred by
+/// the transfers it starts. The counter remains valid even if the future is
+/// cancelled, which lets timeouts report how far the task got.
+pub async fn with_transfer_tracking<F>(transferred_bytes: Arc<AtomicU64>, f: F) -> F::Output where F: Future {
+    TRANSFERRED_BYTES.scope(transferred_bytes, f).await
+}
+
 pub async fn with_context_result<F, R>(context: ReportContext, f: F) -> Result<R, Error> where F: Future<Output = Result<R, Error>> {
     CONTEXT.scope(RefCell::new(Some(context)), async move {
         let res = f.await;
@@ -114,15 +157,20 @@ pub struct StreamReportConfig {
     pub enable_progress_bars: bool,
     pub enable_timers: bool,
     pub include_version: bool,
+    pub json_output: bool,
     pub silent_or_error: bool,
 }
 
 impl StreamReportConfig {
     pub fn from_config(config: &Configuration) -> Self {
+        let json_output
+            = config.settings.json_output.value || FORCE_JSON_OUTPUT.load(Ordering::Relaxed);
+
         Self {
-            enable_progress_bars: config.settings.enable_progress_bars.value,
+            enable_progress_bars: config.settings.enable_progress_bars.value && !json_output,
             enable_timers: config.settings.enable_timers.value,
             include_version: false,
+            json_output,
             silent_or_error: false,
         }
     }
@@ -162,17 +210,211 @@ pub struct ReportCounters {
     pub resolution_count: AtomicU32,
     pub fetch_count: AtomicU32,
     pub fetch_size: AtomicU32,
+    pub network_requests: AtomicU32,
+    pub transfers: std::sync::Mutex<Transfers>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TransferUnit {
+    Bytes,
+    Files,
+}
+
+impl TransferUnit {
+    fn format_amount(&self, amount: u64) -> String {
+        match self {
+            TransferUnit::Bytes => Unit::bytes(amount).to_print_string(),
+            TransferUnit::Files => DataType::Number.colorize(&amount.to_string()),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            TransferUnit::Bytes => "Fetching",
+            TransferUnit::Files => "Extracting",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TransferState {
+    label: Option<String>,
+    unit: TransferUnit,
+    total: Option<u64>,
+    transferred: u64,
+}
+
+/// The transfers (downloads, extractions, ...) started since the beginning
+/// of the current section. Those still in flight are kept individually so
+/// that the most recent ones can be listed; the others are only accounted
+/// for in the aggregate progress.
+#[derive(Debug, Default)]
+pub struct Transfers {
+    next_id: u64,
+    in_flight: BTreeMap<u64, TransferState>,
+    completed: BTreeMap<TransferUnit, u64>,
+}
+
+impl Transfers {
+    /// Returns the amount transferred and the expected total for the given
+    /// unit. In-flight transfers whose size isn't known yet count as if they
+    /// were complete, so they don't stall the bar until their size is known.
+    fn aggregate(&self, unit: TransferUnit) -> Option<(u64, u64)> {
+        let completed
+            = self.completed.get(&unit).copied();
+
+        let in_flight = self.in_flight.values()
+            .filter(|transfer| transfer.unit == unit)
+            .collect_vec();
+
+        if completed.is_none() && in_flight.is_empty() {
+            return None;
+        }
+
+        let mut transferred
+            = completed.unwrap_or(0);
+        let mut total
+            = completed.unwrap_or(0);
+
+        for transfer in in_flight {
+            transferred += transfer.transferred;
+            total += transfer.total.unwrap_or(transfer.transferred).max(transfer.transferred);
+        }
+
+        Some((transferred, total))
+    }
+
+    fn has_in_flight(&self) -> bool {
+        !self.in_flight.is_empty()
+    }
+}
+
+/// Handle on a transfer registered through `StreamReport::start_transfer`.
+/// The transfer is considered complete once the handle is dropped.
+#[derive(Debug)]
+pub struct Transfer {
+    counters: Arc<ReportCounters>,
+    id: u64,
+    unit: TransferUnit,
+}
+
+impl Transfer {
+    pub fn set_total(&self, total: u64) {
+        self.update(|transfer| transfer.total = Some(total));
+    }
+
+    pub fn advance(&self, amount: u64) {
+        if self.unit == TransferUnit::Bytes {
+            let _ = TRANSFERRED_BYTES.try_with(|transferred_bytes| {
+                transferred_bytes.fetch_add(amount, Ordering::Relaxed);
+            });
+        }
+
+        self.update(|transfer| transfer.transferred += amount);
+    }
+
+    fn update<F: FnOnce(&mut TransferState)>(&self, f: F) {
+        let mut transfers
+            = self.counters.transfers.lock().unwrap();
+
+        if let Some(transfer) = transfers.in_flight.get_mut(&self.id) {
+            f(transfer);
+        }
+    }
+}
+
+impl Drop for Transfer {
+    fn drop(&mut self) {
+        let mut transfers
+            = self.counters.transfers.lock().unwrap();
+
+        if let Some(transfer) = transfers.in_flight.remove(&self.id) {
+            *transfers.completed.entry(transfer.unit).or_default() += transfer.transferred;
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum ReportMessage {
-    Line(Severity, String),
+    Line(Severity, ErrorCode, String),
+    Resolution(Locator),
+    Fetch(FetchEvent),
     LogFile(Path),
     PushSection(String),
     PopSection,
     Prompt(PromptType),
 }
 
+#[derive(Debug)]
+pub struct FetchEvent {
+    pub locator: Locator,
+    pub size: u64,
+    pub cache_hit: bool,
+}
+
+/// The events printed (one per line) when the report is configured to
+/// output JSON. Their shape is part of our public interface: fields may be
+/// added, but not renamed nor removed.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonEvent<'a> {
+    Info {
+        message: &'a str,
+    },
+
+    Warning {
+        code: ErrorCode,
+        message: &'a str,
+    },
+
+    Error {
+        code: ErrorCode,
+        message: &'a str,
+    },
+
+    SectionStart {
+        name: &'a str,
+    },
+
+    SectionEnd {
+        name: &'a str,
+        #[serde(rename = "durationMs")]
+        duration_ms: u64,
+    },
+
+    Progress {
+        #[serde(rename = "resolutionCount")]
+        resolution_count: u32,
+        #[serde(rename = "fetchCount")]
+        fetch_count: u32,
+        #[serde(rename = "fetchSize")]
+        fetch_size: u32,
+    },
+
+    Resolution {
+        locator: String,
+    },
+
+    Fetch {
+        locator: String,
+        size: u64,
+        #[serde(rename = "cacheHit")]
+        cache_hit: bool,
+    },
+
+    LogFile {
+        path: String,
+        content: &'a str,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct JsonEnvelope<'a, 'b> {
+    version: u32,
+    #[serde(flatten)]
+    event: &'b JsonEvent<'a>,
+}
+
 struct Reporter {
     config: StreamReportConfig,
 
@@ -182,9 +424,14 @@ struct Reporter {
 
     last_message_type: Option<LastMessageType>,
     start_time: Option<SystemTime>,
+    json_sections: Vec<(String, SystemTime)>,
     buffered_lines: Option<Vec<String>>,
     log_paths: Vec<Path>,
     spinner_idx: Option<usize>,
+    spinner_height: usize,
+    progress_lines: Vec<String>,
+    progress_refreshed_at: Option<Instant>,
+    plain_progress_at: Option<Instant>,
     prompt_tx: mpsc::Sender<String>,
 }
 
@@ -193,6 +440,12 @@ impl Reporter {
         let buffered_lines
             = config.silent_or_error.then_some(Vec::new());
 
+        // Without progress bars, the transfer progress is only worth printing
+        // when nobody's watching the output as it's being written (CI logs,
+        // for instance); it would otherwise just add noise.
+        let plain_progress_at
+            = (!config.enable_progress_bars && !config.json_output && !zpm_utils::is_terminal()).then(Instant::now);
+
         Self {
             config,
             level: 0,
@@ -200,9 +453,14 @@ impl Reporter {
             counters,
             last_message_type: None,
             start_time: None,
+            json_sections: Vec::new(),
             buffered_lines,
             log_paths: Vec::new(),
             spinner_idx: None,
+            spinner_height: 0,
+            progress_lines: Vec::new(),
+            progress_refreshed_at: None,
+            plain_progress_at,
             prompt_tx,
         }
     }
@@ -211,6 +469,12 @@ impl Reporter {
         if self.spinner_idx.is_some() {
             if !self.config.silent_or_error && self.config.enable_progress_bars {
                 write!(writer, "\x1b[2K\r").unwrap();
+
+                for _ in 1..self.spinner_height {
+                    write!(writer, "\x1b[1A\x1b[2K").unwrap();
+                }
+
+                self.spinner_height = 0;
             }
         }
     }
@@ -226,11 +490,133 @@ impl Reporter {
                 let chars = "◴◷◶◵".chars().collect_vec();
                 write!(writer, "{} {}{} {}", prefix, indent, chars[spinner_idx], self.get_spinner_label()).unwrap();
 
+                self.refresh_progress_lines();
+
+                for line in &self.progress_lines {
+                    write!(writer, "\n{} {}  {}", prefix, indent, line).unwrap();
+                }
+
+                self.spinner_height = 1 + self.progress_lines.len();
                 self.spinner_idx = Some((spinner_idx + 1) % chars.len());
             }
         }
     }
 
+    fn format_progress_bar(transferred: u64, total: u64) -> String {
+        let filled = match total {
+            0 => PROGRESS_BAR_WIDTH,
+            _ => (transferred.min(total) as u128 * PROGRESS_BAR_WIDTH as u128 / total as u128) as usize,
+        };
+
+        format!("▕{}{}▏", "█".repeat(filled), "░".repeat(PROGRESS_BAR_WIDTH - filled))
+    }
+
+    /// Recomputes the lines describing the transfers in progress: one
+    /// aggregate progress bar per kind of transfer, followed by the most
+    /// recent transfers. Refreshes are throttled; the previous lines are
+    /// kept in the meantime.
+    fn refresh_progress_lines(&mut self) {
+        if self.progress_refreshed_at.is_some_and(|refreshed_at| refreshed_at.elapsed() < PROGRESS_REFRESH_INTERVAL) {
+            return;
+        }
+
+        self.progress_refreshed_at = Some(Instant::now());
+        self.progress_lines.clear();
+
+        let transfers
+            = self.counters.transfers.lock().unwrap();
+
+        if !transfers.has_in_flight() {
+            return;
+        }
+
+        let color
+            = DataType::Custom(144, 144, 144);
+
+        for unit in [TransferUnit::Bytes, TransferUnit::Files] {
+            let is_unit_in_flight = transfers.in_flight.values()
+                .any(|transfer| transfer.unit == unit);
+
+            if !is_unit_in_flight {
+                continue;
+            }
+
+            let Some((transferred, total)) = transfers.aggregate(unit) else {
+                continue;
+            };
+
+            self.progress_lines.push(color.colorize(&format!(
+                "{} {} {} / {}",
+                unit.label(),
+                Self::format_progress_bar(transferred, total),
+                unit.format_amount(transferred),
+                unit.format_amount(total),
+            )));
+        }
+
+        let recent_transfers = transfers.in_flight.values()
+            .rev()
+            .filter_map(|transfer| transfer.label.as_ref().map(|label| (label, transfer)))
+            .take(MAX_DISPLAYED_TRANSFERS);
+
+        for (label, transfer) in recent_transfers {
+            let amount = match transfer.total {
+                Some(total) => format!("{} / {}", transfer.unit.format_amount(transfer.transferred), transfer.unit.format_amount(total)),
+                None => transfer.unit.format_amount(transfer.transferred),
+            };
+
+            self.progress_lines.push(color.colorize(&format!("  {} ({})", label, amount)));
+        }
+    }
+
+    /// Prints the transfer progress as regular lines, for outputs that
+    /// can't display progress bars; see `plain_progress_at`.
+    pub fn write_plain_progress<T: Write>(&mut self, writer: &mut T) {
+        let Some(plain_progress_at) = self.plain_progress_at else {
+            return;
+        };
+
+        if plain_progress_at.elapsed() < PLAIN_PROGRESS_INTERVAL {
+            return;
+        }
+
+        self.plain_progress_at = Some(Instant::now());
+
+        let mut lines
+            = Vec::new();
+
+        {
+            let transfers
+                = self.counters.transfers.lock().unwrap();
+
+            for unit in [TransferUnit::Bytes, TransferUnit::Files] {
+                let in_flight_count = transfers.in_flight.values()
+                    .filter(|transfer| transfer.unit == unit)
+                    .count();
+
+                if in_flight_count == 0 {
+                    continue;
+                }
+
+                let Some((transferred, total)) = transfers.aggregate(unit) else {
+                    continue;
+                };
+
+                lines.push(format!(
+                    "{}: {} / {} ({} in progress)",
+                    unit.label(),
+                    unit.format_amount(transferred),
+                    unit.format_amount(total),
+                    DataType::Number.colorize(&in_flight_count.to_string()),
+                ));
+            }
+        }
+
+        for line in lines {
+            self.write_line(writer, &line, Severity::Info);
+        }
+    }
+
     fn get_plural_packages(count: u32) -> &'static str {
         if count == 1 {
             "package"
@@ -240,6 +626,32 @@ impl Reporter {
     }
 
     fn get_spinner_label(&self) -> String {
+        let network_requests
+            = self.counters.network_requests.load(std::sync::atomic::Ordering::Relaxed);
+
+        let label
+            = self.get_counters_label();
+
+        if network_requests == 0 {
+            return label;
+        }
+
+        let color
+            = DataType::Custom(144, 144, 144);
+
+        let network_label = color.colorize(&format!(
+            "{} {} in flight",
+            DataType::Number.colorize(&network_requests.to_string()),
+            if network_requests == 1 { "request" } else { "requests" },
+        ));
+
+        match label.is_empty() {
+            true => network_label,
+            false => format!("{} {} {}", label, color.colorize("·"), network_label),
+        }
+    }
+
+    fn get_counters_label(&self) -> String {
         let resolution_count
             = self.counters.resolution_count.load(std::sync::atomic::Ordering::Relaxed);
         let fetch_count
@@ -278,11 +690,19 @@ impl Reporter {
     }
 
     pub fn report<T: Write>(&mut self, writer: &mut T, message: ReportMessage) {
+        if self.config.json_output {
+            return self.report_json(writer, message);
+        }
+
         match message {
-            ReportMessage::Line(severity, message) => {
+            ReportMessage::Line(severity, _, message) => {
                 self.on_line(writer, severity, &message);
             },
 
+            ReportMessage::Resolution(_) | ReportMessage::Fetch(_) => {
+                // Already accounted for by the counters displayed in the spinner
+            },
+
             ReportMessage::LogFile(log_path) => {
                 self.log_paths.push(log_path);
             },
@@ -301,6 +721,89 @@ impl Reporter {
         }
     }
 
+    fn report_json<T: Write>(&mut self, writer: &mut T, message: ReportMessage) {
+        match message {
+            ReportMessage::Line(severity, code, message) => {
+                if severity == Severity::Error {
+                    self.stop_buffering(writer);
+                }
+
+                let message
+                    = ANSI_ESCAPE_REGEX.replace_all(&message, "");
+
+                self.write_json_event(writer, &match severity {
+                    Severity::Info => JsonEvent::Info {message: &message},
+                    Severity::Warning => JsonEvent::Warning {code, message: &message},
+                    Severity::Error => JsonEvent::Error {code, message: &message},
+                });
+            },
+
+            ReportMessage::Resolution(locator) => {
+                self.write_json_event(writer, &JsonEvent::Resolution {
+                    locator: locator.to_file_string(),
+                });
+            },
+
+            ReportMessage::Fetch(event) => {
+                self.write_json_event(writer, &JsonEvent::Fetch {
+                    locator: event.locator.to_file_string(),
+                    size: event.size,
+                    cache_hit: event.cache_hit,
+                });
+            },
+
+            ReportMessage::LogFile(log_path) => {
+                self.log_paths.push(log_path);
+            },
+
+            ReportMessage::PushSection(name) => {
+                let name
+                    = ANSI_ESCAPE_REGEX.replace_all(&name, "").into_owned();
+
+                self.write_json_event(writer, &JsonEvent::SectionStart {name: &name});
+                self.json_sections.push((name, SystemTime::now()));
+            },
+
+            ReportMessage::PopSection => {
+                let (name, start_time) = self.json_sections.pop()
+                    .expect("Cannot pop section when no sections are pushed");
+
+                let resolution_count
+                    = self.counters.resolution_count.swap(0, std::sync::atomic::Ordering::Relaxed);
+                let fetch_count
+                    = self.counters.fetch_count.swap(0, std::sync::atomic::Ordering::Relaxed);
+                let fetch_size
+                    = self.counters.fetch_size.swap(0, std::sync::atomic::Ordering::Relaxed);
+
+                self.reset_transfers();
+
+                if resolution_count > 0 || fetch_count > 0 {
+                    self.write_json_event(writer, &JsonEvent::Progress {resolution_count, fetch_count, fetch_size});
+                }
+
+                let duration_ms = start_time.elapsed()
+                    .map_or(0, |elapsed| elapsed.as_millis() as u64);
+
+                self.write_json_event(writer, &JsonEvent::SectionEnd {name: &name, duration_ms});
+            },
+
+            ReportMessage::Prompt(prompt) => {
+                self.on_prompt(writer, prompt);
+            },
+        }
+    }
+
+    fn write_json_event<T: Write>(&mut self, writer: &mut T, event: &JsonEvent) {
+        let line
+            = JsonDocument::to_string(&JsonEnvelope {version: JSON_EVENTS_VERSION, event}).unwrap();
+
+        if let Some(buffered_lines) = &mut self.buffered_lines {
+            buffered_lines.push(line);
+        } else {
+            writeln!(writer, "{}", line).unwrap();
+        }
+    }
+
     fn on_start<T: Write>(&mut self, writer: &mut T) {
         if self.config.enable_progress_bars {
             writer.write_all(b"\x1b[?25l").unwrap();
@@ -308,6 +811,19 @@ impl Reporter {
     }
 
     fn on_end<T: Write>(&mut self, writer: &mut T) {
+        if self.config.json_output {
+            for log_path in std::mem::take(&mut self.log_paths) {
+                let log_content = log_path.fs_read_text().unwrap();
+
+                self.write_json_event(writer, &JsonEvent::LogFile {
+                    path: log_path.to_file_string(),
+                    content: &log_content,
+                });
+            }
+
+            return;
+        }
+
         for log_path in &self.log_paths {
             writeln!(writer, "\n{}\n", log_path.to_print_string()).unwrap();
 
@@ -392,12 +908,14 @@ impl Reporter {
         self.spinner_idx = None;
 
         let spinner_label
-            = self.get_spinner_label();
+            = self.get_counters_label();
 
         self.counters.resolution_count.store(0, std::sync::atomic::Ordering::Relaxed);
         self.counters.fetch_count.store(0, std::sync::atomic::Ordering::Relaxed);
         self.counters.fetch_size.store(0, std::sync::atomic::Ordering::Relaxed);
 
+        self.reset_transfers();
+
         if !spinner_label.is_empty() {
             self.write_line(writer, &spinner_label, Severity::Info);
         }
@@ -413,6 +931,13 @@ impl Reporter {
         self.level -= 1;
     }
 
+    fn reset_transfers(&mut self) {
+        self.counters.transfers.lock().unwrap().completed.clear();
+
+        self.progress_lines.clear();
+        self.progress_refreshed_at = None;
+    }
+
     fn format_indent(&self) -> String {
         if self.level > 0 {
             "│ ".repeat(self.indent)
@@ -487,7 +1012,10 @@ impl StreamReport {
             }
 
             if reporter.config.include_version {
-                reporter.write_line(&mut io::stdout(), &format!("Yarn {}", get_bin_version()).bold().to_string(), Severity::Info);
+                let version_line
+                    = format!("Yarn {}", get_bin_version()).bold().to_string();
+
+                reporter.report(&mut io::stdout(), ReportMessage::Line(Severity::Info, ErrorCode::Unnamed, version_line));
             }
 
             loop {
@@ -507,6 +1035,7 @@ impl StreamReport {
                     reporter.report(&mut stdout, msg);
                 }
 
+                reporter.write_plain_progress(&mut stdout);
                 reporter.write_spinner(&mut stdout);
 
                 stdout.flush().unwrap();
@@ -526,17 +1055,66 @@ impl StreamReport {
         }
     }
 
+    /// Create a report that forwards its messages to the provided channel
+    /// instead of printing them. Prompts are answered with an empty string,
+    /// so callers embedding zpm should avoid code paths that require them.
+    ///
+    pub fn with_sink(sink: mpsc::Sender<ReportMessage>) -> Self {
+        let counters
+            = Arc::new(ReportCounters::default());
+
+        let (break_request_tx, break_request_rx)
+            = mpsc::channel::<bool>();
+        let (msg_queue_tx, msg_queue_rx)
+            = mpsc::channel::<ReportMessage>();
+        let (prompt_tx, prompt_rx)
+            = mpsc::channel::<String>();
+
+        let handle = std::thread::spawn(move || {
+            loop {
+                let break_request
+                    = break_request_rx.recv_timeout(Duration::from_millis(50));
+
+                for msg in msg_queue_rx.try_iter() {
+                    if let ReportMessage::Prompt(_) = &msg {
+                        let _ = prompt_tx.send(String::new());
+                    }
+
+                    // The caller may have dropped the receiving end; it's not
+                    // our responsibility to keep their messages around.
+                    let _ = sink.send(msg);
+                }
+
+                if break_request == Ok(true) {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            counters,
+            handle,
+            break_request_tx,
+            msg_queue_tx,
+            prompt_rx: Mutex::new(prompt_rx),
+        }
+    }
+
     pub fn info(&self, message: String) {
-        self.report(ReportMessage::Line(Severity::Info, self.with_content_prefix(message)));
+        self.report(ReportMessage::Line(Severity::Info, ErrorCode::Unnamed, self.with_content_prefix(message)));
     }
 
     pub fn warn(&self, message: String) {
-        self.report(ReportMessage::Line(Severity::Warning, self.with_content_prefix(message)));
+        self.warn_with_code(ErrorCode::Unnamed, message);
+    }
+
+    pub fn warn_with_code(&self, code: ErrorCode, message: String) {
+        self.report(ReportMessage::Line(Severity::Warning, code, self.with_content_prefix(message)));
     }
 
     pub fn error(&self, error: Error) {
         if !matches!(error, Error::SilentError) {
-            self.report(ReportMessage::Line(Severity::Error, self.with_content_prefix(error.to_string())));
+            self.report(ReportMessage::Line(Severity::Error, error.code(), self.with_content_prefix(error.to_string())));
         }
 
         if let Error::ChildProcessFailedWithLog(_, log_path) = error {
@@ -544,6 +1122,14 @@ impl StreamReport {
         }
     }
 
+    pub fn resolution(&self, locator: Locator) {
+        self.report(ReportMessage::Resolution(locator));
+    }
+
+    pub fn fetch(&self, locator: Locator, size: u64, cache_hit: bool) {
+        self.report(ReportMessage::Fetch(FetchEvent {locator, size, cache_hit}));
+    }
+
     pub fn push_section(&self, name: String) {
         self.report(ReportMessage::PushSection(name));
     }
@@ -552,27 +1138,49 @@ impl StreamReport {
         self.report(ReportMessage::PopSection);
     }
 
-    fn with_content_prefix(&self, mut message: String) -> String {
-        CONTEXT.with(move |context: &RefCell<Option<ReportContext>>| {
-            let context
-                = context.borrow();
+    /// Registers a new transfer, whose progress will be displayed until the
+    /// returned handle is dropped. The label defaults to the package the
+    /// current task is working on, if any.
+    pub fn start_transfer(&self, unit: TransferUnit, label: Option<String>, total: Option<u64>) -> Transfer {
+        let label
+            = label.or_else(Self::context_label);
 
-            let Some(context) = context.as_ref() else {
-                return message;
-            };
+        let mut transfers
+            = self.counters.transfers.lock().unwrap();
+
+        let id
+            = transfers.next_id;
+
+        transfers.next_id += 1;
+        transfers.in_flight.insert(id, TransferState {label, unit, total, transferred: 0});
 
-            let prefix = match context {
+        Transfer {
+            counters: self.counters.clone(),
+            id,
+            unit,
+        }
+    }
+
+    fn context_label() -> Option<String> {
+        CONTEXT.try_with(|context: &RefCell<Option<ReportContext>>| {
+            context.borrow().as_ref().map(|context| match context {
                 ReportContext::Descriptor(descriptor) => descriptor.to_print_string(),
                 ReportContext::Locator(locator) => locator.to_print_string(),
-            };
+            })
+        }).ok().flatten()
+    }
+
+    fn with_content_prefix(&self, mut message: String) -> String {
+        let Some(prefix) = Self::context_label() else {
+            return message;
+        };
 
-            message.reserve(prefix.len() + 2 + message.len());
+        message.reserve(prefix.len() + 2 + message.len());
 
-            message.insert_str(0, &prefix);
-            message.insert_str(prefix.len(), ": ");
+        message.insert_str(0, &prefix);
+        message.insert_str(prefix.len(), ": ");
 
-            message
-        })
+        message
     }
 
     fn report(&self, message: ReportMessage) {
@@ -0,0 +1,40 @@
+use zpm_primitives::{Descriptor, ExecRange, ExecReference, Locator};
+use zpm_utils::Hash64;
+
+use crate::{
+    error::Error,
+    fetchers,
+    install::{InstallContext, InstallOpResult, IntoResolutionResult, ResolutionResult},
+};
+
+pub async fn resolve_descriptor(context: &InstallContext<'_>, descriptor: &Descriptor, params: &ExecRange, dependencies: Vec<InstallOpResult>) -> Result<ResolutionResult, Error> {
+    let parent_data
+        = dependencies[0].as_fetched();
+
+    let script_path = parent_data.package_data
+        .context_directory()
+        .with_join_str(&params.path);
+
+    // The script hash is part of the reference so that editing the generator
+    // yields a new locator (and thus a new cache entry) rather than silently
+    // reusing the package generated by a previous version of the script.
+    let hash
+        = Hash64::from_data(script_path.fs_read()?);
+
+    let locator = descriptor.resolve_with(ExecReference {
+        path: params.path.clone(),
+        hash,
+    }.into());
+
+    let fetch_result
+        = fetchers::fetch_locator(context.clone(), &locator, false, dependencies).await?;
+
+    fetch_result.into_resolution_result(context)
+}
+
+pub async fn resolve_locator(context: &InstallContext<'_>, locator: &Locator, _params: &ExecReference, dependencies: Vec<InstallOpResult>) -> Result<ResolutionResult, Error> {
+    let fetch_result
+        = fetchers::fetch_locator(context.clone(), locator, false, dependencies).await?;
+
+    fetch_result.into_resolution_result(context)
+}
@@ -4,9 +4,23 @@ use crate::{
     error::Error,
     fetchers,
     install::{InstallContext, InstallOpResult, IntoResolutionResult, ResolutionResult},
+    resolvers::tarball,
 };
 
 pub async fn resolve_descriptor(context: &InstallContext<'_>, descriptor: &Descriptor, range: &FolderRange, dependencies: Vec<InstallOpResult>) -> Result<ResolutionResult, Error> {
+    let parent_data
+        = dependencies[0].as_fetched();
+
+    let target_path = parent_data.package_data
+        .context_directory()
+        .with_join_str(&range.path);
+
+    // Tarballs without a conventional extension are parsed as folders, so we
+    // check the magic bytes of the target to route them to the right fetcher.
+    if target_path.fs_is_file() && zpm_formats::tar::is_tarball(&target_path.fs_read()?) {
+        return tarball::resolve_tarball(context, descriptor, &range.path, &target_path, dependencies).await;
+    }
+
     let locator = descriptor.resolve_with(FolderReference {
         path: range.path.to_string(),
     }.into());
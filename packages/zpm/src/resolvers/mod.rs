@@ -11,6 +11,7 @@ use crate::{
 
 pub mod builtin;
 pub mod catalog;
+pub mod exec;
 pub mod folder;
 pub mod git;
 pub mod link;
@@ -204,6 +205,9 @@ pub async fn resolve_descriptor(context: InstallContext<'_>, descriptor: Descrip
         Range::Patch(params)
             => patch::resolve_descriptor(&context, &descriptor, params, dependencies).await,
 
+        Range::Exec(params)
+            => exec::resolve_descriptor(&context, &descriptor, params, dependencies).await,
+
         Range::Tarball(params)
             => tarball::resolve_descriptor(&context, &descriptor, params, dependencies).await,
 
@@ -251,6 +255,9 @@ pub async fn resolve_locator(context: InstallContext<'_>, locator: Locator, depe
         Reference::Url(params)
             => url::resolve_locator(&context, &locator, params).await,
 
+        Reference::Exec(params)
+            => exec::resolve_locator(&context, &locator, params, dependencies).await,
+
         Reference::Tarball(params)
             => tarball::resolve_locator(&context, &locator, params, dependencies).await,
 
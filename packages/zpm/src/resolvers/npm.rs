@@ -1,19 +1,22 @@
 use std::{collections::BTreeMap, str::FromStr, sync::LazyLock};
 
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use regex::Regex;
 use serde::Deserialize;
-use serde_with::{serde_as, MapSkipError};
+use serde_with::{serde_as, DefaultOnError, MapSkipError};
 use zpm_parsers::{JsonDocument, RawJsonValue};
 use zpm_primitives::{AnonymousSemverRange, Descriptor, Ident, Locator, Reference, RegistryReference, RegistrySemverRange, RegistryTagRange};
-use zpm_utils::UrlEncoded;
+use zpm_utils::{ToFileString, ToHumanString, UrlEncoded};
 
 use crate::{
-    error::Error,
+    error::{Error, ErrorCode},
     http_npm,
     install::{InstallContext, InstallOpResult, IntoResolutionResult, ResolutionResult},
     manifest::RemoteManifest,
     npm,
+    project::Project,
+    report::current_report,
     resolvers::{Resolution, workspace},
 };
 
@@ -23,6 +26,7 @@ static NODE_GYP_MATCH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b(node-g
 /**
  * We need to read the scripts to figure out whether the package has an implicit node-gyp dependency.
  */
+#[serde_as]
 #[derive(Clone, Deserialize, Debug)]
 struct RemoteManifestWithScripts {
     #[serde(flatten)]
@@ -31,6 +35,28 @@ struct RemoteManifestWithScripts {
     #[serde(default)]
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     scripts: BTreeMap<String, String>,
+
+    /// Set by the registry in abbreviated packuments (which don't include
+    /// the scripts) when the version has install scripts.
+    #[serde(default)]
+    #[serde(rename = "hasInstallScript")]
+    has_install_script: bool,
+
+    /// Set by the registry when the version got deprecated; some registries
+    /// use `false` (or an empty or blank string) to say that it isn't.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnError")]
+    deprecated: Option<String>,
+}
+
+async fn report_deprecation(package_ident: &Ident, version: &zpm_semver::Version, manifest: &RemoteManifestWithScripts) {
+    let Some(message) = manifest.deprecated.as_deref().filter(|message| !message.trim().is_empty()) else {
+        return;
+    };
+
+    current_report().await.as_ref().map(|report| {
+        report.warn_with_code(ErrorCode::DeprecatedPackage, format!("{}@{} is deprecated: {}", package_ident.to_print_string(), version.to_print_string(), message));
+    });
 }
 
 fn fix_manifest(manifest: &mut RemoteManifestWithScripts) {
@@ -153,13 +179,39 @@ pub fn resolve_aliased(descriptor: &Descriptor, dependencies: Vec<InstallOpResul
     Ok(inner_resolution)
 }
 
-pub async fn resolve_semver_descriptor(context: &InstallContext<'_>, descriptor: &Descriptor, params: &RegistrySemverRange) -> Result<ResolutionResult, Error> {
+/// The subset of a packument needed to pick a version. The version entries
+/// are kept as raw JSON, so only the one that gets selected ends up being
+/// deserialized.
+#[serde_as]
+#[derive(Deserialize)]
+pub struct RegistryMetadata<'a> {
+    #[serde(default, rename(deserialize = "dist-tags"))]
+    pub dist_tags: BTreeMap<String, zpm_semver::Version>,
+
+    #[serde_as(as = "Option<MapSkipError<_, _>>")]
+    pub time: Option<BTreeMap<zpm_semver::Version, DateTime<Utc>>>,
+
+    #[serde(borrow)]
+    pub versions: BTreeMap<zpm_semver::Version, RawJsonValue<'a>>,
+}
+
+async fn fetch_packument(context: &InstallContext<'_>, package_ident: &Ident) -> Result<Bytes, Error> {
     let project = context.project
         .expect("The project is required for resolving a workspace package");
 
-    let package_ident = params.ident.as_ref()
-        .unwrap_or(&descriptor.ident);
+    // Abbreviated packuments don't list the release times, so we can only
+    // use them when the minimal age gate doesn't need them.
+    let format = match project.config.settings.npm_minimal_age_gate.value {
+        Some(_) => http_npm::PackumentFormat::Full,
+        None => http_npm::PackumentFormat::Abbreviated,
+    };
 
+    fetch_packument_with_format(project, package_ident, format, !context.refresh_lockfile).await
+}
+
+/// Retrieves the packument of the given package from the registry it's
+/// configured to be fetched from, using the credentials configured for it.
+pub async fn fetch_packument_with_format(project: &Project, package_ident: &Ident, format: http_npm::PackumentFormat, use_metadata_cache: bool) -> Result<Bytes, Error> {
     let registry_base
         = http_npm::get_registry(&project.config, package_ident.scope(), false)?;
     let registry_path
@@ -175,6 +227,45 @@ pub async fn resolve_semver_descriptor(context: &InstallContext<'_>, descriptor:
             allow_oidc: false,
         }).await?;
 
+    http_npm::get_packument(&http_npm::NpmHttpParams {
+        http_client: &project.http_client,
+        registry: &registry_base,
+        path: &registry_path,
+        authorization: authorization.as_deref(),
+        otp: None,
+    }, package_ident, format, use_metadata_cache).await
+}
+
+/// Abbreviated packuments don't include the scripts, which we need to detect
+/// implicit node-gyp dependencies. Since the registry flags the versions
+/// that have install scripts, we only need to fetch the full manifest for
+/// those.
+async fn hydrate_manifest(context: &InstallContext<'_>, package_ident: &Ident, version: &zpm_semver::Version, manifest: &RawJsonValue<'_>) -> Result<RemoteManifestWithScripts, Error> {
+    let manifest: RemoteManifestWithScripts
+        = JsonDocument::hydrate_from_value(manifest)?;
+
+    if !manifest.has_install_script || !manifest.scripts.is_empty() {
+        return Ok(manifest);
+    }
+
+    let project = context.project
+        .expect("The project is required for resolving a workspace package");
+
+    let registry_base
+        = http_npm::get_registry(&project.config, package_ident.scope(), false)?;
+    let registry_path
+        = npm::registry_url_for_one_version(package_ident, version);
+
+    let authorization
+        = http_npm::get_authorization(&http_npm::GetAuthorizationOptions {
+            configuration: &project.config,
+            http_client: &project.http_client,
+            registry: &registry_base,
+            ident: Some(package_ident),
+            auth_mode: http_npm::AuthorizationMode::RespectConfiguration,
+            allow_oidc: false,
+        }).await?;
+
     let bytes
         = http_npm::get(&http_npm::NpmHttpParams {
             http_client: &project.http_client,
@@ -184,14 +275,18 @@ pub async fn resolve_semver_descriptor(context: &InstallContext<'_>, descriptor:
             otp: None,
         }).await?;
 
-    #[serde_as]
-    #[derive(Deserialize)]
-    struct RegistryMetadata<'a> {
-        #[serde_as(as = "Option<MapSkipError<_, _>>")]
-        time: Option<BTreeMap<zpm_semver::Version, DateTime<Utc>>>,
-        #[serde(borrow)]
-        versions: BTreeMap<zpm_semver::Version, RawJsonValue<'a>>,
-    }
+    Ok(JsonDocument::hydrate_from_slice(&bytes[..])?)
+}
+
+pub async fn resolve_semver_descriptor(context: &InstallContext<'_>, descriptor: &Descriptor, params: &RegistrySemverRange) -> Result<ResolutionResult, Error> {
+    let project = context.project
+        .expect("The project is required for resolving a workspace package");
+
+    let package_ident = params.ident.as_ref()
+        .unwrap_or(&descriptor.ident);
+
+    let bytes
+        = fetch_packument(context, package_ident).await?;
 
     let registry_data: RegistryMetadata
         = JsonDocument::hydrate_from_slice(&bytes[..])?;
@@ -214,58 +309,47 @@ pub async fn resolve_semver_descriptor(context: &InstallContext<'_>, descriptor:
         }
 
         let manifest
-            = JsonDocument::hydrate_from_value(manifest)?;
+            = hydrate_manifest(context, package_ident, version, manifest).await?;
+
+        report_deprecation(package_ident, version, &manifest).await;
 
         return build_resolution_result(context, descriptor, package_ident, version.clone(), manifest);
     }
 
-    Err(Error::NoCandidatesFound(descriptor.range.clone()))
+    // Some registries publish dist-tags that also happen to be valid ranges
+    // (for example `v2`); we give them a chance before failing
+    let tagged_version = registry_data.dist_tags.get(&params.range.to_file_string())
+        .and_then(|version| registry_data.versions.get_key_value(version));
+
+    if let Some((version, manifest)) = tagged_version {
+        let manifest
+            = hydrate_manifest(context, package_ident, version, manifest).await?;
+
+        report_deprecation(package_ident, version, &manifest).await;
+
+        return build_resolution_result(context, descriptor, package_ident, version.clone(), manifest);
+    }
+
+    if registry_data.dist_tags.is_empty() {
+        return Err(Error::NoCandidatesFound(descriptor.range.clone()));
+    }
+
+    let available_tags = registry_data.dist_tags.iter()
+        .map(|(tag, version)| format!("{} ({})", tag, version.to_print_string()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Err(Error::NoCandidatesFoundWithTags(descriptor.range.clone(), available_tags))
 }
 
 pub async fn resolve_tag_descriptor(context: &InstallContext<'_>, descriptor: &Descriptor, params: &RegistryTagRange) -> Result<ResolutionResult, Error> {
-    let project = context.project
-        .expect("The project is required for resolving a workspace package");
-
     let package_ident = params.ident.as_ref()
         .unwrap_or(&descriptor.ident);
 
-    let registry_base
-        = http_npm::get_registry(&project.config, package_ident.scope(), false)?;
-    let registry_path
-        = npm::registry_url_for_all_versions(&package_ident);
-
-    let authorization
-        = http_npm::get_authorization(&http_npm::GetAuthorizationOptions {
-            configuration: &project.config,
-            http_client: &project.http_client,
-            registry: &registry_base,
-            ident: Some(package_ident),
-            auth_mode: http_npm::AuthorizationMode::RespectConfiguration,
-            allow_oidc: false,
-        }).await?;
-
     let bytes
-        = http_npm::get(&http_npm::NpmHttpParams {
-            http_client: &project.http_client,
-            registry: &registry_base,
-            path: &registry_path,
-            authorization: authorization.as_deref(),
-            otp: None,
-        }).await?;
+        = fetch_packument(context, package_ident).await?;
 
-    #[serde_as]
-    #[derive(Deserialize)]
-    struct RegistryMetadata<'a> {
-        #[serde(rename(deserialize = "dist-tags"))]
-        dist_tags: BTreeMap<String, zpm_semver::Version>,
-        #[serde_as(as = "Option<MapSkipError<_, _>>")]
-        time: Option<BTreeMap<zpm_semver::Version, DateTime<Utc>>>,
-        #[serde(borrow)]
-        versions: BTreeMap<zpm_semver::Version, RawJsonValue<'a>>,
-    }
-
-    // Added lifetime bound to fix 'lifetime may not live long enough'
-    let registry_data: RegistryMetadata<'_>
+    let registry_data: RegistryMetadata
         = JsonDocument::hydrate_from_slice(&bytes[..])?;
 
     let latest_version
@@ -274,20 +358,39 @@ pub async fn resolve_tag_descriptor(context: &InstallContext<'_>, descriptor: &D
         .ok_or_else(|| Error::TagNotFound(params.tag.clone()))?;
 
     let time
-        = registry_data.time;
+        = registry_data.time.as_ref();
 
     let (version, manifest)
-        = registry_data.versions.into_iter()
+        = registry_data.versions.iter()
             .rev()
-            .filter(|(version, _)| version <= &latest_version)
+            .filter(|(version, _)| *version <= latest_version)
             .filter(|(version, _)| !version.rc.is_some() || latest_version.rc.is_some())
-            .find(|(version, _)| is_package_approved(context, package_ident, version, time.as_ref().and_then(|map| map.get(version))))
+            .find(|(version, _)| is_package_approved(context, package_ident, version, time.and_then(|map| map.get(*version))))
             .ok_or_else(|| Error::NoCandidatesFound(AnonymousSemverRange {range: zpm_semver::Range::lte(latest_version.clone())}.into()))?;
 
     let manifest
-        = JsonDocument::hydrate_from_value(&manifest)?;
+        = hydrate_manifest(context, package_ident, version, manifest).await?;
+
+    report_deprecation(package_ident, version, &manifest).await;
+
+    build_resolution_result(context, descriptor, package_ident, version.clone(), manifest)
+}
+
+/// Returns the versions of the given package that can be resolved, sorted
+/// from the oldest to the most recent one. Versions rejected by the minimal
+/// age gate aren't included.
+pub async fn resolve_versions(context: &InstallContext<'_>, package_ident: &Ident) -> Result<Vec<zpm_semver::Version>, Error> {
+    let bytes
+        = fetch_packument(context, package_ident).await?;
+
+    let registry_data: RegistryMetadata
+        = JsonDocument::hydrate_from_slice(&bytes[..])?;
+
+    let versions = registry_data.versions.into_keys()
+        .filter(|version| is_package_approved(context, package_ident, version, registry_data.time.as_ref().and_then(|map| map.get(version))))
+        .collect();
 
-    build_resolution_result(context, descriptor, package_ident, version, manifest)
+    Ok(versions)
 }
 
 pub async fn resolve_locator(context: &InstallContext<'_>, locator: &Locator, params: &RegistryReference) -> Result<ResolutionResult, Error> {
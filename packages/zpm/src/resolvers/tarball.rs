@@ -1,4 +1,5 @@
 use zpm_primitives::{Descriptor, Locator, TarballRange, TarballReference};
+use zpm_utils::{Hash64, Path};
 
 use crate::{
     error::Error,
@@ -7,8 +8,28 @@ use crate::{
 };
 
 pub async fn resolve_descriptor(context: &InstallContext<'_>, descriptor: &Descriptor, params: &TarballRange, dependencies: Vec<InstallOpResult>) -> Result<ResolutionResult, Error> {
+    let parent_data
+        = dependencies[0].as_fetched();
+
+    let tarball_path = parent_data.package_data
+        .context_directory()
+        .with_join_str(&params.path);
+
+    resolve_tarball(context, descriptor, &params.path, &tarball_path, dependencies).await
+}
+
+/// Resolves a `file:` descriptor pointing at a tarball. Local tarballs are
+/// often rebuilt in place (for instance by `npm pack` in a sibling folder)
+/// without their version changing, so the reference includes the hash of the
+/// archive; otherwise the lockfile would keep pointing at the cache entry
+/// created from the first build.
+pub async fn resolve_tarball(context: &InstallContext<'_>, descriptor: &Descriptor, path: &str, tarball_path: &Path, dependencies: Vec<InstallOpResult>) -> Result<ResolutionResult, Error> {
+    let hash
+        = Hash64::from_data(tarball_path.fs_read()?);
+
     let locator = descriptor.resolve_with(TarballReference {
-        path: params.path.clone(),
+        path: path.to_string(),
+        hash: Some(hash),
     }.into());
 
     let fetch_result
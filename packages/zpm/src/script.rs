@@ -1,22 +1,32 @@
-use std::{collections::BTreeMap, ffi::OsStr, fs::Permissions, io::Read, os::unix::{fs::PermissionsExt, process::ExitStatusExt}, process::{ExitStatus, Output}, sync::{Arc, LazyLock}};
+use std::{collections::BTreeMap, ffi::OsStr, fs::Permissions, io::Read, os::unix::{fs::PermissionsExt, process::ExitStatusExt}, process::{ExitStatus, Output}, sync::{Arc, LazyLock}, time::Duration};
 
 use serde::{Deserialize, Serialize};
 use zpm_parsers::JsonDocument;
 use zpm_primitives::Locator;
-use zpm_utils::{shell_escape, to_shell_line, FromFileString, Hash64, Path, ToFileString};
+use zpm_utils::{shell_escape, to_shell_line, wait_with_forwarded_output, FromFileString, Hash64, OutputMode, Path, ToFileString};
 use itertools::Itertools;
 use regex::Regex;
 use tokio::process::Command;
 
+use zpm_config::NodeLinker;
+
 use crate::{
     error::Error,
-    project::Project,
+    http_npm,
+    project::{Project, Workspace},
 };
 
-static CJS_LOADER_MATCHER: LazyLock<Regex> = LazyLock::new(|| regex::Regex::new(r"\s*--require\s+\S*\.pnp\.c?js\s*").unwrap());
-static ESM_LOADER_MATCHER: LazyLock<Regex> = LazyLock::new(|| regex::Regex::new(r"\s*--experimental-loader\s+\S*\.pnp\.loader\.mjs\s*").unwrap());
+#[cfg(test)]
+#[path = "./script.test.rs"]
+mod script_tests;
+
+static CJS_LOADER_MATCHER: LazyLock<Regex> = LazyLock::new(|| regex::Regex::new(r#"\s*--require\s+(?:"(?:[^"\\]|\\.)*\.pnp\.c?js"|\S*\.pnp\.c?js)\s*"#).unwrap());
+static ESM_LOADER_MATCHER: LazyLock<Regex> = LazyLock::new(|| regex::Regex::new(r#"\s*--experimental-loader\s+(?:"(?:[^"\\]|\\.)*\.pnp\.loader\.mjs"|\S*\.pnp\.loader\.mjs)\s*"#).unwrap());
 static JS_EXTENSION: LazyLock<Regex> = LazyLock::new(|| regex::Regex::new(r"\.[cm]?[jt]sx?$").unwrap());
 
+/// How long a timed out script gets to exit after SIGTERM before we SIGKILL it.
+const SCRIPT_KILL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
 fn make_path_wrapper(bin_dir: &Path, name: &str, argv0: &str, args: &Vec<String>) -> Result<(), Error> {
     if cfg!(windows) {
         let cmd_script = format!(
@@ -44,6 +54,56 @@ fn make_path_wrapper(bin_dir: &Path, name: &str, argv0: &str, args: &Vec<String>
     Ok(())
 }
 
+/// Exposes a manifest field the same way npm does: nested keys and array
+/// indices are joined with underscores (for example `npm_package_scripts_test`
+/// or `npm_package_files_0`), and strings are exposed without quotes.
+fn attach_manifest_variables(env: &mut BTreeMap<String, Option<String>>, name: &str, value: &serde_json::Value) {
+    let value_str = match value {
+        serde_json::Value::Null => {
+            return;
+        },
+
+        serde_json::Value::Object(fields) => {
+            for (key, value) in fields {
+                attach_manifest_variables(env, &format!("{}_{}", name, key), value);
+            }
+
+            return;
+        },
+
+        serde_json::Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                attach_manifest_variables(env, &format!("{}_{}", name, index), value);
+            }
+
+            return;
+        },
+
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Bool(value) => value.to_string(),
+        serde_json::Value::Number(value) => value.to_string(),
+    };
+
+    let env_name = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' {c} else {'_'})
+        .collect::<String>();
+
+    env.insert(env_name, Some(value_str));
+}
+
+fn kill_process(pid: u32, is_group_leader: bool, signal: libc::c_int) {
+    // When the child is the leader of its own process group, sending the
+    // signal to the negated pid also reaches the processes it spawned.
+    let target = match is_group_leader {
+        true => -(pid as libc::pid_t),
+        false => pid as libc::pid_t,
+    };
+
+    unsafe {
+        libc::kill(target, signal);
+    }
+}
+
 fn is_node_script(p: Path) -> bool {
     if let Some(ext) = p.extname() {
         return JS_EXTENSION.is_match(ext);
@@ -298,13 +358,53 @@ impl From<ScriptResult> for ExitStatus {
     }
 }
 
+/// Quotes the given value if needed so that Node.js reads it as a single
+/// argument when parsing NODE_OPTIONS.
+fn quote_node_option(value: &str) -> String {
+    if !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        return value.to_string();
+    }
+
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Returns the NODE_OPTIONS value to pass to the child process. The loaders
+/// injected by a parent process (for example when calling `yarn node` from a
+/// script) are removed first so they don't accumulate, then ours are put in
+/// front so they're loaded before any `--require` set by the user.
+fn inject_node_options(current: Option<&str>, injected: &[String]) -> Option<String> {
+    let current
+        = current.unwrap_or_default();
+
+    let user_options = CJS_LOADER_MATCHER.replace_all(current, " ");
+    let user_options = ESM_LOADER_MATCHER.replace_all(&user_options, " ");
+    let user_options = user_options.trim();
+
+    let node_options
+        = injected.iter()
+            .map(|option| option.as_str())
+            .chain((!user_options.is_empty()).then_some(user_options))
+            .join(" ");
+
+    // When set to an empty string, some tools consider it as explicitly
+    // set to the empty value, and do not set their own value.
+    if node_options.is_empty() {
+        return None;
+    }
+
+    Some(node_options)
+}
+
 pub struct ScriptEnvironment {
     cwd: Path,
     binaries: ScriptBinaries,
+    bin_paths: Vec<Path>,
     env: BTreeMap<String, Option<String>>,
     node_args: Vec<String>,
-    shell_forwarding: bool,
+    output_mode: OutputMode,
     stdin: Option<String>,
+    timeout: Option<Duration>,
+    native_shell: bool,
 }
 
 impl ScriptEnvironment {
@@ -312,10 +412,13 @@ impl ScriptEnvironment {
         let mut value = Self {
             cwd: Path::current_dir().unwrap(),
             binaries: ScriptBinaries::new().with_standard()?,
+            bin_paths: Vec::new(),
             env: BTreeMap::new(),
             node_args: Vec::new(),
-            shell_forwarding: false,
+            output_mode: OutputMode::Capture,
             stdin: None,
+            timeout: None,
+            native_shell: false,
         };
 
         if let Ok(val) = std::env::var("YARNSW_DETECTED_ROOT") {
@@ -342,24 +445,6 @@ impl ScriptEnvironment {
     //     current.insert_str(0, value);
     // }
 
-    fn append_env(&mut self, key: &str, separator: char, value: &str) {
-        let current = self.env.entry(key.to_string())
-            .or_insert_with(|| std::env::var(key).ok());
-
-        match current {
-            Some(existing) => {
-                if !existing.is_empty() {
-                    existing.push(separator);
-                }
-                existing.push_str(value);
-            },
-
-            None => {
-                *current = Some(value.to_string());
-            },
-        }
-    }
-
     pub fn with_env(mut self, env: BTreeMap<String, String>) -> Self {
         for (key, value) in env {
             self.env.insert(key, Some(value));
@@ -377,13 +462,25 @@ impl ScriptEnvironment {
         self
     }
 
+    /// Sets the variables describing the script being run, as npm does.
+    pub fn with_lifecycle_event(mut self, event: &str, script: &str) -> Self {
+        self.env.insert("npm_lifecycle_event".to_string(), Some(event.to_string()));
+        self.env.insert("npm_lifecycle_script".to_string(), Some(script.to_string()));
+        self
+    }
+
     pub fn with_node_args(mut self, args: Vec<String>) -> Self {
         self.node_args = args;
         self
     }
 
     pub fn enable_shell_forwarding(mut self) -> Self {
-        self.shell_forwarding = true;
+        self.output_mode = OutputMode::Forward;
+        self
+    }
+
+    pub fn with_output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
         self
     }
 
@@ -392,46 +489,47 @@ impl ScriptEnvironment {
         self
     }
 
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
     pub fn with_project(mut self, project: &Project) -> Self {
-        self.remove_pnp_loader();
+        self.native_shell = project.config.settings.enable_native_shell.value;
 
-        if let Some(pnp_path) = project.pnp_path().if_exists() {
-            self.append_env("NODE_OPTIONS", ' ', &format!("--require {}", pnp_path.to_file_string()));
+        let mut injected_node_options
+            = vec![];
+
+        if project.config.settings.node_linker.value == NodeLinker::Pnp {
+            if let Some(pnp_path) = project.pnp_path().if_exists() {
+                injected_node_options.push(format!("--require {}", quote_node_option(&pnp_path.to_file_string())));
+            }
+
+            if let Some(pnp_loader_path) = project.pnp_loader_path().if_exists() {
+                injected_node_options.push(format!("--experimental-loader {}", quote_node_option(&pnp_loader_path.to_file_string())));
+            }
         }
 
-        if let Some(pnp_loader_path) = project.pnp_loader_path().if_exists() {
-            self.append_env("NODE_OPTIONS", ' ', &format!("--experimental-loader {}", pnp_loader_path.to_file_string()));
+        let current_node_options = self.env.get("NODE_OPTIONS")
+            .cloned()
+            .unwrap_or_else(|| std::env::var("NODE_OPTIONS").ok());
+
+        let node_options
+            = inject_node_options(current_node_options.as_deref(), &injected_node_options);
+
+        if node_options != current_node_options {
+            self.env.insert("NODE_OPTIONS".to_string(), node_options);
         }
 
         self.env.insert("PROJECT_CWD".to_string(), Some(project.project_cwd.to_file_string()));
         self.env.insert("INIT_CWD".to_string(), Some(project.project_cwd.with_join(&project.shell_cwd).to_file_string()));
         self.env.insert("CACHE_CWD".to_string(), Some(project.preferred_cache_path().to_file_string()));
 
-        self
-    }
-
-    fn remove_pnp_loader(&mut self) {
-        let current = self.env.get("NODE_OPTIONS")
-            .and_then(|opt| opt.clone())
-            .or_else(|| std::env::var("NODE_OPTIONS").ok());
-
-        let Some(current) = current else {
-            return;
-        };
-
-        let updated = CJS_LOADER_MATCHER.replace_all(&current, " ");
-        let updated = ESM_LOADER_MATCHER.replace_all(&updated, " ");
-        let updated = updated.trim();
-
-        if current != updated {
-            // When set to an empty string, some tools consider it as explicitly
-            // set to the empty value, and do not set their own value.
-            if updated.is_empty() {
-                self.env.insert("NODE_OPTIONS".to_string(), None);
-            } else {
-                self.env.insert("NODE_OPTIONS".to_string(), Some(updated.to_string()));
-            }
+        if let Ok(registry) = http_npm::get_registry(&project.config, None, false) {
+            self.env.insert("npm_config_registry".to_string(), Some(registry.to_string()));
         }
+
+        self
     }
 
     pub fn with_standard_binaries(mut self) -> Self {
@@ -457,6 +555,17 @@ impl ScriptEnvironment {
             .with_join(package_location_rel)
             .with_join_str("package.json");
 
+        let manifest = manifest_location_abs.fs_read_text().ok()
+            .and_then(|manifest_text| serde_json::from_str::<serde_json::Value>(&manifest_text).ok());
+
+        if let Some(serde_json::Value::Object(fields)) = manifest {
+            for (key, value) in fields {
+                if key != "readme" && !key.starts_with('_') {
+                    attach_manifest_variables(&mut self.env, &format!("npm_package_{}", key), &value);
+                }
+            }
+        }
+
         self.env.insert("npm_package_name".to_string(), Some(locator.ident.to_file_string()));
         self.env.insert("npm_package_version".to_string(), Some(resolution.version.to_file_string()));
         self.env.insert("npm_package_json".to_string(), Some(manifest_location_abs.to_file_string()));
@@ -486,6 +595,39 @@ impl ScriptEnvironment {
         Ok(self)
     }
 
+    /// Makes the binaries of the given workspace and of its ancestor
+    /// workspaces available on the PATH, the closest ones taking precedence.
+    /// With the node-modules and pnpm linkers we add their `node_modules/.bin`
+    /// folders; with PnP there's no such folder, so we shim the visible
+    /// binaries instead.
+    pub fn with_bin_path(mut self, project: &Project, workspace: &Workspace) -> Result<Self, Error> {
+        let mut workspaces = project.workspaces.iter()
+            .filter(|ancestor| ancestor.path.contains(&workspace.path))
+            .collect_vec();
+
+        // Shims are written in order, so later entries override earlier ones;
+        // we thus go from the outermost workspace to the innermost one.
+        workspaces.sort_by_key(|ancestor| ancestor.path.as_str().len());
+
+        for ancestor in workspaces {
+            match project.config.settings.node_linker.value {
+                NodeLinker::Pnp => {
+                    let binaries
+                        = project.package_visible_binaries(&ancestor.locator())?;
+
+                    self.binaries = std::mem::take(&mut self.binaries)
+                        .with_package(&binaries, &project.project_cwd)?;
+                },
+
+                NodeLinker::NodeModules | NodeLinker::Pnpm => {
+                    self.bin_paths.insert(0, ancestor.path.with_join_str("node_modules/.bin"));
+                },
+            }
+        }
+
+        Ok(self)
+    }
+
     pub fn with_cwd(mut self, cwd: Path) -> Self {
         self.cwd = cwd;
         self
@@ -552,15 +694,11 @@ impl ScriptEnvironment {
             .unwrap_or_else(|| std::env::var("PATH").ok())
             .unwrap_or_default();
 
-        let next_env_path = match env_path.is_empty() {
-            true => {
-                bin_dir.to_file_string()
-            },
-
-            false => {
-                format!("{}:{}", bin_dir.to_file_string(), env_path)
-            },
-        };
+        let next_env_path
+            = std::iter::once(bin_dir.to_file_string())
+                .chain(self.bin_paths.iter().map(|bin_path| bin_path.to_file_string()))
+                .chain((!env_path.is_empty()).then_some(env_path))
+                .join(":");
 
         cmd.env("PATH", next_env_path);
         cmd.env("BERRY_BIN_FOLDER", bin_dir.to_file_string());
@@ -571,11 +709,22 @@ impl ScriptEnvironment {
             cmd.stdin(std::process::Stdio::piped());
         }
 
-        if !self.shell_forwarding {
+        if self.output_mode != OutputMode::Forward {
             cmd.stdout(std::process::Stdio::piped());
             cmd.stderr(std::process::Stdio::piped());
         }
 
+        // Running the script in its own process group lets us kill everything
+        // it spawned if it times out. We don't do it when attached to a
+        // terminal, since the script would then stop receiving the signals
+        // (such as SIGINT on Ctrl-C) sent to the foreground process group.
+        let is_group_leader
+            = self.timeout.is_some() && !std::io::IsTerminal::is_terminal(&std::io::stdin());
+
+        if is_group_leader {
+            cmd.process_group(0);
+        }
+
         let mut child
             = cmd.spawn()
                 .map_err(|e| Error::SpawnFailed { name: program.to_string(), path: self.cwd.clone(), error: Arc::new(Box::new(e)) })?;
@@ -587,18 +736,58 @@ impl ScriptEnvironment {
             }
         }
 
-        let output = match self.shell_forwarding {
-            false => {
-                child.wait_with_output().await.unwrap()
-            },
+        let pid
+            = child.id();
+
+        let output_mode
+            = self.output_mode;
+
+        let wait_future = async move {
+            match output_mode {
+                OutputMode::Capture => {
+                    child.wait_with_output().await.unwrap()
+                },
+
+                OutputMode::Forward => {
+                    Output {
+                        status: child.wait().await.unwrap(),
+                        stdout: Vec::new(),
+                        stderr: Vec::new(),
+                    }
+                },
 
-            true => {
-                Output {
-                    status: child.wait().await.unwrap(),
-                    stdout: Vec::new(),
-                    stderr: Vec::new(),
+                OutputMode::ForwardAndCapture => {
+                    wait_with_forwarded_output(child).await.unwrap()
+                },
+            }
+        };
+
+        let output = match (self.timeout, pid) {
+            (Some(timeout), Some(pid)) => {
+                let mut wait_future
+                    = Box::pin(wait_future);
+
+                match tokio::time::timeout(timeout, &mut wait_future).await {
+                    Ok(output) => output,
+
+                    Err(_) => {
+                        kill_process(pid, is_group_leader, libc::SIGTERM);
+
+                        if tokio::time::timeout(SCRIPT_KILL_GRACE_PERIOD, &mut wait_future).await.is_err() {
+                            kill_process(pid, is_group_leader, libc::SIGKILL);
+                            wait_future.await;
+                        }
+
+                        return Err(Error::ScriptTimeout {
+                            script: to_shell_line(cmd.as_std()).unwrap_or_else(|_| program.to_string()),
+                        });
+                    },
                 }
             },
+
+            _ => {
+                wait_future.await
+            },
         };
 
         Ok(ScriptResult::new(output, cmd.as_std()))
@@ -622,6 +811,19 @@ impl ScriptEnvironment {
     }
 
     pub async fn run_script<I, S>(&mut self, script: &str, args: I) -> Result<ScriptResult, Error> where I: IntoIterator<Item = S>, S: AsRef<OsStr> + ToString {
+        if !self.native_shell {
+            // The builtin shell runs in its own process, so that it benefits
+            // from the same output capture and timeout handling as binaries
+            let self_path
+                = Path::current_exe()?;
+
+            let shell_args
+                = ["debug".to_string(), "sh".to_string(), script.to_string()].into_iter()
+                    .chain(args.into_iter().map(|arg| arg.to_string()));
+
+            return self.run_exec(&self_path.to_file_string(), shell_args).await;
+        }
+
         let mut final_script
             = script.to_string();
 
@@ -0,0 +1,57 @@
+use super::*;
+
+fn pnp_options() -> Vec<String> {
+    vec![
+        "--require /project/.pnp.cjs".to_string(),
+        "--experimental-loader /project/.pnp.loader.mjs".to_string(),
+    ]
+}
+
+#[test]
+fn it_injects_the_pnp_loaders_before_the_user_options() {
+    assert_eq!(
+        inject_node_options(Some("--require ./setup.js --max-old-space-size=4096"), &pnp_options()),
+        Some("--require /project/.pnp.cjs --experimental-loader /project/.pnp.loader.mjs --require ./setup.js --max-old-space-size=4096".to_string()),
+    );
+}
+
+#[test]
+fn it_doesnt_duplicate_the_pnp_loaders_on_nested_invocations() {
+    let outer
+        = inject_node_options(Some("--require ./setup.js"), &pnp_options());
+
+    let inner
+        = inject_node_options(outer.as_deref(), &pnp_options());
+
+    assert_eq!(inner, outer);
+}
+
+#[test]
+fn it_removes_the_pnp_loaders_of_other_projects() {
+    assert_eq!(
+        inject_node_options(Some("--require /other/.pnp.cjs --require ./setup.js --experimental-loader /other/.pnp.loader.mjs"), &pnp_options()),
+        Some("--require /project/.pnp.cjs --experimental-loader /project/.pnp.loader.mjs --require ./setup.js".to_string()),
+    );
+
+    assert_eq!(
+        inject_node_options(Some(r#"--require "/my project/.pnp.cjs" --inspect"#), &[]),
+        Some("--inspect".to_string()),
+    );
+}
+
+#[test]
+fn it_leaves_the_user_options_untouched_without_pnp() {
+    assert_eq!(
+        inject_node_options(Some("--require ./a.js --require ./b.js"), &[]),
+        Some("--require ./a.js --require ./b.js".to_string()),
+    );
+
+    assert_eq!(inject_node_options(None, &[]), None);
+    assert_eq!(inject_node_options(Some("--require /project/.pnp.cjs"), &[]), None);
+}
+
+#[test]
+fn it_quotes_paths_containing_spaces() {
+    assert_eq!(quote_node_option("/project/.pnp.cjs"), "/project/.pnp.cjs");
+    assert_eq!(quote_node_option("/my project/.pnp.cjs"), r#""/my project/.pnp.cjs""#);
+}
@@ -0,0 +1,825 @@
+use std::{collections::BTreeMap, fs::{File, OpenOptions}, io::{PipeReader, PipeWriter, Read, Write}, path::{Path as StdPath, PathBuf}, process::Stdio};
+
+use globset::GlobBuilder;
+use itertools::Itertools;
+use zpm_utils::exit_code_from_status;
+
+use crate::shell::parser::{ChainOperator, Chain, Command, DefaultOperator, Pipeline, Redirection, RedirectionKind, Script, SimpleCommand, Word, WordPart};
+
+#[cfg(test)]
+#[path = "./interpreter.test.rs"]
+mod interpreter_tests;
+
+const GLOB_CHARACTERS: [char; 3] = ['*', '?', '['];
+
+/// Returned when the `exit` builtin is called; it stops the execution of the
+/// current shell (or subshell) with the given status.
+pub struct Exit(pub u8);
+
+enum Stream {
+    Stdin,
+    Stdout,
+    Stderr,
+    File(File),
+    Reader(PipeReader),
+    Writer(PipeWriter),
+}
+
+impl Stream {
+    fn try_clone(&self) -> std::io::Result<Stream> {
+        Ok(match self {
+            Stream::Stdin => Stream::Stdin,
+            Stream::Stdout => Stream::Stdout,
+            Stream::Stderr => Stream::Stderr,
+            Stream::File(file) => Stream::File(file.try_clone()?),
+            Stream::Reader(reader) => Stream::Reader(reader.try_clone()?),
+            Stream::Writer(writer) => Stream::Writer(writer.try_clone()?),
+        })
+    }
+
+    fn to_stdio(&self) -> std::io::Result<Stdio> {
+        Ok(match self {
+            Stream::Stdin => Stdio::inherit(),
+            Stream::Stdout => Stdio::from(std::io::stdout()),
+            Stream::Stderr => Stdio::from(std::io::stderr()),
+            Stream::File(file) => Stdio::from(file.try_clone()?),
+            Stream::Reader(reader) => Stdio::from(reader.try_clone()?),
+            Stream::Writer(writer) => Stdio::from(writer.try_clone()?),
+        })
+    }
+
+    fn write_all(&self, data: &[u8]) -> std::io::Result<()> {
+        match self {
+            Stream::Stdout => {
+                let mut stdout = std::io::stdout();
+                stdout.write_all(data)?;
+                stdout.flush()
+            },
+
+            Stream::Stderr => {
+                std::io::stderr().write_all(data)
+            },
+
+            Stream::File(file) => {
+                file.try_clone()?.write_all(data)
+            },
+
+            Stream::Writer(writer) => {
+                writer.try_clone()?.write_all(data)
+            },
+
+            Stream::Stdin | Stream::Reader(_) => {
+                Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Bad file descriptor"))
+            },
+        }
+    }
+}
+
+pub struct Io {
+    stdin: Stream,
+    stdout: Stream,
+    stderr: Stream,
+}
+
+impl Io {
+    pub fn inherit() -> Self {
+        Self {
+            stdin: Stream::Stdin,
+            stdout: Stream::Stdout,
+            stderr: Stream::Stderr,
+        }
+    }
+
+    fn try_clone(&self) -> std::io::Result<Io> {
+        Ok(Io {
+            stdin: self.stdin.try_clone()?,
+            stdout: self.stdout.try_clone()?,
+            stderr: self.stderr.try_clone()?,
+        })
+    }
+
+    fn get(&self, fd: u32) -> Option<&Stream> {
+        match fd {
+            0 => Some(&self.stdin),
+            1 => Some(&self.stdout),
+            2 => Some(&self.stderr),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, fd: u32, stream: Stream) -> Result<(), String> {
+        match fd {
+            0 => self.stdin = stream,
+            1 => self.stdout = stream,
+            2 => self.stderr = stream,
+            _ => return Err(format!("{fd}: Unsupported file descriptor")),
+        }
+
+        Ok(())
+    }
+
+    /// Prints the given message on the stderr stream and returns the status
+    /// the failing command should exit with.
+    fn report(&self, message: &str, status: u8) -> u8 {
+        let _ = self.stderr.write_all(format!("{message}\n").as_bytes());
+        status
+    }
+}
+
+#[derive(Clone)]
+pub struct Shell {
+    cwd: PathBuf,
+    env: BTreeMap<String, String>,
+    last_status: u8,
+}
+
+impl Shell {
+    pub fn new(cwd: PathBuf, env: BTreeMap<String, String>) -> Self {
+        Self {
+            cwd,
+            env,
+            last_status: 0,
+        }
+    }
+
+    pub fn run_script(&mut self, script: &Script, io: &Io) -> Result<u8, Exit> {
+        for chain in &script.chains {
+            self.last_status
+                = self.run_chain(chain, io)?;
+        }
+
+        Ok(self.last_status)
+    }
+
+    fn run_chain(&mut self, chain: &Chain, io: &Io) -> Result<u8, Exit> {
+        self.last_status
+            = self.run_pipeline(&chain.first, io)?;
+
+        for (operator, pipeline) in &chain.rest {
+            let should_run = match operator {
+                ChainOperator::And => self.last_status == 0,
+                ChainOperator::Or => self.last_status != 0,
+            };
+
+            if should_run {
+                self.last_status
+                    = self.run_pipeline(pipeline, io)?;
+            }
+        }
+
+        Ok(self.last_status)
+    }
+
+    fn run_pipeline(&mut self, pipeline: &Pipeline, io: &Io) -> Result<u8, Exit> {
+        let status = match pipeline.commands.as_slice() {
+            [command] => {
+                self.run_command(command, io)?
+            },
+
+            commands => {
+                match self.run_piped_commands(commands, io) {
+                    Ok(status) => status,
+                    Err(error) => io.report(&format!("pipe: {error}"), 1),
+                }
+            },
+        };
+
+        Ok(match (pipeline.negated, status) {
+            (false, status) => status,
+            (true, 0) => 1,
+            (true, _) => 0,
+        })
+    }
+
+    /// Runs each element of the pipeline in its own thread (and with its own
+    /// copy of the shell state, as a subshell would); the pipeline status is
+    /// the one of its last element.
+    fn run_piped_commands(&self, commands: &[Command], io: &Io) -> std::io::Result<u8> {
+        let mut command_ios
+            = Vec::with_capacity(commands.len());
+
+        let mut previous_reader: Option<PipeReader>
+            = None;
+
+        for index in 0..commands.len() {
+            let stdin = match previous_reader.take() {
+                Some(reader) => Stream::Reader(reader),
+                None => io.stdin.try_clone()?,
+            };
+
+            let stdout = match index + 1 < commands.len() {
+                true => {
+                    let (reader, writer)
+                        = std::io::pipe()?;
+
+                    previous_reader = Some(reader);
+                    Stream::Writer(writer)
+                },
+
+                false => {
+                    io.stdout.try_clone()?
+                },
+            };
+
+            command_ios.push(Io {
+                stdin,
+                stdout,
+                stderr: io.stderr.try_clone()?,
+            });
+        }
+
+        // Each thread owns its streams and drops them as soon as its command
+        // exits, so that the next command in the pipeline sees the end of its
+        // input.
+        let statuses = std::thread::scope(|scope| {
+            let handles = commands.iter()
+                .zip(command_ios)
+                .map(|(command, command_io)| {
+                    let mut shell
+                        = self.clone();
+
+                    scope.spawn(move || {
+                        shell.run_command(command, &command_io)
+                            .unwrap_or_else(|Exit(status)| status)
+                    })
+                })
+                .collect_vec();
+
+            handles.into_iter()
+                .map(|handle| handle.join().unwrap_or(1))
+                .collect_vec()
+        });
+
+        Ok(statuses.last().copied().unwrap_or(0))
+    }
+
+    fn run_command(&mut self, command: &Command, io: &Io) -> Result<u8, Exit> {
+        match command {
+            Command::Simple(command) => {
+                self.run_simple_command(command, io)
+            },
+
+            Command::Subshell {body, redirections} => {
+                let subshell_io = match self.apply_redirections(redirections, io) {
+                    Ok(subshell_io) => subshell_io,
+                    Err(message) => return Ok(io.report(&message, 1)),
+                };
+
+                let mut subshell
+                    = self.clone();
+
+                Ok(subshell.run_script(body, &subshell_io).unwrap_or_else(|Exit(status)| status))
+            },
+        }
+    }
+
+    fn run_simple_command(&mut self, command: &SimpleCommand, io: &Io) -> Result<u8, Exit> {
+        let args = command.args.iter()
+            .flat_map(|word| self.expand_word(word, io))
+            .collect_vec();
+
+        let assignments = command.assignments.iter()
+            .map(|(name, value)| (name.clone(), self.expand_text(value, io)))
+            .collect_vec();
+
+        let command_io = match self.apply_redirections(&command.redirections, io) {
+            Ok(command_io) => command_io,
+            Err(message) => return Ok(io.report(&message, 1)),
+        };
+
+        let Some((program, program_args)) = args.split_first() else {
+            self.env.extend(assignments);
+            return Ok(0);
+        };
+
+        match program.as_str() {
+            "cd" => Ok(self.run_cd(program_args, &command_io)),
+            "echo" => Ok(run_echo(program_args, &command_io)),
+            "pwd" => Ok(self.run_pwd(&command_io)),
+            "exit" => Err(self.run_exit(program_args, &command_io)),
+            "true" => Ok(0),
+            "false" => Ok(1),
+
+            _ => {
+                let mut env
+                    = self.env.clone();
+
+                env.extend(assignments);
+
+                Ok(self.spawn(program, program_args, env, &command_io))
+            },
+        }
+    }
+
+    fn spawn(&self, program: &str, args: &[String], env: BTreeMap<String, String>, io: &Io) -> u8 {
+        // Windows only looks for `.exe` files on its own, which would miss the
+        // `.cmd` shims we generate for the binaries; those also have to go
+        // through `cmd` since they can't be spawned directly.
+        let resolved_program = match cfg!(windows) {
+            true => find_program(program, &self.cwd, &env),
+            false => None,
+        };
+
+        let mut cmd = match resolved_program {
+            Some(path) if is_batch_file(&path) => {
+                let mut cmd
+                    = std::process::Command::new(get_env_var(&env, "COMSPEC").unwrap_or("cmd.exe"));
+
+                cmd.arg("/C");
+                cmd.arg(path);
+                cmd
+            },
+
+            Some(path) => {
+                std::process::Command::new(path)
+            },
+
+            None => {
+                std::process::Command::new(program)
+            },
+        };
+
+        cmd.args(args);
+        cmd.current_dir(&self.cwd);
+        cmd.env_clear();
+        cmd.envs(env);
+
+        let stdio_result = (|| -> std::io::Result<()> {
+            cmd.stdin(io.stdin.to_stdio()?);
+            cmd.stdout(io.stdout.to_stdio()?);
+            cmd.stderr(io.stderr.to_stdio()?);
+            Ok(())
+        })();
+
+        if let Err(error) = stdio_result {
+            return io.report(&format!("{program}: {error}"), 1);
+        }
+
+        match cmd.status() {
+            Ok(status) => {
+                exit_code_from_status(&status)
+            },
+
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                io.report(&format!("command not found: {program}"), 127)
+            },
+
+            Err(error) => {
+                io.report(&format!("{program}: {error}"), 126)
+            },
+        }
+    }
+
+    fn run_cd(&mut self, args: &[String], io: &Io) -> u8 {
+        let target = match args.first() {
+            Some(target) => target.clone(),
+            None => self.env.get("HOME").cloned().unwrap_or_default(),
+        };
+
+        let next_cwd = match std::fs::canonicalize(self.cwd.join(&target)) {
+            Ok(next_cwd) if next_cwd.is_dir() => next_cwd,
+            _ => return io.report(&format!("cd: {target}: No such directory"), 1),
+        };
+
+        self.env.insert("PWD".to_string(), next_cwd.to_string_lossy().into_owned());
+        self.cwd = next_cwd;
+
+        0
+    }
+
+    fn run_pwd(&self, io: &Io) -> u8 {
+        let output
+            = format!("{}\n", self.cwd.to_string_lossy());
+
+        match io.stdout.write_all(output.as_bytes()) {
+            Ok(()) => 0,
+            Err(_) => 1,
+        }
+    }
+
+    fn run_exit(&self, args: &[String], io: &Io) -> Exit {
+        match args.first() {
+            None => {
+                Exit(self.last_status)
+            },
+
+            Some(code) => {
+                match code.parse::<i64>() {
+                    Ok(code) => Exit(code.rem_euclid(256) as u8),
+                    Err(_) => Exit(io.report(&format!("exit: {code}: Numeric argument required"), 2)),
+                }
+            },
+        }
+    }
+
+    fn apply_redirections(&self, redirections: &[Redirection], io: &Io) -> Result<Io, String> {
+        let mut io
+            = io.try_clone().map_err(|error| error.to_string())?;
+
+        for redirection in redirections {
+            let target
+                = self.expand_text(&redirection.target, &io);
+
+            let open_error
+                = |error: std::io::Error| format!("{target}: {error}");
+
+            match redirection.kind {
+                RedirectionKind::Read => {
+                    let file = File::open(self.cwd.join(&target))
+                        .map_err(open_error)?;
+
+                    io.set(redirection.fd, Stream::File(file))?;
+                },
+
+                RedirectionKind::Write | RedirectionKind::Append => {
+                    let file = self.open_for_writing(&target, redirection.kind == RedirectionKind::Append)
+                        .map_err(open_error)?;
+
+                    io.set(redirection.fd, Stream::File(file))?;
+                },
+
+                RedirectionKind::WriteAll | RedirectionKind::AppendAll => {
+                    let file = self.open_for_writing(&target, redirection.kind == RedirectionKind::AppendAll)
+                        .map_err(open_error)?;
+
+                    io.set(2, Stream::File(file.try_clone().map_err(open_error)?))?;
+                    io.set(1, Stream::File(file))?;
+                },
+
+                RedirectionKind::Duplicate => {
+                    let stream = target.parse::<u32>().ok()
+                        .and_then(|fd| io.get(fd))
+                        .ok_or_else(|| format!("{target}: Bad file descriptor"))?
+                        .try_clone()
+                        .map_err(open_error)?;
+
+                    io.set(redirection.fd, stream)?;
+                },
+            }
+        }
+
+        Ok(io)
+    }
+
+    fn open_for_writing(&self, target: &str, append: bool) -> std::io::Result<File> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(self.cwd.join(target))
+    }
+
+    fn lookup_variable(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(self.last_status.to_string()),
+            "$" => Some(std::process::id().to_string()),
+            "#" => Some("0".to_string()),
+            "0" => Some("yarn-script".to_string()),
+            _ => self.env.get(name).cloned(),
+        }
+    }
+
+    fn expand_part(&self, part: &WordPart, io: &Io) -> String {
+        match part {
+            WordPart::Variable(name) => {
+                self.lookup_variable(name).unwrap_or_default()
+            },
+
+            WordPart::VariableWithDefault {name, operator, default} => {
+                let value
+                    = self.lookup_variable(name);
+
+                let use_default = match operator {
+                    DefaultOperator::UnsetOrEmpty => value.as_deref().is_none_or(str::is_empty),
+                    DefaultOperator::Unset => value.is_none(),
+                    DefaultOperator::SetAndNotEmpty => value.as_deref().is_some_and(|value| !value.is_empty()),
+                    DefaultOperator::Set => value.is_some(),
+                };
+
+                let is_alternative
+                    = matches!(operator, DefaultOperator::SetAndNotEmpty | DefaultOperator::Set);
+
+                match (use_default, is_alternative) {
+                    (true, _) => self.expand_text(default, io),
+                    (false, true) => String::new(),
+                    (false, false) => value.unwrap_or_default(),
+                }
+            },
+
+            WordPart::CommandSubstitution {script, ..} => {
+                self.run_substitution(script, io)
+            },
+
+            WordPart::Bare(text) | WordPart::Quoted(text) => {
+                text.clone()
+            },
+        }
+    }
+
+    /// Runs the script in a subshell and returns what it printed on its
+    /// stdout, minus the trailing newlines.
+    fn run_substitution(&self, script: &Script, io: &Io) -> String {
+        let capture = || -> std::io::Result<Vec<u8>> {
+            let (mut reader, writer)
+                = std::io::pipe()?;
+
+            let substitution_io = Io {
+                stdin: io.stdin.try_clone()?,
+                stdout: Stream::Writer(writer),
+                stderr: io.stderr.try_clone()?,
+            };
+
+            let mut subshell
+                = self.clone();
+
+            std::thread::scope(|scope| {
+                // The thread owns the write end of the pipe, so that we see the
+                // end of the output as soon as the script exits
+                scope.spawn(move || {
+                    let _ = subshell.run_script(script, &substitution_io);
+                });
+
+                let mut output
+                    = vec![];
+
+                reader.read_to_end(&mut output)?;
+
+                Ok(output)
+            })
+        };
+
+        match capture() {
+            Ok(output) => {
+                String::from_utf8_lossy(&output).trim_end_matches(['\n', '\r']).to_string()
+            },
+
+            Err(error) => {
+                io.report(&format!("command substitution: {error}"), 1);
+                String::new()
+            },
+        }
+    }
+
+    /// Expands a word into a single string, without globbing nor splitting.
+    fn expand_text(&self, word: &Word, io: &Io) -> String {
+        word.parts.iter()
+            .map(|part| self.expand_part(part, io))
+            .collect()
+    }
+
+    /// Expands a word into the arguments it represents. Unquoted command
+    /// substitutions are split on whitespace, unquoted words that expand to an
+    /// empty string are dropped, and unquoted glob characters are matched
+    /// against the filesystem (the word is kept as-is if nothing matches).
+    fn expand_word(&self, word: &Word, io: &Io) -> Vec<String> {
+        let mut fields
+            = vec![Field::default()];
+
+        for part in &word.parts {
+            let field
+                = fields.last_mut().expect("There's always at least one field");
+
+            match part {
+                WordPart::Bare(text) => {
+                    field.has_glob |= text.contains(GLOB_CHARACTERS);
+
+                    field.literal.push_str(text);
+                    field.pattern.push_str(text);
+                },
+
+                WordPart::Quoted(text) => {
+                    field.has_quotes = true;
+
+                    field.literal.push_str(text);
+                    field.pattern.push_str(&escape_glob(text));
+                },
+
+                WordPart::CommandSubstitution {is_quoted: false, ..} => {
+                    let value
+                        = self.expand_part(part, io);
+
+                    for (index, segment) in value.split(|c: char| c.is_ascii_whitespace()).enumerate() {
+                        if index > 0 {
+                            fields.push(Field::default());
+                        }
+
+                        fields.last_mut().expect("There's always at least one field")
+                            .push_expanded(segment);
+                    }
+                },
+
+                WordPart::Variable(_) | WordPart::VariableWithDefault {..} | WordPart::CommandSubstitution {..} => {
+                    let value
+                        = self.expand_part(part, io);
+
+                    field.push_expanded(&value);
+                },
+            }
+        }
+
+        fields.into_iter()
+            .flat_map(|field| field.into_args(&self.cwd))
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Field {
+    literal: String,
+    pattern: String,
+    has_glob: bool,
+    has_quotes: bool,
+}
+
+impl Field {
+    fn push_expanded(&mut self, value: &str) {
+        self.literal.push_str(value);
+        self.pattern.push_str(&escape_glob(value));
+    }
+
+    fn into_args(self, cwd: &StdPath) -> Vec<String> {
+        if self.has_glob {
+            let matches
+                = expand_glob(cwd, &self.pattern);
+
+            if !matches.is_empty() {
+                return matches;
+            }
+        }
+
+        if self.literal.is_empty() && !self.has_quotes {
+            return vec![];
+        }
+
+        vec![self.literal]
+    }
+}
+
+fn run_echo(args: &[String], io: &Io) -> u8 {
+    let (args, newline) = match args.first().map(String::as_str) {
+        Some("-n") => (&args[1..], ""),
+        _ => (args, "\n"),
+    };
+
+    let output
+        = format!("{}{}", args.join(" "), newline);
+
+    match io.stdout.write_all(output.as_bytes()) {
+        Ok(()) => 0,
+        Err(_) => 1,
+    }
+}
+
+fn escape_glob(text: &str) -> String {
+    let mut escaped
+        = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '{' | '}' | '\\') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+fn unescape_glob(text: &str) -> String {
+    let mut unescaped
+        = String::with_capacity(text.len());
+
+    let mut chars
+        = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            _ => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+/// Matches the pattern against the filesystem one path segment at a time, the
+/// same way POSIX shells do; hidden files are only matched by segments that
+/// explicitly start with a dot. Results are sorted.
+fn expand_glob(cwd: &StdPath, pattern: &str) -> Vec<String> {
+    let (root, relative_pattern, prefix) = match pattern.strip_prefix('/') {
+        Some(relative_pattern) => (PathBuf::from("/"), relative_pattern, "/"),
+        None => (cwd.to_path_buf(), pattern, ""),
+    };
+
+    let mut candidates
+        = vec![String::new()];
+
+    for segment in relative_pattern.split('/').filter(|segment| !segment.is_empty()) {
+        if !segment.contains(GLOB_CHARACTERS) {
+            let name
+                = unescape_glob(segment);
+
+            candidates = candidates.into_iter()
+                .map(|candidate| join_candidate(&candidate, &name))
+                .collect();
+
+            continue;
+        }
+
+        let Ok(glob) = GlobBuilder::new(segment).literal_separator(true).backslash_escape(true).build() else {
+            return vec![];
+        };
+
+        let matcher
+            = glob.compile_matcher();
+
+        let mut next_candidates
+            = vec![];
+
+        for candidate in &candidates {
+            let Ok(entries) = std::fs::read_dir(root.join(candidate)) else {
+                continue;
+            };
+
+            let names = entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| !name.starts_with('.') || segment.starts_with('.'))
+                .filter(|name| matcher.is_match(name))
+                .sorted();
+
+            next_candidates.extend(names.map(|name| join_candidate(candidate, &name)));
+        }
+
+        candidates = next_candidates;
+    }
+
+    let directories_only
+        = pattern.ends_with('/');
+
+    candidates.into_iter()
+        .filter(|candidate| !candidate.is_empty())
+        .filter(|candidate| match directories_only {
+            true => root.join(candidate).is_dir(),
+            false => root.join(candidate).symlink_metadata().is_ok(),
+        })
+        .map(|candidate| format!("{prefix}{candidate}{}", if directories_only {"/"} else {""}))
+        .collect()
+}
+
+fn join_candidate(candidate: &str, name: &str) -> String {
+    match candidate.is_empty() {
+        true => name.to_string(),
+        false => format!("{candidate}/{name}"),
+    }
+}
+
+/// Environment variable names are case-insensitive on Windows (`PATH` is
+/// usually spelled `Path` there).
+fn get_env_var<'a>(env: &'a BTreeMap<String, String>, name: &str) -> Option<&'a str> {
+    env.iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Resolves the given program the way the Windows shell does: relative to the
+/// cwd if it contains a separator, otherwise against each `PATH` entry, trying
+/// each of the `PATHEXT` extensions in order.
+fn find_program(program: &str, cwd: &StdPath, env: &BTreeMap<String, String>) -> Option<PathBuf> {
+    let extensions = get_env_var(env, "PATHEXT")
+        .unwrap_or(".COM;.EXE;.BAT;.CMD")
+        .split(';')
+        .filter(|extension| !extension.is_empty())
+        .collect_vec();
+
+    let directories = match program.contains(['/', '\\']) {
+        true => vec![cwd.to_path_buf()],
+        false => get_env_var(env, "PATH").map(|path| std::env::split_paths(path).collect_vec()).unwrap_or_default(),
+    };
+
+    directories.iter().find_map(|directory| {
+        let base
+            = directory.join(program);
+
+        if base.extension().is_some() && base.is_file() {
+            return Some(base);
+        }
+
+        extensions.iter()
+            .map(|extension| {
+                let mut candidate
+                    = base.clone().into_os_string();
+
+                candidate.push(extension);
+                PathBuf::from(candidate)
+            })
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+fn is_batch_file(path: &StdPath) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("cmd") || extension.eq_ignore_ascii_case("bat"))
+}
@@ -0,0 +1,28 @@
+#[cfg(windows)]
+#[test]
+fn it_runs_cmd_shims_found_through_the_path() {
+    use zpm_utils::Path;
+
+    use super::*;
+    use crate::shell::parser::parse_script;
+
+    let bin_dir
+        = Path::temp_dir().unwrap();
+
+    bin_dir
+        .with_join_str("hello.cmd")
+        .fs_write_text("@exit /b %1\r\n").unwrap();
+
+    let env = BTreeMap::from([
+        ("Path".to_string(), bin_dir.to_path_buf().to_string_lossy().into_owned()),
+        ("PATHEXT".to_string(), ".EXE;.CMD".to_string()),
+    ]);
+
+    let mut shell
+        = Shell::new(bin_dir.to_path_buf(), env);
+
+    let script
+        = parse_script("hello 7").unwrap();
+
+    assert_eq!(shell.run_script(&script, &Io::inherit()).unwrap_or_else(|Exit(status)| status), 7);
+}
@@ -0,0 +1,28 @@
+use std::collections::BTreeMap;
+
+use crate::{error::Error, shell::interpreter::{Exit, Io, Shell}};
+
+mod interpreter;
+pub mod parser;
+
+/// Runs the given script through the builtin shell, in the current process;
+/// the arguments are appended to the last command of the script without being
+/// interpreted. Returns the exit status of the last pipeline that ran.
+pub fn run_script(source: &str, args: &[String]) -> Result<u8, Error> {
+    let mut script
+        = parser::parse_script(source)?;
+
+    script.append_args(args);
+
+    let cwd
+        = std::env::current_dir()?;
+
+    let env = std::env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut shell
+        = Shell::new(cwd, env);
+
+    Ok(shell.run_script(&script, &Io::inherit()).unwrap_or_else(|Exit(status)| status))
+}
@@ -0,0 +1,881 @@
+use crate::error::Error;
+
+#[cfg(test)]
+#[path = "./parser.test.rs"]
+mod parser_tests;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefaultOperator {
+    /// `${VAR:-default}`; used when the variable is unset or empty
+    UnsetOrEmpty,
+    /// `${VAR-default}`; used when the variable is unset
+    Unset,
+    /// `${VAR:+alternative}`; used when the variable is set and not empty
+    SetAndNotEmpty,
+    /// `${VAR+alternative}`; used when the variable is set
+    Set,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordPart {
+    /// Unquoted text; glob characters it contains are expanded
+    Bare(String),
+    /// Quoted or escaped text; used verbatim
+    Quoted(String),
+    Variable(String),
+    VariableWithDefault {name: String, operator: DefaultOperator, default: Word},
+    /// `$(command)` or `` `command` ``; unquoted substitutions are split on
+    /// whitespace into multiple arguments
+    CommandSubstitution {script: Script, is_quoted: bool},
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+impl Word {
+    pub fn literal(value: &str) -> Self {
+        Self {parts: vec![WordPart::Quoted(value.to_string())]}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedirectionKind {
+    /// `<`
+    Read,
+    /// `>`
+    Write,
+    /// `>>`
+    Append,
+    /// `>&N`; the target is the file descriptor to duplicate
+    Duplicate,
+    /// `&>`; redirects both stdout and stderr
+    WriteAll,
+    /// `&>>`
+    AppendAll,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirection {
+    pub fd: u32,
+    pub kind: RedirectionKind,
+    pub target: Word,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleCommand {
+    pub assignments: Vec<(String, Word)>,
+    pub args: Vec<Word>,
+    pub redirections: Vec<Redirection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Simple(SimpleCommand),
+    Subshell {body: Script, redirections: Vec<Redirection>},
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    pub negated: bool,
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainOperator {
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chain {
+    pub first: Pipeline,
+    pub rest: Vec<(ChainOperator, Pipeline)>,
+}
+
+impl Chain {
+    fn last_pipeline_mut(&mut self) -> &mut Pipeline {
+        match self.rest.last_mut() {
+            Some((_, pipeline)) => pipeline,
+            None => &mut self.first,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub chains: Vec<Chain>,
+}
+
+impl Script {
+    /// Appends the given arguments to the last command of the script, as
+    /// literal words; they thus reach the command exactly as they were
+    /// provided, without being subject to expansion.
+    pub fn append_args(&mut self, args: &[String]) {
+        if args.is_empty() {
+            return;
+        }
+
+        let words
+            = args.iter().map(|arg| Word::literal(arg));
+
+        let last_command = self.chains.last_mut()
+            .map(|chain| chain.last_pipeline_mut())
+            .and_then(|pipeline| pipeline.commands.last_mut());
+
+        match last_command {
+            Some(Command::Simple(command)) => {
+                command.args.extend(words);
+            },
+
+            _ => {
+                self.chains.push(Chain {
+                    first: Pipeline {
+                        negated: false,
+                        commands: vec![Command::Simple(SimpleCommand {args: words.collect(), ..Default::default()})],
+                    },
+                    rest: vec![],
+                });
+            },
+        }
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_special_variable(c: char) -> bool {
+    c.is_ascii_digit() || matches!(c, '?' | '@' | '*' | '#' | '$')
+}
+
+fn is_word_boundary(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(' | ')' | '<' | '>')
+}
+
+pub fn parse_script(source: &str) -> Result<Script, Error> {
+    let mut parser
+        = Parser {chars: source.chars().collect(), pos: 0};
+
+    let script
+        = parser.parse_script(false)?;
+
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error("unexpected character"));
+    }
+
+    Ok(script)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> Error {
+        match self.peek() {
+            Some(c) => Error::ShellParseError(format!("{message} near {:?} at offset {}", c, self.pos)),
+            None => Error::ShellParseError(format!("{message} at end of input")),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, value: &str) -> bool {
+        value.chars().enumerate().all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    fn skip_blanks(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' => {
+                    self.pos += 1;
+                },
+
+                '\\' if self.peek_at(1) == Some('\n') => {
+                    self.pos += 2;
+                },
+
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                },
+
+                _ => break,
+            }
+        }
+    }
+
+    fn skip_blanks_and_newlines(&mut self) {
+        loop {
+            self.skip_blanks();
+
+            if self.peek() != Some('\n') {
+                break;
+            }
+
+            self.pos += 1;
+        }
+    }
+
+    fn parse_script(&mut self, in_subshell: bool) -> Result<Script, Error> {
+        let mut script
+            = Script::default();
+
+        loop {
+            self.skip_blanks();
+
+            match self.peek() {
+                None => break,
+                Some(')') if in_subshell => break,
+
+                Some('\n' | ';') => {
+                    self.pos += 1;
+                    continue;
+                },
+
+                _ => {},
+            }
+
+            script.chains.push(self.parse_chain()?);
+
+            self.skip_blanks();
+
+            match self.peek() {
+                None => break,
+                Some(')') if in_subshell => break,
+
+                Some('\n' | ';') => {
+                    self.pos += 1;
+                },
+
+                Some('&') => {
+                    return Err(self.error("background jobs aren't supported"));
+                },
+
+                _ => {
+                    return Err(self.error("unexpected character"));
+                },
+            }
+        }
+
+        Ok(script)
+    }
+
+    fn parse_chain(&mut self) -> Result<Chain, Error> {
+        let first
+            = self.parse_pipeline()?;
+
+        let mut rest
+            = vec![];
+
+        loop {
+            self.skip_blanks();
+
+            let operator = if self.starts_with("&&") {
+                ChainOperator::And
+            } else if self.starts_with("||") {
+                ChainOperator::Or
+            } else {
+                break;
+            };
+
+            self.pos += 2;
+            self.skip_blanks_and_newlines();
+
+            rest.push((operator, self.parse_pipeline()?));
+        }
+
+        Ok(Chain {first, rest})
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Pipeline, Error> {
+        self.skip_blanks();
+
+        let negated
+            = self.peek() == Some('!') && self.peek_at(1).is_some_and(|c| c.is_whitespace());
+
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut commands
+            = vec![self.parse_command()?];
+
+        loop {
+            self.skip_blanks();
+
+            if self.peek() != Some('|') || self.starts_with("||") {
+                break;
+            }
+
+            self.pos += 1;
+            self.skip_blanks_and_newlines();
+
+            commands.push(self.parse_command()?);
+        }
+
+        Ok(Pipeline {negated, commands})
+    }
+
+    fn parse_command(&mut self) -> Result<Command, Error> {
+        self.skip_blanks();
+
+        if self.peek() == Some('(') {
+            self.pos += 1;
+
+            let body
+                = self.parse_script(true)?;
+
+            if self.peek() != Some(')') {
+                return Err(self.error("expected a closing parenthesis"));
+            }
+
+            self.pos += 1;
+
+            let mut redirections
+                = vec![];
+
+            loop {
+                self.skip_blanks();
+
+                if !self.is_redirection_start() {
+                    break;
+                }
+
+                redirections.push(self.parse_redirection()?);
+            }
+
+            return Ok(Command::Subshell {body, redirections});
+        }
+
+        let mut command
+            = SimpleCommand::default();
+
+        loop {
+            self.skip_blanks();
+
+            if self.is_redirection_start() {
+                command.redirections.push(self.parse_redirection()?);
+                continue;
+            }
+
+            match self.peek() {
+                None => break,
+                Some(c) if is_word_boundary(c) => break,
+                _ => {},
+            }
+
+            let word
+                = self.parse_word()?;
+
+            match command.args.is_empty().then(|| split_assignment(&word)).flatten() {
+                Some(assignment) => command.assignments.push(assignment),
+                None => command.args.push(word),
+            }
+        }
+
+        if command.assignments.is_empty() && command.args.is_empty() && command.redirections.is_empty() {
+            return Err(self.error("expected a command"));
+        }
+
+        Ok(Command::Simple(command))
+    }
+
+    fn is_redirection_start(&self) -> bool {
+        let mut offset
+            = 0;
+
+        while self.peek_at(offset).is_some_and(|c| c.is_ascii_digit()) {
+            offset += 1;
+        }
+
+        match self.peek_at(offset) {
+            Some('<' | '>') => true,
+            Some('&') => offset == 0 && self.peek_at(1) == Some('>'),
+            _ => false,
+        }
+    }
+
+    fn parse_redirection(&mut self) -> Result<Redirection, Error> {
+        let mut fd_str
+            = String::new();
+
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_digit()) {
+            fd_str.push(c);
+            self.pos += 1;
+        }
+
+        let explicit_fd = match fd_str.is_empty() {
+            true => None,
+            false => Some(fd_str.parse::<u32>().map_err(|_| self.error("invalid file descriptor"))?),
+        };
+
+        let (default_fd, kind, length) = if self.starts_with("&>>") {
+            (1, RedirectionKind::AppendAll, 3)
+        } else if self.starts_with("&>") {
+            (1, RedirectionKind::WriteAll, 2)
+        } else if self.starts_with(">>") {
+            (1, RedirectionKind::Append, 2)
+        } else if self.starts_with(">&") {
+            (1, RedirectionKind::Duplicate, 2)
+        } else if self.starts_with(">") {
+            (1, RedirectionKind::Write, 1)
+        } else if self.starts_with("<<") {
+            return Err(self.error("here-documents aren't supported"));
+        } else if self.starts_with("<") {
+            (0, RedirectionKind::Read, 1)
+        } else {
+            return Err(self.error("expected a redirection"));
+        };
+
+        self.pos += length;
+        self.skip_blanks();
+
+        match self.peek() {
+            Some(c) if !is_word_boundary(c) => {},
+            _ => return Err(self.error("expected a redirection target")),
+        }
+
+        let target
+            = self.parse_word()?;
+
+        Ok(Redirection {fd: explicit_fd.unwrap_or(default_fd), kind, target})
+    }
+
+    fn parse_word(&mut self) -> Result<Word, Error> {
+        let mut parts
+            = vec![];
+
+        let mut bare
+            = String::new();
+
+        while let Some(c) = self.peek() {
+            if is_word_boundary(c) {
+                break;
+            }
+
+            match c {
+                '\'' => {
+                    flush_bare(&mut parts, &mut bare);
+                    parts.push(WordPart::Quoted(self.parse_single_quoted()?));
+                },
+
+                '"' => {
+                    flush_bare(&mut parts, &mut bare);
+                    self.parse_double_quoted(&mut parts)?;
+                },
+
+                '\\' => {
+                    self.pos += 1;
+
+                    match self.peek() {
+                        Some('\n') => {
+                            self.pos += 1;
+                        },
+
+                        Some(escaped) => {
+                            flush_bare(&mut parts, &mut bare);
+                            parts.push(WordPart::Quoted(escaped.to_string()));
+                            self.pos += 1;
+                        },
+
+                        None => {
+                            bare.push('\\');
+                        },
+                    }
+                },
+
+                '`' => {
+                    flush_bare(&mut parts, &mut bare);
+                    parts.push(self.parse_backquoted(false)?);
+                },
+
+                '$' => {
+                    match self.parse_dollar(false)? {
+                        Some(part) => {
+                            flush_bare(&mut parts, &mut bare);
+                            parts.push(part);
+                        },
+
+                        None => {
+                            bare.push('$');
+                        },
+                    }
+                },
+
+                _ => {
+                    bare.push(c);
+                    self.pos += 1;
+                },
+            }
+        }
+
+        flush_bare(&mut parts, &mut bare);
+
+        Ok(Word {parts})
+    }
+
+    fn parse_single_quoted(&mut self) -> Result<String, Error> {
+        self.pos += 1;
+
+        let mut value
+            = String::new();
+
+        loop {
+            match self.peek() {
+                Some('\'') => {
+                    self.pos += 1;
+                    return Ok(value);
+                },
+
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                },
+
+                None => {
+                    return Err(self.error("unterminated single quote"));
+                },
+            }
+        }
+    }
+
+    fn parse_double_quoted(&mut self, parts: &mut Vec<WordPart>) -> Result<(), Error> {
+        self.pos += 1;
+
+        let mut value
+            = String::new();
+
+        let start_len
+            = parts.len();
+
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    break;
+                },
+
+                Some('\\') => {
+                    match self.peek_at(1) {
+                        Some('\n') => {},
+                        Some(escaped @ ('$' | '`' | '"' | '\\')) => value.push(escaped),
+
+                        Some(other) => {
+                            value.push('\\');
+                            value.push(other);
+                        },
+
+                        None => return Err(self.error("unterminated double quote")),
+                    }
+
+                    self.pos += 2;
+                },
+
+                Some('`') => {
+                    flush_quoted(parts, &mut value);
+                    parts.push(self.parse_backquoted(true)?);
+                },
+
+                Some('$') => {
+                    match self.parse_dollar(true)? {
+                        Some(part) => {
+                            flush_quoted(parts, &mut value);
+                            parts.push(part);
+                        },
+
+                        None => {
+                            value.push('$');
+                        },
+                    }
+                },
+
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                },
+
+                None => {
+                    return Err(self.error("unterminated double quote"));
+                },
+            }
+        }
+
+        // Even an empty string yields a part, so that `""` is still an argument
+        if !value.is_empty() || parts.len() == start_len {
+            parts.push(WordPart::Quoted(value));
+        }
+
+        Ok(())
+    }
+
+    /// Parses a variable reference or command substitution starting at a `$`
+    /// sign. Returns `None` (and only consumes the `$` sign) if it isn't
+    /// followed by a variable name.
+    fn parse_dollar(&mut self, is_quoted: bool) -> Result<Option<WordPart>, Error> {
+        match self.peek_at(1) {
+            Some('(') if self.peek_at(2) == Some('(') => {
+                Err(self.error("arithmetic expansions aren't supported"))
+            },
+
+            Some('(') => {
+                self.pos += 2;
+
+                let script
+                    = self.parse_script(true)?;
+
+                if self.peek() != Some(')') {
+                    return Err(self.error("expected a closing parenthesis"));
+                }
+
+                self.pos += 1;
+
+                Ok(Some(WordPart::CommandSubstitution {script, is_quoted}))
+            },
+
+            Some('{') => {
+                self.pos += 2;
+                self.parse_braced_variable().map(Some)
+            },
+
+            Some(c) if is_special_variable(c) => {
+                self.pos += 2;
+                Ok(Some(WordPart::Variable(c.to_string())))
+            },
+
+            Some(c) if is_name_start(c) => {
+                self.pos += 1;
+                Ok(Some(WordPart::Variable(self.parse_name())))
+            },
+
+            _ => {
+                self.pos += 1;
+                Ok(None)
+            },
+        }
+    }
+
+    /// Parses a `` `command` `` substitution; backslashes only escape the
+    /// characters that would otherwise end or alter the substitution, and
+    /// what remains is parsed as a script of its own.
+    fn parse_backquoted(&mut self, is_quoted: bool) -> Result<WordPart, Error> {
+        self.pos += 1;
+
+        let mut source
+            = String::new();
+
+        loop {
+            match self.peek() {
+                Some('`') => {
+                    self.pos += 1;
+                    break;
+                },
+
+                Some('\\') => {
+                    match self.peek_at(1) {
+                        Some(escaped @ ('$' | '`' | '\\')) => source.push(escaped),
+                        Some('"') if is_quoted => source.push('"'),
+
+                        Some(other) => {
+                            source.push('\\');
+                            source.push(other);
+                        },
+
+                        None => return Err(self.error("unterminated command substitution")),
+                    }
+
+                    self.pos += 2;
+                },
+
+                Some(c) => {
+                    source.push(c);
+                    self.pos += 1;
+                },
+
+                None => {
+                    return Err(self.error("unterminated command substitution"));
+                },
+            }
+        }
+
+        let script
+            = parse_script(&source)?;
+
+        Ok(WordPart::CommandSubstitution {script, is_quoted})
+    }
+
+    fn parse_name(&mut self) -> String {
+        let mut name
+            = String::new();
+
+        while let Some(c) = self.peek().filter(|c| is_name_char(*c)) {
+            name.push(c);
+            self.pos += 1;
+        }
+
+        name
+    }
+
+    fn parse_braced_variable(&mut self) -> Result<WordPart, Error> {
+        let name = match self.peek() {
+            Some(c) if is_special_variable(c) => {
+                self.pos += 1;
+                c.to_string()
+            },
+
+            Some(c) if is_name_start(c) => {
+                self.parse_name()
+            },
+
+            _ => {
+                return Err(self.error("expected a variable name"));
+            },
+        };
+
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(WordPart::Variable(name));
+        }
+
+        let operator = if self.starts_with(":-") {
+            DefaultOperator::UnsetOrEmpty
+        } else if self.starts_with(":+") {
+            DefaultOperator::SetAndNotEmpty
+        } else if self.starts_with("-") {
+            DefaultOperator::Unset
+        } else if self.starts_with("+") {
+            DefaultOperator::Set
+        } else {
+            return Err(self.error("unsupported parameter expansion"));
+        };
+
+        self.pos += match operator {
+            DefaultOperator::UnsetOrEmpty | DefaultOperator::SetAndNotEmpty => 2,
+            DefaultOperator::Unset | DefaultOperator::Set => 1,
+        };
+
+        let default
+            = self.parse_braced_default()?;
+
+        Ok(WordPart::VariableWithDefault {name, operator, default})
+    }
+
+    /// Parses the default value of a `${VAR:-default}` expansion; whitespace and
+    /// operators are part of the value, which ends at the closing brace.
+    fn parse_braced_default(&mut self) -> Result<Word, Error> {
+        let mut parts
+            = vec![];
+
+        let mut text
+            = String::new();
+
+        loop {
+            match self.peek() {
+                Some('}') => {
+                    self.pos += 1;
+                    break;
+                },
+
+                Some('\'') => {
+                    flush_quoted(&mut parts, &mut text);
+                    parts.push(WordPart::Quoted(self.parse_single_quoted()?));
+                },
+
+                Some('"') => {
+                    flush_quoted(&mut parts, &mut text);
+                    self.parse_double_quoted(&mut parts)?;
+                },
+
+                Some('\\') => {
+                    match self.peek_at(1) {
+                        Some(escaped) => text.push(escaped),
+                        None => return Err(self.error("unterminated parameter expansion")),
+                    }
+
+                    self.pos += 2;
+                },
+
+                Some('$') => {
+                    match self.parse_dollar(true)? {
+                        Some(part) => {
+                            flush_quoted(&mut parts, &mut text);
+                            parts.push(part);
+                        },
+
+                        None => {
+                            text.push('$');
+                        },
+                    }
+                },
+
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                },
+
+                None => {
+                    return Err(self.error("unterminated parameter expansion"));
+                },
+            }
+        }
+
+        flush_quoted(&mut parts, &mut text);
+
+        Ok(Word {parts})
+    }
+}
+
+fn flush_bare(parts: &mut Vec<WordPart>, bare: &mut String) {
+    if !bare.is_empty() {
+        parts.push(WordPart::Bare(std::mem::take(bare)));
+    }
+}
+
+fn flush_quoted(parts: &mut Vec<WordPart>, text: &mut String) {
+    if !text.is_empty() {
+        parts.push(WordPart::Quoted(std::mem::take(text)));
+    }
+}
+
+/// Turns words such as `FOO=bar` into assignments; only the leading words of
+/// a command are considered.
+fn split_assignment(word: &Word) -> Option<(String, Word)> {
+    let Some(WordPart::Bare(first)) = word.parts.first() else {
+        return None;
+    };
+
+    let (name, value)
+        = first.split_once('=')?;
+
+    let is_valid_name
+        = name.chars().next().is_some_and(is_name_start) && name.chars().all(is_name_char);
+
+    if !is_valid_name {
+        return None;
+    }
+
+    let mut parts
+        = vec![];
+
+    if !value.is_empty() {
+        parts.push(WordPart::Bare(value.to_string()));
+    }
+
+    parts.extend(word.parts[1..].iter().cloned());
+
+    Some((name.to_string(), Word {parts}))
+}
@@ -0,0 +1,142 @@
+use super::*;
+
+fn bare(value: &str) -> WordPart {
+    WordPart::Bare(value.to_string())
+}
+
+fn quoted(value: &str) -> WordPart {
+    WordPart::Quoted(value.to_string())
+}
+
+fn word(parts: Vec<WordPart>) -> Word {
+    Word {parts}
+}
+
+fn simple(script: &Script) -> &SimpleCommand {
+    match &script.chains[0].first.commands[0] {
+        Command::Simple(command) => command,
+        command => panic!("Expected a simple command, got {command:?}"),
+    }
+}
+
+#[test]
+fn it_parses_chains_and_pipelines() {
+    let script
+        = parse_script("a && b || c | d; e\nf").unwrap();
+
+    assert_eq!(script.chains.len(), 3);
+
+    let operators = script.chains[0].rest.iter()
+        .map(|(operator, _)| *operator)
+        .collect::<Vec<_>>();
+
+    assert_eq!(operators, vec![ChainOperator::And, ChainOperator::Or]);
+    assert_eq!(script.chains[0].rest[1].1.commands.len(), 2);
+}
+
+#[test]
+fn it_parses_quotes_and_variables() {
+    let script
+        = parse_script(r#"echo 'a $B' "c $D ${E:-f g}" \*"#).unwrap();
+
+    assert_eq!(simple(&script).args, vec![
+        word(vec![bare("echo")]),
+        word(vec![quoted("a $B")]),
+        word(vec![
+            quoted("c "),
+            WordPart::Variable("D".to_string()),
+            quoted(" "),
+            WordPart::VariableWithDefault {name: "E".to_string(), operator: DefaultOperator::UnsetOrEmpty, default: word(vec![quoted("f g")])},
+        ]),
+        word(vec![quoted("*")]),
+    ]);
+}
+
+#[test]
+fn it_keeps_empty_quoted_arguments() {
+    let script
+        = parse_script(r#"echo """#).unwrap();
+
+    assert_eq!(simple(&script).args[1], word(vec![quoted("")]));
+}
+
+#[test]
+fn it_parses_assignments_and_redirections() {
+    let script
+        = parse_script("FOO=bar BAZ= node script.js > out.txt 2>&1 < in.txt").unwrap();
+
+    let command
+        = simple(&script);
+
+    assert_eq!(command.assignments, vec![
+        ("FOO".to_string(), word(vec![bare("bar")])),
+        ("BAZ".to_string(), word(vec![])),
+    ]);
+
+    assert_eq!(command.args, vec![word(vec![bare("node")]), word(vec![bare("script.js")])]);
+
+    assert_eq!(command.redirections, vec![
+        Redirection {fd: 1, kind: RedirectionKind::Write, target: word(vec![bare("out.txt")])},
+        Redirection {fd: 2, kind: RedirectionKind::Duplicate, target: word(vec![bare("1")])},
+        Redirection {fd: 0, kind: RedirectionKind::Read, target: word(vec![bare("in.txt")])},
+    ]);
+}
+
+#[test]
+fn it_parses_subshells() {
+    let script
+        = parse_script("(cd dir && make) > log").unwrap();
+
+    let Command::Subshell {body, redirections} = &script.chains[0].first.commands[0] else {
+        panic!("Expected a subshell");
+    };
+
+    assert_eq!(body.chains.len(), 1);
+    assert_eq!(body.chains[0].rest.len(), 1);
+    assert_eq!(redirections.len(), 1);
+}
+
+#[test]
+fn it_appends_arguments_to_the_last_command() {
+    let mut script
+        = parse_script("build && test").unwrap();
+
+    script.append_args(&["--watch".to_string(), "$HOME *".to_string()]);
+
+    let Command::Simple(command) = &script.chains[0].rest[0].1.commands[0] else {
+        panic!("Expected a simple command");
+    };
+
+    assert_eq!(command.args, vec![
+        word(vec![bare("test")]),
+        word(vec![quoted("--watch")]),
+        word(vec![quoted("$HOME *")]),
+    ]);
+}
+
+#[test]
+fn it_parses_command_substitutions() {
+    let script
+        = parse_script(r#"echo $(pwd)/a "`echo \"b\"`""#).unwrap();
+
+    assert_eq!(simple(&script).args, vec![
+        word(vec![bare("echo")]),
+        word(vec![
+            WordPart::CommandSubstitution {script: parse_script("pwd").unwrap(), is_quoted: false},
+            bare("/a"),
+        ]),
+        word(vec![
+            WordPart::CommandSubstitution {script: parse_script(r#"echo "b""#).unwrap(), is_quoted: true},
+        ]),
+    ]);
+}
+
+#[test]
+fn it_rejects_unsupported_syntax() {
+    assert!(parse_script("echo $((1 + 2))").is_err());
+    assert!(parse_script("echo $(pwd").is_err());
+    assert!(parse_script("sleep 1 &").is_err());
+    assert!(parse_script("echo 'unterminated").is_err());
+    assert!(parse_script("(echo").is_err());
+    assert!(parse_script("a &&").is_err());
+}
@@ -0,0 +1,188 @@
+use zpm_utils::FromFileString;
+
+use crate::error::Error;
+
+#[cfg(test)]
+#[path = "./spdx.test.rs"]
+mod spdx_tests;
+
+/// A parsed SPDX license expression, such as `MIT OR (Apache-2.0 AND
+/// GPL-2.0-only WITH Classpath-exception-2.0)`. `WITH` binds tighter than
+/// `AND`, which binds tighter than `OR`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpdxExpression {
+    License {
+        id: String,
+        exception: Option<String>,
+    },
+
+    And(Box<SpdxExpression>, Box<SpdxExpression>),
+    Or(Box<SpdxExpression>, Box<SpdxExpression>),
+}
+
+impl SpdxExpression {
+    /// Returns the licenses referenced by the expression, in order.
+    pub fn licenses(&self) -> Vec<(&str, Option<&str>)> {
+        match self {
+            SpdxExpression::License {id, exception}
+                => vec![(id.as_str(), exception.as_deref())],
+
+            SpdxExpression::And(left, right) | SpdxExpression::Or(left, right)
+                => left.licenses().into_iter().chain(right.licenses()).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    And,
+    Or,
+    With,
+    Id(&'a str),
+}
+
+fn tokenize(src: &str) -> Vec<Token<'_>> {
+    let mut tokens
+        = Vec::new();
+
+    for word in src.split_whitespace() {
+        let mut rest
+            = word;
+
+        while !rest.is_empty() {
+            if let Some(next) = rest.strip_prefix('(') {
+                tokens.push(Token::Open);
+                rest = next;
+                continue;
+            }
+
+            if let Some(next) = rest.strip_prefix(')') {
+                tokens.push(Token::Close);
+                rest = next;
+                continue;
+            }
+
+            let end = rest.find(['(', ')'])
+                .unwrap_or(rest.len());
+
+            let (id, next)
+                = rest.split_at(end);
+
+            tokens.push(match id {
+                "AND" | "and" => Token::And,
+                "OR" | "or" => Token::Or,
+                "WITH" | "with" => Token::With,
+                _ => Token::Id(id),
+            });
+
+            rest = next;
+        }
+    }
+
+    tokens
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Token<'a>>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> Error {
+        Error::InvalidSpdxExpression(self.src.to_string())
+    }
+
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token
+            = self.tokens.get(self.position).cloned();
+
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<SpdxExpression, Error> {
+        let mut expression
+            = self.parse_and()?;
+
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            expression = SpdxExpression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<SpdxExpression, Error> {
+        let mut expression
+            = self.parse_atom()?;
+
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            expression = SpdxExpression::And(Box::new(expression), Box::new(self.parse_atom()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_atom(&mut self) -> Result<SpdxExpression, Error> {
+        match self.next() {
+            Some(Token::Open) => {
+                let expression
+                    = self.parse_or()?;
+
+                match self.next() {
+                    Some(Token::Close) => Ok(expression),
+                    _ => Err(self.error()),
+                }
+            },
+
+            Some(Token::Id(id)) => {
+                let exception = if self.peek() == Some(&Token::With) {
+                    self.position += 1;
+
+                    match self.next() {
+                        Some(Token::Id(exception)) => Some(exception.to_string()),
+                        _ => return Err(self.error()),
+                    }
+                } else {
+                    None
+                };
+
+                Ok(SpdxExpression::License {
+                    id: id.to_string(),
+                    exception,
+                })
+            },
+
+            _ => Err(self.error()),
+        }
+    }
+}
+
+impl FromFileString for SpdxExpression {
+    type Error = Error;
+
+    fn from_file_string(src: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            src,
+            tokens: tokenize(src),
+            position: 0,
+        };
+
+        let expression
+            = parser.parse_or()?;
+
+        if parser.position != parser.tokens.len() {
+            return Err(parser.error());
+        }
+
+        Ok(expression)
+    }
+}
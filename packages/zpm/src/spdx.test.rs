@@ -0,0 +1,61 @@
+use zpm_utils::FromFileString;
+
+use super::*;
+
+fn license(id: &str) -> SpdxExpression {
+    SpdxExpression::License {
+        id: id.to_string(),
+        exception: None,
+    }
+}
+
+#[test]
+fn parses_a_single_license() {
+    assert_eq!(SpdxExpression::from_file_string("MIT").unwrap(), license("MIT"));
+    assert_eq!(SpdxExpression::from_file_string("  Apache-2.0  ").unwrap(), license("Apache-2.0"));
+}
+
+#[test]
+fn parses_exceptions() {
+    assert_eq!(SpdxExpression::from_file_string("GPL-2.0-only WITH Classpath-exception-2.0").unwrap(), SpdxExpression::License {
+        id: "GPL-2.0-only".to_string(),
+        exception: Some("Classpath-exception-2.0".to_string()),
+    });
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    assert_eq!(SpdxExpression::from_file_string("MIT OR Apache-2.0 AND BSD-3-Clause").unwrap(), SpdxExpression::Or(
+        Box::new(license("MIT")),
+        Box::new(SpdxExpression::And(Box::new(license("Apache-2.0")), Box::new(license("BSD-3-Clause")))),
+    ));
+}
+
+#[test]
+fn parses_parentheses() {
+    assert_eq!(SpdxExpression::from_file_string("(MIT OR Apache-2.0) AND ISC").unwrap(), SpdxExpression::And(
+        Box::new(SpdxExpression::Or(Box::new(license("MIT")), Box::new(license("Apache-2.0")))),
+        Box::new(license("ISC")),
+    ));
+
+    assert_eq!(SpdxExpression::from_file_string("(MIT)").unwrap(), license("MIT"));
+}
+
+#[test]
+fn lists_the_referenced_licenses() {
+    let expression
+        = SpdxExpression::from_file_string("(MIT OR GPL-2.0-only WITH Classpath-exception-2.0) AND ISC").unwrap();
+
+    assert_eq!(expression.licenses(), vec![
+        ("MIT", None),
+        ("GPL-2.0-only", Some("Classpath-exception-2.0")),
+        ("ISC", None),
+    ]);
+}
+
+#[test]
+fn rejects_invalid_expressions() {
+    for src in ["", "MIT OR", "AND MIT", "(MIT", "MIT)", "MIT Apache-2.0", "MIT WITH", "MIT WITH (ISC)"] {
+        assert!(SpdxExpression::from_file_string(src).is_err(), "{src:?} should be rejected");
+    }
+}
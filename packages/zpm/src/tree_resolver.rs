@@ -21,6 +21,13 @@ pub struct ResolutionTree {
     pub optional_builds: BTreeSet<Locator>,
 }
 
+/// An override that only applies to the dependencies of a package reached
+/// through the given chain of idents (`a/b` for a `a/b/c` resolution).
+pub struct PathOverride {
+    pub path: Vec<Ident>,
+    pub descriptor: Descriptor,
+}
+
 #[derive(Default)]
 pub struct TreeResolver {
     resolution_tree: ResolutionTree,
@@ -33,6 +40,7 @@ pub struct TreeResolver {
     virtual_instances: BTreeMap<Locator, BTreeMap<(Ident, Vec<Locator>), Descriptor>>,
     volatile_descriptor: BTreeSet<Descriptor>,
     volatile_locator: BTreeSet<Locator>,
+    path_overrides: Vec<PathOverride>,
 }
 
 impl TreeResolver {
@@ -78,6 +86,39 @@ impl TreeResolver {
         self
     }
 
+    pub fn with_path_overrides(mut self, path_overrides: Vec<PathOverride>) -> Self {
+        self.path_overrides = path_overrides;
+        self
+    }
+
+    fn resolution_stack_ends_with(&self, idents: &[Ident]) -> bool {
+        idents.len() <= self.resolution_stack.len() && self.resolution_stack.iter().rev()
+            .zip(idents.iter().rev())
+            .all(|(locator, ident)| locator.ident == *ident)
+    }
+
+    /// Whether the given dependency of the package at the top of the
+    /// resolution stack is part of the path of an override, in which case
+    /// it must be copied so the override doesn't leak to the other branches
+    /// of the tree that use the same package.
+    fn is_on_override_path(&self, ident: &Ident) -> bool {
+        self.path_overrides.iter().any(|path_override| {
+            path_override.path.iter().enumerate().skip(1).any(|(index, segment)| {
+                segment == ident && self.resolution_stack_ends_with(&path_override.path[..index])
+            })
+        })
+    }
+
+    /// Returns the overrides that apply to the dependencies of the given
+    /// dependency of the package at the top of the resolution stack.
+    fn matching_path_overrides(&self, ident: &Ident) -> Vec<Descriptor> {
+        self.path_overrides.iter()
+            .filter(|path_override| path_override.path.last() == Some(ident))
+            .filter(|path_override| self.resolution_stack_ends_with(&path_override.path[..path_override.path.len() - 1]))
+            .map(|path_override| path_override.descriptor.clone())
+            .collect()
+    }
+
     pub fn run(mut self) -> ResolutionTree {
         let roots = self.resolution_tree.roots.iter()
             .sorted()
@@ -143,6 +184,7 @@ impl TreeResolver {
             next_peer_slots: BTreeMap<Ident, Locator>,
 
             is_optional: bool,
+            is_path_copy: bool,
         }
 
         let mut virtual_operations = vec![];
@@ -182,7 +224,10 @@ impl TreeResolver {
             let has_peer_dependencies = !pkg
                 .peer_dependencies.is_empty();
 
-            if !has_peer_dependencies {
+            let is_path_copy
+                = self.is_on_override_path(&dependency_descriptor.ident);
+
+            if !has_peer_dependencies && !is_path_copy {
                 self.resolve_peer_dependencies(
                     dependency_descriptor,
                     &dependency_locator.clone(),
@@ -205,6 +250,18 @@ impl TreeResolver {
 
             virtualized_resolution.locator = virtualized_locator.clone();
 
+            if is_path_copy {
+                for override_descriptor in self.matching_path_overrides(&dependency_descriptor.ident) {
+                    let is_regular_dependency
+                        = virtualized_resolution.dependencies.contains_key(&override_descriptor.ident)
+                            && !virtualized_resolution.peer_dependencies.contains_key(&override_descriptor.ident);
+
+                    if is_regular_dependency {
+                        virtualized_resolution.dependencies.insert(override_descriptor.ident.clone(), override_descriptor);
+                    }
+                }
+            }
+
             // We need to add it so it can be removed from within the nested resolve_peer_dependencies_impl call
             self.volatile_locator.insert(virtualized_locator.clone());
 
@@ -219,9 +276,15 @@ impl TreeResolver {
                 virtualized_descriptor: virtualized_descriptor.clone(),
                 virtualized_locator: virtualized_locator.clone(),
 
-                next_peer_slots: BTreeMap::new(),
+                // Packages only copied for an override have no peer dependency
+                // of their own, so they keep forwarding those of their parent
+                next_peer_slots: match has_peer_dependencies {
+                    true => BTreeMap::new(),
+                    false => peer_slots.clone(),
+                },
 
                 is_optional,
+                is_path_copy,
             });
 
             self.resolution_tree.descriptor_to_locator.insert(
@@ -333,6 +396,12 @@ impl TreeResolver {
                     continue;
                 }
 
+                // Copies made for an override must stay specific to their path,
+                // even if their dependencies are the same as another instance's
+                if operation.is_path_copy {
+                    continue;
+                }
+
                 let virtual_instance_resolutions: Vec<_> = self.resolution_tree.locator_resolutions
                     .get(&operation.virtualized_locator).unwrap()
                     .dependencies.values()
@@ -462,8 +531,12 @@ impl TreeResolver {
                 virtualized_resolution.dependencies.remove(missing_peer_dependency);
             }
 
-            // No need to keep track of the original package after it's been virtualized
-            self.resolution_tree.optional_builds.remove(&operation.physical_locator);
+            // No need to keep track of the original package after it's been
+            // virtualized; packages copied for an override may still be used
+            // as-is by other branches, though
+            if !operation.is_path_copy {
+                self.resolution_tree.optional_builds.remove(&operation.physical_locator);
+            }
         }
     }
 
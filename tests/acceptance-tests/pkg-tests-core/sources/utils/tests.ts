@@ -401,7 +401,7 @@ export const startPackageServer = ({type}: {type: keyof typeof packageServerUrls
   };
 
   const processors: {[requestType in RequestType]: (parsedRequest: Request, request: IncomingMessage, response: ServerResponse) => Promise<void>} = {
-    async [RequestType.PackageInfo](parsedRequest, _, response) {
+    async [RequestType.PackageInfo](parsedRequest, request, response) {
       if (parsedRequest.type !== RequestType.PackageInfo)
         throw new Error(`Assertion failed: Invalid request type`);
 
@@ -469,7 +469,14 @@ export const startPackageServer = ({type}: {type: keyof typeof packageServerUrls
         },
       });
 
-      response.writeHead(200, {[`Content-Type`]: `application/json`});
+      const etag = `"${crypto.createHash(`sha1`).update(data).digest(`hex`)}"`;
+      if (request.headers[`if-none-match`] === etag) {
+        response.writeHead(304, {[`ETag`]: etag});
+        response.end();
+        return;
+      }
+
+      response.writeHead(200, {[`Content-Type`]: `application/json`, [`ETag`]: etag});
       response.end(data);
     },
 
@@ -51,6 +51,24 @@ describe(`Basic tests`, () => {
         ),
       );
 
+      test(
+        `it should treat empty dependency ranges as wildcards`,
+        makeTemporaryEnv(
+          {
+            dependencies: {[`no-deps`]: ``},
+          },
+          config,
+          async ({path, run, source}) => {
+            await run(`install`);
+
+            await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+              name: `no-deps`,
+              version: `2.0.0`,
+            });
+          },
+        ),
+      );
+
       test(
         `it should correctly install a single scoped dependency that contains no sub-dependencies`,
         makeTemporaryEnv(
@@ -1,4 +1,4 @@
-import {npath, ppath} from '@yarnpkg/fslib';
+import {npath, ppath, xfs} from '@yarnpkg/fslib';
 
 import {environments} from './constraints/environments';
 
@@ -125,6 +125,142 @@ describe(`Commands`, () => {
       await run(`constraints`);
     }));
 
+    it(`should reuse the previous result when neither the config nor the project changed`, makeTemporaryEnv({}, async ({path, run, source}) => {
+      await run(`install`);
+
+      const config = `
+        require('fs').appendFileSync(require('path').join(__dirname, 'runs.log'), 'run\\n');
+
+        exports.constraints = ({Yarn}) => {
+        };
+      `;
+
+      await writeFile(ppath.join(path, `yarn.config.cjs`), config);
+
+      await run(`constraints`);
+      await run(`constraints`);
+
+      await expect(xfs.readFilePromise(ppath.join(path, `runs.log`), `utf8`)).resolves.toEqual(`run\n`);
+
+      await writeFile(ppath.join(path, `yarn.config.cjs`), `${config}\n// changed\n`);
+
+      await run(`constraints`);
+
+      await expect(xfs.readFilePromise(ppath.join(path, `runs.log`), `utf8`)).resolves.toEqual(`run\nrun\n`);
+    }));
+
+    it(`should run again when a file required by the config changed`, makeTemporaryEnv({}, async ({path, run, source}) => {
+      await run(`install`);
+
+      await writeFile(ppath.join(path, `helper.js`), `
+        exports.version = '1.0.0';
+      `);
+
+      await writeFile(ppath.join(path, `yarn.config.cjs`), `
+        require('fs').appendFileSync(require('path').join(__dirname, 'runs.log'), 'run\\n');
+
+        require('./helper');
+
+        exports.constraints = ({Yarn}) => {
+        };
+      `);
+
+      await run(`constraints`);
+      await run(`constraints`);
+
+      await expect(xfs.readFilePromise(ppath.join(path, `runs.log`), `utf8`)).resolves.toEqual(`run\n`);
+
+      await writeFile(ppath.join(path, `helper.js`), `
+        exports.version = '2.0.0';
+      `);
+
+      await run(`constraints`);
+
+      await expect(xfs.readFilePromise(ppath.join(path, `runs.log`), `utf8`)).resolves.toEqual(`run\nrun\n`);
+    }));
+
+    it(`should transpile yarn.config.ts files before running them`, makeTemporaryEnv({
+      dependencies: {
+        [`esbuild`]: `1.0.0`,
+      },
+    }, async ({path, run, source}) => {
+      await run(`install`);
+
+      await writeFile(ppath.join(path, `helper.js`), `
+        exports.value = 'bar';
+      `);
+
+      await writeFile(ppath.join(path, `yarn.config.ts`), `
+        interface Context { Yarn: any }
+
+        export default {
+          constraints(context: Context) {
+            context.Yarn.workspace().set('foo', require('./helper').value);
+          },
+        };
+      `);
+
+      await run(`constraints`, `--fix`);
+
+      await expect(xfs.readJsonPromise(ppath.join(path, `package.json`))).resolves.toMatchObject({
+        foo: `bar`,
+      });
+    }));
+
+    it(`should prefer yarn.config.cjs over yarn.config.ts`, makeTemporaryEnv({}, async ({path, run, source}) => {
+      await run(`install`);
+
+      await writeFile(ppath.join(path, `yarn.config.ts`), `
+        throw new Error('This should not run');
+      `);
+
+      await writeFile(ppath.join(path, `yarn.config.cjs`), `
+        exports.constraints = ({Yarn}) => {
+        };
+      `);
+
+      await run(`constraints`);
+    }));
+
+    it(`should list the changes applied by --fix`, makeTemporaryEnv({
+      dependencies: {
+        [`no-deps`]: `1.0.0`,
+      },
+    }, async ({path, run, source}) => {
+      await run(`install`);
+
+      await writeFile(ppath.join(path, `yarn.config.cjs`), `
+        exports.constraints = ({Yarn}) => {
+          Yarn.workspace().set('license', 'MIT');
+          Yarn.workspace().unset('dependencies.no-deps');
+        };
+      `);
+
+      const {stdout} = await run(`constraints`, `--fix`);
+
+      expect(stdout).toContain(`Applied 2 fixes`);
+      expect(stdout).toMatch(/license: undefined → "MIT"/);
+      expect(stdout).toMatch(/dependencies\["no-deps"\]: "1\.0\.0" → undefined/);
+
+      await expect(xfs.readJsonPromise(ppath.join(path, `package.json`))).resolves.toEqual({
+        name: expect.any(String),
+        license: `MIT`,
+      });
+    }));
+
+    it(`should exit with a non-zero code when --fix can't fix everything`, makeTemporaryEnv({}, async ({path, run, source}) => {
+      await run(`install`);
+
+      await writeFile(ppath.join(path, `yarn.config.cjs`), `
+        exports.constraints = ({Yarn}) => {
+          Yarn.workspace().set('license', 'MIT');
+          Yarn.workspace().set('license', 'ISC');
+        };
+      `);
+
+      await expect(run(`constraints`, `--fix`)).rejects.toThrow(/Conflict detected in constraint targeting license/);
+    }));
+
     it(`shouldn't report errors when comparing identical objects`, makeTemporaryEnv({
       foo: {
         ok: true,
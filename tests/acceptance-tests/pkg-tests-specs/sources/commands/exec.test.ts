@@ -48,5 +48,59 @@ describe(`Commands`, () => {
         });
       }),
     );
+
+    test(
+      `it should fallback to the binaries from the PATH`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await run(`install`);
+
+        await expect(run(`exec`, `node`, `-e`, `process.exit(42)`)).rejects.toMatchObject({
+          code: 42,
+        });
+      }),
+    );
+
+    test(
+      `it should suggest near-miss binaries when the binary cannot be found`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await xfs.mkdirPromise(ppath.join(path, `my-tool`));
+        await xfs.writeFilePromise(ppath.join(path, `my-tool/index.js`), `console.log(42)`);
+        await xfs.writeJsonPromise(ppath.join(path, `my-tool`, Filename.manifest), {
+          name: `my-tool`,
+          bin: `./index.js`,
+        });
+
+        await xfs.writeJsonPromise(ppath.join(path, Filename.manifest), {
+          dependencies: {
+            [`my-tool`]: `portal:./my-tool`,
+          },
+        });
+
+        await run(`install`);
+
+        await expect(run(`exec`, `my-toll`)).rejects.toMatchObject({
+          stdout: expect.stringContaining(`did you mean one of: my-tool?`),
+        });
+      }),
+    );
+
+    test(
+      `it should run the command in the directory given by --cwd`,
+      makeTemporaryEnv({
+        private: true,
+        workspaces: [`packages/*`],
+      }, async ({path, run, source}) => {
+        await xfs.mkdirPromise(ppath.join(path, `packages/foo`), {recursive: true});
+        await xfs.writeJsonPromise(ppath.join(path, `packages/foo`, Filename.manifest), {
+          name: `foo`,
+        });
+
+        await run(`install`);
+
+        await expect(run(`exec`, `--cwd`, `packages/foo`, `node -p "process.cwd() + ' ' + process.env.INIT_CWD"`)).resolves.toMatchObject({
+          stdout: `${npath.fromPortablePath(ppath.join(path, `packages/foo`))} ${npath.fromPortablePath(path)}\n`,
+        });
+      }),
+    );
   });
 });
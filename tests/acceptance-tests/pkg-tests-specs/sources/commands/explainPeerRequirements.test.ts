@@ -0,0 +1,63 @@
+import {misc} from 'pkg-tests-core';
+
+describe(`Commands`, () => {
+  describe(`explain peer-requirements`, () => {
+    test(
+      `it should report missing peer dependencies`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`peer-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`explain`, `peer-requirements`);
+
+        expect(stdout).toContain(`no-deps`);
+        expect(stdout).toContain(`missing`);
+      }),
+    );
+
+    test(
+      `it should report incompatible peer dependencies`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`peer-deps-fixed`]: `1.0.0`,
+          [`no-deps`]: `2.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`explain`, `peer-requirements`, `no-deps`, `--json`);
+        const [entry] = misc.parseJsonStream(stdout);
+
+        expect(entry.value).toEqual(`no-deps`);
+
+        const requirements = Object.values(entry.children) as Array<any>;
+        expect(requirements).toHaveLength(1);
+
+        expect(requirements[0].children).toMatchObject({
+          [`Range`]: `^1.0.0`,
+          [`Provided by`]: `no-deps@npm:2.0.0`,
+          [`Status`]: `❌ incompatible version provided`,
+        });
+      }),
+    );
+
+    test(
+      `it shouldn't list satisfied requirements by default`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`peer-deps`]: `1.0.0`,
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`explain`, `peer-requirements`, `--json`);
+
+        expect(misc.parseJsonStream(stdout)).toEqual([]);
+      }),
+    );
+  });
+});
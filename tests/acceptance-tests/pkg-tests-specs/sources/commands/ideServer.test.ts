@@ -0,0 +1,107 @@
+import {ppath}    from '@yarnpkg/fslib';
+import {fs, misc} from 'pkg-tests-core';
+
+const request = (id: number, method: string, params: object = {}) => {
+  return `${JSON.stringify({jsonrpc: `2.0`, id, method, params})}\n`;
+};
+
+describe(`Commands`, () => {
+  describe(`ide-server`, () => {
+    test(
+      `it should require a handshake before answering requests`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`ide-server`, {
+          stdin: [
+            request(1, `getWorkspaces`),
+            request(2, `handshake`, {protocolVersion: 999}),
+            request(3, `handshake`, {protocolVersion: 1}),
+          ].join(``),
+        });
+
+        expect(misc.parseJsonStream(stdout)).toEqual([
+          {jsonrpc: `2.0`, id: 1, error: {code: -32002, message: expect.any(String)}},
+          {jsonrpc: `2.0`, id: 2, error: {code: -32001, message: expect.any(String)}},
+          {jsonrpc: `2.0`, id: 3, result: {protocolVersion: 1}},
+        ]);
+      }),
+    );
+
+    test(
+      `it should answer queries about the project`,
+      makeTemporaryEnv({
+        workspaces: [`packages/*`],
+      }, async ({path, run, source}) => {
+        await fs.writeJson(ppath.join(path, `packages/a/package.json`), {
+          name: `a`,
+          dependencies: {
+            [`no-deps`]: `1.0.0`,
+          },
+        });
+
+        await run(`install`);
+
+        const {stdout} = await run(`ide-server`, {
+          stdin: [
+            request(1, `handshake`, {protocolVersion: 1}),
+            request(2, `getWorkspaces`),
+            request(3, `resolveRequest`, {ident: `no-deps`, from: `a@workspace:packages/a`}),
+            request(4, `getDependents`, {locator: `no-deps@npm:1.0.0`}),
+            request(5, `getDiagnostics`, {workspace: `a`}),
+          ].join(``),
+        });
+
+        const [, workspaces, resolution, dependents, diagnostics] = misc.parseJsonStream(stdout);
+
+        expect(workspaces.result).toContainEqual({
+          name: `a`,
+          cwd: ppath.join(path, `packages/a`),
+          locator: `a@workspace:packages/a`,
+        });
+
+        expect(resolution.result).toMatchObject({
+          descriptor: `no-deps@npm:1.0.0`,
+          locator: `no-deps@npm:1.0.0`,
+        });
+
+        expect(dependents.result).toEqual([`a@workspace:packages/a`]);
+        expect(diagnostics.result).toEqual([]);
+      }),
+    );
+
+    test(
+      `it should report dependencies missing from the install`,
+      makeTemporaryEnv({
+        workspaces: [`packages/*`],
+      }, async ({path, run, source}) => {
+        await fs.writeJson(ppath.join(path, `packages/a/package.json`), {
+          name: `a`,
+        });
+
+        await run(`install`);
+
+        await fs.writeJson(ppath.join(path, `packages/a/package.json`), {
+          name: `a`,
+          dependencies: {
+            [`no-deps`]: `1.0.0`,
+          },
+        });
+
+        const {stdout} = await run(`ide-server`, {
+          stdin: [
+            request(1, `handshake`, {protocolVersion: 1}),
+            request(2, `getDiagnostics`, {workspace: `a`}),
+          ].join(``),
+        });
+
+        const [, diagnostics] = misc.parseJsonStream(stdout);
+
+        expect(diagnostics.result).toContainEqual(expect.objectContaining({
+          code: `missingDependency`,
+          ident: `no-deps`,
+        }));
+      }),
+    );
+  });
+});
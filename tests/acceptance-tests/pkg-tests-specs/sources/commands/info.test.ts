@@ -237,5 +237,61 @@ describe(`Commands`, () => {
         });
       }),
     );
+
+    test(
+      `it should print the manifest published on the registry when using --registry`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        const {stdout} = await run(`info`, `--registry`, `no-deps@^1.0.0`, `--json`);
+        expect(stdout).toMatchJSON(expect.objectContaining({
+          name: `no-deps`,
+          version: `1.1.0`,
+        }));
+      }),
+    );
+
+    test(
+      `it should only print the requested fields when using --registry`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        const {stdout} = await run(`info`, `--registry`, `no-deps`, `name`, `version`);
+        expect(stdout).toEqual(`no-deps\n2.0.0\n`);
+      }),
+    );
+
+    test(
+      `it should read the manifest from the cache when using --registry --cached`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`info`, `--registry`, `--cached`, `no-deps`, `version`);
+        expect(stdout).toEqual(`1.0.0\n`);
+      }),
+    );
+
+    test(
+      `it should select the version matching the --version range when using --registry`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        const {stdout} = await run(`info`, `--registry`, `no-deps`, `version`, `--version`, `^1.0.0`);
+        expect(stdout).toEqual(`1.1.0\n`);
+      }),
+    );
+
+    test(
+      `it should skip the fields that don't exist when using --registry`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        const {stdout} = await run(`info`, `--registry`, `no-deps`, `doesNotExist.nested`, `version`);
+        expect(stdout).toEqual(`2.0.0\n`);
+      }),
+    );
+
+    test(
+      `it should reject the fields that don't exist when using --registry --strict`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await expect(run(`info`, `--registry`, `no-deps`, `doesNotExist`, `--strict`)).rejects.toThrow();
+      }),
+    );
   });
 });
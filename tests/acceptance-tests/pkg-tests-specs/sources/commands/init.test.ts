@@ -70,5 +70,88 @@ describe(`Commands`, () => {
         });
       }),
     );
+
+    test(
+      `it should extract the files of a registry template when using --template`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await xfs.mktempPromise(async tmpDir => {
+          const pkgDir = ppath.join(tmpDir, `my-package`);
+          await xfs.mkdirpPromise(pkgDir);
+
+          await run(`init`, `--template`, `init-template`, {
+            cwd: pkgDir,
+          });
+
+          await expect(xfs.readFilePromise(ppath.join(pkgDir, `README.md`), `utf8`)).resolves.toMatch(/^# my-package\n/);
+          await expect(xfs.readFilePromise(ppath.join(pkgDir, `src/index.js`), `utf8`)).resolves.toEqual(`console.log(\`Hello from my-package\`);\n`);
+
+          await expect(xfs.readJsonPromise(ppath.join(pkgDir, Filename.manifest))).resolves.toMatchObject({
+            name: `my-package`,
+          });
+
+          // The template isn't allowed to run scripts by default
+          await expect(xfs.existsPromise(ppath.join(pkgDir, `init-script.log`))).resolves.toEqual(false);
+        });
+      }),
+    );
+
+    test(
+      `it should run the init script of allowed templates`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await xfs.mktempPromise(async tmpDir => {
+          const pkgDir = ppath.join(tmpDir, `my-package`);
+          await xfs.mkdirpPromise(pkgDir);
+
+          await xfs.writeFilePromise(ppath.join(pkgDir, `.yarnrc.yml`), `allowedBuildDependencies:\n  - init-template\n`);
+
+          await run(`init`, `--template`, `init-template`, {
+            cwd: pkgDir,
+          });
+
+          await expect(xfs.readFilePromise(ppath.join(pkgDir, `init-script.log`), `utf8`)).resolves.toEqual(`init\n`);
+        });
+      }),
+    );
+
+    test(
+      `it should scaffold a new workspace when using --workspace with a path`,
+      makeTemporaryEnv({
+        private: true,
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await run(`init`, `--workspace=packages/my-workspace`);
+
+        await expect(xfs.readJsonPromise(ppath.join(path, `packages/my-workspace`, Filename.manifest))).resolves.toEqual({
+          name: `my-workspace`,
+        });
+
+        await expect(xfs.readJsonPromise(ppath.join(path, Filename.manifest))).resolves.toMatchObject({
+          workspaces: [`packages/my-workspace`],
+        });
+
+        await expect(run(`workspaces`, `list`, `--json`)).resolves.toMatchObject({
+          stdout: expect.stringContaining(`"name":"my-workspace"`),
+        });
+      }),
+    );
+
+    test(
+      `it shouldn't add the workspace to the root manifest if it's already matched by a pattern`,
+      makeTemporaryEnv({
+        private: true,
+        workspaces: [`packages/*`],
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await run(`init`, `--workspace=packages/my-workspace`);
+
+        await expect(xfs.readJsonPromise(ppath.join(path, Filename.manifest))).resolves.toMatchObject({
+          workspaces: [`packages/*`],
+        });
+
+        await expect(xfs.existsPromise(ppath.join(path, `packages/my-workspace`, Filename.manifest))).resolves.toEqual(true);
+      }),
+    );
   });
 });
@@ -8,64 +8,170 @@ describe(`Commands`, () => {
       makeTemporaryEnv({}, async ({path, run, source}) => {
         const {stdout} = await run(`install`, `--json`);
 
-        expect(misc.parseJsonStream(stdout)).toEqual([{
-          data: `Yarn 0.0.0`,
-          displayName: `YN0000`,
-          indent: `· `,
-          name: 0,
-          type: `info`,
-        }, {
-          data: `┌ Resolution step`,
-          displayName: `YN0000`,
-          indent: ``,
-          name: null,
-          type: `info`,
-        }, {
-          data: `└ Completed`,
-          displayName: `YN0000`,
-          indent: ``,
-          name: null,
-          type: `info`,
-        }, {
-          data: `┌ Fetch step`,
-          displayName: `YN0000`,
-          indent: ``,
-          name: null,
-          type: `info`,
-        }, {
-          data: `└ Completed`,
-          displayName: `YN0000`,
-          indent: ``,
-          name: null,
+        const events = misc.parseJsonStream(stdout);
+
+        expect(events[0]).toEqual({
+          version: 2,
           type: `info`,
+          message: expect.stringMatching(/^Yarn /),
+        });
+
+        expect(events.filter((event: any) => event.type.startsWith(`section_`))).toEqual([{
+          version: 2,
+          type: `section_start`,
+          name: `Installing packages`,
         }, {
-          data: `┌ Link step`,
-          displayName: `YN0000`,
-          indent: ``,
-          name: null,
-          type: `info`,
+          version: 2,
+          type: `section_end`,
+          name: `Installing packages`,
+          durationMs: expect.any(Number),
         }, {
-          data: `└ Completed`,
-          displayName: `YN0000`,
-          indent: ``,
-          name: null,
-          type: `info`,
+          version: 2,
+          type: `section_start`,
+          name: `Linking the project`,
         }, {
-          data: `Done`,
-          displayName: `YN0000`,
-          indent: `· `,
-          name: 0,
-          type: `info`,
+          version: 2,
+          type: `section_end`,
+          name: `Linking the project`,
+          durationMs: expect.any(Number),
         }]);
       }),
     );
 
+    test(
+      `it should print JSON events when using the global --json flag`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        const {stdout} = await run(`--json`, `install`);
+
+        const events = misc.parseJsonStream(stdout);
+
+        expect(events).toContainEqual({
+          version: 2,
+          type: `section_start`,
+          name: `Installing packages`,
+        });
+
+        expect(events).toContainEqual({
+          version: 2,
+          type: `resolution`,
+          locator: `no-deps@npm:1.0.0`,
+        });
+      }),
+    );
+
+    test(
+      `it should report the fetched packages when using --json`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        const {stdout} = await run(`install`, `--json`);
+
+        const events = misc.parseJsonStream(stdout);
+
+        expect(events).toContainEqual({
+          version: 2,
+          type: `fetch`,
+          locator: `no-deps@npm:1.0.0`,
+          size: expect.any(Number),
+          cacheHit: false,
+        });
+
+        expect(events).toContainEqual(expect.objectContaining({
+          type: `progress`,
+          fetchCount: 1,
+        }));
+      }),
+    );
+
+    test(
+      `it should enable the JSON output through YARN_JSON_OUTPUT`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        const {stdout} = await run(`install`, {
+          env: {
+            YARN_JSON_OUTPUT: `1`,
+          },
+        });
+
+        expect(misc.parseJsonStream(stdout)).toContainEqual({
+          version: 2,
+          type: `section_start`,
+          name: `Installing packages`,
+        });
+      }),
+    );
+
+    test(
+      `it should report errors with a stable code when using --json`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await expect(run(`install`, `--json`, `--immutable`)).rejects.toThrow(/"type":"error","code":"IMMUTABLE_LOCKFILE"/);
+      }),
+    );
+
+    test(
+      `it should summarize the packages changed by the install`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+          [`one-fixed-dep`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await xfs.writeJsonPromise(ppath.join(path, Filename.manifest), {
+          dependencies: {
+            [`no-deps`]: `2.0.0`,
+            [`no-deps-bins`]: `1.0.0`,
+          },
+        });
+
+        const {stdout} = await run(`install`);
+
+        expect(stdout).toContain(`Packages: 1 added, 1 removed, 1 changed`);
+        expect(stdout).toContain(`+ no-deps-bins@1.0.0`);
+        expect(stdout).toContain(`- one-fixed-dep@1.0.0`);
+        expect(stdout).toContain(`~ no-deps 1.0.0 → 2.0.0`);
+      }),
+    );
+
+    test(
+      `it shouldn't summarize the packages changed by the install when using --no-summary`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await xfs.writeJsonPromise(ppath.join(path, Filename.manifest), {
+          dependencies: {
+            [`no-deps`]: `2.0.0`,
+          },
+        });
+
+        const {stdout} = await run(`install`, `--no-summary`);
+
+        expect(stdout).not.toContain(`no-deps 1.0.0 → 2.0.0`);
+      }),
+    );
+
     test(
       `it should print the logs to the standard output when using --inline-builds`,
       makeTemporaryEnv({
         dependencies: {
           [`no-deps-scripted`]: `1.0.0`,
         },
+      }, {
+        allowedBuildDependencies: [`no-deps-scripted`],
       }, async ({path, run, source}) => {
         const {stdout} = await run(`install`, `--inline-builds`);
 
@@ -80,6 +186,8 @@ describe(`Commands`, () => {
         dependencies: {
           [`no-deps-scripted`]: `1.0.0`,
         },
+      }, {
+        allowedBuildDependencies: [`no-deps-scripted`],
       }, async ({path, run, source}) => {
         const {stdout} = await run(`install`, `--inline-builds`, `--mode=skip-build`);
 
@@ -94,6 +202,8 @@ describe(`Commands`, () => {
         dependencies: {
           [`no-deps-scripted`]: `1.0.0`,
         },
+      }, {
+        allowedBuildDependencies: [`no-deps-scripted`],
       }, async ({path, run, source}) => {
         const pnpPath = ppath.join(path, Filename.pnpCjs);
 
@@ -218,6 +328,119 @@ describe(`Commands`, () => {
       }),
     );
 
+    test(
+      `it should recompute the lockfile checksums from the cache when using --refresh-checksums`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const lockfilePath = ppath.join(path, Filename.lockfile);
+        const lockfileContent = await xfs.readFilePromise(lockfilePath, `utf8`);
+
+        const outdatedLockfile = lockfileContent.replace(/checksum: [0-9a-f]+/, `checksum: 0000000000000000`);
+        expect(outdatedLockfile).not.toEqual(lockfileContent);
+
+        await xfs.writeFilePromise(lockfilePath, outdatedLockfile);
+
+        // The archive is already in the cache, so it shouldn't be downloaded again
+        await run(`install`, `--refresh-checksums`, {enableNetwork: false});
+
+        await expect(xfs.readFilePromise(lockfilePath, `utf8`)).resolves.toEqual(lockfileContent);
+      }),
+    );
+
+    test(
+      `it should validate the cache content when using --check-cache`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`install`, `--check-cache`);
+        expect(stdout).toContain(`Verified 1 cached packages`);
+      }),
+    );
+
+    test(
+      `it should report the packages whose cached archive got altered when using --check-cache`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+          [`no-deps-alias`]: `npm:no-deps@2.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const cachePath = ppath.join(path, `.yarn/cache`);
+        const cacheEntries = await xfs.readdirPromise(cachePath);
+
+        const originalArchive = cacheEntries.find(entry => entry.startsWith(`no-deps-npm-1.0.0`));
+        const replacementArchive = cacheEntries.find(entry => entry.startsWith(`no-deps-npm-2.0.0`));
+
+        // Both archives store their files under the same path, so swapping
+        // them keeps a valid archive with a different content
+        await xfs.copyFilePromise(ppath.join(cachePath, replacementArchive!), ppath.join(cachePath, originalArchive!));
+
+        await expect(run(`install`, `--check-cache`)).rejects.toMatchObject({
+          code: 1,
+          stdout: expect.stringContaining(`no-deps@npm:1.0.0`),
+        });
+      }),
+    );
+
+    test(
+      `it should keep tag ranges pinned when using --refresh-lockfile`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `latest`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+        await run(`set`, `resolution`, `no-deps@npm:latest`, `npm:1.0.0`);
+
+        await run(`install`, `--refresh-lockfile`);
+
+        await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+          name: `no-deps`,
+          version: `1.0.0`,
+        });
+      }),
+    );
+
+    test(
+      `it should re-resolve tag ranges when using --refresh-lockfile with refreshTagRanges set to follow`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `latest`,
+        },
+      }, {
+        refreshTagRanges: `follow`,
+      }, async ({path, run, source}) => {
+        await run(`install`);
+        await run(`set`, `resolution`, `no-deps@npm:latest`, `npm:1.0.0`);
+
+        await run(`install`);
+
+        // Sanity check
+        await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+          name: `no-deps`,
+          version: `1.0.0`,
+        });
+
+        await run(`install`, `--refresh-lockfile`);
+
+        await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+          name: `no-deps`,
+          version: `2.0.0`,
+        });
+      }),
+    );
+
     test(
       `it should block invalid lockfiles when using --refresh-lockfile with --immutable`,
       makeTemporaryEnv({
@@ -820,6 +1043,79 @@ describe(`Commands`, () => {
       }),
     );
 
+    test(
+      `it should skip and report the build scripts of dependencies when \`allowedBuildDependencies\` is empty`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps-scripted`]: `1.0.0`,
+        },
+        scripts: {
+          postinstall: `echo 'module.exports = ["postinstall"];' > log.js`,
+        },
+      }, async ({path, run, source}) => {
+        const {stdout} = await run(`install`, `--inline-builds`);
+        expect(stdout).toMatch(/their builds were skipped: no-deps-scripted@npm:1\.0\.0; add them to allowedBuildDependencies/);
+
+        await expect(source(`require('no-deps-scripted/log')`)).rejects.toThrow();
+
+        // Workspaces are always allowed to run their build scripts
+        await expect(source(`require('./log')`)).resolves.toEqual([
+          `postinstall`,
+        ]);
+      }),
+    );
+
+    test(
+      `it should only run the build scripts of dependencies matching the \`allowedBuildDependencies\` ranges`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps-scripted`]: `1.0.0`,
+          [`no-deps-scripted-bis`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await xfs.writeJsonPromise(ppath.join(path, Filename.rc), {
+          allowedBuildDependencies: [`no-deps-scripted@^1.0.0`, `no-deps-scripted-bis@^2.0.0`],
+        });
+
+        const {stdout} = await run(`install`, `--inline-builds`);
+        expect(stdout).toMatch(/their builds were skipped: no-deps-scripted-bis@npm:1\.0\.0/);
+
+        await expect(source(`require('no-deps-scripted/log')`)).resolves.toEqual([
+          `preinstall`,
+          `install`,
+          `postinstall`,
+        ]);
+
+        await expect(source(`require('no-deps-scripted-bis/log')`)).rejects.toThrow();
+      }),
+    );
+
+    test(
+      `it should only run the build scripts of dependencies listed in \`allowedBuildDependencies\` when using \`enableScripts: allowlist\``,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps-scripted`]: `1.0.0`,
+          [`no-deps-scripted-bis`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await xfs.writeJsonPromise(ppath.join(path, Filename.rc), {
+          enableScripts: `allowlist`,
+          allowedBuildDependencies: [`no-deps-scripted@^1.0.0`],
+        });
+
+        const {stdout} = await run(`install`, `--inline-builds`);
+        expect(stdout).toMatch(/their builds were skipped: no-deps-scripted-bis@npm:1\.0\.0; add them to allowedBuildDependencies/);
+
+        await expect(source(`require('no-deps-scripted/log')`)).resolves.toEqual([
+          `preinstall`,
+          `install`,
+          `postinstall`,
+        ]);
+
+        await expect(source(`require('no-deps-scripted-bis/log')`)).rejects.toThrow();
+      }),
+    );
+
     test(
       `it should print an info when \`dependenciesMeta[].built: false\`, even when using using \`enableScripts: false\``,
       makeTemporaryEnv({
@@ -963,5 +1259,74 @@ module.exports = {
         });
       }),
     );
+
+    test(
+      `it should report the network statistics when using --verbose`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+          [`one-fixed-dep`]: `1.0.0`,
+        },
+      }, async ({path, run}) => {
+        const {stdout} = await run(`install`, `--verbose`);
+
+        expect(stdout).toMatch(/Sent \d+ network requests, opened \d+ connections, reused \d+/);
+      }),
+    );
+
+    test(
+      `it should skip the dev dependencies when using --production`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`one-fixed-dep`]: `1.0.0`,
+        },
+        devDependencies: {
+          [`no-deps-bins`]: `1.0.0`,
+        },
+      }, {
+        nodeLinker: `node-modules`,
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const lockfile
+          = await xfs.readFilePromise(ppath.join(path, Filename.lockfile), `utf8`);
+
+        await run(`install`, `--production`, `--immutable`);
+
+        expect(xfs.existsSync(ppath.join(path, `node_modules/no-deps-bins`))).toEqual(false);
+        await expect(source(`require('one-fixed-dep')`)).resolves.toMatchObject({
+          name: `one-fixed-dep`,
+        });
+
+        expect(await xfs.readFilePromise(ppath.join(path, Filename.lockfile), `utf8`)).toEqual(lockfile);
+      }),
+    );
+
+    test(
+      `it should skip the dev dependencies when NODE_ENV is set to production`,
+      makeTemporaryEnv({
+        devDependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, {
+        nodeLinker: `node-modules`,
+      }, async ({path, run}) => {
+        await run(`install`, {
+          env: {
+            NODE_ENV: `production`,
+          },
+        });
+
+        expect(xfs.existsSync(ppath.join(path, `node_modules/no-deps`))).toEqual(false);
+
+        await run(`install`, `--no-production`, {
+          env: {
+            NODE_ENV: `production`,
+          },
+        });
+
+        expect(xfs.existsSync(ppath.join(path, `node_modules/no-deps`))).toEqual(true);
+      }),
+    );
   });
 });
@@ -1,6 +1,8 @@
 import {Filename, ppath, xfs} from '@yarnpkg/fslib';
 import {tests}                from 'pkg-tests-core';
 
+const {setPackageWhitelist} = tests;
+
 describe(`Commands`, () => {
   describe(`npm audit`, () => {
     test(
@@ -284,5 +286,102 @@ describe(`Commands`, () => {
         await expect(run(`npm`, `audit`)).rejects.toThrow(/no-deps-deprecated-whitespace \(deprecation\)/);
       }),
     );
+
+    test(
+      `it should upgrade vulnerable packages within their ranges when using --fix`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await setPackageWhitelist(new Map([[`vulnerable`, new Set([`1.0.0`])]]), async () => {
+          await run(`add`, `vulnerable@^1.0.0`);
+        });
+
+        await expect(run(`npm`, `audit`, `--fix`)).resolves.toMatchObject({
+          stdout: expect.stringContaining(`Upgraded 1 package to a non-vulnerable version`),
+        });
+
+        await expect(xfs.readJsonPromise(ppath.join(path, Filename.manifest))).resolves.toStrictEqual({
+          dependencies: {
+            [`vulnerable`]: `^1.0.0`,
+          },
+        });
+
+        await expect(source(`require('vulnerable')`)).resolves.toMatchObject({
+          version: `1.1.0`,
+        });
+
+        await run(`npm`, `audit`);
+      }),
+    );
+
+    test(
+      `it shouldn't change the lockfile when using --fix with --dry-run`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await setPackageWhitelist(new Map([[`vulnerable`, new Set([`1.0.0`])]]), async () => {
+          await run(`add`, `vulnerable@^1.0.0`);
+        });
+
+        const lockfile = await xfs.readFilePromise(ppath.join(path, Filename.lockfile), `utf8`);
+
+        await expect(run(`npm`, `audit`, `--fix`, `--dry-run`)).resolves.toMatchObject({
+          stdout: expect.stringContaining(`Would upgrade 1 package to a non-vulnerable version`),
+        });
+
+        await expect(xfs.readFilePromise(ppath.join(path, Filename.lockfile), `utf8`)).resolves.toEqual(lockfile);
+      }),
+    );
+
+    test(
+      `it should suggest a resolutions override for advisories that can't be fixed within the declared ranges`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`vulnerable-dep`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await expect(run(`npm`, `audit`, `-R`, `--fix`)).rejects.toMatchObject({
+          stdout: expect.stringMatching(/"vulnerable@npm:1\.0\.0": "1\.1\.0"/),
+        });
+
+        await expect(source(`require('vulnerable-dep')`)).resolves.toMatchObject({
+          dependencies: {
+            [`vulnerable`]: {
+              version: `1.0.0`,
+            },
+          },
+        });
+      }),
+    );
+
+    test(
+      `it should only fix the packages selected by the audit when using --fix`,
+      makeTemporaryEnv({
+        private: true,
+        workspaces: [`packages/*`],
+      }, async ({path, run, source}) => {
+        await xfs.mkdirpPromise(ppath.join(path, `packages/a`));
+        await xfs.writeJsonPromise(ppath.join(path, `packages/a`, Filename.manifest), {
+          name: `a`,
+          dependencies: {
+            [`vulnerable`]: `^1.0.0`,
+          },
+        });
+
+        await setPackageWhitelist(new Map([[`vulnerable`, new Set([`1.0.0`])]]), async () => {
+          await run(`install`);
+        });
+
+        await expect(run(`npm`, `audit`, `--fix`)).resolves.toMatchObject({
+          stdout: expect.stringContaining(`No audit suggestions`),
+        });
+
+        await expect(run(`npm`, `audit`, `-A`)).rejects.toThrow(/https:\/\/example\.com\/advisories\/1/);
+
+        await expect(run(`npm`, `audit`, `-A`, `--fix`)).resolves.toMatchObject({
+          stdout: expect.stringContaining(`Upgraded 1 package to a non-vulnerable version`),
+        });
+
+        await run(`npm`, `audit`, `-A`);
+      }),
+    );
   });
 });
@@ -36,6 +36,53 @@ describe(`Commands`, () => {
       }),
     );
 
+    test(
+      `it should include the bundled dependencies`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+        bundledDependencies: [`no-deps`],
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await expect(genPackList(run)).resolves.toEqual(expect.arrayContaining([
+          `node_modules/no-deps/package.json`,
+          `node_modules/no-deps/index.js`,
+        ]));
+      }),
+    );
+
+    test(
+      `it should include the dependencies of the bundled dependencies`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`one-fixed-dep`]: `1.0.0`,
+          [`no-deps`]: `2.0.0`,
+        },
+        bundledDependencies: [`one-fixed-dep`, `no-deps`],
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await expect(genPackList(run)).resolves.toEqual(expect.arrayContaining([
+          `node_modules/one-fixed-dep/package.json`,
+          `node_modules/one-fixed-dep/node_modules/no-deps/package.json`,
+          `node_modules/no-deps/package.json`,
+        ]));
+      }),
+    );
+
+    test(
+      `it should reject bundled dependencies that aren't dependencies`,
+      makeTemporaryEnv({
+        bundledDependencies: [`no-deps`],
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await expect(run(`pack`, `--dry-run`)).rejects.toThrow(/isn't a dependency of the packed workspace/);
+      }),
+    );
+
     test(
       `it should only keep the files covered by the "files" field`,
       makeTemporaryEnv({
@@ -403,6 +450,66 @@ describe(`Commands`, () => {
       }),
     );
 
+    test(
+      `it should apply nested ignore files relative to their own folder`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await fsUtils.writeFile(`${path}/x.js`, `module.exports = 42;\n`);
+        await fsUtils.writeFile(`${path}/foo/x.js`, `module.exports = 42;\n`);
+        await fsUtils.writeFile(`${path}/foo/y.js`, `module.exports = 42;\n`);
+        await fsUtils.writeFile(`${path}/foo/bar/y.js`, `module.exports = 42;\n`);
+        await fsUtils.writeFile(`${path}/foo/.npmignore`, `/y.js\n`);
+
+        await run(`install`);
+
+        await expect(genPackList(run)).resolves.toEqual([
+          `foo/bar/y.js`,
+          `foo/x.js`,
+          `package.json`,
+          `x.js`,
+        ]);
+      }),
+    );
+
+    test(
+      `it should apply directory patterns at any depth`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await fsUtils.writeFile(`${path}/build`, `module.exports = 42;\n`);
+        await fsUtils.writeFile(`${path}/foo/build/x.js`, `module.exports = 42;\n`);
+        await fsUtils.writeFile(`${path}/foo/y.js`, `module.exports = 42;\n`);
+        await fsUtils.writeFile(`${path}/.npmignore`, `build/\n`);
+
+        await run(`install`);
+
+        await expect(genPackList(run)).resolves.toEqual([
+          `build`,
+          `foo/y.js`,
+          `package.json`,
+        ]);
+      }),
+    );
+
+    test(
+      `it shouldn't pack configuration files, even when listed in the "files" field`,
+      makeTemporaryEnv({
+        files: [
+          `/lib`,
+          `.npmrc`,
+        ],
+      }, async ({path, run, source}) => {
+        await fsUtils.writeFile(`${path}/.npmrc`, `//registry.example.org/:_authToken=secret\n`);
+        await fsUtils.writeFile(`${path}/lib/.npmrc`, `//registry.example.org/:_authToken=secret\n`);
+        await fsUtils.writeFile(`${path}/lib/package-lock.json`, `{}\n`);
+        await fsUtils.writeFile(`${path}/lib/x.js`, `module.exports = 42;\n`);
+
+        await run(`install`);
+
+        await expect(genPackList(run)).resolves.toEqual([
+          `lib/x.js`,
+          `package.json`,
+        ]);
+      }),
+    );
+
     test(
       `it should keep digging inside excluded folders (unlike gitignore!)`,
       makeTemporaryEnv({}, async ({path, run, source}) => {
@@ -595,6 +702,91 @@ describe(`Commands`, () => {
       }),
     );
 
+    test(
+      `it should replace workspace:^ with a caret range on the depended-on workspace version`,
+      makeTemporaryEnv({
+        workspaces: [`./dependency`, `./dependant`],
+      }, async({path, run, source}) => {
+        await fsUtils.writeJson(`${path}/dependency/package.json`, {
+          name: `@test/dependency`,
+          version: `1.2.3`,
+        });
+
+        await fsUtils.writeJson(`${path}/dependant/package.json`, {
+          name: `@test/dependant`,
+          version: `1.0.0`,
+          dependencies: {
+            [`@test/dependency`]: `workspace:^`,
+          },
+        });
+
+        await run(`install`);
+        await run(`pack`, {
+          cwd: `${path}/dependant`,
+        });
+
+        await fsUtils.unpackToDirectory(path, `${path}/dependant/package.tgz`);
+
+        const packedManifest = await xfs.readJsonPromise(`${path}/package/package.json`);
+        expect(packedManifest.dependencies[`@test/dependency`]).toBe(`^1.2.3`);
+
+        const originalManifest = await xfs.readJsonPromise(`${path}/dependant/package.json`);
+        expect(originalManifest.dependencies[`@test/dependency`]).toBe(`workspace:^`);
+      }),
+    );
+
+    test(
+      `it should refuse to pack a workspace depending on a private workspace`,
+      makeTemporaryEnv({
+        workspaces: [`./dependency`, `./dependant`],
+      }, async({path, run, source}) => {
+        await fsUtils.writeJson(`${path}/dependency/package.json`, {
+          name: `@test/dependency`,
+          version: `1.0.0`,
+          private: true,
+        });
+
+        await fsUtils.writeJson(`${path}/dependant/package.json`, {
+          name: `@test/dependant`,
+          version: `1.0.0`,
+          dependencies: {
+            [`@test/dependency`]: `workspace:^`,
+          },
+        });
+
+        await run(`install`);
+
+        await expect(run(`pack`, {cwd: `${path}/dependant`})).rejects.toMatchObject({
+          stdout: expect.stringContaining(`it's private and won't be published`),
+        });
+      }),
+    );
+
+    test(
+      `it should refuse to pack a workspace depending on a workspace without version`,
+      makeTemporaryEnv({
+        workspaces: [`./dependency`, `./dependant`],
+      }, async({path, run, source}) => {
+        await fsUtils.writeJson(`${path}/dependency/package.json`, {
+          name: `@test/dependency`,
+        });
+
+        await fsUtils.writeJson(`${path}/dependant/package.json`, {
+          name: `@test/dependant`,
+          version: `1.0.0`,
+          dependencies: {
+            [`@test/dependency`]: `workspace:*`,
+          },
+        });
+
+        await run(`install`);
+
+        await expect(run(`pack`, {cwd: `${path}/dependant`})).rejects.toMatchObject({
+          stdout: expect.stringContaining(`it has no version`),
+        });
+      }),
+    );
+
     test(
       `it should always include README (and its variants), even with a "files" field`,
       makeTemporaryEnv({
@@ -700,6 +892,33 @@ describe(`Commands`, () => {
       }),
     );
 
+    test(
+      `it should set the +x flag on executableFiles entries even if the file on disk isn't executable`,
+      makeTemporaryEnv({
+        publishConfig: {
+          executableFiles: [
+            `./bin/run.sh`,
+          ],
+        },
+      }, async ({path, run, source}) => {
+        await xfs.mkdirPromise(`${path}/bin`);
+        await xfs.writeFilePromise(`${path}/bin/run.sh`, `#!/bin/sh\n`);
+        await xfs.chmodPromise(`${path}/bin/run.sh`, 0o644);
+
+        await run(`install`);
+        await run(`pack`);
+
+        const mode = await new Promise(resolve => {
+          tar.t({
+            file: npath.fromPortablePath(`${path}/package.tgz`),
+            onentry: entry => resolve(entry.mode),
+          }, [`package/bin/run.sh`]);
+        });
+
+        expect(mode).toEqual(0o755);
+      }),
+    );
+
     test(
       `it should make the filename non-descriptive by default`,
       makeTemporaryEnv({
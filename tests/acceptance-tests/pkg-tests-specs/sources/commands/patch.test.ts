@@ -56,6 +56,28 @@ describe(`Commands`, () => {
       }),
     );
 
+    test(
+      `it should only print the editable folder and locator when using --json`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`patch`, `no-deps`, `--json`);
+        const patchInfo = JSON.parse(stdout);
+
+        expect(patchInfo).toEqual({
+          locator: `no-deps@npm:1.0.0`,
+          path: expect.any(String),
+        });
+
+        expect(npath.isAbsolute(patchInfo.path)).toEqual(true);
+        await expect(xfs.existsPromise(ppath.join(npath.toPortablePath(patchInfo.path), `package.json`))).resolves.toEqual(true);
+      }),
+    );
+
     test(
       `it should augment current patches when using the -u flag`,
       makeTemporaryEnv({
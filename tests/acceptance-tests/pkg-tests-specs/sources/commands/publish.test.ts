@@ -174,4 +174,24 @@ describe(`publish`, () =>   {
       });
     }),
   );
+
+  testIf(
+    () => !process.env.GITHUB_ACTIONS && !process.env.GITLAB_CI,
+    `should refuse to publish without provenance when it can't be generated`,
+    makeTemporaryEnv({
+      name: `provenance-required`,
+      version: `1.0.0`,
+      publishConfig: {
+        provenance: true,
+      },
+    }, async ({run}) => {
+      await run(`install`);
+
+      await expect(run(`npm`, `publish`, {
+        env: {
+          YARN_NPM_AUTH_TOKEN: validLogins.fooUser.npmAuthToken,
+        },
+      })).rejects.toThrow(`Provenance requires an OIDC token`);
+    }),
+  );
 });
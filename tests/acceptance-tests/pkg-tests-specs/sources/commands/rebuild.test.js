@@ -1,12 +1,14 @@
 describe(`Commands`, () => {
   describe(`rebuild`, () => {
     test(
-      `it rebuild everything when called without arguments`,
+      `it rebuild everything when called with --force and without arguments`,
       makeTemporaryEnv({
         dependencies: {
           [`no-deps-scripted`]: `1.0.0`,
           [`no-deps-scripted-bis`]: `1.0.0`,
         },
+      }, {
+        allowedBuildDependencies: [`no-deps-scripted`, `no-deps-scripted-bis`],
       }, async ({path, run, source}) => {
         await run(`install`);
 
@@ -16,7 +18,7 @@ describe(`Commands`, () => {
           `postinstall`,
         ]);
 
-        await run(`rebuild`);
+        await run(`rebuild`, `--force`);
 
         await expect(source(`require('no-deps-scripted/log')`)).resolves.toEqual([
           `preinstall`,
@@ -39,12 +41,14 @@ describe(`Commands`, () => {
     );
 
     test(
-      `it rebuild a single package when called with arguments`,
+      `it rebuild a single package when called with --force and arguments`,
       makeTemporaryEnv({
         dependencies: {
           [`no-deps-scripted`]: `1.0.0`,
           [`no-deps-scripted-bis`]: `1.0.0`,
         },
+      }, {
+        allowedBuildDependencies: [`no-deps-scripted`, `no-deps-scripted-bis`],
       }, async ({path, run, source}) => {
         await run(`install`);
 
@@ -54,7 +58,7 @@ describe(`Commands`, () => {
           `postinstall`,
         ]);
 
-        await run(`rebuild`, `no-deps-scripted`);
+        await run(`rebuild`, `--force`, `no-deps-scripted`);
 
         await expect(source(`require('no-deps-scripted/log')`)).resolves.toEqual([
           `preinstall`,
@@ -72,5 +76,66 @@ describe(`Commands`, () => {
         ]);
       }),
     );
+
+    test(
+      `it doesn't rebuild packages whose inputs didn't change without --force`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps-scripted`]: `1.0.0`,
+        },
+      }, {
+        allowedBuildDependencies: [`no-deps-scripted`],
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`rebuild`);
+        expect(stdout).toMatch(/Skipped the builds of no-deps-scripted@npm:1\.0\.0/);
+
+        await expect(source(`require('no-deps-scripted/log')`)).resolves.toEqual([
+          `preinstall`,
+          `install`,
+          `postinstall`,
+        ]);
+      }),
+    );
+
+    test(
+      `it builds the listed packages even if they aren't allowed to run their build scripts`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps-scripted`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await expect(source(`require('no-deps-scripted/log')`)).rejects.toThrow();
+
+        await run(`rebuild`, `no-deps-scripted`);
+
+        await expect(source(`require('no-deps-scripted/log')`)).resolves.toEqual([
+          `preinstall`,
+          `install`,
+          `postinstall`,
+        ]);
+      }),
+    );
+
+    test(
+      `it reports which packages matched the listed names`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps-scripted`]: `1.0.0`,
+        },
+      }, {
+        allowedBuildDependencies: [`no-deps-scripted`],
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`rebuild`, `no-deps-scripted`, `no-deps-scriptde`);
+
+        expect(stdout).toMatch(/Rebuilding no-deps-scripted \(no-deps-scripted@npm:1\.0\.0\)/);
+        expect(stdout).toMatch(/No installed package named no-deps-scriptde has build scripts/);
+      }),
+    );
   });
 });
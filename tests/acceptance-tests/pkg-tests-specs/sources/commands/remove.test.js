@@ -201,5 +201,51 @@ describe(`Commands`, () => {
         await expect(readManifest(path)).resolves.not.toHaveProperty(`dependencies`);
       }),
     );
+
+    test(
+      `it should warn about resolutions that don't target any package anymore`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`one-fixed-dep`]: `1.0.0`,
+          [`no-deps-bins`]: `1.0.0`,
+        },
+        resolutions: {
+          [`no-deps`]: `2.0.0`,
+          [`no-deps-bins`]: `2.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        const {stdout} = await run(`remove`, `one-fixed-dep`, `--skip-prompt`);
+
+        expect(stdout).toMatch(/The no-deps resolution doesn't target any package of the project anymore/);
+        expect(stdout).not.toMatch(/The no-deps-bins resolution/);
+
+        await expect(readManifest(path)).resolves.toMatchObject({
+          resolutions: {
+            [`no-deps`]: `2.0.0`,
+          },
+        });
+      }),
+    );
+
+    test(
+      `it should warn about package extensions that don't target any package anymore`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, {
+        packageExtensions: {
+          [`no-deps@*`]: {
+            dependencies: {
+              [`no-deps-bins`]: `1.0.0`,
+            },
+          },
+        },
+      }, async ({path, run, source}) => {
+        const {stdout} = await run(`remove`, `no-deps`);
+
+        expect(stdout).toMatch(/The no-deps@\* package extension doesn't target any package of the project anymore/);
+      }),
+    );
   });
 });
@@ -198,5 +198,161 @@ describe(`Commands`, () => {
         },
       ),
     );
+    test(`it should let scripts call the binaries from their dependencies with the node-modules linker`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`has-bin-entries`]: `1.0.0`,
+          },
+          scripts: {
+            [`test`]: `has-bin-entries success`,
+          },
+        },
+        {
+          nodeLinker: `node-modules`,
+        },
+        async ({path, run, source}) => {
+          await run(`install`);
+
+          await expect(run(`run`, `test`)).resolves.toMatchObject({
+            stdout: `success\n`,
+          });
+        },
+      ),
+    );
+
+    test(`it should let workspace scripts call the binaries installed in their ancestor workspaces with the node-modules linker`,
+      makeTemporaryEnv(
+        {
+          workspaces: [`packages/*`],
+          dependencies: {
+            [`has-bin-entries`]: `1.0.0`,
+          },
+        },
+        {
+          nodeLinker: `node-modules`,
+        },
+        async ({path, run, source}) => {
+          await xfs.mkdirpPromise(ppath.join(path, `packages/child`));
+          await xfs.writeJsonPromise(ppath.join(path, `packages/child/package.json`), {
+            name: `child`,
+            scripts: {
+              [`test`]: `has-bin-entries success`,
+            },
+          });
+
+          await run(`install`);
+
+          await expect(run(`run`, `test`, {
+            cwd: ppath.join(path, `packages/child`),
+          })).resolves.toMatchObject({
+            stdout: `success\n`,
+          });
+        },
+      ),
+    );
+
+    test(`it should run the scripts of the root workspace from any workspace when using --top-level`,
+      makeTemporaryEnv(
+        {
+          workspaces: [`packages/*`],
+          scripts: {
+            [`where`]: `node -p 'require("./package.json").workspaces[0]'`,
+          },
+        },
+        async ({path, run, source}) => {
+          await xfs.mkdirpPromise(ppath.join(path, `packages/child`));
+          await xfs.writeJsonPromise(ppath.join(path, `packages/child/package.json`), {
+            name: `child`,
+          });
+
+          await run(`install`);
+
+          await expect(run(`run`, `-T`, `where`, {
+            cwd: ppath.join(path, `packages/child`),
+          })).resolves.toMatchObject({
+            stdout: `packages/*\n`,
+          });
+        },
+      ),
+    );
+
+    test(`it should fall back to the scripts of the root workspace when enableScriptInheritance is set`,
+      makeTemporaryEnv(
+        {
+          workspaces: [`packages/*`],
+          dependencies: {
+            [`has-bin-entries`]: `1.0.0`,
+          },
+          scripts: {
+            [`where`]: `node -p 'require("./package.json").name'`,
+            [`test`]: `has-bin-entries success`,
+          },
+        },
+        {
+          enableScriptInheritance: true,
+          nodeLinker: `node-modules`,
+        },
+        async ({path, run, source}) => {
+          await xfs.mkdirpPromise(ppath.join(path, `packages/child`));
+          await xfs.writeJsonPromise(ppath.join(path, `packages/child/package.json`), {
+            name: `child`,
+            scripts: {
+              [`local`]: `echo local`,
+            },
+          });
+
+          await run(`install`);
+
+          const cwd = ppath.join(path, `packages/child`);
+
+          await expect(run(`run`, `where`, {cwd})).resolves.toMatchObject({
+            stdout: `child\n`,
+          });
+
+          await expect(run(`run`, `test`, {cwd})).resolves.toMatchObject({
+            stdout: `success\n`,
+          });
+
+          const {stdout} = await run(`run`, `--json`, {cwd});
+
+          expect(misc.parseJsonStream(stdout)).toEqual([{
+            name: `local`,
+            script: `echo local`,
+          }, {
+            name: `where`,
+            script: `node -p 'require("./package.json").name'`,
+            inherited: true,
+          }, {
+            name: `test`,
+            script: `has-bin-entries success`,
+            inherited: true,
+          }]);
+        },
+      ),
+    );
+
+    test(`it shouldn't fall back to the scripts of the root workspace by default`,
+      makeTemporaryEnv(
+        {
+          workspaces: [`packages/*`],
+          scripts: {
+            [`where`]: `echo root`,
+          },
+        },
+        async ({path, run, source}) => {
+          await xfs.mkdirpPromise(ppath.join(path, `packages/child`));
+          await xfs.writeJsonPromise(ppath.join(path, `packages/child/package.json`), {
+            name: `child`,
+          });
+
+          await run(`install`);
+
+          await expect(run(`run`, `where`, {
+            cwd: ppath.join(path, `packages/child`),
+          })).rejects.toThrow();
+        },
+      ),
+    );
   });
 });
@@ -266,5 +266,55 @@ describe(`Commands`, () => {
         await expect(xfs.readFilePromise(lockfilePath, `utf8`)).resolves.toContain(`resolution: "no-deps@npm:1.0.0"`);
       }),
     );
+
+    test(
+      `it should list the unplugged packages along with their location`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+          [`@types/is-number`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+        await run(`unplug`, `no-deps`);
+
+        const {stdout} = await run(`unplug`, `--list`, `--json`);
+        const entries = stdout.trim().split(`\n`).map(line => JSON.parse(line));
+
+        expect(entries).toEqual([{
+          locator: `no-deps@npm:1.0.0`,
+          location: expect.stringContaining(`.yarn/ignore/unplugged/`),
+        }]);
+      }),
+    );
+
+    test(
+      `it shouldn't modify anything when using \`--dry-run\``,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        const {stdout} = await run(`unplug`, `no-deps`, `--dry-run`);
+        expect(stdout).toContain(`no-deps@npm:1.0.0`);
+
+        await expect(readManifest(path, {key: `dependenciesMeta`})).resolves.toBeUndefined();
+      }),
+    );
+
+    test(
+      `it should throw when no installed package matches the patterns`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await expect(run(`unplug`, `does-not-exist`)).rejects.toThrow(/No installed package matches/);
+      }),
+    );
   });
 });
@@ -1,4 +1,7 @@
 import {Filename, ppath, xfs} from '@yarnpkg/fslib';
+import {tests}                from 'pkg-tests-core';
+
+const {setPackageWhitelist} = tests;
 
 describe(`Commands`, () => {
   describe(`up`, () => {
@@ -112,6 +115,31 @@ describe(`Commands`, () => {
       }),
     );
 
+    test(
+      `it should keep the tag of dependencies declared through a tag`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `latest`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+        await run(`set`, `resolution`, `no-deps@npm:latest`, `npm:1.0.0`);
+
+        await run(`up`, `no-deps`);
+
+        await expect(xfs.readJsonPromise(ppath.join(path, Filename.manifest))).resolves.toMatchObject({
+          dependencies: {
+            [`no-deps`]: `latest`,
+          },
+        });
+
+        await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+          name: `no-deps`,
+          version: `2.0.0`,
+        });
+      }),
+    );
+
     test(
       `it should upgrade regular dependencies to the current project (fixed tag)`,
       makeTemporaryEnv({
@@ -152,6 +180,8 @@ describe(`Commands`, () => {
         dependencies: {
           [`no-deps-scripted`]: `1.0.0`,
         },
+      }, {
+        allowedBuildDependencies: [`no-deps-scripted`],
       }, async ({path, run, source}) => {
         const {stdout} = await run(`up`, `no-deps-scripted`, `--mode=skip-build`, {
           env: {
@@ -170,6 +200,8 @@ describe(`Commands`, () => {
         dependencies: {
           [`no-deps-scripted`]: `1.0.0`,
         },
+      }, {
+        allowedBuildDependencies: [`no-deps-scripted`],
       }, async ({path, run, source}) => {
         const {stdout} = await run(`up`, `no-deps-scripted`, `--recursive`, `--mode=skip-build`, {
           env: {
@@ -181,5 +213,65 @@ describe(`Commands`, () => {
         expect(stdout).not.toContain(`STDOUT preinstall out`);
       }),
     );
+    test(
+      `it should use defaultSemverRangePrefix when upgrading dependencies matching a glob pattern`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `^1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`config`, `set`, `defaultSemverRangePrefix`, `~`);
+
+        await run(`up`, `no-*`);
+
+        await expect(xfs.readJsonPromise(ppath.join(path, Filename.manifest))).resolves.toStrictEqual({
+          dependencies: {
+            [`no-deps`]: `~2.0.0`,
+          },
+        });
+      }),
+    );
+
+    test(
+      `it should fall back to the non-interactive behavior when using --interactive outside of a terminal`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `^1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`up`, `--interactive`, `no-*`);
+
+        await expect(xfs.readJsonPromise(ppath.join(path, Filename.manifest))).resolves.toStrictEqual({
+          dependencies: {
+            [`no-deps`]: `^2.0.0`,
+          },
+        });
+      }),
+    );
+
+    test(
+      `it should refresh the lockfile entries without touching the manifests when using --recursive`,
+      makeTemporaryEnv({}, async ({path, run, source}) => {
+        await setPackageWhitelist(new Map([[`no-deps`, new Set([`1.0.0`])]]), async () => {
+          await run(`add`, `one-range-dep`);
+        });
+
+        await run(`up`, `-R`, `no-deps`);
+
+        await expect(xfs.readJsonPromise(ppath.join(path, Filename.manifest))).resolves.toStrictEqual({
+          dependencies: {
+            [`one-range-dep`]: `^1.0.0`,
+          },
+        });
+
+        await expect(source(`require('one-range-dep')`)).resolves.toMatchObject({
+          dependencies: {
+            [`no-deps`]: {
+              version: `1.1.0`,
+            },
+          },
+        });
+      }),
+    );
   });
 });
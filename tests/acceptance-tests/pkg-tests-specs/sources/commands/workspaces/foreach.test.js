@@ -251,6 +251,46 @@ describe(`Commands`, () => {
     );
 
 
+    test(
+      `should refuse to run in topological order when workspaces form a cycle`,
+      makeTemporaryEnv(
+        {
+          private: true,
+          workspaces: [`packages/*`],
+        },
+        async ({path, run}) => {
+          await writeJson(`${path}/packages/workspace-a/package.json`, {
+            name: `workspace-a`,
+            version: `1.0.0`,
+            scripts: {
+              print: `echo Test Workspace A`,
+            },
+            dependencies: {
+              [`workspace-b`]: `workspace:*`,
+            },
+          });
+
+          await writeJson(`${path}/packages/workspace-b/package.json`, {
+            name: `workspace-b`,
+            version: `1.0.0`,
+            scripts: {
+              print: `echo Test Workspace B`,
+            },
+            devDependencies: {
+              [`workspace-a`]: `workspace:*`,
+            },
+          });
+
+          const {stdout} = await run(`install`);
+          expect(stdout).toMatch(/workspace-b → workspace-a \(through devDependencies\)/);
+
+          await expect(run(`workspaces`, `foreach`, `--all`, `--topological`, `run`, `print`)).rejects.toMatchObject({
+            stdout: expect.stringMatching(/workspace-a → workspace-b \(through dependencies\)/),
+          });
+        },
+      ),
+    );
+
     test(
       `should prefix the output when run with one --verbose`,
       makeTemporaryEnv(
@@ -31,18 +31,27 @@ describe(`Commands`, () => {
             [`.`]: {
               location: `.`,
               name: null,
+              path: `${path}`,
+              version: null,
+              private: true,
               workspaceDependencies: [],
               mismatchedWorkspaceDependencies: [],
             },
             [`packages/workspace-a`]: {
               location: `packages/workspace-a`,
               name: `workspace-a`,
+              path: `${path}/packages/workspace-a`,
+              version: `1.0.0`,
+              private: false,
               workspaceDependencies: [],
               mismatchedWorkspaceDependencies: [],
             },
             [`packages/workspace-b`]: {
               location: `packages/workspace-b`,
               name: `workspace-b`,
+              path: `${path}/packages/workspace-b`,
+              version: `1.0.0`,
+              private: false,
               workspaceDependencies: [],
               mismatchedWorkspaceDependencies: [],
             },
@@ -82,18 +91,27 @@ describe(`Commands`, () => {
             [`.`]: {
               location: `.`,
               name: null,
+              path: `${path}`,
+              version: null,
+              private: true,
               workspaceDependencies: [],
               mismatchedWorkspaceDependencies: [],
             },
             [`packages/workspace-a`]: {
               location: `packages/workspace-a`,
               name: `workspace-a`,
+              path: `${path}/packages/workspace-a`,
+              version: `1.0.0`,
+              private: false,
               workspaceDependencies: [`packages/workspace-b`],
               mismatchedWorkspaceDependencies: [],
             },
             [`packages/workspace-b`]: {
               location: `packages/workspace-b`,
               name: `workspace-b`,
+              path: `${path}/packages/workspace-b`,
+              version: `1.0.0`,
+              private: false,
               workspaceDependencies: [`packages/workspace-a`],
               mismatchedWorkspaceDependencies: [],
             },
@@ -130,18 +148,102 @@ describe(`Commands`, () => {
             [`.`]: {
               location: `.`,
               name: null,
+              path: `${path}`,
+              version: null,
+              private: true,
               workspaceDependencies: [],
               mismatchedWorkspaceDependencies: [],
             },
             [`packages/workspace-a`]: {
               location: `packages/workspace-a`,
               name: `workspace-a`,
+              path: `${path}/packages/workspace-a`,
+              version: `1.0.0`,
+              private: false,
               workspaceDependencies: [],
               mismatchedWorkspaceDependencies: [`workspace-b@2.0.0`],
             },
             [`packages/workspace-b`]: {
               location: `packages/workspace-b`,
               name: `workspace-b`,
+              path: `${path}/packages/workspace-b`,
+              version: `1.0.0`,
+              private: false,
+              workspaceDependencies: [],
+              mismatchedWorkspaceDependencies: [],
+            },
+          });
+        },
+      ),
+    );
+
+    test(
+      `workspace-a requires a workspace: range that workspace-b doesn't match`,
+      makeTemporaryEnv(
+        {
+          private: true,
+          workspaces: [`packages/*`],
+        },
+        async ({path, run}) => {
+          await writeJson(`${path}/packages/workspace-a/package.json`, {
+            name: `workspace-a`,
+            version: `1.0.0`,
+            dependencies: {
+              [`workspace-b`]: `workspace:^2.0.0`,
+              [`workspace-c`]: `workspace:^`,
+            },
+          });
+
+          await writeJson(`${path}/packages/workspace-b/package.json`, {
+            name: `workspace-b`,
+            version: `1.0.0`,
+          });
+
+          await writeJson(`${path}/packages/workspace-c/package.json`, {
+            name: `workspace-c`,
+            private: true,
+          });
+
+          // The project can't be installed, but the command should still work
+          await expect(run(`install`)).rejects.toThrow();
+
+          await expect(parseJsonStream(
+            (await run(`workspaces`, `list`, `-v`, `--json`)).stdout,
+            `location`,
+          )).toEqual({
+            [`.`]: {
+              location: `.`,
+              name: null,
+              path: `${path}`,
+              version: null,
+              private: true,
+              workspaceDependencies: [],
+              mismatchedWorkspaceDependencies: [],
+            },
+            [`packages/workspace-a`]: {
+              location: `packages/workspace-a`,
+              name: `workspace-a`,
+              path: `${path}/packages/workspace-a`,
+              version: `1.0.0`,
+              private: false,
+              workspaceDependencies: [`packages/workspace-c`],
+              mismatchedWorkspaceDependencies: [`workspace-b@workspace:^2.0.0`],
+            },
+            [`packages/workspace-b`]: {
+              location: `packages/workspace-b`,
+              name: `workspace-b`,
+              path: `${path}/packages/workspace-b`,
+              version: `1.0.0`,
+              private: false,
+              workspaceDependencies: [],
+              mismatchedWorkspaceDependencies: [],
+            },
+            [`packages/workspace-c`]: {
+              location: `packages/workspace-c`,
+              name: `workspace-c`,
+              path: `${path}/packages/workspace-c`,
+              version: null,
+              private: true,
               workspaceDependencies: [],
               mismatchedWorkspaceDependencies: [],
             },
@@ -185,6 +287,9 @@ describe(`Commands`, () => {
           [`packages/workspace-b`]: {
             location: `packages/workspace-b`,
             name: `workspace-b`,
+            path: `${path}/packages/workspace-b`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [],
             mismatchedWorkspaceDependencies: [],
           },
@@ -204,6 +309,9 @@ describe(`Commands`, () => {
           [`packages/workspace-a`]: {
             location: `packages/workspace-a`,
             name: `workspace-a`,
+            path: `${path}/packages/workspace-a`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [],
             mismatchedWorkspaceDependencies: [],
           },
@@ -251,12 +359,18 @@ describe(`Commands`, () => {
           [`packages/workspace-b`]: {
             location: `packages/workspace-b`,
             name: `workspace-b`,
+            path: `${path}/packages/workspace-b`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [],
             mismatchedWorkspaceDependencies: [],
           },
           [`packages/workspace-c`]: {
             location: `packages/workspace-c`,
             name: `workspace-c`,
+            path: `${path}/packages/workspace-c`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [],
             mismatchedWorkspaceDependencies: [],
           },
@@ -283,6 +397,9 @@ describe(`Commands`, () => {
           [`packages/workspace-b`]: {
             location: `packages/workspace-b`,
             name: `workspace-b`,
+            path: `${path}/packages/workspace-b`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [
               `packages/workspace-a`,
               `packages/workspace-c`,
@@ -292,6 +409,9 @@ describe(`Commands`, () => {
           [`packages/workspace-c`]: {
             location: `packages/workspace-c`,
             name: `workspace-c`,
+            path: `${path}/packages/workspace-c`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [
               `packages/workspace-a`,
             ],
@@ -313,12 +433,18 @@ describe(`Commands`, () => {
           [`packages/workspace-a`]: {
             location: `packages/workspace-a`,
             name: `workspace-a`,
+            path: `${path}/packages/workspace-a`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [],
             mismatchedWorkspaceDependencies: [],
           },
           [`packages/workspace-b`]: {
             location: `packages/workspace-b`,
             name: `workspace-b`,
+            path: `${path}/packages/workspace-b`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [
               `packages/workspace-a`,
               `packages/workspace-c`,
@@ -328,6 +454,9 @@ describe(`Commands`, () => {
           [`packages/workspace-c/packages/workspace-d`]: {
             location: `packages/workspace-c/packages/workspace-d`,
             name: `workspace-d`,
+            path: `${path}/packages/workspace-c/packages/workspace-d`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [
               `packages/workspace-b`,
             ],
@@ -336,6 +465,9 @@ describe(`Commands`, () => {
           [`packages/workspace-c/packages/workspace-d/packages/workspace-e`]: {
             location: `packages/workspace-c/packages/workspace-d/packages/workspace-e`,
             name: `workspace-e`,
+            path: `${path}/packages/workspace-c/packages/workspace-d/packages/workspace-e`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [
               `packages/workspace-c/packages/workspace-d`,
             ],
@@ -344,6 +476,9 @@ describe(`Commands`, () => {
           [`packages/workspace-c/packages/workspace-f`]: {
             location: `packages/workspace-c/packages/workspace-f`,
             name: `workspace-f`,
+            path: `${path}/packages/workspace-c/packages/workspace-f`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [
               `packages/workspace-c/packages/workspace-d/packages/workspace-e`,
             ],
@@ -352,6 +487,9 @@ describe(`Commands`, () => {
           [`packages/workspace-c`]: {
             location: `packages/workspace-c`,
             name: `workspace-c`,
+            path: `${path}/packages/workspace-c`,
+            version: `1.0.0`,
+            private: false,
             workspaceDependencies: [
               `packages/workspace-a`,
             ],
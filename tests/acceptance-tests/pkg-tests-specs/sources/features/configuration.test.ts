@@ -133,5 +133,30 @@ describe(`Features`, () => {
       // https://github.com/yarnpkg/berry/pull/5213
       await expect(run(`config`, `get`, `--json`, `packageExtensions`)).rejects.toMatchObject({stdout: expect.stringContaining(`Internal Error: Expected configuration setting "packageExtensions['@lezer/html@*'].dependencies['@lezer/javascript@*']" to be a string, got object`)});
     }));
+
+    test(`it should report the file, line, and column of invalid settings`, makeTemporaryEnv({
+    }, async ({path, run, source}) => {
+      await xfs.writeFilePromise(ppath.join(path, Filename.rc), `enableTelemetry: false\nnodeLinker: [pnp]\n`);
+
+      await expect(run(`install`)).rejects.toMatchObject({
+        stdout: expect.stringContaining(`${ppath.join(path, Filename.rc)}:2:13`),
+      });
+
+      await expect(run(`install`)).rejects.toMatchObject({
+        stdout: expect.stringContaining(`2 | nodeLinker: [pnp]\n                ^`),
+      });
+    }));
+
+    test(`it should report errors from both the home and project configurations`, makeTemporaryEnv({
+    }, async ({path, run, source}) => {
+      await xfs.mkdirPromise(ppath.join(path, `..`), {recursive: true});
+
+      await xfs.writeFilePromise(ppath.join(path, `..`, Filename.rc), `preferInteractive: [true]\n`);
+      await xfs.writeFilePromise(ppath.join(path, Filename.rc), `nodeLinker: [pnp]\n`);
+
+      await expect(run(`install`)).rejects.toMatchObject({
+        stdout: expect.stringMatching(new RegExp(`${ppath.join(path, `..`, Filename.rc)}:1:\\d+[\\s\\S]*${ppath.join(path, Filename.rc)}:1:\\d+`)),
+      });
+    }));
   });
 });
@@ -0,0 +1,42 @@
+describe(`Features`, () => {
+  describe(`engines`, () => {
+    test(
+      `it should warn when the running Node version doesn't satisfy engines.node`,
+      makeTemporaryEnv({
+        engines: {
+          node: `>=1000.0.0`,
+        },
+      }, async ({run}) => {
+        const {stdout} = await run(`install`);
+
+        expect(stdout).toContain(`doesn't satisfy the engines.node range required by the project (>=1000.0.0)`);
+      }),
+    );
+
+    test(
+      `it shouldn't warn when the running Node version satisfies engines.node`,
+      makeTemporaryEnv({
+        engines: {
+          node: `>=1.0.0`,
+        },
+      }, async ({run}) => {
+        const {stdout} = await run(`install`);
+
+        expect(stdout).not.toContain(`engines.node`);
+      }),
+    );
+
+    test(
+      `it should fail the install on mismatch when engineStrict is set`,
+      makeTemporaryEnv({
+        engines: {
+          node: `>=1000.0.0`,
+        },
+      }, {
+        engineStrict: true,
+      }, async ({run}) => {
+        await expect(run(`install`)).rejects.toThrow(/doesn't satisfy the range required by the project \(>=1000\.0\.0\)/);
+      }),
+    );
+  });
+});
@@ -0,0 +1,74 @@
+import {xfs, PortablePath} from '@yarnpkg/fslib';
+
+describe(`Features`, () => {
+  describe(`httpHeaders`, () => {
+    test(
+      `it should install packages when custom headers are configured`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`no-deps`]: `1.0.0`,
+          },
+        },
+        async ({path, run, source}) => {
+          await xfs.writeJsonPromise(`${path}/.yarnrc.yml` as PortablePath, {
+            httpHeaders: {
+              [`X-Org-Team`]: `platform`,
+            },
+          });
+
+          await run(`install`);
+
+          await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+            name: `no-deps`,
+            version: `1.0.0`,
+          });
+        },
+      ),
+    );
+
+    test(
+      `it should refuse to override authentication headers`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`no-deps`]: `1.0.0`,
+          },
+        },
+        async ({path, run}) => {
+          await xfs.writeJsonPromise(`${path}/.yarnrc.yml` as PortablePath, {
+            httpHeaders: {
+              [`Authorization`]: `Bearer foo`,
+            },
+          });
+
+          await expect(run(`install`)).rejects.toThrow(`The Authorization header can't be configured through httpHeaders`);
+        },
+      ),
+    );
+
+    test(
+      `it should refuse to override hop-by-hop headers on specific registries`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`no-deps`]: `1.0.0`,
+          },
+        },
+        async ({path, run}) => {
+          await xfs.writeJsonPromise(`${path}/.yarnrc.yml` as PortablePath, {
+            npmRegistries: {
+              [`https://registry.example.org`]: {
+                httpHeaders: {
+                  [`Connection`]: `close`,
+                },
+              },
+            },
+          });
+
+          await expect(run(`install`)).rejects.toThrow(`The Connection header can't be configured through httpHeaders`);
+        },
+      ),
+    );
+  });
+});
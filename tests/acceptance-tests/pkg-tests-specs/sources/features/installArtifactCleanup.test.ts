@@ -74,6 +74,7 @@ describe(`Install Artifact Cleanup`, () => {
             },
           }, {
             pnpEnableEsmLoader: true,
+            allowedBuildDependencies: [`no-deps-scripted`],
           }, async ({path, run, source}) => {
             await run(`install`);
 
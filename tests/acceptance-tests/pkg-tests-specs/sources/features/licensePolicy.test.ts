@@ -0,0 +1,87 @@
+describe(`Features`, () => {
+  describe(`licensePolicy`, () => {
+    test(
+      `it should warn about production dependencies using a denied license`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`depends-on-gpl-license`]: `1.0.0`,
+        },
+      }, {
+        licensePolicy: {
+          deny: [`GPL-*`],
+        },
+      }, async ({run}) => {
+        const {stdout} = await run(`install`);
+
+        expect(stdout).toContain(`uses a license denied by the policy (GPL-3.0-only)`);
+        expect(stdout).toMatch(/depends-on-gpl-license@npm:1\.0\.0 → gpl-license@npm:1\.0\.0/);
+      }),
+    );
+
+    test(
+      `it should fail the install when the policy is enforced`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`gpl-license`]: `1.0.0`,
+        },
+      }, {
+        licensePolicy: {
+          deny: [`GPL-*`],
+          enforce: true,
+        },
+      }, async ({run}) => {
+        await expect(run(`install`)).rejects.toThrow(/uses a license denied by the policy \(GPL-3\.0-only\)/);
+      }),
+    );
+
+    test(
+      `it should only report packages whose licenses aren't part of the allow list`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`dual-license`]: `1.0.0`,
+          [`gpl-license`]: `1.0.0`,
+        },
+      }, {
+        licensePolicy: {
+          allow: [`MIT`],
+          enforce: true,
+        },
+      }, async ({run}) => {
+        await expect(run(`install`)).rejects.toThrow(/gpl-license@npm:1\.0\.0 uses a license denied by the policy/);
+        await expect(run(`install`)).rejects.not.toThrow(/dual-license@npm:1\.0\.0/);
+      }),
+    );
+
+    test(
+      `it shouldn't check the licenses of dev dependencies`,
+      makeTemporaryEnv({
+        devDependencies: {
+          [`gpl-license`]: `1.0.0`,
+        },
+      }, {
+        licensePolicy: {
+          deny: [`GPL-*`],
+          enforce: true,
+        },
+      }, async ({run}) => {
+        await expect(run(`install`)).resolves.toMatchObject({code: 0});
+      }),
+    );
+
+    test(
+      `it should report packages without a license according to unknownLicenseSeverity`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      }, {
+        licensePolicy: {
+          deny: [`GPL-*`],
+          unknownLicenseSeverity: `error`,
+        },
+      }, async ({run}) => {
+        await expect(run(`install`)).rejects.toThrow(/no-deps@npm:1\.0\.0 doesn't declare a valid license \(none\)/);
+      }),
+    );
+  });
+});
@@ -99,17 +99,64 @@ describe(`Features`, () => {
 
             await run(`install`);
 
-            const expectedLockfile = tests.FEATURE_CHECKS.mergeConflictTheirs
-              ? expectedV1Lockfile
-              : expectedV2Lockfile;
-
+            // Both sides are kept, and the highest version wins regardless of
+            // which branch got merged last
             const postFixLockfile = await xfs.readFilePromise(ppath.join(path, Filename.lockfile), `utf8`);
-            expect(postFixLockfile).toEqual(expectedLockfile);
+            expect(postFixLockfile).toEqual(expectedV2Lockfile);
           }
         },
       ),
     );
 
+    test(
+      `it should keep the packages added on both sides of a merge conflict`,
+      makeTemporaryEnv(
+        {},
+        async ({path, run, source}) => {
+          await exec.execGitInit({cwd: path});
+
+          await run(`install`);
+
+          await exec.execFile(`git`, [`add`, `-A`], {cwd: path});
+          await exec.execFile(`git`, [`commit`, `-a`, `-m`, `my-commit`], {cwd: path});
+
+          await exec.execFile(`git`, [`checkout`, `-b`, `no-deps`], {cwd: path});
+          await run(`add`, `no-deps@1.0.0`);
+          await exec.execFile(`git`, [`add`, `-A`], {cwd: path});
+          await exec.execFile(`git`, [`commit`, `-a`, `-m`, `commit-no-deps`], {cwd: path});
+
+          await exec.execFile(`git`, [`checkout`, `master`], {cwd: path});
+          await exec.execFile(`git`, [`checkout`, `-b`, `scoped-no-deps`], {cwd: path});
+          await run(`add`, `@scoped/no-deps@1.0.0`);
+          await exec.execFile(`git`, [`add`, `-A`], {cwd: path});
+          await exec.execFile(`git`, [`commit`, `-a`, `-m`, `commit-scoped-no-deps`], {cwd: path});
+
+          await expect(exec.execFile(`git`, [`merge`, `no-deps`], {cwd: path, env: {LC_ALL: `C`}})).rejects.toThrow(/CONFLICT/);
+
+          // The manifest conflict is the user's to resolve; the lockfile one isn't
+          await xfs.writeJsonPromise(ppath.join(path, Filename.manifest), {
+            dependencies: {
+              [`@scoped/no-deps`]: `1.0.0`,
+              [`no-deps`]: `1.0.0`,
+            },
+          });
+
+          const preFixLockfile = await xfs.readFilePromise(ppath.join(path, Filename.lockfile), `utf8`);
+          expect(preFixLockfile).toContain(`<<<<<<<`);
+
+          await run(`install`);
+
+          const postFixLockfile = await xfs.readFilePromise(ppath.join(path, Filename.lockfile), `utf8`);
+          expect(postFixLockfile).not.toMatch(/^(<<<<<<<|=======|>>>>>>>)/m);
+          expect(postFixLockfile).toContain(`no-deps@npm:1.0.0`);
+          expect(postFixLockfile).toContain(`@scoped/no-deps@npm:1.0.0`);
+
+          await expect(source(`require('no-deps')`)).resolves.toMatchObject({version: `1.0.0`});
+          await expect(source(`require('@scoped/no-deps')`)).resolves.toMatchObject({version: `1.0.0`});
+        },
+      ),
+    );
+
     test(
       `it should properly fix merge conflicts when old is Yarn 1 and new is Yarn 2`,
       makeTemporaryEnv(
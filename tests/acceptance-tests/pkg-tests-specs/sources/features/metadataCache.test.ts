@@ -0,0 +1,62 @@
+import {Filename, ppath, xfs} from '@yarnpkg/fslib';
+import {tests}                from 'pkg-tests-core';
+
+describe(`Features`, () => {
+  describe(`Metadata cache`, () => {
+    test(
+      `it should revalidate the cached packuments rather than download them again`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `^1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await xfs.removePromise(ppath.join(path, Filename.lockfile));
+
+        await expect(run(`install`)).resolves.toMatchObject({
+          stdout: expect.stringContaining(`Reused 1 cached metadata entries after revalidating them`),
+        });
+
+        await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+          name: `no-deps`,
+          version: `1.1.0`,
+        });
+      }),
+    );
+
+    test(
+      `it should bypass the metadata cache when using --refresh-lockfile`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`no-deps`]: `^1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await run(`install`);
+
+        await expect(run(`install`, `--refresh-lockfile`)).resolves.toMatchObject({
+          stdout: expect.not.stringContaining(`cached metadata entries`),
+        });
+      }),
+    );
+
+    test(
+      `it shouldn't cache the packuments retrieved with credentials`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`@private/package`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        await xfs.writeFilePromise(ppath.join(path, Filename.rc), `npmAuthToken: "${tests.validLogins.fooUser.npmAuthToken}"\n`);
+
+        await run(`install`);
+
+        await xfs.removePromise(ppath.join(path, Filename.lockfile));
+
+        await expect(run(`install`)).resolves.toMatchObject({
+          stdout: expect.not.stringContaining(`cached metadata entries`),
+        });
+      }),
+    );
+  });
+});
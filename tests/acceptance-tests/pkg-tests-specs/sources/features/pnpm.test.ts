@@ -269,5 +269,51 @@ describe(`Features`, () => {
         },
       ),
     );
+
+    test(
+      `it should hardlink identical files from the content-addressable store`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`no-deps`]: `1.0.0`,
+            [`no-deps-bins`]: `1.0.0`,
+          },
+        },
+        {
+          nodeLinker: `pnpm`,
+        },
+        async ({path, run, source}) => {
+          await run(`install`);
+
+          const noDepsStat = await xfs.statPromise(ppath.join(path, `node_modules/no-deps/index.js`));
+          const noDepsBinsStat = await xfs.statPromise(ppath.join(path, `node_modules/no-deps-bins/index.js`));
+
+          expect(noDepsStat.ino).toEqual(noDepsBinsStat.ino);
+          expect(noDepsStat.nlink).toBeGreaterThanOrEqual(3);
+        },
+      ),
+    );
+
+    test(
+      `it should copy the files of packages with build scripts rather than hardlinking them`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`no-deps-scripted`]: `1.0.0`,
+          },
+        },
+        {
+          nodeLinker: `pnpm`,
+          allowedBuildDependencies: [`no-deps-scripted`],
+        },
+        async ({path, run, source}) => {
+          await run(`install`);
+
+          const indexStat = await xfs.statPromise(ppath.join(path, `node_modules/no-deps-scripted/index.js`));
+
+          expect(indexStat.nlink).toEqual(1);
+        },
+      ),
+    );
   });
 });
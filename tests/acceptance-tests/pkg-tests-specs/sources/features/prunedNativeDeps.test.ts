@@ -370,5 +370,118 @@ describe(`Features`, () => {
         version: `1.0.0`,
       }]);
     }));
+    it(`should skip packages excluded from the current platform when linking with the node-modules linker`, makeTemporaryEnv({
+      optionalDependencies: {
+        [`native-darwin-bin`]: `1.0.0`,
+      },
+    }, async ({path, run, source}) => {
+      await xfs.writeJsonPromise(ppath.join(path, Filename.rc), {
+        nodeLinker: `node-modules`,
+        supportedArchitectures: {
+          os: [`linux`],
+        },
+      });
+
+      await run(`install`);
+
+      expect(xfs.existsSync(ppath.join(path, `node_modules/native-darwin-bin`))).toEqual(false);
+      expect(xfs.existsSync(ppath.join(path, `node_modules/.bin/native-darwin-bin`))).toEqual(false);
+    }));
+
+    it(`should report packages excluded from the current platform as not fetched`, makeTemporaryEnv({
+      optionalDependencies: {
+        [`native-darwin-bin`]: `1.0.0`,
+      },
+    }, async ({path, run, source}) => {
+      await xfs.writeJsonPromise(ppath.join(path, Filename.rc), {
+        supportedArchitectures: {
+          os: [`linux`],
+        },
+      });
+
+      await run(`install`);
+
+      await expect(run(`why`, `native-darwin-bin`)).resolves.toMatchObject({
+        stdout: expect.stringContaining(`not fetched (platform excluded)`),
+      });
+
+      await expect(run(`info`, `native-darwin-bin`)).resolves.toMatchObject({
+        stdout: expect.stringContaining(`not fetched (platform excluded)`),
+      });
+    }));
+
+    it(`should report a targeted error when running a binary provided by a package excluded from the current platform`, makeTemporaryEnv({
+      optionalDependencies: {
+        [`native-darwin-bin`]: `1.0.0`,
+      },
+    }, async ({path, run, source}) => {
+      await xfs.writeJsonPromise(ppath.join(path, Filename.rc), {
+        supportedArchitectures: {
+          os: [`linux`],
+        },
+      });
+
+      await run(`install`);
+
+      await expect(run(`run`, `native-darwin-bin`)).rejects.toThrow(/doesn't support the current platform \(native-darwin-bin\)/);
+    }));
+
+    it(`shouldn't blame excluded packages for binaries they don't provide`, makeTemporaryEnv({
+      optionalDependencies: {
+        [`native-darwin-bin`]: `1.0.0`,
+      },
+    }, async ({path, run, source}) => {
+      await xfs.writeJsonPromise(ppath.join(path, Filename.rc), {
+        supportedArchitectures: {
+          os: [`linux`],
+        },
+      });
+
+      await run(`install`);
+
+      await expect(run(`run`, `some-unknown-binary`)).rejects.not.toThrow(/doesn't support the current platform/);
+    }));
+
+    it(`should report the packages skipped because of their platform requirements`, makeTemporaryEnv({
+      optionalDependencies: {
+        [`native-darwin-bin`]: `1.0.0`,
+      },
+    }, async ({path, run, source}) => {
+      await xfs.writeJsonPromise(ppath.join(path, Filename.rc), {
+        supportedArchitectures: {
+          os: [`linux`],
+        },
+      });
+
+      await expect(run(`install`)).resolves.toMatchObject({
+        stdout: expect.stringMatching(/native-darwin-bin@npm:1\.0\.0 isn't compatible with .*linux.* and has been skipped/),
+      });
+    }));
+
+    for (const os of [`foo`, `bar`]) {
+      it(`should support excluding platforms with the ! prefix (${os})`, makeTemporaryEnv({
+        optionalDependencies: {
+          [`native-not-foo`]: `1.0.0`,
+        },
+      }, async ({path, run, source}) => {
+        const recording = await startRegistryRecording(async () => {
+          await run(`install`, {
+            env: {
+              YARN_OS_OVERRIDE: os,
+            },
+          });
+        });
+
+        const tarballRequests = recording.filter(request => {
+          return request.type === RequestType.PackageTarball;
+        });
+
+        expect(tarballRequests).toEqual(os === `foo` ? [] : [{
+          type: RequestType.PackageTarball,
+          localName: `native-not-foo`,
+          version: `1.0.0`,
+        }]);
+      }));
+    }
   });
 });
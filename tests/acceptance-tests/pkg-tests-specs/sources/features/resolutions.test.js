@@ -75,6 +75,138 @@ describe(`Features`, () => {
       ),
     );
 
+    test(
+      `it should let an override scoped to a parent package take precedence over a global one`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`one-fixed-dep`]: `1.0.0`,
+            [`one-range-dep`]: `1.0.0`,
+          },
+          resolutions: {
+            [`no-deps`]: `2.0.0`,
+            [`one-range-dep/no-deps`]: `1.1.0`,
+          },
+        },
+        async ({path, run, source}) => {
+          await run(`install`);
+
+          await expect(source(`require('one-fixed-dep')`)).resolves.toMatchObject({
+            dependencies: {
+              [`no-deps`]: {
+                name: `no-deps`,
+                version: `2.0.0`,
+              },
+            },
+          });
+
+          await expect(source(`require('one-range-dep')`)).resolves.toMatchObject({
+            dependencies: {
+              [`no-deps`]: {
+                name: `no-deps`,
+                version: `1.1.0`,
+              },
+            },
+          });
+        },
+      ),
+    );
+
+    test(
+      `it should only apply overrides scoped to a nested dependency path along that path`,
+      makeTemporaryEnv(
+        {
+          workspaces: [`my-workspace`],
+          dependencies: {
+            [`one-fixed-dep`]: `1.0.0`,
+            [`my-workspace`]: `workspace:*`,
+          },
+          resolutions: {
+            [`no-deps`]: `2.0.0`,
+            [`my-workspace/one-fixed-dep/no-deps`]: `1.1.0`,
+          },
+        },
+        async ({path, run, source}) => {
+          await writeFile(`${path}/my-workspace/index.js`, `module.exports = require('one-fixed-dep');\n`);
+          await writeJson(`${path}/my-workspace/package.json`, {
+            name: `my-workspace`,
+            dependencies: {
+              [`one-fixed-dep`]: `1.0.0`,
+            },
+          });
+
+          await run(`install`);
+
+          await expect(source(`require('one-fixed-dep')`)).resolves.toMatchObject({
+            name: `one-fixed-dep`,
+            version: `1.0.0`,
+            dependencies: {
+              [`no-deps`]: {
+                name: `no-deps`,
+                version: `2.0.0`,
+              },
+            },
+          });
+
+          await expect(source(`require('my-workspace')`)).resolves.toMatchObject({
+            name: `one-fixed-dep`,
+            version: `1.0.0`,
+            dependencies: {
+              [`no-deps`]: {
+                name: `no-deps`,
+                version: `1.1.0`,
+              },
+            },
+          });
+        },
+      ),
+    );
+
+    test(
+      `it should support overrides scoped to a nested dependency path through scoped packages`,
+      makeTemporaryEnv(
+        {
+          workspaces: [`my-workspace`],
+          dependencies: {
+            [`one-fixed-dep`]: `1.0.0`,
+            [`@scoped/my-workspace`]: `workspace:*`,
+          },
+          resolutions: {
+            [`@scoped/my-workspace/one-fixed-dep/no-deps`]: `2.0.0`,
+          },
+        },
+        async ({path, run, source}) => {
+          await writeFile(`${path}/my-workspace/index.js`, `module.exports = require('one-fixed-dep');\n`);
+          await writeJson(`${path}/my-workspace/package.json`, {
+            name: `@scoped/my-workspace`,
+            dependencies: {
+              [`one-fixed-dep`]: `1.0.0`,
+            },
+          });
+
+          await run(`install`);
+
+          await expect(source(`require('one-fixed-dep')`)).resolves.toMatchObject({
+            dependencies: {
+              [`no-deps`]: {
+                name: `no-deps`,
+                version: `1.0.0`,
+              },
+            },
+          });
+
+          await expect(source(`require('@scoped/my-workspace')`)).resolves.toMatchObject({
+            dependencies: {
+              [`no-deps`]: {
+                name: `no-deps`,
+                version: `2.0.0`,
+              },
+            },
+          });
+        },
+      ),
+    );
+
     test(
       `it should support overriding a packages with another, but only if it originally resolved to a specific reference`,
       makeTemporaryEnv(
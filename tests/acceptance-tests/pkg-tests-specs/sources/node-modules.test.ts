@@ -106,6 +106,7 @@ describe(`Node_Modules`, () => {
       },
       {
         nodeLinker: `node-modules`,
+        allowedBuildDependencies: [`pkg`],
       },
       async ({path, run, source}) => {
         await writeJson(npath.toPortablePath(`${path}/pkg/package.json`), {
@@ -601,6 +602,58 @@ describe(`Node_Modules`, () => {
     ),
   );
 
+  test(`should lay out one copy of a package per set of peer dependencies provided by the workspaces`,
+    // . -> workspace-a -> peer-deps --> no-deps
+    //                  -> no-deps@1
+    //   -> workspace-b -> peer-deps --> no-deps
+    //                  -> no-deps@2
+    makeTemporaryEnv(
+      {
+        private: true,
+        workspaces: [`packages/*`],
+        dependencies: {
+          [`workspace-a`]: `workspace:*`,
+          [`workspace-b`]: `workspace:*`,
+        },
+      },
+      {
+        nodeLinker: `node-modules`,
+      },
+      async ({path, run, source}) => {
+        for (const [name, version] of [[`workspace-a`, `1.0.0`], [`workspace-b`, `2.0.0`]]) {
+          await writeJson(npath.toPortablePath(`${path}/packages/${name}/package.json`), {
+            name,
+            version: `1.0.0`,
+            dependencies: {
+              [`peer-deps`]: `1.0.0`,
+              [`no-deps`]: version,
+            },
+          });
+
+          await writeFile(npath.toPortablePath(`${path}/packages/${name}/index.js`), `module.exports = require('peer-deps');`);
+        }
+
+        await run(`install`);
+
+        await expect(source(`require('workspace-a')`)).resolves.toMatchObject({
+          peerDependencies: {
+            [`no-deps`]: {
+              version: `1.0.0`,
+            },
+          },
+        });
+
+        await expect(source(`require('workspace-b')`)).resolves.toMatchObject({
+          peerDependencies: {
+            [`no-deps`]: {
+              version: `2.0.0`,
+            },
+          },
+        });
+      },
+    ),
+  );
+
 
   test(`should not hoist a single package past workspace hoist border`,
     // . -> workspace -> dep
@@ -738,6 +791,32 @@ describe(`Node_Modules`, () => {
     ),
   );
 
+  test(`should create self-reference symlinks for workspaces depending on themselves`,
+    makeTemporaryEnv(
+      {
+        workspaces: [`ws`],
+      },
+      {
+        nodeLinker: `node-modules`,
+      },
+      async ({path, run, source}) => {
+        await writeJson(npath.toPortablePath(`${path}/ws/package.json`), {
+          name: `ws`,
+          dependencies: {
+            ws: `workspace:*`,
+          },
+        });
+
+        await xfs.writeFilePromise(`${path}/ws/utils.js` as PortablePath, `module.exports = 42;\n`);
+
+        await run(`install`);
+
+        expect(await xfs.realpathPromise(`${path}/ws/node_modules/ws` as PortablePath)).toEqual(await xfs.realpathPromise(`${path}/ws` as PortablePath));
+        await expect(source(`require('./ws/node_modules/ws/utils.js')`)).resolves.toEqual(42);
+      },
+    ),
+  );
+
   test(`should not hoist multiple packages past workspace hoist border`,
     // . -> workspace -> dep1 -> dep2
     // should be hoisted to:
@@ -783,6 +862,71 @@ describe(`Node_Modules`, () => {
     ),
   );
 
+  test(`should give each workspace its own copy of a shared package when hoisting is limited to workspaces`,
+    // . -> ws1 -> no-deps
+    //   -> ws2 -> no-deps
+    // should not be hoisted to the top
+    makeTemporaryEnv(
+      {
+        private: true,
+        workspaces: [`ws1`, `ws2`],
+      },
+      {
+        nodeLinker: `node-modules`,
+        nmHoistingLimits: `workspaces`,
+      },
+      async ({path, run, source}) => {
+        for (const name of [`ws1`, `ws2`]) {
+          await writeJson(npath.toPortablePath(`${path}/${name}/package.json`), {
+            name,
+            version: `1.0.0`,
+            dependencies: {
+              [`no-deps`]: `1.0.0`,
+            },
+          });
+        }
+
+        await run(`install`);
+
+        expect(await xfs.existsPromise(`${path}/node_modules/no-deps` as PortablePath)).toEqual(false);
+        expect(await xfs.existsPromise(`${path}/ws1/node_modules/no-deps` as PortablePath)).toEqual(true);
+        expect(await xfs.existsPromise(`${path}/ws2/node_modules/no-deps` as PortablePath)).toEqual(true);
+      },
+    ),
+  );
+
+  test(`should keep transitive dependencies within the direct dependencies of workspaces when hoisting is limited to dependencies`,
+    // . -> one-fixed-dep -> no-deps
+    // should not be hoisted to:
+    // . -> one-fixed-dep
+    //   -> no-deps
+    makeTemporaryEnv(
+      {
+        dependencies: {
+          [`one-fixed-dep`]: `1.0.0`,
+        },
+      },
+      {
+        nodeLinker: `node-modules`,
+        nmHoistingLimits: `dependencies`,
+      },
+      async ({path, run, source}) => {
+        await run(`install`);
+
+        expect(await xfs.existsPromise(`${path}/node_modules/no-deps` as PortablePath)).toEqual(false);
+        expect(await xfs.existsPromise(`${path}/node_modules/one-fixed-dep/node_modules/no-deps` as PortablePath)).toEqual(true);
+
+        await expect(source(`require('one-fixed-dep')`)).resolves.toMatchObject({
+          dependencies: {
+            [`no-deps`]: {
+              version: `1.0.0`,
+            },
+          },
+        });
+      },
+    ),
+  );
+
   test(`should support dependencies hoist border`,
     // . -> workspace -> dep1 -> dep2 -> dep3
     // should be hoised to:
@@ -1103,6 +1247,46 @@ describe(`Node_Modules`, () => {
       }),
   );
 
+  test(`should link portals targeting a workspace of another project without modifying it`,
+    makeTemporaryEnv({},
+      {
+        nodeLinker: `node-modules`,
+      },
+      async ({path, run, source}) => {
+        await xfs.mktempPromise(async otherProject => {
+          await xfs.writeJsonPromise(`${otherProject}/package.json` as PortablePath, {
+            workspaces: [`pkg`],
+          });
+
+          await xfs.mkdirPromise(`${otherProject}/pkg` as PortablePath);
+          await xfs.writeJsonPromise(`${otherProject}/pkg/package.json` as PortablePath, {
+            name: `pkg`,
+            dependencies: {
+              [`no-deps`]: `1.0.0`,
+            },
+          });
+
+          await xfs.writeFilePromise(`${otherProject}/pkg/index.js` as PortablePath, `module.exports = require('no-deps/package.json').version;\n`);
+
+          await xfs.writeJsonPromise(`${path}/package.json` as PortablePath, {
+            dependencies: {
+              [`pkg`]: `portal:${otherProject}/pkg`,
+            },
+          });
+
+          await run(`install`);
+
+          expect(await xfs.realpathPromise(`${path}/node_modules/pkg` as PortablePath)).toEqual(await xfs.realpathPromise(`${otherProject}/pkg` as PortablePath));
+          await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+            version: `1.0.0`,
+          });
+
+          expect(await xfs.existsPromise(`${otherProject}/pkg/node_modules` as PortablePath)).toBeFalsy();
+          expect(await xfs.existsPromise(`${otherProject}/node_modules` as PortablePath)).toBeFalsy();
+        });
+      }),
+  );
+
   test(`should still hoist direct dependencies from portal target to parent with nmHoistingLimits: dependencies`,
     makeTemporaryEnv({},
       {
@@ -1838,6 +2022,7 @@ describe(`Node_Modules`, () => {
       },
       {
         nodeLinker: `node-modules`,
+        allowedBuildDependencies: [`no-deps-scripted`, `one-dep-scripted`],
       },
       async ({path, run, source}) => {
         await run(`install`);
@@ -2151,6 +2336,40 @@ describe(`Node_Modules`, () => {
     ),
   );
 
+  test(
+    `should materialize packages as regular files when using nmMode: copy-reflink`,
+    makeTemporaryEnv(
+      {
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      },
+      {
+        nodeLinker: `node-modules`,
+        nmMode: `copy-reflink`,
+      },
+      async ({path, run, source}) => {
+        await run(`install`);
+
+        const manifestPath = ppath.join(path, `node_modules/no-deps/package.json`);
+        expect((await xfs.lstatPromise(manifestPath)).isFile()).toBeTruthy();
+
+        await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+          name: `no-deps`,
+          version: `1.0.0`,
+        });
+
+        await xfs.removePromise(manifestPath);
+        await run(`install`);
+
+        await expect(source(`require('no-deps/package.json')`)).resolves.toMatchObject({
+          name: `no-deps`,
+          version: `1.0.0`,
+        });
+      },
+    ),
+  );
+
   testIf(() => process.platform !== `win32`,
     `'winLinkType: symlinks' not-on Windows should use symlinks in node_modules directories`,
     makeTemporaryEnv(
@@ -2176,4 +2395,144 @@ describe(`Node_Modules`, () => {
       },
     ),
   );
+
+  test(`should leave the packages that didn't change since the last install untouched`,
+    makeTemporaryEnv(
+      {
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      },
+      {
+        nodeLinker: `node-modules`,
+      },
+      async ({path, run, source}) => {
+        await run(`install`);
+
+        await xfs.writeFilePromise(`${path}/node_modules/no-deps/marker.txt` as PortablePath, ``);
+
+        await run(`install`);
+        expect(await xfs.existsPromise(`${path}/node_modules/no-deps/marker.txt` as PortablePath)).toEqual(true);
+
+        await xfs.writeJsonPromise(`${path}/package.json` as PortablePath, {
+          dependencies: {
+            [`no-deps`]: `2.0.0`,
+          },
+        });
+
+        await run(`install`);
+        expect(await xfs.existsPromise(`${path}/node_modules/no-deps/marker.txt` as PortablePath)).toEqual(false);
+
+        await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+          version: `2.0.0`,
+        });
+      },
+    ),
+  );
+
+  test(`should fully re-link the node_modules folder when its state file is corrupted`,
+    makeTemporaryEnv(
+      {
+        dependencies: {
+          [`no-deps`]: `1.0.0`,
+        },
+      },
+      {
+        nodeLinker: `node-modules`,
+      },
+      async ({path, run, source}) => {
+        await run(`install`);
+
+        await xfs.writeFilePromise(`${path}/node_modules/no-deps/marker.txt` as PortablePath, ``);
+        await xfs.writeFilePromise(`${path}/.yarn/ignore/nm-state` as PortablePath, `{not json`);
+
+        await run(`install`);
+        expect(await xfs.existsPromise(`${path}/node_modules/no-deps/marker.txt` as PortablePath)).toEqual(false);
+
+        await expect(source(`require('no-deps')`)).resolves.toMatchObject({
+          version: `1.0.0`,
+        });
+      },
+    ),
+  );
+
+  test(`should not inject anything in NODE_OPTIONS when running node`,
+    makeTemporaryEnv(
+      {},
+      {
+        nodeLinker: `node-modules`,
+      },
+      async ({path, run}) => {
+        await run(`install`);
+
+        await xfs.writeFilePromise(`${path}/foo.js` as PortablePath, ``);
+
+        const userRequire = `--require ${npath.join(npath.fromPortablePath(path), `foo.js`)}`;
+
+        const {stdout} = await run(`node`, `-p`, `process.env.NODE_OPTIONS`, {env: {NODE_OPTIONS: userRequire}});
+        expect(stdout.trim().split(`\n`).pop()).toEqual(userRequire);
+      },
+    ),
+  );
+
+  test(`should neither write any file nor spend long linking when reinstalling a large project that didn't change`,
+    makeTemporaryEnv(
+      {},
+      {
+        nodeLinker: `node-modules`,
+      },
+      async ({path, run}) => {
+        const dependencies: Record<string, string> = {};
+
+        for (let t = 0; t < 1000; ++t) {
+          const name = `pkg-${t}`;
+
+          await xfs.mkdirpPromise(ppath.join(path, `packages/${name}` as PortablePath));
+          await xfs.writeJsonPromise(ppath.join(path, `packages/${name}/package.json` as PortablePath), {name, version: `1.0.0`});
+          await xfs.writeFilePromise(ppath.join(path, `packages/${name}/index.js` as PortablePath), `module.exports = ${t};\n`);
+
+          dependencies[name] = `file:./packages/${name}`;
+        }
+
+        await xfs.writeJsonPromise(ppath.join(path, Filename.manifest), {dependencies});
+
+        await run(`install`);
+
+        const collectModificationTimes = async () => {
+          const modificationTimes = new Map<PortablePath, number>();
+          const queue = [ppath.join(path, Filename.nodeModules)];
+
+          while (queue.length > 0) {
+            const folderPath = queue.pop()!;
+
+            for (const entry of await xfs.readdirPromise(folderPath, {withFileTypes: true})) {
+              const entryPath = ppath.join(folderPath, entry.name);
+              modificationTimes.set(entryPath, (await xfs.lstatPromise(entryPath)).mtimeMs);
+
+              if (entry.isDirectory()) {
+                queue.push(entryPath);
+              }
+            }
+          }
+
+          return modificationTimes;
+        };
+
+        const modificationTimesBefore = await collectModificationTimes();
+        expect(modificationTimesBefore.size).toBeGreaterThan(2000);
+
+        const tracePath = ppath.join(path, `trace.json` as PortablePath);
+        await run(`install`, `--profile-out`, tracePath);
+
+        expect(await collectModificationTimes()).toEqual(modificationTimesBefore);
+
+        const {traceEvents} = await xfs.readJsonPromise(tracePath);
+        const linkEvent = traceEvents.find((event: any) => event.name === `link_project`);
+
+        // Durations are expressed in microseconds
+        expect(linkEvent.dur).toBeLessThan(1000 * 1000);
+      },
+    ),
+    120000,
+  );
 });
@@ -162,6 +162,17 @@ describe(`Plugins`, () => {
         }),
       );
 
+      test(
+        `it should not add @types when package provides its own types through its exports`,
+        makeTemporaryEnv({}, {
+          tsEnableAutoTypes: true,
+        }, async ({path, run, source}) => {
+          await run(`add`, `exports-types`);
+
+          await expect(readManifest(path)).resolves.not.toHaveProperty(`devDependencies`);
+        }),
+      );
+
       test(
         `it should automatically add @types for scoped packages`,
         makeTemporaryEnv({}, {
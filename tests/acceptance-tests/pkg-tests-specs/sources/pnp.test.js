@@ -55,6 +55,24 @@ describe(`Plug'n'Play`, () => {
     ),
   );
 
+  test(
+    `it should let Node resolve dependencies through the generated .pnp.cjs file`,
+    makeTemporaryEnv(
+      {dependencies: {[`no-deps`]: `1.0.0`}},
+      async ({path, run, source}) => {
+        await run(`install`);
+
+        const stdout = cp.execFileSync(`node`, [`--require`, `./.pnp.cjs`, `-p`, `require('no-deps').name`], {
+          env: {...process.env, NODE_OPTIONS: ``},
+          cwd: npath.fromPortablePath(path),
+          encoding: `utf8`,
+        });
+
+        expect(stdout.trim()).toEqual(`no-deps`);
+      },
+    ),
+  );
+
   test(
     `it should resolve two identical packages with the same object (easy)`,
     makeTemporaryEnv(
@@ -468,6 +486,60 @@ describe(`Plug'n'Play`, () => {
     ),
   );
 
+  test(
+    `it should give each workspace its own instance of a package when they provide different peer dependencies`,
+    makeTemporaryEnv(
+      {
+        private: true,
+        workspaces: [`packages/*`],
+        dependencies: {
+          [`workspace-a`]: `workspace:*`,
+          [`workspace-b`]: `workspace:*`,
+        },
+      },
+      async ({path, run, source}) => {
+        for (const [name, version] of [[`workspace-a`, `1.0.0`], [`workspace-b`, `2.0.0`]]) {
+          await writeJson(`${path}/packages/${name}/package.json`, {
+            name,
+            version: `1.0.0`,
+            dependencies: {
+              [`peer-deps`]: `1.0.0`,
+              [`no-deps`]: version,
+            },
+          });
+
+          await writeFile(`${path}/packages/${name}/index.js`, `module.exports = require('peer-deps');`);
+        }
+
+        await run(`install`);
+
+        await expect(source(`require('workspace-a')`)).resolves.toMatchObject({
+          name: `peer-deps`,
+          peerDependencies: {
+            [`no-deps`]: {
+              name: `no-deps`,
+              version: `1.0.0`,
+            },
+          },
+        });
+
+        await expect(source(`require('workspace-b')`)).resolves.toMatchObject({
+          name: `peer-deps`,
+          peerDependencies: {
+            [`no-deps`]: {
+              name: `no-deps`,
+              version: `2.0.0`,
+            },
+          },
+        });
+
+        // Virtual instances only exist in the install state; the lockfile
+        // only lists the physical packages
+        await expect(xfs.readFilePromise(ppath.join(path, Filename.lockfile), `utf8`)).resolves.not.toContain(`virtual:`);
+      },
+    ),
+  );
+
   test(
     `it should install in such a way that two identical packages with the same peer dependencies are the same instances (simple)`,
     makeTemporaryEnv(
@@ -1318,6 +1390,9 @@ describe(`Plug'n'Play`, () => {
       {
         dependencies: {[`no-deps-scripted`]: `1.0.0`},
       },
+      {
+        allowedBuildDependencies: [`no-deps-scripted`],
+      },
       async ({path, run, source}) => {
         await run(`install`);
 
@@ -1367,6 +1442,9 @@ describe(`Plug'n'Play`, () => {
       {
         dependencies: {[`prefer-unplugged-false`]: `1.0.0`},
       },
+      {
+        allowedBuildDependencies: [`prefer-unplugged-false`],
+      },
       async ({path, run, source}) => {
         await run(`install`);
 
@@ -1381,6 +1459,9 @@ describe(`Plug'n'Play`, () => {
       {
         dependencies: {[`no-deps-scripted`]: `1.0.0`},
       },
+      {
+        allowedBuildDependencies: [`no-deps-scripted`],
+      },
       async ({path, run, source}) => {
         await run(`install`);
 
@@ -1466,6 +1547,30 @@ describe(`Plug'n'Play`, () => {
     }),
   );
 
+  test(
+    `it should inject the PnP hook exactly once, before the user-provided --require`,
+    makeTemporaryEnv({}, async ({path, run, source}) => {
+      await run(`install`);
+
+      await writeFile(`${path}/foo.js`, ``);
+
+      const userRequire = `--require ${npath.join(npath.fromPortablePath(path), `foo.js`)}`;
+
+      const {stdout} = await run(`node`, `-p`, `process.env.NODE_OPTIONS`, {env: {NODE_OPTIONS: userRequire}});
+      const nodeOptions = stdout.trim().split(`\n`).pop();
+
+      expect(nodeOptions).toMatch(/^--require \S*\.pnp\.cjs /);
+      expect(nodeOptions.endsWith(userRequire)).toEqual(true);
+
+      // Simulates a nested `yarn node` call, which inherits the NODE_OPTIONS we set
+      const {stdout: nestedStdout} = await run(`node`, `-p`, `process.env.NODE_OPTIONS`, {env: {NODE_OPTIONS: nodeOptions}});
+      const nestedNodeOptions = nestedStdout.trim().split(`\n`).pop();
+
+      expect(nestedNodeOptions).toEqual(nodeOptions);
+      expect(nestedNodeOptions.match(/\.pnp\.cjs/g)).toHaveLength(1);
+    }),
+  );
+
   test(
     `it should allow external modules to require internal ones`,
     makeTemporaryEnv({
@@ -1921,6 +2026,35 @@ describe(`Plug'n'Play`, () => {
     ),
   );
 
+  test(
+    `it should only bypass the PnP resolution for requires coming from ignored folders`,
+    makeTemporaryEnv(
+      {},
+      {
+        pnpIgnorePatterns: [`vendor/**`, `legacy/**`],
+      },
+      async ({path, run, source}) => {
+        await xfs.mkdirPromise(`${path}/vendor/node_modules/vendored-dep`, {recursive: true});
+        await xfs.writeFilePromise(`${path}/vendor/node_modules/vendored-dep/index.js`, `module.exports = 42;`);
+        await xfs.writeFilePromise(`${path}/vendor/index.js`, `module.exports = require('vendored-dep');`);
+
+        await xfs.mkdirPromise(`${path}/src`);
+        await xfs.writeFilePromise(`${path}/src/index.js`, `module.exports = require('vendored-dep');`);
+
+        await run(`install`);
+
+        await expect(source(`require('./vendor/index.js')`)).resolves.toEqual(42);
+
+        await expect(source(`require('./src/index.js')`)).rejects.toMatchObject({
+          externalException: {
+            code: `MODULE_NOT_FOUND`,
+            pnpCode: `UNDECLARED_DEPENDENCY`,
+          },
+        });
+      },
+    ),
+  );
+
   test(
     `it shouldn't match on dot files with pnpIgnorePatterns`,
     makeTemporaryEnv(
@@ -1945,6 +2079,9 @@ describe(`Plug'n'Play`, () => {
           'no-deps-scripted': `*`,
         },
       },
+      {
+        allowedBuildDependencies: [`no-deps-scripted`],
+      },
       async ({path, run, source}) => {
         await run(`install`);
 
@@ -67,6 +67,31 @@ describe(`Protocols`, () => {
       }),
     );
 
+    test(
+      `it should expose the runtime environment through environment variables`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`dynamic-pkg`]: `exec:./genpkg.js`,
+        },
+      }, async ({path, run, source}) => {
+        await xfs.writeFilePromise(`${path}/genpkg.js`, `
+          const {EXEC_BUILD_DIR, EXEC_TEMP_DIR, EXEC_LOCATOR} = process.env;
+          const env = {EXEC_BUILD_DIR, EXEC_TEMP_DIR, EXEC_LOCATOR, isBuildDirCwd: fs.realpathSync(EXEC_BUILD_DIR) === process.cwd()};
+          fs.writeFileSync(path.join(EXEC_BUILD_DIR, 'index.js'), \`module.exports = \${JSON.stringify(env)};\`);
+          fs.writeFileSync(path.join(EXEC_BUILD_DIR, 'package.json'), '{}');
+        `);
+
+        await run(`install`);
+
+        await expect(source(`require('dynamic-pkg')`)).resolves.toMatchObject({
+          EXEC_BUILD_DIR: expect.any(String),
+          EXEC_TEMP_DIR: expect.any(String),
+          EXEC_LOCATOR: expect.stringMatching(/^dynamic-pkg@exec:\.\/genpkg\.js#/),
+          isBuildDirCwd: true,
+        });
+      }),
+    );
+
     test(
       `it should update the cache`,
       makeTemporaryEnv({
@@ -63,5 +63,35 @@ describe(`Protocols`, () => {
         });
       }),
     );
+
+    test(
+      `it should detect tarballs without a conventional extension`,
+      makeTemporaryEnv({
+        dependencies: {
+          [`pkg`]: `file:./vendor/pkg`,
+        },
+      }, async ({path, run, source}) => {
+        const noDeps1 = await getPackageArchivePath(`no-deps`, `1.0.0`);
+        const noDeps2 = await getPackageArchivePath(`no-deps`, `2.0.0`);
+
+        const destination = ppath.join(path, `vendor/pkg`);
+
+        await xfs.mkdirPromise(ppath.dirname(destination));
+
+        await xfs.copyPromise(destination, noDeps1);
+        await run(`install`);
+        await expect(source(`require('pkg/package.json')`)).resolves.toMatchObject({
+          name: `no-deps`,
+          version: `1.0.0`,
+        });
+
+        await xfs.copyPromise(destination, noDeps2);
+        await run(`install`);
+        await expect(source(`require('pkg/package.json')`)).resolves.toMatchObject({
+          name: `no-deps`,
+          version: `2.0.0`,
+        });
+      }),
+    );
   });
 });
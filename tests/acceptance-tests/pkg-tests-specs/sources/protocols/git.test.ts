@@ -54,6 +54,9 @@ describe(`Protocols`, () => {
             [`has-prepack`]: tests.startPackageServer().then(url => `${url}/repositories/has-prepack.git`),
           },
         },
+        {
+          allowedBuildDependencies: [`has-prepack`],
+        },
         async ({path, run, source}) => {
           await run(`install`);
 
@@ -70,6 +73,48 @@ describe(`Protocols`, () => {
             [`no-prepack`]: tests.startPackageServer().then(url => `${url}/repositories/no-prepack.git`),
           },
         },
+        {
+          allowedBuildDependencies: [`no-prepack`],
+        },
+        async ({path, run, source}) => {
+          await run(`install`);
+
+          await expect(source(`require('no-prepack')`)).resolves.toEqual(42);
+        },
+      ),
+    );
+
+    test(
+      `it should support fetching the full history of git dependencies`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`has-prepack`]: tests.startPackageServer().then(url => `${url}/repositories/has-prepack.git`),
+          },
+        },
+        {
+          gitFetchDepth: 0,
+          allowedBuildDependencies: [`has-prepack`],
+        },
+        async ({path, run, source}) => {
+          await run(`install`);
+
+          await expect(source(`require('has-prepack')`)).resolves.toEqual(42);
+        },
+      ),
+    );
+
+    test(
+      `it should pack the repository as-is when enableGitPrepare is disabled`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`no-prepack`]: tests.startPackageServer().then(url => `${url}/repositories/no-prepack.git`),
+          },
+        },
+        {
+          enableGitPrepare: false,
+        },
         async ({path, run, source}) => {
           await run(`install`);
 
@@ -87,6 +132,9 @@ describe(`Protocols`, () => {
             [`pkg-b`]: tests.startPackageServer().then(url => `${url}/repositories/deep-projects.git#cwd=projects/pkg-b`),
           },
         },
+        {
+          allowedBuildDependencies: [`pkg-a`, `pkg-b`],
+        },
         async ({path, run, source}) => {
           await run(`install`);
 
@@ -112,6 +160,9 @@ describe(`Protocols`, () => {
             [`lib-b`]: tests.startPackageServer().then(url => `${url}/repositories/deep-projects.git#cwd=projects/pkg-b&workspace=lib`),
           },
         },
+        {
+          allowedBuildDependencies: [`lib-a`, `lib-b`],
+        },
         async ({path, run, source}) => {
           await run(`install`);
 
@@ -140,6 +191,9 @@ describe(`Protocols`, () => {
             [`pkg-b`]: tests.startPackageServer().then(url => `${url}/repositories/workspaces.git#workspace=pkg-b`),
           },
         },
+        {
+          allowedBuildDependencies: [`pkg-a`, `pkg-b`],
+        },
         async ({path, run, source}) => {
           await run(`install`);
 
@@ -156,6 +210,45 @@ describe(`Protocols`, () => {
       ),
     );
 
+    test(
+      `it should support selecting workspaces by path`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`pkg-a`]: tests.startPackageServer().then(url => `${url}/repositories/workspaces.git#workspace=packages/pkg-a`),
+          },
+        },
+        {
+          allowedBuildDependencies: [`pkg-a`],
+        },
+        async ({path, run, source}) => {
+          await run(`install`);
+
+          await expect(source(`require('pkg-a/package.json')`)).resolves.toMatchObject({
+            name: `pkg-a`,
+            version: `1.0.0`,
+          });
+        },
+      ),
+    );
+
+    test(
+      `it should report workspace selectors that don't match any workspace`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`pkg-c`]: tests.startPackageServer().then(url => `${url}/repositories/workspaces.git#workspace=pkg-c`),
+          },
+        },
+        {
+          allowedBuildDependencies: [`pkg-c`],
+        },
+        async ({path, run, source}) => {
+          await expect(run(`install`)).rejects.toThrow(`No workspace matches pkg-c in the git repository`);
+        },
+      ),
+    );
+
     test(
       `it should use Yarn Classic to setup classic repositories`,
       makeTemporaryEnv(
@@ -164,6 +257,9 @@ describe(`Protocols`, () => {
             [`yarn-1-project`]: tests.startPackageServer().then(url => `${url}/repositories/yarn-1-project.git`),
           },
         },
+        {
+          allowedBuildDependencies: [`yarn-1-project`],
+        },
         async ({path, run, source}) => {
           await expect(run(`install`, {
             env: {
@@ -188,6 +284,9 @@ describe(`Protocols`, () => {
             [`npm-project`]: tests.startPackageServer().then(url => `${url}/repositories/npm-project.git`),
           },
         },
+        {
+          allowedBuildDependencies: [`npm-project`],
+        },
         async ({path, run, source}) => {
           await run(`install`);
 
@@ -204,6 +303,9 @@ describe(`Protocols`, () => {
             [`npm-has-prepack`]: tests.startPackageServer().then(url => `${url}/repositories/npm-has-prepack.git`),
           },
         },
+        {
+          allowedBuildDependencies: [`npm-has-prepack`],
+        },
         async ({path, run, source}) => {
           await expect(run(`install`, {
             env: {
@@ -236,6 +338,9 @@ describe(`Protocols`, () => {
             [`pkg-b`]: tests.startPackageServer().then(url => `${url}/repositories/npm-workspaces.git#workspace=pkg-b`),
           },
         },
+        {
+          allowedBuildDependencies: [`pkg-a`, `pkg-b`],
+        },
         async ({path, run, source}) => {
           const {code, stdout, stderr} = await execUtils.execvp(`npm`, [`--version`], {cwd: path});
           if (code !== 0)
@@ -271,6 +376,9 @@ describe(`Protocols`, () => {
             [`yarn-1-project`]: tests.startPackageServer().then(url => `${url}/repositories/yarn-1-project.git`),
           },
         },
+        {
+          allowedBuildDependencies: [`yarn-1-project`],
+        },
         async ({path, run, source}) => {
           // This checks that the `set version classic` part of `scriptUtils.prepareExternalProject` doesn't use Corepack.
           // The rest of the install will fail though.
@@ -295,6 +403,9 @@ describe(`Protocols`, () => {
             [`no-lockfile-project`]: tests.startPackageServer().then(url => `${url}/repositories/no-lockfile-project.git`),
           },
         },
+        {
+          allowedBuildDependencies: [`no-lockfile-project`],
+        },
         async ({path, run, source}) => {
           await expect(run(`install`, {
             env: {
@@ -314,6 +425,9 @@ describe(`Protocols`, () => {
             [`yarn-1-project`]: tests.startPackageServer().then(url => `${url}/repositories/yarn-1-project.git`),
           },
         },
+        {
+          allowedBuildDependencies: [`yarn-1-project`],
+        },
         async ({path, run, source}) => {
           await expect(run(`install`)).resolves.toBeTruthy();
 
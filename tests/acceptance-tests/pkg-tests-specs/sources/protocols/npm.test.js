@@ -164,5 +164,48 @@ describe(`Protocols`, () => {
         },
       ),
     );
+
+    test(
+      `it should warn when resolving a deprecated version`,
+      makeTemporaryEnv(
+        {
+          dependencies: {[`no-deps-deprecated`]: `^1.0.0`},
+        },
+        async ({path, run, source}) => {
+          const {stdout} = await run(`install`);
+
+          expect(stdout).toContain(`no-deps-deprecated@1.0.0 is deprecated: ¯\\_(ツ)_/¯`);
+        },
+      ),
+    );
+
+    test(
+      `it shouldn't warn when the deprecation message is empty or blank`,
+      makeTemporaryEnv(
+        {
+          dependencies: {
+            [`no-deps-deprecated-empty`]: `^1.0.0`,
+            [`no-deps-deprecated-whitespace`]: `^1.0.0`,
+          },
+        },
+        async ({path, run, source}) => {
+          const {stdout} = await run(`install`);
+
+          expect(stdout).not.toContain(`is deprecated`);
+        },
+      ),
+    );
+
+    test(
+      `it should list the available dist-tags when no version matches the range`,
+      makeTemporaryEnv(
+        {
+          dependencies: {[`no-deps-tags`]: `^2.0.0`},
+        },
+        async ({path, run, source}) => {
+          await expect(run(`install`)).rejects.toThrowError(/No candidates found for .*; the available tags are latest \(1\.0\.0\), rc \(1\.0\.0-rc\.1\)/);
+        },
+      ),
+    );
   });
 });
@@ -1,5 +1,5 @@
-import {PortablePath} from '@yarnpkg/fslib';
-import {fs}           from 'pkg-tests-core';
+import {PortablePath, xfs} from '@yarnpkg/fslib';
+import {fs}                from 'pkg-tests-core';
 
 const {writeJson} = fs;
 
@@ -33,6 +33,37 @@ describe(`Protocols`, () => {
         await expect(run(`install`)).resolves.toBeTruthy();
       },
     ));
+
+    test(`it should link ^ and ~ ranges to the workspaces on disk`, makeTemporaryEnv(
+      {
+        private: true,
+        workspaces: [`config`, `utils`],
+        dependencies: {
+          config: `workspace:^`,
+          utils: `workspace:~`,
+        },
+      },
+      async ({path, run, source}) => {
+        await writeJson(`${path}/config/package.json` as PortablePath, {
+          name: `config`,
+          version: `1.2.3`,
+        });
+
+        await writeJson(`${path}/utils/package.json` as PortablePath, {
+          name: `utils`,
+          version: `4.5.6`,
+        });
+
+        await xfs.writeFilePromise(`${path}/config/index.js` as PortablePath, `module.exports = 'local';\n`);
+        await xfs.writeFilePromise(`${path}/utils/index.js` as PortablePath, `module.exports = 'local';\n`);
+
+        await run(`install`);
+
+        await expect(source(`require('config')`)).resolves.toEqual(`local`);
+        await expect(source(`require('utils')`)).resolves.toEqual(`local`);
+      },
+    ));
+
     test(
       `it should recognize prereleases in wildcard ranges`,
       makeTemporaryEnv(
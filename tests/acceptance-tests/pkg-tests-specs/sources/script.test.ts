@@ -59,7 +59,7 @@ describe(`Scripts tests`, () => {
 
   test(
     `it should correctly run empty install scripts`,
-    makeTemporaryEnv({dependencies: {[`no-deps-scripted-empty`]: `1.0.0`}}, async ({path, run, source}) => {
+    makeTemporaryEnv({dependencies: {[`no-deps-scripted-empty`]: `1.0.0`}}, {allowedBuildDependencies: [`no-deps-scripted-empty`]}, async ({path, run, source}) => {
       await run(`install`);
     }),
   );
@@ -342,7 +342,7 @@ describe(`Scripts tests`, () => {
 
       test(
         `it should run install scripts during the install`,
-        makeTemporaryEnv({dependencies: {[`no-deps-scripted`]: `1.0.0`}}, async ({path, run, source}) => {
+        makeTemporaryEnv({dependencies: {[`no-deps-scripted`]: `1.0.0`}}, {allowedBuildDependencies: [`no-deps-scripted`]}, async ({path, run, source}) => {
           await run(`install`);
 
           await expect(source(`require('no-deps-scripted/log.js')`)).resolves.toEqual([
@@ -457,14 +457,25 @@ describe(`Scripts tests`, () => {
 
       test(
         `it should abort with an error if a package can't be built`,
-        makeTemporaryEnv({dependencies: {[`no-deps-scripted-to-fail`]: `1.0.0`}}, async ({path, run, source}) => {
+        makeTemporaryEnv({dependencies: {[`no-deps-scripted-to-fail`]: `1.0.0`}}, {allowedBuildDependencies: [`no-deps-scripted-to-fail`]}, async ({path, run, source}) => {
           await expect(run(`install`)).rejects.toThrow();
         }),
       );
 
+      test(
+        `it should keep the output of failed builds in a per-package log file`,
+        makeTemporaryEnv({dependencies: {[`no-deps-scripted-to-fail`]: `1.0.0`}}, {allowedBuildDependencies: [`no-deps-scripted-to-fail`]}, async ({path, run, source}) => {
+          await expect(run(`install`)).rejects.toMatchObject({
+            stdout: expect.stringContaining(`no-deps-scripted-to-fail@npm:1.0.0 failed to build`),
+          });
+
+          await expect(xfs.readdirPromise(ppath.join(path, `.yarn/ignore/build-logs`))).resolves.toHaveLength(1);
+        }),
+      );
+
       test(
         `it shouldn't abort with an error if the package that can't be built is optional`,
-        makeTemporaryEnv({optionalDependencies: {[`no-deps-scripted-to-fail`]: `1.0.0`}}, async ({path, run, source}) => {
+        makeTemporaryEnv({optionalDependencies: {[`no-deps-scripted-to-fail`]: `1.0.0`}}, {allowedBuildDependencies: [`no-deps-scripted-to-fail`]}, async ({path, run, source}) => {
           await run(`install`);
 
           await expect(source(`require('no-deps-scripted-to-fail')`)).resolves.toMatchObject({
@@ -476,7 +487,7 @@ describe(`Scripts tests`, () => {
 
       test(
         `it shouldn't abort with an error if the package that can't be built is a transitive dependency of an optional package`,
-        makeTemporaryEnv({optionalDependencies: {[`no-deps-scripted-to-deeply-fail`]: `1.0.0`}}, async ({path, run, source}) => {
+        makeTemporaryEnv({optionalDependencies: {[`no-deps-scripted-to-deeply-fail`]: `1.0.0`}}, {allowedBuildDependencies: [`no-deps-scripted-to-fail`]}, async ({path, run, source}) => {
           await run(`install`);
 
           await expect(source(`require('no-deps-scripted-to-deeply-fail')`)).resolves.toMatchObject({
@@ -498,6 +509,9 @@ describe(`Scripts tests`, () => {
           {
             dependencies: {[`one-dep-scripted`]: `1.0.0`},
           },
+          {
+            allowedBuildDependencies: [`one-dep-scripted`],
+          },
           async ({path, run, source}) => {
             await run(`install`);
           },
@@ -510,6 +524,9 @@ describe(`Scripts tests`, () => {
           {
             dependencies: {[`no-deps-nested-postinstall`]: `1.0.0`},
           },
+          {
+            allowedBuildDependencies: [`no-deps-nested-postinstall`],
+          },
           async ({path, run, source}) => {
             await run(`install`);
           },
@@ -522,6 +539,9 @@ describe(`Scripts tests`, () => {
           {
             dependencies: {[`binding-gyp-scripts`]: `1.0.0`},
           },
+          {
+            allowedBuildDependencies: [`binding-gyp-scripts`],
+          },
           async ({path, run, source}) => {
             await run(`install`, {env: {}});
 
@@ -540,6 +560,8 @@ describe(`Scripts tests`, () => {
           dependencies: {
             [`no-deps-scripted`]: `1.0.0`,
           },
+        }, {
+          allowedBuildDependencies: [`no-deps-scripted`],
         }, async ({path, run, source}) => {
           await run(`install`);
 
@@ -674,4 +696,147 @@ describe(`Scripts tests`, () => {
       );
     });
   }
+
+  test(
+    `it should kill scripts that run for longer than scriptTimeout`,
+    makeTemporaryEnv({
+      scripts: {
+        [`sleep`]: `echo started; sleep 60; echo finished`,
+      },
+    }, async ({path, run, source}) => {
+      await run(`install`);
+
+      const start = Date.now();
+
+      await expect(run(`run`, `sleep`, {
+        env: {
+          YARN_SCRIPT_TIMEOUT: `1s`,
+        },
+      })).rejects.toThrow(/Script timed out and was killed/);
+
+      expect(Date.now() - start).toBeLessThan(30000);
+    }),
+  );
+
+  test(
+    `it should pass the script arguments through without interpreting them`,
+    makeTemporaryEnv({
+      scripts: {
+        [`args`]: `node -p 'JSON.stringify(process.argv.slice(1))' --`,
+      },
+    }, async ({path, run, source}) => {
+      await run(`install`);
+
+      await expect(run(`run`, `args`, `--foo`, `hello world`, `$HOME`, `*`, `"quoted"`)).resolves.toMatchObject({
+        stdout: `["--foo","hello world","$HOME","*","\\"quoted\\""]\n`,
+      });
+    }),
+  );
+
+  test(
+    `it should support the builtin shell syntax`,
+    makeTemporaryEnv({
+      scripts: {
+        [`shell`]: `FOO=bar; (cd dir && echo \${FOO:-unset} \${MISSING:-default} *.txt) > out; false || echo ok`,
+      },
+    }, async ({path, run, source}) => {
+      await run(`install`);
+
+      await xfs.mkdirpPromise(ppath.join(path, `dir`));
+      await xfs.writeFilePromise(ppath.join(path, `dir/a.txt`), ``);
+      await xfs.writeFilePromise(ppath.join(path, `dir/b.txt`), ``);
+
+      await expect(run(`run`, `shell`)).resolves.toMatchObject({
+        stdout: `ok\n`,
+      });
+
+      await expect(xfs.readFilePromise(ppath.join(path, `out`), `utf8`)).resolves.toEqual(`bar default a.txt b.txt\n`);
+    }),
+  );
+
+  test(
+    `it should support command substitutions in the builtin shell`,
+    makeTemporaryEnv({
+      scripts: {
+        [`subst`]: `echo $(cd dir && pwd)/file "\`echo a   b\`" $(echo c   d)`,
+      },
+    }, async ({path, run, source}) => {
+      await run(`install`);
+
+      await xfs.mkdirpPromise(ppath.join(path, `dir`));
+
+      await expect(run(`run`, `subst`)).resolves.toMatchObject({
+        stdout: `${npath.fromPortablePath(ppath.join(path, `dir`))}/file a   b c d\n`,
+      });
+    }),
+  );
+
+  test(
+    `it should report the exit code of the last pipeline element`,
+    makeTemporaryEnv({
+      scripts: {
+        [`pipe`]: `exit 3 | node -e 'process.exit(5)'`,
+      },
+    }, async ({path, run, source}) => {
+      await run(`install`);
+
+      await expect(run(`run`, `pipe`)).rejects.toMatchObject({
+        code: 5,
+      });
+    }),
+  );
+
+  test(
+    `it should run scripts through the system shell when enableNativeShell is set`,
+    makeTemporaryEnv({
+      scripts: {
+        [`native`]: `if true; then echo native; fi`,
+      },
+    }, async ({path, run, source}) => {
+      await run(`install`);
+
+      await expect(run(`run`, `native`, {
+        env: {
+          YARN_ENABLE_NATIVE_SHELL: `true`,
+        },
+      })).resolves.toMatchObject({
+        stdout: `native\n`,
+      });
+    }),
+  );
+
+  test(
+    `it should expose the npm_package and npm_config variables to scripts`,
+    makeTemporaryEnv({
+      name: `my-package`,
+      version: `1.2.3`,
+      main: `./index.js`,
+      files: [`lib`, `bin`],
+      config: {
+        port: 8080,
+        nested: {enabled: true},
+      },
+      scripts: {
+        [`print:env`]: `node -p 'JSON.stringify(Object.fromEntries(Object.entries(process.env).filter(([key]) => key.startsWith("npm_"))))'`,
+      },
+    }, async ({path, run, source}) => {
+      await run(`install`);
+
+      const {stdout} = await run(`run`, `print:env`);
+
+      expect(JSON.parse(stdout)).toMatchObject({
+        npm_package_name: `my-package`,
+        npm_package_version: `1.2.3`,
+        npm_package_main: `./index.js`,
+        npm_package_files_0: `lib`,
+        npm_package_files_1: `bin`,
+        npm_package_config_port: `8080`,
+        npm_package_config_nested_enabled: `true`,
+        npm_package_scripts_print_env: expect.stringContaining(`node -p`),
+        npm_lifecycle_event: `print:env`,
+        npm_lifecycle_script: expect.stringContaining(`node -p`),
+        npm_config_registry: expect.stringMatching(/^https?:\/\//),
+      });
+    }),
+  );
 });